@@ -0,0 +1,360 @@
+//! Layered configuration loading, shared by the service binaries.
+//!
+//! Every service keeps its serde-default-driven config struct; this
+//! crate replaces the hand-rolled `Config::load` functions with one
+//! loader that layers the sources a deployment actually has, later
+//! sources winning: built-in defaults, then the JSON config file, then
+//! environment variables, then `--set` overrides from the command line.
+//! Environment variables are named `PREFIX__SECTION__FIELD` (double
+//! underscores separate path segments, since field names contain single
+//! ones); values parse as JSON first and fall back to plain strings, so
+//! `WEB__PORT=9000` and `FETCH__ALLOWED_HOSTS='["cdn"]'` both work.
+//!
+//! Deserialization failures name the offending field path, and the
+//! [`Secret`] wrapper keeps credential values out of `Debug` output and
+//! of the `print-effective-config` serialization every binary offers.
+
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize, Serializer};
+use serde_json::{Map, Value};
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, thiserror::Error)]
+pub enum ConfigError {
+    #[error("cannot read config file {path}: {source}")]
+    Read {
+        path: PathBuf,
+        #[source]
+        source: std::io::Error,
+    },
+    #[error("config file {path} is not valid JSON: {source}")]
+    Parse {
+        path: PathBuf,
+        #[source]
+        source: serde_json::Error,
+    },
+    /// A merged value the target type rejects; `path` is the field
+    /// path, e.g. `web.port`.
+    #[error("invalid config value at {path}: {message}")]
+    Invalid { path: String, message: String },
+    #[error("bad override '{assignment}': {message}")]
+    Override { assignment: String, message: String },
+}
+
+/// A credential value. Deserializes from a plain string but renders as
+/// `<redacted>` in both `Debug` and serialized output, so neither logs
+/// nor `print-effective-config` can leak it.
+#[derive(Clone, Deserialize)]
+#[serde(transparent)]
+pub struct Secret(String);
+
+impl Secret {
+    pub fn new(value: impl Into<String>) -> Self {
+        Self(value.into())
+    }
+
+    /// The actual value, for handing to the client that needs it.
+    pub fn expose(&self) -> &str {
+        &self.0
+    }
+}
+
+impl From<String> for Secret {
+    fn from(value: String) -> Self {
+        Self(value)
+    }
+}
+
+impl fmt::Debug for Secret {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Secret(<redacted>)")
+    }
+}
+
+impl Serialize for Secret {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str("<redacted>")
+    }
+}
+
+/// Builds a config value from layered sources; see the module docs for
+/// the precedence order.
+#[derive(Debug)]
+pub struct Loader {
+    env_prefix: String,
+    base: Value,
+    file: Option<PathBuf>,
+    overrides: Vec<(String, Value)>,
+}
+
+impl Loader {
+    /// A loader reading environment variables starting `{env_prefix}__`.
+    pub fn new(env_prefix: &str) -> Self {
+        Self {
+            env_prefix: env_prefix.to_string(),
+            base: Value::Object(Map::new()),
+            file: None,
+            overrides: Vec::new(),
+        }
+    }
+
+    /// The bottom layer, usually the config the service falls back to
+    /// when no file exists. Without this, defaults come only from the
+    /// target type's serde defaults.
+    pub fn defaults<T: Serialize>(mut self, value: &T) -> Result<Self, ConfigError> {
+        self.base = serde_json::to_value(value).map_err(|e| ConfigError::Invalid {
+            path: String::new(),
+            message: format!("defaults are not serializable: {e}"),
+        })?;
+        Ok(self)
+    }
+
+    /// The config file layer; a missing file contributes nothing rather
+    /// than failing, matching how the services have always started on a
+    /// fresh machine.
+    pub fn file(mut self, path: &Path) -> Self {
+        self.file = Some(path.to_path_buf());
+        self
+    }
+
+    /// A single `key.path=value` override from the command line; the
+    /// value parses as JSON first and falls back to a plain string.
+    pub fn set(mut self, assignment: &str) -> Result<Self, ConfigError> {
+        let (key, value) = assignment
+            .split_once('=')
+            .ok_or_else(|| ConfigError::Override {
+                assignment: assignment.to_string(),
+                message: "expected key.path=value".to_string(),
+            })?;
+        if key.is_empty() {
+            return Err(ConfigError::Override {
+                assignment: assignment.to_string(),
+                message: "empty key".to_string(),
+            });
+        }
+        self.overrides.push((key.to_string(), parse_scalar(value)));
+        Ok(self)
+    }
+
+    /// Merge the layers and deserialize the result.
+    pub fn load<T: DeserializeOwned>(&self) -> Result<T, ConfigError> {
+        let mut merged = self.base.clone();
+        if let Some(path) = &self.file {
+            if path.exists() {
+                let raw = std::fs::read_to_string(path).map_err(|source| ConfigError::Read {
+                    path: path.clone(),
+                    source,
+                })?;
+                let layer: Value =
+                    serde_json::from_str(&raw).map_err(|source| ConfigError::Parse {
+                        path: path.clone(),
+                        source,
+                    })?;
+                merge(&mut merged, layer);
+            }
+        }
+        for (key, value) in env_layer(&self.env_prefix) {
+            set_path(&mut merged, &key, value)?;
+        }
+        for (key, value) in &self.overrides {
+            set_path(&mut merged, key, value.clone())?;
+        }
+        serde_path_to_error::deserialize(merged).map_err(|e| ConfigError::Invalid {
+            path: e.path().to_string(),
+            message: e.into_inner().to_string(),
+        })
+    }
+}
+
+/// Environment variables under the prefix as dotted paths, sorted so
+/// the layer applies deterministically.
+fn env_layer(prefix: &str) -> Vec<(String, Value)> {
+    let prefix = format!("{prefix}__");
+    let mut entries: Vec<(String, Value)> = std::env::vars()
+        .filter_map(|(name, value)| {
+            let path = name.strip_prefix(&prefix)?;
+            Some((path.to_ascii_lowercase().replace("__", "."), parse_scalar(&value)))
+        })
+        .collect();
+    entries.sort_by(|a, b| a.0.cmp(&b.0));
+    entries
+}
+
+fn parse_scalar(raw: &str) -> Value {
+    serde_json::from_str(raw).unwrap_or_else(|_| Value::String(raw.to_string()))
+}
+
+/// Deep-merge `layer` over `target`: objects merge per key, everything
+/// else replaces wholesale.
+fn merge(target: &mut Value, layer: Value) {
+    match (target, layer) {
+        (Value::Object(target), Value::Object(layer)) => {
+            for (key, value) in layer {
+                match target.get_mut(&key) {
+                    Some(slot) => merge(slot, value),
+                    None => {
+                        target.insert(key, value);
+                    }
+                }
+            }
+        }
+        (target, layer) => *target = layer,
+    }
+}
+
+/// Set a dotted path, creating intermediate objects as needed.
+fn set_path(target: &mut Value, path: &str, value: Value) -> Result<(), ConfigError> {
+    let mut slot = target;
+    let mut walked = String::new();
+    for segment in path.split('.') {
+        if !walked.is_empty() {
+            walked.push('.');
+        }
+        walked.push_str(segment);
+        let Value::Object(object) = slot else {
+            return Err(ConfigError::Invalid {
+                path: walked,
+                message: "cannot set a field below a non-object value".to_string(),
+            });
+        };
+        slot = object.entry(segment.to_string()).or_insert(Value::Null);
+        if slot.is_null() {
+            *slot = Value::Object(Map::new());
+        }
+    }
+    *slot = value;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, Default, Serialize, Deserialize, PartialEq)]
+    struct Sample {
+        #[serde(default)]
+        name: String,
+        #[serde(default)]
+        web: Web,
+        #[serde(default)]
+        password: Option<Secret>,
+    }
+
+    #[derive(Debug, Serialize, Deserialize, PartialEq)]
+    struct Web {
+        #[serde(default = "default_port")]
+        port: u16,
+        #[serde(default)]
+        hosts: Vec<String>,
+    }
+
+    impl Default for Web {
+        fn default() -> Self {
+            Self {
+                port: default_port(),
+                hosts: Vec::new(),
+            }
+        }
+    }
+
+    fn default_port() -> u16 {
+        8080
+    }
+
+    impl PartialEq for Secret {
+        fn eq(&self, other: &Self) -> bool {
+            self.0 == other.0
+        }
+    }
+
+    #[test]
+    fn later_layers_win_and_objects_merge_deep() {
+        let file = tempfile::NamedTempFile::new().unwrap();
+        std::fs::write(
+            file.path(),
+            r#"{"name": "from-file", "web": {"port": 9000}}"#,
+        )
+        .unwrap();
+        let sample: Sample = Loader::new("AURUM_CONFIG_TEST_LAYERS")
+            .file(file.path())
+            .set("web.hosts=[\"a\", \"b\"]")
+            .unwrap()
+            .load()
+            .unwrap();
+        // The file set the port, the override set the hosts, and serde
+        // defaults filled what no layer touched.
+        assert_eq!(sample.name, "from-file");
+        assert_eq!(sample.web.port, 9000);
+        assert_eq!(sample.web.hosts, vec!["a", "b"]);
+    }
+
+    #[test]
+    fn environment_variables_override_the_file() {
+        std::env::set_var("AURUM_CONFIG_TEST_ENV__WEB__PORT", "7000");
+        std::env::set_var("AURUM_CONFIG_TEST_ENV__NAME", "from-env");
+        let file = tempfile::NamedTempFile::new().unwrap();
+        std::fs::write(file.path(), r#"{"name": "from-file"}"#).unwrap();
+        let sample: Sample = Loader::new("AURUM_CONFIG_TEST_ENV")
+            .file(file.path())
+            .load()
+            .unwrap();
+        std::env::remove_var("AURUM_CONFIG_TEST_ENV__WEB__PORT");
+        std::env::remove_var("AURUM_CONFIG_TEST_ENV__NAME");
+        assert_eq!(sample.name, "from-env");
+        assert_eq!(sample.web.port, 7000);
+    }
+
+    #[test]
+    fn invalid_values_are_reported_with_their_field_path() {
+        let err = Loader::new("AURUM_CONFIG_TEST_PATHS")
+            .set("web.port=not-a-port")
+            .unwrap()
+            .load::<Sample>()
+            .unwrap_err();
+        assert!(matches!(&err, ConfigError::Invalid { path, .. } if path == "web.port"));
+        let err = Loader::new("AURUM_CONFIG_TEST_PATHS")
+            .set("no-equals-sign")
+            .unwrap_err();
+        assert!(matches!(err, ConfigError::Override { .. }));
+    }
+
+    #[test]
+    fn secrets_deserialize_but_never_render() {
+        let sample: Sample = Loader::new("AURUM_CONFIG_TEST_SECRETS")
+            .set("password=hunter2")
+            .unwrap()
+            .load()
+            .unwrap();
+        let password = sample.password.as_ref().unwrap();
+        assert_eq!(password.expose(), "hunter2");
+        assert_eq!(format!("{password:?}"), "Secret(<redacted>)");
+        let rendered = serde_json::to_string(&sample).unwrap();
+        assert!(!rendered.contains("hunter2"));
+        assert!(rendered.contains("<redacted>"));
+    }
+
+    #[test]
+    fn defaults_sit_under_every_other_layer() {
+        let defaults = Sample {
+            name: "built-in".to_string(),
+            web: Web {
+                port: 1234,
+                hosts: vec!["default-host".to_string()],
+            },
+            password: None,
+        };
+        let missing = Path::new("/nonexistent/config.json");
+        let sample: Sample = Loader::new("AURUM_CONFIG_TEST_DEFAULTS")
+            .defaults(&defaults)
+            .unwrap()
+            .file(missing)
+            .set("name=overridden")
+            .unwrap()
+            .load()
+            .unwrap();
+        assert_eq!(sample.name, "overridden");
+        assert_eq!(sample.web.port, 1234);
+        assert_eq!(sample.web.hosts, vec!["default-host"]);
+    }
+}
@@ -0,0 +1,344 @@
+//! Shared image preprocessing for the face pipeline.
+//!
+//! Phone photos routinely arrive rotated via the EXIF orientation tag
+//! and in a wide-gamut color space (iPhones shoot Display P3). Decoders
+//! ignore both, so without correction the detector sees sideways faces
+//! and the embedder sees skewed colors. [`normalize`] fixes the bytes
+//! once, before they reach any model backend: it applies the EXIF
+//! orientation and converts tagged Display P3 pixels to sRGB. Images
+//! that are already upright sRGB — and bytes that are not decodable
+//! images at all — pass through untouched.
+
+use anyhow::{Context, Result};
+use image::DynamicImage;
+use std::borrow::Cow;
+use std::io::Cursor;
+use tracing::warn;
+
+/// The source color space, as far as the JPEG's ICC tag reveals it.
+/// Untagged images are treated as sRGB, per convention.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColorProfile {
+    Srgb,
+    DisplayP3,
+    /// A tagged profile this module does not convert; passed through
+    /// with a warning.
+    Other,
+}
+
+impl ColorProfile {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ColorProfile::Srgb => "srgb",
+            ColorProfile::DisplayP3 => "display_p3",
+            ColorProfile::Other => "other",
+        }
+    }
+}
+
+/// What [`normalize`] found and did.
+#[derive(Debug, Clone, Copy)]
+pub struct Normalization {
+    /// EXIF orientation (1-8); values 2-8 were corrected.
+    pub orientation: u8,
+    pub profile: ColorProfile,
+    /// True when the returned bytes were rewritten.
+    pub changed: bool,
+}
+
+/// Normalize an uploaded photo to upright sRGB. Returns the original
+/// bytes unchanged when there is nothing to fix or the bytes are not a
+/// decodable image.
+pub fn normalize(image: &[u8]) -> Result<(Cow<'_, [u8]>, Normalization)> {
+    let orientation = exif_orientation(image).unwrap_or(1);
+    let profile = color_profile(image);
+    if profile == ColorProfile::Other {
+        warn!("image carries an ICC profile this module does not convert; colors passed through");
+    }
+    let unchanged = Normalization {
+        orientation,
+        profile,
+        changed: false,
+    };
+    let needs_rotate = (2..=8).contains(&orientation);
+    let needs_color = profile == ColorProfile::DisplayP3;
+    if !needs_rotate && !needs_color {
+        return Ok((Cow::Borrowed(image), unchanged));
+    }
+    let Ok(mut decoded) = image::load_from_memory(image) else {
+        return Ok((Cow::Borrowed(image), unchanged));
+    };
+    if needs_rotate {
+        decoded = apply_orientation(decoded, orientation);
+    }
+    if needs_color {
+        decoded = p3_to_srgb(decoded);
+    }
+    let mut buf = Vec::new();
+    decoded
+        .write_to(&mut Cursor::new(&mut buf), image::ImageFormat::Png)
+        .context("failed to encode normalized image")?;
+    Ok((
+        Cow::Owned(buf),
+        Normalization {
+            orientation,
+            profile,
+            changed: true,
+        },
+    ))
+}
+
+/// Undo an EXIF orientation (per the spec's eight cases) so the pixels
+/// are upright.
+fn apply_orientation(image: DynamicImage, orientation: u8) -> DynamicImage {
+    match orientation {
+        2 => image.fliph(),
+        3 => image.rotate180(),
+        4 => image.flipv(),
+        5 => image.rotate90().fliph(),
+        6 => image.rotate90(),
+        7 => image.rotate90().flipv(),
+        8 => image.rotate270(),
+        _ => image,
+    }
+}
+
+/// Convert Display P3 pixels to sRGB: decode the shared sRGB-style
+/// transfer curve, apply the linear P3-to-sRGB matrix, re-encode.
+fn p3_to_srgb(image: DynamicImage) -> DynamicImage {
+    let mut rgba = image.to_rgba8();
+    for pixel in rgba.pixels_mut() {
+        let [r, g, b, a] = pixel.0;
+        let (r, g, b) = (to_linear(r), to_linear(g), to_linear(b));
+        pixel.0 = [
+            from_linear(1.2249 * r - 0.2247 * g),
+            from_linear(-0.0420 * r + 1.0419 * g),
+            from_linear(-0.0197 * r - 0.0786 * g + 1.0979 * b),
+            a,
+        ];
+    }
+    DynamicImage::ImageRgba8(rgba)
+}
+
+fn to_linear(v: u8) -> f32 {
+    let v = v as f32 / 255.0;
+    if v <= 0.04045 {
+        v / 12.92
+    } else {
+        ((v + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+fn from_linear(v: f32) -> u8 {
+    let v = v.clamp(0.0, 1.0);
+    let v = if v <= 0.003_130_8 {
+        v * 12.92
+    } else {
+        1.055 * v.powf(1.0 / 2.4) - 0.055
+    };
+    (v * 255.0).round() as u8
+}
+
+/// JPEG marker segments up to the scan data: `(marker, payload)` pairs.
+fn segments(bytes: &[u8]) -> Vec<(u8, &[u8])> {
+    let mut out = Vec::new();
+    if bytes.len() < 2 || bytes[0..2] != [0xFF, 0xD8] {
+        return out;
+    }
+    let mut i = 2;
+    while i + 4 <= bytes.len() {
+        if bytes[i] != 0xFF {
+            break;
+        }
+        let marker = bytes[i + 1];
+        // Standalone markers carry no length.
+        if marker == 0x01 || (0xD0..=0xD8).contains(&marker) {
+            i += 2;
+            continue;
+        }
+        if marker == 0xDA || marker == 0xD9 {
+            break;
+        }
+        let len = u16::from_be_bytes([bytes[i + 2], bytes[i + 3]]) as usize;
+        if len < 2 || i + 2 + len > bytes.len() {
+            break;
+        }
+        out.push((marker, &bytes[i + 4..i + 2 + len]));
+        i += 2 + len;
+    }
+    out
+}
+
+/// The EXIF orientation tag from the APP1 segment, when present and in
+/// the valid 1-8 range. The TIFF walk is deliberately minimal: IFD0
+/// only, which is where orientation lives.
+fn exif_orientation(bytes: &[u8]) -> Option<u8> {
+    let tiff = segments(bytes)
+        .into_iter()
+        .find(|(marker, data)| *marker == 0xE1 && data.starts_with(b"Exif\0\0"))
+        .map(|(_, data)| &data[6..])?;
+    let little_endian = match tiff.get(0..2)? {
+        b"II" => true,
+        b"MM" => false,
+        _ => return None,
+    };
+    let read16 = |offset: usize| -> Option<u16> {
+        let pair = [*tiff.get(offset)?, *tiff.get(offset + 1)?];
+        Some(if little_endian {
+            u16::from_le_bytes(pair)
+        } else {
+            u16::from_be_bytes(pair)
+        })
+    };
+    let read32 = |offset: usize| -> Option<u32> {
+        let quad = [
+            *tiff.get(offset)?,
+            *tiff.get(offset + 1)?,
+            *tiff.get(offset + 2)?,
+            *tiff.get(offset + 3)?,
+        ];
+        Some(if little_endian {
+            u32::from_le_bytes(quad)
+        } else {
+            u32::from_be_bytes(quad)
+        })
+    };
+    if read16(2)? != 42 {
+        return None;
+    }
+    let ifd = read32(4)? as usize;
+    let count = read16(ifd)? as usize;
+    for entry in 0..count {
+        let offset = ifd + 2 + entry * 12;
+        if read16(offset)? == 0x0112 {
+            let value = read16(offset + 8)?;
+            if (1..=8).contains(&value) {
+                return Some(value as u8);
+            }
+        }
+    }
+    None
+}
+
+/// Classify the embedded ICC profile by its description text. Exact
+/// profile parsing is overkill for routing between "convert from P3",
+/// "already sRGB", and "leave alone".
+fn color_profile(bytes: &[u8]) -> ColorProfile {
+    let mut icc = Vec::new();
+    for (marker, data) in segments(bytes) {
+        // APP2 ICC chunks: identifier, chunk index, chunk count, payload.
+        if marker == 0xE2 && data.starts_with(b"ICC_PROFILE\0") && data.len() > 14 {
+            icc.extend_from_slice(&data[14..]);
+        }
+    }
+    if icc.is_empty() {
+        return ColorProfile::Srgb;
+    }
+    if contains_text(&icc, "Display P3") {
+        ColorProfile::DisplayP3
+    } else if contains_text(&icc, "sRGB") {
+        ColorProfile::Srgb
+    } else {
+        ColorProfile::Other
+    }
+}
+
+/// Search for `needle` as ASCII and as the UTF-16BE the `desc`/`mluc`
+/// tags use.
+fn contains_text(haystack: &[u8], needle: &str) -> bool {
+    let ascii = needle.as_bytes();
+    if haystack.windows(ascii.len()).any(|w| w == ascii) {
+        return true;
+    }
+    let utf16: Vec<u8> = needle.bytes().flat_map(|b| [0, b]).collect();
+    haystack.windows(utf16.len()).any(|w| w == utf16)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::GenericImageView;
+
+    fn jpeg(width: u32, height: u32, color: [u8; 3]) -> Vec<u8> {
+        let mut img = image::RgbImage::new(width, height);
+        for pixel in img.pixels_mut() {
+            pixel.0 = color;
+        }
+        let mut buf = Vec::new();
+        DynamicImage::ImageRgb8(img)
+            .write_to(&mut Cursor::new(&mut buf), image::ImageFormat::Jpeg)
+            .unwrap();
+        buf
+    }
+
+    /// Splice a marker segment in right after SOI.
+    fn with_segment(jpeg: &[u8], marker: u8, payload: &[u8]) -> Vec<u8> {
+        let len = (payload.len() + 2) as u16;
+        let mut out = jpeg[..2].to_vec();
+        out.extend_from_slice(&[0xFF, marker]);
+        out.extend_from_slice(&len.to_be_bytes());
+        out.extend_from_slice(payload);
+        out.extend_from_slice(&jpeg[2..]);
+        out
+    }
+
+    fn exif_payload(orientation: u8) -> Vec<u8> {
+        let mut payload = b"Exif\0\0".to_vec();
+        payload.extend_from_slice(b"II");
+        payload.extend_from_slice(&42u16.to_le_bytes());
+        payload.extend_from_slice(&8u32.to_le_bytes());
+        payload.extend_from_slice(&1u16.to_le_bytes());
+        payload.extend_from_slice(&0x0112u16.to_le_bytes());
+        payload.extend_from_slice(&3u16.to_le_bytes());
+        payload.extend_from_slice(&1u32.to_le_bytes());
+        payload.extend_from_slice(&[orientation, 0, 0, 0]);
+        payload.extend_from_slice(&0u32.to_le_bytes());
+        payload
+    }
+
+    #[test]
+    fn exif_orientation_is_parsed_and_applied() {
+        let rotated = with_segment(&jpeg(4, 2, [200, 50, 50]), 0xE1, &exif_payload(6));
+        let (bytes, report) = normalize(&rotated).unwrap();
+        assert_eq!(report.orientation, 6);
+        assert!(report.changed);
+        let upright = image::load_from_memory(&bytes).unwrap();
+        assert_eq!(upright.dimensions(), (2, 4));
+    }
+
+    #[test]
+    fn display_p3_colors_are_converted_to_srgb() {
+        let mut icc = b"ICC_PROFILE\0\x01\x01".to_vec();
+        icc.extend_from_slice(b"....Display P3....");
+        let tagged = with_segment(&jpeg(2, 2, [64, 200, 100]), 0xE2, &icc);
+        let (bytes, report) = normalize(&tagged).unwrap();
+        assert_eq!(report.profile, ColorProfile::DisplayP3);
+        assert!(report.changed);
+        let converted = image::load_from_memory(&bytes).unwrap();
+        let [r, g, b, _] = converted.get_pixel(0, 0).0;
+        // A saturated P3 green shifts visibly; sRGB can only reach it by
+        // pushing green up and the others down.
+        assert!(g > 200 && r < 64, "got ({r}, {g}, {b})");
+
+        // Grays are on the white axis in both spaces and must survive.
+        let gray = with_segment(&jpeg(2, 2, [128, 128, 128]), 0xE2, &icc);
+        let (bytes, _) = normalize(&gray).unwrap();
+        let converted = image::load_from_memory(&bytes).unwrap();
+        let [r, g, b, _] = converted.get_pixel(0, 0).0;
+        assert!(r.abs_diff(128) <= 2 && g.abs_diff(128) <= 2 && b.abs_diff(128) <= 2);
+    }
+
+    #[test]
+    fn upright_srgb_and_undecodable_inputs_pass_through() {
+        let plain = jpeg(2, 2, [10, 20, 30]);
+        let (bytes, report) = normalize(&plain).unwrap();
+        assert!(!report.changed);
+        assert_eq!(bytes.as_ref(), plain.as_slice());
+        assert_eq!(report.profile, ColorProfile::Srgb);
+
+        let garbage = b"[0.1, 0.2] not an image";
+        let (bytes, report) = normalize(garbage).unwrap();
+        assert!(!report.changed);
+        assert_eq!(bytes.as_ref(), garbage.as_slice());
+    }
+}
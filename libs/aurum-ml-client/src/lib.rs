@@ -0,0 +1,421 @@
+//! Typed client for the face ML services.
+//!
+//! Callers in other Rust services (build-monitor health checks, the
+//! future scoring service) talk to face-detection and face-embedding
+//! through this crate instead of hand-rolled reqwest calls. The client
+//! retries transient failures with jittered exponential backoff inside
+//! an overall time budget, and a per-service circuit breaker fails fast
+//! once a service is clearly down instead of stacking doomed requests
+//! behind timeouts.
+
+use anyhow::{anyhow, bail, Context, Result};
+use rand::Rng;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tracing::{debug, warn};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ClientConfig {
+    /// Base URL of face-detection, e.g. `http://127.0.0.1:9700`.
+    #[serde(default = "default_detection_url")]
+    pub detection_url: String,
+    /// Base URL of face-embedding, e.g. `http://127.0.0.1:9701`.
+    #[serde(default = "default_embedding_url")]
+    pub embedding_url: String,
+    /// Overall budget per call, retries and backoff included.
+    #[serde(default = "default_budget_ms")]
+    pub budget_ms: u64,
+    /// Per-attempt timeout; the final attempt gets whatever is left of
+    /// the budget if that is shorter.
+    #[serde(default = "default_attempt_timeout_ms")]
+    pub attempt_timeout_ms: u64,
+    #[serde(default = "default_max_attempts")]
+    pub max_attempts: u32,
+    /// Base backoff before the second attempt; doubles per attempt and
+    /// is jittered by ±50%.
+    #[serde(default = "default_backoff_ms")]
+    pub backoff_ms: u64,
+    /// Consecutive failures that open a service's circuit.
+    #[serde(default = "default_breaker_threshold")]
+    pub breaker_threshold: u32,
+    /// How long an open circuit rejects calls before probing again.
+    #[serde(default = "default_breaker_cooldown_secs")]
+    pub breaker_cooldown_secs: u64,
+}
+
+impl Default for ClientConfig {
+    fn default() -> Self {
+        Self {
+            detection_url: default_detection_url(),
+            embedding_url: default_embedding_url(),
+            budget_ms: default_budget_ms(),
+            attempt_timeout_ms: default_attempt_timeout_ms(),
+            max_attempts: default_max_attempts(),
+            backoff_ms: default_backoff_ms(),
+            breaker_threshold: default_breaker_threshold(),
+            breaker_cooldown_secs: default_breaker_cooldown_secs(),
+        }
+    }
+}
+
+fn default_detection_url() -> String {
+    "http://127.0.0.1:9700".to_string()
+}
+
+fn default_embedding_url() -> String {
+    "http://127.0.0.1:9701".to_string()
+}
+
+fn default_budget_ms() -> u64 {
+    10_000
+}
+
+fn default_attempt_timeout_ms() -> u64 {
+    3_000
+}
+
+fn default_max_attempts() -> u32 {
+    3
+}
+
+fn default_backoff_ms() -> u64 {
+    200
+}
+
+fn default_breaker_threshold() -> u32 {
+    5
+}
+
+fn default_breaker_cooldown_secs() -> u64 {
+    30
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BoundingBox {
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Landmark {
+    pub name: String,
+    pub x: f32,
+    pub y: f32,
+    pub confidence: f32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Face {
+    pub bbox: BoundingBox,
+    pub confidence: f32,
+    #[serde(default)]
+    pub landmarks: Vec<Landmark>,
+    #[serde(default)]
+    pub landmark_confidence: f32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Detection {
+    pub count: usize,
+    pub faces: Vec<Face>,
+}
+
+/// Threshold overrides for a detect call; absent fields use the
+/// service's configured defaults.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct DetectOptions {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub min_confidence: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub iou_threshold: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_faces: Option<usize>,
+    /// Object-storage reference sent instead of an image body.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub source: Option<String>,
+}
+
+/// An embedding in whichever format the service returned.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "format", rename_all = "snake_case")]
+pub enum Embedding {
+    Float32 { values: Vec<f32> },
+    /// IEEE 754 half-precision bit patterns.
+    Float16 { values: Vec<u16> },
+    Int8 { values: Vec<i8>, scale: f32 },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Embedded {
+    pub dimension: usize,
+    pub embedding: Embedding,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct EmbedOptions {
+    /// "float32", "float16", or "int8"; the service's default when
+    /// absent.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub format: Option<String>,
+    /// Object-storage reference sent instead of an image body.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub source: Option<String>,
+}
+
+pub struct MlClient {
+    config: ClientConfig,
+    client: reqwest::Client,
+    detection: Breaker,
+    embedding: Breaker,
+}
+
+impl MlClient {
+    pub fn new(config: ClientConfig) -> Self {
+        let threshold = config.breaker_threshold;
+        let cooldown = Duration::from_secs(config.breaker_cooldown_secs);
+        Self {
+            client: reqwest::Client::new(),
+            detection: Breaker::new("face-detection", threshold, cooldown),
+            embedding: Breaker::new("face-embedding", threshold, cooldown),
+            config,
+        }
+    }
+
+    /// Detect faces in `image`, or in the object `options.source`
+    /// references when `image` is empty.
+    pub async fn detect(&self, image: Vec<u8>, options: &DetectOptions) -> Result<Detection> {
+        let url = format!("{}/api/detect", self.config.detection_url);
+        let query = serde_json::to_value(options)?;
+        self.call(&self.detection, move |client| {
+            client.post(&url).query(&query).body(image.clone())
+        })
+        .await
+    }
+
+    /// Embed the aligned face crop in `image`, or the object
+    /// `options.source` references when `image` is empty.
+    pub async fn embed(&self, image: Vec<u8>, options: &EmbedOptions) -> Result<Embedded> {
+        let url = format!("{}/api/embed", self.config.embedding_url);
+        let query = serde_json::to_value(options)?;
+        self.call(&self.embedding, move |client| {
+            client.post(&url).query(&query).body(image.clone())
+        })
+        .await
+    }
+
+    /// One-shot health probes, for monitoring; no retries, no breaker.
+    pub async fn detection_health(&self) -> Result<()> {
+        self.health(&self.config.detection_url).await
+    }
+
+    pub async fn embedding_health(&self) -> Result<()> {
+        self.health(&self.config.embedding_url).await
+    }
+
+    async fn health(&self, base: &str) -> Result<()> {
+        self.client
+            .get(format!("{base}/health"))
+            .timeout(Duration::from_millis(self.config.attempt_timeout_ms))
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .with_context(|| format!("health check against {base} failed"))?;
+        Ok(())
+    }
+
+    /// Run one logical call: admit through the breaker, then retry
+    /// transient failures with jittered backoff until the budget or the
+    /// attempt limit runs out.
+    async fn call<T, F>(&self, breaker: &Breaker, make: F) -> Result<T>
+    where
+        T: DeserializeOwned,
+        F: Fn(&reqwest::Client) -> reqwest::RequestBuilder,
+    {
+        breaker.admit()?;
+        let deadline = Instant::now() + Duration::from_millis(self.config.budget_ms);
+        let mut attempt = 0;
+        loop {
+            attempt += 1;
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if remaining.is_zero() {
+                breaker.record_failure();
+                bail!("{} call ran out of its time budget", breaker.name);
+            }
+            let timeout = remaining.min(Duration::from_millis(self.config.attempt_timeout_ms));
+            let error = match make(&self.client).timeout(timeout).send().await {
+                Ok(response) => {
+                    let status = response.status();
+                    if status.is_success() {
+                        breaker.record_success();
+                        return response
+                            .json()
+                            .await
+                            .with_context(|| format!("{} returned an unexpected body", breaker.name));
+                    }
+                    let body = response.text().await.unwrap_or_default();
+                    let error = anyhow!("{} returned {status}: {}", breaker.name, body.trim());
+                    if status.is_client_error() {
+                        // The service is up and rejected this request;
+                        // retrying the same input cannot help.
+                        breaker.record_success();
+                        return Err(error);
+                    }
+                    error
+                }
+                Err(e) => anyhow!("{} request failed: {e}", breaker.name),
+            };
+            if attempt >= self.config.max_attempts {
+                breaker.record_failure();
+                return Err(error.context(format!("giving up after {attempt} attempts")));
+            }
+            let delay = backoff(attempt, self.config.backoff_ms, rand::rng().random_range(0.5..1.5));
+            debug!(service = breaker.name, attempt, ?delay, "retrying after {error:#}");
+            tokio::time::sleep(delay.min(remaining)).await;
+        }
+    }
+}
+
+/// Exponential backoff before attempt `attempt + 1`, scaled by a jitter
+/// factor so synchronized callers spread out.
+fn backoff(attempt: u32, base_ms: u64, jitter: f64) -> Duration {
+    let exponential = base_ms.saturating_mul(1 << (attempt - 1).min(16));
+    Duration::from_millis((exponential as f64 * jitter) as u64)
+}
+
+/// Consecutive-failure circuit breaker for one service. Open circuits
+/// reject calls immediately; after the cooldown a single probe call is
+/// admitted, and its outcome closes or re-opens the circuit.
+struct Breaker {
+    name: &'static str,
+    threshold: u32,
+    cooldown: Duration,
+    state: Mutex<BreakerState>,
+}
+
+#[derive(Default)]
+struct BreakerState {
+    consecutive_failures: u32,
+    open_until: Option<Instant>,
+}
+
+impl Breaker {
+    fn new(name: &'static str, threshold: u32, cooldown: Duration) -> Self {
+        Self {
+            name,
+            threshold: threshold.max(1),
+            cooldown,
+            state: Mutex::new(BreakerState::default()),
+        }
+    }
+
+    fn admit(&self) -> Result<()> {
+        let mut state = self.state.lock().expect("breaker lock poisoned");
+        match state.open_until {
+            Some(until) if Instant::now() < until => {
+                bail!("{} circuit is open, rejecting call", self.name)
+            }
+            Some(_) => {
+                // Cooldown over: admit this call as the probe. Leaving
+                // the failure count at the threshold re-opens the
+                // circuit on the first failed probe.
+                debug!(service = self.name, "circuit half-open, probing");
+                state.open_until = None;
+                Ok(())
+            }
+            None => Ok(()),
+        }
+    }
+
+    fn record_success(&self) {
+        let mut state = self.state.lock().expect("breaker lock poisoned");
+        state.consecutive_failures = 0;
+        state.open_until = None;
+    }
+
+    fn record_failure(&self) {
+        let mut state = self.state.lock().expect("breaker lock poisoned");
+        state.consecutive_failures += 1;
+        if state.consecutive_failures >= self.threshold {
+            state.open_until = Some(Instant::now() + self.cooldown);
+            warn!(
+                service = self.name,
+                failures = state.consecutive_failures,
+                "circuit opened"
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn breaker_opens_at_the_threshold_and_probes_after_cooldown() {
+        let breaker = Breaker::new("face-detection", 2, Duration::from_secs(60));
+        breaker.record_failure();
+        assert!(breaker.admit().is_ok());
+        breaker.record_failure();
+        assert!(breaker.admit().unwrap_err().to_string().contains("open"));
+
+        // With no cooldown the next call is admitted as a probe, and a
+        // failed probe re-opens immediately.
+        let breaker = Breaker::new("face-detection", 2, Duration::ZERO);
+        breaker.record_failure();
+        breaker.record_failure();
+        assert!(breaker.admit().is_ok());
+        breaker.record_failure();
+        breaker.record_success();
+        assert!(breaker.admit().is_ok());
+    }
+
+    #[test]
+    fn backoff_doubles_and_scales_by_jitter()  {
+        assert_eq!(backoff(1, 200, 1.0), Duration::from_millis(200));
+        assert_eq!(backoff(2, 200, 1.0), Duration::from_millis(400));
+        assert_eq!(backoff(3, 200, 0.5), Duration::from_millis(400));
+    }
+
+    #[tokio::test]
+    async fn transient_failures_are_retried_inside_the_budget() {
+        // A canned server: 500 on the first request, then a detection.
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        tokio::spawn(async move {
+            for attempt in 0.. {
+                let (mut socket, _) = listener.accept().await.unwrap();
+                let mut buf = [0u8; 4096];
+                let _ = tokio::io::AsyncReadExt::read(&mut socket, &mut buf).await;
+                let response = if attempt == 0 {
+                    "HTTP/1.1 500 Internal Server Error\r\ncontent-length: 0\r\nconnection: close\r\n\r\n".to_string()
+                } else {
+                    let body = r#"{"count":1,"faces":[{"bbox":{"x":1.0,"y":2.0,"width":3.0,"height":4.0},"confidence":0.9}]}"#;
+                    format!(
+                        "HTTP/1.1 200 OK\r\ncontent-type: application/json\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{body}",
+                        body.len()
+                    )
+                };
+                tokio::io::AsyncWriteExt::write_all(&mut socket, response.as_bytes())
+                    .await
+                    .unwrap();
+            }
+        });
+        let config = ClientConfig {
+            detection_url: format!("http://127.0.0.1:{port}"),
+            backoff_ms: 1,
+            ..ClientConfig::default()
+        };
+        let client = MlClient::new(config);
+        let detection = client
+            .detect(b"img".to_vec(), &DetectOptions::default())
+            .await
+            .unwrap();
+        assert_eq!(detection.count, 1);
+        assert_eq!(detection.faces[0].bbox.width, 3.0);
+        assert!(detection.faces[0].landmarks.is_empty());
+    }
+}
@@ -0,0 +1,318 @@
+//! Shared email delivery for the monitoring crates.
+//!
+//! build-monitor and self-healing-system both need to mail operators
+//! about failures; this crate gives them one SMTP path (via lettre) with
+//! TLS, authentication, HTML + plaintext bodies, and suppression of
+//! repeated alerts so a flapping service does not flood an inbox.
+
+use anyhow::{Context, Result};
+use lettre::message::{Mailbox, MultiPart};
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::{AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tracing::debug;
+
+/// SMTP connection settings, embedded in each service's config file.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SmtpConfig {
+    pub host: String,
+    #[serde(default = "default_port")]
+    pub port: u16,
+    /// Sender address, e.g. `alerts@example.com`.
+    pub from: String,
+    #[serde(default)]
+    pub username: Option<String>,
+    /// Redacted in logs and serialized output; see
+    /// [`aurum_config::Secret`].
+    #[serde(default)]
+    pub password: Option<aurum_config::Secret>,
+    #[serde(default)]
+    pub tls: TlsMode,
+    /// Identical alerts inside this window are counted, not re-sent.
+    #[serde(default = "default_batch_window_secs")]
+    pub batch_window_secs: u64,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TlsMode {
+    /// STARTTLS upgrade on a plaintext connection (port 587).
+    #[default]
+    Starttls,
+    /// TLS from the first byte (port 465).
+    Implicit,
+    /// No encryption; only sensible against a relay on localhost.
+    None,
+}
+
+impl SmtpConfig {
+    /// Build settings from `SMTP_HOST`, `SMTP_FROM`, and friends, for
+    /// deployments that keep credentials out of the config file. Returns
+    /// `None` unless both required variables are set.
+    pub fn from_env() -> Option<Self> {
+        let host = std::env::var("SMTP_HOST").ok()?;
+        let from = std::env::var("SMTP_FROM").ok()?;
+        let tls = match std::env::var("SMTP_TLS").ok().as_deref() {
+            Some("implicit") => TlsMode::Implicit,
+            Some("none") => TlsMode::None,
+            _ => TlsMode::Starttls,
+        };
+        Some(Self {
+            host,
+            port: std::env::var("SMTP_PORT")
+                .ok()
+                .and_then(|p| p.parse().ok())
+                .unwrap_or_else(default_port),
+            from,
+            username: std::env::var("SMTP_USERNAME").ok(),
+            password: std::env::var("SMTP_PASSWORD").ok().map(Into::into),
+            tls,
+            batch_window_secs: default_batch_window_secs(),
+        })
+    }
+}
+
+fn default_port() -> u16 {
+    587
+}
+
+fn default_batch_window_secs() -> u64 {
+    300
+}
+
+/// A rendered email with both bodies; build one with [`EmailMessage::render`].
+#[derive(Debug, Clone)]
+pub struct EmailMessage {
+    pub subject: String,
+    pub text: String,
+    pub html: String,
+}
+
+impl EmailMessage {
+    /// Render the shared alert template: a short intro line, a table of
+    /// labelled fields, and a preformatted detail block (log tails and
+    /// the like). The plaintext body carries the same content.
+    pub fn render(subject: &str, intro: &str, fields: &[(&str, &str)], detail: &str) -> Self {
+        let mut text = format!("{intro}\n\n");
+        for (label, value) in fields {
+            text.push_str(&format!("{label}: {value}\n"));
+        }
+        if !detail.is_empty() {
+            text.push_str(&format!("\n{detail}\n"));
+        }
+
+        let mut rows = String::new();
+        for (label, value) in fields {
+            rows.push_str(&format!(
+                "<tr><td style=\"padding:2px 12px 2px 0;color:#555\">{}</td><td style=\"padding:2px 0\">{}</td></tr>",
+                escape(label),
+                escape(value)
+            ));
+        }
+        let detail_block = if detail.is_empty() {
+            String::new()
+        } else {
+            format!(
+                "<pre style=\"background:#f6f8fa;padding:8px;border-radius:4px;overflow:auto\">{}</pre>",
+                escape(detail)
+            )
+        };
+        let html = format!(
+            "<html><body style=\"font-family:sans-serif;font-size:14px;color:#111\">\
+             <p>{}</p><table>{rows}</table>{detail_block}</body></html>",
+            escape(intro)
+        );
+
+        Self {
+            subject: subject.to_string(),
+            text,
+            html,
+        }
+    }
+}
+
+fn escape(s: &str) -> String {
+    s.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;")
+}
+
+/// What to do with an alert that may be a repeat of a recent one.
+#[derive(Debug, PartialEq, Eq)]
+enum Decision {
+    /// Deliver now; `suppressed` repeats were swallowed since the last
+    /// delivery of this key.
+    Send { suppressed: u32 },
+    Suppress,
+}
+
+/// Per-key repeat suppression: the first alert goes out immediately,
+/// repeats inside the window are only counted, and the first repeat after
+/// the window carries the count.
+#[derive(Debug, Default)]
+struct RepeatFilter {
+    seen: HashMap<String, (Instant, u32)>,
+}
+
+impl RepeatFilter {
+    fn check(&mut self, key: &str, window: Duration, now: Instant) -> Decision {
+        match self.seen.get_mut(key) {
+            Some((last_sent, suppressed)) if now.duration_since(*last_sent) < window => {
+                *suppressed += 1;
+                Decision::Suppress
+            }
+            Some((last_sent, suppressed)) => {
+                let count = *suppressed;
+                *last_sent = now;
+                *suppressed = 0;
+                Decision::Send { suppressed: count }
+            }
+            None => {
+                self.seen.insert(key.to_string(), (now, 0));
+                Decision::Send { suppressed: 0 }
+            }
+        }
+    }
+}
+
+/// An SMTP client plus the repeat filter, shared across alert sources.
+pub struct Mailer {
+    transport: AsyncSmtpTransport<Tokio1Executor>,
+    from: Mailbox,
+    window: Duration,
+    repeats: Mutex<RepeatFilter>,
+}
+
+impl Mailer {
+    pub fn new(config: &SmtpConfig) -> Result<Self> {
+        let mut builder = match config.tls {
+            TlsMode::Starttls => {
+                AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(&config.host)?
+            }
+            TlsMode::Implicit => AsyncSmtpTransport::<Tokio1Executor>::relay(&config.host)?,
+            TlsMode::None => {
+                AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(&config.host)
+            }
+        }
+        .port(config.port);
+        if let (Some(username), Some(password)) = (&config.username, &config.password) {
+            builder = builder.credentials(Credentials::new(
+                username.clone(),
+                password.expose().to_string(),
+            ));
+        }
+        Ok(Self {
+            transport: builder.build(),
+            from: config
+                .from
+                .parse()
+                .with_context(|| format!("invalid from address {:?}", config.from))?,
+            window: Duration::from_secs(config.batch_window_secs),
+            repeats: Mutex::new(RepeatFilter::default()),
+        })
+    }
+
+    /// Deliver a message to every recipient in one email.
+    pub async fn send(&self, to: &[String], message: &EmailMessage) -> Result<()> {
+        let mut builder = Message::builder()
+            .from(self.from.clone())
+            .subject(&message.subject);
+        for recipient in to {
+            builder = builder.to(recipient
+                .parse()
+                .with_context(|| format!("invalid recipient address {recipient:?}"))?);
+        }
+        let email = builder.multipart(MultiPart::alternative_plain_html(
+            message.text.clone(),
+            message.html.clone(),
+        ))?;
+        self.transport.send(email).await?;
+        Ok(())
+    }
+
+    /// Like [`send`](Self::send), but alerts sharing `key` inside the
+    /// batch window are counted instead of re-sent; the next delivery
+    /// notes how many repeats were swallowed. Returns whether an email
+    /// actually went out.
+    pub async fn send_deduplicated(
+        &self,
+        to: &[String],
+        key: &str,
+        message: &EmailMessage,
+    ) -> Result<bool> {
+        let decision = self
+            .repeats
+            .lock()
+            .expect("repeat filter lock poisoned")
+            .check(key, self.window, Instant::now());
+        match decision {
+            Decision::Suppress => {
+                debug!(key, "suppressed repeated alert email");
+                Ok(false)
+            }
+            Decision::Send { suppressed: 0 } => {
+                self.send(to, message).await?;
+                Ok(true)
+            }
+            Decision::Send { suppressed } => {
+                let note = format!("Repeated {suppressed} more time(s) since the last email.");
+                let mut batched = message.clone();
+                batched.text.push_str(&format!("\n{note}\n"));
+                batched.html = batched.html.replace(
+                    "</body>",
+                    &format!("<p style=\"color:#555\">{}</p></body>", escape(&note)),
+                );
+                self.send(to, &batched).await?;
+                Ok(true)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn repeat_filter_suppresses_inside_the_window() {
+        let mut filter = RepeatFilter::default();
+        let window = Duration::from_secs(60);
+        let start = Instant::now();
+        assert_eq!(filter.check("a", window, start), Decision::Send { suppressed: 0 });
+        assert_eq!(filter.check("a", window, start + Duration::from_secs(10)), Decision::Suppress);
+        assert_eq!(filter.check("a", window, start + Duration::from_secs(20)), Decision::Suppress);
+        assert_eq!(
+            filter.check("a", window, start + Duration::from_secs(61)),
+            Decision::Send { suppressed: 2 }
+        );
+        // A different key is not affected.
+        assert_eq!(filter.check("b", window, start), Decision::Send { suppressed: 0 });
+    }
+
+    #[test]
+    fn render_produces_both_bodies_and_escapes_html() {
+        let message = EmailMessage::render(
+            "build failed",
+            "A build <broke>",
+            &[("service", "web"), ("severity", "critical")],
+            "error[E0308]: mismatched types",
+        );
+        assert_eq!(message.subject, "build failed");
+        assert!(message.text.contains("service: web"));
+        assert!(message.text.contains("error[E0308]"));
+        assert!(message.html.contains("A build &lt;broke&gt;"));
+        assert!(message.html.contains("<pre"));
+        assert!(!message.html.contains("<broke>"));
+    }
+
+    #[test]
+    fn smtp_config_defaults() {
+        let config: SmtpConfig =
+            serde_json::from_str(r#"{"host":"mail.example.com","from":"a@example.com"}"#).unwrap();
+        assert_eq!(config.port, 587);
+        assert_eq!(config.tls, TlsMode::Starttls);
+        assert_eq!(config.batch_window_secs, 300);
+        assert!(config.username.is_none());
+    }
+}
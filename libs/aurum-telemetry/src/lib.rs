@@ -0,0 +1,151 @@
+//! Shared logging and tracing setup for the monitoring binaries.
+//!
+//! Every binary calls [`init`] instead of wiring tracing_subscriber by
+//! hand. On top of the usual env-filtered fmt output it adds:
+//!
+//! - JSON log lines when `LOG_FORMAT=json`, for log shippers;
+//! - OTLP span export over HTTP when `OTEL_EXPORTER_OTLP_ENDPOINT` is
+//!   set, so spans land in Grafana Tempo;
+//! - W3C `traceparent` propagation: [`current_traceparent`] for outgoing
+//!   HTTP calls and [`request_span`] for incoming ones, which keeps one
+//!   trace id across build-monitor → self-healing → ML service hops.
+//!
+//! The exporter speaks OTLP/JSON directly through reqwest rather than
+//! pulling the opentelemetry crate stack into every service.
+
+mod otlp;
+
+use anyhow::Result;
+use otlp::OtlpLayer;
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::Layer;
+
+/// Header carrying trace context between services.
+pub const TRACEPARENT: &str = "traceparent";
+
+#[derive(Debug, Clone)]
+pub struct TelemetryConfig {
+    /// `service.name` resource attribute on exported spans.
+    pub service_name: String,
+    /// Fallback log filter when `RUST_LOG` is unset.
+    pub default_filter: String,
+    /// Emit one JSON object per log line instead of human-readable text.
+    pub json_logs: bool,
+    /// OTLP/HTTP collector base URL; spans are POSTed to `/v1/traces`.
+    pub otlp_endpoint: Option<String>,
+}
+
+impl TelemetryConfig {
+    /// The standard setup: service name fixed by the binary, everything
+    /// else from `LOG_FORMAT`, `OTEL_EXPORTER_OTLP_ENDPOINT`, and
+    /// `RUST_LOG`.
+    pub fn from_env(service_name: &str, default_filter: &str) -> Self {
+        Self {
+            service_name: service_name.to_string(),
+            default_filter: default_filter.to_string(),
+            json_logs: std::env::var("LOG_FORMAT").is_ok_and(|v| v.eq_ignore_ascii_case("json")),
+            otlp_endpoint: std::env::var("OTEL_EXPORTER_OTLP_ENDPOINT")
+                .ok()
+                .filter(|v| !v.is_empty()),
+        }
+    }
+}
+
+/// Install the global subscriber. Must run inside the tokio runtime when
+/// an OTLP endpoint is configured, because the exporter runs as a task.
+pub fn init(config: TelemetryConfig) -> Result<()> {
+    let filter = tracing_subscriber::EnvFilter::try_from_default_env()
+        .unwrap_or_else(|_| config.default_filter.clone().into());
+    let fmt = if config.json_logs {
+        tracing_subscriber::fmt::layer().json().boxed()
+    } else {
+        tracing_subscriber::fmt::layer().boxed()
+    };
+    let otlp = OtlpLayer::new(config.service_name, config.otlp_endpoint);
+    tracing_subscriber::registry()
+        .with(filter)
+        .with(fmt)
+        .with(otlp)
+        .try_init()?;
+    Ok(())
+}
+
+/// The `traceparent` header value for the span currently entered on this
+/// thread, for injection into outgoing HTTP requests. `None` before
+/// [`init`] or outside any span.
+pub fn current_traceparent() -> Option<String> {
+    otlp::current_context().map(|(trace_id, span_id)| {
+        format!("00-{}-{}-01", hex::encode(trace_id), hex::encode(span_id))
+    })
+}
+
+/// Decode a `traceparent` header into (trace id, parent span id).
+pub fn parse_traceparent(value: &str) -> Option<([u8; 16], [u8; 8])> {
+    let mut parts = value.trim().split('-');
+    let _version = parts.next()?;
+    let trace_hex = parts.next()?;
+    let span_hex = parts.next()?;
+    let trace_id: [u8; 16] = hex::decode(trace_hex).ok()?.try_into().ok()?;
+    let span_id: [u8; 8] = hex::decode(span_hex).ok()?.try_into().ok()?;
+    if trace_id == [0; 16] || span_id == [0; 8] {
+        return None;
+    }
+    Some((trace_id, span_id))
+}
+
+/// Span wrapping one incoming HTTP request. When the caller sent a
+/// `traceparent` header its trace continues here; otherwise the request
+/// starts a fresh trace.
+pub fn request_span(method: &str, path: &str, traceparent: Option<&str>) -> tracing::Span {
+    tracing::info_span!(
+        "http_request",
+        %method,
+        %path,
+        traceparent = traceparent.unwrap_or("")
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tracing_subscriber::layer::SubscriberExt;
+
+    #[test]
+    fn traceparent_round_trips() {
+        let trace_id = [0xab; 16];
+        let span_id = [0x12; 8];
+        let header = format!("00-{}-{}-01", hex::encode(trace_id), hex::encode(span_id));
+        assert_eq!(parse_traceparent(&header), Some((trace_id, span_id)));
+        assert_eq!(parse_traceparent("garbage"), None);
+        // All-zero ids are invalid per the W3C spec.
+        assert_eq!(
+            parse_traceparent(&format!("00-{}-{}-01", hex::encode([0u8; 16]), hex::encode([0u8; 8]))),
+            None
+        );
+    }
+
+    #[test]
+    fn child_spans_share_the_trace_and_remote_context_is_adopted() {
+        let subscriber = tracing_subscriber::registry()
+            .with(OtlpLayer::new("test".to_string(), None));
+        tracing::subscriber::with_default(subscriber, || {
+            assert_eq!(current_traceparent(), None);
+            let root = tracing::info_span!("root");
+            let (root_trace, child_trace) = root.in_scope(|| {
+                let root_header = current_traceparent().unwrap();
+                let child = tracing::info_span!("child");
+                let child_header = child.in_scope(|| current_traceparent().unwrap());
+                (root_header, child_header)
+            });
+            // Same 32-hex trace id, different span ids.
+            assert_eq!(root_trace[3..35], child_trace[3..35]);
+            assert_ne!(root_trace[36..52], child_trace[36..52]);
+
+            let remote = "00-0123456789abcdef0123456789abcdef-00f067aa0ba902b7-01";
+            let span = request_span("POST", "/api/issues", Some(remote));
+            let header = span.in_scope(|| current_traceparent().unwrap());
+            assert_eq!(&header[3..35], "0123456789abcdef0123456789abcdef");
+        });
+    }
+}
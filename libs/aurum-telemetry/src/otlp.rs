@@ -0,0 +1,263 @@
+//! Tracing layer that assigns W3C trace context to spans and exports
+//! finished spans as OTLP/JSON over HTTP.
+
+use std::cell::RefCell;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::sync::mpsc;
+use tracing::field::{Field, Visit};
+use tracing::span::{Attributes, Id};
+use tracing_subscriber::layer::{Context, Layer};
+use tracing_subscriber::registry::LookupSpan;
+
+thread_local! {
+    /// Stack of (trace id, span id) for the spans entered on this thread,
+    /// read by `current_traceparent`.
+    static CURRENT: RefCell<Vec<([u8; 16], [u8; 8])>> = const { RefCell::new(Vec::new()) };
+}
+
+pub(crate) fn current_context() -> Option<([u8; 16], [u8; 8])> {
+    CURRENT.with(|stack| stack.borrow().last().copied())
+}
+
+/// Per-span state stored in the registry while the span is live.
+struct SpanData {
+    trace_id: [u8; 16],
+    span_id: [u8; 8],
+    parent_span_id: Option<[u8; 8]>,
+    start: SystemTime,
+    attributes: Vec<(String, String)>,
+}
+
+/// A finished span on its way to the exporter task.
+pub(crate) struct SpanRecord {
+    trace_id: [u8; 16],
+    span_id: [u8; 8],
+    parent_span_id: Option<[u8; 8]>,
+    name: &'static str,
+    start: SystemTime,
+    end: SystemTime,
+    attributes: Vec<(String, String)>,
+}
+
+pub(crate) struct OtlpLayer {
+    sender: Option<mpsc::UnboundedSender<SpanRecord>>,
+}
+
+impl OtlpLayer {
+    /// Without an endpoint the layer still assigns trace context (so
+    /// propagation works) but exports nothing.
+    pub(crate) fn new(service_name: String, endpoint: Option<String>) -> Self {
+        let sender = endpoint.map(|endpoint| {
+            let (tx, rx) = mpsc::unbounded_channel();
+            tokio::spawn(export_loop(endpoint, service_name, rx));
+            tx
+        });
+        Self { sender }
+    }
+}
+
+impl<S> Layer<S> for OtlpLayer
+where
+    S: tracing::Subscriber + for<'a> LookupSpan<'a>,
+{
+    fn on_new_span(&self, attrs: &Attributes<'_>, id: &Id, ctx: Context<'_, S>) {
+        let Some(span) = ctx.span(id) else { return };
+        let mut visitor = FieldVisitor::default();
+        attrs.record(&mut visitor);
+        // A non-empty traceparent field means the caller handed us remote
+        // context (see `request_span`); it wins over the local parent.
+        let remote = visitor
+            .fields
+            .iter()
+            .find(|(key, _)| key == crate::TRACEPARENT)
+            .and_then(|(_, value)| crate::parse_traceparent(value));
+        let (trace_id, parent_span_id) = if let Some((trace_id, parent)) = remote {
+            (trace_id, Some(parent))
+        } else if let Some(parent) = span.parent() {
+            match parent.extensions().get::<SpanData>() {
+                Some(data) => (data.trace_id, Some(data.span_id)),
+                None => (rand::random(), None),
+            }
+        } else {
+            (rand::random(), None)
+        };
+        visitor.fields.retain(|(key, _)| key != crate::TRACEPARENT);
+        span.extensions_mut().insert(SpanData {
+            trace_id,
+            span_id: rand::random(),
+            parent_span_id,
+            start: SystemTime::now(),
+            attributes: visitor.fields,
+        });
+    }
+
+    fn on_enter(&self, id: &Id, ctx: Context<'_, S>) {
+        let Some(span) = ctx.span(id) else { return };
+        let entry = span
+            .extensions()
+            .get::<SpanData>()
+            .map(|data| (data.trace_id, data.span_id));
+        if let Some(entry) = entry {
+            CURRENT.with(|stack| stack.borrow_mut().push(entry));
+        }
+    }
+
+    fn on_exit(&self, _id: &Id, _ctx: Context<'_, S>) {
+        CURRENT.with(|stack| {
+            stack.borrow_mut().pop();
+        });
+    }
+
+    fn on_close(&self, id: Id, ctx: Context<'_, S>) {
+        let Some(sender) = &self.sender else { return };
+        let Some(span) = ctx.span(&id) else { return };
+        let name = span.name();
+        let Some(data) = span.extensions_mut().remove::<SpanData>() else {
+            return;
+        };
+        // A full channel cannot happen (unbounded); a closed one means the
+        // exporter died, and dropping spans is the right failure mode.
+        let _ = sender.send(SpanRecord {
+            trace_id: data.trace_id,
+            span_id: data.span_id,
+            parent_span_id: data.parent_span_id,
+            name,
+            start: data.start,
+            end: SystemTime::now(),
+            attributes: data.attributes,
+        });
+    }
+}
+
+#[derive(Default)]
+struct FieldVisitor {
+    fields: Vec<(String, String)>,
+}
+
+impl Visit for FieldVisitor {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.fields.push((field.name().to_string(), value.to_string()));
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+        self.fields.push((field.name().to_string(), format!("{value:?}")));
+    }
+}
+
+const BATCH_SIZE: usize = 128;
+const FLUSH_INTERVAL: Duration = Duration::from_secs(3);
+
+async fn export_loop(
+    endpoint: String,
+    service_name: String,
+    mut rx: mpsc::UnboundedReceiver<SpanRecord>,
+) {
+    let client = reqwest::Client::new();
+    let url = format!("{}/v1/traces", endpoint.trim_end_matches('/'));
+    let mut batch = Vec::new();
+    let mut tick = tokio::time::interval(FLUSH_INTERVAL);
+    loop {
+        tokio::select! {
+            record = rx.recv() => match record {
+                Some(record) => {
+                    batch.push(record);
+                    if batch.len() >= BATCH_SIZE {
+                        flush(&client, &url, &service_name, &mut batch).await;
+                    }
+                }
+                None => {
+                    flush(&client, &url, &service_name, &mut batch).await;
+                    return;
+                }
+            },
+            _ = tick.tick() => {
+                if !batch.is_empty() {
+                    flush(&client, &url, &service_name, &mut batch).await;
+                }
+            }
+        }
+    }
+}
+
+async fn flush(client: &reqwest::Client, url: &str, service_name: &str, batch: &mut Vec<SpanRecord>) {
+    let body = encode(service_name, batch);
+    batch.clear();
+    // Failed exports only cost spans; debug level so an absent collector
+    // does not spam the log.
+    match client.post(url).json(&body).send().await {
+        Ok(response) if !response.status().is_success() => {
+            tracing::debug!(status = %response.status(), "otlp collector rejected spans");
+        }
+        Ok(_) => {}
+        Err(e) => tracing::debug!("otlp export failed: {e:#}"),
+    }
+}
+
+/// OTLP/JSON request body for one batch of spans.
+fn encode(service_name: &str, batch: &[SpanRecord]) -> serde_json::Value {
+    let spans: Vec<serde_json::Value> = batch.iter().map(encode_span).collect();
+    serde_json::json!({
+        "resourceSpans": [{
+            "resource": {
+                "attributes": [
+                    {"key": "service.name", "value": {"stringValue": service_name}}
+                ]
+            },
+            "scopeSpans": [{
+                "scope": {"name": "aurum-telemetry"},
+                "spans": spans,
+            }]
+        }]
+    })
+}
+
+fn encode_span(record: &SpanRecord) -> serde_json::Value {
+    let attributes: Vec<serde_json::Value> = record
+        .attributes
+        .iter()
+        .map(|(key, value)| serde_json::json!({"key": key, "value": {"stringValue": value}}))
+        .collect();
+    serde_json::json!({
+        "traceId": hex::encode(record.trace_id),
+        "spanId": hex::encode(record.span_id),
+        "parentSpanId": record.parent_span_id.map(hex::encode).unwrap_or_default(),
+        "name": record.name,
+        "kind": 1,
+        "startTimeUnixNano": unix_nanos(record.start).to_string(),
+        "endTimeUnixNano": unix_nanos(record.end).to_string(),
+        "attributes": attributes,
+    })
+}
+
+fn unix_nanos(time: SystemTime) -> u128 {
+    time.duration_since(UNIX_EPOCH).unwrap_or_default().as_nanos()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encode_produces_otlp_shape() {
+        let record = SpanRecord {
+            trace_id: [1; 16],
+            span_id: [2; 8],
+            parent_span_id: Some([3; 8]),
+            name: "http_request",
+            start: UNIX_EPOCH + Duration::from_secs(1),
+            end: UNIX_EPOCH + Duration::from_secs(2),
+            attributes: vec![("method".to_string(), "POST".to_string())],
+        };
+        let body = encode("build-monitor", &[record]);
+        let resource = &body["resourceSpans"][0];
+        assert_eq!(
+            resource["resource"]["attributes"][0]["value"]["stringValue"],
+            "build-monitor"
+        );
+        let span = &resource["scopeSpans"][0]["spans"][0];
+        assert_eq!(span["traceId"], hex::encode([1u8; 16]));
+        assert_eq!(span["parentSpanId"], hex::encode([3u8; 8]));
+        assert_eq!(span["startTimeUnixNano"], "1000000000");
+        assert_eq!(span["attributes"][0]["key"], "method");
+    }
+}
@@ -0,0 +1,229 @@
+//! Historical build analytics computed from the persisted build history:
+//! failure rates, mean time to recovery, duration trends, and the failure
+//! messages that come up most often.
+
+use crate::config::ServiceConfig;
+use crate::database::Database;
+use crate::types::{BuildResult, BuildStatus};
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::collections::HashMap;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FailureSignature {
+    pub signature: String,
+    pub count: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ServiceAnalytics {
+    pub service: String,
+    pub total_builds: u64,
+    pub failure_rate: f64,
+    pub mean_duration_ms: Option<i64>,
+    /// Mean duration of the newer half of builds relative to the older
+    /// half; above 1.0 means builds are getting slower.
+    pub duration_trend: Option<f64>,
+    /// Mean time from the start of a failure streak to the next passing
+    /// build; `None` when no recovery happened in the window.
+    pub mean_time_to_recovery_ms: Option<i64>,
+    /// Most frequent normalized failure messages, most common first.
+    pub failure_signatures: Vec<FailureSignature>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct AnalyticsReport {
+    pub window_days: i64,
+    pub generated_at: DateTime<Utc>,
+    pub services: Vec<ServiceAnalytics>,
+}
+
+pub async fn report(
+    database: &Database,
+    services: &[ServiceConfig],
+    window_days: i64,
+) -> Result<AnalyticsReport> {
+    let mut analytics = Vec::with_capacity(services.len());
+    for service in services {
+        let builds = database.builds_in_window(&service.name, window_days).await?;
+        analytics.push(analyze(&service.name, &builds));
+    }
+    Ok(AnalyticsReport {
+        window_days,
+        generated_at: Utc::now(),
+        services: analytics,
+    })
+}
+
+/// Aggregate a service's builds; `builds` must be ordered oldest first.
+pub fn analyze(service: &str, builds: &[BuildResult]) -> ServiceAnalytics {
+    let finished: Vec<&BuildResult> = builds
+        .iter()
+        .filter(|b| {
+            matches!(
+                b.status,
+                BuildStatus::Success | BuildStatus::Failed | BuildStatus::TimedOut
+            )
+        })
+        .collect();
+    let total = finished.len() as u64;
+    let failures: Vec<&&BuildResult> = finished
+        .iter()
+        .filter(|b| b.status != BuildStatus::Success)
+        .collect();
+    let failure_rate = if total == 0 {
+        0.0
+    } else {
+        failures.len() as f64 / total as f64
+    };
+
+    let durations: Vec<i64> = finished.iter().filter_map(|b| b.duration_ms).collect();
+    let mean_duration_ms = mean(&durations);
+    // Trend needs enough samples on both sides to say anything.
+    let duration_trend = if durations.len() >= 6 {
+        let mid = durations.len() / 2;
+        match (mean(&durations[..mid]), mean(&durations[mid..])) {
+            (Some(older), Some(newer)) if older > 0 => Some(newer as f64 / older as f64),
+            _ => None,
+        }
+    } else {
+        None
+    };
+
+    // A recovery spans from the start of a failure streak to the finish of
+    // the next passing build.
+    let mut recoveries: Vec<i64> = Vec::new();
+    let mut streak_start: Option<DateTime<Utc>> = None;
+    for build in &finished {
+        if build.status == BuildStatus::Success {
+            if let (Some(start), Some(end)) = (streak_start.take(), build.finished_at) {
+                recoveries.push((end - start).num_milliseconds());
+            }
+        } else if streak_start.is_none() {
+            streak_start = Some(build.started_at);
+        }
+    }
+    let mean_time_to_recovery_ms = mean(&recoveries);
+
+    let mut counts: HashMap<String, u64> = HashMap::new();
+    for failure in &failures {
+        *counts.entry(signature(failure.error.as_deref())).or_default() += 1;
+    }
+    let mut failure_signatures: Vec<FailureSignature> = counts
+        .into_iter()
+        .map(|(signature, count)| FailureSignature { signature, count })
+        .collect();
+    failure_signatures.sort_by(|a, b| b.count.cmp(&a.count).then(a.signature.cmp(&b.signature)));
+    failure_signatures.truncate(5);
+
+    ServiceAnalytics {
+        service: service.to_string(),
+        total_builds: total,
+        failure_rate,
+        mean_duration_ms,
+        duration_trend,
+        mean_time_to_recovery_ms,
+        failure_signatures,
+    }
+}
+
+/// One line per service, suitable for the weekly summary notification.
+pub fn summarize(report: &AnalyticsReport) -> String {
+    let mut lines = vec![format!("weekly build report ({}d window)", report.window_days)];
+    for s in &report.services {
+        let mttr = match s.mean_time_to_recovery_ms {
+            Some(ms) => format!("{}m", ms / 60_000),
+            None => "n/a".to_string(),
+        };
+        lines.push(format!(
+            "{}: {} builds, {:.0}% failed, MTTR {}{}",
+            s.service,
+            s.total_builds,
+            s.failure_rate * 100.0,
+            mttr,
+            s.failure_signatures
+                .first()
+                .map(|f| format!(", top failure: {} ({}x)", f.signature, f.count))
+                .unwrap_or_default(),
+        ));
+    }
+    lines.join("\n")
+}
+
+fn mean(values: &[i64]) -> Option<i64> {
+    if values.is_empty() {
+        None
+    } else {
+        Some(values.iter().sum::<i64>() / values.len() as i64)
+    }
+}
+
+/// Collapse commit hashes and numbers in the first line of an error so
+/// transient details don't split one root cause into many signatures.
+fn signature(error: Option<&str>) -> String {
+    let line = error
+        .and_then(|e| e.lines().find(|l| !l.trim().is_empty()))
+        .unwrap_or("unknown")
+        .trim();
+    line.split_whitespace()
+        .map(|token| {
+            if token.len() >= 7 && token.chars().all(|c| c.is_ascii_hexdigit()) {
+                "<sha>"
+            } else if token.chars().all(|c| c.is_ascii_digit()) {
+                "<n>"
+            } else {
+                token
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+
+    fn build(status: BuildStatus, start_min: i64, duration_ms: i64, error: Option<&str>) -> BuildResult {
+        let mut b = BuildResult::started("web", "abc");
+        b.started_at = Utc::now() + Duration::minutes(start_min);
+        b.status = status;
+        b.finished_at = Some(b.started_at + Duration::milliseconds(duration_ms));
+        b.duration_ms = Some(duration_ms);
+        b.error = error.map(String::from);
+        b
+    }
+
+    #[test]
+    fn failure_rate_mttr_and_signatures() {
+        let builds = vec![
+            build(BuildStatus::Success, 0, 1000, None),
+            build(BuildStatus::Failed, 10, 1000, Some("tsc failed at commit deadbeef001")),
+            build(BuildStatus::Failed, 20, 1000, Some("tsc failed at commit deadbeef002")),
+            // Recovers 20 minutes (+1s build) after the streak started.
+            build(BuildStatus::Success, 30, 1000, None),
+        ];
+        let a = analyze("web", &builds);
+        assert_eq!(a.total_builds, 4);
+        assert!((a.failure_rate - 0.5).abs() < f64::EPSILON);
+        assert_eq!(a.mean_time_to_recovery_ms, Some(20 * 60_000 + 1000));
+        // Both failures normalize to the same signature.
+        assert_eq!(a.failure_signatures.len(), 1);
+        assert_eq!(a.failure_signatures[0].count, 2);
+        assert_eq!(a.failure_signatures[0].signature, "tsc failed at commit <sha>");
+    }
+
+    #[test]
+    fn trend_compares_newer_builds_to_older_ones() {
+        let builds: Vec<BuildResult> = (0..8)
+            .map(|i| {
+                let duration = if i < 4 { 10_000 } else { 20_000 };
+                build(BuildStatus::Success, i * 10, duration, None)
+            })
+            .collect();
+        let a = analyze("web", &builds);
+        assert_eq!(a.duration_trend, Some(2.0));
+        assert_eq!(a.mean_duration_ms, Some(15_000));
+    }
+}
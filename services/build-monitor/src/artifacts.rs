@@ -0,0 +1,116 @@
+//! Artifact records for successful builds.
+//!
+//! Every successful build leaves behind an exact, addressable artifact —
+//! the image digest for docker builds, a sha256-checksummed binary for the
+//! rest — so a rollback can redeploy what actually passed instead of
+//! rebuilding at the old commit and hoping the toolchain is deterministic.
+//! Binaries can additionally be uploaded to an S3/MinIO bucket.
+
+use crate::config::ArtifactConfig;
+use crate::database::Database;
+use crate::types::{Artifact, BuildResult};
+use anyhow::{bail, Context, Result};
+use sha2::{Digest, Sha256};
+use std::io::Read;
+use std::path::Path;
+use std::process::Command;
+use tracing::info;
+
+pub struct ArtifactStore {
+    config: Option<ArtifactConfig>,
+    database: Database,
+}
+
+impl ArtifactStore {
+    pub fn new(config: Option<ArtifactConfig>, database: Database) -> Self {
+        Self { config, database }
+    }
+
+    /// Record the image produced by a docker build.
+    pub async fn record_image(
+        &self,
+        build: &BuildResult,
+        image: &str,
+        digest: Option<String>,
+    ) -> Result<()> {
+        self.database
+            .record_artifact(&Artifact::new(build, "image", image, digest))
+            .await
+    }
+
+    /// Checksum a binary produced by a build, upload it when a bucket is
+    /// configured, and record the result.
+    pub async fn record_binary(&self, build: &BuildResult, path: &Path) -> Result<()> {
+        let checksum = sha256_file(path)
+            .with_context(|| format!("failed to checksum artifact {}", path.display()))?;
+        let reference = match self.upload(build, path)? {
+            Some(remote) => remote,
+            None => path.display().to_string(),
+        };
+        self.database
+            .record_artifact(&Artifact::new(build, "binary", &reference, Some(checksum)))
+            .await
+    }
+
+    /// Upload through the aws CLI (works against MinIO via --endpoint-url);
+    /// returns the remote URL, or `None` when no bucket is configured.
+    fn upload(&self, build: &BuildResult, path: &Path) -> Result<Option<String>> {
+        let Some(config) = self.config.as_ref().filter(|c| c.bucket.is_some()) else {
+            return Ok(None);
+        };
+        let bucket = config.bucket.as_deref().unwrap();
+        let key = format!(
+            "{}/{}/{}",
+            build.service,
+            &build.commit[..build.commit.len().min(12)],
+            path.file_name().map(|n| n.to_string_lossy()).unwrap_or_default()
+        );
+        let url = format!("s3://{bucket}/{key}");
+        let mut command = Command::new("aws");
+        command.args(["s3", "cp", &path.display().to_string(), &url]);
+        if let Some(endpoint) = &config.s3_endpoint {
+            command.args(["--endpoint-url", endpoint]);
+        }
+        info!(service = %build.service, %url, "uploading build artifact");
+        let output = command.output().context("failed to invoke aws s3 cp")?;
+        if !output.status.success() {
+            bail!(
+                "artifact upload to {url} exited with {}: {}",
+                output.status,
+                String::from_utf8_lossy(&output.stderr).trim()
+            );
+        }
+        Ok(Some(url))
+    }
+}
+
+/// Streaming sha256 of a file, hex-encoded.
+pub fn sha256_file(path: &Path) -> Result<String> {
+    let mut file = std::fs::File::open(path)?;
+    let mut hasher = Sha256::new();
+    let mut buf = [0u8; 64 * 1024];
+    loop {
+        let n = file.read(&mut buf)?;
+        if n == 0 {
+            break;
+        }
+        hasher.update(&buf[..n]);
+    }
+    Ok(hex::encode(hasher.finalize()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn checksums_match_known_sha256() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("artifact.bin");
+        std::fs::write(&path, b"hello world").unwrap();
+        assert_eq!(
+            sha256_file(&path).unwrap(),
+            "b94d27b9934d3e08a52e52d7da7dabfac484efe37a5380ee9088f7ace2efcde9"
+        );
+    }
+}
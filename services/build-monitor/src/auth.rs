@@ -0,0 +1,150 @@
+//! Bearer-token authentication with viewer/operator/admin roles.
+//!
+//! Tokens are configured by environment variable name, never inline, so a
+//! checked-in config file can't leak credentials. Without an auth section
+//! the API stays open and every caller acts as an anonymous admin, which
+//! matches the previous behaviour for local use.
+
+use crate::config::AuthConfig;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use tracing::warn;
+
+/// Roles ordered by privilege: a higher role implies the lower ones.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Role {
+    /// Read-only access to dashboards and history.
+    Viewer,
+    /// May trigger rollbacks and pause/resume monitoring.
+    Operator,
+    /// May approve or reject pending rollbacks.
+    Admin,
+}
+
+impl Role {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Role::Viewer => "viewer",
+            Role::Operator => "operator",
+            Role::Admin => "admin",
+        }
+    }
+}
+
+/// The authenticated caller, attached to requests by the auth middleware.
+#[derive(Debug, Clone)]
+pub struct Identity {
+    pub name: String,
+    pub role: Role,
+}
+
+impl Identity {
+    pub fn allows(&self, required: Role) -> bool {
+        self.role >= required
+    }
+}
+
+pub struct Authenticator {
+    tokens: HashMap<String, Identity>,
+    enabled: bool,
+}
+
+impl Authenticator {
+    pub fn new(config: Option<AuthConfig>) -> Self {
+        let Some(config) = config else {
+            return Self {
+                tokens: HashMap::new(),
+                enabled: false,
+            };
+        };
+        let mut tokens = HashMap::new();
+        for entry in &config.tokens {
+            match std::env::var(&entry.token_env) {
+                Ok(token) if !token.is_empty() => {
+                    tokens.insert(
+                        token,
+                        Identity {
+                            name: entry.name.clone(),
+                            role: entry.role,
+                        },
+                    );
+                }
+                _ => warn!(
+                    name = %entry.name,
+                    env = %entry.token_env,
+                    "auth token env var not set; this principal cannot log in"
+                ),
+            }
+        }
+        Self {
+            tokens,
+            enabled: true,
+        }
+    }
+
+    pub fn enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /// Resolve a bearer token to an identity. With auth disabled every
+    /// caller is an anonymous admin.
+    pub fn authenticate(&self, bearer: Option<&str>) -> Option<Identity> {
+        if !self.enabled {
+            return Some(Identity {
+                name: "anonymous".to_string(),
+                role: Role::Admin,
+            });
+        }
+        bearer.and_then(|t| self.tokens.get(t)).cloned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::TokenConfig;
+
+    #[test]
+    fn roles_imply_lower_privileges() {
+        let operator = Identity {
+            name: "ops".into(),
+            role: Role::Operator,
+        };
+        assert!(operator.allows(Role::Viewer));
+        assert!(operator.allows(Role::Operator));
+        assert!(!operator.allows(Role::Admin));
+    }
+
+    #[test]
+    fn tokens_resolve_from_the_environment() {
+        std::env::set_var("BM_TEST_VIEWER_TOKEN", "hunter2");
+        let auth = Authenticator::new(Some(AuthConfig {
+            tokens: vec![
+                TokenConfig {
+                    name: "dashboards".into(),
+                    token_env: "BM_TEST_VIEWER_TOKEN".into(),
+                    role: Role::Viewer,
+                },
+                TokenConfig {
+                    name: "missing".into(),
+                    token_env: "BM_TEST_UNSET_TOKEN".into(),
+                    role: Role::Admin,
+                },
+            ],
+        }));
+        assert!(auth.enabled());
+        let identity = auth.authenticate(Some("hunter2")).unwrap();
+        assert_eq!(identity.name, "dashboards");
+        assert_eq!(identity.role, Role::Viewer);
+        assert!(auth.authenticate(Some("wrong")).is_none());
+        assert!(auth.authenticate(None).is_none());
+    }
+
+    #[test]
+    fn disabled_auth_grants_anonymous_admin() {
+        let auth = Authenticator::new(None);
+        let identity = auth.authenticate(None).unwrap();
+        assert_eq!(identity.role, Role::Admin);
+    }
+}
@@ -0,0 +1,397 @@
+//! Resumable git bisect engine.
+//!
+//! Unlike the old ad-hoc binary search, every judged commit is persisted,
+//! so a bisect interrupted by a restart picks up where it left off, commits
+//! can be marked untestable (skipped), and progress is observable through
+//! the web API and the `bisect` CLI subcommand.
+
+use crate::database::Database;
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::Row;
+use tracing::info;
+use uuid::Uuid;
+
+/// Verdict for a single tested commit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CommitVerdict {
+    Good,
+    Bad,
+    /// Untestable (e.g. the tree doesn't build for unrelated reasons).
+    Skipped,
+}
+
+impl CommitVerdict {
+    fn as_str(&self) -> &'static str {
+        match self {
+            CommitVerdict::Good => "good",
+            CommitVerdict::Bad => "bad",
+            CommitVerdict::Skipped => "skipped",
+        }
+    }
+
+    fn parse(s: &str) -> Self {
+        match s {
+            "good" => CommitVerdict::Good,
+            "bad" => CommitVerdict::Bad,
+            _ => CommitVerdict::Skipped,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BisectStatus {
+    Running,
+    Completed,
+    /// Too many skipped commits to identify a unique first bad commit.
+    Inconclusive,
+}
+
+impl BisectStatus {
+    fn as_str(&self) -> &'static str {
+        match self {
+            BisectStatus::Running => "running",
+            BisectStatus::Completed => "completed",
+            BisectStatus::Inconclusive => "inconclusive",
+        }
+    }
+
+    fn parse(s: &str) -> Self {
+        match s {
+            "running" => BisectStatus::Running,
+            "completed" => BisectStatus::Completed,
+            _ => BisectStatus::Inconclusive,
+        }
+    }
+}
+
+/// A bisect between a known-good and known-bad commit for one service.
+#[derive(Debug, Clone, Serialize)]
+pub struct BisectSession {
+    pub id: Uuid,
+    pub service: String,
+    pub good_commit: String,
+    pub bad_commit: String,
+    pub status: BisectStatus,
+    pub first_bad: Option<String>,
+    pub tested: u32,
+    pub skipped: u32,
+    pub remaining: u32,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+pub struct BisectEngine {
+    database: Database,
+}
+
+impl BisectEngine {
+    pub fn new(database: Database) -> Self {
+        Self { database }
+    }
+
+    /// Find or create the session for (service, good, bad). Reusing the
+    /// session is what makes bisects resumable after a restart.
+    pub async fn open_session(
+        &self,
+        service: &str,
+        good: &str,
+        bad: &str,
+    ) -> Result<BisectSession> {
+        let existing = sqlx::query(
+            "SELECT id FROM bisect_sessions WHERE service = ?1 AND good_commit = ?2 AND bad_commit = ?3",
+        )
+        .bind(service)
+        .bind(good)
+        .bind(bad)
+        .fetch_optional(self.database.pool())
+        .await?;
+        if let Some(row) = existing {
+            let id: String = row.get("id");
+            return self.session(Uuid::parse_str(&id)?).await;
+        }
+        let id = Uuid::new_v4();
+        let now = Utc::now().to_rfc3339();
+        sqlx::query(
+            r#"
+            INSERT INTO bisect_sessions
+                (id, service, good_commit, bad_commit, status, first_bad, created_at, updated_at)
+            VALUES (?1, ?2, ?3, ?4, 'running', NULL, ?5, ?5)
+            "#,
+        )
+        .bind(id.to_string())
+        .bind(service)
+        .bind(good)
+        .bind(bad)
+        .bind(&now)
+        .execute(self.database.pool())
+        .await?;
+        self.session(id).await
+    }
+
+    /// Run (or resume) a bisect over `commits` (oldest..newest, where the
+    /// newest is the known-bad head). `test` judges one commit.
+    pub async fn run<F>(
+        &self,
+        session: &BisectSession,
+        commits: &[String],
+        mut test: F,
+    ) -> Result<BisectSession>
+    where
+        F: FnMut(&str) -> Result<CommitVerdict>,
+    {
+        if commits.is_empty() {
+            self.finish(session.id, BisectStatus::Inconclusive, None).await?;
+            return self.session(session.id).await;
+        }
+        let mut verdicts = self.verdicts(session.id).await?;
+        loop {
+            match next_candidate(commits, &verdicts) {
+                Step::Test(idx) => {
+                    let commit = &commits[idx];
+                    let verdict = test(commit)?;
+                    info!(
+                        session = %session.id,
+                        commit = %commit,
+                        verdict = verdict.as_str(),
+                        "bisect step"
+                    );
+                    self.record_verdict(session.id, commit, verdict).await?;
+                    verdicts.push((commit.clone(), verdict));
+                }
+                Step::Done(first_bad) => {
+                    self.finish(session.id, BisectStatus::Completed, Some(&first_bad))
+                        .await?;
+                    break;
+                }
+                Step::Inconclusive => {
+                    self.finish(session.id, BisectStatus::Inconclusive, None).await?;
+                    break;
+                }
+            }
+        }
+        self.session(session.id).await
+    }
+
+    pub async fn session(&self, id: Uuid) -> Result<BisectSession> {
+        let row = sqlx::query("SELECT * FROM bisect_sessions WHERE id = ?1")
+            .bind(id.to_string())
+            .fetch_one(self.database.pool())
+            .await?;
+        let verdicts = self.verdicts(id).await?;
+        let skipped = verdicts
+            .iter()
+            .filter(|(_, v)| *v == CommitVerdict::Skipped)
+            .count() as u32;
+        let created_at: String = row.get("created_at");
+        let updated_at: String = row.get("updated_at");
+        Ok(BisectSession {
+            id,
+            service: row.get("service"),
+            good_commit: row.get("good_commit"),
+            bad_commit: row.get("bad_commit"),
+            status: BisectStatus::parse(row.get("status")),
+            first_bad: row.get("first_bad"),
+            tested: verdicts.len() as u32,
+            skipped,
+            remaining: 0,
+            created_at: DateTime::parse_from_rfc3339(&created_at)?.with_timezone(&Utc),
+            updated_at: DateTime::parse_from_rfc3339(&updated_at)?.with_timezone(&Utc),
+        })
+    }
+
+    pub async fn sessions(&self, limit: i64) -> Result<Vec<BisectSession>> {
+        let rows = sqlx::query("SELECT id FROM bisect_sessions ORDER BY created_at DESC LIMIT ?1")
+            .bind(limit)
+            .fetch_all(self.database.pool())
+            .await?;
+        let mut sessions = Vec::with_capacity(rows.len());
+        for row in rows {
+            let id: String = row.get("id");
+            sessions.push(self.session(Uuid::parse_str(&id)?).await?);
+        }
+        Ok(sessions)
+    }
+
+    async fn verdicts(&self, session_id: Uuid) -> Result<Vec<(String, CommitVerdict)>> {
+        let rows = sqlx::query(
+            "SELECT commit_sha, verdict FROM bisect_commits WHERE session_id = ?1",
+        )
+        .bind(session_id.to_string())
+        .fetch_all(self.database.pool())
+        .await?;
+        Ok(rows
+            .into_iter()
+            .map(|row| {
+                let verdict: String = row.get("verdict");
+                (row.get("commit_sha"), CommitVerdict::parse(&verdict))
+            })
+            .collect())
+    }
+
+    /// Record a verdict; doubles as the cached-build-result store so a
+    /// resumed bisect never rebuilds a judged commit.
+    pub async fn record_verdict(
+        &self,
+        session_id: Uuid,
+        commit: &str,
+        verdict: CommitVerdict,
+    ) -> Result<()> {
+        sqlx::query(
+            r#"
+            INSERT INTO bisect_commits (session_id, commit_sha, verdict)
+            VALUES (?1, ?2, ?3)
+            ON CONFLICT(session_id, commit_sha) DO UPDATE SET verdict = excluded.verdict
+            "#,
+        )
+        .bind(session_id.to_string())
+        .bind(commit)
+        .bind(verdict.as_str())
+        .execute(self.database.pool())
+        .await?;
+        sqlx::query("UPDATE bisect_sessions SET updated_at = ?1 WHERE id = ?2")
+            .bind(Utc::now().to_rfc3339())
+            .bind(session_id.to_string())
+            .execute(self.database.pool())
+            .await?;
+        Ok(())
+    }
+
+    async fn finish(
+        &self,
+        session_id: Uuid,
+        status: BisectStatus,
+        first_bad: Option<&str>,
+    ) -> Result<()> {
+        sqlx::query(
+            "UPDATE bisect_sessions SET status = ?1, first_bad = ?2, updated_at = ?3 WHERE id = ?4",
+        )
+        .bind(status.as_str())
+        .bind(first_bad)
+        .bind(Utc::now().to_rfc3339())
+        .bind(session_id.to_string())
+        .execute(self.database.pool())
+        .await?;
+        Ok(())
+    }
+}
+
+enum Step {
+    /// Test the commit at this index next.
+    Test(usize),
+    /// The first bad commit has been identified.
+    Done(String),
+    /// Every remaining candidate is skipped.
+    Inconclusive,
+}
+
+/// Classic bisect step selection over an ordered commit list where the last
+/// commit is known bad: narrow (last_good, first_bad) and pick the middle
+/// untested commit, stepping around skipped ones.
+fn next_candidate(commits: &[String], verdicts: &[(String, CommitVerdict)]) -> Step {
+    let verdict_of = |c: &str| verdicts.iter().rev().find(|(v, _)| v == c).map(|(_, v)| *v);
+
+    // Range endpoints: lo = highest known-good index, hi = lowest known-bad
+    // index (the final commit is bad by definition).
+    let mut lo: isize = -1;
+    let mut hi: usize = commits.len() - 1;
+    for (idx, commit) in commits.iter().enumerate() {
+        match verdict_of(commit) {
+            Some(CommitVerdict::Good) => lo = idx as isize,
+            Some(CommitVerdict::Bad) => {
+                hi = idx;
+                break;
+            }
+            _ => {}
+        }
+    }
+
+    // Untested, unskipped candidates strictly inside (lo, hi).
+    let candidates: Vec<usize> = ((lo + 1) as usize..hi)
+        .filter(|&i| verdict_of(&commits[i]).is_none())
+        .collect();
+    if candidates.is_empty() {
+        let unresolved_skips = ((lo + 1) as usize..hi)
+            .any(|i| verdict_of(&commits[i]) == Some(CommitVerdict::Skipped));
+        if unresolved_skips {
+            return Step::Inconclusive;
+        }
+        return Step::Done(commits[hi].clone());
+    }
+    Step::Test(candidates[candidates.len() / 2])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn run_bisect(results: &[Option<bool>]) -> BisectSession {
+        let db = Database::open_in_memory().await.unwrap();
+        let engine = BisectEngine::new(db);
+        let commits: Vec<String> = (0..results.len()).map(|i| format!("c{i}")).collect();
+        let session = engine.open_session("svc", "base", "head").await.unwrap();
+        engine
+            .run(&session, &commits, |c| {
+                let idx: usize = c[1..].parse().unwrap();
+                Ok(match results[idx] {
+                    Some(true) => CommitVerdict::Good,
+                    Some(false) => CommitVerdict::Bad,
+                    None => CommitVerdict::Skipped,
+                })
+            })
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn finds_first_bad_commit() {
+        let session = run_bisect(&[
+            Some(true),
+            Some(true),
+            Some(false),
+            Some(false),
+            Some(false),
+        ])
+        .await;
+        assert_eq!(session.status, BisectStatus::Completed);
+        assert_eq!(session.first_bad.as_deref(), Some("c2"));
+    }
+
+    #[tokio::test]
+    async fn skipped_neighbours_make_result_inconclusive() {
+        let session = run_bisect(&[Some(true), None, Some(false), Some(false)]).await;
+        assert_eq!(session.status, BisectStatus::Inconclusive);
+    }
+
+    #[tokio::test]
+    async fn resumed_session_reuses_cached_verdicts() {
+        let db = Database::open_in_memory().await.unwrap();
+        let engine = BisectEngine::new(db);
+        let commits: Vec<String> = (0..6).map(|i| format!("c{i}")).collect();
+        let session = engine.open_session("svc", "base", "head").await.unwrap();
+        // Pre-record a verdict as if a previous run was interrupted.
+        engine
+            .record_verdict(session.id, "c2", CommitVerdict::Good)
+            .await
+            .unwrap();
+
+        let mut rebuilt = Vec::new();
+        let done = engine
+            .run(&session, &commits, |c| {
+                rebuilt.push(c.to_string());
+                let idx: usize = c[1..].parse().unwrap();
+                Ok(if idx < 4 {
+                    CommitVerdict::Good
+                } else {
+                    CommitVerdict::Bad
+                })
+            })
+            .await
+            .unwrap();
+        assert_eq!(done.first_bad.as_deref(), Some("c4"));
+        assert!(!rebuilt.contains(&"c2".to_string()), "cached verdict was rebuilt");
+    }
+}
@@ -0,0 +1,280 @@
+//! Build strategy dispatch.
+//!
+//! Not every service ships as a Docker image: the Rust tools build with
+//! cargo and the web apps with npm. [`ServiceBuilder`] runs whichever
+//! strategy the service declares, always producing a [`BuildOutcome`].
+
+use crate::config::{BuildStrategy, CacheConfig, MatrixVariant, ServiceConfig};
+use crate::docker::{BuildOutcome, DockerManager};
+use anyhow::{Context, Result};
+use std::path::Path;
+use std::process::Command;
+use tracing::info;
+
+pub struct ServiceBuilder {
+    docker: DockerManager,
+    cache: CacheConfig,
+}
+
+impl ServiceBuilder {
+    pub fn new() -> Self {
+        Self {
+            docker: DockerManager::new(),
+            cache: CacheConfig::default(),
+        }
+    }
+
+    pub fn with_cache(docker: DockerManager, cache: CacheConfig) -> Self {
+        Self { docker, cache }
+    }
+
+    /// Build `service` from the checkout at `dir` using its declared
+    /// strategy. Build failures are reported in the outcome, not as errors.
+    pub fn build(&self, service: &ServiceConfig, dir: &Path) -> Result<BuildOutcome> {
+        self.build_variant(service, None, dir)
+    }
+
+    /// `build`, for one matrix variant: extra features and a target for
+    /// cargo strategies, a platform for docker builds, extra
+    /// environment for every command-based strategy.
+    pub fn build_variant(
+        &self,
+        service: &ServiceConfig,
+        variant: Option<&MatrixVariant>,
+        dir: &Path,
+    ) -> Result<BuildOutcome> {
+        info!(
+            service = %service.name,
+            strategy = ?service.build_strategy,
+            variant = variant.map(|v| v.name.as_str()).unwrap_or("default"),
+            "starting build"
+        );
+        // Resolve the env references up front so a missing secret fails
+        // the build before anything runs; the variant's env wins over
+        // the service's.
+        let mut declared = service.env.clone();
+        if let Some(variant) = variant {
+            declared.extend(variant.env.clone());
+        }
+        let resolved = crate::secrets::resolve_map(&declared)?;
+        let secrets = crate::secrets::secret_values(&resolved);
+        let mut envs: Vec<(String, String)> = resolved
+            .into_iter()
+            .map(|(key, r)| (key, r.value))
+            .collect();
+        let mut outcome = match &service.build_strategy {
+            BuildStrategy::Docker => {
+                self.docker
+                    .build_image_for(service, dir, variant.and_then(|v| v.target.as_deref()))?
+            }
+            BuildStrategy::Cargo { package, features } => {
+                let args = cargo_args(package.as_deref(), features, variant);
+                // Shared caches keep worktree and bisect builds from
+                // recompiling the dependency graph every time.
+                if let Some(home) = &self.cache.cargo_home {
+                    envs.push(("CARGO_HOME".to_string(), home.display().to_string()));
+                }
+                if let Some(target) = &self.cache.cargo_target_dir {
+                    envs.push(("CARGO_TARGET_DIR".to_string(), target.display().to_string()));
+                }
+                run_command_with_env("cargo", &args, dir, &envs, service.limits.timeout_secs)?
+            }
+            BuildStrategy::Npm { workspace, script } => {
+                let mut args = vec!["run".to_string(), script.clone()];
+                if let Some(workspace) = workspace {
+                    args.push(format!("--workspace={workspace}"));
+                }
+                run_command_with_env("npm", &args, dir, &envs, service.limits.timeout_secs)?
+            }
+            BuildStrategy::CustomCommand { command, args } => {
+                run_command_with_env(command, args, dir, &envs, service.limits.timeout_secs)?
+            }
+        };
+        outcome.log = crate::secrets::redact(&outcome.log, &secrets);
+        Ok(outcome)
+    }
+}
+
+impl Default for ServiceBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Assemble the `cargo build` argument list for a strategy plus an
+/// optional matrix variant: variant features are appended to the
+/// strategy's, and a variant target becomes `--target`.
+fn cargo_args(
+    package: Option<&str>,
+    features: &[String],
+    variant: Option<&MatrixVariant>,
+) -> Vec<String> {
+    let mut args = vec!["build".to_string(), "--release".to_string()];
+    if let Some(package) = package {
+        args.push("-p".to_string());
+        args.push(package.to_string());
+    }
+    let mut features = features.to_vec();
+    if let Some(variant) = variant {
+        features.extend(variant.features.iter().cloned());
+    }
+    if !features.is_empty() {
+        args.push("--features".to_string());
+        args.push(features.join(","));
+    }
+    if let Some(target) = variant.and_then(|v| v.target.as_deref()) {
+        args.push("--target".to_string());
+        args.push(target.to_string());
+    }
+    args
+}
+
+fn run_command_with_env(
+    program: &str,
+    args: &[String],
+    dir: &Path,
+    envs: &[(String, String)],
+    timeout_secs: u64,
+) -> Result<BuildOutcome> {
+    let mut command = Command::new(program);
+    command
+        .args(args)
+        .current_dir(dir)
+        .stdout(std::process::Stdio::piped())
+        .stderr(std::process::Stdio::piped());
+    for (key, value) in envs {
+        command.env(key, value);
+    }
+    let child = command
+        .spawn()
+        .with_context(|| format!("failed to invoke {program}"))?;
+    crate::docker::collect_with_timeout(child, std::time::Duration::from_secs(timeout_secs))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn service(strategy: BuildStrategy) -> ServiceConfig {
+        ServiceConfig {
+            name: "test".into(),
+            paths: vec![],
+            dockerfile: None,
+            health_port: None,
+            health_path: "/health".into(),
+            build_strategy: strategy,
+            depends_on: Vec::new(),
+            limits: crate::config::BuildLimits::default(),
+            probe: crate::config::ProbeConfig::default(),
+            artifact_path: None,
+            env: Default::default(),
+            build_args: Default::default(),
+            matrix: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn custom_command_success_and_failure() {
+        let builder = ServiceBuilder::new();
+        let dir = std::env::temp_dir();
+
+        let ok = builder
+            .build(
+                &service(BuildStrategy::CustomCommand {
+                    command: "sh".into(),
+                    args: vec!["-c".into(), "echo built".into()],
+                }),
+                &dir,
+            )
+            .unwrap();
+        assert!(ok.success);
+        assert!(ok.log.contains("built"));
+
+        let failed = builder
+            .build(
+                &service(BuildStrategy::CustomCommand {
+                    command: "sh".into(),
+                    args: vec!["-c".into(), "echo nope >&2; exit 1".into()],
+                }),
+                &dir,
+            )
+            .unwrap();
+        assert!(!failed.success);
+        assert!(failed.log.contains("nope"));
+    }
+
+    #[test]
+    fn hung_build_is_killed_and_marked_timed_out() {
+        let builder = ServiceBuilder::new();
+        let mut svc = service(BuildStrategy::CustomCommand {
+            command: "sh".into(),
+            args: vec!["-c".into(), "sleep 30".into()],
+        });
+        svc.limits.timeout_secs = 1;
+        let outcome = builder.build(&svc, &std::env::temp_dir()).unwrap();
+        assert!(!outcome.success);
+        assert!(outcome.timed_out);
+        assert!(outcome.log.contains("timeout"));
+    }
+
+    #[test]
+    fn cargo_args_merge_variant_features_and_target() {
+        assert_eq!(cargo_args(None, &[], None), vec!["build", "--release"]);
+
+        let variant: MatrixVariant = serde_json::from_str(
+            r#"{ "name": "arm", "features": ["extra"], "target": "aarch64-unknown-linux-gnu" }"#,
+        )
+        .unwrap();
+        assert_eq!(
+            cargo_args(Some("build-monitor"), &["base".into()], Some(&variant)),
+            vec![
+                "build",
+                "--release",
+                "-p",
+                "build-monitor",
+                "--features",
+                "base,extra",
+                "--target",
+                "aarch64-unknown-linux-gnu",
+            ]
+        );
+    }
+
+    #[test]
+    fn variant_env_overrides_service_env() {
+        let builder = ServiceBuilder::new();
+        let mut svc = service(BuildStrategy::CustomCommand {
+            command: "sh".into(),
+            args: vec!["-c".into(), "echo mode=$MODE".into()],
+        });
+        svc.env.insert("MODE".into(), "release".into());
+        let variant: MatrixVariant = serde_json::from_str(
+            r#"{ "name": "debug", "env": { "MODE": "debug" } }"#,
+        )
+        .unwrap();
+
+        let outcome = builder
+            .build_variant(&svc, Some(&variant), &std::env::temp_dir())
+            .unwrap();
+        assert!(outcome.success);
+        assert!(outcome.log.contains("mode=debug"));
+    }
+
+    #[test]
+    fn strategy_deserializes_with_docker_default() {
+        let svc: ServiceConfig = serde_json::from_str(
+            r#"{ "name": "web", "dockerfile": "apps/web/Dockerfile" }"#,
+        )
+        .unwrap();
+        assert!(matches!(svc.build_strategy, BuildStrategy::Docker));
+
+        let svc: ServiceConfig = serde_json::from_str(
+            r#"{ "name": "tools", "build_strategy": { "type": "cargo", "package": "build-monitor" } }"#,
+        )
+        .unwrap();
+        assert!(matches!(
+            svc.build_strategy,
+            BuildStrategy::Cargo { ref package, .. } if package.as_deref() == Some("build-monitor")
+        ));
+    }
+}
@@ -0,0 +1,144 @@
+//! HTTP client for a running build-monitor daemon.
+//!
+//! CLI subcommands prefer talking to the daemon's web API over opening the
+//! repo and database directly, so a command run next to a live daemon
+//! can't race it on the git checkout or the SQLite file.
+
+use anyhow::{Context, Result};
+use std::time::Duration;
+
+pub struct DaemonClient {
+    endpoint: String,
+    token: Option<String>,
+    client: reqwest::Client,
+}
+
+impl DaemonClient {
+    pub fn new(endpoint: String, token: Option<String>) -> Self {
+        Self {
+            endpoint: endpoint.trim_end_matches('/').to_string(),
+            token,
+            client: reqwest::Client::new(),
+        }
+    }
+
+    pub fn endpoint(&self) -> &str {
+        &self.endpoint
+    }
+
+    /// Quick liveness probe; used to decide between client and direct mode.
+    pub async fn is_reachable(&self) -> bool {
+        let request = self
+            .client
+            .get(format!("{}/health", self.endpoint))
+            .timeout(Duration::from_secs(2));
+        matches!(request.send().await, Ok(r) if r.status().is_success())
+    }
+
+    pub async fn status(&self) -> Result<serde_json::Value> {
+        self.get("/api/services").await
+    }
+
+    pub async fn history(&self, service: Option<&str>, limit: i64) -> Result<serde_json::Value> {
+        match service {
+            Some(name) => {
+                self.get(&format!("/api/services/{name}/builds?limit={limit}"))
+                    .await
+            }
+            None => {
+                let dashboard = self.get("/api/dashboard").await?;
+                Ok(dashboard
+                    .get("recent_builds")
+                    .cloned()
+                    .unwrap_or(serde_json::Value::Array(Vec::new())))
+            }
+        }
+    }
+
+    pub async fn rollback(
+        &self,
+        service: &str,
+        to_commit: &str,
+        strategy: &str,
+    ) -> Result<serde_json::Value> {
+        self.post(
+            &format!("/api/services/{service}/rollback"),
+            &serde_json::json!({
+                "to_commit": to_commit,
+                "strategy": strategy,
+                "reason": "manual rollback via CLI",
+            }),
+        )
+        .await
+    }
+
+    pub async fn pause(
+        &self,
+        service: Option<&str>,
+        reason: &str,
+        minutes: Option<i64>,
+    ) -> Result<serde_json::Value> {
+        self.post(
+            "/api/pause",
+            &serde_json::json!({
+                "service": service,
+                "reason": reason,
+                "minutes": minutes,
+            }),
+        )
+        .await
+    }
+
+    pub async fn resume(&self, service: Option<&str>) -> Result<serde_json::Value> {
+        self.post("/api/resume", &serde_json::json!({ "service": service }))
+            .await
+    }
+
+    pub async fn unfreeze(&self, service: &str) -> Result<serde_json::Value> {
+        self.post(
+            &format!("/api/services/{service}/unfreeze"),
+            &serde_json::json!({}),
+        )
+        .await
+    }
+
+    async fn get(&self, path: &str) -> Result<serde_json::Value> {
+        let mut request = self.client.get(format!("{}{path}", self.endpoint));
+        if let Some(token) = &self.token {
+            request = request.bearer_auth(token);
+        }
+        let response = request
+            .send()
+            .await
+            .with_context(|| format!("GET {path} failed"))?;
+        decode(path, response).await
+    }
+
+    async fn post(&self, path: &str, body: &serde_json::Value) -> Result<serde_json::Value> {
+        let mut request = self.client.post(format!("{}{path}", self.endpoint));
+        if let Some(token) = &self.token {
+            request = request.bearer_auth(token);
+        }
+        let response = request
+            .json(body)
+            .send()
+            .await
+            .with_context(|| format!("POST {path} failed"))?;
+        decode(path, response).await
+    }
+}
+
+async fn decode(path: &str, response: reqwest::Response) -> Result<serde_json::Value> {
+    let status = response.status();
+    let body: serde_json::Value = response
+        .json()
+        .await
+        .with_context(|| format!("{path} returned a non-JSON body"))?;
+    if !status.is_success() {
+        anyhow::bail!(
+            "daemon rejected {path}: {status}: {}",
+            body.get("error").and_then(|e| e.as_str()).unwrap_or("unknown error")
+        );
+    }
+    Ok(body)
+}
@@ -0,0 +1,165 @@
+//! Docker Compose orchestration driver.
+//!
+//! The production stack runs under docker-compose, so when a compose file
+//! is configured the monitor maps services to their compose entries:
+//! redeploys go through `docker compose up -d --no-deps` instead of raw
+//! `docker run`, and dependency ordering defaults to what the compose file
+//! declares.
+
+use crate::config::ComposeConfig;
+use anyhow::{bail, Context, Result};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::process::Command;
+use tracing::info;
+
+/// The subset of a compose service entry the monitor cares about.
+#[derive(Debug, Clone)]
+pub struct ComposeService {
+    pub image: Option<String>,
+    pub depends_on: Vec<String>,
+    pub has_healthcheck: bool,
+}
+
+#[derive(Debug, Clone)]
+pub struct ComposeDriver {
+    config: ComposeConfig,
+}
+
+impl ComposeDriver {
+    pub fn new(config: ComposeConfig) -> Self {
+        Self { config }
+    }
+
+    /// Parse the compose file into per-service specs.
+    pub fn load(&self) -> Result<BTreeMap<String, ComposeService>> {
+        let raw = std::fs::read_to_string(&self.config.file).with_context(|| {
+            format!("failed to read compose file {}", self.config.file.display())
+        })?;
+        parse(&raw).with_context(|| {
+            format!("failed to parse compose file {}", self.config.file.display())
+        })
+    }
+
+    /// Recreate one service from its compose entry without touching its
+    /// dependencies — the equivalent of `up -d --no-deps --build <name>`.
+    pub fn up(&self, service: &str, build: bool) -> Result<()> {
+        info!(service, build, "recreating service via compose");
+        let mut args = self.base_args();
+        args.extend(["up", "-d", "--no-deps"].map(String::from));
+        if build {
+            args.push("--build".to_string());
+        }
+        args.push(service.to_string());
+        let output = Command::new("docker")
+            .args(&args)
+            .output()
+            .context("failed to invoke docker compose")?;
+        if !output.status.success() {
+            bail!(
+                "docker compose up for {service} exited with {}: {}",
+                output.status,
+                String::from_utf8_lossy(&output.stderr).trim()
+            );
+        }
+        Ok(())
+    }
+
+    fn base_args(&self) -> Vec<String> {
+        let mut args = vec![
+            "compose".to_string(),
+            "-f".to_string(),
+            self.config.file.display().to_string(),
+        ];
+        if let Some(project) = &self.config.project {
+            args.push("-p".to_string());
+            args.push(project.clone());
+        }
+        args
+    }
+}
+
+/// Compose allows `depends_on` as either a plain list or a map with
+/// conditions; both collapse to the dependency names here.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+enum DependsOn {
+    List(Vec<String>),
+    Map(BTreeMap<String, serde_yaml::Value>),
+}
+
+impl DependsOn {
+    fn names(self) -> Vec<String> {
+        match self {
+            DependsOn::List(names) => names,
+            DependsOn::Map(map) => map.into_keys().collect(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct ComposeEntry {
+    #[serde(default)]
+    image: Option<String>,
+    #[serde(default)]
+    depends_on: Option<DependsOn>,
+    #[serde(default)]
+    healthcheck: Option<serde_yaml::Value>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct ComposeFile {
+    #[serde(default)]
+    services: BTreeMap<String, ComposeEntry>,
+}
+
+fn parse(raw: &str) -> Result<BTreeMap<String, ComposeService>> {
+    let file: ComposeFile = serde_yaml::from_str(raw)?;
+    Ok(file
+        .services
+        .into_iter()
+        .map(|(name, entry)| {
+            (
+                name,
+                ComposeService {
+                    image: entry.image,
+                    depends_on: entry.depends_on.map(DependsOn::names).unwrap_or_default(),
+                    has_healthcheck: entry.healthcheck.is_some(),
+                },
+            )
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_both_depends_on_forms_and_healthchecks() {
+        let spec = parse(
+            r#"
+services:
+  web:
+    image: aurum/web:latest
+    depends_on:
+      - ml-api
+    healthcheck:
+      test: ["CMD", "curl", "-f", "http://localhost:3000/health"]
+  ml-api:
+    image: aurum/ml-api:latest
+    depends_on:
+      redis:
+        condition: service_healthy
+  redis:
+    image: redis:7
+"#,
+        )
+        .unwrap();
+        assert_eq!(spec["web"].depends_on, vec!["ml-api"]);
+        assert!(spec["web"].has_healthcheck);
+        assert_eq!(spec["ml-api"].depends_on, vec!["redis"]);
+        assert!(!spec["redis"].has_healthcheck);
+        assert_eq!(spec["redis"].image.as_deref(), Some("redis:7"));
+    }
+}
@@ -0,0 +1,851 @@
+//! Configuration loading for the build monitor.
+//!
+//! Configuration lives in a JSON file (`build-monitor.json`) next to the
+//! monitored repository, loaded through the shared layered loader:
+//! defaults, then the file, then `BUILD_MONITOR__*` environment
+//! variables, then `--set` overrides. Secrets stay in their own
+//! environment variables, named by the `*_env` fields.
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MonitorConfig {
+    /// Path to the git repository being monitored.
+    pub repo_path: PathBuf,
+    /// Branch that represents production.
+    #[serde(default = "default_branch")]
+    pub branch: String,
+    /// Poll interval in seconds for new commits.
+    #[serde(default = "default_poll_interval")]
+    pub poll_interval_secs: u64,
+    /// Path of the SQLite database used for build history.
+    #[serde(default = "default_database_path")]
+    pub database_path: PathBuf,
+    /// Directory where compressed build logs are stored.
+    #[serde(default = "default_log_dir")]
+    pub log_dir: PathBuf,
+    /// Services under management.
+    #[serde(default)]
+    pub services: Vec<ServiceConfig>,
+    #[serde(default)]
+    pub web: WebConfig,
+    #[serde(default)]
+    pub notifications: NotificationConfig,
+    #[serde(default)]
+    pub rollback: RollbackConfig,
+    #[serde(default)]
+    pub flaky: FlakyConfig,
+    /// Remote image registry; without it images stay local-only.
+    #[serde(default)]
+    pub registry: Option<RegistryConfig>,
+    /// Local image retention policy applied during periodic cleanup.
+    #[serde(default)]
+    pub retention: RetentionConfig,
+    /// Build-cache behaviour shared by docker and cargo builds.
+    #[serde(default)]
+    pub cache: CacheConfig,
+    /// Extra branches and PRs to build in isolation.
+    #[serde(default)]
+    pub watch: WatchConfig,
+    /// GitHub Checks reporting for built commits.
+    #[serde(default)]
+    pub github: Option<GithubConfig>,
+    /// Recurring windows during which failure handling is suppressed.
+    #[serde(default)]
+    pub maintenance: Vec<crate::maintenance::MaintenanceWindow>,
+    /// Self-healing daemon to file repeated code failures with.
+    #[serde(default)]
+    pub healing: Option<HealingConfig>,
+    /// Compose file the production stack runs under; when set, redeploys
+    /// go through `docker compose` and dependencies default from it.
+    #[serde(default)]
+    pub compose: Option<ComposeConfig>,
+    /// Host-resource thresholds for the watchdog.
+    #[serde(default)]
+    pub watchdog: WatchdogConfig,
+    /// Artifact storage for successful builds.
+    #[serde(default)]
+    pub artifacts: Option<ArtifactConfig>,
+    /// Leader election between monitor replicas sharing one database.
+    #[serde(default)]
+    pub election: ElectionConfig,
+}
+
+/// Which replica of a redundant monitor pair gets to act.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ElectionConfig {
+    /// Only the lease-holding replica builds, rolls back, and redeploys;
+    /// the standby serves the read API. Disable for single-instance
+    /// deployments.
+    #[serde(default = "default_election_enabled")]
+    pub enabled: bool,
+    /// Lease TTL; a dead leader is replaced after at most this long.
+    #[serde(default = "default_lease_ttl")]
+    pub lease_ttl_secs: u64,
+}
+
+impl Default for ElectionConfig {
+    fn default() -> Self {
+        Self {
+            enabled: default_election_enabled(),
+            lease_ttl_secs: default_lease_ttl(),
+        }
+    }
+}
+
+fn default_election_enabled() -> bool {
+    true
+}
+
+fn default_lease_ttl() -> u64 {
+    30
+}
+
+/// Where build check runs are posted.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GithubConfig {
+    /// Repository in `owner/name` form.
+    pub repo: String,
+    /// Environment variable holding the API token.
+    #[serde(default = "default_github_token_env")]
+    pub token_env: String,
+    /// API base URL; override for GitHub Enterprise.
+    #[serde(default = "default_github_api_url")]
+    pub api_url: String,
+}
+
+fn default_github_token_env() -> String {
+    "GITHUB_TOKEN".to_string()
+}
+
+fn default_github_api_url() -> String {
+    "https://api.github.com".to_string()
+}
+
+/// Self-healing daemon that turns repeated code failures into issues.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HealingConfig {
+    /// Base URL of the self-healing daemon's API.
+    pub endpoint: String,
+    /// Consecutive failures before an issue is filed.
+    #[serde(default = "default_healing_threshold")]
+    pub failure_threshold: u32,
+    /// Ask the daemon to start patch generation immediately.
+    #[serde(default)]
+    pub auto_patch: bool,
+    /// Drive the full failure → reproduction → patch → validation loop
+    /// after filing, instead of leaving the daemon to poll.
+    #[serde(default)]
+    pub orchestrate: bool,
+    /// Command the daemon runs to re-check the reproduction after a patch
+    /// validates (it sees the candidate as `$TEST_CASE`); the issue only
+    /// counts as resolved once this stops failing. Without it the loop
+    /// gates on validation alone.
+    #[serde(default)]
+    pub repro_command: Option<String>,
+}
+
+fn default_healing_threshold() -> u32 {
+    2
+}
+
+/// Thresholds for the host-resource watchdog.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WatchdogConfig {
+    /// Warn below this much free disk.
+    #[serde(default = "default_warn_free_disk_mb")]
+    pub warn_free_disk_mb: u64,
+    /// Force image cleanup below this much free disk.
+    #[serde(default = "default_cleanup_free_disk_mb")]
+    pub cleanup_free_disk_mb: u64,
+    /// Warn below this much available memory.
+    #[serde(default = "default_warn_free_memory_mb")]
+    pub warn_free_memory_mb: u64,
+    /// Warn above this 1-minute load average per CPU.
+    #[serde(default = "default_max_load_per_cpu")]
+    pub max_load_per_cpu: f64,
+    /// Minimum seconds between repeated warnings of the same kind.
+    #[serde(default = "default_warn_cooldown_secs")]
+    pub warn_cooldown_secs: u64,
+}
+
+impl Default for WatchdogConfig {
+    fn default() -> Self {
+        Self {
+            warn_free_disk_mb: default_warn_free_disk_mb(),
+            cleanup_free_disk_mb: default_cleanup_free_disk_mb(),
+            warn_free_memory_mb: default_warn_free_memory_mb(),
+            max_load_per_cpu: default_max_load_per_cpu(),
+            warn_cooldown_secs: default_warn_cooldown_secs(),
+        }
+    }
+}
+
+fn default_warn_free_disk_mb() -> u64 {
+    2048
+}
+
+fn default_cleanup_free_disk_mb() -> u64 {
+    1024
+}
+
+fn default_warn_free_memory_mb() -> u64 {
+    512
+}
+
+fn default_max_load_per_cpu() -> f64 {
+    2.0
+}
+
+fn default_warn_cooldown_secs() -> u64 {
+    3600
+}
+
+/// Where build artifacts go. Image digests are always recorded; binaries
+/// are uploaded only when a bucket is configured.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ArtifactConfig {
+    /// S3-compatible endpoint (MinIO); omit for real S3.
+    #[serde(default)]
+    pub s3_endpoint: Option<String>,
+    /// Bucket binaries are uploaded to.
+    #[serde(default)]
+    pub bucket: Option<String>,
+}
+
+/// The compose deployment the monitored services belong to.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ComposeConfig {
+    /// Path to the compose file (e.g. docker-compose.prod.yml).
+    pub file: PathBuf,
+    /// Compose project name; defaults to the directory name.
+    #[serde(default)]
+    pub project: Option<String>,
+}
+
+/// Non-production refs the monitor builds without ever rolling back.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct WatchConfig {
+    /// Branch names to watch in addition to the production branch.
+    #[serde(default)]
+    pub branches: Vec<String>,
+    /// Also watch open pull requests via GitHub's `refs/pull/*/head` refs.
+    #[serde(default)]
+    pub prs: bool,
+}
+
+/// Cache settings that keep incremental monitor builds cheap.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CacheConfig {
+    /// Build images through BuildKit instead of the legacy builder.
+    #[serde(default = "default_true")]
+    pub buildkit: bool,
+    /// Export inline cache metadata so pushed images seed later builds.
+    #[serde(default = "default_true")]
+    pub inline_cache: bool,
+    /// Additional `--cache-from` image references.
+    #[serde(default)]
+    pub cache_from: Vec<String>,
+    /// Shared CARGO_HOME so cargo builds reuse the registry cache across
+    /// worktrees; unset leaves cargo's default.
+    #[serde(default)]
+    pub cargo_home: Option<PathBuf>,
+    /// Shared CARGO_TARGET_DIR so bisect worktree builds reuse artifacts.
+    #[serde(default)]
+    pub cargo_target_dir: Option<PathBuf>,
+}
+
+impl Default for CacheConfig {
+    fn default() -> Self {
+        Self {
+            buildkit: true,
+            inline_cache: true,
+            cache_from: Vec::new(),
+            cargo_home: None,
+            cargo_target_dir: None,
+        }
+    }
+}
+
+/// Remote registry that built images are pushed to and rollback images are
+/// pulled from.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RegistryConfig {
+    /// Registry host (and optional namespace), e.g. `registry.example.com/aurum`.
+    pub url: String,
+    #[serde(default)]
+    pub username: Option<String>,
+    /// Name of the environment variable holding the registry password, so
+    /// the secret itself never lands in the config file.
+    #[serde(default)]
+    pub password_env: Option<String>,
+    /// Push successful builds; pulls for rollbacks happen regardless.
+    #[serde(default = "default_true")]
+    pub push: bool,
+}
+
+/// How many commit-tagged images to keep locally per service.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RetentionConfig {
+    /// Images older than this many days are removed.
+    #[serde(default = "default_max_age_days")]
+    pub max_age_days: i64,
+    /// At most this many commit-tagged images are kept per service.
+    #[serde(default = "default_max_images")]
+    pub max_images_per_service: usize,
+}
+
+impl Default for RetentionConfig {
+    fn default() -> Self {
+        Self {
+            max_age_days: default_max_age_days(),
+            max_images_per_service: default_max_images(),
+        }
+    }
+}
+
+fn default_max_age_days() -> i64 {
+    14
+}
+
+fn default_max_images() -> usize {
+    10
+}
+
+/// Retry and flakiness-classification behaviour for builds.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FlakyConfig {
+    /// Re-run a failed build up to this many extra times.
+    #[serde(default = "default_max_retries")]
+    pub max_retries: u32,
+    /// Flakiness score at or above which failures stop triggering
+    /// rollbacks and escalated notifications.
+    #[serde(default = "default_flaky_threshold")]
+    pub flaky_threshold: f64,
+    /// Number of recent builds considered when scoring flakiness.
+    #[serde(default = "default_flaky_window")]
+    pub window: i64,
+}
+
+impl Default for FlakyConfig {
+    fn default() -> Self {
+        Self {
+            max_retries: default_max_retries(),
+            flaky_threshold: default_flaky_threshold(),
+            window: default_flaky_window(),
+        }
+    }
+}
+
+fn default_max_retries() -> u32 {
+    1
+}
+
+fn default_flaky_threshold() -> f64 {
+    0.5
+}
+
+fn default_flaky_window() -> i64 {
+    50
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ServiceConfig {
+    /// Service name, matching the compose service / image name.
+    pub name: String,
+    /// Paths (relative to the repo root) that belong to this service; a
+    /// commit only triggers a build when it touches one of them.
+    #[serde(default)]
+    pub paths: Vec<String>,
+    /// Dockerfile used to build the service image (Docker strategy only).
+    #[serde(default)]
+    pub dockerfile: Option<PathBuf>,
+    /// Container port probed by health checks.
+    #[serde(default)]
+    pub health_port: Option<u16>,
+    /// HTTP path probed by health checks, e.g. `/health`.
+    #[serde(default = "default_health_path")]
+    pub health_path: String,
+    /// How the service is built; defaults to a Docker image build.
+    #[serde(default)]
+    pub build_strategy: BuildStrategy,
+    /// Other managed services this one depends on; a build of a dependency
+    /// cascades into a build of this service.
+    #[serde(default)]
+    pub depends_on: Vec<String>,
+    /// Resource limits applied to this service's builds.
+    #[serde(default)]
+    pub limits: BuildLimits,
+    /// How this service's health is probed.
+    #[serde(default)]
+    pub probe: ProbeConfig,
+    /// Binary produced by non-docker builds (relative to the repo root),
+    /// checksummed and stored as the build's artifact.
+    #[serde(default)]
+    pub artifact_path: Option<PathBuf>,
+    /// Environment for builds and container runs. Values may be literal
+    /// or references (`env://VAR`, `file:///path`,
+    /// `vault://path#field`); referenced values are redacted from logs.
+    #[serde(default)]
+    pub env: std::collections::BTreeMap<String, String>,
+    /// Docker build args, with the same reference syntax as `env`.
+    #[serde(default)]
+    pub build_args: std::collections::BTreeMap<String, String>,
+    /// Build variants run as a matrix after the regular build — extra
+    /// feature sets, cross-compilation targets for the ARM nodes — with
+    /// per-variant status on the build record. The service only counts
+    /// green when every required variant also passes; the regular build
+    /// still produces the deployable artifact.
+    #[serde(default)]
+    pub matrix: Vec<MatrixVariant>,
+}
+
+/// One variant in a service's build matrix.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MatrixVariant {
+    /// Variant name recorded on the build, e.g. "arm64" or "minimal".
+    pub name: String,
+    /// Extra cargo features, on top of the strategy's (cargo builds).
+    #[serde(default)]
+    pub features: Vec<String>,
+    /// Cross-compilation target: a triple like
+    /// `aarch64-unknown-linux-gnu` for cargo builds, a platform like
+    /// `linux/arm64` for docker builds.
+    #[serde(default)]
+    pub target: Option<String>,
+    /// Extra environment over the service's, with the same reference
+    /// syntax; applied to command-based strategies.
+    #[serde(default)]
+    pub env: std::collections::BTreeMap<String, String>,
+    /// Required variants gate the service's green status; a failing
+    /// optional variant only raises an alert.
+    #[serde(default = "default_variant_required")]
+    pub required: bool,
+}
+
+/// Health probe behaviour for one service.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProbeConfig {
+    /// Probe mechanism; HTTP against the published health endpoint by
+    /// default.
+    #[serde(default)]
+    pub kind: ProbeKind,
+    /// Consecutive successful probes before a service counts as healthy.
+    #[serde(default = "default_success_threshold")]
+    pub success_threshold: u32,
+    /// Consecutive failed probes before a service counts as down.
+    #[serde(default = "default_failure_threshold_probe")]
+    pub failure_threshold: u32,
+    /// Per-probe timeout.
+    #[serde(default = "default_probe_timeout")]
+    pub timeout_secs: u64,
+    /// Seconds between probes; unset probes every monitor cycle.
+    #[serde(default)]
+    pub interval_secs: Option<u64>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ProbeKind {
+    /// HTTP GET against `health_port`/`health_path` on localhost.
+    #[default]
+    Http,
+    /// Bare TCP connect to `health_port`.
+    Tcp,
+    /// Docker HEALTHCHECK / run-state inspection.
+    Docker,
+}
+
+impl Default for ProbeConfig {
+    fn default() -> Self {
+        Self {
+            kind: ProbeKind::default(),
+            success_threshold: default_success_threshold(),
+            failure_threshold: default_failure_threshold_probe(),
+            timeout_secs: default_probe_timeout(),
+            interval_secs: None,
+        }
+    }
+}
+
+fn default_success_threshold() -> u32 {
+    1
+}
+
+fn default_failure_threshold_probe() -> u32 {
+    3
+}
+
+fn default_probe_timeout() -> u64 {
+    5
+}
+
+/// Caps that keep one runaway build from starving the host or wedging the
+/// monitoring loop.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BuildLimits {
+    /// Builds running longer than this are killed and recorded as timed out.
+    #[serde(default = "default_build_timeout")]
+    pub timeout_secs: u64,
+    /// CPU cores available to docker builds, e.g. 2.0.
+    #[serde(default)]
+    pub cpus: Option<f64>,
+    /// Memory available to docker builds, in docker syntax, e.g. "2g".
+    #[serde(default)]
+    pub memory: Option<String>,
+    /// Builds refuse to start with less than this much free disk.
+    #[serde(default = "default_min_free_disk_mb")]
+    pub min_free_disk_mb: u64,
+}
+
+impl Default for BuildLimits {
+    fn default() -> Self {
+        Self {
+            timeout_secs: default_build_timeout(),
+            cpus: None,
+            memory: None,
+            min_free_disk_mb: default_min_free_disk_mb(),
+        }
+    }
+}
+
+fn default_build_timeout() -> u64 {
+    1800
+}
+
+fn default_min_free_disk_mb() -> u64 {
+    1024
+}
+
+/// Per-service build strategy with strategy-specific options.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum BuildStrategy {
+    /// `docker build` using the service's Dockerfile.
+    #[default]
+    Docker,
+    /// `cargo build --release`, optionally scoped to one package.
+    Cargo {
+        #[serde(default)]
+        package: Option<String>,
+        #[serde(default)]
+        features: Vec<String>,
+    },
+    /// `npm run <script>`, optionally scoped to one workspace.
+    Npm {
+        #[serde(default)]
+        workspace: Option<String>,
+        #[serde(default = "default_npm_script")]
+        script: String,
+    },
+    /// An arbitrary command run from the checkout root.
+    CustomCommand {
+        command: String,
+        #[serde(default)]
+        args: Vec<String>,
+    },
+}
+
+fn default_npm_script() -> String {
+    "build".to_string()
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WebConfig {
+    #[serde(default = "default_bind")]
+    pub bind: String,
+    #[serde(default = "default_port")]
+    pub port: u16,
+    /// API authentication; without it the API is open (local use).
+    #[serde(default)]
+    pub auth: Option<AuthConfig>,
+    /// TLS termination; without it the server speaks plain HTTP.
+    #[serde(default)]
+    pub tls: Option<TlsConfig>,
+}
+
+impl Default for WebConfig {
+    fn default() -> Self {
+        Self {
+            bind: default_bind(),
+            port: default_port(),
+            auth: None,
+            tls: None,
+        }
+    }
+}
+
+/// Certificate material for the embedded web server, PEM-encoded.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TlsConfig {
+    pub cert_path: PathBuf,
+    pub key_path: PathBuf,
+    /// CA bundle for client-certificate verification; setting it turns on
+    /// mTLS and rejects clients without a certificate signed by this CA.
+    #[serde(default)]
+    pub client_ca_path: Option<PathBuf>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuthConfig {
+    pub tokens: Vec<TokenConfig>,
+}
+
+/// One API principal; the token itself lives in an environment variable.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TokenConfig {
+    /// Who this token identifies; recorded in the audit log.
+    pub name: String,
+    /// Environment variable holding the token value.
+    pub token_env: String,
+    pub role: crate::auth::Role,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct NotificationConfig {
+    /// Master switch for all channels.
+    #[serde(default)]
+    pub enabled: bool,
+    #[serde(default)]
+    pub slack: Option<crate::notifications::SlackChannelConfig>,
+    #[serde(default)]
+    pub discord: Option<crate::notifications::DiscordChannelConfig>,
+    #[serde(default)]
+    pub email: Option<crate::notifications::EmailChannelConfig>,
+    #[serde(default)]
+    pub telegram: Option<crate::notifications::TelegramChannelConfig>,
+    #[serde(default)]
+    pub pagerduty: Option<crate::notifications::PagerDutyChannelConfig>,
+    /// Per-channel routing rules keyed by channel name ("slack",
+    /// "pagerduty", ...). Channels without an entry receive everything.
+    #[serde(default)]
+    pub routing: std::collections::HashMap<String, crate::notifications::ChannelRouting>,
+    /// Delivery retry behaviour shared by all channels.
+    #[serde(default)]
+    pub retry: crate::notifications::RetryPolicy,
+    /// Suppression and escalation of repeated notifications.
+    #[serde(default)]
+    pub dedup: crate::notifications::DedupConfig,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RollbackConfig {
+    /// Automatic rollback after this many consecutive failed builds.
+    #[serde(default = "default_failure_threshold")]
+    pub failure_threshold: u32,
+    /// Whether automatic rollbacks are permitted at all.
+    #[serde(default = "default_true")]
+    pub auto_rollback: bool,
+    /// Checks evaluated before a rollback starts.
+    #[serde(default)]
+    pub pre_checks: Vec<crate::rollback::CheckSpec<crate::rollback::PreCheck>>,
+    /// Checks evaluated after a rollback completes.
+    #[serde(default)]
+    pub post_checks: Vec<crate::rollback::CheckSpec<crate::rollback::PostCheck>>,
+    /// Gradual-rollout behaviour for canary and blue-green strategies.
+    #[serde(default)]
+    pub canary: CanaryConfig,
+    /// Edge proxy integration used for traffic shifting; without it the
+    /// gradual strategies fall back to an immediate switch.
+    #[serde(default)]
+    pub nginx: Option<NginxConfig>,
+    /// Command run by the smoke-test post-check, with ROLLBACK_SERVICE set
+    /// in its environment.
+    #[serde(default)]
+    pub smoke_test_command: Option<Vec<String>>,
+    /// Approval gating for automatic rollbacks.
+    #[serde(default)]
+    pub approval: ApprovalConfig,
+    /// Emergency freeze once rollbacks start ping-ponging.
+    #[serde(default)]
+    pub freeze: FreezeConfig,
+}
+
+/// Freeze a service once it keeps getting rolled back: further automatic
+/// builds and deploys are blocked until an operator explicitly unfreezes
+/// it, instead of re-deploying the same broken state in a loop.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FreezeConfig {
+    /// Master switch for the automatic freeze.
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+    /// Rollbacks within the window that trigger the freeze.
+    #[serde(default = "default_freeze_rollbacks")]
+    pub max_rollbacks: u32,
+    /// Window the rollbacks are counted over.
+    #[serde(default = "default_freeze_window_hours")]
+    pub window_hours: i64,
+}
+
+impl Default for FreezeConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            max_rollbacks: default_freeze_rollbacks(),
+            window_hours: default_freeze_window_hours(),
+        }
+    }
+}
+
+fn default_freeze_rollbacks() -> u32 {
+    3
+}
+
+fn default_freeze_window_hours() -> i64 {
+    24
+}
+
+/// Gate automatic rollbacks behind a human decision once they get big.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ApprovalConfig {
+    /// Master switch; when off every automatic rollback proceeds unattended.
+    #[serde(default)]
+    pub enabled: bool,
+    /// Rollbacks spanning more than this many commits wait for approval;
+    /// smaller ones still proceed automatically.
+    #[serde(default)]
+    pub blast_radius_commits: u32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CanaryConfig {
+    /// Traffic percentages stepped through before full cutover.
+    #[serde(default = "default_canary_steps")]
+    pub steps: Vec<u8>,
+    /// Seconds to bake at each step before health is re-evaluated.
+    #[serde(default = "default_bake_time")]
+    pub bake_time_secs: u64,
+}
+
+impl Default for CanaryConfig {
+    fn default() -> Self {
+        Self {
+            steps: default_canary_steps(),
+            bake_time_secs: default_bake_time(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NginxConfig {
+    /// Directory of upstream config files included by the proxy.
+    pub upstream_dir: PathBuf,
+    /// Command that reloads the proxy after an upstream rewrite.
+    #[serde(default = "default_reload_command")]
+    pub reload_command: Vec<String>,
+}
+
+fn default_canary_steps() -> Vec<u8> {
+    vec![10, 50, 100]
+}
+
+fn default_bake_time() -> u64 {
+    60
+}
+
+fn default_reload_command() -> Vec<String> {
+    vec!["nginx".to_string(), "-s".to_string(), "reload".to_string()]
+}
+
+impl Default for RollbackConfig {
+    fn default() -> Self {
+        Self {
+            failure_threshold: default_failure_threshold(),
+            auto_rollback: true,
+            pre_checks: Vec::new(),
+            post_checks: Vec::new(),
+            canary: CanaryConfig::default(),
+            nginx: None,
+            smoke_test_command: None,
+            approval: ApprovalConfig::default(),
+            freeze: FreezeConfig::default(),
+        }
+    }
+}
+
+impl MonitorConfig {
+    /// Load configuration from `path`, layered under the environment and
+    /// `--set key.path=value` overrides, with defaults pointed at the
+    /// current directory underneath.
+    pub fn load_with(path: &Path, overrides: &[String]) -> Result<Self> {
+        let mut loader = aurum_config::Loader::new("BUILD_MONITOR")
+            .defaults(&Self::default_for_repo(PathBuf::from(".")))?
+            .file(path);
+        for assignment in overrides {
+            loader = loader.set(assignment)?;
+        }
+        Ok(loader.load()?)
+    }
+
+    pub fn default_for_repo(repo_path: PathBuf) -> Self {
+        Self {
+            repo_path,
+            branch: default_branch(),
+            poll_interval_secs: default_poll_interval(),
+            database_path: default_database_path(),
+            log_dir: default_log_dir(),
+            services: Vec::new(),
+            web: WebConfig::default(),
+            notifications: NotificationConfig::default(),
+            rollback: RollbackConfig::default(),
+            flaky: FlakyConfig::default(),
+            registry: None,
+            retention: RetentionConfig::default(),
+            cache: CacheConfig::default(),
+            watch: WatchConfig::default(),
+            github: None,
+            maintenance: Vec::new(),
+            healing: None,
+            compose: None,
+            watchdog: WatchdogConfig::default(),
+            artifacts: None,
+            election: ElectionConfig::default(),
+        }
+    }
+
+    pub fn service(&self, name: &str) -> Option<&ServiceConfig> {
+        self.services.iter().find(|s| s.name == name)
+    }
+}
+
+fn default_branch() -> String {
+    "main".to_string()
+}
+
+fn default_poll_interval() -> u64 {
+    60
+}
+
+fn default_database_path() -> PathBuf {
+    PathBuf::from("build-monitor.db")
+}
+
+fn default_log_dir() -> PathBuf {
+    PathBuf::from("build-logs")
+}
+
+fn default_health_path() -> String {
+    "/health".to_string()
+}
+
+fn default_variant_required() -> bool {
+    true
+}
+
+fn default_bind() -> String {
+    "0.0.0.0".to_string()
+}
+
+fn default_port() -> u16 {
+    9090
+}
+
+fn default_failure_threshold() -> u32 {
+    3
+}
+
+fn default_true() -> bool {
+    true
+}
@@ -0,0 +1,981 @@
+//! SQLite persistence for build history, rollbacks, and alerts.
+
+use crate::paging::PageRequest;
+use crate::types::{
+    Alert, Artifact, AuditEntry, BuildResult, BuildStatus, Deployment, Freeze, Severity,
+};
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use sqlx::sqlite::{SqliteConnectOptions, SqlitePoolOptions};
+use sqlx::{Row, SqlitePool};
+use std::path::Path;
+use uuid::Uuid;
+
+#[derive(Clone)]
+pub struct Database {
+    pool: SqlitePool,
+}
+
+impl Database {
+    pub async fn open(path: &Path) -> Result<Self> {
+        let options = SqliteConnectOptions::new()
+            .filename(path)
+            .create_if_missing(true);
+        let pool = SqlitePoolOptions::new()
+            .max_connections(8)
+            .connect_with(options)
+            .await
+            .with_context(|| format!("failed to open database at {}", path.display()))?;
+        let db = Self { pool };
+        db.init_schema().await?;
+        Ok(db)
+    }
+
+    /// In-memory database used by tests.
+    #[cfg(test)]
+    pub async fn open_in_memory() -> Result<Self> {
+        let pool = SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect("sqlite::memory:")
+            .await?;
+        let db = Self { pool };
+        db.init_schema().await?;
+        Ok(db)
+    }
+
+    async fn init_schema(&self) -> Result<()> {
+        sqlx::raw_sql(
+            r#"
+            CREATE TABLE IF NOT EXISTS builds (
+                id TEXT PRIMARY KEY,
+                service TEXT NOT NULL,
+                commit_sha TEXT NOT NULL,
+                status TEXT NOT NULL,
+                started_at TEXT NOT NULL,
+                finished_at TEXT,
+                duration_ms INTEGER,
+                error TEXT,
+                attempts INTEGER NOT NULL DEFAULT 1,
+                branch TEXT,
+                variants TEXT
+            );
+            CREATE INDEX IF NOT EXISTS idx_builds_service ON builds(service, started_at DESC);
+
+            CREATE TABLE IF NOT EXISTS rollbacks (
+                id TEXT PRIMARY KEY,
+                service TEXT NOT NULL,
+                from_commit TEXT NOT NULL,
+                to_commit TEXT NOT NULL,
+                strategy TEXT NOT NULL,
+                status TEXT NOT NULL,
+                reason TEXT,
+                progress_percent INTEGER NOT NULL DEFAULT 0,
+                pre_checks TEXT NOT NULL DEFAULT '[]',
+                post_checks TEXT NOT NULL DEFAULT '[]',
+                approved_by TEXT,
+                decided_at TEXT,
+                created_at TEXT NOT NULL,
+                completed_at TEXT
+            );
+
+            CREATE TABLE IF NOT EXISTS bisect_sessions (
+                id TEXT PRIMARY KEY,
+                service TEXT NOT NULL,
+                good_commit TEXT NOT NULL,
+                bad_commit TEXT NOT NULL,
+                status TEXT NOT NULL,
+                first_bad TEXT,
+                created_at TEXT NOT NULL,
+                updated_at TEXT NOT NULL
+            );
+
+            CREATE TABLE IF NOT EXISTS bisect_commits (
+                session_id TEXT NOT NULL,
+                commit_sha TEXT NOT NULL,
+                verdict TEXT NOT NULL,
+                PRIMARY KEY (session_id, commit_sha)
+            );
+
+            CREATE TABLE IF NOT EXISTS notification_deliveries (
+                id TEXT PRIMARY KEY,
+                channel TEXT NOT NULL,
+                kind TEXT NOT NULL,
+                severity TEXT NOT NULL,
+                service TEXT,
+                message TEXT NOT NULL,
+                status TEXT NOT NULL,
+                attempts INTEGER NOT NULL,
+                last_error TEXT,
+                created_at TEXT NOT NULL
+            );
+            CREATE INDEX IF NOT EXISTS idx_deliveries_created
+                ON notification_deliveries(created_at DESC);
+
+            CREATE TABLE IF NOT EXISTS deployments (
+                id TEXT PRIMARY KEY,
+                service TEXT NOT NULL,
+                commit_sha TEXT NOT NULL,
+                image TEXT NOT NULL,
+                digest TEXT,
+                deployed_by TEXT NOT NULL,
+                deployed_at TEXT NOT NULL
+            );
+            CREATE INDEX IF NOT EXISTS idx_deployments_service
+                ON deployments(service, deployed_at DESC);
+
+            CREATE TABLE IF NOT EXISTS alerts (
+                id TEXT PRIMARY KEY,
+                severity TEXT NOT NULL,
+                service TEXT,
+                message TEXT NOT NULL,
+                created_at TEXT NOT NULL
+            );
+
+            CREATE TABLE IF NOT EXISTS artifacts (
+                id TEXT PRIMARY KEY,
+                build_id TEXT NOT NULL,
+                service TEXT NOT NULL,
+                commit_sha TEXT NOT NULL,
+                kind TEXT NOT NULL,
+                reference TEXT NOT NULL,
+                digest TEXT,
+                created_at TEXT NOT NULL
+            );
+            CREATE INDEX IF NOT EXISTS idx_artifacts_service
+                ON artifacts(service, commit_sha, created_at DESC);
+
+            CREATE TABLE IF NOT EXISTS audit_log (
+                id TEXT PRIMARY KEY,
+                actor TEXT NOT NULL,
+                role TEXT NOT NULL,
+                action TEXT NOT NULL,
+                target TEXT,
+                detail TEXT,
+                created_at TEXT NOT NULL
+            );
+            CREATE INDEX IF NOT EXISTS idx_audit_created ON audit_log(created_at DESC);
+
+            CREATE TABLE IF NOT EXISTS build_cache (
+                service TEXT NOT NULL,
+                tree_hash TEXT NOT NULL,
+                commit_sha TEXT NOT NULL,
+                success INTEGER NOT NULL,
+                created_at TEXT NOT NULL,
+                PRIMARY KEY (service, tree_hash)
+            );
+
+            CREATE TABLE IF NOT EXISTS freezes (
+                service TEXT PRIMARY KEY,
+                reason TEXT NOT NULL,
+                created_at TEXT NOT NULL
+            );
+
+            CREATE TABLE IF NOT EXISTS leases (
+                name TEXT PRIMARY KEY,
+                holder TEXT NOT NULL,
+                expires_at TEXT NOT NULL
+            );
+            "#,
+        )
+        .execute(&self.pool)
+        .await?;
+        // Databases created before build matrices existed lack the
+        // variants column; the error on an already-migrated database is
+        // the only signal ALTER TABLE gives, so it is ignored.
+        let _ = sqlx::raw_sql("ALTER TABLE builds ADD COLUMN variants TEXT")
+            .execute(&self.pool)
+            .await;
+        Ok(())
+    }
+
+    pub fn pool(&self) -> &SqlitePool {
+        &self.pool
+    }
+
+    /// Claim or renew the named lease for `holder`; the single upsert
+    /// means two racing replicas cannot both win. Succeeds when the lease
+    /// is free, expired, or already ours.
+    pub async fn try_acquire_lease(&self, name: &str, holder: &str, ttl_secs: u64) -> Result<bool> {
+        let now = Utc::now();
+        let expires_at = (now + chrono::Duration::seconds(ttl_secs as i64)).to_rfc3339();
+        let result = sqlx::query(
+            r#"
+            INSERT INTO leases (name, holder, expires_at) VALUES (?1, ?2, ?3)
+            ON CONFLICT(name) DO UPDATE SET holder = excluded.holder, expires_at = excluded.expires_at
+            WHERE leases.holder = excluded.holder OR leases.expires_at < ?4
+            "#,
+        )
+        .bind(name)
+        .bind(holder)
+        .bind(expires_at)
+        .bind(now.to_rfc3339())
+        .execute(&self.pool)
+        .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Drop our own lease row so a standby takes over immediately instead
+    /// of waiting out the TTL.
+    pub async fn release_lease(&self, name: &str, holder: &str) -> Result<()> {
+        sqlx::query("DELETE FROM leases WHERE name = ?1 AND holder = ?2")
+            .bind(name)
+            .bind(holder)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    pub async fn record_build(&self, build: &BuildResult) -> Result<()> {
+        sqlx::query(
+            r#"
+            INSERT INTO builds (id, service, commit_sha, status, started_at, finished_at, duration_ms, error, attempts, branch, variants)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)
+            ON CONFLICT(id) DO UPDATE SET
+                status = excluded.status,
+                finished_at = excluded.finished_at,
+                duration_ms = excluded.duration_ms,
+                error = excluded.error,
+                attempts = excluded.attempts,
+                variants = excluded.variants
+            "#,
+        )
+        .bind(build.id.to_string())
+        .bind(&build.service)
+        .bind(&build.commit)
+        .bind(build.status.as_str())
+        .bind(build.started_at.to_rfc3339())
+        .bind(build.finished_at.map(|t| t.to_rfc3339()))
+        .bind(build.duration_ms)
+        .bind(&build.error)
+        .bind(build.attempts as i64)
+        .bind(&build.branch)
+        .bind(if build.variants.is_empty() {
+            None
+        } else {
+            Some(serde_json::to_string(&build.variants)?)
+        })
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    pub async fn recent_builds(&self, service: Option<&str>, limit: i64) -> Result<Vec<BuildResult>> {
+        let rows = match service {
+            Some(name) => {
+                sqlx::query(
+                    "SELECT * FROM builds WHERE service = ?1 ORDER BY started_at DESC LIMIT ?2",
+                )
+                .bind(name)
+                .bind(limit)
+                .fetch_all(&self.pool)
+                .await?
+            }
+            None => {
+                sqlx::query("SELECT * FROM builds ORDER BY started_at DESC LIMIT ?1")
+                    .bind(limit)
+                    .fetch_all(&self.pool)
+                    .await?
+            }
+        };
+        rows.iter().map(row_to_build).collect()
+    }
+
+    /// One page of build history, filtered and keyset-paginated per the
+    /// request. Fetches one row beyond the page size so the caller can
+    /// tell whether a next page exists.
+    pub async fn builds_page(&self, request: &PageRequest) -> Result<Vec<BuildResult>> {
+        let sql = if request.descending {
+            r#"
+            SELECT * FROM builds
+            WHERE (?1 IS NULL OR service = ?1)
+              AND (?2 IS NULL OR status = ?2)
+              AND (?3 IS NULL OR commit_sha = ?3)
+              AND (?4 IS NULL OR started_at >= ?4)
+              AND (?5 IS NULL OR started_at <= ?5)
+              AND (?6 IS NULL OR started_at < ?6 OR (started_at = ?6 AND id < ?7))
+            ORDER BY started_at DESC, id DESC LIMIT ?8
+            "#
+        } else {
+            r#"
+            SELECT * FROM builds
+            WHERE (?1 IS NULL OR service = ?1)
+              AND (?2 IS NULL OR status = ?2)
+              AND (?3 IS NULL OR commit_sha = ?3)
+              AND (?4 IS NULL OR started_at >= ?4)
+              AND (?5 IS NULL OR started_at <= ?5)
+              AND (?6 IS NULL OR started_at > ?6 OR (started_at = ?6 AND id > ?7))
+            ORDER BY started_at ASC, id ASC LIMIT ?8
+            "#
+        };
+        let after = request.after.as_ref();
+        let rows = sqlx::query(sql)
+            .bind(&request.service)
+            .bind(&request.status)
+            .bind(&request.commit)
+            .bind(&request.since)
+            .bind(&request.until)
+            .bind(after.map(|(ts, _)| ts))
+            .bind(after.map(|(_, id)| id))
+            .bind(request.fetch_limit())
+            .fetch_all(&self.pool)
+            .await?;
+        rows.iter().map(row_to_build).collect()
+    }
+
+    /// Production-branch builds for a service within the last `days`,
+    /// oldest first, for trend analysis.
+    pub async fn builds_in_window(&self, service: &str, days: i64) -> Result<Vec<BuildResult>> {
+        let cutoff = (Utc::now() - chrono::Duration::days(days)).to_rfc3339();
+        let rows = sqlx::query(
+            "SELECT * FROM builds WHERE service = ?1 AND branch IS NULL AND started_at >= ?2 ORDER BY started_at ASC",
+        )
+        .bind(service)
+        .bind(cutoff)
+        .fetch_all(&self.pool)
+        .await?;
+        rows.iter().map(row_to_build).collect()
+    }
+
+    pub async fn build_by_id(&self, id: Uuid) -> Result<Option<BuildResult>> {
+        let row = sqlx::query("SELECT * FROM builds WHERE id = ?1")
+            .bind(id.to_string())
+            .fetch_optional(&self.pool)
+            .await?;
+        row.as_ref().map(row_to_build).transpose()
+    }
+
+    /// Number of consecutive failed builds for a service, counting back from
+    /// the most recent build.
+    pub async fn consecutive_failures(&self, service: &str) -> Result<u32> {
+        let rows = sqlx::query(
+            "SELECT status FROM builds WHERE service = ?1 AND branch IS NULL AND status IN ('success','failed','timed_out') ORDER BY started_at DESC LIMIT 50",
+        )
+        .bind(service)
+        .fetch_all(&self.pool)
+        .await?;
+        let mut count = 0;
+        for row in rows {
+            let status: String = row.get("status");
+            if status != "success" {
+                count += 1;
+            } else {
+                break;
+            }
+        }
+        Ok(count)
+    }
+
+    pub async fn last_successful_commit(&self, service: &str) -> Result<Option<String>> {
+        let row = sqlx::query(
+            "SELECT commit_sha FROM builds WHERE service = ?1 AND branch IS NULL AND status = 'success' ORDER BY started_at DESC LIMIT 1",
+        )
+        .bind(service)
+        .fetch_optional(&self.pool)
+        .await?;
+        Ok(row.map(|r| r.get("commit_sha")))
+    }
+
+    /// Share of recent builds that initially failed but passed on retry,
+    /// over the builds that initially failed at all. 0.0 when there were no
+    /// initial failures in the window.
+    pub async fn flakiness_score(&self, service: &str, window: i64) -> Result<f64> {
+        let rows = sqlx::query(
+            "SELECT status, attempts FROM builds WHERE service = ?1 AND branch IS NULL AND status IN ('success','failed','timed_out') ORDER BY started_at DESC LIMIT ?2",
+        )
+        .bind(service)
+        .bind(window)
+        .fetch_all(&self.pool)
+        .await?;
+        let mut initial_failures = 0u32;
+        let mut passed_on_retry = 0u32;
+        for row in rows {
+            let status: String = row.get("status");
+            let attempts: i64 = row.get("attempts");
+            if status != "success" {
+                initial_failures += 1;
+            } else if attempts > 1 {
+                initial_failures += 1;
+                passed_on_retry += 1;
+            }
+        }
+        if initial_failures == 0 {
+            return Ok(0.0);
+        }
+        Ok(f64::from(passed_on_retry) / f64::from(initial_failures))
+    }
+
+    /// Cached outcome of building `service` against content `tree_hash`,
+    /// as `(commit it was built at, success)`. Commits with identical
+    /// service trees (merges, reverts) share one entry.
+    pub async fn cached_build_result(
+        &self,
+        service: &str,
+        tree_hash: &str,
+    ) -> Result<Option<(String, bool)>> {
+        let row = sqlx::query(
+            "SELECT commit_sha, success FROM build_cache WHERE service = ?1 AND tree_hash = ?2",
+        )
+        .bind(service)
+        .bind(tree_hash)
+        .fetch_optional(&self.pool)
+        .await?;
+        Ok(row.map(|r| (r.get("commit_sha"), r.get::<i64, _>("success") != 0)))
+    }
+
+    pub async fn record_build_cache(
+        &self,
+        service: &str,
+        tree_hash: &str,
+        commit: &str,
+        success: bool,
+    ) -> Result<()> {
+        sqlx::query(
+            r#"
+            INSERT INTO build_cache (service, tree_hash, commit_sha, success, created_at)
+            VALUES (?1, ?2, ?3, ?4, ?5)
+            ON CONFLICT(service, tree_hash) DO UPDATE SET
+                commit_sha = excluded.commit_sha,
+                success = excluded.success,
+                created_at = excluded.created_at
+            "#,
+        )
+        .bind(service)
+        .bind(tree_hash)
+        .bind(commit)
+        .bind(success as i64)
+        .bind(Utc::now().to_rfc3339())
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    pub async fn record_deployment(&self, d: &Deployment) -> Result<()> {
+        sqlx::query(
+            r#"
+            INSERT INTO deployments (id, service, commit_sha, image, digest, deployed_by, deployed_at)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)
+            "#,
+        )
+        .bind(d.id.to_string())
+        .bind(&d.service)
+        .bind(&d.commit)
+        .bind(&d.image)
+        .bind(&d.digest)
+        .bind(&d.deployed_by)
+        .bind(d.deployed_at.to_rfc3339())
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    /// The most recent deployment of each service.
+    pub async fn current_deployments(&self) -> Result<Vec<Deployment>> {
+        let rows = sqlx::query(
+            r#"
+            SELECT d.* FROM deployments d
+            JOIN (SELECT service, MAX(deployed_at) AS latest FROM deployments GROUP BY service) x
+              ON d.service = x.service AND d.deployed_at = x.latest
+            ORDER BY d.service
+            "#,
+        )
+        .fetch_all(&self.pool)
+        .await?;
+        rows.iter().map(row_to_deployment).collect()
+    }
+
+    pub async fn deployment_history(&self, service: &str, limit: i64) -> Result<Vec<Deployment>> {
+        let rows = sqlx::query(
+            "SELECT * FROM deployments WHERE service = ?1 ORDER BY deployed_at DESC LIMIT ?2",
+        )
+        .bind(service)
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await?;
+        rows.iter().map(row_to_deployment).collect()
+    }
+
+    /// Distinct commits that were actually deployed for a service.
+    pub async fn deployed_commits(&self, service: &str) -> Result<Vec<String>> {
+        let rows = sqlx::query("SELECT DISTINCT commit_sha FROM deployments WHERE service = ?1")
+            .bind(service)
+            .fetch_all(&self.pool)
+            .await?;
+        Ok(rows.iter().map(|r| r.get("commit_sha")).collect())
+    }
+
+    /// Rollbacks attempted for a service since `cutoff`. Pending and
+    /// rejected ones never touched the deployment and don't count.
+    pub async fn rollbacks_since(&self, service: &str, cutoff: DateTime<Utc>) -> Result<i64> {
+        let row = sqlx::query(
+            "SELECT COUNT(*) AS n FROM rollbacks
+             WHERE service = ?1 AND created_at >= ?2
+               AND status IN ('in_progress', 'completed', 'failed')",
+        )
+        .bind(service)
+        .bind(cutoff.to_rfc3339())
+        .fetch_one(&self.pool)
+        .await?;
+        Ok(row.get("n"))
+    }
+
+    /// Freeze a service. Idempotent: a second freeze keeps the first
+    /// one's reason and timestamp.
+    pub async fn freeze_service(&self, service: &str, reason: &str) -> Result<()> {
+        sqlx::query(
+            "INSERT INTO freezes (service, reason, created_at) VALUES (?1, ?2, ?3)
+             ON CONFLICT(service) DO NOTHING",
+        )
+        .bind(service)
+        .bind(reason)
+        .bind(Utc::now().to_rfc3339())
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    /// Lift a freeze; returns whether one was active.
+    pub async fn unfreeze_service(&self, service: &str) -> Result<bool> {
+        let done = sqlx::query("DELETE FROM freezes WHERE service = ?1")
+            .bind(service)
+            .execute(&self.pool)
+            .await?;
+        Ok(done.rows_affected() > 0)
+    }
+
+    pub async fn frozen_reason(&self, service: &str) -> Result<Option<String>> {
+        let row = sqlx::query("SELECT reason FROM freezes WHERE service = ?1")
+            .bind(service)
+            .fetch_optional(&self.pool)
+            .await?;
+        Ok(row.map(|r| r.get("reason")))
+    }
+
+    pub async fn frozen_services(&self) -> Result<Vec<Freeze>> {
+        let rows = sqlx::query("SELECT * FROM freezes ORDER BY created_at DESC")
+            .fetch_all(&self.pool)
+            .await?;
+        rows.iter()
+            .map(|r| {
+                let created_at: String = r.get("created_at");
+                Ok(Freeze {
+                    service: r.get("service"),
+                    reason: r.get("reason"),
+                    created_at: DateTime::parse_from_rfc3339(&created_at)?.with_timezone(&Utc),
+                })
+            })
+            .collect()
+    }
+
+    pub async fn record_alert(&self, severity: Severity, service: Option<&str>, message: &str) -> Result<()> {
+        sqlx::query(
+            "INSERT INTO alerts (id, severity, service, message, created_at) VALUES (?1, ?2, ?3, ?4, ?5)",
+        )
+        .bind(Uuid::new_v4().to_string())
+        .bind(severity.as_str())
+        .bind(service)
+        .bind(message)
+        .bind(Utc::now().to_rfc3339())
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    /// One page of recorded alerts, filtered and keyset-paginated per
+    /// the request; the `severity` and `service` filters apply here.
+    pub async fn alerts_page(&self, request: &PageRequest) -> Result<Vec<Alert>> {
+        let sql = if request.descending {
+            r#"
+            SELECT * FROM alerts
+            WHERE (?1 IS NULL OR severity = ?1)
+              AND (?2 IS NULL OR service = ?2)
+              AND (?3 IS NULL OR created_at >= ?3)
+              AND (?4 IS NULL OR created_at <= ?4)
+              AND (?5 IS NULL OR created_at < ?5 OR (created_at = ?5 AND id < ?6))
+            ORDER BY created_at DESC, id DESC LIMIT ?7
+            "#
+        } else {
+            r#"
+            SELECT * FROM alerts
+            WHERE (?1 IS NULL OR severity = ?1)
+              AND (?2 IS NULL OR service = ?2)
+              AND (?3 IS NULL OR created_at >= ?3)
+              AND (?4 IS NULL OR created_at <= ?4)
+              AND (?5 IS NULL OR created_at > ?5 OR (created_at = ?5 AND id > ?6))
+            ORDER BY created_at ASC, id ASC LIMIT ?7
+            "#
+        };
+        let after = request.after.as_ref();
+        let rows = sqlx::query(sql)
+            .bind(&request.severity)
+            .bind(&request.service)
+            .bind(&request.since)
+            .bind(&request.until)
+            .bind(after.map(|(ts, _)| ts))
+            .bind(after.map(|(_, id)| id))
+            .bind(request.fetch_limit())
+            .fetch_all(&self.pool)
+            .await?;
+        rows.iter().map(row_to_alert).collect()
+    }
+
+    pub async fn record_artifact(&self, artifact: &Artifact) -> Result<()> {
+        sqlx::query(
+            "INSERT INTO artifacts (id, build_id, service, commit_sha, kind, reference, digest, created_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+        )
+        .bind(artifact.id.to_string())
+        .bind(artifact.build_id.to_string())
+        .bind(&artifact.service)
+        .bind(&artifact.commit)
+        .bind(&artifact.kind)
+        .bind(&artifact.reference)
+        .bind(&artifact.digest)
+        .bind(artifact.created_at.to_rfc3339())
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    /// The most recent artifact recorded for a service at a commit.
+    pub async fn artifact_for(&self, service: &str, commit: &str) -> Result<Option<Artifact>> {
+        let row = sqlx::query(
+            "SELECT * FROM artifacts WHERE service = ?1 AND commit_sha = ?2 ORDER BY created_at DESC LIMIT 1",
+        )
+        .bind(service)
+        .bind(commit)
+        .fetch_optional(&self.pool)
+        .await?;
+        row.as_ref().map(row_to_artifact).transpose()
+    }
+
+    pub async fn artifacts_for_service(&self, service: &str, limit: i64) -> Result<Vec<Artifact>> {
+        let rows = sqlx::query(
+            "SELECT * FROM artifacts WHERE service = ?1 ORDER BY created_at DESC LIMIT ?2",
+        )
+        .bind(service)
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await?;
+        rows.iter().map(row_to_artifact).collect()
+    }
+
+    pub async fn record_audit(
+        &self,
+        actor: &str,
+        role: &str,
+        action: &str,
+        target: Option<&str>,
+        detail: Option<&str>,
+    ) -> Result<()> {
+        sqlx::query(
+            "INSERT INTO audit_log (id, actor, role, action, target, detail, created_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+        )
+        .bind(Uuid::new_v4().to_string())
+        .bind(actor)
+        .bind(role)
+        .bind(action)
+        .bind(target)
+        .bind(detail)
+        .bind(Utc::now().to_rfc3339())
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    pub async fn recent_audit(&self, limit: i64) -> Result<Vec<AuditEntry>> {
+        let rows = sqlx::query("SELECT * FROM audit_log ORDER BY created_at DESC LIMIT ?1")
+            .bind(limit)
+            .fetch_all(&self.pool)
+            .await?;
+        rows.iter()
+            .map(|row| {
+                let id: String = row.get("id");
+                let created_at: String = row.get("created_at");
+                Ok(AuditEntry {
+                    id: Uuid::parse_str(&id)?,
+                    actor: row.get("actor"),
+                    role: row.get("role"),
+                    action: row.get("action"),
+                    target: row.get("target"),
+                    detail: row.get("detail"),
+                    created_at: DateTime::parse_from_rfc3339(&created_at)?.with_timezone(&Utc),
+                })
+            })
+            .collect()
+    }
+}
+
+fn row_to_alert(row: &sqlx::sqlite::SqliteRow) -> Result<Alert> {
+    let id: String = row.get("id");
+    let severity: String = row.get("severity");
+    let created_at: String = row.get("created_at");
+    Ok(Alert {
+        id: Uuid::parse_str(&id)?,
+        severity: Severity::parse(&severity),
+        service: row.get("service"),
+        message: row.get("message"),
+        created_at: parse_ts(&created_at)?,
+    })
+}
+
+fn row_to_artifact(row: &sqlx::sqlite::SqliteRow) -> Result<Artifact> {
+    let id: String = row.get("id");
+    let build_id: String = row.get("build_id");
+    let created_at: String = row.get("created_at");
+    Ok(Artifact {
+        id: Uuid::parse_str(&id)?,
+        build_id: Uuid::parse_str(&build_id)?,
+        service: row.get("service"),
+        commit: row.get("commit_sha"),
+        kind: row.get("kind"),
+        reference: row.get("reference"),
+        digest: row.get("digest"),
+        created_at: DateTime::parse_from_rfc3339(&created_at)?.with_timezone(&Utc),
+    })
+}
+
+fn row_to_build(row: &sqlx::sqlite::SqliteRow) -> Result<BuildResult> {
+    let id: String = row.get("id");
+    let started_at: String = row.get("started_at");
+    let finished_at: Option<String> = row.get("finished_at");
+    Ok(BuildResult {
+        id: Uuid::parse_str(&id)?,
+        service: row.get("service"),
+        commit: row.get("commit_sha"),
+        status: BuildStatus::parse(row.get("status")),
+        started_at: parse_ts(&started_at)?,
+        finished_at: finished_at.as_deref().map(parse_ts).transpose()?,
+        duration_ms: row.get("duration_ms"),
+        error: row.get("error"),
+        attempts: row.get::<i64, _>("attempts") as u32,
+        branch: row.get("branch"),
+        variants: row
+            .get::<Option<String>, _>("variants")
+            .as_deref()
+            .map(serde_json::from_str)
+            .transpose()?
+            .unwrap_or_default(),
+    })
+}
+
+fn row_to_deployment(row: &sqlx::sqlite::SqliteRow) -> Result<Deployment> {
+    let id: String = row.get("id");
+    let deployed_at: String = row.get("deployed_at");
+    Ok(Deployment {
+        id: Uuid::parse_str(&id)?,
+        service: row.get("service"),
+        commit: row.get("commit_sha"),
+        image: row.get("image"),
+        digest: row.get("digest"),
+        deployed_by: row.get("deployed_by"),
+        deployed_at: parse_ts(&deployed_at)?,
+    })
+}
+
+fn parse_ts(s: &str) -> Result<DateTime<Utc>> {
+    Ok(DateTime::parse_from_rfc3339(s)?.with_timezone(&Utc))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn flakiness_score_counts_retried_passes() {
+        let db = Database::open_in_memory().await.unwrap();
+        // Two initial failures: one recovered on retry, one failed outright.
+        let mut recovered = BuildResult::started("web", "a");
+        recovered.attempts = 2;
+        recovered.finish(BuildStatus::Success, None);
+        db.record_build(&recovered).await.unwrap();
+        let mut failed = BuildResult::started("web", "b");
+        failed.finish(BuildStatus::Failed, None);
+        db.record_build(&failed).await.unwrap();
+        let mut clean = BuildResult::started("web", "c");
+        clean.finish(BuildStatus::Success, None);
+        db.record_build(&clean).await.unwrap();
+
+        let score = db.flakiness_score("web", 50).await.unwrap();
+        assert!((score - 0.5).abs() < f64::EPSILON);
+        assert_eq!(db.flakiness_score("other", 50).await.unwrap(), 0.0);
+    }
+
+    #[tokio::test]
+    async fn variant_statuses_round_trip() {
+        let db = Database::open_in_memory().await.unwrap();
+        let mut build = BuildResult::started("web", "a");
+        build.variants.push(crate::types::VariantStatus {
+            name: "arm".into(),
+            status: BuildStatus::Failed,
+            duration_ms: 1200,
+            required: true,
+        });
+        build.finish(BuildStatus::Failed, Some("required variant failed".into()));
+        db.record_build(&build).await.unwrap();
+
+        let stored = &db.recent_builds(Some("web"), 1).await.unwrap()[0];
+        assert_eq!(stored.variants.len(), 1);
+        assert_eq!(stored.variants[0].name, "arm");
+        assert_eq!(stored.variants[0].status, BuildStatus::Failed);
+        assert!(stored.variants[0].required);
+    }
+
+    #[tokio::test]
+    async fn current_deployments_returns_latest_per_service() {
+        let db = Database::open_in_memory().await.unwrap();
+        let mut first = Deployment::new("web", "aaa", "web:monitor", None, "build-monitor");
+        first.deployed_at -= chrono::Duration::minutes(5);
+        db.record_deployment(&first).await.unwrap();
+        db.record_deployment(&Deployment::new("web", "bbb", "web:monitor", None, "alice"))
+            .await
+            .unwrap();
+
+        let current = db.current_deployments().await.unwrap();
+        assert_eq!(current.len(), 1);
+        assert_eq!(current[0].commit, "bbb");
+        assert_eq!(current[0].deployed_by, "alice");
+        assert_eq!(db.deployed_commits("web").await.unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn build_cache_keeps_latest_verdict_per_tree() {
+        let db = Database::open_in_memory().await.unwrap();
+        assert!(db.cached_build_result("web", "h1").await.unwrap().is_none());
+        db.record_build_cache("web", "h1", "aaa", true).await.unwrap();
+        assert_eq!(
+            db.cached_build_result("web", "h1").await.unwrap(),
+            Some(("aaa".to_string(), true))
+        );
+        // A rebuild of the same tree overwrites the entry.
+        db.record_build_cache("web", "h1", "bbb", false).await.unwrap();
+        assert_eq!(
+            db.cached_build_result("web", "h1").await.unwrap(),
+            Some(("bbb".to_string(), false))
+        );
+        assert!(db.cached_build_result("api", "h1").await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn freezes_persist_until_explicitly_lifted() {
+        let db = Database::open_in_memory().await.unwrap();
+        assert!(db.frozen_reason("api").await.unwrap().is_none());
+        db.freeze_service("api", "3 rollbacks in the last 24 hours")
+            .await
+            .unwrap();
+        // A repeat freeze keeps the original reason.
+        db.freeze_service("api", "later reason").await.unwrap();
+        assert_eq!(
+            db.frozen_reason("api").await.unwrap().as_deref(),
+            Some("3 rollbacks in the last 24 hours")
+        );
+        assert_eq!(db.frozen_services().await.unwrap().len(), 1);
+        assert!(db.unfreeze_service("api").await.unwrap());
+        assert!(!db.unfreeze_service("api").await.unwrap());
+        assert!(db.frozen_reason("api").await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn builds_page_filters_and_walks_by_cursor() {
+        use crate::paging::{cursor, paginate, PageQuery};
+        let db = Database::open_in_memory().await.unwrap();
+        for (age, commit, status) in [
+            (3, "aaa", BuildStatus::Success),
+            (2, "bbb", BuildStatus::Failed),
+            (1, "ccc", BuildStatus::Success),
+        ] {
+            let mut build = BuildResult::started("web", commit);
+            build.started_at -= chrono::Duration::minutes(age);
+            build.finish(status, None);
+            db.record_build(&build).await.unwrap();
+        }
+
+        // Walk the full history one entry at a time, newest first.
+        let request = PageQuery {
+            limit: 1,
+            ..Default::default()
+        }
+        .request()
+        .unwrap();
+        let first = paginate(db.builds_page(&request).await.unwrap(), &request, |b| {
+            cursor(b.started_at, b.id)
+        });
+        assert_eq!(first.items[0].commit, "ccc");
+        let request = PageQuery {
+            limit: 1,
+            cursor: first.next_cursor.clone(),
+            ..Default::default()
+        }
+        .request()
+        .unwrap();
+        let second = paginate(db.builds_page(&request).await.unwrap(), &request, |b| {
+            cursor(b.started_at, b.id)
+        });
+        assert_eq!(second.items[0].commit, "bbb");
+        assert!(second.next_cursor.is_some());
+
+        // Filters narrow the page; the last page carries no cursor.
+        let request = PageQuery {
+            status: Some("success".to_string()),
+            ..Default::default()
+        }
+        .request()
+        .unwrap();
+        let successes = paginate(db.builds_page(&request).await.unwrap(), &request, |b| {
+            cursor(b.started_at, b.id)
+        });
+        assert_eq!(successes.count, 2);
+        assert!(successes.next_cursor.is_none());
+
+        // Oldest first when asked.
+        let request = PageQuery {
+            order: Some("asc".to_string()),
+            ..Default::default()
+        }
+        .request()
+        .unwrap();
+        let oldest = db.builds_page(&request).await.unwrap();
+        assert_eq!(oldest[0].commit, "aaa");
+    }
+
+    #[tokio::test]
+    async fn alerts_page_filters_by_severity_and_service() {
+        let db = Database::open_in_memory().await.unwrap();
+        db.record_alert(Severity::Critical, Some("web"), "build failed twice")
+            .await
+            .unwrap();
+        db.record_alert(Severity::Info, None, "monitor restarted")
+            .await
+            .unwrap();
+
+        let request = crate::paging::PageQuery::default().request().unwrap();
+        assert_eq!(db.alerts_page(&request).await.unwrap().len(), 2);
+
+        let request = crate::paging::PageQuery {
+            severity: Some("critical".to_string()),
+            service: Some("web".to_string()),
+            ..Default::default()
+        }
+        .request()
+        .unwrap();
+        let alerts = db.alerts_page(&request).await.unwrap();
+        assert_eq!(alerts.len(), 1);
+        assert_eq!(alerts[0].severity, Severity::Critical);
+        assert_eq!(alerts[0].message, "build failed twice");
+    }
+
+    #[tokio::test]
+    async fn build_round_trip() {
+        let db = Database::open_in_memory().await.unwrap();
+        let mut build = BuildResult::started("ml-api", "abc123");
+        db.record_build(&build).await.unwrap();
+        build.finish(BuildStatus::Failed, Some("boom".into()));
+        db.record_build(&build).await.unwrap();
+
+        let recent = db.recent_builds(Some("ml-api"), 10).await.unwrap();
+        assert_eq!(recent.len(), 1);
+        assert_eq!(recent[0].status, BuildStatus::Failed);
+        assert_eq!(recent[0].error.as_deref(), Some("boom"));
+        assert_eq!(db.consecutive_failures("ml-api").await.unwrap(), 1);
+    }
+}
@@ -0,0 +1,586 @@
+//! Docker integration: image builds, container health checks, and cleanup.
+//!
+//! Builds stream a tar of the build context to `docker build -` so the
+//! monitor does not depend on the daemon seeing the same filesystem.
+
+use crate::compose::ComposeDriver;
+use crate::config::{CacheConfig, RegistryConfig, RetentionConfig, ServiceConfig};
+use anyhow::{bail, Context, Result};
+use std::path::Path;
+use std::process::{Command, Stdio};
+use tracing::{debug, info};
+
+/// Result of one image build: success flag plus the captured output.
+pub struct BuildOutcome {
+    pub success: bool,
+    /// The build was killed for exceeding its time limit.
+    pub timed_out: bool,
+    pub log: String,
+}
+
+#[derive(Clone, Default)]
+pub struct DockerManager {
+    registry: Option<RegistryConfig>,
+    cache: CacheConfig,
+    compose: Option<ComposeDriver>,
+}
+
+impl DockerManager {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_registry(registry: Option<RegistryConfig>) -> Self {
+        Self {
+            registry,
+            ..Self::default()
+        }
+    }
+
+    pub fn with_cache(mut self, cache: CacheConfig) -> Self {
+        self.cache = cache;
+        self
+    }
+
+    pub fn with_compose(mut self, compose: Option<ComposeDriver>) -> Self {
+        self.compose = compose;
+        self
+    }
+
+    /// Image reference for a service built at a specific commit, qualified
+    /// with the registry when one is configured.
+    pub fn commit_tag(&self, service: &str, commit: &str) -> String {
+        let short = &commit[..commit.len().min(12)];
+        match &self.registry {
+            Some(r) => format!("{}/{service}:{short}", r.url.trim_end_matches('/')),
+            None => format!("{service}:{short}"),
+        }
+    }
+
+    /// Tag a successful build with its commit SHA and push it to the
+    /// registry when configured to do so.
+    pub fn publish_build(&self, service: &str, commit: &str) -> Result<()> {
+        let source = format!("{service}:monitor");
+        let target = self.commit_tag(service, commit);
+        let status = Command::new("docker")
+            .args(["tag", &source, &target])
+            .status()
+            .context("failed to invoke docker tag")?;
+        if !status.success() {
+            bail!("docker tag {source} -> {target} exited with {status}");
+        }
+        if let Some(registry) = &self.registry {
+            if registry.push {
+                self.login(registry)?;
+                let status = Command::new("docker")
+                    .args(["push", &target])
+                    .status()
+                    .context("failed to invoke docker push")?;
+                if !status.success() {
+                    bail!("docker push {target} exited with {status}");
+                }
+                info!(image = %target, "pushed image to registry");
+            }
+        }
+        Ok(())
+    }
+
+    /// Resolve the image to roll back to: a locally cached commit tag, then
+    /// a registry pull, then the plain `:monitor` tag as a last resort.
+    pub fn rollback_image(&self, service: &str, commit: &str) -> String {
+        let tagged = self.commit_tag(service, commit);
+        if self.image_exists(&tagged).unwrap_or(false) {
+            return tagged;
+        }
+        if self.registry.is_some() && self.pull_image(&tagged).is_ok() {
+            return tagged;
+        }
+        format!("{service}:monitor")
+    }
+
+    fn pull_image(&self, image: &str) -> Result<()> {
+        if let Some(registry) = &self.registry {
+            self.login(registry)?;
+        }
+        let status = Command::new("docker")
+            .args(["pull", image])
+            .status()
+            .context("failed to invoke docker pull")?;
+        if !status.success() {
+            bail!("docker pull {image} exited with {status}");
+        }
+        Ok(())
+    }
+
+    fn login(&self, registry: &RegistryConfig) -> Result<()> {
+        let (Some(username), Some(password_env)) = (&registry.username, &registry.password_env)
+        else {
+            // Anonymous or pre-authenticated daemon.
+            return Ok(());
+        };
+        let password = std::env::var(password_env)
+            .with_context(|| format!("registry password env var {password_env} not set"))?;
+        let mut child = Command::new("docker")
+            .args(["login", &registry.url, "-u", username, "--password-stdin"])
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()
+            .context("failed to invoke docker login")?;
+        use std::io::Write;
+        child
+            .stdin
+            .take()
+            .expect("stdin was piped")
+            .write_all(password.as_bytes())?;
+        let status = child.wait()?;
+        if !status.success() {
+            bail!("docker login to {} failed", registry.url);
+        }
+        Ok(())
+    }
+
+    /// Remove commit-tagged images for a service that exceed the retention
+    /// policy by age or count. The floating `:monitor` tag always survives.
+    pub fn enforce_retention(&self, service: &str, retention: &RetentionConfig) -> Result<()> {
+        let output = Command::new("docker")
+            .args([
+                "image",
+                "ls",
+                "--filter",
+                &format!("reference=*{service}*"),
+                "--format",
+                "{{.Repository}}:{{.Tag}}\t{{.CreatedAt}}",
+            ])
+            .output()
+            .context("failed to invoke docker image ls")?;
+        if !output.status.success() {
+            bail!("docker image ls exited with {}", output.status);
+        }
+        let cutoff = chrono::Utc::now() - chrono::Duration::days(retention.max_age_days);
+        let mut kept = 0usize;
+        // `docker image ls` lists newest first, so everything past the count
+        // limit (or older than the cutoff) goes.
+        for line in String::from_utf8_lossy(&output.stdout).lines() {
+            let Some((reference, created)) = line.split_once('\t') else {
+                continue;
+            };
+            if reference.ends_with(":monitor") {
+                continue;
+            }
+            let too_old = created
+                .trim_end_matches(" UTC")
+                .parse::<chrono::DateTime<chrono::FixedOffset>>()
+                .ok()
+                .or_else(|| {
+                    chrono::DateTime::parse_from_str(
+                        created.trim_end_matches(" UTC"),
+                        "%Y-%m-%d %H:%M:%S %z",
+                    )
+                    .ok()
+                })
+                .is_some_and(|t| t.with_timezone(&chrono::Utc) < cutoff);
+            kept += 1;
+            if too_old || kept > retention.max_images_per_service {
+                debug!(image = reference, "removing image past retention policy");
+                let _ = Command::new("docker").args(["rmi", reference]).output();
+            }
+        }
+        Ok(())
+    }
+
+    /// Build the image for `service` from the checkout at `context_dir`,
+    /// tagging it `<service>:monitor`. A failed build is reported in the
+    /// returned outcome, not as an error. Matrix builds pass a platform
+    /// and get their own `<service>:monitor-<platform>` tag instead, so
+    /// they never clobber the deployable image.
+    pub fn build_image_for(
+        &self,
+        service: &ServiceConfig,
+        context_dir: &Path,
+        platform: Option<&str>,
+    ) -> Result<BuildOutcome> {
+        let dockerfile = service
+            .dockerfile
+            .as_ref()
+            .with_context(|| format!("service {} has no dockerfile configured", service.name))?;
+        self.preflight_disk_check(service.limits.min_free_disk_mb)?;
+        let tag = match platform {
+            Some(platform) => format!("{}:monitor-{}", service.name, platform.replace('/', "-")),
+            None => format!("{}:monitor", service.name),
+        };
+        info!(service = %service.name, "building image {tag}");
+
+        let mut command = Command::new("docker");
+        command.args([
+            "build",
+            "-t",
+            &tag,
+            "-f",
+            &dockerfile.to_string_lossy(),
+        ]);
+        if let Some(platform) = platform {
+            command.args(["--platform", platform]);
+        }
+        if self.cache.buildkit {
+            command.env("DOCKER_BUILDKIT", "1");
+            if self.cache.inline_cache {
+                // Inline cache metadata makes pushed images usable as
+                // --cache-from sources on other hosts.
+                command.args(["--build-arg", "BUILDKIT_INLINE_CACHE=1"]);
+            }
+            // The previous image of this service is the best cache source.
+            command.args(["--cache-from", &tag]);
+            for source in &self.cache.cache_from {
+                command.args(["--cache-from", source]);
+            }
+        }
+        // Declared build args, with secret references resolved; their
+        // values are scrubbed from the captured log below.
+        let build_args = crate::secrets::resolve_map(&service.build_args)?;
+        let secrets = crate::secrets::secret_values(&build_args);
+        for (key, resolved) in &build_args {
+            command.args(["--build-arg", &format!("{key}={}", resolved.value)]);
+        }
+        if let Some(cpus) = service.limits.cpus {
+            // docker build takes quota/period rather than --cpus.
+            command.args([
+                "--cpu-period=100000",
+                &format!("--cpu-quota={}", (cpus * 100_000.0) as u64),
+            ]);
+        }
+        if let Some(memory) = &service.limits.memory {
+            command.args(["--memory", memory]);
+        }
+        command.arg("-");
+        let mut child = command
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .context("failed to invoke docker build")?;
+
+        // Stream the context straight into the daemon; nothing is written
+        // to disk and ignored paths never leave the checkout.
+        let stdin = child.stdin.take().expect("stdin was piped");
+        let ignore = DockerIgnore::load(context_dir);
+        let tar_result = write_context_tar(stdin, context_dir, &ignore);
+        let mut outcome = collect_with_timeout(
+            child,
+            std::time::Duration::from_secs(service.limits.timeout_secs),
+        )?;
+        outcome.log = crate::secrets::redact(&outcome.log, &secrets);
+        // A tar write error usually just means the daemon rejected the
+        // context; the build output carries the real diagnostic.
+        if let Err(e) = tar_result {
+            debug!(service = %service.name, "context streaming ended early: {e:#}");
+        }
+        if !outcome.success {
+            debug!(service = %service.name, timed_out = outcome.timed_out, "docker build failed");
+        }
+        Ok(outcome)
+    }
+
+    /// Refuse to start a build when the filesystem backing the daemon is
+    /// nearly full; a build that dies half-way on ENOSPC leaves far more
+    /// mess than a skipped one.
+    fn preflight_disk_check(&self, min_free_mb: u64) -> Result<()> {
+        if min_free_mb == 0 {
+            return Ok(());
+        }
+        let free_mb = crate::platform::free_disk_mb("/var/lib/docker")?;
+        if free_mb < min_free_mb {
+            bail!("refusing to build: only {free_mb} MB free, {min_free_mb} MB required");
+        }
+        Ok(())
+    }
+
+    /// Whether the given image tag exists locally.
+    pub fn image_exists(&self, image: &str) -> Result<bool> {
+        let output = Command::new("docker")
+            .args(["image", "inspect", image])
+            .output()
+            .context("failed to invoke docker image inspect")?;
+        Ok(output.status.success())
+    }
+
+    /// Content digest (image ID) of a local image, when it exists.
+    pub fn image_digest(&self, image: &str) -> Result<Option<String>> {
+        let output = Command::new("docker")
+            .args(["image", "inspect", "--format", "{{.Id}}", image])
+            .output()
+            .context("failed to invoke docker image inspect")?;
+        if !output.status.success() {
+            return Ok(None);
+        }
+        let id = String::from_utf8_lossy(&output.stdout).trim().to_string();
+        Ok((!id.is_empty()).then_some(id))
+    }
+
+    /// Start a detached container under an explicit name, with the
+    /// service's resolved environment injected.
+    pub fn run_container(&self, name: &str, image: &str, env: &[(String, String)]) -> Result<()> {
+        let _ = Command::new("docker").args(["rm", "-f", name]).output();
+        let mut command = Command::new("docker");
+        command.args(["run", "-d", "--name", name]);
+        for (key, value) in env {
+            command.args(["-e", &format!("{key}={value}")]);
+        }
+        let status = command
+            .arg(image)
+            .status()
+            .context("failed to invoke docker run")?;
+        if !status.success() {
+            bail!("docker run for {name} exited with {status}");
+        }
+        Ok(())
+    }
+
+    /// Remove a container, tolerating it not existing.
+    pub fn remove_container(&self, name: &str) -> Result<()> {
+        let _ = Command::new("docker")
+            .args(["rm", "-f", name])
+            .output()
+            .context("failed to invoke docker rm")?;
+        Ok(())
+    }
+
+    /// Swap the running container to the given image tag. Under compose the
+    /// service is recreated from its compose entry (which picks up the
+    /// image we just tagged) so networks, volumes, and env survive.
+    pub fn redeploy(&self, service: &ServiceConfig, image: &str) -> Result<()> {
+        if let Some(compose) = &self.compose {
+            return compose.up(&service.name, false);
+        }
+        info!(service = %service.name, image, "redeploying container");
+        let env: Vec<(String, String)> = crate::secrets::resolve_map(&service.env)?
+            .into_iter()
+            .map(|(key, resolved)| (key, resolved.value))
+            .collect();
+        self.run_container(&service.name, image, &env)
+    }
+
+    /// Remove dangling images left behind by monitor builds.
+    pub fn cleanup_resources(&self) -> Result<()> {
+        let _ = Command::new("docker")
+            .args(["image", "prune", "-f"])
+            .output()
+            .context("failed to invoke docker image prune")?;
+        Ok(())
+    }
+}
+
+
+/// Drain a child's captured output and wait for it, killing it when the
+/// deadline passes. Used for every build invocation so a hung build can
+/// never wedge the monitoring loop.
+pub(crate) fn collect_with_timeout(
+    mut child: std::process::Child,
+    timeout: std::time::Duration,
+) -> Result<BuildOutcome> {
+    use std::io::Read;
+
+    // Drain pipes on threads so a chatty build can't fill them and stall.
+    let mut readers = Vec::new();
+    if let Some(mut stdout) = child.stdout.take() {
+        readers.push(std::thread::spawn(move || {
+            let mut buf = String::new();
+            let _ = stdout.read_to_string(&mut buf);
+            buf
+        }));
+    }
+    if let Some(mut stderr) = child.stderr.take() {
+        readers.push(std::thread::spawn(move || {
+            let mut buf = String::new();
+            let _ = stderr.read_to_string(&mut buf);
+            buf
+        }));
+    }
+
+    let deadline = std::time::Instant::now() + timeout;
+    let status = loop {
+        if let Some(status) = child.try_wait().context("failed to wait for build")? {
+            break Some(status);
+        }
+        if std::time::Instant::now() >= deadline {
+            let _ = child.kill();
+            let _ = child.wait();
+            break None;
+        }
+        std::thread::sleep(std::time::Duration::from_millis(200));
+    };
+
+    let mut log = String::new();
+    for reader in readers {
+        let part = reader.join().unwrap_or_default();
+        if !part.is_empty() {
+            if !log.is_empty() {
+                log.push('\n');
+            }
+            log.push_str(&part);
+        }
+    }
+    match status {
+        Some(status) => Ok(BuildOutcome {
+            success: status.success(),
+            timed_out: false,
+            log,
+        }),
+        None => {
+            log.push_str(&format!("\nbuild killed after exceeding {}s timeout", timeout.as_secs()));
+            Ok(BuildOutcome {
+                success: false,
+                timed_out: true,
+                log,
+            })
+        }
+    }
+}
+
+/// Patterns from the context's `.dockerignore`, applied docker-style: the
+/// last matching pattern decides, `!` re-includes, and a pattern matching a
+/// directory excludes everything beneath it.
+struct DockerIgnore {
+    rules: Vec<(glob::Pattern, bool)>,
+}
+
+impl DockerIgnore {
+    fn load(context_dir: &Path) -> Self {
+        let raw = std::fs::read_to_string(context_dir.join(".dockerignore")).unwrap_or_default();
+        let mut rules = Vec::new();
+        for line in raw.lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (pattern, negate) = match line.strip_prefix('!') {
+                Some(rest) => (rest, true),
+                None => (line, false),
+            };
+            let pattern = pattern.trim_end_matches('/');
+            if let Ok(compiled) = glob::Pattern::new(pattern) {
+                rules.push((compiled, negate));
+            }
+        }
+        Self { rules }
+    }
+
+    fn excluded(&self, rel: &str) -> bool {
+        let mut excluded = false;
+        for (pattern, negate) in &self.rules {
+            if Self::matches(pattern, rel) {
+                excluded = !negate;
+            }
+        }
+        excluded
+    }
+
+    /// A pattern matching any ancestor directory also matches the path.
+    fn matches(pattern: &glob::Pattern, rel: &str) -> bool {
+        if pattern.matches(rel) {
+            return true;
+        }
+        let mut prefix = String::new();
+        for part in rel.split('/') {
+            if !prefix.is_empty() {
+                prefix.push('/');
+            }
+            prefix.push_str(part);
+            if pattern.matches(&prefix) {
+                return true;
+            }
+        }
+        false
+    }
+}
+
+/// Stream the build context as a tar archive into `writer`, skipping paths
+/// excluded by `.dockerignore`.
+fn write_context_tar<W: std::io::Write>(
+    writer: W,
+    context_dir: &Path,
+    ignore: &DockerIgnore,
+) -> Result<()> {
+    let mut builder = tar::Builder::new(writer);
+    builder.follow_symlinks(false);
+    append_dir(&mut builder, context_dir, context_dir, ignore)?;
+    builder.into_inner()?.flush()?;
+    Ok(())
+}
+
+fn append_dir<W: std::io::Write>(
+    builder: &mut tar::Builder<W>,
+    root: &Path,
+    dir: &Path,
+    ignore: &DockerIgnore,
+) -> Result<()> {
+    let mut entries: Vec<_> = std::fs::read_dir(dir)
+        .with_context(|| format!("failed to read {}", dir.display()))?
+        .collect::<std::io::Result<_>>()?;
+    entries.sort_by_key(|e| e.file_name());
+    for entry in entries {
+        let path = entry.path();
+        let rel = path
+            .strip_prefix(root)
+            .expect("entry is under the context root")
+            .to_string_lossy()
+            .replace('\\', "/");
+        if ignore.excluded(&rel) {
+            continue;
+        }
+        let file_type = entry.file_type()?;
+        if file_type.is_dir() {
+            append_dir(builder, root, &path, ignore)?;
+        } else {
+            builder.append_path_with_name(&path, &rel)?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dockerignore_last_match_wins() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(
+            dir.path().join(".dockerignore"),
+            "# build artifacts\ntarget/\nnode_modules\n*.log\n!keep.log\n",
+        )
+        .unwrap();
+        let ignore = DockerIgnore::load(dir.path());
+        assert!(ignore.excluded("target"));
+        assert!(ignore.excluded("target/release/app"));
+        assert!(ignore.excluded("node_modules/x/y.js"));
+        assert!(ignore.excluded("debug.log"));
+        assert!(!ignore.excluded("keep.log"));
+        assert!(!ignore.excluded("src/main.rs"));
+    }
+
+    #[test]
+    fn context_tar_skips_ignored_paths() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join(".dockerignore"), "target\n").unwrap();
+        std::fs::write(dir.path().join("Dockerfile"), "FROM scratch\n").unwrap();
+        std::fs::create_dir_all(dir.path().join("target/release")).unwrap();
+        std::fs::write(dir.path().join("target/release/app"), "bin").unwrap();
+        std::fs::create_dir_all(dir.path().join("src")).unwrap();
+        std::fs::write(dir.path().join("src/main.rs"), "fn main() {}\n").unwrap();
+
+        let mut buf = Vec::new();
+        let ignore = DockerIgnore::load(dir.path());
+        write_context_tar(&mut buf, dir.path(), &ignore).unwrap();
+
+        let mut archive = tar::Archive::new(buf.as_slice());
+        let paths: Vec<String> = archive
+            .entries()
+            .unwrap()
+            .map(|e| e.unwrap().path().unwrap().to_string_lossy().into_owned())
+            .collect();
+        assert!(paths.contains(&"Dockerfile".to_string()));
+        assert!(paths.contains(&"src/main.rs".to_string()));
+        assert!(!paths.iter().any(|p| p.starts_with("target")));
+    }
+}
@@ -0,0 +1,132 @@
+//! In-process event bus for build, rollback, and health events.
+//!
+//! Subsystems publish [`MonitorEvent`]s onto a tokio broadcast channel; the
+//! web server fans them out to dashboard clients over SSE so the UI does not
+//! have to poll `/api/dashboard`.
+
+use crate::types::{BuildResult, ServiceHealth, Severity};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use tokio::sync::broadcast;
+
+/// Buffered events per subscriber before slow clients start losing events.
+const EVENT_BUFFER: usize = 256;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum MonitorEvent {
+    BuildStarted {
+        service: String,
+        commit: String,
+    },
+    BuildSucceeded {
+        build: BuildResult,
+    },
+    BuildFailed {
+        build: BuildResult,
+    },
+    RollbackStarted {
+        service: String,
+        from_commit: String,
+        to_commit: String,
+    },
+    RollbackCompleted {
+        service: String,
+        to_commit: String,
+        success: bool,
+    },
+    HealthChanged {
+        service: String,
+        previous: ServiceHealth,
+        current: ServiceHealth,
+    },
+    Alert {
+        severity: Severity,
+        service: Option<String>,
+        message: String,
+    },
+}
+
+impl MonitorEvent {
+    /// SSE `event:` name for this event, letting clients subscribe with
+    /// `EventSource.addEventListener` per type.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            MonitorEvent::BuildStarted { .. } => "build_started",
+            MonitorEvent::BuildSucceeded { .. } => "build_succeeded",
+            MonitorEvent::BuildFailed { .. } => "build_failed",
+            MonitorEvent::RollbackStarted { .. } => "rollback_started",
+            MonitorEvent::RollbackCompleted { .. } => "rollback_completed",
+            MonitorEvent::HealthChanged { .. } => "health_changed",
+            MonitorEvent::Alert { .. } => "alert",
+        }
+    }
+}
+
+/// An event paired with the time it was published.
+#[derive(Debug, Clone, Serialize)]
+pub struct StampedEvent {
+    pub timestamp: DateTime<Utc>,
+    #[serde(flatten)]
+    pub event: MonitorEvent,
+}
+
+/// Cheaply cloneable publisher handle.
+#[derive(Clone)]
+pub struct EventBus {
+    sender: broadcast::Sender<StampedEvent>,
+}
+
+impl EventBus {
+    pub fn new() -> Self {
+        let (sender, _) = broadcast::channel(EVENT_BUFFER);
+        Self { sender }
+    }
+
+    /// Publish an event; returns silently when nobody is subscribed.
+    pub fn publish(&self, event: MonitorEvent) {
+        let _ = self.sender.send(StampedEvent {
+            timestamp: Utc::now(),
+            event,
+        });
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<StampedEvent> {
+        self.sender.subscribe()
+    }
+}
+
+impl Default for EventBus {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn subscribers_receive_published_events() {
+        let bus = EventBus::new();
+        let mut rx = bus.subscribe();
+        bus.publish(MonitorEvent::BuildStarted {
+            service: "ml-api".into(),
+            commit: "abc".into(),
+        });
+        let stamped = rx.recv().await.unwrap();
+        assert_eq!(stamped.event.kind(), "build_started");
+    }
+
+    #[test]
+    fn events_serialize_with_type_tag() {
+        let json = serde_json::to_value(MonitorEvent::HealthChanged {
+            service: "web".into(),
+            previous: ServiceHealth::Healthy,
+            current: ServiceHealth::Down,
+        })
+        .unwrap();
+        assert_eq!(json["type"], "health_changed");
+        assert_eq!(json["current"], "down");
+    }
+}
@@ -0,0 +1,277 @@
+//! Git plumbing for the monitored repository.
+//!
+//! All operations shell out to the `git` binary in the monitored checkout;
+//! the monitor assumes it has exclusive ownership of that working tree.
+
+use anyhow::{bail, Context, Result};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+pub struct GitMonitor {
+    repo_path: PathBuf,
+    branch: String,
+}
+
+impl GitMonitor {
+    pub fn new(repo_path: &Path, branch: &str) -> Self {
+        Self {
+            repo_path: repo_path.to_path_buf(),
+            branch: branch.to_string(),
+        }
+    }
+
+    pub fn repo_path(&self) -> &Path {
+        &self.repo_path
+    }
+
+    fn git(&self, args: &[&str]) -> Result<String> {
+        let output = Command::new("git")
+            .args(args)
+            .current_dir(&self.repo_path)
+            .output()
+            .with_context(|| format!("failed to run git {args:?}"))?;
+        if !output.status.success() {
+            bail!(
+                "git {:?} failed: {}",
+                args,
+                String::from_utf8_lossy(&output.stderr).trim()
+            );
+        }
+        Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+    }
+
+    /// Fetch the remote and return the current head of the monitored branch.
+    pub fn fetch_head(&self) -> Result<String> {
+        // A missing remote is tolerated so local-only repos can be monitored.
+        let _ = self.git(&["fetch", "origin", &self.branch]);
+        self.git(&["rev-parse", &format!("origin/{}", self.branch)])
+            .or_else(|_| self.git(&["rev-parse", &self.branch]))
+    }
+
+    /// Commits between `from` (exclusive) and `to` (inclusive), oldest first.
+    pub fn commits_between(&self, from: &str, to: &str) -> Result<Vec<String>> {
+        let out = self.git(&["rev-list", "--reverse", &format!("{from}..{to}")])?;
+        Ok(out.lines().map(|l| l.to_string()).collect())
+    }
+
+    /// Files touched by a commit, relative to the repo root.
+    pub fn changed_files(&self, commit: &str) -> Result<Vec<String>> {
+        let out = self.git(&[
+            "diff-tree",
+            "--no-commit-id",
+            "--name-only",
+            "-r",
+            commit,
+        ])?;
+        Ok(out.lines().map(|l| l.to_string()).collect())
+    }
+
+    pub fn commit_summary(&self, commit: &str) -> Result<String> {
+        self.git(&["log", "-1", "--format=%h %s", commit])
+    }
+
+    /// Fetch and resolve the heads of watched branches and, optionally,
+    /// every open pull request (GitHub's `refs/pull/*/head` namespace).
+    pub fn watched_refs(
+        &self,
+        branches: &[String],
+        include_prs: bool,
+    ) -> Result<Vec<(String, String)>> {
+        let mut refs = Vec::new();
+        for branch in branches {
+            if branch == &self.branch {
+                continue;
+            }
+            let _ = self.git(&["fetch", "origin", branch]);
+            let head = self
+                .git(&["rev-parse", &format!("origin/{branch}")])
+                .or_else(|_| self.git(&["rev-parse", branch]));
+            if let Ok(head) = head {
+                refs.push((branch.clone(), head));
+            }
+        }
+        if include_prs {
+            let _ = self.git(&[
+                "fetch",
+                "origin",
+                "+refs/pull/*/head:refs/remotes/origin/pr/*",
+            ]);
+            if let Ok(list) = self.git(&[
+                "for-each-ref",
+                "--format=%(refname:short) %(objectname)",
+                "refs/remotes/origin/pr",
+            ]) {
+                for line in list.lines() {
+                    if let Some((name, sha)) = line.split_once(' ') {
+                        refs.push((
+                            name.trim_start_matches("origin/").to_string(),
+                            sha.to_string(),
+                        ));
+                    }
+                }
+            }
+        }
+        Ok(refs)
+    }
+
+    /// Content key for a build at `commit`: the object hash of each given
+    /// path in that commit's tree, or the commit's whole tree hash when no
+    /// paths are given. Two commits with equal keys (merges, reverts,
+    /// touched-nothing-relevant rebases) build identically, whatever their
+    /// history looks like.
+    pub fn tree_hash(&self, commit: &str, paths: &[String]) -> Result<String> {
+        if paths.is_empty() {
+            return self.git(&["rev-parse", &format!("{commit}^{{tree}}")]);
+        }
+        let mut sorted: Vec<&str> = paths.iter().map(|p| p.trim_end_matches('/')).collect();
+        sorted.sort_unstable();
+        sorted.dedup();
+        let mut parts = Vec::with_capacity(sorted.len());
+        for path in sorted {
+            // A path can be absent at older commits; that is still a
+            // well-defined, cacheable state.
+            let hash = self
+                .git(&["rev-parse", &format!("{commit}:{path}")])
+                .unwrap_or_else(|_| "absent".to_string());
+            parts.push(format!("{path}@{hash}"));
+        }
+        Ok(parts.join(","))
+    }
+
+    /// Run the given build closure against `commit` checked out in a
+    /// detached `git worktree` under a temp directory, so the monitored
+    /// checkout is never disturbed and callers can run in parallel.
+    pub fn test_build_at_commit<T, F>(&self, commit: &str, build: F) -> Result<T>
+    where
+        F: FnOnce(&Path) -> Result<T>,
+    {
+        let worktree = tempfile::Builder::new()
+            .prefix("build-monitor-worktree-")
+            .tempdir()
+            .context("failed to create worktree directory")?;
+        // `tempdir` creates the directory; `git worktree add` wants to own
+        // it, so hand it a path inside.
+        let checkout = worktree.path().join("src");
+        self.git(&[
+            "worktree",
+            "add",
+            "--detach",
+            &checkout.to_string_lossy(),
+            commit,
+        ])?;
+        let result = build(&checkout);
+        // Always detach the worktree again, even when the build errored;
+        // stale registrations would block future bisects.
+        let removed = self.git(&["worktree", "remove", "--force", &checkout.to_string_lossy()]);
+        let ok = result?;
+        removed?;
+        Ok(ok)
+    }
+
+    /// Walk history backwards from `head` looking for the most recent commit
+    /// that built successfully for the given service.
+    pub fn find_last_good_commit<F>(&self, head: &str, max_depth: usize, mut check: F) -> Result<Option<String>>
+    where
+        F: FnMut(&str) -> Result<bool>,
+    {
+        let out = self.git(&["rev-list", &format!("--max-count={max_depth}"), head])?;
+        for commit in out.lines().skip(1) {
+            if check(commit)? {
+                return Ok(Some(commit.to_string()));
+            }
+        }
+        Ok(None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn init_repo(dir: &Path) -> String {
+        let run = |args: &[&str]| {
+            let out = std::process::Command::new("git")
+                .args(args)
+                .current_dir(dir)
+                .env("GIT_AUTHOR_NAME", "test")
+                .env("GIT_AUTHOR_EMAIL", "test@example.com")
+                .env("GIT_COMMITTER_NAME", "test")
+                .env("GIT_COMMITTER_EMAIL", "test@example.com")
+                .output()
+                .unwrap();
+            assert!(out.status.success(), "git {args:?}: {}", String::from_utf8_lossy(&out.stderr));
+            String::from_utf8_lossy(&out.stdout).trim().to_string()
+        };
+        run(&["init", "-q", "-b", "main"]);
+        std::fs::write(dir.join("marker.txt"), "v1").unwrap();
+        run(&["add", "."]);
+        run(&["commit", "-q", "-m", "initial"]);
+        run(&["rev-parse", "HEAD"])
+    }
+
+    #[test]
+    fn test_build_at_commit_uses_isolated_worktree() {
+        let dir = tempfile::tempdir().unwrap();
+        let commit = init_repo(dir.path());
+        let repo = GitMonitor::new(dir.path(), "main");
+
+        let repo_root = dir.path().canonicalize().unwrap();
+        let ok = repo
+            .test_build_at_commit(&commit, |checkout| {
+                // The build runs somewhere else entirely...
+                assert_ne!(checkout.canonicalize().unwrap(), repo_root);
+                // ...but sees the commit's content.
+                Ok(checkout.join("marker.txt").exists())
+            })
+            .unwrap();
+        assert!(ok);
+        // The live checkout was never touched and no worktree lingers.
+        assert_eq!(repo.git(&["rev-parse", "HEAD"]).unwrap(), commit);
+        assert_eq!(repo.git(&["worktree", "list"]).unwrap().lines().count(), 1);
+    }
+
+    #[test]
+    fn tree_hash_matches_for_identical_content() {
+        let dir = tempfile::tempdir().unwrap();
+        let first = init_repo(dir.path());
+        let run = |args: &[&str]| {
+            let out = std::process::Command::new("git")
+                .args(args)
+                .current_dir(dir.path())
+                .env("GIT_AUTHOR_NAME", "test")
+                .env("GIT_AUTHOR_EMAIL", "test@example.com")
+                .env("GIT_COMMITTER_NAME", "test")
+                .env("GIT_COMMITTER_EMAIL", "test@example.com")
+                .output()
+                .unwrap();
+            assert!(out.status.success(), "git {args:?}: {}", String::from_utf8_lossy(&out.stderr));
+            String::from_utf8_lossy(&out.stdout).trim().to_string()
+        };
+        std::fs::write(dir.path().join("marker.txt"), "v2").unwrap();
+        run(&["commit", "-q", "-am", "change"]);
+        let changed = run(&["rev-parse", "HEAD"]);
+        // A revert restores the exact tree of the first commit.
+        std::fs::write(dir.path().join("marker.txt"), "v1").unwrap();
+        run(&["commit", "-q", "-am", "revert"]);
+        let reverted = run(&["rev-parse", "HEAD"]);
+
+        let repo = GitMonitor::new(dir.path(), "main");
+        let paths = vec!["marker.txt".to_string()];
+        assert_eq!(
+            repo.tree_hash(&first, &paths).unwrap(),
+            repo.tree_hash(&reverted, &paths).unwrap()
+        );
+        assert_ne!(
+            repo.tree_hash(&first, &paths).unwrap(),
+            repo.tree_hash(&changed, &paths).unwrap()
+        );
+        // Whole-tree keys behave the same for path-less services.
+        assert_eq!(
+            repo.tree_hash(&first, &[]).unwrap(),
+            repo.tree_hash(&reverted, &[]).unwrap()
+        );
+        // Absent paths still produce a stable key.
+        let missing = vec!["not/there".to_string()];
+        assert_eq!(repo.tree_hash(&first, &missing).unwrap(), "not/there@absent");
+    }
+}
@@ -0,0 +1,132 @@
+//! GitHub Checks API integration.
+//!
+//! Every commit the monitor builds gets a check run named after the
+//! service, so build status shows up directly on commits and PRs. The
+//! token comes from the environment (a PAT or a GitHub App installation
+//! token); without one the integration is silently disabled.
+
+use crate::config::GithubConfig;
+use crate::types::{BuildResult, BuildStatus};
+use anyhow::{Context, Result};
+use serde_json::json;
+use tracing::debug;
+
+pub struct GithubChecks {
+    config: Option<GithubConfig>,
+    token: Option<String>,
+    client: reqwest::Client,
+}
+
+impl GithubChecks {
+    pub fn new(config: Option<GithubConfig>) -> Self {
+        let token = config
+            .as_ref()
+            .and_then(|c| std::env::var(&c.token_env).ok());
+        Self {
+            config,
+            token,
+            client: reqwest::Client::new(),
+        }
+    }
+
+    pub fn enabled(&self) -> bool {
+        self.config.is_some() && self.token.is_some()
+    }
+
+    /// Mark the commit's check run as in progress.
+    pub async fn report_started(&self, service: &str, commit: &str) {
+        let body = json!({
+            "name": self.check_name(service),
+            "head_sha": commit,
+            "status": "in_progress",
+        });
+        if let Err(e) = self.post_check(&body).await {
+            debug!(service, commit, "failed to post check run: {e:#}");
+        }
+    }
+
+    /// Post the final conclusion with a summary and a log excerpt.
+    pub async fn report_finished(&self, build: &BuildResult, log: &str) {
+        let (conclusion, summary) = match build.status {
+            BuildStatus::Success => ("success", format!("build passed in {} attempt(s)", build.attempts)),
+            BuildStatus::TimedOut => ("timed_out", "build exceeded its time limit".to_string()),
+            _ => ("failure", build.error.clone().unwrap_or_else(|| "build failed".to_string())),
+        };
+        let body = json!({
+            "name": self.check_name(&build.service),
+            "head_sha": build.commit,
+            "status": "completed",
+            "conclusion": conclusion,
+            "output": {
+                "title": summary,
+                "summary": summary,
+                "text": format!("```\n{}\n```", log_excerpt(log)),
+            },
+        });
+        if let Err(e) = self.post_check(&body).await {
+            debug!(service = %build.service, commit = %build.commit, "failed to post check run: {e:#}");
+        }
+    }
+
+    fn check_name(&self, service: &str) -> String {
+        format!("build-monitor / {service}")
+    }
+
+    async fn post_check(&self, body: &serde_json::Value) -> Result<()> {
+        let (Some(config), Some(token)) = (&self.config, &self.token) else {
+            return Ok(());
+        };
+        let url = format!(
+            "{}/repos/{}/check-runs",
+            config.api_url.trim_end_matches('/'),
+            config.repo
+        );
+        let response = self
+            .client
+            .post(&url)
+            .bearer_auth(token)
+            .header("Accept", "application/vnd.github+json")
+            .header("User-Agent", "build-monitor")
+            .json(body)
+            .send()
+            .await
+            .context("check-run request failed")?;
+        if !response.status().is_success() {
+            anyhow::bail!("GitHub returned {}", response.status());
+        }
+        Ok(())
+    }
+}
+
+/// Last lines of the build log, bounded well under the Checks API's output
+/// size limit.
+fn log_excerpt(log: &str) -> String {
+    const MAX_LINES: usize = 40;
+    const MAX_BYTES: usize = 4000;
+    let lines: Vec<&str> = log.lines().collect();
+    let start = lines.len().saturating_sub(MAX_LINES);
+    let mut excerpt = lines[start..].join("\n");
+    if excerpt.len() > MAX_BYTES {
+        let cut = excerpt.len() - MAX_BYTES;
+        // Cut on a char boundary near the byte limit.
+        let boundary = (cut..excerpt.len())
+            .find(|i| excerpt.is_char_boundary(*i))
+            .unwrap_or(cut);
+        excerpt = format!("...{}", &excerpt[boundary..]);
+    }
+    excerpt
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn excerpt_keeps_the_tail_within_bounds() {
+        let log: String = (0..200).map(|i| format!("line {i}\n")).collect();
+        let excerpt = log_excerpt(&log);
+        assert!(excerpt.ends_with("line 199"));
+        assert!(!excerpt.contains("line 100"));
+        assert!(excerpt.len() <= 4004);
+    }
+}
@@ -0,0 +1,149 @@
+//! Dependency graph between managed services.
+//!
+//! Services declare `depends_on` in their config; the graph expands a
+//! change to a shared component into rebuilds of everything downstream and
+//! orders those rebuilds so dependencies always build first.
+
+use crate::config::ServiceConfig;
+use anyhow::{bail, Result};
+use std::collections::{HashMap, HashSet};
+
+#[derive(Debug, Clone, Default)]
+pub struct ServiceGraph {
+    /// service -> services it depends on
+    depends_on: HashMap<String, Vec<String>>,
+    /// service -> services that depend on it directly
+    dependents: HashMap<String, Vec<String>>,
+}
+
+impl ServiceGraph {
+    /// Build the graph, rejecting unknown dependencies and cycles.
+    pub fn build(services: &[ServiceConfig]) -> Result<Self> {
+        let names: HashSet<&str> = services.iter().map(|s| s.name.as_str()).collect();
+        let mut graph = Self::default();
+        for service in services {
+            for dep in &service.depends_on {
+                if !names.contains(dep.as_str()) {
+                    bail!("service {} depends on unknown service {dep}", service.name);
+                }
+                graph
+                    .dependents
+                    .entry(dep.clone())
+                    .or_default()
+                    .push(service.name.clone());
+            }
+            graph
+                .depends_on
+                .insert(service.name.clone(), service.depends_on.clone());
+        }
+        // A valid topological order exists only for acyclic graphs.
+        graph.topo_order(graph.depends_on.keys().cloned())?;
+        Ok(graph)
+    }
+
+    /// All services that transitively depend on `name`.
+    pub fn dependents_of(&self, name: &str) -> Vec<String> {
+        let mut seen = HashSet::new();
+        let mut stack = vec![name.to_string()];
+        while let Some(current) = stack.pop() {
+            for dependent in self.dependents.get(&current).into_iter().flatten() {
+                if seen.insert(dependent.clone()) {
+                    stack.push(dependent.clone());
+                }
+            }
+        }
+        let mut out: Vec<String> = seen.into_iter().collect();
+        out.sort();
+        out
+    }
+
+    /// Order the given services so every service comes after everything it
+    /// depends on (Kahn's algorithm, restricted to the given set).
+    pub fn topo_order(&self, services: impl IntoIterator<Item = String>) -> Result<Vec<String>> {
+        let set: HashSet<String> = services.into_iter().collect();
+        let mut in_degree: HashMap<&str, usize> = set
+            .iter()
+            .map(|s| {
+                let deps = self
+                    .depends_on
+                    .get(s)
+                    .map(|d| d.iter().filter(|d| set.contains(*d)).count())
+                    .unwrap_or(0);
+                (s.as_str(), deps)
+            })
+            .collect();
+        let mut ready: Vec<&str> = in_degree
+            .iter()
+            .filter(|(_, d)| **d == 0)
+            .map(|(s, _)| *s)
+            .collect();
+        ready.sort();
+        let mut order = Vec::with_capacity(set.len());
+        while let Some(next) = ready.pop() {
+            order.push(next.to_string());
+            for dependent in self.dependents.get(next).into_iter().flatten() {
+                if let Some(d) = in_degree.get_mut(dependent.as_str()) {
+                    *d -= 1;
+                    if *d == 0 {
+                        ready.push(dependent);
+                        ready.sort();
+                    }
+                }
+            }
+        }
+        if order.len() != set.len() {
+            bail!("dependency cycle among services: {:?}", set);
+        }
+        Ok(order)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn service(name: &str, deps: &[&str]) -> ServiceConfig {
+        serde_json::from_value(serde_json::json!({
+            "name": name,
+            "depends_on": deps,
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn cascades_and_orders_downstream_builds() {
+        let graph = ServiceGraph::build(&[
+            service("common", &[]),
+            service("face-detection", &["common"]),
+            service("face-embedding", &["common"]),
+            service("ml-api", &["face-detection", "face-embedding"]),
+        ])
+        .unwrap();
+
+        let dependents = graph.dependents_of("common");
+        assert_eq!(dependents, vec!["face-detection", "face-embedding", "ml-api"]);
+
+        let order = graph
+            .topo_order(
+                ["ml-api", "common", "face-detection"]
+                    .iter()
+                    .map(|s| s.to_string()),
+            )
+            .unwrap();
+        assert_eq!(order[0], "common");
+        assert_eq!(order.last().map(String::as_str), Some("ml-api"));
+    }
+
+    #[test]
+    fn rejects_cycles_and_unknown_dependencies() {
+        let err = ServiceGraph::build(&[service("a", &["b"]), service("b", &["a"])])
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("cycle"), "{err}");
+
+        let err = ServiceGraph::build(&[service("a", &["missing"])])
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("unknown service"), "{err}");
+    }
+}
@@ -0,0 +1,167 @@
+//! Integration with the self-healing daemon.
+//!
+//! Repeated build failures that look like code problems (compiler or test
+//! failures, not infrastructure flakes) get filed as issues against the
+//! self-healing system's API, which can then attempt patch generation.
+
+use crate::config::HealingConfig;
+use anyhow::{Context, Result};
+use serde::Serialize;
+use serde_json::json;
+
+/// Coarse classification of a failed build, derived from its log.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FailureClass {
+    Compiler,
+    Test,
+    Infrastructure,
+    Unknown,
+}
+
+impl FailureClass {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            FailureClass::Compiler => "compiler",
+            FailureClass::Test => "test",
+            FailureClass::Infrastructure => "infrastructure",
+            FailureClass::Unknown => "unknown",
+        }
+    }
+}
+
+/// Best-effort classification by scanning the build log for well-known
+/// failure markers. Infrastructure wins over code signals: a registry
+/// timeout mid-compile is still a flake, not a code bug.
+pub fn classify(log: &str) -> FailureClass {
+    let lower = log.to_lowercase();
+    const INFRA: &[&str] = &[
+        "connection refused",
+        "connection reset",
+        "network is unreachable",
+        "no space left on device",
+        "registry returned",
+        "tls handshake",
+        "build killed after exceeding",
+    ];
+    if INFRA.iter().any(|m| lower.contains(m)) {
+        return FailureClass::Infrastructure;
+    }
+    const COMPILER: &[&str] = &[
+        "error[e",
+        "syntaxerror",
+        "cannot find module",
+        "cannot find name",
+        "type error",
+        "compilation failed",
+        "error ts",
+    ];
+    if COMPILER.iter().any(|m| lower.contains(m)) {
+        return FailureClass::Compiler;
+    }
+    const TEST: &[&str] = &["test result: failed", "tests failed", "assertionerror", "expect(received)"];
+    if TEST.iter().any(|m| lower.contains(m)) {
+        return FailureClass::Test;
+    }
+    FailureClass::Unknown
+}
+
+pub struct HealingClient {
+    config: Option<HealingConfig>,
+    client: reqwest::Client,
+}
+
+impl HealingClient {
+    pub fn new(config: Option<HealingConfig>) -> Self {
+        Self {
+            config,
+            client: reqwest::Client::new(),
+        }
+    }
+
+    pub fn enabled(&self) -> bool {
+        self.config.is_some()
+    }
+
+    pub fn failure_threshold(&self) -> u32 {
+        self.config.as_ref().map(|c| c.failure_threshold).unwrap_or(u32::MAX)
+    }
+
+    /// Open an issue record on the self-healing daemon with enough context
+    /// to reproduce the failure; optionally asks it to start patching.
+    /// Returns the daemon's id for the issue, for the orchestration loop.
+    pub async fn file_issue(
+        &self,
+        service: &str,
+        commit: &str,
+        class: FailureClass,
+        log: &str,
+        affected_files: &[String],
+    ) -> Result<Option<String>> {
+        let Some(config) = &self.config else {
+            return Ok(None);
+        };
+        let body = json!({
+            "source": "build-monitor",
+            "service": service,
+            "commit": commit,
+            "classification": class.as_str(),
+            "log": log_tail(log),
+            "affected_files": affected_files,
+            "generate_patch": config.auto_patch,
+        });
+        let url = format!("{}/api/issues", config.endpoint.trim_end_matches('/'));
+        // The handoff span ties the self-healing side of the trace back to
+        // the build failure that caused it.
+        let span = tracing::info_span!("self_healing.file_issue", %service, class = class.as_str());
+        let mut request = self.client.post(&url).json(&body);
+        if let Some(traceparent) = span.in_scope(aurum_telemetry::current_traceparent) {
+            request = request.header(aurum_telemetry::TRACEPARENT, traceparent);
+        }
+        let response = request
+            .send()
+            .await
+            .context("self-healing request failed")?;
+        if !response.status().is_success() {
+            anyhow::bail!("self-healing daemon returned {}", response.status());
+        }
+        let issue: serde_json::Value = response
+            .json()
+            .await
+            .context("self-healing response was not JSON")?;
+        Ok(issue
+            .get("id")
+            .and_then(|id| id.as_str())
+            .map(str::to_string))
+    }
+}
+
+/// Last lines of the log, bounded so issue payloads stay small.
+fn log_tail(log: &str) -> String {
+    const MAX_LINES: usize = 100;
+    let lines: Vec<&str> = log.lines().collect();
+    let start = lines.len().saturating_sub(MAX_LINES);
+    lines[start..].join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn classification_prefers_infrastructure_over_code_signals() {
+        assert_eq!(
+            classify("error[E0308]: mismatched types"),
+            FailureClass::Compiler
+        );
+        assert_eq!(
+            classify("FAIL src/app.test.ts\nexpect(received).toBe(expected)"),
+            FailureClass::Test
+        );
+        assert_eq!(
+            classify("error[E0308]: mismatched types\nerror: connection reset by peer"),
+            FailureClass::Infrastructure
+        );
+        assert_eq!(classify("something exploded"), FailureClass::Unknown);
+    }
+}
@@ -0,0 +1,116 @@
+//! Leader election for redundant monitor replicas.
+//!
+//! Two replicas pointed at the same database (and thus the same
+//! production stack) must not both build, roll back, or redeploy. A
+//! lease row in the database decides who acts: the holder does
+//! everything, the other replica serves the dashboard and read API until
+//! the lease expires or is released.
+
+use crate::database::Database;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use tracing::{info, warn};
+
+const LEASE_NAME: &str = "build-monitor";
+
+pub struct LeaderElector {
+    database: Database,
+    /// Fresh per process so a restarted replica never inherits a claim.
+    holder: String,
+    ttl_secs: u64,
+    /// Disabled election (the single-instance default behaviour) reports
+    /// every replica as leader.
+    enabled: bool,
+    leader: AtomicBool,
+}
+
+impl LeaderElector {
+    pub fn new(database: Database, enabled: bool, ttl_secs: u64) -> Self {
+        let host = std::env::var("HOSTNAME").unwrap_or_else(|_| "local".to_string());
+        Self {
+            database,
+            holder: format!("{host}/{}", uuid::Uuid::new_v4()),
+            ttl_secs,
+            enabled,
+            leader: AtomicBool::new(!enabled),
+        }
+    }
+
+    pub fn is_leader(&self) -> bool {
+        self.leader.load(Ordering::Relaxed)
+    }
+
+    /// Acquire or renew once, logging any change of role.
+    pub async fn try_acquire(&self) -> bool {
+        if !self.enabled {
+            return true;
+        }
+        let now_leader = match self
+            .database
+            .try_acquire_lease(LEASE_NAME, &self.holder, self.ttl_secs)
+            .await
+        {
+            Ok(acquired) => acquired,
+            Err(e) => {
+                // Failing to renew demotes us; a stack with no active
+                // monitor beats one with two.
+                warn!("lease renewal failed: {e:#}");
+                false
+            }
+        };
+        let was_leader = self.leader.swap(now_leader, Ordering::Relaxed);
+        if now_leader && !was_leader {
+            info!(holder = %self.holder, "this replica is now the active monitor");
+        } else if !now_leader && was_leader {
+            warn!(holder = %self.holder, "this replica lost the monitor lease");
+        }
+        now_leader
+    }
+
+    /// Hand the lease back on shutdown so the standby takes over now
+    /// rather than after the TTL runs out.
+    pub async fn resign(&self) {
+        if !self.enabled || !self.leader.swap(false, Ordering::Relaxed) {
+            return;
+        }
+        if let Err(e) = self.database.release_lease(LEASE_NAME, &self.holder).await {
+            warn!("failed to release the monitor lease: {e:#}");
+        }
+    }
+
+    /// Background renewal at a third of the TTL, so a single missed
+    /// renewal does not flap leadership.
+    pub fn spawn(self: &Arc<Self>) {
+        if !self.enabled {
+            return;
+        }
+        let elector = self.clone();
+        tokio::spawn(async move {
+            let interval = std::time::Duration::from_secs((elector.ttl_secs / 3).max(1));
+            loop {
+                elector.try_acquire().await;
+                tokio::time::sleep(interval).await;
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn the_lease_is_exclusive_until_released() {
+        let db = Database::open_in_memory().await.unwrap();
+        let active = LeaderElector::new(db.clone(), true, 60);
+        let standby = LeaderElector::new(db.clone(), true, 60);
+        assert!(active.try_acquire().await);
+        assert!(!standby.try_acquire().await);
+        assert!(active.try_acquire().await, "holder renewal must succeed");
+
+        active.resign().await;
+        assert!(standby.try_acquire().await);
+        assert!(!active.try_acquire().await);
+        assert!(!active.is_leader());
+    }
+}
@@ -0,0 +1,76 @@
+//! Persistent storage of captured build logs.
+//!
+//! Logs are written once per build, gzip-compressed, under the configured
+//! log directory as `<build-id>.log.gz`.
+
+use anyhow::{Context, Result};
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use std::io::{Read, Write};
+use std::path::PathBuf;
+use uuid::Uuid;
+
+#[derive(Clone)]
+pub struct LogStore {
+    dir: PathBuf,
+}
+
+impl LogStore {
+    pub fn new(dir: PathBuf) -> Result<Self> {
+        std::fs::create_dir_all(&dir)
+            .with_context(|| format!("failed to create log directory {}", dir.display()))?;
+        Ok(Self { dir })
+    }
+
+    fn path(&self, build_id: Uuid) -> PathBuf {
+        self.dir.join(format!("{build_id}.log.gz"))
+    }
+
+    /// Persist the full log for a build, replacing any previous content.
+    pub fn store(&self, build_id: Uuid, log: &str) -> Result<()> {
+        let file = std::fs::File::create(self.path(build_id))?;
+        let mut encoder = GzEncoder::new(file, Compression::default());
+        encoder.write_all(log.as_bytes())?;
+        encoder.finish()?;
+        Ok(())
+    }
+
+    /// Full decompressed log, or None when no log was captured.
+    pub fn read(&self, build_id: Uuid) -> Result<Option<String>> {
+        let path = self.path(build_id);
+        if !path.exists() {
+            return Ok(None);
+        }
+        let file = std::fs::File::open(path)?;
+        let mut decoder = GzDecoder::new(file);
+        let mut log = String::new();
+        decoder.read_to_string(&mut log)?;
+        Ok(Some(log))
+    }
+
+    /// The last `lines` lines of a build log.
+    pub fn tail(&self, build_id: Uuid, lines: usize) -> Result<Option<String>> {
+        Ok(self.read(build_id)?.map(|log| {
+            let all: Vec<&str> = log.lines().collect();
+            let start = all.len().saturating_sub(lines);
+            all[start..].join("\n")
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn store_and_tail_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = LogStore::new(dir.path().to_path_buf()).unwrap();
+        let id = Uuid::new_v4();
+        store.store(id, "line1\nline2\nline3").unwrap();
+        assert_eq!(store.read(id).unwrap().unwrap(), "line1\nline2\nline3");
+        assert_eq!(store.tail(id, 2).unwrap().unwrap(), "line2\nline3");
+        assert!(store.read(Uuid::new_v4()).unwrap().is_none());
+    }
+}
@@ -0,0 +1,352 @@
+//! build-monitor: watches the production branch, builds affected services,
+//! and rolls back automatically when builds or health checks fail.
+
+mod analytics;
+mod artifacts;
+mod auth;
+mod bisect;
+mod builder;
+mod client;
+mod compose;
+mod config;
+mod database;
+mod docker;
+mod events;
+mod git;
+mod github;
+mod graph;
+mod healing;
+mod leader;
+mod logs;
+mod maintenance;
+mod metrics;
+mod monitor;
+mod notifications;
+mod orchestrator;
+mod paging;
+mod platform;
+mod probe;
+mod rollback;
+mod secrets;
+mod tls;
+mod traffic;
+mod types;
+mod watchdog;
+mod web;
+
+use anyhow::Result;
+use clap::{Parser, Subcommand};
+use client::DaemonClient;
+use config::MonitorConfig;
+use monitor::BuildMonitor;
+use rollback::RollbackStrategy;
+use std::path::PathBuf;
+use web::WebServer;
+
+#[derive(Parser)]
+#[command(name = "build-monitor", about = "Aurum production build monitor")]
+struct Cli {
+    /// Path to the configuration file.
+    #[arg(long, default_value = "build-monitor.json")]
+    config: PathBuf,
+    /// URL of a running daemon; defaults to the web address from config.
+    #[arg(long)]
+    endpoint: Option<String>,
+    /// API token for the daemon (defaults to $BUILD_MONITOR_TOKEN).
+    #[arg(long)]
+    token: Option<String>,
+    /// Override a config value over the file and environment, e.g.
+    /// `--set web.port=9000`; repeatable.
+    #[arg(long = "set", value_name = "KEY=VALUE")]
+    set: Vec<String>,
+    #[command(subcommand)]
+    command: Command,
+}
+
+/// Connect to a running daemon, or fall back to direct repo access when
+/// none is reachable.
+async fn daemon_client(
+    endpoint: &Option<String>,
+    token: &Option<String>,
+    config: &MonitorConfig,
+) -> Option<DaemonClient> {
+    let endpoint = endpoint.clone().unwrap_or_else(|| {
+        let scheme = if config.web.tls.is_some() { "https" } else { "http" };
+        format!("{scheme}://127.0.0.1:{}", config.web.port)
+    });
+    let token = token
+        .clone()
+        .or_else(|| std::env::var("BUILD_MONITOR_TOKEN").ok());
+    let client = DaemonClient::new(endpoint, token);
+    if client.is_reachable().await {
+        Some(client)
+    } else {
+        eprintln!(
+            "no daemon reachable at {}; falling back to direct mode",
+            client.endpoint()
+        );
+        None
+    }
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Start the monitoring daemon (and web dashboard).
+    Start {
+        /// Disable the embedded web server.
+        #[arg(long)]
+        no_web: bool,
+    },
+    /// Print current status of all monitored services.
+    Status,
+    /// Roll a service back to a specific commit.
+    Rollback {
+        service: String,
+        to_commit: String,
+        #[arg(long, default_value = "immediate")]
+        strategy: String,
+    },
+    /// Bisect a service's build between a good and bad commit; resumes a
+    /// previous session for the same range if one exists.
+    Bisect {
+        service: String,
+        good_commit: String,
+        bad_commit: String,
+    },
+    /// Print the captured log of a build.
+    Logs {
+        build_id: uuid::Uuid,
+        /// Only print the last N lines.
+        #[arg(long)]
+        tail: Option<usize>,
+        /// Wait for a running build to finish before printing.
+        #[arg(long)]
+        follow: bool,
+    },
+    /// Pause failure alerts and automatic rollbacks on a running daemon.
+    Pause {
+        /// Service to pause; omit to pause everything.
+        service: Option<String>,
+        #[arg(long)]
+        reason: String,
+        /// Auto-resume after this many minutes; omit to pause indefinitely.
+        #[arg(long)]
+        minutes: Option<i64>,
+    },
+    /// Lift a pause on a running daemon.
+    Resume {
+        /// Service to resume; omit to lift a global pause.
+        service: Option<String>,
+    },
+    /// Lift an emergency freeze so automatic builds and deploys resume.
+    Unfreeze {
+        service: String,
+    },
+    /// Show recent build history.
+    History {
+        #[arg(long)]
+        service: Option<String>,
+        #[arg(long, default_value_t = 20)]
+        limit: i64,
+    },
+    /// Print the effective configuration — defaults, file, environment,
+    /// and --set overrides merged — as JSON, with secrets redacted.
+    PrintEffectiveConfig,
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    aurum_telemetry::init(aurum_telemetry::TelemetryConfig::from_env(
+        "build-monitor",
+        "build_monitor=info,warn",
+    ))?;
+
+    let cli = Cli::parse();
+    let config = MonitorConfig::load_with(&cli.config, &cli.set)?;
+
+    match cli.command {
+        Command::Start { no_web } => {
+            // Fail on a fresh (or non-Linux dev) machine with one clear
+            // message instead of on the first build.
+            platform::preflight(&config)?;
+            let monitor = BuildMonitor::new(config).await?;
+            if !no_web {
+                let server = WebServer::new(monitor.clone());
+                println!(
+                    "Web dashboard available at http://{}:{}/",
+                    monitor.config.web.bind, monitor.config.web.port
+                );
+                tokio::spawn(async move {
+                    if let Err(e) = server.serve().await {
+                        tracing::error!("web server exited: {e:#}");
+                    }
+                });
+            }
+            // On ctrl-c, release the leader lease so a standby replica
+            // takes over immediately.
+            tokio::select! {
+                result = monitor.clone().run() => result,
+                _ = tokio::signal::ctrl_c() => {
+                    monitor.resign_leadership().await;
+                    Ok(())
+                }
+            }
+        }
+        Command::Status => {
+            if let Some(client) = daemon_client(&cli.endpoint, &cli.token, &config).await {
+                let statuses = client.status().await?;
+                println!("{}", serde_json::to_string_pretty(&statuses)?);
+                return Ok(());
+            }
+            let monitor = BuildMonitor::new(config).await?;
+            let statuses = monitor.service_statuses().await?;
+            println!("{}", serde_json::to_string_pretty(&statuses)?);
+            Ok(())
+        }
+        Command::Rollback {
+            service,
+            to_commit,
+            strategy,
+        } => {
+            if let Some(client) = daemon_client(&cli.endpoint, &cli.token, &config).await {
+                let result = client.rollback(&service, &to_commit, &strategy).await?;
+                println!("{}", serde_json::to_string_pretty(&result)?);
+                return Ok(());
+            }
+            let monitor = BuildMonitor::new(config).await?;
+            let svc = monitor
+                .config
+                .service(&service)
+                .cloned()
+                .ok_or_else(|| anyhow::anyhow!("unknown service {service}"))?;
+            let from = monitor
+                .database
+                .last_successful_commit(&service)
+                .await?
+                .unwrap_or_else(|| "unknown".to_string());
+            let result = monitor
+                .rollback
+                .perform_rollback(
+                    &svc,
+                    &from,
+                    &to_commit,
+                    RollbackStrategy::parse(&strategy),
+                    Some("manual rollback via CLI".to_string()),
+                )
+                .await?;
+            println!("{}", serde_json::to_string_pretty(&result)?);
+            Ok(())
+        }
+        Command::Bisect {
+            service,
+            good_commit,
+            bad_commit,
+        } => {
+            let monitor = BuildMonitor::new(config).await?;
+            let svc = monitor
+                .config
+                .service(&service)
+                .cloned()
+                .ok_or_else(|| anyhow::anyhow!("unknown service {service}"))?;
+            let commits = monitor.git.commits_between(&good_commit, &bad_commit)?;
+            let session = monitor
+                .bisect
+                .open_session(&service, &good_commit, &bad_commit)
+                .await?;
+            let session = monitor
+                .bisect
+                .run(&session, &commits, |candidate| {
+                    println!("testing {candidate}...");
+                    let built = monitor.git.test_build_at_commit(candidate, |checkout| {
+                        monitor.builder.build(&svc, checkout).map(|o| o.success)
+                    });
+                    Ok(match built {
+                        Ok(true) => bisect::CommitVerdict::Good,
+                        Ok(false) => bisect::CommitVerdict::Bad,
+                        Err(_) => bisect::CommitVerdict::Skipped,
+                    })
+                })
+                .await?;
+            println!("{}", serde_json::to_string_pretty(&session)?);
+            Ok(())
+        }
+        Command::Logs {
+            build_id,
+            tail,
+            follow,
+        } => {
+            let monitor = BuildMonitor::new(config).await?;
+            if follow {
+                loop {
+                    match monitor.database.build_by_id(build_id).await? {
+                        Some(b) if b.finished_at.is_none() => {
+                            tokio::time::sleep(std::time::Duration::from_secs(2)).await;
+                        }
+                        _ => break,
+                    }
+                }
+            }
+            let log = match tail {
+                Some(n) => monitor.logs.tail(build_id, n)?,
+                None => monitor.logs.read(build_id)?,
+            };
+            match log {
+                Some(log) => println!("{log}"),
+                None => eprintln!("no log captured for build {build_id}"),
+            }
+            Ok(())
+        }
+        Command::Pause {
+            service,
+            reason,
+            minutes,
+        } => {
+            // Pauses live in the daemon's memory, so there is no direct mode.
+            let client = daemon_client(&cli.endpoint, &cli.token, &config)
+                .await
+                .ok_or_else(|| anyhow::anyhow!("pausing requires a running daemon"))?;
+            let pause = client.pause(service.as_deref(), &reason, minutes).await?;
+            println!("{}", serde_json::to_string_pretty(&pause)?);
+            Ok(())
+        }
+        Command::Resume { service } => {
+            let client = daemon_client(&cli.endpoint, &cli.token, &config)
+                .await
+                .ok_or_else(|| anyhow::anyhow!("resuming requires a running daemon"))?;
+            let result = client.resume(service.as_deref()).await?;
+            println!("{}", serde_json::to_string_pretty(&result)?);
+            Ok(())
+        }
+        Command::Unfreeze { service } => {
+            if let Some(client) = daemon_client(&cli.endpoint, &cli.token, &config).await {
+                let result = client.unfreeze(&service).await?;
+                println!("{}", serde_json::to_string_pretty(&result)?);
+                return Ok(());
+            }
+            // Freezes are persisted, so a stopped daemon can be unfrozen
+            // directly against the database.
+            let monitor = BuildMonitor::new(config).await?;
+            let lifted = monitor.unfreeze(&service).await?;
+            println!("{}", serde_json::json!({ "unfrozen": lifted }));
+            Ok(())
+        }
+        Command::History { service, limit } => {
+            if let Some(client) = daemon_client(&cli.endpoint, &cli.token, &config).await {
+                let builds = client.history(service.as_deref(), limit).await?;
+                println!("{}", serde_json::to_string_pretty(&builds)?);
+                return Ok(());
+            }
+            let monitor = BuildMonitor::new(config).await?;
+            let builds = monitor
+                .database
+                .recent_builds(service.as_deref(), limit)
+                .await?;
+            println!("{}", serde_json::to_string_pretty(&builds)?);
+            Ok(())
+        }
+        Command::PrintEffectiveConfig => {
+            println!("{}", serde_json::to_string_pretty(&config)?);
+            Ok(())
+        }
+    }
+}
@@ -0,0 +1,173 @@
+//! Pause/resume controls and scheduled maintenance windows.
+//!
+//! While a service (or everything) is paused, the monitor keeps recording
+//! builds but suppresses failure alerts and automatic rollbacks, so a
+//! planned deployment doesn't page anyone or get "fixed" from under the
+//! operator.
+
+use chrono::{DateTime, Datelike, Timelike, Utc};
+use serde::{Deserialize, Serialize};
+use tokio::sync::RwLock;
+use uuid::Uuid;
+
+/// A recurring window during which failure handling is suppressed.
+/// Hours are UTC; a window wraps midnight when `end_hour < start_hour`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MaintenanceWindow {
+    /// Service the window applies to; `None` covers every service.
+    #[serde(default)]
+    pub service: Option<String>,
+    /// Lowercase three-letter weekdays ("sat", "sun"); empty = every day.
+    #[serde(default)]
+    pub days: Vec<String>,
+    pub start_hour: u32,
+    pub end_hour: u32,
+}
+
+impl MaintenanceWindow {
+    pub fn applies(&self, service: &str, now: DateTime<Utc>) -> bool {
+        if let Some(scoped) = &self.service {
+            if scoped != service {
+                return false;
+            }
+        }
+        if !self.days.is_empty() {
+            let day = now.weekday().to_string().to_lowercase();
+            if !self.days.iter().any(|d| d.eq_ignore_ascii_case(&day)) {
+                return false;
+            }
+        }
+        let hour = now.hour();
+        if self.start_hour <= self.end_hour {
+            hour >= self.start_hour && hour < self.end_hour
+        } else {
+            hour >= self.start_hour || hour < self.end_hour
+        }
+    }
+}
+
+/// An operator-requested pause, optionally expiring on its own.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Pause {
+    pub id: Uuid,
+    /// Paused service; `None` pauses everything.
+    pub service: Option<String>,
+    pub reason: String,
+    /// Auto-resume time; `None` lasts until an explicit resume.
+    pub until: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+pub struct Maintenance {
+    windows: Vec<MaintenanceWindow>,
+    pauses: RwLock<Vec<Pause>>,
+}
+
+impl Maintenance {
+    pub fn new(windows: Vec<MaintenanceWindow>) -> Self {
+        Self {
+            windows,
+            pauses: RwLock::new(Vec::new()),
+        }
+    }
+
+    pub async fn pause(
+        &self,
+        service: Option<String>,
+        reason: String,
+        until: Option<DateTime<Utc>>,
+    ) -> Pause {
+        let pause = Pause {
+            id: Uuid::new_v4(),
+            service,
+            reason,
+            until,
+            created_at: Utc::now(),
+        };
+        self.pauses.write().await.push(pause.clone());
+        pause
+    }
+
+    /// Lift pauses for the given scope; returns whether any were active.
+    pub async fn resume(&self, service: Option<&str>) -> bool {
+        let mut pauses = self.pauses.write().await;
+        let before = pauses.len();
+        pauses.retain(|p| p.service.as_deref() != service);
+        before != pauses.len()
+    }
+
+    /// Currently active pauses, dropping any that have expired.
+    pub async fn active_pauses(&self) -> Vec<Pause> {
+        let now = Utc::now();
+        let mut pauses = self.pauses.write().await;
+        pauses.retain(|p| p.until.is_none_or(|t| t > now));
+        pauses.clone()
+    }
+
+    /// Why failure handling is currently suppressed for `service`, if it is.
+    pub async fn paused_reason(&self, service: &str) -> Option<String> {
+        let now = Utc::now();
+        for pause in self.active_pauses().await {
+            if pause.service.is_none() || pause.service.as_deref() == Some(service) {
+                return Some(pause.reason);
+            }
+        }
+        self.windows
+            .iter()
+            .find(|w| w.applies(service, now))
+            .map(|_| "scheduled maintenance window".to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn window_matches_day_hours_and_scope() {
+        // 2026-08-29 is a Saturday.
+        let saturday_night = Utc.with_ymd_and_hms(2026, 8, 29, 23, 30, 0).unwrap();
+        let window = MaintenanceWindow {
+            service: None,
+            days: vec!["sat".into()],
+            start_hour: 22,
+            end_hour: 2,
+        };
+        assert!(window.applies("web", saturday_night));
+        // Wraps past midnight... but Sunday 01:00 is a different day.
+        let sunday_early = Utc.with_ymd_and_hms(2026, 8, 30, 1, 0, 0).unwrap();
+        assert!(!window.applies("web", sunday_early));
+
+        let scoped = MaintenanceWindow {
+            service: Some("ml-api".into()),
+            days: vec![],
+            start_hour: 0,
+            end_hour: 24,
+        };
+        assert!(scoped.applies("ml-api", saturday_night));
+        assert!(!scoped.applies("web", saturday_night));
+    }
+
+    #[tokio::test]
+    async fn pauses_expire_and_resume_by_scope() {
+        let maintenance = Maintenance::new(Vec::new());
+        maintenance
+            .pause(Some("web".into()), "deploying".into(), None)
+            .await;
+        maintenance
+            .pause(None, "expired".into(), Some(Utc::now() - chrono::Duration::minutes(1)))
+            .await;
+
+        // The expired global pause is gone; the scoped one holds.
+        assert_eq!(
+            maintenance.paused_reason("web").await.as_deref(),
+            Some("deploying")
+        );
+        assert_eq!(maintenance.paused_reason("ml-api").await, None);
+
+        assert!(maintenance.resume(Some("web")).await);
+        assert_eq!(maintenance.paused_reason("web").await, None);
+        assert!(!maintenance.resume(Some("web")).await);
+    }
+}
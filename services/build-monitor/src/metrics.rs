@@ -0,0 +1,116 @@
+//! Prometheus metrics for the monitor, served at `/metrics`.
+
+use crate::types::{BuildResult, BuildStatus};
+use anyhow::Result;
+use prometheus::{
+    Encoder, HistogramOpts, HistogramVec, IntCounterVec, IntGauge, Opts, Registry, TextEncoder,
+};
+
+pub struct MetricsCollector {
+    registry: Registry,
+    build_duration: HistogramVec,
+    builds_total: IntCounterVec,
+    build_failures: IntCounterVec,
+    queue_depth: IntGauge,
+    rollbacks_total: IntCounterVec,
+}
+
+impl MetricsCollector {
+    pub fn new() -> Result<Self> {
+        let registry = Registry::new();
+        let build_duration = HistogramVec::new(
+            HistogramOpts::new(
+                "build_monitor_build_duration_seconds",
+                "Wall-clock duration of service builds",
+            )
+            .buckets(vec![5.0, 15.0, 30.0, 60.0, 120.0, 300.0, 600.0, 1800.0]),
+            &["service"],
+        )?;
+        let builds_total = IntCounterVec::new(
+            Opts::new("build_monitor_builds_total", "Builds by final status"),
+            &["service", "status"],
+        )?;
+        let build_failures = IntCounterVec::new(
+            Opts::new(
+                "build_monitor_build_failures_total",
+                "Builds that ended failed or timed out",
+            ),
+            &["service"],
+        )?;
+        let queue_depth = IntGauge::new(
+            "build_monitor_queue_depth",
+            "Services still waiting to build in the current poll cycle",
+        )?;
+        let rollbacks_total = IntCounterVec::new(
+            Opts::new("build_monitor_rollbacks_total", "Rollbacks by final status"),
+            &["service", "status"],
+        )?;
+        registry.register(Box::new(build_duration.clone()))?;
+        registry.register(Box::new(builds_total.clone()))?;
+        registry.register(Box::new(build_failures.clone()))?;
+        registry.register(Box::new(queue_depth.clone()))?;
+        registry.register(Box::new(rollbacks_total.clone()))?;
+        Ok(Self {
+            registry,
+            build_duration,
+            builds_total,
+            build_failures,
+            queue_depth,
+            rollbacks_total,
+        })
+    }
+
+    pub fn observe_build(&self, build: &BuildResult) {
+        if let Some(duration_ms) = build.duration_ms {
+            self.build_duration
+                .with_label_values(&[&build.service])
+                .observe(duration_ms as f64 / 1000.0);
+        }
+        self.builds_total
+            .with_label_values(&[&build.service, build.status.as_str()])
+            .inc();
+        if matches!(build.status, BuildStatus::Failed | BuildStatus::TimedOut) {
+            self.build_failures
+                .with_label_values(&[&build.service])
+                .inc();
+        }
+    }
+
+    pub fn observe_rollback(&self, service: &str, status: &str) {
+        self.rollbacks_total
+            .with_label_values(&[service, status])
+            .inc();
+    }
+
+    pub fn set_queue_depth(&self, depth: usize) {
+        self.queue_depth.set(depth as i64);
+    }
+
+    /// Render everything in the Prometheus text exposition format.
+    pub fn gather(&self) -> Result<String> {
+        let mut buf = Vec::new();
+        TextEncoder::new().encode(&self.registry.gather(), &mut buf)?;
+        Ok(String::from_utf8(buf)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn builds_and_rollbacks_show_up_in_exposition() {
+        let metrics = MetricsCollector::new().unwrap();
+        let mut build = BuildResult::started("web", "abc");
+        build.finish(BuildStatus::Failed, Some("boom".into()));
+        metrics.observe_build(&build);
+        metrics.observe_rollback("web", "completed");
+        metrics.set_queue_depth(3);
+
+        let text = metrics.gather().unwrap();
+        assert!(text.contains("build_monitor_builds_total{service=\"web\",status=\"failed\"} 1"));
+        assert!(text.contains("build_monitor_build_failures_total{service=\"web\"} 1"));
+        assert!(text.contains("build_monitor_queue_depth 3"));
+        assert!(text.contains("build_monitor_rollbacks_total{service=\"web\",status=\"completed\"} 1"));
+    }
+}
@@ -0,0 +1,1118 @@
+//! The main monitoring loop: poll for commits, build affected services,
+//! track health, and trigger rollbacks on repeated failures.
+
+use crate::artifacts::ArtifactStore;
+use crate::auth::Authenticator;
+use crate::config::{MonitorConfig, ServiceConfig};
+use crate::database::Database;
+use crate::bisect::{BisectEngine, CommitVerdict};
+use crate::builder::ServiceBuilder;
+use crate::docker::DockerManager;
+use crate::events::{EventBus, MonitorEvent};
+use crate::git::GitMonitor;
+use crate::github::GithubChecks;
+use crate::graph::ServiceGraph;
+use crate::healing::HealingClient;
+use crate::logs::LogStore;
+use crate::maintenance::Maintenance;
+use crate::metrics::MetricsCollector;
+use crate::notifications::{NotificationKind, NotificationManager};
+use crate::probe::{HealthProber, ProbeState};
+use crate::rollback::{RollbackManager, RollbackStrategy};
+use crate::types::{BuildResult, BuildStatus, ServiceHealth, ServiceStatus, Severity};
+use crate::watchdog::Watchdog;
+use anyhow::Result;
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::sync::RwLock;
+use tracing::{error, info, warn};
+
+pub struct BuildMonitor {
+    pub config: MonitorConfig,
+    pub database: Database,
+    pub git: GitMonitor,
+    pub docker: DockerManager,
+    pub builder: ServiceBuilder,
+    pub bisect: BisectEngine,
+    pub notifications: NotificationManager,
+    pub rollback: RollbackManager,
+    pub graph: ServiceGraph,
+    pub events: EventBus,
+    pub logs: LogStore,
+    pub metrics: Arc<MetricsCollector>,
+    pub maintenance: Maintenance,
+    pub auth: Authenticator,
+    pub artifacts: ArtifactStore,
+    github: GithubChecks,
+    healing: HealingClient,
+    /// Drives filed issues through the repro → patch → validation loop,
+    /// when configured.
+    orchestrator: Option<crate::orchestrator::Orchestrator>,
+    leader: Arc<crate::leader::LeaderElector>,
+    watchdog: Watchdog,
+    prober: HealthProber,
+    health: RwLock<HashMap<String, ProbeState>>,
+    last_probe: RwLock<HashMap<String, std::time::Instant>>,
+    last_seen_head: RwLock<Option<String>>,
+    last_summary: RwLock<Option<std::time::Instant>>,
+    watched_heads: RwLock<HashMap<String, String>>,
+}
+
+impl BuildMonitor {
+    pub async fn new(mut config: MonitorConfig) -> Result<Arc<Self>> {
+        // Under compose, dependency ordering defaults from the compose
+        // file for services that don't declare their own.
+        let compose = config.compose.clone().map(crate::compose::ComposeDriver::new);
+        if let Some(driver) = &compose {
+            match driver.load() {
+                Ok(spec) => {
+                    let monitored: Vec<String> =
+                        config.services.iter().map(|s| s.name.clone()).collect();
+                    for service in &mut config.services {
+                        match spec.get(&service.name) {
+                            None => warn!(
+                                service = %service.name,
+                                "service has no entry in the compose file"
+                            ),
+                            Some(entry) => {
+                                info!(
+                                    service = %service.name,
+                                    image = ?entry.image,
+                                    "mapped to compose entry"
+                                );
+                                if matches!(service.probe.kind, crate::config::ProbeKind::Docker)
+                                    && !entry.has_healthcheck
+                                {
+                                    warn!(
+                                        service = %service.name,
+                                        "docker probe configured but compose entry has no healthcheck"
+                                    );
+                                }
+                                if service.depends_on.is_empty() {
+                                    service.depends_on = entry
+                                        .depends_on
+                                        .iter()
+                                        .filter(|d| monitored.contains(d))
+                                        .cloned()
+                                        .collect();
+                                }
+                            }
+                        }
+                    }
+                }
+                Err(e) => warn!("failed to load compose file: {e:#}"),
+            }
+        }
+        let database = Database::open(&config.database_path).await?;
+        let git = GitMonitor::new(&config.repo_path, &config.branch);
+        let notifications =
+            NotificationManager::new(config.notifications.clone()).with_database(database.clone());
+        info!(channels = ?notifications.channel_names(), "notification channels registered");
+        let docker = DockerManager::with_registry(config.registry.clone())
+            .with_cache(config.cache.clone())
+            .with_compose(compose);
+        let metrics = Arc::new(MetricsCollector::new()?);
+        let rollback = RollbackManager::new(
+            config.rollback.clone(),
+            config.services.clone(),
+            database.clone(),
+            docker.clone(),
+        )
+        .with_metrics(metrics.clone());
+        let logs = LogStore::new(config.log_dir.clone())?;
+        let graph = ServiceGraph::build(&config.services)?;
+        Ok(Arc::new(Self {
+            graph,
+            logs,
+            git,
+            builder: ServiceBuilder::with_cache(docker.clone(), config.cache.clone()),
+            docker,
+            bisect: BisectEngine::new(database.clone()),
+            notifications,
+            rollback,
+            events: EventBus::new(),
+            metrics,
+            maintenance: Maintenance::new(config.maintenance.clone()),
+            auth: Authenticator::new(config.web.auth.clone()),
+            artifacts: ArtifactStore::new(config.artifacts.clone(), database.clone()),
+            github: GithubChecks::new(config.github.clone()),
+            healing: HealingClient::new(config.healing.clone()),
+            orchestrator: crate::orchestrator::Orchestrator::from_config(config.healing.as_ref()),
+            leader: Arc::new(crate::leader::LeaderElector::new(
+                database.clone(),
+                config.election.enabled,
+                config.election.lease_ttl_secs,
+            )),
+            watchdog: Watchdog::new(config.watchdog.clone()),
+            prober: HealthProber::new(),
+            health: RwLock::new(HashMap::new()),
+            last_probe: RwLock::new(HashMap::new()),
+            last_seen_head: RwLock::new(None),
+            last_summary: RwLock::new(None),
+            watched_heads: RwLock::new(HashMap::new()),
+            database,
+            config,
+        }))
+    }
+
+    /// Whether this replica holds the monitor lease; followers only serve
+    /// the read API.
+    pub fn is_leader(&self) -> bool {
+        self.leader.is_leader()
+    }
+
+    /// Release the monitor lease; called on clean shutdown.
+    pub async fn resign_leadership(&self) {
+        self.leader.resign().await;
+    }
+
+    /// Run the poll loop until the process is stopped.
+    pub async fn run(self: Arc<Self>) -> Result<()> {
+        self.leader.try_acquire().await;
+        self.leader.spawn();
+        let interval = std::time::Duration::from_secs(self.config.poll_interval_secs);
+        let mut cycles: u64 = 0;
+        loop {
+            // Standby replicas keep the dashboard alive but leave building,
+            // probing, and rollbacks to the lease holder.
+            if !self.is_leader() {
+                tokio::time::sleep(interval).await;
+                continue;
+            }
+            if let Err(e) = self.check_host_resources().await {
+                warn!("resource watchdog failed: {e:#}");
+            }
+            if let Err(e) = self.poll_once().await {
+                error!("poll cycle failed: {e:#}");
+            }
+            if let Err(e) = self.poll_watched_refs().await {
+                error!("watched-ref cycle failed: {e:#}");
+            }
+            if let Err(e) = self.check_service_health().await {
+                error!("health check cycle failed: {e:#}");
+            }
+            if let Err(e) = self.maybe_send_weekly_summary().await {
+                warn!("weekly summary failed: {e:#}");
+            }
+            self.notifications.flush_digests().await;
+            cycles += 1;
+            // Prune dangling images roughly hourly so monitor builds don't
+            // fill the disk.
+            if cycles.is_multiple_of(60) {
+                if let Err(e) = self.docker.cleanup_resources() {
+                    warn!("docker cleanup failed: {e:#}");
+                }
+                for service in &self.config.services {
+                    if let Err(e) = self
+                        .docker
+                        .enforce_retention(&service.name, &self.config.retention)
+                    {
+                        warn!(service = %service.name, "image retention failed: {e:#}");
+                    }
+                }
+            }
+            tokio::time::sleep(interval).await;
+        }
+    }
+
+    /// One poll cycle: detect new commits and build affected services.
+    pub async fn poll_once(&self) -> Result<()> {
+        let head = self.git.fetch_head()?;
+        let previous = self.last_seen_head.read().await.clone();
+        let commits = match previous {
+            Some(prev) if prev != head => self.git.commits_between(&prev, &head)?,
+            Some(_) => return Ok(()),
+            None => vec![head.clone()],
+        };
+        *self.last_seen_head.write().await = Some(head);
+
+        for commit in commits {
+            // Collect directly affected services, cascade to everything
+            // downstream, and build in dependency order.
+            let mut affected: Vec<String> = Vec::new();
+            for service in &self.config.services {
+                if self.should_build_service(service, &commit)? {
+                    affected.push(service.name.clone());
+                    affected.extend(self.graph.dependents_of(&service.name));
+                }
+            }
+            affected.sort();
+            affected.dedup();
+            let order = self.graph.topo_order(affected)?;
+            let mut remaining = order.len();
+            self.metrics.set_queue_depth(remaining);
+            for name in order {
+                if let Some(service) = self.config.service(&name) {
+                    if let Some(reason) = self.frozen_reason(&name).await {
+                        info!(
+                            service = %name,
+                            reason,
+                            "skipping build: service is frozen until explicitly unfrozen"
+                        );
+                    } else
+                    // Merges and reverts produce commits whose service
+                    // content is identical to something already built;
+                    // the tree-hash cache skips those outright.
+                    if let Some(prior) = self.cached_success(service, &commit).await? {
+                        info!(
+                            service = %name,
+                            commit,
+                            prior,
+                            "skipping build: identical tree already built successfully"
+                        );
+                    } else {
+                        self.build_service(service, &commit).await?;
+                    }
+                }
+                remaining -= 1;
+                self.metrics.set_queue_depth(remaining);
+            }
+        }
+        Ok(())
+    }
+
+    /// Build new heads of watched branches and PRs in isolated worktrees.
+    /// These builds record results and publish events but never retry,
+    /// page, or roll anything back.
+    pub async fn poll_watched_refs(&self) -> Result<()> {
+        let watch = &self.config.watch;
+        if watch.branches.is_empty() && !watch.prs {
+            return Ok(());
+        }
+        for (ref_name, head) in self.git.watched_refs(&watch.branches, watch.prs)? {
+            {
+                let mut heads = self.watched_heads.write().await;
+                if heads.get(&ref_name).is_some_and(|h| h == &head) {
+                    continue;
+                }
+                heads.insert(ref_name.clone(), head.clone());
+            }
+            self.build_ref(&ref_name, &head).await?;
+        }
+        Ok(())
+    }
+
+    async fn build_ref(&self, ref_name: &str, head: &str) -> Result<()> {
+        info!(r#ref = ref_name, commit = head, "building watched ref");
+        for service in &self.config.services {
+            let mut build = BuildResult::started_on(&service.name, head, ref_name);
+            self.database.record_build(&build).await?;
+            self.events.publish(MonitorEvent::BuildStarted {
+                service: service.name.clone(),
+                commit: head.to_string(),
+            });
+            if self.github.enabled() {
+                self.github.report_started(&service.name, head).await;
+            }
+            let outcome = self
+                .git
+                .test_build_at_commit(head, |checkout| self.builder.build(service, checkout))
+                .unwrap_or_else(|e| crate::docker::BuildOutcome {
+                    success: false,
+                    timed_out: false,
+                    log: format!("build error: {e:#}"),
+                });
+            if let Err(e) = self.logs.store(build.id, &outcome.log) {
+                warn!(service = %service.name, "failed to persist build log: {e:#}");
+            }
+            let status = if outcome.success {
+                BuildStatus::Success
+            } else if outcome.timed_out {
+                BuildStatus::TimedOut
+            } else {
+                BuildStatus::Failed
+            };
+            build.finish(status, (!outcome.success).then(|| "build failed".to_string()));
+            self.database.record_build(&build).await?;
+            self.metrics.observe_build(&build);
+            self.events.publish(if outcome.success {
+                MonitorEvent::BuildSucceeded { build: build.clone() }
+            } else {
+                MonitorEvent::BuildFailed { build: build.clone() }
+            });
+            if self.github.enabled() {
+                self.github.report_finished(&build, &outcome.log).await;
+            }
+        }
+        Ok(())
+    }
+
+    /// Paths that determine a service's build content: its own declared
+    /// paths plus those of everything it depends on, since a dependent
+    /// rebuilds against dependency changes its own tree never shows.
+    /// Empty means "the whole tree" — any involved service without
+    /// declared paths widens the key to that.
+    fn cache_paths(&self, service: &ServiceConfig) -> Vec<String> {
+        let mut paths = Vec::new();
+        let mut queue = vec![service.name.clone()];
+        let mut seen = std::collections::HashSet::new();
+        while let Some(name) = queue.pop() {
+            if !seen.insert(name.clone()) {
+                continue;
+            }
+            let Some(svc) = self.config.service(&name) else {
+                continue;
+            };
+            if svc.paths.is_empty() {
+                return Vec::new();
+            }
+            paths.extend(svc.paths.iter().cloned());
+            queue.extend(svc.depends_on.iter().cloned());
+        }
+        paths.sort();
+        paths.dedup();
+        paths
+    }
+
+    /// The commit a prior successful build of identical content ran at,
+    /// if the cache has one. Tree-hash failures just miss the cache.
+    async fn cached_success(&self, service: &ServiceConfig, commit: &str) -> Result<Option<String>> {
+        let hash = match self.git.tree_hash(commit, &self.cache_paths(service)) {
+            Ok(hash) => hash,
+            Err(e) => {
+                warn!(service = %service.name, "failed to compute tree hash: {e:#}");
+                return Ok(None);
+            }
+        };
+        Ok(self
+            .database
+            .cached_build_result(&service.name, &hash)
+            .await?
+            .and_then(|(prior, success)| success.then_some(prior)))
+    }
+
+    /// Why a service is frozen, or `None` when it isn't. A database error
+    /// degrades to "not frozen" with a warning rather than stalling the
+    /// poll loop.
+    async fn frozen_reason(&self, service: &str) -> Option<String> {
+        match self.database.frozen_reason(service).await {
+            Ok(reason) => reason,
+            Err(e) => {
+                warn!(service, "freeze lookup failed: {e:#}");
+                None
+            }
+        }
+    }
+
+    /// Rollback ping-pong guard: once a service has been rolled back the
+    /// configured number of times inside the window, freeze it so nothing
+    /// automatic rebuilds or re-deploys the same broken state. Only an
+    /// explicit unfreeze lifts it.
+    async fn maybe_freeze(&self, service: &ServiceConfig) -> Result<()> {
+        let freeze = &self.config.rollback.freeze;
+        if !freeze.enabled {
+            return Ok(());
+        }
+        let cutoff = chrono::Utc::now() - chrono::Duration::hours(freeze.window_hours);
+        let recent = self.database.rollbacks_since(&service.name, cutoff).await?;
+        if recent < i64::from(freeze.max_rollbacks)
+            || self.database.frozen_reason(&service.name).await?.is_some()
+        {
+            return Ok(());
+        }
+        let reason = format!(
+            "{recent} rollbacks in the last {} hours",
+            freeze.window_hours
+        );
+        self.database.freeze_service(&service.name, &reason).await?;
+        self.database
+            .record_alert(
+                Severity::Critical,
+                Some(&service.name),
+                &format!("service frozen: {reason}"),
+            )
+            .await?;
+        let base = format!(
+            "http://{}:{}/api/services/{}/unfreeze",
+            self.config.web.bind, self.config.web.port, service.name
+        );
+        self.notifications
+            .notify(
+                NotificationKind::Rollback,
+                Severity::Critical,
+                Some(&service.name),
+                &format!(
+                    "service FROZEN after {reason}; automatic builds and deploys are blocked until POST {base}"
+                ),
+            )
+            .await;
+        Ok(())
+    }
+
+    /// Lift an emergency freeze; returns whether one was active.
+    pub async fn unfreeze(&self, service: &str) -> Result<bool> {
+        let lifted = self.database.unfreeze_service(service).await?;
+        if lifted {
+            self.database
+                .record_alert(
+                    Severity::Info,
+                    Some(service),
+                    "freeze lifted; automatic builds and deploys resume",
+                )
+                .await?;
+            info!(service, "freeze lifted");
+        }
+        Ok(lifted)
+    }
+
+    /// A service builds when the commit touches one of its declared paths
+    /// (or when it declares no paths at all).
+    pub fn should_build_service(&self, service: &ServiceConfig, commit: &str) -> Result<bool> {
+        if service.paths.is_empty() {
+            return Ok(true);
+        }
+        let changed = self.git.changed_files(commit)?;
+        Ok(changed
+            .iter()
+            .any(|f| service.paths.iter().any(|p| f.starts_with(p.as_str()))))
+    }
+
+    pub async fn build_service(&self, service: &ServiceConfig, commit: &str) -> Result<BuildResult> {
+        let mut build = BuildResult::started(&service.name, commit);
+        self.database.record_build(&build).await?;
+        self.events.publish(MonitorEvent::BuildStarted {
+            service: service.name.clone(),
+            commit: commit.to_string(),
+        });
+        if self.github.enabled() {
+            self.github.report_started(&service.name, commit).await;
+        }
+
+        // Retry failed builds before classifying them: transient registry
+        // and network hiccups dominate our failure logs.
+        let max_attempts = 1 + self.config.flaky.max_retries;
+        let mut outcome;
+        loop {
+            outcome = self
+                .builder
+                .build(service, self.git.repo_path())
+                .unwrap_or_else(|e| {
+                    warn!(service = %service.name, "build errored: {e:#}");
+                    crate::docker::BuildOutcome {
+                        success: false,
+                        timed_out: false,
+                        log: format!("build error: {e:#}"),
+                    }
+                });
+            // Retrying a hung build would just hang again for the full
+            // timeout; give up immediately.
+            if outcome.success || outcome.timed_out || build.attempts >= max_attempts {
+                break;
+            }
+            build.attempts += 1;
+            info!(service = %service.name, attempt = build.attempts, "retrying failed build");
+        }
+
+        // Matrix variants run after the regular build, one attempt each:
+        // the regular build still produces the deployable artifact, and a
+        // failing required variant fails the service even when that build
+        // passed. Optional variants only raise a warning.
+        for variant in &service.matrix {
+            let started = std::time::Instant::now();
+            let result = self
+                .builder
+                .build_variant(service, Some(variant), self.git.repo_path())
+                .unwrap_or_else(|e| {
+                    warn!(service = %service.name, variant = %variant.name, "variant errored: {e:#}");
+                    crate::docker::BuildOutcome {
+                        success: false,
+                        timed_out: false,
+                        log: format!("build error: {e:#}"),
+                    }
+                });
+            let status = if result.success {
+                BuildStatus::Success
+            } else if result.timed_out {
+                BuildStatus::TimedOut
+            } else {
+                BuildStatus::Failed
+            };
+            build.variants.push(crate::types::VariantStatus {
+                name: variant.name.clone(),
+                status,
+                duration_ms: started.elapsed().as_millis() as i64,
+                required: variant.required,
+            });
+            outcome.log.push_str(&format!("\n=== variant {} ===\n", variant.name));
+            outcome.log.push_str(&result.log);
+            if !result.success {
+                if variant.required {
+                    outcome.success = false;
+                    outcome.timed_out |= result.timed_out;
+                } else {
+                    self.database
+                        .record_alert(
+                            Severity::Warning,
+                            Some(&service.name),
+                            &format!("optional build variant {} failed", variant.name),
+                        )
+                        .await?;
+                }
+            }
+        }
+
+        if let Err(e) = self.logs.store(build.id, &outcome.log) {
+            warn!(service = %service.name, "failed to persist build log: {e:#}");
+        }
+
+        if outcome.success {
+            if build.attempts > 1 {
+                self.database
+                    .record_alert(
+                        Severity::Info,
+                        Some(&service.name),
+                        &format!("build passed after {} attempts (possible flake)", build.attempts),
+                    )
+                    .await?;
+            }
+            build.finish(BuildStatus::Success, None);
+            self.database.record_build(&build).await?;
+            self.metrics.observe_build(&build);
+            if matches!(service.build_strategy, crate::config::BuildStrategy::Docker) {
+                if let Err(e) = self.docker.publish_build(&service.name, commit) {
+                    warn!(service = %service.name, "failed to publish image: {e:#}");
+                }
+                let tag = self.docker.commit_tag(&service.name, commit);
+                let digest = self.docker.image_digest(&tag).ok().flatten();
+                if let Err(e) = self.artifacts.record_image(&build, &tag, digest).await {
+                    warn!(service = %service.name, "failed to record image artifact: {e:#}");
+                }
+            } else if let Some(artifact_path) = &service.artifact_path {
+                let path = self.git.repo_path().join(artifact_path);
+                if let Err(e) = self.artifacts.record_binary(&build, &path).await {
+                    warn!(service = %service.name, "failed to record binary artifact: {e:#}");
+                }
+            }
+            self.events.publish(MonitorEvent::BuildSucceeded {
+                build: build.clone(),
+            });
+        } else {
+            if outcome.timed_out {
+                build.finish(BuildStatus::TimedOut, Some("build timed out".into()));
+            } else {
+                build.finish(BuildStatus::Failed, Some("docker build failed".into()));
+            }
+            self.database.record_build(&build).await?;
+            self.metrics.observe_build(&build);
+            self.events.publish(MonitorEvent::BuildFailed {
+                build: build.clone(),
+            });
+            if let Some(reason) = self.maintenance.paused_reason(&service.name).await {
+                // Planned work in progress: keep the record but don't page
+                // anyone or roll anything back.
+                info!(service = %service.name, reason, "suppressing failure handling (paused)");
+                self.database
+                    .record_alert(
+                        Severity::Info,
+                        Some(&service.name),
+                        &format!("build failure suppressed (paused: {reason})"),
+                    )
+                    .await?;
+                if self.github.enabled() {
+                    self.github.report_finished(&build, &outcome.log).await;
+                }
+                return Ok(build);
+            }
+            let flakiness = self
+                .database
+                .flakiness_score(&service.name, self.config.flaky.window)
+                .await?;
+            if flakiness >= self.config.flaky.flaky_threshold {
+                // Known-flaky service: don't page anyone or roll back over
+                // what is most likely noise; leave a trace instead.
+                info!(
+                    service = %service.name,
+                    flakiness,
+                    "suppressing failure handling for known-flaky service"
+                );
+                self.database
+                    .record_alert(
+                        Severity::Info,
+                        Some(&service.name),
+                        &format!("build failure suppressed (flakiness score {flakiness:.2})"),
+                    )
+                    .await?;
+            } else {
+                self.handle_build_failure(service, commit, &outcome.log).await?;
+            }
+        }
+        if self.github.enabled() {
+            self.github.report_finished(&build, &outcome.log).await;
+        }
+        match self.git.tree_hash(commit, &self.cache_paths(service)) {
+            Ok(hash) => {
+                if let Err(e) = self
+                    .database
+                    .record_build_cache(&service.name, &hash, commit, outcome.success)
+                    .await
+                {
+                    warn!(service = %service.name, "failed to record build cache: {e:#}");
+                }
+            }
+            Err(e) => warn!(service = %service.name, "failed to compute tree hash: {e:#}"),
+        }
+        Ok(build)
+    }
+
+    async fn handle_build_failure(
+        &self,
+        service: &ServiceConfig,
+        commit: &str,
+        log: &str,
+    ) -> Result<()> {
+        let failures = self.database.consecutive_failures(&service.name).await?;
+        let summary = self
+            .git
+            .commit_summary(commit)
+            .unwrap_or_else(|_| commit[..commit.len().min(8)].to_string());
+        self.notifications
+            .notify(
+                NotificationKind::BuildFailure,
+                Severity::Warning,
+                Some(&service.name),
+                &format!("build failed at {summary} ({failures} consecutive)"),
+            )
+            .await;
+
+        // Repeated code failures (not infrastructure flakes) go to the
+        // self-healing system for a patch attempt.
+        if self.healing.enabled() && failures >= self.healing.failure_threshold() {
+            let class = crate::healing::classify(log);
+            if matches!(
+                class,
+                crate::healing::FailureClass::Compiler | crate::healing::FailureClass::Test
+            ) {
+                let files = self.git.changed_files(commit).unwrap_or_default();
+                match self
+                    .healing
+                    .file_issue(&service.name, commit, class, log, &files)
+                    .await
+                {
+                    Ok(issue_id) => {
+                        self.database
+                            .record_alert(
+                                Severity::Info,
+                                Some(&service.name),
+                                &format!(
+                                    "filed {} failure with self-healing system",
+                                    class.as_str()
+                                ),
+                            )
+                            .await?;
+                        // Drive the closed loop in the background: repro
+                        // synthesis, patch, validation, and the repro gate.
+                        if let (Some(orchestrator), Some(issue_id)) =
+                            (&self.orchestrator, issue_id)
+                        {
+                            let orchestrator = orchestrator.clone();
+                            let database = self.database.clone();
+                            let service_name = service.name.clone();
+                            tokio::spawn(async move {
+                                match orchestrator.run(&issue_id).await {
+                                    Ok(outcome) => {
+                                        let (severity, verdict) = if outcome.resolved {
+                                            (Severity::Info, "resolved")
+                                        } else {
+                                            (Severity::Warning, "unresolved")
+                                        };
+                                        let _ = database
+                                            .record_alert(
+                                                severity,
+                                                Some(&service_name),
+                                                &format!(
+                                                    "healing loop finished {verdict} for issue {issue_id}"
+                                                ),
+                                            )
+                                            .await;
+                                    }
+                                    Err(e) => {
+                                        warn!(service = %service_name, "healing loop failed: {e:#}")
+                                    }
+                                }
+                            });
+                        }
+                    }
+                    Err(e) => warn!(service = %service.name, "self-healing handoff failed: {e:#}"),
+                }
+            }
+        }
+
+        if let Some(first_bad) = self.isolate_failure(service, commit).await? {
+            self.database
+                .record_alert(
+                    Severity::Warning,
+                    Some(&service.name),
+                    &format!("first failing commit isolated: {first_bad}"),
+                )
+                .await?;
+        }
+
+        if self.rollback.auto_rollback_enabled() && failures >= self.rollback.failure_threshold() {
+            if let Some(good) = self.find_last_good_commit(&service.name).await? {
+                let conflicts = self.rollback_conflicts(service, &good).await?;
+                if !conflicts.is_empty() {
+                    warn!(
+                        service = %service.name,
+                        ?conflicts,
+                        "skipping automatic rollback: dependents were built against newer code"
+                    );
+                    self.database
+                        .record_alert(
+                            Severity::Warning,
+                            Some(&service.name),
+                            &format!(
+                                "rollback to {good} blocked: incompatible with {}",
+                                conflicts.join(", ")
+                            ),
+                        )
+                        .await?;
+                    return Ok(());
+                }
+                let span = self.git.commits_between(&good, commit).map(|c| c.len()).unwrap_or(usize::MAX);
+                if self.rollback.needs_approval(span) {
+                    let pending = self
+                        .rollback
+                        .create_pending(
+                            service,
+                            commit,
+                            &good,
+                            RollbackStrategy::Immediate,
+                            Some(format!("{failures} consecutive build failures")),
+                        )
+                        .await?;
+                    let base = format!(
+                        "http://{}:{}/api/rollbacks/{}",
+                        self.config.web.bind, self.config.web.port, pending.id
+                    );
+                    self.notifications
+                        .notify(
+                            NotificationKind::Rollback,
+                            Severity::Critical,
+                            Some(&service.name),
+                            &format!(
+                                "rollback of {span} commits awaits approval: POST {base}/approve or {base}/reject"
+                            ),
+                        )
+                        .await;
+                    return Ok(());
+                }
+                info!(service = %service.name, to = %good, "failure threshold reached, rolling back");
+                self.events.publish(MonitorEvent::RollbackStarted {
+                    service: service.name.clone(),
+                    from_commit: commit.to_string(),
+                    to_commit: good.clone(),
+                });
+                let result = self
+                    .rollback
+                    .perform_rollback(
+                        service,
+                        commit,
+                        &good,
+                        RollbackStrategy::Immediate,
+                        Some(format!("{failures} consecutive build failures")),
+                    )
+                    .await;
+                let success = result.is_ok();
+                self.events.publish(MonitorEvent::RollbackCompleted {
+                    service: service.name.clone(),
+                    to_commit: good,
+                    success,
+                });
+                self.notifications
+                    .notify(
+                        NotificationKind::Rollback,
+                        Severity::Critical,
+                        Some(&service.name),
+                        if success {
+                            "automatic rollback completed"
+                        } else {
+                            "automatic rollback FAILED"
+                        },
+                    )
+                    .await;
+                self.maybe_freeze(service).await?;
+            } else {
+                warn!(service = %service.name, "no known-good commit to roll back to");
+            }
+        }
+        Ok(())
+    }
+
+    /// Bisect between the last known-good commit and the failing commit to
+    /// find the first commit that breaks the service's build. The session is
+    /// persisted, so an interrupted bisect resumes on the next failure.
+    async fn isolate_failure(&self, service: &ServiceConfig, bad: &str) -> Result<Option<String>> {
+        let Some(good) = self.database.last_successful_commit(&service.name).await? else {
+            return Ok(None);
+        };
+        let commits = self.git.commits_between(&good, bad)?;
+        if commits.len() < 2 {
+            return Ok(commits.into_iter().next());
+        }
+        let session = self.bisect.open_session(&service.name, &good, bad).await?;
+        // Resolve candidates with identical service trees from the cache
+        // instead of rebuilding them; merges and reverts inside the range
+        // come for free. Fresh verdicts feed the cache afterwards.
+        let paths = self.cache_paths(service);
+        let mut cached: HashMap<String, bool> = HashMap::new();
+        for candidate in &commits {
+            if let Ok(hash) = self.git.tree_hash(candidate, &paths) {
+                if let Some((_, success)) = self
+                    .database
+                    .cached_build_result(&service.name, &hash)
+                    .await?
+                {
+                    cached.insert(candidate.clone(), success);
+                }
+            }
+        }
+        let mut fresh: Vec<(String, bool)> = Vec::new();
+        let session = self
+            .bisect
+            .run(&session, &commits, |candidate| {
+                if let Some(&success) = cached.get(candidate) {
+                    return Ok(if success {
+                        CommitVerdict::Good
+                    } else {
+                        CommitVerdict::Bad
+                    });
+                }
+                let built = self.git.test_build_at_commit(candidate, |checkout| {
+                    self.builder.build(service, checkout).map(|o| o.success)
+                });
+                Ok(match built {
+                    Ok(success) => {
+                        fresh.push((candidate.to_string(), success));
+                        if success {
+                            CommitVerdict::Good
+                        } else {
+                            CommitVerdict::Bad
+                        }
+                    }
+                    // Checkout/build-infrastructure errors: untestable.
+                    Err(_) => CommitVerdict::Skipped,
+                })
+            })
+            .await?;
+        for (candidate, success) in fresh {
+            if let Ok(hash) = self.git.tree_hash(&candidate, &paths) {
+                if let Err(e) = self
+                    .database
+                    .record_build_cache(&service.name, &hash, &candidate, success)
+                    .await
+                {
+                    warn!(service = %service.name, "failed to record build cache: {e:#}");
+                }
+            }
+        }
+        Ok(session.first_bad)
+    }
+
+    /// The most recent commit with a successful recorded build, falling back
+    /// to rebuilding history when the database is empty.
+    pub async fn find_last_good_commit(&self, service: &str) -> Result<Option<String>> {
+        if let Some(commit) = self.database.last_successful_commit(service).await? {
+            return Ok(Some(commit));
+        }
+        let Some(svc) = self.config.service(service) else {
+            return Ok(None);
+        };
+        let head = self.git.fetch_head()?;
+        // A candidate only counts as known-good when deployment records show
+        // it actually ran; with no records at all (fresh database) any
+        // candidate passes through to the rebuild check.
+        let deployed: std::collections::HashSet<String> = self
+            .database
+            .deployed_commits(service)
+            .await?
+            .into_iter()
+            .collect();
+        self.git.find_last_good_commit(&head, 25, |candidate| {
+            if !deployed.is_empty() && !deployed.contains(candidate) {
+                return Ok(false);
+            }
+            self.git
+                .test_build_at_commit(candidate, |checkout| {
+                self.builder.build(svc, checkout).map(|o| o.success)
+            })
+        })
+    }
+
+    /// Dependents that would be left running against a newer version of
+    /// `service` than `to_commit` provides. A dependent conflicts when its
+    /// currently deployed commit is ahead of the rollback target and the
+    /// commits in between touch this service's paths.
+    pub async fn rollback_conflicts(
+        &self,
+        service: &ServiceConfig,
+        to_commit: &str,
+    ) -> Result<Vec<String>> {
+        let mut conflicts = Vec::new();
+        for dependent in self.graph.dependents_of(&service.name) {
+            let Some(deployment) = self
+                .database
+                .deployment_history(&dependent, 1)
+                .await?
+                .into_iter()
+                .next()
+            else {
+                continue;
+            };
+            let Ok(span) = self.git.commits_between(to_commit, &deployment.commit) else {
+                continue;
+            };
+            let touches_service = span.iter().any(|c| {
+                self.git
+                    .changed_files(c)
+                    .map(|files| {
+                        files
+                            .iter()
+                            .any(|f| service.paths.iter().any(|p| f.starts_with(p.as_str())))
+                    })
+                    .unwrap_or(false)
+            });
+            if touches_service {
+                conflicts.push(dependent);
+            }
+        }
+        Ok(conflicts)
+    }
+
+    /// Probe every service due for a probe and publish transitions. Health
+    /// only flips after the configured success/failure streaks.
+    pub async fn check_service_health(&self) -> Result<()> {
+        for service in &self.config.services {
+            if let Some(interval) = service.probe.interval_secs {
+                let mut last = self.last_probe.write().await;
+                let now = std::time::Instant::now();
+                match last.get(&service.name) {
+                    Some(t) if now.duration_since(*t).as_secs() < interval => continue,
+                    _ => {
+                        last.insert(service.name.clone(), now);
+                    }
+                }
+            }
+            let ok = self.prober.probe(service).await;
+            let (previous, current) = {
+                let mut map = self.health.write().await;
+                let state = map.entry(service.name.clone()).or_default();
+                let previous = state.health;
+                (previous, state.observe(ok, &service.probe))
+            };
+
+            if previous != current {
+                self.events.publish(MonitorEvent::HealthChanged {
+                    service: service.name.clone(),
+                    previous,
+                    current,
+                });
+                if current == ServiceHealth::Down {
+                    if let Some(reason) = self.maintenance.paused_reason(&service.name).await {
+                        info!(service = %service.name, reason, "suppressing down alert (paused)");
+                        continue;
+                    }
+                    self.database
+                        .record_alert(Severity::Critical, Some(&service.name), "service is down")
+                        .await?;
+                    self.notifications
+                        .notify(
+                            NotificationKind::ServiceDown,
+                            Severity::Critical,
+                            Some(&service.name),
+                            "service is down",
+                        )
+                        .await;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Sample host resources, warn before builds start failing on them,
+    /// and force image cleanup when disk gets critical.
+    async fn check_host_resources(&self) -> Result<()> {
+        let snapshot = self.watchdog.snapshot()?;
+        for issue in crate::watchdog::evaluate(&snapshot, self.watchdog.config()) {
+            if self.watchdog.should_warn(&issue).await {
+                self.database
+                    .record_alert(Severity::Warning, None, &issue.message())
+                    .await?;
+                self.notifications
+                    .notify(NotificationKind::System, Severity::Warning, None, &issue.message())
+                    .await;
+            }
+        }
+        if snapshot.free_disk_mb < self.watchdog.config().cleanup_free_disk_mb {
+            warn!(free_mb = snapshot.free_disk_mb, "disk critical, forcing image cleanup");
+            if let Err(e) = self.docker.cleanup_resources() {
+                warn!("forced cleanup failed: {e:#}");
+            }
+            for service in &self.config.services {
+                if let Err(e) = self
+                    .docker
+                    .enforce_retention(&service.name, &self.config.retention)
+                {
+                    warn!(service = %service.name, "forced retention failed: {e:#}");
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Send the analytics summary once a week of uptime has passed. The
+    /// clock starts at the first poll cycle, so a restart never re-sends.
+    async fn maybe_send_weekly_summary(&self) -> Result<()> {
+        const WEEK_SECS: u64 = 7 * 24 * 3600;
+        {
+            let mut last = self.last_summary.write().await;
+            match *last {
+                None => {
+                    *last = Some(std::time::Instant::now());
+                    return Ok(());
+                }
+                Some(t) if t.elapsed().as_secs() < WEEK_SECS => return Ok(()),
+                _ => *last = Some(std::time::Instant::now()),
+            }
+        }
+        let report = crate::analytics::report(&self.database, &self.config.services, 7).await?;
+        self.notifications
+            .notify(
+                NotificationKind::System,
+                Severity::Info,
+                None,
+                &crate::analytics::summarize(&report),
+            )
+            .await;
+        Ok(())
+    }
+
+    /// Dashboard snapshot for all configured services.
+    pub async fn service_statuses(&self) -> Result<Vec<ServiceStatus>> {
+        let health: HashMap<String, ServiceHealth> = self
+            .health
+            .read()
+            .await
+            .iter()
+            .map(|(name, state)| (name.clone(), state.health))
+            .collect();
+        let mut statuses = Vec::with_capacity(self.config.services.len());
+        for service in &self.config.services {
+            let builds = self.database.recent_builds(Some(&service.name), 1).await?;
+            statuses.push(ServiceStatus {
+                name: service.name.clone(),
+                health: health
+                    .get(&service.name)
+                    .copied()
+                    .unwrap_or(ServiceHealth::Unknown),
+                last_build: builds.into_iter().next(),
+                last_good_commit: self.database.last_successful_commit(&service.name).await?,
+                consecutive_failures: self.database.consecutive_failures(&service.name).await?,
+                flakiness_score: self
+                    .database
+                    .flakiness_score(&service.name, self.config.flaky.window)
+                    .await?,
+            });
+        }
+        Ok(statuses)
+    }
+}
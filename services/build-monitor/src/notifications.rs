@@ -0,0 +1,1085 @@
+//! Outbound notifications for build and rollback events.
+//!
+//! Channels implement [`NotificationChannel`] and are registered on the
+//! [`NotificationManager`] from [`NotificationConfig`]. The legacy
+//! `SLACK_WEBHOOK_URL` / `DISCORD_WEBHOOK_URL` environment variables are
+//! still honored when the corresponding channel is absent from config.
+
+use crate::config::NotificationConfig;
+use crate::types::Severity;
+use anyhow::Result;
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use tracing::{info, warn};
+
+/// Category of event a notification describes, used by routing rules.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NotificationKind {
+    BuildFailure,
+    BuildSuccess,
+    Rollback,
+    ServiceDown,
+    System,
+}
+
+/// A single notification to be delivered to matching channels.
+#[derive(Debug, Clone)]
+pub struct Notification {
+    pub kind: NotificationKind,
+    pub severity: Severity,
+    pub service: Option<String>,
+    pub message: String,
+}
+
+impl Notification {
+    /// One-line rendering used by text-oriented channels.
+    pub fn summary(&self) -> String {
+        match &self.service {
+            Some(s) => format!("[{}] {s}: {}", self.severity.as_str(), self.message),
+            None => format!("[{}] {}", self.severity.as_str(), self.message),
+        }
+    }
+}
+
+/// A delivery backend (Slack, PagerDuty, ...).
+#[async_trait]
+pub trait NotificationChannel: Send + Sync {
+    /// Stable channel name used in logs and delivery records.
+    fn name(&self) -> &str;
+
+    async fn send(&self, notification: &Notification) -> Result<()>;
+}
+
+// ---------------------------------------------------------------------------
+// Routing
+// ---------------------------------------------------------------------------
+
+/// Per-channel routing rules. A channel without routing receives everything.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ChannelRouting {
+    /// Only deliver notifications at or above this severity.
+    #[serde(default)]
+    pub min_severity: Option<Severity>,
+    /// Only deliver these event kinds; empty means all kinds.
+    #[serde(default)]
+    pub kinds: Vec<NotificationKind>,
+    /// Service-name globs (e.g. `face-*`); empty means all services.
+    /// Notifications without a service always pass this filter.
+    #[serde(default)]
+    pub services: Vec<String>,
+    /// Suppress non-critical notifications during these hours (UTC).
+    #[serde(default)]
+    pub quiet_hours: Option<QuietHours>,
+    /// Batch non-critical notifications into periodic summaries instead
+    /// of delivering each one; critical notifications still go straight
+    /// through.
+    #[serde(default)]
+    pub digest: Option<DigestInterval>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DigestInterval {
+    Hourly,
+    Daily,
+}
+
+impl DigestInterval {
+    fn period(&self) -> std::time::Duration {
+        match self {
+            DigestInterval::Hourly => std::time::Duration::from_secs(3600),
+            DigestInterval::Daily => std::time::Duration::from_secs(24 * 3600),
+        }
+    }
+
+    fn as_str(&self) -> &'static str {
+        match self {
+            DigestInterval::Hourly => "hourly",
+            DigestInterval::Daily => "daily",
+        }
+    }
+}
+
+/// Suppression of repeated notifications. A signature (kind, service,
+/// message with numbers normalized away) that repeats inside the window
+/// is counted rather than re-delivered, with one escalation once the
+/// count shows the problem is not going away.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DedupConfig {
+    #[serde(default = "default_window_minutes")]
+    pub window_minutes: u64,
+    /// The occurrence count that triggers the escalation notification.
+    #[serde(default = "default_escalate_after")]
+    pub escalate_after: u32,
+}
+
+impl Default for DedupConfig {
+    fn default() -> Self {
+        Self {
+            window_minutes: default_window_minutes(),
+            escalate_after: default_escalate_after(),
+        }
+    }
+}
+
+fn default_window_minutes() -> u64 {
+    30
+}
+
+fn default_escalate_after() -> u32 {
+    5
+}
+
+/// An hour range in UTC; `start == end` means the window is empty, and
+/// ranges may wrap around midnight (e.g. 22..6).
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct QuietHours {
+    pub start_hour: u8,
+    pub end_hour: u8,
+}
+
+impl QuietHours {
+    fn contains(&self, hour: u8) -> bool {
+        if self.start_hour <= self.end_hour {
+            (self.start_hour..self.end_hour).contains(&hour)
+        } else {
+            hour >= self.start_hour || hour < self.end_hour
+        }
+    }
+}
+
+impl ChannelRouting {
+    /// Whether a notification should be delivered at the given UTC hour.
+    pub fn allows(&self, n: &Notification, hour: u8) -> bool {
+        if let Some(min) = self.min_severity {
+            if n.severity < min {
+                return false;
+            }
+        }
+        if !self.kinds.is_empty() && !self.kinds.contains(&n.kind) {
+            return false;
+        }
+        if !self.services.is_empty() {
+            if let Some(service) = &n.service {
+                let matched = self.services.iter().any(|pattern| {
+                    glob::Pattern::new(pattern)
+                        .map(|p| p.matches(service))
+                        .unwrap_or(false)
+                });
+                if !matched {
+                    return false;
+                }
+            }
+        }
+        if let Some(quiet) = &self.quiet_hours {
+            if n.severity < Severity::Critical && quiet.contains(hour) {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Channel configuration (embedded in NotificationConfig)
+// ---------------------------------------------------------------------------
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SlackChannelConfig {
+    pub webhook_url: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DiscordChannelConfig {
+    pub webhook_url: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EmailChannelConfig {
+    /// Recipient addresses, comma-separated.
+    pub to: String,
+    /// SMTP settings; when absent, `SMTP_HOST`/`SMTP_FROM` and friends
+    /// are consulted, and without those the channel only logs.
+    #[serde(default)]
+    pub smtp: Option<aurum_notify::SmtpConfig>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TelegramChannelConfig {
+    pub bot_token: String,
+    pub chat_id: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PagerDutyChannelConfig {
+    /// Events v2 integration (routing) key.
+    pub routing_key: String,
+}
+
+// ---------------------------------------------------------------------------
+// Channel implementations
+// ---------------------------------------------------------------------------
+
+struct SlackChannel {
+    config: SlackChannelConfig,
+    client: reqwest::Client,
+}
+
+#[async_trait]
+impl NotificationChannel for SlackChannel {
+    fn name(&self) -> &str {
+        "slack"
+    }
+
+    async fn send(&self, n: &Notification) -> Result<()> {
+        self.client
+            .post(&self.config.webhook_url)
+            .json(&json!({ "text": n.summary() }))
+            .send()
+            .await?
+            .error_for_status()?;
+        Ok(())
+    }
+}
+
+struct DiscordChannel {
+    config: DiscordChannelConfig,
+    client: reqwest::Client,
+}
+
+#[async_trait]
+impl NotificationChannel for DiscordChannel {
+    fn name(&self) -> &str {
+        "discord"
+    }
+
+    async fn send(&self, n: &Notification) -> Result<()> {
+        self.client
+            .post(&self.config.webhook_url)
+            .json(&json!({ "content": n.summary() }))
+            .send()
+            .await?
+            .error_for_status()?;
+        Ok(())
+    }
+}
+
+struct EmailChannel {
+    recipients: Vec<String>,
+    mailer: Option<aurum_notify::Mailer>,
+}
+
+impl EmailChannel {
+    fn new(config: EmailChannelConfig) -> Self {
+        let smtp = config.smtp.or_else(aurum_notify::SmtpConfig::from_env);
+        let mailer = smtp.and_then(|smtp| match aurum_notify::Mailer::new(&smtp) {
+            Ok(mailer) => Some(mailer),
+            Err(e) => {
+                warn!("email channel falling back to logging, bad smtp config: {e:#}");
+                None
+            }
+        });
+        Self {
+            recipients: config
+                .to
+                .split(',')
+                .map(|s| s.trim().to_string())
+                .filter(|s| !s.is_empty())
+                .collect(),
+            mailer,
+        }
+    }
+}
+
+#[async_trait]
+impl NotificationChannel for EmailChannel {
+    fn name(&self) -> &str {
+        "email"
+    }
+
+    async fn send(&self, n: &Notification) -> Result<()> {
+        let Some(mailer) = &self.mailer else {
+            // No SMTP configured; log so the alert is at least visible in
+            // the journal.
+            info!(to = ?self.recipients, "email notification: {}", n.summary());
+            return Ok(());
+        };
+        let kind = serde_json::to_string(&n.kind)
+            .unwrap_or_default()
+            .trim_matches('"')
+            .to_string();
+        let service = n.service.as_deref().unwrap_or("-");
+        let message = aurum_notify::EmailMessage::render(
+            &format!("[build-monitor] {}", n.summary()),
+            "build-monitor raised an alert.",
+            &[
+                ("kind", &kind),
+                ("severity", n.severity.as_str()),
+                ("service", service),
+            ],
+            &n.message,
+        );
+        // Repeats of the same event within the batch window are counted
+        // into the next email instead of sent individually.
+        let key = format!("{kind}/{service}");
+        mailer
+            .send_deduplicated(&self.recipients, &key, &message)
+            .await?;
+        Ok(())
+    }
+}
+
+struct TelegramChannel {
+    config: TelegramChannelConfig,
+    client: reqwest::Client,
+}
+
+#[async_trait]
+impl NotificationChannel for TelegramChannel {
+    fn name(&self) -> &str {
+        "telegram"
+    }
+
+    async fn send(&self, n: &Notification) -> Result<()> {
+        let url = format!(
+            "https://api.telegram.org/bot{}/sendMessage",
+            self.config.bot_token
+        );
+        self.client
+            .post(&url)
+            .json(&json!({
+                "chat_id": self.config.chat_id,
+                "text": n.summary(),
+            }))
+            .send()
+            .await?
+            .error_for_status()?;
+        Ok(())
+    }
+}
+
+struct PagerDutyChannel {
+    config: PagerDutyChannelConfig,
+    client: reqwest::Client,
+}
+
+impl PagerDutyChannel {
+    fn pd_severity(severity: Severity) -> &'static str {
+        match severity {
+            Severity::Info => "info",
+            Severity::Warning => "warning",
+            Severity::Critical => "critical",
+        }
+    }
+}
+
+#[async_trait]
+impl NotificationChannel for PagerDutyChannel {
+    fn name(&self) -> &str {
+        "pagerduty"
+    }
+
+    async fn send(&self, n: &Notification) -> Result<()> {
+        self.client
+            .post("https://events.pagerduty.com/v2/enqueue")
+            .json(&json!({
+                "routing_key": self.config.routing_key,
+                "event_action": "trigger",
+                "dedup_key": format!(
+                    "build-monitor/{}/{}",
+                    n.service.as_deref().unwrap_or("global"),
+                    n.message
+                ),
+                "payload": {
+                    "summary": n.summary(),
+                    "source": n.service.as_deref().unwrap_or("build-monitor"),
+                    "severity": Self::pd_severity(n.severity),
+                    "component": n.service,
+                },
+            }))
+            .send()
+            .await?
+            .error_for_status()?;
+        Ok(())
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Delivery audit log
+// ---------------------------------------------------------------------------
+
+/// Outcome of attempting to deliver one notification to one channel.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DeliveryStatus {
+    Delivered,
+    /// All retries exhausted; kept as a dead letter for inspection.
+    DeadLetter,
+}
+
+impl DeliveryStatus {
+    fn as_str(&self) -> &'static str {
+        match self {
+            DeliveryStatus::Delivered => "delivered",
+            DeliveryStatus::DeadLetter => "dead_letter",
+        }
+    }
+
+    fn parse(s: &str) -> Self {
+        match s {
+            "delivered" => DeliveryStatus::Delivered,
+            _ => DeliveryStatus::DeadLetter,
+        }
+    }
+}
+
+/// One row of the delivery audit log.
+#[derive(Debug, Clone, Serialize)]
+pub struct DeliveryRecord {
+    pub id: uuid::Uuid,
+    pub channel: String,
+    pub kind: NotificationKind,
+    pub severity: Severity,
+    pub service: Option<String>,
+    pub message: String,
+    pub status: DeliveryStatus,
+    pub attempts: u32,
+    pub last_error: Option<String>,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+}
+
+/// Retry behaviour for webhook deliveries.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RetryPolicy {
+    #[serde(default = "default_max_attempts")]
+    pub max_attempts: u32,
+    #[serde(default = "default_base_delay_ms")]
+    pub base_delay_ms: u64,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: default_max_attempts(),
+            base_delay_ms: default_base_delay_ms(),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Exponential backoff delay before the given retry (1-based).
+    pub fn delay(&self, attempt: u32) -> std::time::Duration {
+        let factor = 1u64 << attempt.min(6);
+        std::time::Duration::from_millis(self.base_delay_ms.saturating_mul(factor))
+    }
+}
+
+fn default_max_attempts() -> u32 {
+    3
+}
+
+fn default_base_delay_ms() -> u64 {
+    500
+}
+
+// ---------------------------------------------------------------------------
+// Manager
+// ---------------------------------------------------------------------------
+
+pub struct NotificationManager {
+    enabled: bool,
+    channels: Vec<Box<dyn NotificationChannel>>,
+    routing: std::collections::HashMap<String, ChannelRouting>,
+    retry: RetryPolicy,
+    dedup: DedupConfig,
+    dedup_state: std::sync::Mutex<std::collections::HashMap<String, DedupState>>,
+    digests: std::sync::Mutex<std::collections::HashMap<String, DigestBuffer>>,
+    database: Option<crate::database::Database>,
+}
+
+struct DedupState {
+    first_seen: std::time::Instant,
+    count: u32,
+}
+
+/// Pending digest entries for one channel, aggregated by summary line.
+struct DigestBuffer {
+    interval: DigestInterval,
+    since: std::time::Instant,
+    entries: Vec<(String, u32)>,
+    total: u32,
+    max_severity: Severity,
+}
+
+enum DedupVerdict {
+    Deliver,
+    Suppress,
+    Escalate { count: u32 },
+}
+
+impl NotificationManager {
+    pub fn new(config: NotificationConfig) -> Self {
+        let client = reqwest::Client::new();
+        let mut channels: Vec<Box<dyn NotificationChannel>> = Vec::new();
+
+        let slack = config.slack.clone().or_else(|| {
+            std::env::var("SLACK_WEBHOOK_URL")
+                .ok()
+                .map(|webhook_url| SlackChannelConfig { webhook_url })
+        });
+        if let Some(slack) = slack {
+            channels.push(Box::new(SlackChannel {
+                config: slack,
+                client: client.clone(),
+            }));
+        }
+
+        let discord = config.discord.clone().or_else(|| {
+            std::env::var("DISCORD_WEBHOOK_URL")
+                .ok()
+                .map(|webhook_url| DiscordChannelConfig { webhook_url })
+        });
+        if let Some(discord) = discord {
+            channels.push(Box::new(DiscordChannel {
+                config: discord,
+                client: client.clone(),
+            }));
+        }
+
+        let email = config.email.clone().or_else(|| {
+            std::env::var("ALERT_EMAIL")
+                .ok()
+                .map(|to| EmailChannelConfig { to, smtp: None })
+        });
+        if let Some(email) = email {
+            channels.push(Box::new(EmailChannel::new(email)));
+        }
+
+        if let Some(telegram) = config.telegram.clone() {
+            channels.push(Box::new(TelegramChannel {
+                config: telegram,
+                client: client.clone(),
+            }));
+        }
+
+        if let Some(pagerduty) = config.pagerduty.clone() {
+            channels.push(Box::new(PagerDutyChannel {
+                config: pagerduty,
+                client,
+            }));
+        }
+
+        Self {
+            enabled: config.enabled,
+            channels,
+            routing: config.routing.clone(),
+            retry: config.retry.clone(),
+            dedup: config.dedup.clone(),
+            dedup_state: std::sync::Mutex::new(std::collections::HashMap::new()),
+            digests: std::sync::Mutex::new(std::collections::HashMap::new()),
+            database: None,
+        }
+    }
+
+    /// Enable the delivery audit log and dead-letter store.
+    pub fn with_database(mut self, database: crate::database::Database) -> Self {
+        self.database = Some(database);
+        self
+    }
+
+    /// Registered channel names, mostly for diagnostics.
+    pub fn channel_names(&self) -> Vec<&str> {
+        self.channels.iter().map(|c| c.name()).collect()
+    }
+
+    /// Fan a notification out to every channel whose routing rules match.
+    /// Delivery errors are logged and swallowed; monitoring must not stall
+    /// on a webhook.
+    pub async fn notify(
+        &self,
+        kind: NotificationKind,
+        severity: Severity,
+        service: Option<&str>,
+        message: &str,
+    ) {
+        if !self.enabled {
+            return;
+        }
+        let mut notification = Notification {
+            kind,
+            severity,
+            service: service.map(|s| s.to_string()),
+            message: message.to_string(),
+        };
+        match self.dedup_verdict(&notification) {
+            DedupVerdict::Deliver => {}
+            DedupVerdict::Suppress => {
+                info!(service, message, "suppressing repeated notification");
+                return;
+            }
+            DedupVerdict::Escalate { count } => {
+                notification.severity = Severity::Critical;
+                notification.message = format!(
+                    "{message} ({count} occurrences in {} minutes)",
+                    self.dedup.window_minutes
+                );
+            }
+        }
+        use chrono::Timelike;
+        let hour = chrono::Utc::now().hour() as u8;
+        for channel in &self.channels {
+            if let Some(routing) = self.routing.get(channel.name()) {
+                if !routing.allows(&notification, hour) {
+                    continue;
+                }
+                if let Some(interval) = routing.digest {
+                    if notification.severity < Severity::Critical {
+                        self.buffer_digest(channel.name(), interval, &notification);
+                        continue;
+                    }
+                }
+            }
+            self.deliver_with_retry(channel.as_ref(), &notification).await;
+        }
+    }
+
+    /// Classify a notification against the suppression window. The first
+    /// occurrence of a signature delivers, the `escalate_after`th inside
+    /// the window escalates, and everything else is suppressed; once the
+    /// window lapses the signature starts over.
+    fn dedup_verdict(&self, n: &Notification) -> DedupVerdict {
+        let window = std::time::Duration::from_secs(self.dedup.window_minutes * 60);
+        let now = std::time::Instant::now();
+        let mut state = self.dedup_state.lock().expect("dedup lock poisoned");
+        state.retain(|_, s| now.duration_since(s.first_seen) < window);
+        let entry = state.entry(signature(n)).or_insert(DedupState {
+            first_seen: now,
+            count: 0,
+        });
+        entry.count += 1;
+        if entry.count == 1 {
+            DedupVerdict::Deliver
+        } else if entry.count == self.dedup.escalate_after {
+            DedupVerdict::Escalate { count: entry.count }
+        } else {
+            DedupVerdict::Suppress
+        }
+    }
+
+    fn buffer_digest(&self, channel: &str, interval: DigestInterval, n: &Notification) {
+        let mut digests = self.digests.lock().expect("digest lock poisoned");
+        let buffer = digests
+            .entry(channel.to_string())
+            .or_insert_with(|| DigestBuffer {
+                interval,
+                since: std::time::Instant::now(),
+                entries: Vec::new(),
+                total: 0,
+                max_severity: n.severity,
+            });
+        buffer.total += 1;
+        if n.severity > buffer.max_severity {
+            buffer.max_severity = n.severity;
+        }
+        let summary = n.summary();
+        match buffer.entries.iter_mut().find(|(line, _)| line == &summary) {
+            Some((_, count)) => *count += 1,
+            None => buffer.entries.push((summary, 1)),
+        }
+    }
+
+    /// Deliver digest summaries whose interval has elapsed; the monitor
+    /// loop calls this once per poll cycle.
+    pub async fn flush_digests(&self) {
+        self.flush_digests_where(|buffer| buffer.since.elapsed() >= buffer.interval.period())
+            .await;
+    }
+
+    async fn flush_digests_where(&self, is_due: impl Fn(&DigestBuffer) -> bool) {
+        let due: Vec<(String, Notification)> = {
+            let mut digests = self.digests.lock().expect("digest lock poisoned");
+            let mut due = Vec::new();
+            digests.retain(|channel, buffer| {
+                if !is_due(buffer) {
+                    return true;
+                }
+                let mut message = format!(
+                    "{} digest: {} notifications\n",
+                    buffer.interval.as_str(),
+                    buffer.total
+                );
+                for (line, count) in &buffer.entries {
+                    if *count > 1 {
+                        message.push_str(&format!("- {line} (x{count})\n"));
+                    } else {
+                        message.push_str(&format!("- {line}\n"));
+                    }
+                }
+                due.push((
+                    channel.clone(),
+                    Notification {
+                        kind: NotificationKind::System,
+                        severity: buffer.max_severity,
+                        service: None,
+                        message: message.trim_end().to_string(),
+                    },
+                ));
+                false
+            });
+            due
+        };
+        for (name, notification) in due {
+            if let Some(channel) = self.channels.iter().find(|c| c.name() == name) {
+                self.deliver_with_retry(channel.as_ref(), &notification).await;
+            }
+        }
+    }
+
+    /// Attempt delivery with exponential backoff; exhausted deliveries land
+    /// in the dead-letter log instead of being silently dropped.
+    async fn deliver_with_retry(&self, channel: &dyn NotificationChannel, n: &Notification) {
+        let mut attempts = 0;
+        let mut last_error = None;
+        let status = loop {
+            attempts += 1;
+            match channel.send(n).await {
+                Ok(()) => break DeliveryStatus::Delivered,
+                Err(e) => {
+                    warn!(
+                        channel = channel.name(),
+                        attempt = attempts,
+                        "notification delivery failed: {e:#}"
+                    );
+                    last_error = Some(format!("{e:#}"));
+                    if attempts >= self.retry.max_attempts {
+                        break DeliveryStatus::DeadLetter;
+                    }
+                    tokio::time::sleep(self.retry.delay(attempts)).await;
+                }
+            }
+        };
+        self.record_delivery(channel.name(), n, status, attempts, last_error)
+            .await;
+    }
+
+    async fn record_delivery(
+        &self,
+        channel: &str,
+        n: &Notification,
+        status: DeliveryStatus,
+        attempts: u32,
+        last_error: Option<String>,
+    ) {
+        let Some(database) = &self.database else {
+            return;
+        };
+        let result = sqlx::query(
+            r#"
+            INSERT INTO notification_deliveries
+                (id, channel, kind, severity, service, message, status, attempts, last_error, created_at)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)
+            "#,
+        )
+        .bind(uuid::Uuid::new_v4().to_string())
+        .bind(channel)
+        .bind(serde_json::to_string(&n.kind).unwrap_or_default().trim_matches('"').to_string())
+        .bind(n.severity.as_str())
+        .bind(&n.service)
+        .bind(&n.message)
+        .bind(status.as_str())
+        .bind(attempts as i64)
+        .bind(&last_error)
+        .bind(chrono::Utc::now().to_rfc3339())
+        .execute(database.pool())
+        .await;
+        if let Err(e) = result {
+            warn!("failed to record notification delivery: {e:#}");
+        }
+    }
+
+    /// Recent delivery attempts, newest first.
+    pub async fn recent_deliveries(&self, limit: i64) -> Result<Vec<DeliveryRecord>> {
+        use sqlx::Row;
+        let Some(database) = &self.database else {
+            return Ok(Vec::new());
+        };
+        let rows = sqlx::query(
+            "SELECT * FROM notification_deliveries ORDER BY created_at DESC LIMIT ?1",
+        )
+        .bind(limit)
+        .fetch_all(database.pool())
+        .await?;
+        rows.into_iter()
+            .map(|row| {
+                let id: String = row.get("id");
+                let kind: String = row.get("kind");
+                let severity: String = row.get("severity");
+                let status: String = row.get("status");
+                let created_at: String = row.get("created_at");
+                Ok(DeliveryRecord {
+                    id: uuid::Uuid::parse_str(&id)?,
+                    channel: row.get("channel"),
+                    kind: serde_json::from_str(&format!("\"{kind}\""))?,
+                    severity: serde_json::from_str(&format!("\"{severity}\""))?,
+                    service: row.get("service"),
+                    message: row.get("message"),
+                    status: DeliveryStatus::parse(&status),
+                    attempts: row.get::<i64, _>("attempts") as u32,
+                    last_error: row.get("last_error"),
+                    created_at: chrono::DateTime::parse_from_rfc3339(&created_at)?
+                        .with_timezone(&chrono::Utc),
+                })
+            })
+            .collect()
+    }
+}
+
+/// Dedup signature: kind, service, and the message with digit runs
+/// collapsed, so "build failed (3 consecutive)" and "(4 consecutive)"
+/// count as the same failure.
+fn signature(n: &Notification) -> String {
+    let mut normalized = String::with_capacity(n.message.len());
+    let mut in_digits = false;
+    for c in n.message.chars() {
+        if c.is_ascii_digit() {
+            if !in_digits {
+                normalized.push('#');
+            }
+            in_digits = true;
+        } else {
+            normalized.push(c);
+            in_digits = false;
+        }
+    }
+    format!(
+        "{:?}/{}/{normalized}",
+        n.kind,
+        n.service.as_deref().unwrap_or("-")
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn channels_built_from_config() {
+        let config = NotificationConfig {
+            enabled: true,
+            slack: Some(SlackChannelConfig {
+                webhook_url: "https://hooks.slack.example/x".into(),
+            }),
+            telegram: Some(TelegramChannelConfig {
+                bot_token: "t".into(),
+                chat_id: "c".into(),
+            }),
+            pagerduty: Some(PagerDutyChannelConfig {
+                routing_key: "rk".into(),
+            }),
+            ..Default::default()
+        };
+        let manager = NotificationManager::new(config);
+        let names = manager.channel_names();
+        assert!(names.contains(&"slack"));
+        assert!(names.contains(&"telegram"));
+        assert!(names.contains(&"pagerduty"));
+    }
+
+    fn notification(kind: NotificationKind, severity: Severity, service: &str) -> Notification {
+        Notification {
+            kind,
+            severity,
+            service: Some(service.into()),
+            message: "m".into(),
+        }
+    }
+
+    #[test]
+    fn routing_filters_by_severity_kind_and_service_glob() {
+        let routing = ChannelRouting {
+            min_severity: Some(Severity::Warning),
+            kinds: vec![NotificationKind::BuildFailure, NotificationKind::Rollback],
+            services: vec!["face-*".into()],
+            quiet_hours: None,
+            digest: None,
+        };
+        let n = notification(NotificationKind::BuildFailure, Severity::Warning, "face-detection");
+        assert!(routing.allows(&n, 12));
+        let n = notification(NotificationKind::BuildSuccess, Severity::Warning, "face-detection");
+        assert!(!routing.allows(&n, 12));
+        let n = notification(NotificationKind::BuildFailure, Severity::Info, "face-detection");
+        assert!(!routing.allows(&n, 12));
+        let n = notification(NotificationKind::BuildFailure, Severity::Warning, "ml-api");
+        assert!(!routing.allows(&n, 12));
+    }
+
+    #[test]
+    fn quiet_hours_suppress_non_critical_and_wrap_midnight() {
+        let routing = ChannelRouting {
+            quiet_hours: Some(QuietHours {
+                start_hour: 22,
+                end_hour: 6,
+            }),
+            ..Default::default()
+        };
+        let warn = notification(NotificationKind::BuildFailure, Severity::Warning, "web");
+        let crit = notification(NotificationKind::ServiceDown, Severity::Critical, "web");
+        assert!(!routing.allows(&warn, 23));
+        assert!(!routing.allows(&warn, 3));
+        assert!(routing.allows(&warn, 12));
+        assert!(routing.allows(&crit, 23));
+    }
+
+    #[test]
+    fn retry_policy_backs_off_exponentially() {
+        let policy = RetryPolicy {
+            max_attempts: 4,
+            base_delay_ms: 100,
+        };
+        assert_eq!(policy.delay(1).as_millis(), 200);
+        assert_eq!(policy.delay(2).as_millis(), 400);
+        assert_eq!(policy.delay(3).as_millis(), 800);
+    }
+
+    #[tokio::test]
+    async fn failed_deliveries_land_in_dead_letter_log() {
+        struct FailingChannel;
+        #[async_trait]
+        impl NotificationChannel for FailingChannel {
+            fn name(&self) -> &str {
+                "failing"
+            }
+            async fn send(&self, _n: &Notification) -> Result<()> {
+                anyhow::bail!("connection refused")
+            }
+        }
+
+        let db = crate::database::Database::open_in_memory().await.unwrap();
+        let mut manager = NotificationManager::new(NotificationConfig {
+            enabled: true,
+            retry: RetryPolicy {
+                max_attempts: 2,
+                base_delay_ms: 1,
+            },
+            ..Default::default()
+        })
+        .with_database(db);
+        manager.channels.push(Box::new(FailingChannel));
+
+        manager
+            .notify(NotificationKind::BuildFailure, Severity::Warning, Some("web"), "boom")
+            .await;
+
+        let deliveries = manager.recent_deliveries(10).await.unwrap();
+        assert_eq!(deliveries.len(), 1);
+        assert_eq!(deliveries[0].status, DeliveryStatus::DeadLetter);
+        assert_eq!(deliveries[0].attempts, 2);
+        assert_eq!(deliveries[0].last_error.as_deref(), Some("connection refused"));
+    }
+
+    struct RecordingChannel {
+        name: &'static str,
+        sent: std::sync::Arc<std::sync::Mutex<Vec<Notification>>>,
+    }
+
+    #[async_trait]
+    impl NotificationChannel for RecordingChannel {
+        fn name(&self) -> &str {
+            self.name
+        }
+        async fn send(&self, n: &Notification) -> Result<()> {
+            self.sent.lock().unwrap().push(n.clone());
+            Ok(())
+        }
+    }
+
+    fn recording_manager(config: NotificationConfig) -> (NotificationManager, std::sync::Arc<std::sync::Mutex<Vec<Notification>>>) {
+        let sent = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+        let mut manager = NotificationManager::new(config);
+        manager.channels.push(Box::new(RecordingChannel {
+            name: "rec",
+            sent: sent.clone(),
+        }));
+        (manager, sent)
+    }
+
+    #[tokio::test]
+    async fn repeats_are_suppressed_and_escalate_once() {
+        let (manager, sent) = recording_manager(NotificationConfig {
+            enabled: true,
+            dedup: DedupConfig {
+                window_minutes: 30,
+                escalate_after: 3,
+            },
+            ..Default::default()
+        });
+        // Varying counters normalize into one signature.
+        for failures in 1..=4 {
+            manager
+                .notify(
+                    NotificationKind::BuildFailure,
+                    Severity::Warning,
+                    Some("web"),
+                    &format!("build failed at abc feat ({failures} consecutive)"),
+                )
+                .await;
+        }
+        let sent = sent.lock().unwrap();
+        assert_eq!(sent.len(), 2);
+        assert_eq!(sent[0].severity, Severity::Warning);
+        // The third occurrence escalated; the fourth was suppressed.
+        assert_eq!(sent[1].severity, Severity::Critical);
+        assert!(sent[1].message.contains("3 occurrences in 30 minutes"));
+    }
+
+    #[tokio::test]
+    async fn digest_channels_batch_until_flushed() {
+        let mut routing = std::collections::HashMap::new();
+        routing.insert(
+            "rec".to_string(),
+            ChannelRouting {
+                digest: Some(DigestInterval::Hourly),
+                ..Default::default()
+            },
+        );
+        let (manager, sent) = recording_manager(NotificationConfig {
+            enabled: true,
+            routing,
+            ..Default::default()
+        });
+        manager
+            .notify(NotificationKind::BuildFailure, Severity::Warning, Some("web"), "build failed")
+            .await;
+        manager
+            .notify(NotificationKind::BuildFailure, Severity::Warning, Some("api"), "build failed")
+            .await;
+        // Critical notifications bypass the digest.
+        manager
+            .notify(NotificationKind::ServiceDown, Severity::Critical, Some("web"), "service is down")
+            .await;
+        assert_eq!(sent.lock().unwrap().len(), 1);
+        // Nothing is due yet...
+        manager.flush_digests().await;
+        assert_eq!(sent.lock().unwrap().len(), 1);
+        // ...but once the hour is up the summary goes out.
+        manager.flush_digests_where(|_| true).await;
+        let sent = sent.lock().unwrap();
+        assert_eq!(sent.len(), 2);
+        assert_eq!(sent[1].kind, NotificationKind::System);
+        assert!(sent[1].message.starts_with("hourly digest: 2 notifications"));
+        assert!(sent[1].message.contains("[warning] web: build failed"));
+    }
+
+    #[test]
+    fn summary_includes_service_and_severity() {
+        let n = Notification {
+            kind: NotificationKind::ServiceDown,
+            severity: Severity::Critical,
+            service: Some("ml-api".into()),
+            message: "service is down".into(),
+        };
+        assert_eq!(n.summary(), "[critical] ml-api: service is down");
+    }
+}
@@ -0,0 +1,151 @@
+//! Closed-loop orchestration across the monitoring and healing services.
+//!
+//! Filing an issue only starts the advertised loop; this module drives it
+//! to the end: ask the self-healing daemon to synthesize a reproduction
+//! for the failure, generate a candidate patch, validate it, and — when a
+//! reproduction and a repro command are available — re-execute the
+//! reproduction so the issue only counts as resolved once the patch makes
+//! it stop failing.
+
+use crate::config::HealingConfig;
+use anyhow::{bail, Context, Result};
+use serde::Serialize;
+use serde_json::{json, Value};
+use tracing::info;
+
+/// What one orchestration run achieved.
+#[derive(Debug, Serialize)]
+pub struct OrchestrationOutcome {
+    pub issue_id: String,
+    /// Stored reproduction test case, when the log yielded one.
+    pub test_case_id: Option<String>,
+    pub patch_id: Option<String>,
+    /// Whether the candidate patch passed build-and-test validation.
+    pub validated: bool,
+    /// Whether the reproduction stopped failing after validation; absent
+    /// when no reproduction or repro command was available.
+    pub repro_cleared: Option<bool>,
+    /// The loop's verdict: validated and, where a reproduction gate was
+    /// available, cleared it.
+    pub resolved: bool,
+}
+
+#[derive(Clone)]
+pub struct Orchestrator {
+    endpoint: String,
+    repro_command: Option<String>,
+    client: reqwest::Client,
+}
+
+impl Orchestrator {
+    /// Built only when the healing config opts into orchestration.
+    pub fn from_config(config: Option<&HealingConfig>) -> Option<Self> {
+        let config = config.filter(|config| config.orchestrate)?;
+        Some(Self {
+            endpoint: config.endpoint.trim_end_matches('/').to_string(),
+            repro_command: config.repro_command.clone(),
+            client: reqwest::Client::new(),
+        })
+    }
+
+    /// Drive a freshly filed issue through reproduction, patch
+    /// generation, validation, and the reproduction gate.
+    pub async fn run(&self, issue_id: &str) -> Result<OrchestrationOutcome> {
+        let test = self
+            .post(&format!("/api/issues/{issue_id}/tests/generate"), None)
+            .await?;
+        let test_case_id = test
+            .pointer("/test/test_case_id")
+            .and_then(Value::as_str)
+            .map(str::to_string);
+
+        let patch = self
+            .post(&format!("/api/issues/{issue_id}/generate"), None)
+            .await?;
+        let patch_id = patch
+            .get("id")
+            .and_then(Value::as_str)
+            .map(str::to_string)
+            .context("patch generation returned no id")?;
+
+        let validated = self
+            .post(&format!("/api/patches/{patch_id}/validate"), None)
+            .await?
+            .pointer("/validation/passed")
+            .and_then(Value::as_bool)
+            .unwrap_or(false);
+
+        // The point of the loop: the patch is only trusted once the
+        // reproduction that proved the bug stops failing.
+        let repro_cleared = match (&test_case_id, &self.repro_command) {
+            (Some(test_case), Some(command)) if validated => self
+                .post(
+                    &format!("/api/tests/{test_case}/execute"),
+                    Some(json!({ "command": command })),
+                )
+                .await?
+                .get("still_failing")
+                .and_then(Value::as_bool)
+                .map(|still_failing| !still_failing),
+            _ => None,
+        };
+        let resolved = validated && repro_cleared.unwrap_or(true);
+        info!(
+            issue = issue_id,
+            patch = patch_id,
+            validated,
+            ?repro_cleared,
+            resolved,
+            "orchestration loop finished"
+        );
+        Ok(OrchestrationOutcome {
+            issue_id: issue_id.to_string(),
+            test_case_id,
+            patch_id: Some(patch_id),
+            validated,
+            repro_cleared,
+            resolved,
+        })
+    }
+
+    async fn post(&self, path: &str, body: Option<Value>) -> Result<Value> {
+        let mut request = self.client.post(format!("{}{path}", self.endpoint));
+        if let Some(body) = body {
+            request = request.json(&body);
+        }
+        let response = request
+            .send()
+            .await
+            .with_context(|| format!("request to {path} failed"))?;
+        let status = response.status();
+        let payload: Value = response
+            .json()
+            .await
+            .with_context(|| format!("{path} returned a non-JSON response"))?;
+        if !status.is_success() {
+            bail!("{path} returned {status}: {payload}");
+        }
+        Ok(payload)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn built_only_when_orchestration_is_requested() {
+        assert!(Orchestrator::from_config(None).is_none());
+        let mut config = HealingConfig {
+            endpoint: "http://localhost:9400/".to_string(),
+            failure_threshold: 2,
+            auto_patch: false,
+            orchestrate: false,
+            repro_command: None,
+        };
+        assert!(Orchestrator::from_config(Some(&config)).is_none());
+        config.orchestrate = true;
+        let orchestrator = Orchestrator::from_config(Some(&config)).unwrap();
+        assert_eq!(orchestrator.endpoint, "http://localhost:9400");
+    }
+}
@@ -0,0 +1,224 @@
+//! Cursor pagination, filtering, and ordering shared by the history
+//! endpoints (`/api/services/{name}/builds`, `/api/rollbacks`,
+//! `/api/alerts`).
+//!
+//! Pages are keyset-based over the entry's timestamp with the id as a
+//! tie-breaker, so a page stays stable while new entries arrive —
+//! offsets would shift under insertion. The cursor is the timestamp and
+//! id of the last entry served, opaque to clients, echoed back as
+//! `cursor` to fetch the page after it.
+
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// Query parameters accepted by every paginated endpoint; filters that
+/// do not apply to an endpoint are simply ignored there.
+#[derive(Debug, Clone, Deserialize)]
+pub struct PageQuery {
+    #[serde(default = "default_page_limit")]
+    pub limit: i64,
+    /// `next_cursor` from the previous page; omit for the first page.
+    #[serde(default)]
+    pub cursor: Option<String>,
+    /// `desc` (newest first, the default) or `asc`.
+    #[serde(default)]
+    pub order: Option<String>,
+    #[serde(default)]
+    pub status: Option<String>,
+    #[serde(default)]
+    pub severity: Option<String>,
+    #[serde(default)]
+    pub service: Option<String>,
+    #[serde(default)]
+    pub commit: Option<String>,
+    /// Inclusive lower bound on the entry timestamp, RFC 3339.
+    #[serde(default)]
+    pub since: Option<DateTime<Utc>>,
+    /// Inclusive upper bound on the entry timestamp, RFC 3339.
+    #[serde(default)]
+    pub until: Option<DateTime<Utc>>,
+}
+
+fn default_page_limit() -> i64 {
+    50
+}
+
+impl Default for PageQuery {
+    fn default() -> Self {
+        Self {
+            limit: default_page_limit(),
+            cursor: None,
+            order: None,
+            status: None,
+            severity: None,
+            service: None,
+            commit: None,
+            since: None,
+            until: None,
+        }
+    }
+}
+
+impl PageQuery {
+    /// Resolve into the filter the database layer consumes. A malformed
+    /// cursor or order is rejected rather than silently serving page one.
+    pub fn request(&self) -> Result<PageRequest> {
+        let descending = match self.order.as_deref() {
+            None | Some("desc") => true,
+            Some("asc") => false,
+            Some(other) => return Err(anyhow!("unknown order {other} (expected asc or desc)")),
+        };
+        let after = self.cursor.as_deref().map(decode_cursor).transpose()?;
+        Ok(PageRequest {
+            limit: self.limit.clamp(1, 500),
+            descending,
+            after,
+            status: self.status.clone(),
+            severity: self.severity.clone(),
+            service: self.service.clone(),
+            commit: self.commit.clone(),
+            since: self.since.map(|t| t.to_rfc3339()),
+            until: self.until.map(|t| t.to_rfc3339()),
+        })
+    }
+}
+
+/// A resolved page request. Timestamps are RFC 3339 strings because
+/// that is how the database stores them; lexicographic comparison
+/// matches chronological order for the UTC offsets we write.
+#[derive(Debug, Clone)]
+pub struct PageRequest {
+    pub limit: i64,
+    pub descending: bool,
+    /// Decoded cursor: the timestamp and id of the last entry on the
+    /// previous page.
+    pub after: Option<(String, String)>,
+    pub status: Option<String>,
+    pub severity: Option<String>,
+    pub service: Option<String>,
+    pub commit: Option<String>,
+    pub since: Option<String>,
+    pub until: Option<String>,
+}
+
+impl PageRequest {
+    /// How many rows the query should fetch: one beyond the page, so the
+    /// presence of a next page is known without a COUNT query.
+    pub fn fetch_limit(&self) -> i64 {
+        self.limit + 1
+    }
+}
+
+/// One page of results with its envelope metadata.
+#[derive(Debug, Serialize)]
+pub struct Page<T> {
+    pub items: Vec<T>,
+    pub count: usize,
+    /// Pass back as `cursor` to fetch the page after this one; absent on
+    /// the last page.
+    pub next_cursor: Option<String>,
+}
+
+/// Trim rows fetched with [`PageRequest::fetch_limit`] to the page size
+/// and derive the envelope.
+pub fn paginate<T>(
+    mut fetched: Vec<T>,
+    request: &PageRequest,
+    cursor_of: impl Fn(&T) -> String,
+) -> Page<T> {
+    let has_more = fetched.len() as i64 > request.limit;
+    if has_more {
+        fetched.truncate(request.limit as usize);
+    }
+    let next_cursor = if has_more {
+        fetched.last().map(cursor_of)
+    } else {
+        None
+    };
+    Page {
+        count: fetched.len(),
+        next_cursor,
+        items: fetched,
+    }
+}
+
+pub fn cursor(ts: DateTime<Utc>, id: Uuid) -> String {
+    format!("{}~{id}", ts.to_rfc3339())
+}
+
+fn decode_cursor(cursor: &str) -> Result<(String, String)> {
+    cursor
+        .split_once('~')
+        .map(|(ts, id)| (ts.to_string(), id.to_string()))
+        .ok_or_else(|| anyhow!("malformed cursor"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cursors_round_trip_and_bad_input_is_rejected() {
+        let ts = Utc::now();
+        let id = Uuid::new_v4();
+        let query = PageQuery {
+            cursor: Some(cursor(ts, id)),
+            ..Default::default()
+        };
+        let request = query.request().unwrap();
+        assert_eq!(
+            request.after,
+            Some((ts.to_rfc3339(), id.to_string()))
+        );
+
+        let query = PageQuery {
+            cursor: Some("not a cursor".to_string()),
+            ..Default::default()
+        };
+        assert!(query.request().is_err());
+        let query = PageQuery {
+            order: Some("sideways".to_string()),
+            ..Default::default()
+        };
+        assert!(query.request().is_err());
+    }
+
+    #[test]
+    fn paginate_trims_the_probe_row_and_points_at_the_next_page() {
+        let request = PageQuery {
+            limit: 2,
+            ..Default::default()
+        }
+        .request()
+        .unwrap();
+        // Three rows back means a full page plus proof of more.
+        let page = paginate(vec!["a", "b", "c"], &request, |s| s.to_string());
+        assert_eq!(page.items, vec!["a", "b"]);
+        assert_eq!(page.count, 2);
+        assert_eq!(page.next_cursor.as_deref(), Some("b"));
+
+        let page = paginate(vec!["c"], &request, |s| s.to_string());
+        assert_eq!(page.count, 1);
+        assert!(page.next_cursor.is_none());
+    }
+
+    #[test]
+    fn limits_are_clamped_to_a_sane_range() {
+        let request = PageQuery {
+            limit: 0,
+            ..Default::default()
+        }
+        .request()
+        .unwrap();
+        assert_eq!(request.limit, 1);
+        let request = PageQuery {
+            limit: 10_000,
+            ..Default::default()
+        }
+        .request()
+        .unwrap();
+        assert_eq!(request.limit, 500);
+    }
+}
@@ -0,0 +1,152 @@
+//! Host-platform compatibility layer.
+//!
+//! The monitor drives git, docker, cargo, and npm through their CLIs,
+//! which ship the same on Linux, macOS, and Windows, and it never
+//! changes the process working directory — checkouts and builds always
+//! hand `current_dir` to the child process instead. What actually
+//! differs between hosts is the Unix userland the resource probes
+//! assumed (`df`, `/proc`) and which tools happen to be installed on a
+//! dev machine. Both live here: probes answer "unknown" where their
+//! source is missing so the watchdog degrades instead of erroring, and
+//! [`preflight`] reports absent tools once at startup instead of as
+//! confusing mid-poll failures.
+
+use crate::config::{BuildStrategy, MonitorConfig};
+use anyhow::{bail, Result};
+use std::process::Command;
+
+/// Verify the external tools this configuration actually needs are
+/// invocable, so a monitor started on a fresh dev machine fails with
+/// one actionable message rather than on its first build.
+pub fn preflight(config: &MonitorConfig) -> Result<()> {
+    let mut required = vec![("git", "monitoring the repository")];
+    let uses_docker = config.compose.is_some()
+        || config
+            .services
+            .iter()
+            .any(|s| matches!(s.build_strategy, BuildStrategy::Docker));
+    if uses_docker {
+        required.push(("docker", "docker build strategies and redeploys"));
+    }
+    if config
+        .artifacts
+        .as_ref()
+        .is_some_and(|a| a.bucket.is_some())
+    {
+        required.push(("aws", "artifact uploads to the configured bucket"));
+    }
+    let missing: Vec<String> = required
+        .into_iter()
+        .filter(|(tool, _)| !tool_available(tool))
+        .map(|(tool, needed_for)| format!("{tool} (needed for {needed_for})"))
+        .collect();
+    if !missing.is_empty() {
+        bail!(
+            "required tools not found on PATH: {}; install them or adjust the configuration",
+            missing.join(", ")
+        );
+    }
+    Ok(())
+}
+
+fn tool_available(program: &str) -> bool {
+    Command::new(program)
+        .arg("--version")
+        .stdout(std::process::Stdio::null())
+        .stderr(std::process::Stdio::null())
+        .status()
+        .is_ok()
+}
+
+/// Free space in MB on the filesystem holding `path`, falling back to
+/// the root filesystem when the path does not exist. Unknown — as on
+/// hosts without `df` — reports as effectively unlimited, so thresholds
+/// never fire on bad data.
+#[cfg(unix)]
+pub(crate) fn free_disk_mb(path: &str) -> Result<u64> {
+    use anyhow::Context;
+    let output = Command::new("df")
+        .args(["-Pk", path])
+        .output()
+        .or_else(|_| Command::new("df").args(["-Pk", "/"]).output())
+        .context("failed to invoke df")?;
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let available_kb: u64 = stdout
+        .lines()
+        .nth(1)
+        .and_then(|l| l.split_whitespace().nth(3))
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(u64::MAX);
+    Ok(available_kb / 1024)
+}
+
+#[cfg(not(unix))]
+pub(crate) fn free_disk_mb(_path: &str) -> Result<u64> {
+    Ok(u64::MAX)
+}
+
+/// Available memory in MB, when the host exposes it. Only Linux has
+/// `/proc/meminfo`; elsewhere the watchdog simply skips the memory
+/// threshold.
+pub(crate) fn free_memory_mb() -> Option<u64> {
+    let meminfo = std::fs::read_to_string("/proc/meminfo").ok()?;
+    meminfo
+        .lines()
+        .find(|l| l.starts_with("MemAvailable:"))
+        .and_then(|l| l.split_whitespace().nth(1))
+        .and_then(|kb| kb.parse::<u64>().ok())
+        .map(|kb| kb / 1024)
+}
+
+/// 1-minute load average per CPU from `/proc/loadavg`, Linux only like
+/// [`free_memory_mb`].
+pub(crate) fn load_per_cpu() -> Option<f64> {
+    let loadavg = std::fs::read_to_string("/proc/loadavg").ok()?;
+    let one_minute: f64 = loadavg.split_whitespace().next()?.parse().ok()?;
+    let cpus = std::thread::available_parallelism().map(|n| n.get()).ok()? as f64;
+    Some(one_minute / cpus)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::ServiceConfig;
+
+    fn config() -> MonitorConfig {
+        serde_json::from_str(r#"{ "repo_path": "/tmp/repo" }"#).unwrap()
+    }
+
+    #[test]
+    fn preflight_passes_with_no_services_configured() {
+        // Only git is required, and the test environment has it.
+        preflight(&config()).unwrap();
+    }
+
+    #[test]
+    fn preflight_names_the_missing_tool_and_why() {
+        let mut config = config();
+        // Force the aws requirement; the binary is absent in CI.
+        config.artifacts = Some(crate::config::ArtifactConfig {
+            bucket: Some("builds".to_string()),
+            ..Default::default()
+        });
+        if tool_available("aws") {
+            // Dev machines with the aws CLI can't observe the failure.
+            return;
+        }
+        let err = preflight(&config).unwrap_err().to_string();
+        assert!(err.contains("aws"), "{err}");
+        assert!(err.contains("artifact uploads"), "{err}");
+    }
+
+    #[test]
+    fn docker_is_only_required_when_a_strategy_needs_it() {
+        let mut config = config();
+        config.services = vec![serde_json::from_str::<ServiceConfig>(
+            r#"{ "name": "tools", "build_strategy": { "type": "cargo" } }"#,
+        )
+        .unwrap()];
+        // Cargo-only configurations never demand docker.
+        preflight(&config).unwrap();
+    }
+}
@@ -0,0 +1,161 @@
+//! Health probes issued from the monitor process itself.
+//!
+//! Exec-ing curl inside containers breaks on distroless images, so probes
+//! run out-of-process: an HTTP GET against the published port, a bare TCP
+//! connect, or an inspection of Docker's own HEALTHCHECK status.
+
+use crate::config::{ProbeConfig, ProbeKind, ServiceConfig};
+use crate::types::ServiceHealth;
+use anyhow::{Context, Result};
+use std::process::Command;
+use std::time::Duration;
+use tracing::debug;
+
+pub struct HealthProber {
+    client: reqwest::Client,
+}
+
+impl HealthProber {
+    pub fn new() -> Self {
+        Self {
+            client: reqwest::Client::new(),
+        }
+    }
+
+    /// One probe of the service's published endpoint, per its configured
+    /// probe kind. Returns whether the probe succeeded.
+    pub async fn probe(&self, service: &ServiceConfig) -> bool {
+        let timeout = Duration::from_secs(service.probe.timeout_secs);
+        match service.probe.kind {
+            ProbeKind::Http => {
+                let Some(port) = service.health_port else {
+                    return true;
+                };
+                let url = format!("http://127.0.0.1:{port}{}", service.health_path);
+                match self.client.get(&url).timeout(timeout).send().await {
+                    Ok(response) => response.status().is_success(),
+                    Err(e) => {
+                        debug!(service = %service.name, "http probe failed: {e}");
+                        false
+                    }
+                }
+            }
+            ProbeKind::Tcp => {
+                let Some(port) = service.health_port else {
+                    return true;
+                };
+                tokio::time::timeout(
+                    timeout,
+                    tokio::net::TcpStream::connect(("127.0.0.1", port)),
+                )
+                .await
+                .map(|r| r.is_ok())
+                .unwrap_or(false)
+            }
+            ProbeKind::Docker => self.probe_container(&service.name),
+        }
+    }
+
+    /// Probe an arbitrary container through Docker's own view of it: the
+    /// HEALTHCHECK status when the image defines one, otherwise whether the
+    /// container is running at all. This is the only probe that works for
+    /// containers without published ports (e.g. canaries).
+    pub fn probe_container(&self, container: &str) -> bool {
+        match container_health(container) {
+            Ok(Some(status)) => status == "healthy" || status == "running",
+            _ => false,
+        }
+    }
+}
+
+impl Default for HealthProber {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Docker's health status for a container: the HEALTHCHECK state when
+/// defined, else the plain run state. `None` when the container is gone.
+fn container_health(container: &str) -> Result<Option<String>> {
+    let output = Command::new("docker")
+        .args([
+            "inspect",
+            "--format",
+            "{{if .State.Health}}{{.State.Health.Status}}{{else}}{{.State.Status}}{{end}}",
+            container,
+        ])
+        .output()
+        .context("failed to invoke docker inspect")?;
+    if !output.status.success() {
+        return Ok(None);
+    }
+    Ok(Some(
+        String::from_utf8_lossy(&output.stdout).trim().to_string(),
+    ))
+}
+
+/// Per-service streak tracking that turns individual probe results into a
+/// health state, flipping only after the configured thresholds.
+#[derive(Debug, Clone, Copy)]
+pub struct ProbeState {
+    pub health: ServiceHealth,
+    ok_streak: u32,
+    fail_streak: u32,
+}
+
+impl Default for ProbeState {
+    fn default() -> Self {
+        Self {
+            health: ServiceHealth::Unknown,
+            ok_streak: 0,
+            fail_streak: 0,
+        }
+    }
+}
+
+impl ProbeState {
+    /// Fold one probe result in and return the (possibly unchanged) health.
+    pub fn observe(&mut self, ok: bool, config: &ProbeConfig) -> ServiceHealth {
+        if ok {
+            self.ok_streak += 1;
+            self.fail_streak = 0;
+            if self.ok_streak >= config.success_threshold {
+                self.health = ServiceHealth::Healthy;
+            }
+        } else {
+            self.fail_streak += 1;
+            self.ok_streak = 0;
+            if self.fail_streak >= config.failure_threshold {
+                self.health = ServiceHealth::Down;
+            } else if self.health == ServiceHealth::Healthy {
+                self.health = ServiceHealth::Degraded;
+            }
+        }
+        self.health
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn health_flips_only_after_thresholds() {
+        let config = ProbeConfig {
+            success_threshold: 2,
+            failure_threshold: 3,
+            ..ProbeConfig::default()
+        };
+        let mut state = ProbeState::default();
+
+        assert_eq!(state.observe(true, &config), ServiceHealth::Unknown);
+        assert_eq!(state.observe(true, &config), ServiceHealth::Healthy);
+        // One or two failures only degrade; the third marks it down.
+        assert_eq!(state.observe(false, &config), ServiceHealth::Degraded);
+        assert_eq!(state.observe(false, &config), ServiceHealth::Degraded);
+        assert_eq!(state.observe(false, &config), ServiceHealth::Down);
+        // Recovery needs the full success streak again.
+        assert_eq!(state.observe(true, &config), ServiceHealth::Down);
+        assert_eq!(state.observe(true, &config), ServiceHealth::Healthy);
+    }
+}
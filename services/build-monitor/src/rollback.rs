@@ -0,0 +1,787 @@
+//! Automated rollback of services to a previously good commit.
+
+use crate::config::{RollbackConfig, ServiceConfig};
+use crate::database::Database;
+use crate::docker::DockerManager;
+use crate::paging::PageRequest;
+use crate::probe::HealthProber;
+use crate::traffic::TrafficController;
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::Row;
+use tracing::{info, warn};
+use uuid::Uuid;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RollbackStrategy {
+    /// Tear down and restart on the target image in one step.
+    Immediate,
+    /// Shift a percentage of traffic to the target before full cutover.
+    Canary,
+    /// Bring up the target stack alongside and switch atomically.
+    BlueGreen,
+}
+
+impl RollbackStrategy {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RollbackStrategy::Immediate => "immediate",
+            RollbackStrategy::Canary => "canary",
+            RollbackStrategy::BlueGreen => "blue_green",
+        }
+    }
+
+    pub fn parse(s: &str) -> Self {
+        match s {
+            "canary" => RollbackStrategy::Canary,
+            "blue_green" => RollbackStrategy::BlueGreen,
+            _ => RollbackStrategy::Immediate,
+        }
+    }
+}
+
+/// Checks that may run before a rollback is attempted.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PreCheck {
+    TargetImageExists,
+    ServiceQuiesced,
+    ConfigValid,
+}
+
+impl PreCheck {
+    pub fn name(&self) -> &'static str {
+        match self {
+            PreCheck::TargetImageExists => "target_image_exists",
+            PreCheck::ServiceQuiesced => "service_quiesced",
+            PreCheck::ConfigValid => "config_valid",
+        }
+    }
+}
+
+/// Checks that may run after a rollback completes.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PostCheck {
+    HealthCheck,
+    SmokeTest,
+    DependencyCheck,
+}
+
+impl PostCheck {
+    pub fn name(&self) -> &'static str {
+        match self {
+            PostCheck::HealthCheck => "health_check",
+            PostCheck::SmokeTest => "smoke_test",
+            PostCheck::DependencyCheck => "dependency_check",
+        }
+    }
+}
+
+/// One configured check together with whether its failure blocks the
+/// rollback or is merely reported.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CheckSpec<C> {
+    pub check: C,
+    #[serde(default = "default_required")]
+    pub required: bool,
+}
+
+fn default_required() -> bool {
+    true
+}
+
+/// Recorded outcome of a single pre- or post-rollback check.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CheckOutcome {
+    pub name: String,
+    pub required: bool,
+    pub passed: bool,
+    pub detail: Option<String>,
+}
+
+impl CheckOutcome {
+    fn blocks(&self) -> bool {
+        self.required && !self.passed
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RollbackStatus {
+    Pending,
+    /// Waiting for a human decision before anything is touched.
+    PendingApproval,
+    /// A human declined the rollback; nothing was touched.
+    Rejected,
+    InProgress,
+    Completed,
+    Failed,
+}
+
+impl RollbackStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RollbackStatus::Pending => "pending",
+            RollbackStatus::PendingApproval => "pending_approval",
+            RollbackStatus::Rejected => "rejected",
+            RollbackStatus::InProgress => "in_progress",
+            RollbackStatus::Completed => "completed",
+            RollbackStatus::Failed => "failed",
+        }
+    }
+
+    pub fn parse(s: &str) -> Self {
+        match s {
+            "pending" => RollbackStatus::Pending,
+            "pending_approval" => RollbackStatus::PendingApproval,
+            "rejected" => RollbackStatus::Rejected,
+            "in_progress" => RollbackStatus::InProgress,
+            "completed" => RollbackStatus::Completed,
+            _ => RollbackStatus::Failed,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RollbackResult {
+    pub id: Uuid,
+    pub service: String,
+    pub from_commit: String,
+    pub to_commit: String,
+    pub strategy: RollbackStrategy,
+    pub status: RollbackStatus,
+    pub reason: Option<String>,
+    /// Percentage of traffic already cut over to the rollback target.
+    pub progress_percent: u8,
+    /// Outcomes of the configured pre-rollback checks.
+    #[serde(default)]
+    pub pre_checks: Vec<CheckOutcome>,
+    /// Outcomes of the configured post-rollback checks.
+    #[serde(default)]
+    pub post_checks: Vec<CheckOutcome>,
+    /// Who approved or rejected a gated rollback.
+    #[serde(default)]
+    pub approved_by: Option<String>,
+    /// When the approval decision was made.
+    #[serde(default)]
+    pub decided_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+    pub completed_at: Option<DateTime<Utc>>,
+}
+
+pub struct RollbackManager {
+    config: RollbackConfig,
+    /// All managed services, consulted by the dependency post-check.
+    services: Vec<ServiceConfig>,
+    database: Database,
+    docker: DockerManager,
+    traffic: TrafficController,
+    prober: HealthProber,
+    metrics: Option<std::sync::Arc<crate::metrics::MetricsCollector>>,
+}
+
+impl RollbackManager {
+    pub fn new(
+        config: RollbackConfig,
+        services: Vec<ServiceConfig>,
+        database: Database,
+        docker: DockerManager,
+    ) -> Self {
+        let traffic = TrafficController::new(config.nginx.clone());
+        Self {
+            config,
+            services,
+            database,
+            docker,
+            traffic,
+            prober: HealthProber::new(),
+            metrics: None,
+        }
+    }
+
+    pub fn with_metrics(mut self, metrics: std::sync::Arc<crate::metrics::MetricsCollector>) -> Self {
+        self.metrics = Some(metrics);
+        self
+    }
+
+    pub fn auto_rollback_enabled(&self) -> bool {
+        self.config.auto_rollback
+    }
+
+    pub fn failure_threshold(&self) -> u32 {
+        self.config.failure_threshold
+    }
+
+    /// Whether an automatic rollback spanning this many commits needs a
+    /// human decision first.
+    pub fn needs_approval(&self, span_commits: usize) -> bool {
+        self.config.approval.enabled
+            && span_commits as u32 > self.config.approval.blast_radius_commits
+    }
+
+    /// Record a rollback that is waiting for approval without touching any
+    /// containers.
+    pub async fn create_pending(
+        &self,
+        service: &ServiceConfig,
+        from_commit: &str,
+        to_commit: &str,
+        strategy: RollbackStrategy,
+        reason: Option<String>,
+    ) -> Result<RollbackResult> {
+        let result = new_result(service, from_commit, to_commit, strategy, reason, RollbackStatus::PendingApproval);
+        self.record(&result).await?;
+        info!(service = %service.name, id = %result.id, "rollback awaiting approval");
+        Ok(result)
+    }
+
+    /// Approve a pending rollback and execute it, recording who approved.
+    pub async fn approve(&self, id: Uuid, approver: &str) -> Result<RollbackResult> {
+        let mut result = self
+            .rollback_by_id(id)
+            .await?
+            .with_context(|| format!("rollback {id} not found"))?;
+        if result.status != RollbackStatus::PendingApproval {
+            anyhow::bail!("rollback {id} is {}, not pending approval", result.status.as_str());
+        }
+        let service = self
+            .services
+            .iter()
+            .find(|s| s.name == result.service)
+            .cloned()
+            .with_context(|| format!("unknown service {}", result.service))?;
+        result.approved_by = Some(approver.to_string());
+        result.decided_at = Some(Utc::now());
+        result.status = RollbackStatus::InProgress;
+        self.record(&result).await?;
+        info!(id = %id, approver, service = %result.service, "rollback approved");
+        self.database
+            .record_alert(
+                crate::types::Severity::Info,
+                Some(&result.service),
+                &format!("rollback {id} approved by {approver}"),
+            )
+            .await?;
+        self.execute(&service, &mut result).await?;
+        Ok(result)
+    }
+
+    /// Reject a pending rollback, recording who rejected it.
+    pub async fn reject(&self, id: Uuid, approver: &str) -> Result<RollbackResult> {
+        let mut result = self
+            .rollback_by_id(id)
+            .await?
+            .with_context(|| format!("rollback {id} not found"))?;
+        if result.status != RollbackStatus::PendingApproval {
+            anyhow::bail!("rollback {id} is {}, not pending approval", result.status.as_str());
+        }
+        result.approved_by = Some(approver.to_string());
+        result.decided_at = Some(Utc::now());
+        result.status = RollbackStatus::Rejected;
+        result.completed_at = Some(Utc::now());
+        self.record(&result).await?;
+        info!(id = %id, approver, service = %result.service, "rollback rejected");
+        self.database
+            .record_alert(
+                crate::types::Severity::Info,
+                Some(&result.service),
+                &format!("rollback {id} rejected by {approver}"),
+            )
+            .await?;
+        Ok(result)
+    }
+
+    /// Roll `service` back to `to_commit`, recording progress in the
+    /// database.
+    pub async fn perform_rollback(
+        &self,
+        service: &ServiceConfig,
+        from_commit: &str,
+        to_commit: &str,
+        strategy: RollbackStrategy,
+        reason: Option<String>,
+    ) -> Result<RollbackResult> {
+        let mut result = new_result(service, from_commit, to_commit, strategy, reason, RollbackStatus::InProgress);
+        self.record(&result).await?;
+        info!(
+            service = %service.name,
+            from = from_commit,
+            to = to_commit,
+            strategy = strategy.as_str(),
+            "performing rollback"
+        );
+        self.execute(service, &mut result).await?;
+        Ok(result)
+    }
+
+    /// Run the checks and the chosen strategy, leaving the final status in
+    /// `result` and the database.
+    async fn execute(&self, service: &ServiceConfig, result: &mut RollbackResult) -> Result<()> {
+        let strategy = result.strategy;
+        // Prefer a prebuilt image for the target commit over rebuilding.
+        let image = self.docker.rollback_image(&service.name, &result.to_commit);
+
+        // When the build recorded an artifact digest, make sure the image
+        // we are about to run is byte-for-byte what passed back then.
+        if let Ok(Some(artifact)) = self
+            .database
+            .artifact_for(&service.name, &result.to_commit)
+            .await
+        {
+            if let (Some(expected), Ok(Some(actual))) =
+                (artifact.digest.as_deref(), self.docker.image_digest(&image))
+            {
+                if expected != actual {
+                    warn!(
+                        service = %service.name,
+                        image,
+                        expected,
+                        actual,
+                        "rollback image digest differs from the recorded artifact"
+                    );
+                    self.database
+                        .record_alert(
+                            crate::types::Severity::Warning,
+                            Some(&service.name),
+                            &format!(
+                                "rolling back with image {image} whose digest differs from the artifact recorded at {}",
+                                &result.to_commit[..result.to_commit.len().min(12)]
+                            ),
+                        )
+                        .await?;
+                }
+            }
+        }
+
+        result.pre_checks = self.run_pre_checks(service, &image, strategy).await;
+        if let Some(blocked) = result.pre_checks.iter().find(|c| c.blocks()) {
+            result.status = RollbackStatus::Failed;
+            result.completed_at = Some(Utc::now());
+            self.record(result).await?;
+            anyhow::bail!(
+                "pre-rollback check {} failed: {}",
+                blocked.name,
+                blocked.detail.as_deref().unwrap_or("no detail")
+            );
+        }
+
+        let outcome = match strategy {
+            RollbackStrategy::Immediate => self.immediate(service, &image, result).await,
+            RollbackStrategy::Canary => self.canary(service, &image, result).await,
+            RollbackStrategy::BlueGreen => self.blue_green(service, &image, result).await,
+        };
+
+        if outcome.is_ok() {
+            result.post_checks = self.run_post_checks(service).await;
+        }
+        let blocked_post = result.post_checks.iter().any(|c| c.blocks());
+        result.status = if outcome.is_ok() && !blocked_post {
+            RollbackStatus::Completed
+        } else {
+            RollbackStatus::Failed
+        };
+        result.completed_at = Some(Utc::now());
+        self.record(result).await?;
+        self.update_deployment_records(result).await?;
+        if let Some(metrics) = &self.metrics {
+            metrics.observe_rollback(&result.service, result.status.as_str());
+        }
+        outcome?;
+        if let Some(blocked) = result.post_checks.iter().find(|c| c.blocks()) {
+            anyhow::bail!(
+                "post-rollback check {} failed: {}",
+                blocked.name,
+                blocked.detail.as_deref().unwrap_or("no detail")
+            );
+        }
+        Ok(())
+    }
+
+    async fn run_pre_checks(
+        &self,
+        service: &ServiceConfig,
+        image: &str,
+        strategy: RollbackStrategy,
+    ) -> Vec<CheckOutcome> {
+        let mut outcomes = Vec::new();
+        for spec in &self.config.pre_checks {
+            let (passed, detail) = match spec.check {
+                PreCheck::TargetImageExists => match self.docker.image_exists(image) {
+                    Ok(true) => (true, None),
+                    Ok(false) => (false, Some(format!("image {image} not found"))),
+                    Err(e) => (false, Some(e.to_string())),
+                },
+                PreCheck::ServiceQuiesced => match self.in_progress_rollbacks(&service.name).await {
+                    Ok(0) => (true, None),
+                    Ok(n) => (false, Some(format!("{n} rollback(s) already in progress"))),
+                    Err(e) => (false, Some(e.to_string())),
+                },
+                PreCheck::ConfigValid => match self.validate_config(service, strategy) {
+                    Ok(()) => (true, None),
+                    Err(e) => (false, Some(e.to_string())),
+                },
+            };
+            outcomes.push(CheckOutcome {
+                name: spec.check.name().to_string(),
+                required: spec.required,
+                passed,
+                detail,
+            });
+        }
+        outcomes
+    }
+
+    async fn run_post_checks(&self, service: &ServiceConfig) -> Vec<CheckOutcome> {
+        let mut outcomes = Vec::new();
+        for spec in &self.config.post_checks {
+            let (passed, detail) = match spec.check {
+                PostCheck::HealthCheck => {
+                    if self.prober.probe(service).await {
+                        (true, None)
+                    } else {
+                        (false, Some("health probe failed".to_string()))
+                    }
+                }
+                PostCheck::SmokeTest => self.run_smoke_test(service),
+                PostCheck::DependencyCheck => self.check_dependents(service).await,
+            };
+            outcomes.push(CheckOutcome {
+                name: spec.check.name().to_string(),
+                required: spec.required,
+                passed,
+                detail,
+            });
+        }
+        outcomes
+    }
+
+    /// A rollback must not start while another one for the same service is
+    /// still running.
+    async fn in_progress_rollbacks(&self, service: &str) -> Result<i64> {
+        let row = sqlx::query(
+            "SELECT COUNT(*) AS n FROM rollbacks WHERE service = ?1 AND status = 'in_progress'",
+        )
+        .bind(service)
+        .fetch_one(self.database.pool())
+        .await?;
+        // The rollback being performed is itself already recorded.
+        Ok((row.get::<i64, _>("n") - 1).max(0))
+    }
+
+    /// The service must carry everything the chosen strategy needs.
+    fn validate_config(&self, service: &ServiceConfig, strategy: RollbackStrategy) -> Result<()> {
+        if strategy != RollbackStrategy::Immediate
+            && self.traffic.enabled()
+            && service.health_port.is_none()
+        {
+            anyhow::bail!("gradual strategies require health_port");
+        }
+        Ok(())
+    }
+
+    /// Run the configured smoke-test command, if any, with the service name
+    /// exposed to the hook.
+    fn run_smoke_test(&self, service: &ServiceConfig) -> (bool, Option<String>) {
+        let Some(command) = &self.config.smoke_test_command else {
+            return (true, Some("no smoke test configured".to_string()));
+        };
+        let Some((program, args)) = command.split_first() else {
+            return (false, Some("smoke_test_command is empty".to_string()));
+        };
+        match std::process::Command::new(program)
+            .args(args)
+            .env("ROLLBACK_SERVICE", &service.name)
+            .output()
+        {
+            Ok(out) if out.status.success() => (true, None),
+            Ok(out) => (
+                false,
+                Some(format!(
+                    "smoke test exited with {}: {}",
+                    out.status,
+                    String::from_utf8_lossy(&out.stderr).trim()
+                )),
+            ),
+            Err(e) => (false, Some(format!("failed to invoke smoke test: {e}"))),
+        }
+    }
+
+    /// Probe the other managed services so a rollback that breaks a
+    /// neighbour is caught immediately.
+    async fn check_dependents(&self, service: &ServiceConfig) -> (bool, Option<String>) {
+        let mut unhealthy = Vec::new();
+        for other in &self.services {
+            if other.name == service.name || other.health_port.is_none() {
+                continue;
+            }
+            if !self.prober.probe(other).await {
+                unhealthy.push(other.name.clone());
+            }
+        }
+        if unhealthy.is_empty() {
+            (true, None)
+        } else {
+            (false, Some(format!("unhealthy dependents: {}", unhealthy.join(", "))))
+        }
+    }
+
+    /// Tear down and restart on the target image in one step.
+    async fn immediate(
+        &self,
+        service: &ServiceConfig,
+        image: &str,
+        result: &mut RollbackResult,
+    ) -> Result<()> {
+        self.docker.redeploy(service, image)?;
+        result.progress_percent = 100;
+        self.record(result).await?;
+        Ok(())
+    }
+
+    /// Gradually shift traffic to a canary container on the target image,
+    /// baking and health-checking at each configured step. Any failed check
+    /// aborts the rollout and restores all traffic to the stable container.
+    async fn canary(
+        &self,
+        service: &ServiceConfig,
+        image: &str,
+        result: &mut RollbackResult,
+    ) -> Result<()> {
+        if !self.traffic.enabled() {
+            info!(service = %service.name, "no traffic controller configured; canary degrades to immediate");
+            return self.immediate(service, image, result).await;
+        }
+        let port = service
+            .health_port
+            .ok_or_else(|| anyhow::anyhow!("canary rollout requires health_port"))?;
+        let canary_name = format!("{}-canary", service.name);
+        self.docker
+            .run_container(&canary_name, image, &resolved_env(service)?)?;
+
+        let bake = std::time::Duration::from_secs(self.config.canary.bake_time_secs);
+        for &step in &self.config.canary.steps {
+            self.traffic.shift(&service.name, port, step)?;
+            result.progress_percent = step.min(100);
+            self.record(result).await?;
+            tokio::time::sleep(bake).await;
+            if !self.prober.probe_container(&canary_name) {
+                self.traffic.reset(&service.name, port)?;
+                self.docker.remove_container(&canary_name)?;
+                anyhow::bail!("canary failed health check at {step}%, rollout aborted");
+            }
+        }
+
+        // Promote: stable container takes over the target image, canary and
+        // its upstream entry are retired.
+        self.docker.redeploy(service, image)?;
+        self.traffic.reset(&service.name, port)?;
+        self.docker.remove_container(&canary_name)?;
+        result.progress_percent = 100;
+        self.record(result).await?;
+        Ok(())
+    }
+
+    /// Bring up a complete green stack on the target image, verify health,
+    /// then cut traffic over atomically.
+    async fn blue_green(
+        &self,
+        service: &ServiceConfig,
+        image: &str,
+        result: &mut RollbackResult,
+    ) -> Result<()> {
+        if !self.traffic.enabled() {
+            info!(service = %service.name, "no traffic controller configured; blue-green degrades to immediate");
+            return self.immediate(service, image, result).await;
+        }
+        let port = service
+            .health_port
+            .ok_or_else(|| anyhow::anyhow!("blue-green rollout requires health_port"))?;
+        let green_name = format!("{}-canary", service.name);
+        self.docker
+            .run_container(&green_name, image, &resolved_env(service)?)?;
+        result.progress_percent = 50;
+        self.record(result).await?;
+
+        tokio::time::sleep(std::time::Duration::from_secs(
+            self.config.canary.bake_time_secs,
+        ))
+        .await;
+        if !self.prober.probe_container(&green_name) {
+            self.docker.remove_container(&green_name)?;
+            anyhow::bail!("green stack failed health check, cutover aborted");
+        }
+
+        self.traffic.shift(&service.name, port, 100)?;
+        self.docker.redeploy(service, image)?;
+        self.traffic.reset(&service.name, port)?;
+        self.docker.remove_container(&green_name)?;
+        result.progress_percent = 100;
+        self.record(result).await?;
+        Ok(())
+    }
+
+    /// Record which version is now considered deployed.
+    async fn update_deployment_records(&self, result: &RollbackResult) -> Result<()> {
+        if result.status != RollbackStatus::Completed {
+            return Ok(());
+        }
+        let image = self.docker.rollback_image(&result.service, &result.to_commit);
+        let digest = self.docker.image_digest(&image).unwrap_or(None);
+        let deployment = crate::types::Deployment::new(
+            &result.service,
+            &result.to_commit,
+            &image,
+            digest,
+            result.approved_by.as_deref().unwrap_or("build-monitor"),
+        );
+        self.database.record_deployment(&deployment).await
+    }
+
+    async fn record(&self, r: &RollbackResult) -> Result<()> {
+        sqlx::query(
+            r#"
+            INSERT INTO rollbacks (id, service, from_commit, to_commit, strategy, status, reason, progress_percent, pre_checks, post_checks, approved_by, decided_at, created_at, completed_at)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14)
+            ON CONFLICT(id) DO UPDATE SET
+                status = excluded.status,
+                progress_percent = excluded.progress_percent,
+                pre_checks = excluded.pre_checks,
+                post_checks = excluded.post_checks,
+                approved_by = excluded.approved_by,
+                decided_at = excluded.decided_at,
+                completed_at = excluded.completed_at
+            "#,
+        )
+        .bind(r.id.to_string())
+        .bind(&r.service)
+        .bind(&r.from_commit)
+        .bind(&r.to_commit)
+        .bind(r.strategy.as_str())
+        .bind(r.status.as_str())
+        .bind(&r.reason)
+        .bind(r.progress_percent as i64)
+        .bind(serde_json::to_string(&r.pre_checks)?)
+        .bind(serde_json::to_string(&r.post_checks)?)
+        .bind(&r.approved_by)
+        .bind(r.decided_at.map(|t| t.to_rfc3339()))
+        .bind(r.created_at.to_rfc3339())
+        .bind(r.completed_at.map(|t| t.to_rfc3339()))
+        .execute(self.database.pool())
+        .await?;
+        Ok(())
+    }
+
+    pub async fn rollback_by_id(&self, id: Uuid) -> Result<Option<RollbackResult>> {
+        let row = sqlx::query("SELECT * FROM rollbacks WHERE id = ?1")
+            .bind(id.to_string())
+            .fetch_optional(self.database.pool())
+            .await?;
+        row.as_ref().map(row_to_rollback).transpose()
+    }
+
+    /// One page of rollback history, filtered and keyset-paginated per
+    /// the request; the `commit` filter matches either end of the move.
+    pub async fn rollback_history(&self, request: &PageRequest) -> Result<Vec<RollbackResult>> {
+        let sql = if request.descending {
+            r#"
+            SELECT * FROM rollbacks
+            WHERE (?1 IS NULL OR service = ?1)
+              AND (?2 IS NULL OR status = ?2)
+              AND (?3 IS NULL OR from_commit = ?3 OR to_commit = ?3)
+              AND (?4 IS NULL OR created_at >= ?4)
+              AND (?5 IS NULL OR created_at <= ?5)
+              AND (?6 IS NULL OR created_at < ?6 OR (created_at = ?6 AND id < ?7))
+            ORDER BY created_at DESC, id DESC LIMIT ?8
+            "#
+        } else {
+            r#"
+            SELECT * FROM rollbacks
+            WHERE (?1 IS NULL OR service = ?1)
+              AND (?2 IS NULL OR status = ?2)
+              AND (?3 IS NULL OR from_commit = ?3 OR to_commit = ?3)
+              AND (?4 IS NULL OR created_at >= ?4)
+              AND (?5 IS NULL OR created_at <= ?5)
+              AND (?6 IS NULL OR created_at > ?6 OR (created_at = ?6 AND id > ?7))
+            ORDER BY created_at ASC, id ASC LIMIT ?8
+            "#
+        };
+        let after = request.after.as_ref();
+        let rows = sqlx::query(sql)
+            .bind(&request.service)
+            .bind(&request.status)
+            .bind(&request.commit)
+            .bind(&request.since)
+            .bind(&request.until)
+            .bind(after.map(|(ts, _)| ts))
+            .bind(after.map(|(_, id)| id))
+            .bind(request.fetch_limit())
+            .fetch_all(self.database.pool())
+            .await?;
+        rows.iter().map(row_to_rollback).collect()
+    }
+}
+
+/// The service's configured environment with secret references resolved,
+/// ready for `docker run -e` injection.
+fn resolved_env(service: &ServiceConfig) -> Result<Vec<(String, String)>> {
+    Ok(crate::secrets::resolve_map(&service.env)?
+        .into_iter()
+        .map(|(key, resolved)| (key, resolved.value))
+        .collect())
+}
+
+fn new_result(
+    service: &ServiceConfig,
+    from_commit: &str,
+    to_commit: &str,
+    strategy: RollbackStrategy,
+    reason: Option<String>,
+    status: RollbackStatus,
+) -> RollbackResult {
+    RollbackResult {
+        id: Uuid::new_v4(),
+        service: service.name.clone(),
+        from_commit: from_commit.to_string(),
+        to_commit: to_commit.to_string(),
+        strategy,
+        status,
+        reason,
+        progress_percent: 0,
+        pre_checks: Vec::new(),
+        post_checks: Vec::new(),
+        approved_by: None,
+        decided_at: None,
+        created_at: Utc::now(),
+        completed_at: None,
+    }
+}
+
+fn row_to_rollback(row: &sqlx::sqlite::SqliteRow) -> Result<RollbackResult> {
+    let id: String = row.get("id");
+    let created_at: String = row.get("created_at");
+    let completed_at: Option<String> = row.get("completed_at");
+    let decided_at: Option<String> = row.get("decided_at");
+    Ok(RollbackResult {
+        id: Uuid::parse_str(&id)?,
+        service: row.get("service"),
+        from_commit: row.get("from_commit"),
+        to_commit: row.get("to_commit"),
+        strategy: RollbackStrategy::parse(row.get("strategy")),
+        status: RollbackStatus::parse(row.get("status")),
+        reason: row.get("reason"),
+        progress_percent: row.get::<i64, _>("progress_percent") as u8,
+        pre_checks: serde_json::from_str(row.get("pre_checks"))?,
+        post_checks: serde_json::from_str(row.get("post_checks"))?,
+        approved_by: row.get("approved_by"),
+        decided_at: decided_at
+            .map(|t| DateTime::parse_from_rfc3339(&t).map(|t| t.with_timezone(&Utc)))
+            .transpose()?,
+        created_at: DateTime::parse_from_rfc3339(&created_at)?.with_timezone(&Utc),
+        completed_at: completed_at
+            .map(|t| DateTime::parse_from_rfc3339(&t).map(|t| t.with_timezone(&Utc)))
+            .transpose()?,
+    })
+}
@@ -0,0 +1,129 @@
+//! Reference resolution for per-service environment and build-arg values.
+//!
+//! Service config values may be literal or references that are resolved
+//! at build time: `env://VAR` reads a process environment variable,
+//! `file:///run/secrets/x` reads a file, and `vault://secret/app#field`
+//! fetches a field with the vault CLI. Anything that came from a
+//! reference is treated as a secret and redacted from captured logs.
+
+use anyhow::{Context, Result};
+use std::collections::BTreeMap;
+use std::process::Command;
+
+/// A resolved config value plus whether it must never appear in logs.
+#[derive(Debug)]
+pub struct Resolved {
+    pub value: String,
+    /// True when the value came from a reference rather than a literal.
+    pub secret: bool,
+}
+
+/// Resolve one value. Literals pass through unchanged.
+pub fn resolve(value: &str) -> Result<Resolved> {
+    if let Some(var) = value.strip_prefix("env://") {
+        let value =
+            std::env::var(var).with_context(|| format!("referenced env var {var} is not set"))?;
+        return Ok(Resolved {
+            value,
+            secret: true,
+        });
+    }
+    if let Some(path) = value.strip_prefix("file://") {
+        let value = std::fs::read_to_string(path)
+            .with_context(|| format!("cannot read referenced file {path}"))?;
+        return Ok(Resolved {
+            value: value.trim_end().to_string(),
+            secret: true,
+        });
+    }
+    if let Some(reference) = value.strip_prefix("vault://") {
+        let (path, field) = reference
+            .split_once('#')
+            .with_context(|| format!("vault reference {reference} needs a #field suffix"))?;
+        let output = Command::new("vault")
+            .args(["kv", "get", &format!("-field={field}"), path])
+            .output()
+            .context("failed to invoke vault")?;
+        if !output.status.success() {
+            anyhow::bail!(
+                "vault kv get {path} failed: {}",
+                String::from_utf8_lossy(&output.stderr).trim()
+            );
+        }
+        return Ok(Resolved {
+            value: String::from_utf8_lossy(&output.stdout).trim_end().to_string(),
+            secret: true,
+        });
+    }
+    Ok(Resolved {
+        value: value.to_string(),
+        secret: false,
+    })
+}
+
+/// Resolve a whole config map, keeping its deterministic order.
+pub fn resolve_map(map: &BTreeMap<String, String>) -> Result<Vec<(String, Resolved)>> {
+    map.iter()
+        .map(|(key, value)| {
+            let resolved =
+                resolve(value).with_context(|| format!("cannot resolve value for {key}"))?;
+            Ok((key.clone(), resolved))
+        })
+        .collect()
+}
+
+/// The secret values out of a resolved map, for redaction.
+pub fn secret_values(resolved: &[(String, Resolved)]) -> Vec<String> {
+    resolved
+        .iter()
+        .filter(|(_, r)| r.secret && !r.value.is_empty())
+        .map(|(_, r)| r.value.clone())
+        .collect()
+}
+
+/// Replace every secret value occurring in `log` with `[redacted]`.
+pub fn redact(log: &str, secrets: &[String]) -> String {
+    let mut out = log.to_string();
+    for secret in secrets {
+        if !secret.is_empty() {
+            out = out.replace(secret.as_str(), "[redacted]");
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn references_resolve_and_mark_secrets() {
+        assert!(!resolve("plain-value").unwrap().secret);
+
+        std::env::set_var("SECRETS_TEST_TOKEN", "hunter2");
+        let from_env = resolve("env://SECRETS_TEST_TOKEN").unwrap();
+        assert_eq!(from_env.value, "hunter2");
+        assert!(from_env.secret);
+        assert!(resolve("env://SECRETS_TEST_MISSING").is_err());
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("token");
+        std::fs::write(&path, "s3cr3t\n").unwrap();
+        let from_file = resolve(&format!("file://{}", path.display())).unwrap();
+        assert_eq!(from_file.value, "s3cr3t");
+        assert!(from_file.secret);
+
+        // A vault reference without a field is rejected before any lookup.
+        assert!(resolve("vault://secret/app").is_err());
+    }
+
+    #[test]
+    fn logs_lose_every_secret_occurrence() {
+        let log = "login with hunter2\nretrying with hunter2\npassword ok";
+        let redacted = redact(log, &["hunter2".to_string()]);
+        assert!(!redacted.contains("hunter2"));
+        assert_eq!(redacted.matches("[redacted]").count(), 2);
+        // Literals are not secrets and stay readable.
+        assert_eq!(redact(log, &[]), log);
+    }
+}
@@ -0,0 +1,99 @@
+//! Traffic shifting between the stable and candidate containers.
+//!
+//! Shifts are expressed as weighted nginx upstream blocks written into the
+//! directory included by the edge proxy, followed by a reload. Container
+//! names resolve through Docker's embedded DNS on the compose network.
+
+use crate::config::NginxConfig;
+use anyhow::{bail, Context, Result};
+use std::process::Command;
+use tracing::info;
+
+pub struct TrafficController {
+    config: Option<NginxConfig>,
+}
+
+impl TrafficController {
+    pub fn new(config: Option<NginxConfig>) -> Self {
+        Self { config }
+    }
+
+    /// Whether gradual traffic shifting is available at all.
+    pub fn enabled(&self) -> bool {
+        self.config.is_some()
+    }
+
+    /// Route `canary_percent`% of traffic to the candidate container
+    /// (`<service>-canary`), the rest to the stable one.
+    pub fn shift(&self, service: &str, port: u16, canary_percent: u8) -> Result<()> {
+        let Some(config) = &self.config else {
+            bail!("traffic shifting requires nginx configuration");
+        };
+        let conf = render_upstream(service, port, canary_percent);
+        let path = config.upstream_dir.join(format!("{service}.conf"));
+        std::fs::write(&path, conf)
+            .with_context(|| format!("failed to write upstream config {}", path.display()))?;
+        info!(service, canary_percent, "shifted traffic");
+        self.reload()
+    }
+
+    /// Route all traffic back to the stable container.
+    pub fn reset(&self, service: &str, port: u16) -> Result<()> {
+        self.shift(service, port, 0)
+    }
+
+    fn reload(&self) -> Result<()> {
+        let Some(config) = &self.config else {
+            return Ok(());
+        };
+        let (program, args) = config
+            .reload_command
+            .split_first()
+            .context("nginx reload_command is empty")?;
+        let status = Command::new(program)
+            .args(args)
+            .status()
+            .with_context(|| format!("failed to invoke {program}"))?;
+        if !status.success() {
+            bail!("nginx reload exited with {status}");
+        }
+        Ok(())
+    }
+}
+
+/// Weighted upstream block sending `canary_percent`% of requests to the
+/// canary container. At 0% or 100% the losing side is omitted entirely so
+/// nginx never probes a stopped container.
+pub fn render_upstream(service: &str, port: u16, canary_percent: u8) -> String {
+    let canary = canary_percent.min(100);
+    let stable = 100 - canary;
+    let mut servers = String::new();
+    if stable > 0 {
+        servers.push_str(&format!("    server {service}:{port} weight={stable};\n"));
+    }
+    if canary > 0 {
+        servers.push_str(&format!("    server {service}-canary:{port} weight={canary};\n"));
+    }
+    format!("upstream {service}_backend {{\n{servers}}}\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn upstream_weights_follow_canary_percent() {
+        let conf = render_upstream("ml-api", 3000, 25);
+        assert!(conf.contains("server ml-api:3000 weight=75;"));
+        assert!(conf.contains("server ml-api-canary:3000 weight=25;"));
+    }
+
+    #[test]
+    fn full_shift_omits_the_losing_side() {
+        let conf = render_upstream("web", 3000, 0);
+        assert!(!conf.contains("web-canary"));
+        let conf = render_upstream("web", 3000, 100);
+        assert!(!conf.contains("server web:3000"));
+        assert!(conf.contains("server web-canary:3000 weight=100;"));
+    }
+}
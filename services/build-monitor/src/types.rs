@@ -0,0 +1,256 @@
+//! Core data types shared across the build-monitor subsystems.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// Outcome of a single build attempt for one service at one commit.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BuildResult {
+    pub id: Uuid,
+    pub service: String,
+    pub commit: String,
+    pub status: BuildStatus,
+    pub started_at: DateTime<Utc>,
+    pub finished_at: Option<DateTime<Utc>>,
+    pub duration_ms: Option<i64>,
+    pub error: Option<String>,
+    /// Number of build attempts made (>1 means the build was retried).
+    pub attempts: u32,
+    /// Watched branch or PR ref this build came from; `None` for the
+    /// monitored production branch.
+    #[serde(default)]
+    pub branch: Option<String>,
+    /// Per-variant results for services with a build matrix; empty when
+    /// the service declares none.
+    #[serde(default)]
+    pub variants: Vec<VariantStatus>,
+}
+
+impl BuildResult {
+    pub fn started(service: &str, commit: &str) -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            service: service.to_string(),
+            commit: commit.to_string(),
+            status: BuildStatus::Running,
+            started_at: Utc::now(),
+            finished_at: None,
+            duration_ms: None,
+            error: None,
+            attempts: 1,
+            branch: None,
+            variants: Vec::new(),
+        }
+    }
+
+    /// A build of a watched branch or PR ref rather than production.
+    pub fn started_on(service: &str, commit: &str, branch: &str) -> Self {
+        Self {
+            branch: Some(branch.to_string()),
+            ..Self::started(service, commit)
+        }
+    }
+
+    pub fn finish(&mut self, status: BuildStatus, error: Option<String>) {
+        let now = Utc::now();
+        self.duration_ms = Some((now - self.started_at).num_milliseconds());
+        self.finished_at = Some(now);
+        self.status = status;
+        self.error = error;
+    }
+}
+
+/// Outcome of one matrix variant within a [`BuildResult`].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VariantStatus {
+    pub name: String,
+    pub status: BuildStatus,
+    pub duration_ms: i64,
+    /// Whether a failure of this variant fails the whole build.
+    pub required: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BuildStatus {
+    Queued,
+    Running,
+    Success,
+    Failed,
+    /// The build exceeded its configured time limit and was killed.
+    TimedOut,
+}
+
+impl BuildStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            BuildStatus::Queued => "queued",
+            BuildStatus::Running => "running",
+            BuildStatus::Success => "success",
+            BuildStatus::Failed => "failed",
+            BuildStatus::TimedOut => "timed_out",
+        }
+    }
+
+    pub fn parse(s: &str) -> Self {
+        match s {
+            "queued" => BuildStatus::Queued,
+            "running" => BuildStatus::Running,
+            "success" => BuildStatus::Success,
+            "timed_out" => BuildStatus::TimedOut,
+            _ => BuildStatus::Failed,
+        }
+    }
+}
+
+/// Last observed health of a monitored service container.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ServiceHealth {
+    Healthy,
+    Degraded,
+    Down,
+    Unknown,
+}
+
+/// A point-in-time summary of one service shown on the dashboard.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ServiceStatus {
+    pub name: String,
+    pub health: ServiceHealth,
+    pub last_build: Option<BuildResult>,
+    pub last_good_commit: Option<String>,
+    pub consecutive_failures: u32,
+    /// 0.0..=1.0 share of recent initially-failing builds that passed on
+    /// retry; high values mean the failures are probably flaky.
+    pub flakiness_score: f64,
+}
+
+/// An emergency freeze: the service keeps being monitored but nothing is
+/// built, deployed, or rolled back automatically until an operator lifts
+/// the freeze.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Freeze {
+    pub service: String,
+    pub reason: String,
+    pub created_at: DateTime<Utc>,
+}
+
+/// One deployment of a service version, recorded whenever the monitor
+/// swaps the running container.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Deployment {
+    pub id: Uuid,
+    pub service: String,
+    pub commit: String,
+    /// Image tag that was started.
+    pub image: String,
+    /// Content digest of the image, when the daemon reports one.
+    pub digest: Option<String>,
+    /// Who (or what) initiated the deployment.
+    pub deployed_by: String,
+    pub deployed_at: DateTime<Utc>,
+}
+
+impl Deployment {
+    pub fn new(
+        service: &str,
+        commit: &str,
+        image: &str,
+        digest: Option<String>,
+        deployed_by: &str,
+    ) -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            service: service.to_string(),
+            commit: commit.to_string(),
+            image: image.to_string(),
+            digest,
+            deployed_by: deployed_by.to_string(),
+            deployed_at: Utc::now(),
+        }
+    }
+}
+
+/// An addressable artifact produced by a successful build: an image digest
+/// for docker builds, a checksummed binary for the rest.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Artifact {
+    pub id: Uuid,
+    pub build_id: Uuid,
+    pub service: String,
+    pub commit: String,
+    /// "image" or "binary".
+    pub kind: String,
+    /// Image tag, local path, or remote URL.
+    pub reference: String,
+    /// Image digest or sha256 of the binary, when known.
+    pub digest: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl Artifact {
+    pub fn new(build: &BuildResult, kind: &str, reference: &str, digest: Option<String>) -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            build_id: build.id,
+            service: build.service.clone(),
+            commit: build.commit.clone(),
+            kind: kind.to_string(),
+            reference: reference.to_string(),
+            digest,
+            created_at: Utc::now(),
+        }
+    }
+}
+
+/// One entry in the audit log: who did what to which target.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuditEntry {
+    pub id: Uuid,
+    pub actor: String,
+    pub role: String,
+    pub action: String,
+    pub target: Option<String>,
+    pub detail: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Severity attached to alerts and notifications.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Severity {
+    Info,
+    Warning,
+    Critical,
+}
+
+impl Severity {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Severity::Info => "info",
+            Severity::Warning => "warning",
+            Severity::Critical => "critical",
+        }
+    }
+
+    pub fn parse(s: &str) -> Self {
+        match s {
+            "warning" => Severity::Warning,
+            "critical" => Severity::Critical,
+            _ => Severity::Info,
+        }
+    }
+}
+
+/// A recorded alert, as served by `/api/alerts`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Alert {
+    pub id: Uuid,
+    pub severity: Severity,
+    /// Absent for monitor-wide alerts.
+    pub service: Option<String>,
+    pub message: String,
+    pub created_at: DateTime<Utc>,
+}
@@ -0,0 +1,162 @@
+//! Host-resource watchdog.
+//!
+//! Builds fail in confusing ways long after the real problem started:
+//! Docker images eat the disk, a runaway build eats memory, load climbs.
+//! The watchdog samples the host every poll cycle, warns through the
+//! notification system before builds start failing, and force-runs image
+//! cleanup when disk gets critical.
+
+use crate::config::WatchdogConfig;
+use anyhow::Result;
+use std::collections::HashMap;
+use std::time::Instant;
+use tokio::sync::RwLock;
+
+/// One sample of the host's resources.
+#[derive(Debug, Clone, Copy)]
+pub struct ResourceSnapshot {
+    pub free_disk_mb: u64,
+    pub free_memory_mb: u64,
+    /// 1-minute load average divided by CPU count.
+    pub load_per_cpu: f64,
+}
+
+/// A threshold crossing worth telling someone about.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ResourceIssue {
+    LowDisk { free_mb: u64, min_mb: u64 },
+    LowMemory { free_mb: u64, min_mb: u64 },
+    HighLoad { per_cpu: f64, max_per_cpu: f64 },
+}
+
+impl ResourceIssue {
+    /// Stable key used for warning cooldowns.
+    pub fn key(&self) -> &'static str {
+        match self {
+            ResourceIssue::LowDisk { .. } => "disk",
+            ResourceIssue::LowMemory { .. } => "memory",
+            ResourceIssue::HighLoad { .. } => "load",
+        }
+    }
+
+    pub fn message(&self) -> String {
+        match self {
+            ResourceIssue::LowDisk { free_mb, min_mb } => {
+                format!("low disk on build host: {free_mb} MB free (threshold {min_mb} MB)")
+            }
+            ResourceIssue::LowMemory { free_mb, min_mb } => {
+                format!("low memory on build host: {free_mb} MB available (threshold {min_mb} MB)")
+            }
+            ResourceIssue::HighLoad { per_cpu, max_per_cpu } => {
+                format!("high load on build host: {per_cpu:.1} per CPU (threshold {max_per_cpu:.1})")
+            }
+        }
+    }
+}
+
+/// Compare a snapshot against configured thresholds.
+pub fn evaluate(snapshot: &ResourceSnapshot, config: &WatchdogConfig) -> Vec<ResourceIssue> {
+    let mut issues = Vec::new();
+    if snapshot.free_disk_mb < config.warn_free_disk_mb {
+        issues.push(ResourceIssue::LowDisk {
+            free_mb: snapshot.free_disk_mb,
+            min_mb: config.warn_free_disk_mb,
+        });
+    }
+    if snapshot.free_memory_mb < config.warn_free_memory_mb {
+        issues.push(ResourceIssue::LowMemory {
+            free_mb: snapshot.free_memory_mb,
+            min_mb: config.warn_free_memory_mb,
+        });
+    }
+    if snapshot.load_per_cpu > config.max_load_per_cpu {
+        issues.push(ResourceIssue::HighLoad {
+            per_cpu: snapshot.load_per_cpu,
+            max_per_cpu: config.max_load_per_cpu,
+        });
+    }
+    issues
+}
+
+pub struct Watchdog {
+    config: WatchdogConfig,
+    /// Last time each issue kind was reported, for the warning cooldown.
+    warned: RwLock<HashMap<&'static str, Instant>>,
+}
+
+impl Watchdog {
+    pub fn new(config: WatchdogConfig) -> Self {
+        Self {
+            config,
+            warned: RwLock::new(HashMap::new()),
+        }
+    }
+
+    pub fn config(&self) -> &WatchdogConfig {
+        &self.config
+    }
+
+    /// Sample the host through the platform probes; values the host
+    /// cannot report come back as "fine" so thresholds never fire on
+    /// missing data.
+    pub fn snapshot(&self) -> Result<ResourceSnapshot> {
+        Ok(ResourceSnapshot {
+            free_disk_mb: crate::platform::free_disk_mb("/var/lib/docker")?,
+            free_memory_mb: crate::platform::free_memory_mb().unwrap_or(u64::MAX),
+            load_per_cpu: crate::platform::load_per_cpu().unwrap_or(0.0),
+        })
+    }
+
+    /// Whether an issue should be reported now, honouring the cooldown.
+    pub async fn should_warn(&self, issue: &ResourceIssue) -> bool {
+        let mut warned = self.warned.write().await;
+        let now = Instant::now();
+        match warned.get(issue.key()) {
+            Some(t) if now.duration_since(*t).as_secs() < self.config.warn_cooldown_secs => false,
+            _ => {
+                warned.insert(issue.key(), now);
+                true
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn snapshot(disk: u64, memory: u64, load: f64) -> ResourceSnapshot {
+        ResourceSnapshot {
+            free_disk_mb: disk,
+            free_memory_mb: memory,
+            load_per_cpu: load,
+        }
+    }
+
+    #[test]
+    fn thresholds_flag_the_right_issues() {
+        let config = WatchdogConfig::default();
+        assert!(evaluate(&snapshot(100_000, 8_000, 0.5), &config).is_empty());
+
+        let issues = evaluate(&snapshot(100, 100, 10.0), &config);
+        let keys: Vec<_> = issues.iter().map(|i| i.key()).collect();
+        assert_eq!(keys, vec!["disk", "memory", "load"]);
+    }
+
+    #[tokio::test]
+    async fn warnings_respect_the_cooldown() {
+        let watchdog = Watchdog::new(WatchdogConfig::default());
+        let issue = ResourceIssue::LowDisk {
+            free_mb: 100,
+            min_mb: 2048,
+        };
+        assert!(watchdog.should_warn(&issue).await);
+        assert!(!watchdog.should_warn(&issue).await);
+        // A different kind of issue warns independently.
+        let load = ResourceIssue::HighLoad {
+            per_cpu: 10.0,
+            max_per_cpu: 2.0,
+        };
+        assert!(watchdog.should_warn(&load).await);
+    }
+}
@@ -0,0 +1,698 @@
+//! Embedded web server exposing the dashboard API.
+
+use crate::auth::{Identity, Role};
+use crate::monitor::BuildMonitor;
+use crate::paging::{cursor, paginate, PageQuery};
+use crate::rollback::RollbackStrategy;
+use axum::extract::{Path, Query, Request, State};
+use axum::http::StatusCode;
+use axum::middleware::Next;
+use axum::response::sse::{Event, KeepAlive, Sse};
+use axum::response::{IntoResponse, Response};
+use axum::routing::{get, post};
+use axum::{Extension, Json, Router};
+use futures::stream::Stream;
+use serde::Deserialize;
+use serde_json::json;
+use std::sync::Arc;
+use tokio_stream::wrappers::errors::BroadcastStreamRecvError;
+use tokio_stream::wrappers::BroadcastStream;
+use tokio_stream::StreamExt;
+use tower_http::cors::CorsLayer;
+use tracing::info;
+
+pub struct WebServer {
+    monitor: Arc<BuildMonitor>,
+}
+
+impl WebServer {
+    pub fn new(monitor: Arc<BuildMonitor>) -> Self {
+        Self { monitor }
+    }
+
+    pub fn router(&self) -> Router {
+        Router::new()
+            .route("/", get(index))
+            .route("/api/dashboard", get(dashboard))
+            .route("/api/events", get(events))
+            .route("/api/services", get(list_services))
+            .route("/api/services/{name}/builds", get(service_builds))
+            .route("/api/builds/{id}", get(build_by_id))
+            .route("/api/services/{name}/builds/{id}/logs", get(build_logs))
+            .route("/api/rollbacks", get(rollback_history))
+            .route("/api/alerts", get(alerts))
+            .route("/api/deployments", get(current_deployments))
+            .route("/api/services/{name}/deployments", get(deployment_history))
+            .route("/api/services/{name}/artifacts", get(service_artifacts))
+            .route("/api/analytics", get(analytics))
+            .route("/api/bisects", get(bisect_sessions))
+            .route("/api/notifications/deliveries", get(notification_deliveries))
+            .route("/api/services/{name}/rollback", post(trigger_rollback))
+            .route("/api/rollbacks/{id}/approve", post(approve_rollback))
+            .route("/api/rollbacks/{id}/reject", post(reject_rollback))
+            .route("/api/freezes", get(list_freezes))
+            .route("/api/services/{name}/unfreeze", post(unfreeze))
+            .route("/api/pauses", get(list_pauses))
+            .route("/api/pause", post(pause))
+            .route("/api/resume", post(resume))
+            .route("/api/audit", get(audit_log))
+            .route("/metrics", get(metrics))
+            .layer(axum::middleware::from_fn_with_state(
+                self.monitor.clone(),
+                require_auth,
+            ))
+            // The health endpoint stays open so probes and the CLI's
+            // reachability check work without a token.
+            .route("/health", get(health))
+            .layer(axum::middleware::from_fn(trace_context))
+            .layer(CorsLayer::permissive())
+            .with_state(self.monitor.clone())
+    }
+
+    pub async fn serve(&self) -> anyhow::Result<()> {
+        let addr = format!(
+            "{}:{}",
+            self.monitor.config.web.bind, self.monitor.config.web.port
+        );
+        match &self.monitor.config.web.tls {
+            Some(tls) => {
+                let config = crate::tls::server_config(tls)?;
+                let mtls = tls.client_ca_path.is_some();
+                info!("web server listening on {addr} (tls, mtls={mtls})");
+                axum_server::bind_rustls(
+                    addr.parse()?,
+                    axum_server::tls_rustls::RustlsConfig::from_config(config),
+                )
+                .serve(self.router().into_make_service())
+                .await?;
+            }
+            None => {
+                let listener = tokio::net::TcpListener::bind(&addr).await?;
+                info!("web server listening on {addr}");
+                axum::serve(listener, self.router()).await?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Run every request inside a span carrying the caller's trace context;
+/// see the aurum-telemetry crate for how the context propagates.
+async fn trace_context(request: Request, next: Next) -> Response {
+    use tracing::Instrument;
+    let traceparent = request
+        .headers()
+        .get(aurum_telemetry::TRACEPARENT)
+        .and_then(|v| v.to_str().ok())
+        .map(|v| v.to_string());
+    let span = aurum_telemetry::request_span(
+        request.method().as_str(),
+        request.uri().path(),
+        traceparent.as_deref(),
+    );
+    next.run(request).instrument(span).await
+}
+
+type ApiResult<T> = Result<T, (StatusCode, Json<serde_json::Value>)>;
+
+/// Reject stack-mutating requests on a standby replica; the caller should
+/// retry against the leader.
+fn require_leader(monitor: &BuildMonitor) -> Result<(), (StatusCode, Json<serde_json::Value>)> {
+    if monitor.is_leader() {
+        Ok(())
+    } else {
+        Err((
+            StatusCode::CONFLICT,
+            Json(json!({ "error": "this replica is not the leader; retry against the active monitor" })),
+        ))
+    }
+}
+
+fn internal_error(e: anyhow::Error) -> (StatusCode, Json<serde_json::Value>) {
+    (
+        StatusCode::INTERNAL_SERVER_ERROR,
+        Json(json!({ "error": format!("{e:#}") })),
+    )
+}
+
+fn bad_request(e: anyhow::Error) -> (StatusCode, Json<serde_json::Value>) {
+    (
+        StatusCode::BAD_REQUEST,
+        Json(json!({ "error": format!("{e:#}") })),
+    )
+}
+
+/// Authenticate every API request and attach the caller's identity. Any
+/// valid token grants read access; mutating handlers check roles on top.
+async fn require_auth(
+    State(monitor): State<Arc<BuildMonitor>>,
+    mut request: Request,
+    next: Next,
+) -> Response {
+    let bearer = request
+        .headers()
+        .get(axum::http::header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "));
+    match monitor.auth.authenticate(bearer) {
+        Some(identity) => {
+            request.extensions_mut().insert(identity);
+            next.run(request).await
+        }
+        None => (
+            StatusCode::UNAUTHORIZED,
+            Json(json!({ "error": "missing or invalid API token" })),
+        )
+            .into_response(),
+    }
+}
+
+fn require(identity: &Identity, required: Role) -> Result<(), (StatusCode, Json<serde_json::Value>)> {
+    if identity.allows(required) {
+        Ok(())
+    } else {
+        Err((
+            StatusCode::FORBIDDEN,
+            Json(json!({ "error": format!("requires the {} role", required.as_str()) })),
+        ))
+    }
+}
+
+/// Dashboard page, embedded at compile time so the binary stays
+/// self-contained. It renders off `/api/dashboard` and live-updates from
+/// `/api/events`.
+async fn index() -> impl IntoResponse {
+    axum::response::Html(include_str!("../assets/dashboard.html"))
+}
+
+/// Prometheus exposition endpoint.
+async fn metrics(State(monitor): State<Arc<BuildMonitor>>) -> ApiResult<impl IntoResponse> {
+    let body = monitor.metrics.gather().map_err(internal_error)?;
+    Ok((
+        [(axum::http::header::CONTENT_TYPE, "text/plain; version=0.0.4")],
+        body,
+    ))
+}
+
+async fn health() -> impl IntoResponse {
+    Json(json!({ "status": "ok" }))
+}
+
+async fn dashboard(State(monitor): State<Arc<BuildMonitor>>) -> ApiResult<impl IntoResponse> {
+    let services = monitor.service_statuses().await.map_err(internal_error)?;
+    let builds = monitor
+        .database
+        .recent_builds(None, 20)
+        .await
+        .map_err(internal_error)?;
+    Ok(Json(json!({
+        "services": services,
+        "recent_builds": builds,
+        "is_leader": monitor.is_leader(),
+    })))
+}
+
+/// Stream monitor events to the dashboard as Server-Sent Events.
+///
+/// Each event is named after [`MonitorEvent::kind`] with a JSON payload, so
+/// clients can use `EventSource.addEventListener("build_failed", ...)`.
+/// Slow consumers that fall more than the channel buffer behind are sent a
+/// `lagged` event instead of being disconnected.
+async fn events(State(monitor): State<Arc<BuildMonitor>>) -> Sse<impl Stream<Item = Result<Event, std::convert::Infallible>>> {
+    let rx = monitor.events.subscribe();
+    let stream = BroadcastStream::new(rx).map(|item| {
+        let event = match item {
+            Ok(stamped) => {
+                let data = serde_json::to_string(&stamped)
+                    .unwrap_or_else(|e| format!("{{\"error\":\"{e}\"}}"));
+                Event::default().event(stamped.event.kind()).data(data)
+            }
+            Err(BroadcastStreamRecvError::Lagged(missed)) => Event::default()
+                .event("lagged")
+                .data(json!({ "missed": missed }).to_string()),
+        };
+        Ok(event)
+    });
+    Sse::new(stream).keep_alive(KeepAlive::default())
+}
+
+async fn list_services(State(monitor): State<Arc<BuildMonitor>>) -> ApiResult<impl IntoResponse> {
+    let services = monitor.service_statuses().await.map_err(internal_error)?;
+    Ok(Json(services))
+}
+
+#[derive(Deserialize)]
+struct BuildsQuery {
+    #[serde(default = "default_limit")]
+    limit: i64,
+}
+
+fn default_limit() -> i64 {
+    50
+}
+
+/// Build history for a service: a cursor-paginated envelope supporting
+/// status/commit/date-range filters and `order=asc|desc`.
+async fn service_builds(
+    State(monitor): State<Arc<BuildMonitor>>,
+    Path(name): Path<String>,
+    Query(query): Query<PageQuery>,
+) -> ApiResult<impl IntoResponse> {
+    let mut request = query.request().map_err(bad_request)?;
+    request.service = Some(name);
+    let fetched = monitor
+        .database
+        .builds_page(&request)
+        .await
+        .map_err(internal_error)?;
+    Ok(Json(paginate(fetched, &request, |b| {
+        cursor(b.started_at, b.id)
+    })))
+}
+
+/// Recent notification delivery attempts (including dead letters), for
+/// debugging "why didn't the alert fire" incidents.
+async fn notification_deliveries(
+    State(monitor): State<Arc<BuildMonitor>>,
+    Query(query): Query<BuildsQuery>,
+) -> ApiResult<impl IntoResponse> {
+    let deliveries = monitor
+        .notifications
+        .recent_deliveries(query.limit)
+        .await
+        .map_err(internal_error)?;
+    Ok(Json(deliveries))
+}
+
+#[derive(Deserialize)]
+struct LogsQuery {
+    /// Return only the last N lines.
+    #[serde(default)]
+    tail: Option<usize>,
+}
+
+/// Captured build log as plain text.
+async fn build_logs(
+    State(monitor): State<Arc<BuildMonitor>>,
+    Path((_name, id)): Path<(String, uuid::Uuid)>,
+    Query(query): Query<LogsQuery>,
+) -> ApiResult<impl IntoResponse> {
+    let log = match query.tail {
+        Some(n) => monitor.logs.tail(id, n),
+        None => monitor.logs.read(id),
+    }
+    .map_err(internal_error)?
+    .ok_or((
+        StatusCode::NOT_FOUND,
+        Json(json!({ "error": "no log captured for this build" })),
+    ))?;
+    Ok(log.into_response())
+}
+
+async fn build_by_id(
+    State(monitor): State<Arc<BuildMonitor>>,
+    Path(id): Path<uuid::Uuid>,
+) -> ApiResult<impl IntoResponse> {
+    let build = monitor
+        .database
+        .build_by_id(id)
+        .await
+        .map_err(internal_error)?
+        .ok_or((
+            StatusCode::NOT_FOUND,
+            Json(json!({ "error": "build not found" })),
+        ))?;
+    Ok(Json(build))
+}
+
+#[derive(Deserialize)]
+struct AnalyticsQuery {
+    /// How many days of history to aggregate.
+    #[serde(default = "default_analytics_days")]
+    days: i64,
+}
+
+fn default_analytics_days() -> i64 {
+    7
+}
+
+/// Aggregated failure rates, MTTR, duration trends, and failure
+/// signatures per service.
+async fn analytics(
+    State(monitor): State<Arc<BuildMonitor>>,
+    Query(query): Query<AnalyticsQuery>,
+) -> ApiResult<impl IntoResponse> {
+    let report = crate::analytics::report(&monitor.database, &monitor.config.services, query.days)
+        .await
+        .map_err(internal_error)?;
+    Ok(Json(report))
+}
+
+/// Bisect sessions with progress, newest first.
+async fn bisect_sessions(
+    State(monitor): State<Arc<BuildMonitor>>,
+    Query(query): Query<BuildsQuery>,
+) -> ApiResult<impl IntoResponse> {
+    let sessions = monitor
+        .bisect
+        .sessions(query.limit)
+        .await
+        .map_err(internal_error)?;
+    Ok(Json(sessions))
+}
+
+/// What is currently running: the latest deployment of each service.
+async fn current_deployments(
+    State(monitor): State<Arc<BuildMonitor>>,
+) -> ApiResult<impl IntoResponse> {
+    let deployments = monitor
+        .database
+        .current_deployments()
+        .await
+        .map_err(internal_error)?;
+    Ok(Json(deployments))
+}
+
+async fn deployment_history(
+    State(monitor): State<Arc<BuildMonitor>>,
+    Path(name): Path<String>,
+    Query(query): Query<BuildsQuery>,
+) -> ApiResult<impl IntoResponse> {
+    let deployments = monitor
+        .database
+        .deployment_history(&name, query.limit)
+        .await
+        .map_err(internal_error)?;
+    Ok(Json(deployments))
+}
+
+/// Recorded build artifacts for a service, newest first.
+async fn service_artifacts(
+    State(monitor): State<Arc<BuildMonitor>>,
+    Path(name): Path<String>,
+    Query(query): Query<BuildsQuery>,
+) -> ApiResult<impl IntoResponse> {
+    let artifacts = monitor
+        .database
+        .artifacts_for_service(&name, query.limit)
+        .await
+        .map_err(internal_error)?;
+    Ok(Json(artifacts))
+}
+
+/// Rollback history: the same paginated envelope as build history, with
+/// service/status/commit/date-range filters.
+async fn rollback_history(
+    State(monitor): State<Arc<BuildMonitor>>,
+    Query(query): Query<PageQuery>,
+) -> ApiResult<impl IntoResponse> {
+    let request = query.request().map_err(bad_request)?;
+    let fetched = monitor
+        .rollback
+        .rollback_history(&request)
+        .await
+        .map_err(internal_error)?;
+    Ok(Json(paginate(fetched, &request, |r| {
+        cursor(r.created_at, r.id)
+    })))
+}
+
+/// Recorded alerts: the same paginated envelope, with severity/service/
+/// date-range filters.
+async fn alerts(
+    State(monitor): State<Arc<BuildMonitor>>,
+    Query(query): Query<PageQuery>,
+) -> ApiResult<impl IntoResponse> {
+    let request = query.request().map_err(bad_request)?;
+    let fetched = monitor
+        .database
+        .alerts_page(&request)
+        .await
+        .map_err(internal_error)?;
+    Ok(Json(paginate(fetched, &request, |a| {
+        cursor(a.created_at, a.id)
+    })))
+}
+
+#[derive(Deserialize)]
+struct RollbackRequest {
+    to_commit: String,
+    #[serde(default)]
+    strategy: Option<String>,
+    #[serde(default)]
+    reason: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct ApprovalRequest {
+    /// Who is making the decision; recorded in the audit log.
+    #[serde(default = "default_approver")]
+    by: String,
+}
+
+fn default_approver() -> String {
+    "api".to_string()
+}
+
+/// Who a decision is attributed to: the authenticated identity when auth
+/// is on, otherwise whatever the request body claims.
+fn decided_by(monitor: &BuildMonitor, identity: &Identity, body: Option<Json<ApprovalRequest>>) -> String {
+    if monitor.auth.enabled() {
+        identity.name.clone()
+    } else {
+        body.map(|Json(r)| r.by).unwrap_or_else(default_approver)
+    }
+}
+
+async fn approve_rollback(
+    State(monitor): State<Arc<BuildMonitor>>,
+    Path(id): Path<uuid::Uuid>,
+    Extension(identity): Extension<Identity>,
+    body: Option<Json<ApprovalRequest>>,
+) -> ApiResult<impl IntoResponse> {
+    require(&identity, Role::Admin)?;
+    require_leader(&monitor)?;
+    let by = decided_by(&monitor, &identity, body);
+    let result = monitor
+        .rollback
+        .approve(id, &by)
+        .await
+        .map_err(internal_error)?;
+    monitor
+        .database
+        .record_audit(
+            &by,
+            identity.role.as_str(),
+            "rollback_approve",
+            Some(&result.service),
+            Some(&id.to_string()),
+        )
+        .await
+        .map_err(internal_error)?;
+    Ok(Json(result))
+}
+
+async fn reject_rollback(
+    State(monitor): State<Arc<BuildMonitor>>,
+    Path(id): Path<uuid::Uuid>,
+    Extension(identity): Extension<Identity>,
+    body: Option<Json<ApprovalRequest>>,
+) -> ApiResult<impl IntoResponse> {
+    require(&identity, Role::Admin)?;
+    require_leader(&monitor)?;
+    let by = decided_by(&monitor, &identity, body);
+    let result = monitor
+        .rollback
+        .reject(id, &by)
+        .await
+        .map_err(internal_error)?;
+    monitor
+        .database
+        .record_audit(
+            &by,
+            identity.role.as_str(),
+            "rollback_reject",
+            Some(&result.service),
+            Some(&id.to_string()),
+        )
+        .await
+        .map_err(internal_error)?;
+    Ok(Json(result))
+}
+
+#[derive(Deserialize)]
+struct PauseRequest {
+    /// Service to pause; omit to pause everything.
+    #[serde(default)]
+    service: Option<String>,
+    reason: String,
+    /// Auto-resume after this many minutes; omit to pause indefinitely.
+    #[serde(default)]
+    minutes: Option<i64>,
+}
+
+#[derive(Deserialize, Default)]
+struct ResumeRequest {
+    /// Service to resume; omit to lift a global pause.
+    #[serde(default)]
+    service: Option<String>,
+}
+
+/// Services frozen after rollback ping-pong, newest first.
+async fn list_freezes(State(monitor): State<Arc<BuildMonitor>>) -> ApiResult<impl IntoResponse> {
+    let freezes = monitor
+        .database
+        .frozen_services()
+        .await
+        .map_err(internal_error)?;
+    Ok(Json(freezes))
+}
+
+/// Lift an emergency freeze so automatic builds and deploys resume.
+async fn unfreeze(
+    State(monitor): State<Arc<BuildMonitor>>,
+    Extension(identity): Extension<Identity>,
+    Path(name): Path<String>,
+) -> ApiResult<impl IntoResponse> {
+    require(&identity, Role::Operator)?;
+    let lifted = monitor.unfreeze(&name).await.map_err(internal_error)?;
+    monitor
+        .database
+        .record_audit(
+            &identity.name,
+            identity.role.as_str(),
+            "unfreeze",
+            Some(&name),
+            None,
+        )
+        .await
+        .map_err(internal_error)?;
+    Ok(Json(json!({ "unfrozen": lifted })))
+}
+
+async fn list_pauses(State(monitor): State<Arc<BuildMonitor>>) -> ApiResult<impl IntoResponse> {
+    Ok(Json(monitor.maintenance.active_pauses().await))
+}
+
+async fn pause(
+    State(monitor): State<Arc<BuildMonitor>>,
+    Extension(identity): Extension<Identity>,
+    Json(req): Json<PauseRequest>,
+) -> ApiResult<impl IntoResponse> {
+    require(&identity, Role::Operator)?;
+    if let Some(name) = &req.service {
+        if monitor.config.service(name).is_none() {
+            return Err((
+                StatusCode::NOT_FOUND,
+                Json(json!({ "error": format!("unknown service {name}") })),
+            ));
+        }
+    }
+    let until = req.minutes.map(|m| chrono::Utc::now() + chrono::Duration::minutes(m));
+    let pause = monitor
+        .maintenance
+        .pause(req.service.clone(), req.reason.clone(), until)
+        .await;
+    monitor
+        .database
+        .record_audit(
+            &identity.name,
+            identity.role.as_str(),
+            "pause",
+            req.service.as_deref().or(Some("all")),
+            Some(&req.reason),
+        )
+        .await
+        .map_err(internal_error)?;
+    Ok(Json(pause))
+}
+
+async fn resume(
+    State(monitor): State<Arc<BuildMonitor>>,
+    Extension(identity): Extension<Identity>,
+    body: Option<Json<ResumeRequest>>,
+) -> ApiResult<impl IntoResponse> {
+    require(&identity, Role::Operator)?;
+    let req = body.map(|Json(r)| r).unwrap_or_default();
+    let resumed = monitor.maintenance.resume(req.service.as_deref()).await;
+    monitor
+        .database
+        .record_audit(
+            &identity.name,
+            identity.role.as_str(),
+            "resume",
+            req.service.as_deref().or(Some("all")),
+            None,
+        )
+        .await
+        .map_err(internal_error)?;
+    Ok(Json(json!({ "resumed": resumed })))
+}
+
+/// Recent audit log entries, newest first.
+async fn audit_log(
+    State(monitor): State<Arc<BuildMonitor>>,
+    Query(query): Query<BuildsQuery>,
+) -> ApiResult<impl IntoResponse> {
+    let entries = monitor
+        .database
+        .recent_audit(query.limit)
+        .await
+        .map_err(internal_error)?;
+    Ok(Json(entries))
+}
+
+async fn trigger_rollback(
+    State(monitor): State<Arc<BuildMonitor>>,
+    Path(name): Path<String>,
+    Extension(identity): Extension<Identity>,
+    Json(req): Json<RollbackRequest>,
+) -> ApiResult<impl IntoResponse> {
+    require(&identity, Role::Operator)?;
+    require_leader(&monitor)?;
+    let service = monitor.config.service(&name).cloned().ok_or((
+        StatusCode::NOT_FOUND,
+        Json(json!({ "error": format!("unknown service {name}") })),
+    ))?;
+    let from = monitor
+        .database
+        .last_successful_commit(&name)
+        .await
+        .map_err(internal_error)?
+        .unwrap_or_else(|| "unknown".to_string());
+    let conflicts = monitor
+        .rollback_conflicts(&service, &req.to_commit)
+        .await
+        .map_err(internal_error)?;
+    if !conflicts.is_empty() {
+        return Err((
+            StatusCode::CONFLICT,
+            Json(json!({
+                "error": "rollback target is incompatible with dependent services",
+                "dependents": conflicts,
+            })),
+        ));
+    }
+    let strategy = req
+        .strategy
+        .as_deref()
+        .map(RollbackStrategy::parse)
+        .unwrap_or(RollbackStrategy::Immediate);
+    monitor
+        .database
+        .record_audit(
+            &identity.name,
+            identity.role.as_str(),
+            "rollback",
+            Some(&name),
+            Some(&format!("to {} ({})", req.to_commit, strategy.as_str())),
+        )
+        .await
+        .map_err(internal_error)?;
+    let result = monitor
+        .rollback
+        .perform_rollback(&service, &from, &req.to_commit, strategy, req.reason)
+        .await
+        .map_err(internal_error)?;
+    Ok(Json(result))
+}
@@ -0,0 +1,188 @@
+//! REST API over the detector, for the miniapp backend and the other ML
+//! services.
+
+use crate::config::{DetectionConfig, TrackingConfig};
+use crate::processors::{DetectionOptions, FaceDetector};
+use crate::tracker::FaceTracker;
+use axum::extract::ws::{Message, WebSocket, WebSocketUpgrade};
+use axum::extract::{Query, State};
+use axum::http::StatusCode;
+use axum::response::IntoResponse;
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use serde_json::json;
+use std::sync::Arc;
+use tower_http::compression::CompressionLayer;
+use tower_http::cors::CorsLayer;
+use tracing::info;
+
+#[derive(Clone)]
+struct AppState {
+    detector: Arc<FaceDetector>,
+    tracking: TrackingConfig,
+    fetch: Arc<aurum_objectstore::FetchConfig>,
+}
+
+pub struct ApiServer {
+    config: DetectionConfig,
+    state: AppState,
+}
+
+impl ApiServer {
+    pub fn new(config: DetectionConfig) -> Self {
+        let state = AppState {
+            detector: Arc::new(FaceDetector::new(&config)),
+            tracking: config.tracking.clone(),
+            fetch: Arc::new(config.fetch.clone()),
+        };
+        Self { config, state }
+    }
+
+    pub fn router(&self) -> Router {
+        Router::new()
+            .route("/api/detect", post(detect))
+            .route("/api/stream", get(stream))
+            .route("/health", get(health))
+            .layer(axum::middleware::from_fn(trace_context))
+            // Negotiated on Accept-Encoding; face lists with landmarks
+            // compress well and group photos return many of them.
+            .layer(CompressionLayer::new())
+            .layer(CorsLayer::permissive())
+            .with_state(self.state.clone())
+    }
+
+    pub async fn serve(&self) -> anyhow::Result<()> {
+        let addr = format!("{}:{}", self.config.web.bind, self.config.web.port);
+        let listener = tokio::net::TcpListener::bind(&addr).await?;
+        info!("api server listening on {addr}");
+        axum::serve(listener, self.router()).await?;
+        Ok(())
+    }
+}
+
+/// Run every request inside a span carrying the caller's trace context,
+/// so one photo's journey through the pipeline shows up as one trace.
+async fn trace_context(
+    request: axum::extract::Request,
+    next: axum::middleware::Next,
+) -> axum::response::Response {
+    use tracing::Instrument;
+    let traceparent = request
+        .headers()
+        .get(aurum_telemetry::TRACEPARENT)
+        .and_then(|v| v.to_str().ok())
+        .map(|v| v.to_string());
+    let span = aurum_telemetry::request_span(
+        request.method().as_str(),
+        request.uri().path(),
+        traceparent.as_deref(),
+    );
+    next.run(request).instrument(span).await
+}
+
+type ApiResult<T> = Result<T, (StatusCode, Json<serde_json::Value>)>;
+
+fn internal_error(e: anyhow::Error) -> (StatusCode, Json<serde_json::Value>) {
+    (
+        StatusCode::INTERNAL_SERVER_ERROR,
+        Json(json!({ "error": format!("{e:#}") })),
+    )
+}
+
+fn unprocessable(e: anyhow::Error) -> (StatusCode, Json<serde_json::Value>) {
+    (
+        StatusCode::UNPROCESSABLE_ENTITY,
+        Json(json!({ "error": format!("{e:#}") })),
+    )
+}
+
+async fn health() -> impl IntoResponse {
+    Json(json!({ "status": "ok" }))
+}
+
+/// Video mode: every binary WebSocket message is one encoded frame, and
+/// every reply is that frame's detection result with per-connection
+/// stable `track_id`s, so the client can annotate a moving face without
+/// flicker. Threshold overrides come in as query parameters on the
+/// upgrade request, like `/api/detect`.
+async fn stream(
+    State(state): State<AppState>,
+    Query(options): Query<DetectionOptions>,
+    upgrade: WebSocketUpgrade,
+) -> impl IntoResponse {
+    upgrade.on_upgrade(move |socket| stream_frames(state, options, socket))
+}
+
+async fn stream_frames(state: AppState, options: DetectionOptions, mut socket: WebSocket) {
+    let mut tracker = FaceTracker::new(state.tracking.clone());
+    let mut frame_index = 0u64;
+    while let Some(Ok(message)) = socket.recv().await {
+        let bytes = match message {
+            Message::Binary(bytes) => bytes,
+            Message::Close(_) => break,
+            // Axum answers pings itself; text frames carry nothing yet.
+            _ => continue,
+        };
+        frame_index += 1;
+        let detector = state.detector.clone();
+        let options = options.clone();
+        // Decode once for the tracker's appearance crops; detection
+        // normalizes its own copy. The backend is a subprocess, so keep
+        // the whole pass off the async runtime.
+        let result = tokio::task::spawn_blocking(move || -> anyhow::Result<_> {
+            let decoded = image::load_from_memory(&bytes)
+                .map_err(|e| anyhow::anyhow!("frame is not a decodable image: {e}"))?;
+            let faces = detector.detect(&bytes, &options)?;
+            Ok((decoded, faces))
+        })
+        .await;
+        // A bad frame answers with an error and the stream carries on;
+        // dropped cameras recover without a reconnect.
+        let reply = match result {
+            Ok(Ok((decoded, faces))) => {
+                let tracked = tracker.update(&decoded, faces);
+                json!({ "frame": frame_index, "count": tracked.len(), "faces": tracked })
+            }
+            Ok(Err(e)) => json!({ "frame": frame_index, "error": format!("{e:#}") }),
+            Err(e) => json!({ "frame": frame_index, "error": format!("detection task panicked: {e}") }),
+        };
+        if socket
+            .send(Message::Text(reply.to_string().into()))
+            .await
+            .is_err()
+        {
+            break;
+        }
+    }
+}
+
+/// Detect faces in the raw image body, or in the object a `source`
+/// query parameter references. Threshold overrides come in as query
+/// parameters: `min_confidence`, `iou_threshold`, and `max_faces`.
+async fn detect(
+    State(state): State<AppState>,
+    Query(options): Query<DetectionOptions>,
+    body: axum::body::Bytes,
+) -> ApiResult<impl IntoResponse> {
+    let image = match &options.source {
+        Some(_) if !body.is_empty() => {
+            return Err(unprocessable(anyhow::anyhow!(
+                "pass either an image body or a source, not both"
+            )));
+        }
+        Some(source) => aurum_objectstore::fetch(source, &state.fetch)
+            .await
+            .map_err(unprocessable)?,
+        None if body.is_empty() => {
+            return Err(unprocessable(anyhow::anyhow!("empty image body")));
+        }
+        None => body.to_vec(),
+    };
+    let detector = state.detector.clone();
+    // The backend is a subprocess; keep it off the async runtime.
+    let faces = tokio::task::spawn_blocking(move || detector.detect(&image, &options))
+        .await
+        .map_err(|e| internal_error(e.into()))?
+        .map_err(unprocessable)?;
+    Ok(Json(json!({ "count": faces.len(), "faces": faces })))
+}
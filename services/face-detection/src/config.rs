@@ -0,0 +1,218 @@
+//! Service configuration, loaded through the shared layered loader:
+//! defaults, then the JSON file, then `FACE_DETECTION__*` environment
+//! variables, then `--set` overrides.
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DetectionConfig {
+    #[serde(default)]
+    pub web: WebConfig,
+    /// The backend that runs the actual model.
+    #[serde(default)]
+    pub detector: DetectorConfig,
+    /// Default thresholds, used when a request does not override them.
+    #[serde(default)]
+    pub thresholds: ThresholdConfig,
+    /// Tiled inference for images too large for one backend pass.
+    #[serde(default)]
+    pub tiling: TilingConfig,
+    /// Cross-frame face tracking for the WebSocket video mode.
+    #[serde(default)]
+    pub tracking: TrackingConfig,
+    /// Object-storage fetch for by-reference image sources.
+    #[serde(default)]
+    pub fetch: aurum_objectstore::FetchConfig,
+}
+
+impl DetectionConfig {
+    /// Load configuration from `path`, layered under the environment
+    /// and `--set key.path=value` overrides; a missing file means
+    /// defaults.
+    pub fn load_with(path: &Path, overrides: &[String]) -> Result<Self> {
+        let mut loader = aurum_config::Loader::new("FACE_DETECTION").file(path);
+        for assignment in overrides {
+            loader = loader.set(assignment)?;
+        }
+        Ok(loader.load()?)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WebConfig {
+    #[serde(default = "default_bind")]
+    pub bind: String,
+    #[serde(default = "default_port")]
+    pub port: u16,
+}
+
+impl Default for WebConfig {
+    fn default() -> Self {
+        Self {
+            bind: default_bind(),
+            port: default_port(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DetectorConfig {
+    /// Backend command run through `sh -c` for each image. It reads the
+    /// image at `$IMAGE` and writes candidate detections to stdout as a
+    /// JSON array; see the processors module for the shape.
+    #[serde(default = "default_command")]
+    pub command: String,
+    /// Wall-clock limit per backend invocation; model startup on a cold
+    /// cache can dominate this.
+    #[serde(default = "default_timeout_secs")]
+    pub timeout_secs: u64,
+}
+
+impl Default for DetectorConfig {
+    fn default() -> Self {
+        Self {
+            command: default_command(),
+            timeout_secs: default_timeout_secs(),
+        }
+    }
+}
+
+/// Detection thresholds. Requests may override each of these, so the
+/// miniapp can run strict for onboarding selfies and lenient for feed
+/// photos against one deployment.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ThresholdConfig {
+    /// Candidates below this confidence are dropped.
+    #[serde(default = "default_min_confidence")]
+    pub min_confidence: f32,
+    /// Non-maximum suppression: of two candidates whose boxes overlap at
+    /// or above this IoU, only the more confident one survives.
+    #[serde(default = "default_iou_threshold")]
+    pub iou_threshold: f32,
+    /// At most this many faces are returned, most confident first.
+    #[serde(default = "default_max_faces")]
+    pub max_faces: usize,
+}
+
+impl Default for ThresholdConfig {
+    fn default() -> Self {
+        Self {
+            min_confidence: default_min_confidence(),
+            iou_threshold: default_iou_threshold(),
+            max_faces: default_max_faces(),
+        }
+    }
+}
+
+/// Tiling for high-resolution group photos. A single backend pass
+/// resizes its input down, which loses the small faces in the back rows;
+/// splitting into overlapping tiles keeps every face near native
+/// resolution.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TilingConfig {
+    /// Images whose longer side exceeds this many pixels are tiled;
+    /// everything smaller takes the single-pass path.
+    #[serde(default = "default_min_dimension")]
+    pub min_dimension: u32,
+    /// Edge length of each square tile.
+    #[serde(default = "default_tile_size")]
+    pub tile_size: u32,
+    /// Overlap between neighboring tiles, so a face on a tile border is
+    /// fully inside at least one of them.
+    #[serde(default = "default_overlap")]
+    pub overlap: u32,
+}
+
+impl Default for TilingConfig {
+    fn default() -> Self {
+        Self {
+            min_dimension: default_min_dimension(),
+            tile_size: default_tile_size(),
+            overlap: default_overlap(),
+        }
+    }
+}
+
+/// Cross-frame tracking for video streams. A face is matched to an
+/// existing track by box overlap first; when the subject moved too far
+/// between frames for the boxes to overlap, a cheap appearance embedding
+/// of the face crop rescues the match instead.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TrackingConfig {
+    /// Minimum IoU between a detection and a track's last box for a
+    /// positional match.
+    #[serde(default = "default_track_min_iou")]
+    pub min_iou: f32,
+    /// Minimum cosine similarity between appearance embeddings for a
+    /// match without box overlap.
+    #[serde(default = "default_track_min_similarity")]
+    pub min_similarity: f32,
+    /// Tracks unseen for this many frames are dropped; their ids are
+    /// never reused within a stream.
+    #[serde(default = "default_track_max_age")]
+    pub max_age_frames: u64,
+}
+
+impl Default for TrackingConfig {
+    fn default() -> Self {
+        Self {
+            min_iou: default_track_min_iou(),
+            min_similarity: default_track_min_similarity(),
+            max_age_frames: default_track_max_age(),
+        }
+    }
+}
+
+fn default_bind() -> String {
+    "127.0.0.1".to_string()
+}
+
+fn default_port() -> u16 {
+    9700
+}
+
+fn default_command() -> String {
+    "retinaface-cli --image \"$IMAGE\"".to_string()
+}
+
+fn default_timeout_secs() -> u64 {
+    30
+}
+
+fn default_min_confidence() -> f32 {
+    0.5
+}
+
+fn default_iou_threshold() -> f32 {
+    0.45
+}
+
+fn default_max_faces() -> usize {
+    16
+}
+
+fn default_min_dimension() -> u32 {
+    2000
+}
+
+fn default_tile_size() -> u32 {
+    1024
+}
+
+fn default_overlap() -> u32 {
+    256
+}
+
+fn default_track_min_iou() -> f32 {
+    0.3
+}
+
+fn default_track_min_similarity() -> f32 {
+    0.9
+}
+
+fn default_track_max_age() -> u64 {
+    30
+}
@@ -0,0 +1,87 @@
+//! face-detection: finds faces and landmarks in photos for the miniapp's
+//! onboarding and feed pipelines.
+
+mod api;
+mod config;
+mod processors;
+mod tracker;
+
+use anyhow::Result;
+use clap::{Parser, Subcommand};
+use config::DetectionConfig;
+use processors::{DetectionOptions, FaceDetector};
+use std::path::PathBuf;
+
+#[derive(Parser)]
+#[command(name = "face-detection", about = "Aurum face detection service")]
+struct Cli {
+    /// Path to the configuration file.
+    #[arg(long, default_value = "face-detection.json")]
+    config: PathBuf,
+    /// Override a config value over the file and environment, e.g.
+    /// `--set web.port=9000`; repeatable.
+    #[arg(long = "set", value_name = "KEY=VALUE")]
+    set: Vec<String>,
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Start the detection API server.
+    Serve,
+    /// Detect faces in a single image and print them as JSON.
+    Detect {
+        image: PathBuf,
+        /// Override the configured confidence floor.
+        #[arg(long)]
+        min_confidence: Option<f32>,
+        /// Override the configured suppression IoU.
+        #[arg(long)]
+        iou_threshold: Option<f32>,
+        /// Override the configured face cap.
+        #[arg(long)]
+        max_faces: Option<usize>,
+    },
+    /// Print the effective configuration — defaults, file, environment,
+    /// and --set overrides merged — as JSON, with secrets redacted.
+    PrintEffectiveConfig,
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    aurum_telemetry::init(aurum_telemetry::TelemetryConfig::from_env(
+        "face-detection",
+        "face_detection=info,warn",
+    ))?;
+
+    let cli = Cli::parse();
+    let config = DetectionConfig::load_with(&cli.config, &cli.set)?;
+
+    match cli.command {
+        Command::Serve => api::ApiServer::new(config).serve().await,
+        Command::Detect {
+            image,
+            min_confidence,
+            iou_threshold,
+            max_faces,
+        } => {
+            let bytes = std::fs::read(&image)?;
+            let detector = FaceDetector::new(&config);
+            let options = DetectionOptions {
+                min_confidence,
+                iou_threshold,
+                max_faces,
+                source: None,
+            };
+            let faces =
+                tokio::task::spawn_blocking(move || detector.detect(&bytes, &options)).await??;
+            println!("{}", serde_json::to_string_pretty(&faces)?);
+            Ok(())
+        }
+        Command::PrintEffectiveConfig => {
+            println!("{}", serde_json::to_string_pretty(&config)?);
+            Ok(())
+        }
+    }
+}
@@ -0,0 +1,445 @@
+//! Image processors behind the detection API.
+//!
+//! The neural network itself runs in an external backend command; this
+//! module turns its raw candidates into the faces the API returns:
+//! confidence filtering, non-maximum suppression, and the per-face
+//! landmark confidence the miniapp uses to judge how trustworthy the
+//! five-point alignment is. Images too large for one backend pass are
+//! split into overlapping tiles, detected per tile, and merged with
+//! cross-tile suppression, so the small faces in a 4000px group photo
+//! are not lost to the backend's input resize.
+
+use crate::config::{DetectionConfig, DetectorConfig, ThresholdConfig, TilingConfig};
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::io::{Cursor, Read};
+use std::process::{Command, Stdio};
+use std::time::{Duration, Instant};
+use tracing::debug;
+
+/// Axis-aligned box in pixel coordinates.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct BoundingBox {
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+}
+
+impl BoundingBox {
+    /// Intersection over union with `other`; 0.0 for disjoint boxes.
+    pub fn iou(&self, other: &BoundingBox) -> f32 {
+        let left = self.x.max(other.x);
+        let top = self.y.max(other.y);
+        let right = (self.x + self.width).min(other.x + other.width);
+        let bottom = (self.y + self.height).min(other.y + other.height);
+        if right <= left || bottom <= top {
+            return 0.0;
+        }
+        let intersection = (right - left) * (bottom - top);
+        let union = self.width * self.height + other.width * other.height - intersection;
+        if union <= 0.0 {
+            0.0
+        } else {
+            intersection / union
+        }
+    }
+}
+
+/// One facial landmark, e.g. "left_eye" or "nose".
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Landmark {
+    pub name: String,
+    pub x: f32,
+    pub y: f32,
+    /// Backend confidence that the landmark is where it says it is.
+    pub confidence: f32,
+}
+
+/// A candidate as the backend reports it, before filtering.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RawDetection {
+    pub bbox: BoundingBox,
+    pub confidence: f32,
+    #[serde(default)]
+    pub landmarks: Vec<Landmark>,
+}
+
+/// A face that survived filtering and suppression.
+#[derive(Debug, Clone, Serialize)]
+pub struct DetectedFace {
+    pub bbox: BoundingBox,
+    pub confidence: f32,
+    pub landmarks: Vec<Landmark>,
+    /// Mean landmark confidence; 0.0 when the backend reported no
+    /// landmarks. Onboarding flows reject faces this says are poorly
+    /// aligned even when the box itself is confident.
+    pub landmark_confidence: f32,
+}
+
+/// Per-request threshold overrides; absent fields fall back to the
+/// configured defaults.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct DetectionOptions {
+    #[serde(default)]
+    pub min_confidence: Option<f32>,
+    #[serde(default)]
+    pub iou_threshold: Option<f32>,
+    #[serde(default)]
+    pub max_faces: Option<usize>,
+    /// Fetch the image from object storage by reference instead of the
+    /// request body; resolved by the API layer before detection runs.
+    #[serde(default)]
+    pub source: Option<String>,
+}
+
+impl DetectionOptions {
+    pub fn resolve(&self, defaults: &ThresholdConfig) -> ThresholdConfig {
+        ThresholdConfig {
+            min_confidence: self.min_confidence.unwrap_or(defaults.min_confidence),
+            iou_threshold: self.iou_threshold.unwrap_or(defaults.iou_threshold),
+            max_faces: self.max_faces.unwrap_or(defaults.max_faces),
+        }
+    }
+}
+
+pub struct FaceDetector {
+    detector: DetectorConfig,
+    defaults: ThresholdConfig,
+    tiling: TilingConfig,
+}
+
+impl FaceDetector {
+    pub fn new(config: &DetectionConfig) -> Self {
+        Self {
+            detector: config.detector.clone(),
+            defaults: config.thresholds.clone(),
+            tiling: config.tiling.clone(),
+        }
+    }
+
+    /// Run the backend over `image` and return the surviving faces, most
+    /// confident first. The image is normalized to upright sRGB first;
+    /// oversized images then go through the tiled path.
+    pub fn detect(&self, image: &[u8], options: &DetectionOptions) -> Result<Vec<DetectedFace>> {
+        let (image, normalization) = aurum_image::normalize(image)?;
+        if normalization.changed {
+            debug!(
+                orientation = normalization.orientation,
+                profile = normalization.profile.as_str(),
+                "normalized image before detection"
+            );
+        }
+        let thresholds = options.resolve(&self.defaults);
+        if let Some(decoded) = self.oversized(image.as_ref()) {
+            return self.detect_tiled(&decoded, &thresholds);
+        }
+        let candidates = self.run_candidates(image.as_ref())?;
+        Ok(select(candidates, &thresholds))
+    }
+
+    /// One backend pass over raw image bytes.
+    fn run_candidates(&self, image: &[u8]) -> Result<Vec<RawDetection>> {
+        let file = tempfile::NamedTempFile::new().context("failed to create image temp file")?;
+        std::fs::write(file.path(), image).context("failed to write image temp file")?;
+        let stdout = run_backend(
+            &self.detector.command,
+            file.path(),
+            Duration::from_secs(self.detector.timeout_secs),
+        )?;
+        serde_json::from_str(&stdout).context("backend produced unparsable candidates")
+    }
+
+    /// Decode only far enough to learn the dimensions; returns the full
+    /// decode when the image is large enough to tile. Bytes the image
+    /// crate cannot read fall through to the single-pass path so the
+    /// backend sees them unchanged.
+    fn oversized(&self, image: &[u8]) -> Option<image::DynamicImage> {
+        let (width, height) = image::ImageReader::new(Cursor::new(image))
+            .with_guessed_format()
+            .ok()?
+            .into_dimensions()
+            .ok()?;
+        if width.max(height) <= self.tiling.min_dimension {
+            return None;
+        }
+        image::load_from_memory(image).ok()
+    }
+
+    /// Detect per overlapping tile, shift every candidate back into
+    /// whole-image coordinates, and let one suppression pass across all
+    /// tiles dedup the faces seen twice in an overlap zone.
+    fn detect_tiled(
+        &self,
+        decoded: &image::DynamicImage,
+        thresholds: &ThresholdConfig,
+    ) -> Result<Vec<DetectedFace>> {
+        let tile = self.tiling.tile_size;
+        let xs = tile_origins(decoded.width(), tile, self.tiling.overlap);
+        let ys = tile_origins(decoded.height(), tile, self.tiling.overlap);
+        debug!(
+            width = decoded.width(),
+            height = decoded.height(),
+            tiles = xs.len() * ys.len(),
+            "tiled inference"
+        );
+        let mut candidates = Vec::new();
+        for &ty in &ys {
+            for &tx in &xs {
+                let crop = decoded.crop_imm(tx, ty, tile, tile);
+                let mut buf = Vec::new();
+                crop.write_to(&mut Cursor::new(&mut buf), image::ImageFormat::Png)
+                    .context("failed to encode tile")?;
+                for detection in self.run_candidates(&buf)? {
+                    candidates.push(offset(detection, tx as f32, ty as f32));
+                }
+            }
+        }
+        Ok(select(candidates, thresholds))
+    }
+}
+
+/// Tile start offsets covering `extent` with `tile`-sized tiles that
+/// overlap by `overlap`; the last tile is pulled back flush with the
+/// edge rather than running past it.
+fn tile_origins(extent: u32, tile: u32, overlap: u32) -> Vec<u32> {
+    if extent <= tile {
+        return vec![0];
+    }
+    let step = tile.saturating_sub(overlap).max(1);
+    let mut origins = Vec::new();
+    let mut origin = 0;
+    loop {
+        if origin + tile >= extent {
+            origins.push(extent - tile);
+            break;
+        }
+        origins.push(origin);
+        origin += step;
+    }
+    origins
+}
+
+/// Shift a tile-local detection into whole-image coordinates.
+fn offset(mut detection: RawDetection, dx: f32, dy: f32) -> RawDetection {
+    detection.bbox.x += dx;
+    detection.bbox.y += dy;
+    for landmark in &mut detection.landmarks {
+        landmark.x += dx;
+        landmark.y += dy;
+    }
+    detection
+}
+
+/// Run the backend command with `$IMAGE` pointing at the image on disk,
+/// killing it at the deadline.
+fn run_backend(command: &str, image: &std::path::Path, timeout: Duration) -> Result<String> {
+    let mut child = Command::new("sh")
+        .arg("-c")
+        .arg(command)
+        .env("IMAGE", image)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .context("failed to invoke detection backend")?;
+    // Drain the pipes on threads so a chatty backend cannot deadlock
+    // against the timeout loop below.
+    let mut stdout_pipe = child.stdout.take().expect("stdout piped");
+    let mut stderr_pipe = child.stderr.take().expect("stderr piped");
+    let stdout = std::thread::spawn(move || {
+        let mut buf = String::new();
+        let _ = stdout_pipe.read_to_string(&mut buf);
+        buf
+    });
+    let stderr = std::thread::spawn(move || {
+        let mut buf = String::new();
+        let _ = stderr_pipe.read_to_string(&mut buf);
+        buf
+    });
+    let deadline = Instant::now() + timeout;
+    let status = loop {
+        if let Some(status) = child.try_wait().context("failed to poll backend")? {
+            break status;
+        }
+        if Instant::now() >= deadline {
+            let _ = child.kill();
+            let _ = child.wait();
+            bail!("detection backend timed out after {}s", timeout.as_secs());
+        }
+        std::thread::sleep(Duration::from_millis(50));
+    };
+    let stdout = stdout.join().unwrap_or_default();
+    let stderr = stderr.join().unwrap_or_default();
+    if !status.success() {
+        bail!("detection backend failed: {}", stderr.trim());
+    }
+    Ok(stdout)
+}
+
+/// Confidence filtering, greedy non-maximum suppression, and the face
+/// cap, in that order.
+fn select(mut candidates: Vec<RawDetection>, thresholds: &ThresholdConfig) -> Vec<DetectedFace> {
+    candidates.retain(|c| c.confidence >= thresholds.min_confidence);
+    candidates.sort_by(|a, b| b.confidence.total_cmp(&a.confidence));
+    let mut faces: Vec<DetectedFace> = Vec::new();
+    for candidate in candidates {
+        if faces.len() >= thresholds.max_faces {
+            break;
+        }
+        if faces
+            .iter()
+            .any(|kept| kept.bbox.iou(&candidate.bbox) >= thresholds.iou_threshold)
+        {
+            continue;
+        }
+        let landmark_confidence = if candidate.landmarks.is_empty() {
+            0.0
+        } else {
+            candidate.landmarks.iter().map(|l| l.confidence).sum::<f32>()
+                / candidate.landmarks.len() as f32
+        };
+        faces.push(DetectedFace {
+            bbox: candidate.bbox,
+            confidence: candidate.confidence,
+            landmarks: candidate.landmarks,
+            landmark_confidence,
+        });
+    }
+    faces
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn candidate(x: f32, confidence: f32) -> RawDetection {
+        RawDetection {
+            bbox: BoundingBox {
+                x,
+                y: 0.0,
+                width: 100.0,
+                height: 100.0,
+            },
+            confidence,
+            landmarks: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn suppression_keeps_the_most_confident_of_overlapping_boxes() {
+        // Two boxes over the same face plus one clearly elsewhere.
+        let candidates = vec![candidate(0.0, 0.7), candidate(10.0, 0.9), candidate(500.0, 0.8)];
+        let faces = select(candidates, &ThresholdConfig::default());
+        assert_eq!(faces.len(), 2);
+        assert_eq!(faces[0].confidence, 0.9);
+        assert_eq!(faces[1].bbox.x, 500.0);
+    }
+
+    #[test]
+    fn request_overrides_beat_configured_defaults() {
+        let candidates = vec![candidate(0.0, 0.9), candidate(500.0, 0.6), candidate(1000.0, 0.3)];
+        let options = DetectionOptions {
+            min_confidence: Some(0.5),
+            iou_threshold: None,
+            max_faces: Some(1),
+            source: None,
+        };
+        let resolved = options.resolve(&ThresholdConfig::default());
+        assert_eq!(resolved.iou_threshold, ThresholdConfig::default().iou_threshold);
+        let faces = select(candidates, &resolved);
+        // The 0.3 candidate fell to min_confidence and max_faces kept one.
+        assert_eq!(faces.len(), 1);
+        assert_eq!(faces[0].confidence, 0.9);
+    }
+
+    #[test]
+    fn landmark_confidence_is_the_mean_over_reported_landmarks() {
+        let mut with = candidate(0.0, 0.9);
+        with.landmarks = vec![
+            Landmark {
+                name: "left_eye".into(),
+                x: 10.0,
+                y: 10.0,
+                confidence: 0.8,
+            },
+            Landmark {
+                name: "right_eye".into(),
+                x: 40.0,
+                y: 10.0,
+                confidence: 0.6,
+            },
+        ];
+        let faces = select(vec![with, candidate(500.0, 0.9)], &ThresholdConfig::default());
+        assert!((faces[0].landmark_confidence - 0.7).abs() < 1e-6);
+        assert_eq!(faces[1].landmark_confidence, 0.0);
+    }
+
+    #[test]
+    fn tile_origins_cover_the_extent_with_overlap() {
+        assert_eq!(tile_origins(800, 1024, 256), vec![0]);
+        let origins = tile_origins(2200, 1024, 256);
+        assert_eq!(origins, vec![0, 768, 1176]);
+        // Every pixel is inside some tile and neighbors share overlap.
+        assert!(origins.windows(2).all(|w| w[1] < w[0] + 1024));
+        assert_eq!(origins.last().unwrap() + 1024, 2200);
+    }
+
+    #[test]
+    fn oversized_images_are_tiled_and_candidates_shifted_back() {
+        let mut png = Vec::new();
+        image::DynamicImage::new_rgb8(2200, 100)
+            .write_to(&mut std::io::Cursor::new(&mut png), image::ImageFormat::Png)
+            .unwrap();
+        let config = DetectionConfig {
+            web: Default::default(),
+            detector: DetectorConfig {
+                // One fixed tile-local candidate per tile.
+                command: concat!(
+                    "echo '[{\"bbox\":{\"x\":10,\"y\":10,\"width\":50,",
+                    "\"height\":50},\"confidence\":0.9}]'"
+                )
+                .to_string(),
+                timeout_secs: 10,
+            },
+            thresholds: ThresholdConfig::default(),
+            tiling: TilingConfig::default(),
+            tracking: Default::default(),
+            fetch: Default::default(),
+        };
+        let faces = FaceDetector::new(&config)
+            .detect(&png, &DetectionOptions::default())
+            .unwrap();
+        // Three tiles across, each reporting x=10 locally.
+        let mut xs: Vec<f32> = faces.iter().map(|f| f.bbox.x).collect();
+        xs.sort_by(f32::total_cmp);
+        assert_eq!(xs, vec![10.0, 778.0, 1186.0]);
+    }
+
+    #[test]
+    fn backend_command_contract_round_trips() {
+        // `cat "$IMAGE"` stands in for a model: the "image" already holds
+        // the candidate JSON the backend contract expects on stdout.
+        let config = DetectionConfig {
+            web: Default::default(),
+            detector: DetectorConfig {
+                command: "cat \"$IMAGE\"".to_string(),
+                timeout_secs: 10,
+            },
+            thresholds: ThresholdConfig::default(),
+            tiling: TilingConfig::default(),
+            tracking: Default::default(),
+            fetch: Default::default(),
+        };
+        let detector = FaceDetector::new(&config);
+        let image = serde_json::to_vec(&vec![candidate(0.0, 0.9)]).unwrap();
+        let faces = detector.detect(&image, &DetectionOptions::default()).unwrap();
+        assert_eq!(faces.len(), 1);
+
+        let mut failing = config;
+        failing.detector.command = "echo broken >&2; exit 3".to_string();
+        let err = FaceDetector::new(&failing)
+            .detect(&image, &DetectionOptions::default())
+            .unwrap_err();
+        assert!(err.to_string().contains("backend failed"));
+    }
+}
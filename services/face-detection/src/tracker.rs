@@ -0,0 +1,232 @@
+//! Cross-frame face tracking for the WebSocket video mode.
+//!
+//! Detection alone gives the client a fresh, unordered face list per
+//! frame, so an overlay annotating a moving face flickers between
+//! subjects. The tracker assigns each face a `track_id` that stays
+//! stable across consecutive frames: matching goes by box IoU first,
+//! and when the subject moved too far between frames for the boxes to
+//! overlap, a cheap appearance embedding of the face crop — a
+//! mean-centered, L2-normalized 8x8 grayscale thumbnail — rescues the
+//! match by cosine similarity. No model runs here; the liveness flow
+//! can follow one subject without a round trip to face-embedding.
+
+use crate::config::TrackingConfig;
+use crate::processors::{BoundingBox, DetectedFace};
+use image::DynamicImage;
+use serde::Serialize;
+
+/// Side length of the square grayscale patch behind the appearance
+/// embedding; 64 dimensions is plenty to tell two faces in one frame
+/// apart, which is all the rescue path needs.
+const PATCH_SIZE: u32 = 8;
+
+/// A detected face carrying its stable per-stream track id.
+#[derive(Debug, Serialize)]
+pub struct TrackedFace {
+    pub track_id: u64,
+    #[serde(flatten)]
+    pub face: DetectedFace,
+}
+
+struct Track {
+    id: u64,
+    bbox: BoundingBox,
+    embedding: Vec<f32>,
+    last_seen: u64,
+}
+
+pub struct FaceTracker {
+    config: TrackingConfig,
+    tracks: Vec<Track>,
+    next_id: u64,
+    frame: u64,
+}
+
+impl FaceTracker {
+    pub fn new(config: TrackingConfig) -> Self {
+        Self {
+            config,
+            tracks: Vec::new(),
+            next_id: 1,
+            frame: 0,
+        }
+    }
+
+    /// Assign track ids to the faces detected in the next frame of the
+    /// stream. Matches are made greedily, best pair first; every
+    /// unmatched face opens a new track, and tracks unseen for longer
+    /// than the configured age are dropped first so a long-gone face
+    /// cannot steal a match.
+    pub fn update(&mut self, frame: &DynamicImage, faces: Vec<DetectedFace>) -> Vec<TrackedFace> {
+        self.frame += 1;
+        let cutoff = self.frame.saturating_sub(self.config.max_age_frames);
+        self.tracks.retain(|t| t.last_seen >= cutoff);
+
+        let embeddings: Vec<Vec<f32>> = faces.iter().map(|f| appearance(frame, &f.bbox)).collect();
+        // Score every eligible face/track pair; IoU and similarity are
+        // both in [0, 1], so their sum ranks "overlapping and alike"
+        // above either alone.
+        let mut pairs = Vec::new();
+        for (fi, face) in faces.iter().enumerate() {
+            for (ti, track) in self.tracks.iter().enumerate() {
+                let iou = face.bbox.iou(&track.bbox);
+                let similarity = cosine(&embeddings[fi], &track.embedding);
+                if iou >= self.config.min_iou || similarity >= self.config.min_similarity {
+                    pairs.push((iou + similarity, fi, ti));
+                }
+            }
+        }
+        pairs.sort_by(|a, b| b.0.total_cmp(&a.0));
+
+        let mut face_track = vec![None; faces.len()];
+        let mut track_taken = vec![false; self.tracks.len()];
+        for (_, fi, ti) in pairs {
+            if face_track[fi].is_some() || track_taken[ti] {
+                continue;
+            }
+            face_track[fi] = Some(ti);
+            track_taken[ti] = true;
+        }
+
+        let mut tracked = Vec::with_capacity(faces.len());
+        for ((face, assigned), embedding) in faces.into_iter().zip(face_track).zip(embeddings) {
+            let track_id = match assigned {
+                Some(ti) => {
+                    let track = &mut self.tracks[ti];
+                    track.bbox = face.bbox;
+                    track.embedding = embedding;
+                    track.last_seen = self.frame;
+                    track.id
+                }
+                None => {
+                    let id = self.next_id;
+                    self.next_id += 1;
+                    self.tracks.push(Track {
+                        id,
+                        bbox: face.bbox,
+                        embedding,
+                        last_seen: self.frame,
+                    });
+                    id
+                }
+            };
+            tracked.push(TrackedFace { track_id, face });
+        }
+        tracked
+    }
+}
+
+/// Appearance embedding of the face crop: the box clamped to the frame,
+/// downsampled to a grayscale patch, mean-centered and L2-normalized.
+/// A flat crop (solid color) embeds as the zero vector, which matches
+/// nothing.
+fn appearance(frame: &DynamicImage, bbox: &BoundingBox) -> Vec<f32> {
+    let x = (bbox.x.max(0.0) as u32).min(frame.width().saturating_sub(1));
+    let y = (bbox.y.max(0.0) as u32).min(frame.height().saturating_sub(1));
+    let width = (bbox.width.max(1.0) as u32).min(frame.width() - x);
+    let height = (bbox.height.max(1.0) as u32).min(frame.height() - y);
+    let patch = frame
+        .crop_imm(x, y, width, height)
+        .resize_exact(PATCH_SIZE, PATCH_SIZE, image::imageops::FilterType::Triangle)
+        .into_luma8();
+    let mut values: Vec<f32> = patch.pixels().map(|p| p.0[0] as f32).collect();
+    let mean = values.iter().sum::<f32>() / values.len() as f32;
+    for value in &mut values {
+        *value -= mean;
+    }
+    let norm = values.iter().map(|v| v * v).sum::<f32>().sqrt();
+    if norm > 0.0 {
+        for value in &mut values {
+            *value /= norm;
+        }
+    }
+    values
+}
+
+fn cosine(a: &[f32], b: &[f32]) -> f32 {
+    // Both sides are unit length (or zero), so the dot product is the
+    // cosine.
+    a.iter().zip(b).map(|(x, y)| x * y).sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{DynamicImage, Rgb, RgbImage};
+
+    /// A frame with a distinctive gradient patch at each given origin,
+    /// so appearance embeddings are meaningful and identical across
+    /// frames.
+    fn frame(patches: &[(u32, u32)]) -> DynamicImage {
+        let mut image = RgbImage::new(640, 480);
+        for &(px, py) in patches {
+            for dy in 0..64 {
+                for dx in 0..64 {
+                    let value = ((dx * 4) ^ (dy * 4)) as u8;
+                    image.put_pixel(px + dx, py + dy, Rgb([value, value, value]));
+                }
+            }
+        }
+        DynamicImage::ImageRgb8(image)
+    }
+
+    fn face(x: f32, y: f32) -> DetectedFace {
+        DetectedFace {
+            bbox: BoundingBox {
+                x,
+                y,
+                width: 64.0,
+                height: 64.0,
+            },
+            confidence: 0.9,
+            landmarks: Vec::new(),
+            landmark_confidence: 0.0,
+        }
+    }
+
+    #[test]
+    fn overlapping_detections_keep_their_track_id() {
+        let mut tracker = FaceTracker::new(TrackingConfig::default());
+        let first = tracker.update(&frame(&[(100, 100)]), vec![face(100.0, 100.0)]);
+        let second = tracker.update(&frame(&[(110, 104)]), vec![face(110.0, 104.0)]);
+        assert_eq!(first[0].track_id, second[0].track_id);
+    }
+
+    #[test]
+    fn a_second_subject_gets_its_own_id() {
+        let mut tracker = FaceTracker::new(TrackingConfig::default());
+        let first = tracker.update(&frame(&[(100, 100)]), vec![face(100.0, 100.0)]);
+        let second = tracker.update(
+            &frame(&[(100, 100), (400, 100)]),
+            vec![face(100.0, 100.0), face(400.0, 100.0)],
+        );
+        assert_eq!(second[0].track_id, first[0].track_id);
+        assert_ne!(second[1].track_id, first[0].track_id);
+    }
+
+    #[test]
+    fn appearance_rescues_a_fast_mover_with_no_box_overlap() {
+        let mut tracker = FaceTracker::new(TrackingConfig::default());
+        let first = tracker.update(&frame(&[(100, 100)]), vec![face(100.0, 100.0)]);
+        // The subject jumped clear across the frame; the identical crop
+        // keeps the id even though the boxes are disjoint.
+        let second = tracker.update(&frame(&[(400, 300)]), vec![face(400.0, 300.0)]);
+        assert_eq!(first[0].track_id, second[0].track_id);
+    }
+
+    #[test]
+    fn stale_tracks_are_dropped_and_ids_never_reused() {
+        let config = TrackingConfig {
+            max_age_frames: 2,
+            ..TrackingConfig::default()
+        };
+        let mut tracker = FaceTracker::new(config);
+        let first = tracker.update(&frame(&[(100, 100)]), vec![face(100.0, 100.0)]);
+        // The subject leaves for longer than the track's allowed age.
+        for _ in 0..3 {
+            assert!(tracker.update(&frame(&[]), Vec::new()).is_empty());
+        }
+        let back = tracker.update(&frame(&[(100, 100)]), vec![face(100.0, 100.0)]);
+        assert_ne!(back[0].track_id, first[0].track_id);
+    }
+}
@@ -0,0 +1,205 @@
+//! REST API over the embedder, for the miniapp backend and the vector
+//! store ingest.
+
+use crate::config::{EmbeddingConfig, OcclusionConfig};
+use crate::embedder::FaceEmbedder;
+use crate::encoding::ResponseEncoding;
+use crate::jobs::{JobMode, JobQueue};
+use crate::occlusion;
+use crate::quantization::{self, Quantization};
+use axum::extract::{Path, Query, State};
+use axum::http::{HeaderMap, StatusCode};
+use axum::response::IntoResponse;
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use serde::Deserialize;
+use serde_json::json;
+use std::sync::Arc;
+use tower_http::compression::CompressionLayer;
+use tower_http::cors::CorsLayer;
+use tracing::info;
+
+#[derive(Clone)]
+struct AppState {
+    embedder: Arc<FaceEmbedder>,
+    queue: Arc<JobQueue>,
+    fetch: Arc<aurum_objectstore::FetchConfig>,
+    occlusion: Arc<OcclusionConfig>,
+}
+
+pub struct ApiServer {
+    config: EmbeddingConfig,
+    state: AppState,
+}
+
+impl ApiServer {
+    /// Build the server and spawn the job queue workers on the current
+    /// runtime.
+    pub fn new(config: EmbeddingConfig) -> Self {
+        let embedder = Arc::new(FaceEmbedder::new(&config));
+        let queue = JobQueue::new(embedder.clone(), &config.queue, &config.occlusion);
+        let fetch = Arc::new(config.fetch.clone());
+        let occlusion = Arc::new(config.occlusion.clone());
+        Self {
+            config,
+            state: AppState {
+                embedder,
+                queue,
+                fetch,
+                occlusion,
+            },
+        }
+    }
+
+    pub fn router(&self) -> Router {
+        Router::new()
+            .route("/api/embed", post(embed))
+            .route("/api/jobs/{id}", get(job_by_id))
+            .route("/health", get(health))
+            .layer(axum::middleware::from_fn(trace_context))
+            // Negotiated on Accept-Encoding; embedding batches are very
+            // compressible even after a binary encoding.
+            .layer(CompressionLayer::new())
+            .layer(CorsLayer::permissive())
+            .with_state(self.state.clone())
+    }
+
+    pub async fn serve(&self) -> anyhow::Result<()> {
+        let addr = format!("{}:{}", self.config.web.bind, self.config.web.port);
+        let listener = tokio::net::TcpListener::bind(&addr).await?;
+        info!("api server listening on {addr}");
+        axum::serve(listener, self.router()).await?;
+        Ok(())
+    }
+}
+
+/// Run every request inside a span carrying the caller's trace context,
+/// so one photo's journey through the pipeline shows up as one trace.
+async fn trace_context(
+    request: axum::extract::Request,
+    next: axum::middleware::Next,
+) -> axum::response::Response {
+    use tracing::Instrument;
+    let traceparent = request
+        .headers()
+        .get(aurum_telemetry::TRACEPARENT)
+        .and_then(|v| v.to_str().ok())
+        .map(|v| v.to_string());
+    let span = aurum_telemetry::request_span(
+        request.method().as_str(),
+        request.uri().path(),
+        traceparent.as_deref(),
+    );
+    next.run(request).instrument(span).await
+}
+
+type ApiResult<T> = Result<T, (StatusCode, Json<serde_json::Value>)>;
+
+fn internal_error(e: anyhow::Error) -> (StatusCode, Json<serde_json::Value>) {
+    (
+        StatusCode::INTERNAL_SERVER_ERROR,
+        Json(json!({ "error": format!("{e:#}") })),
+    )
+}
+
+fn unprocessable(e: anyhow::Error) -> (StatusCode, Json<serde_json::Value>) {
+    (
+        StatusCode::UNPROCESSABLE_ENTITY,
+        Json(json!({ "error": format!("{e:#}") })),
+    )
+}
+
+fn not_found(what: &str) -> (StatusCode, Json<serde_json::Value>) {
+    (
+        StatusCode::NOT_FOUND,
+        Json(json!({ "error": format!("{what} not found") })),
+    )
+}
+
+async fn health() -> impl IntoResponse {
+    Json(json!({ "status": "ok" }))
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct EmbedOptions {
+    /// Output format: "float32" (default), "float16", or "int8".
+    #[serde(default)]
+    format: Quantization,
+    /// "sync" (default) answers inline; "async" queues a job and
+    /// returns its id immediately.
+    #[serde(default)]
+    mode: JobMode,
+    /// Webhook the finished job is POSTed to, in async mode.
+    #[serde(default)]
+    callback_url: Option<String>,
+    /// Fetch the crop from object storage by reference instead of the
+    /// request body.
+    #[serde(default)]
+    source: Option<String>,
+}
+
+/// Embed the aligned face crop in the raw request body, or in the
+/// object a `source` query parameter references. The response encoding
+/// follows the `Accept` header: JSON by default, CBOR or MessagePack
+/// for callers that ask.
+async fn embed(
+    State(state): State<AppState>,
+    Query(options): Query<EmbedOptions>,
+    headers: HeaderMap,
+    body: axum::body::Bytes,
+) -> ApiResult<axum::response::Response> {
+    let encoding = ResponseEncoding::negotiate(&headers);
+    let image = match &options.source {
+        Some(_) if !body.is_empty() => {
+            return Err(unprocessable(anyhow::anyhow!(
+                "pass either an image body or a source, not both"
+            )));
+        }
+        Some(source) => aurum_objectstore::fetch(source, &state.fetch)
+            .await
+            .map_err(unprocessable)?,
+        None if body.is_empty() => {
+            return Err(unprocessable(anyhow::anyhow!("empty image body")));
+        }
+        None => body.to_vec(),
+    };
+    if options.mode == JobMode::Async {
+        let id = state
+            .queue
+            .submit(image, options.format, options.callback_url)
+            .map_err(unprocessable)?;
+        let accepted = encoding.respond(&json!({ "job_id": id, "status": "queued" }));
+        return Ok((StatusCode::ACCEPTED, accepted).into_response());
+    }
+    let embedder = state.embedder.clone();
+    let occlusion_config = state.occlusion.clone();
+    // The backend is a subprocess; keep it off the async runtime.
+    // Occlusion screening is best-effort: an undecodable crop scores
+    // nothing but still goes to the backend, which owns the verdict.
+    let (embedding, occlusion) = tokio::task::spawn_blocking(move || {
+        let report = occlusion_config
+            .enabled
+            .then(|| occlusion::analyze(&image, &occlusion_config).ok())
+            .flatten();
+        (embedder.embed(&image), report)
+    })
+    .await
+    .map_err(|e| internal_error(e.into()))?;
+    let embedding = embedding.map_err(unprocessable)?;
+    Ok(encoding.respond(&json!({
+        "dimension": embedding.len(),
+        "embedding": quantization::quantize(&embedding, options.format),
+        "occlusion": occlusion,
+    })))
+}
+
+/// Poll an async job; completed jobs carry the embedding inline, in the
+/// encoding the `Accept` header asks for.
+async fn job_by_id(
+    State(state): State<AppState>,
+    Path(id): Path<uuid::Uuid>,
+    headers: HeaderMap,
+) -> ApiResult<impl IntoResponse> {
+    let job = state.queue.get(id).ok_or_else(|| not_found("job"))?;
+    Ok(ResponseEncoding::negotiate(&headers).respond(&job))
+}
@@ -0,0 +1,172 @@
+//! Service configuration, loaded through the shared layered loader:
+//! defaults, then the JSON file, then `FACE_EMBEDDING__*` environment
+//! variables, then `--set` overrides.
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EmbeddingConfig {
+    #[serde(default)]
+    pub web: WebConfig,
+    /// The backend that runs the actual model.
+    #[serde(default)]
+    pub embedder: EmbedderConfig,
+    /// Occlusion screening of crops before they are embedded.
+    #[serde(default)]
+    pub occlusion: OcclusionConfig,
+    /// Worker pool for async embedding jobs.
+    #[serde(default)]
+    pub queue: QueueConfig,
+    /// Where bulk re-embedding runs write their results.
+    #[serde(default)]
+    pub store: StoreConfig,
+    /// Object-storage fetch for by-reference image sources.
+    #[serde(default)]
+    pub fetch: aurum_objectstore::FetchConfig,
+}
+
+impl EmbeddingConfig {
+    /// Load configuration from `path`, layered under the environment
+    /// and `--set key.path=value` overrides; a missing file means
+    /// defaults.
+    pub fn load_with(path: &Path, overrides: &[String]) -> Result<Self> {
+        let mut loader = aurum_config::Loader::new("FACE_EMBEDDING").file(path);
+        for assignment in overrides {
+            loader = loader.set(assignment)?;
+        }
+        Ok(loader.load()?)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WebConfig {
+    #[serde(default = "default_bind")]
+    pub bind: String,
+    #[serde(default = "default_port")]
+    pub port: u16,
+}
+
+impl Default for WebConfig {
+    fn default() -> Self {
+        Self {
+            bind: default_bind(),
+            port: default_port(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EmbedderConfig {
+    /// Backend command run through `sh -c` for each face crop. It reads
+    /// the image at `$IMAGE` and writes the embedding to stdout as a
+    /// JSON array of floats.
+    #[serde(default = "default_command")]
+    pub command: String,
+    /// Wall-clock limit per backend invocation.
+    #[serde(default = "default_timeout_secs")]
+    pub timeout_secs: u64,
+}
+
+impl Default for EmbedderConfig {
+    fn default() -> Self {
+        Self {
+            command: default_command(),
+            timeout_secs: default_timeout_secs(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OcclusionConfig {
+    /// Score crops for masks, sunglasses, and partial coverage, and
+    /// return the scores alongside the embedding.
+    #[serde(default = "default_occlusion_enabled")]
+    pub enabled: bool,
+    /// Any occlusion score at or above this marks the crop as partially
+    /// occluded, signalling onboarding to ask for a retake.
+    #[serde(default = "default_partial_threshold")]
+    pub partial_threshold: f32,
+}
+
+impl Default for OcclusionConfig {
+    fn default() -> Self {
+        Self {
+            enabled: default_occlusion_enabled(),
+            partial_threshold: default_partial_threshold(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QueueConfig {
+    /// Concurrent embedding workers; each one runs a backend subprocess.
+    #[serde(default = "default_workers")]
+    pub workers: usize,
+    /// Jobs held in the queue before submissions are rejected; a bulk
+    /// campaign driver retries rejected submissions with backoff.
+    #[serde(default = "default_depth")]
+    pub depth: usize,
+}
+
+impl Default for QueueConfig {
+    fn default() -> Self {
+        Self {
+            workers: default_workers(),
+            depth: default_depth(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StoreConfig {
+    /// Directory embeddings are written to, one JSON file per source,
+    /// next to the resumability checkpoint.
+    #[serde(default = "default_store_directory")]
+    pub directory: std::path::PathBuf,
+}
+
+impl Default for StoreConfig {
+    fn default() -> Self {
+        Self {
+            directory: default_store_directory(),
+        }
+    }
+}
+
+fn default_bind() -> String {
+    "127.0.0.1".to_string()
+}
+
+fn default_port() -> u16 {
+    9701
+}
+
+fn default_command() -> String {
+    "arcface-cli --image \"$IMAGE\"".to_string()
+}
+
+fn default_timeout_secs() -> u64 {
+    30
+}
+
+fn default_occlusion_enabled() -> bool {
+    true
+}
+
+fn default_partial_threshold() -> f32 {
+    0.35
+}
+
+fn default_workers() -> usize {
+    2
+}
+
+fn default_depth() -> usize {
+    1024
+}
+
+fn default_store_directory() -> std::path::PathBuf {
+    std::path::PathBuf::from("embeddings")
+}
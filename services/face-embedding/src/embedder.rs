@@ -0,0 +1,113 @@
+//! The embedding backend: an external model command per face crop.
+
+use crate::config::{EmbedderConfig, EmbeddingConfig};
+use anyhow::{bail, Context, Result};
+use std::io::Read;
+use std::process::{Command, Stdio};
+use std::time::{Duration, Instant};
+use tracing::debug;
+
+pub struct FaceEmbedder {
+    embedder: EmbedderConfig,
+}
+
+impl FaceEmbedder {
+    pub fn new(config: &EmbeddingConfig) -> Self {
+        Self {
+            embedder: config.embedder.clone(),
+        }
+    }
+
+    /// Run the backend over an aligned face crop and return the
+    /// full-precision embedding. The crop is normalized to upright sRGB
+    /// first, so EXIF-rotated or wide-gamut uploads do not skew vectors.
+    pub fn embed(&self, image: &[u8]) -> Result<Vec<f32>> {
+        let (image, normalization) = aurum_image::normalize(image)?;
+        if normalization.changed {
+            debug!(
+                orientation = normalization.orientation,
+                profile = normalization.profile.as_str(),
+                "normalized crop before embedding"
+            );
+        }
+        let file = tempfile::NamedTempFile::new().context("failed to create image temp file")?;
+        std::fs::write(file.path(), image.as_ref()).context("failed to write image temp file")?;
+        let stdout = run_backend(
+            &self.embedder.command,
+            file.path(),
+            Duration::from_secs(self.embedder.timeout_secs),
+        )?;
+        let embedding: Vec<f32> =
+            serde_json::from_str(&stdout).context("backend produced an unparsable embedding")?;
+        if embedding.is_empty() {
+            bail!("backend produced an empty embedding");
+        }
+        Ok(embedding)
+    }
+}
+
+/// Run the backend command with `$IMAGE` pointing at the crop on disk,
+/// killing it at the deadline.
+fn run_backend(command: &str, image: &std::path::Path, timeout: Duration) -> Result<String> {
+    let mut child = Command::new("sh")
+        .arg("-c")
+        .arg(command)
+        .env("IMAGE", image)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .context("failed to invoke embedding backend")?;
+    // Drain the pipes on threads so a chatty backend cannot deadlock
+    // against the timeout loop below.
+    let mut stdout_pipe = child.stdout.take().expect("stdout piped");
+    let mut stderr_pipe = child.stderr.take().expect("stderr piped");
+    let stdout = std::thread::spawn(move || {
+        let mut buf = String::new();
+        let _ = stdout_pipe.read_to_string(&mut buf);
+        buf
+    });
+    let stderr = std::thread::spawn(move || {
+        let mut buf = String::new();
+        let _ = stderr_pipe.read_to_string(&mut buf);
+        buf
+    });
+    let deadline = Instant::now() + timeout;
+    let status = loop {
+        if let Some(status) = child.try_wait().context("failed to poll backend")? {
+            break status;
+        }
+        if Instant::now() >= deadline {
+            let _ = child.kill();
+            let _ = child.wait();
+            bail!("embedding backend timed out after {}s", timeout.as_secs());
+        }
+        std::thread::sleep(Duration::from_millis(50));
+    };
+    let stdout = stdout.join().unwrap_or_default();
+    let stderr = stderr.join().unwrap_or_default();
+    if !status.success() {
+        bail!("embedding backend failed: {}", stderr.trim());
+    }
+    Ok(stdout)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::EmbeddingConfig;
+
+    #[test]
+    fn backend_command_contract_round_trips() {
+        // `cat "$IMAGE"` stands in for a model: the "image" already holds
+        // the embedding JSON the backend contract expects on stdout.
+        let mut config = EmbeddingConfig::load_with(std::path::Path::new("/nonexistent"), &[]).unwrap();
+        config.embedder.command = "cat \"$IMAGE\"".to_string();
+        let embedder = FaceEmbedder::new(&config);
+        let embedding = embedder.embed(b"[0.1, -0.2, 0.3]").unwrap();
+        assert_eq!(embedding, vec![0.1, -0.2, 0.3]);
+
+        config.embedder.command = "echo '[]'".to_string();
+        let err = FaceEmbedder::new(&config).embed(b"ignored").unwrap_err();
+        assert!(err.to_string().contains("empty embedding"));
+    }
+}
@@ -0,0 +1,148 @@
+//! Response encoding negotiated from the `Accept` header.
+//!
+//! A 512-float embedding is ~10 KB as JSON text and under 3 KB as CBOR
+//! or MessagePack, and batch callers multiply that. Callers that send
+//! `Accept: application/cbor` or `application/msgpack` get the same
+//! response body in that encoding; everyone else keeps JSON, so
+//! browsers and curl are unaffected.
+
+use axum::http::{header, HeaderMap};
+use axum::response::{IntoResponse, Response};
+use serde::Serialize;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ResponseEncoding {
+    Json,
+    Cbor,
+    MessagePack,
+}
+
+impl ResponseEncoding {
+    /// Pick the encoding from the request's `Accept` header: the first
+    /// listed media type we can produce wins, and an absent or
+    /// unrecognized header falls back to JSON. Full q-value ordering is
+    /// deliberately out of scope — callers wanting CBOR just ask for it
+    /// first.
+    pub fn negotiate(headers: &HeaderMap) -> Self {
+        let Some(accept) = headers.get(header::ACCEPT).and_then(|v| v.to_str().ok()) else {
+            return Self::Json;
+        };
+        for entry in accept.split(',') {
+            let media_type = entry.split(';').next().unwrap_or("").trim();
+            match media_type {
+                "application/cbor" => return Self::Cbor,
+                "application/msgpack" | "application/x-msgpack" => return Self::MessagePack,
+                "application/json" | "application/*" | "*/*" => return Self::Json,
+                _ => {}
+            }
+        }
+        Self::Json
+    }
+
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            Self::Json => "application/json",
+            Self::Cbor => "application/cbor",
+            Self::MessagePack => "application/msgpack",
+        }
+    }
+
+    /// Serialize `value` in this encoding with the matching
+    /// content-type. Encoding failures answer 500; every type we
+    /// serialize here round-trips in all three encodings, so one firing
+    /// is a bug.
+    pub fn respond<T: Serialize>(&self, value: &T) -> Response {
+        let body = match self {
+            Self::Json => serde_json::to_vec(value).map_err(|e| e.to_string()),
+            Self::Cbor => {
+                let mut buf = Vec::new();
+                ciborium::into_writer(value, &mut buf)
+                    .map(|()| buf)
+                    .map_err(|e| e.to_string())
+            }
+            Self::MessagePack => rmp_serde::to_vec_named(value).map_err(|e| e.to_string()),
+        };
+        match body {
+            Ok(body) => (
+                [(header::CONTENT_TYPE, self.content_type())],
+                body,
+            )
+                .into_response(),
+            Err(e) => (
+                axum::http::StatusCode::INTERNAL_SERVER_ERROR,
+                format!("response encoding failed: {e}"),
+            )
+                .into_response(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::HeaderValue;
+
+    fn accept(value: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(header::ACCEPT, HeaderValue::from_str(value).unwrap());
+        headers
+    }
+
+    #[test]
+    fn the_first_producible_media_type_wins() {
+        assert_eq!(
+            ResponseEncoding::negotiate(&accept("application/cbor")),
+            ResponseEncoding::Cbor
+        );
+        assert_eq!(
+            ResponseEncoding::negotiate(&accept("application/x-msgpack, application/json")),
+            ResponseEncoding::MessagePack
+        );
+        // JSON listed first keeps JSON even when CBOR follows.
+        assert_eq!(
+            ResponseEncoding::negotiate(&accept("application/json, application/cbor")),
+            ResponseEncoding::Json
+        );
+    }
+
+    #[test]
+    fn unknown_or_absent_accept_falls_back_to_json() {
+        assert_eq!(
+            ResponseEncoding::negotiate(&HeaderMap::new()),
+            ResponseEncoding::Json
+        );
+        assert_eq!(
+            ResponseEncoding::negotiate(&accept("text/html;q=0.9, image/png")),
+            ResponseEncoding::Json
+        );
+        assert_eq!(
+            ResponseEncoding::negotiate(&accept("*/*")),
+            ResponseEncoding::Json
+        );
+    }
+
+    #[test]
+    fn cbor_and_msgpack_round_trip_an_embedding_payload() {
+        #[derive(serde::Serialize, serde::Deserialize, PartialEq, Debug)]
+        struct Payload {
+            dimension: usize,
+            embedding: Vec<f32>,
+        }
+        let payload = Payload {
+            dimension: 3,
+            embedding: vec![0.25, -1.5, 3.0],
+        };
+
+        let mut cbor = Vec::new();
+        ciborium::into_writer(&payload, &mut cbor).unwrap();
+        let back: Payload = ciborium::from_reader(cbor.as_slice()).unwrap();
+        assert_eq!(back, payload);
+
+        let msgpack = rmp_serde::to_vec_named(&payload).unwrap();
+        let back: Payload = rmp_serde::from_slice(&msgpack).unwrap();
+        assert_eq!(back, payload);
+        // The binary encodings are what we promise: smaller than the
+        // JSON text.
+        assert!(cbor.len() < serde_json::to_vec(&payload).unwrap().len());
+    }
+}
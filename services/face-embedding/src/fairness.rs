@@ -0,0 +1,434 @@
+//! Demographic fairness evaluation over a labeled benchmark.
+//!
+//! Before launch the pipeline has to show comparable error rates across
+//! demographic groups, not just a good aggregate number. The `evaluate`
+//! subcommand embeds every crop in a benchmark directory — aligned
+//! crops, i.e. the detection stage's output — and scores all image
+//! pairs at a similarity threshold: same-subject pairs should match
+//! (a miss is a false reject), different-subject pairs should not (a
+//! match is a false accept). Accuracy, FAR, and FRR are reported
+//! overall and per attribute value, so a gap between groups is visible
+//! directly in the audit artifact.
+//!
+//! The benchmark directory holds the image files plus a `labels.jsonl`
+//! with one record per line: `{"file": "a.png", "subject": "s1",
+//! "attributes": {"gender": "f", "age": "18-30"}}`. Attribute keys are
+//! free-form; every key present in the labels becomes a breakdown axis.
+
+use crate::config::EmbeddingConfig;
+use crate::embedder::FaceEmbedder;
+use crate::quantization::cosine_similarity;
+use anyhow::{bail, Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tracing::{info, warn};
+
+const LABELS_FILE: &str = "labels.jsonl";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FairnessFormat {
+    Json,
+    Html,
+}
+
+impl FairnessFormat {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            FairnessFormat::Json => "json",
+            FairnessFormat::Html => "html",
+        }
+    }
+
+    pub fn parse(s: &str) -> Result<Self> {
+        match s {
+            "json" => Ok(FairnessFormat::Json),
+            "html" => Ok(FairnessFormat::Html),
+            other => bail!("unknown report format '{other}'; expected json or html"),
+        }
+    }
+}
+
+/// One line of `labels.jsonl`.
+#[derive(Debug, Deserialize)]
+struct LabeledImage {
+    file: String,
+    subject: String,
+    #[serde(default)]
+    attributes: BTreeMap<String, String>,
+}
+
+/// Pair-classification outcomes at the evaluation threshold.
+#[derive(Debug, Default, Serialize)]
+pub struct Metrics {
+    pub genuine_pairs: usize,
+    pub impostor_pairs: usize,
+    /// Different-subject pairs scoring at or above the threshold.
+    pub false_accepts: usize,
+    /// Same-subject pairs scoring below the threshold.
+    pub false_rejects: usize,
+    /// Correctly classified pairs over all pairs.
+    pub accuracy: f64,
+    /// False accepts over impostor pairs.
+    pub far: f64,
+    /// False rejects over genuine pairs.
+    pub frr: f64,
+}
+
+impl Metrics {
+    fn record(&mut self, genuine: bool, accepted: bool) {
+        if genuine {
+            self.genuine_pairs += 1;
+            if !accepted {
+                self.false_rejects += 1;
+            }
+        } else {
+            self.impostor_pairs += 1;
+            if accepted {
+                self.false_accepts += 1;
+            }
+        }
+    }
+
+    fn finalize(&mut self) {
+        let pairs = self.genuine_pairs + self.impostor_pairs;
+        if pairs > 0 {
+            let errors = self.false_accepts + self.false_rejects;
+            self.accuracy = (pairs - errors) as f64 / pairs as f64;
+        }
+        if self.impostor_pairs > 0 {
+            self.far = self.false_accepts as f64 / self.impostor_pairs as f64;
+        }
+        if self.genuine_pairs > 0 {
+            self.frr = self.false_rejects as f64 / self.genuine_pairs as f64;
+        }
+    }
+}
+
+/// Metrics over the pairs where both images carry the same value for
+/// one attribute.
+#[derive(Debug, Serialize)]
+pub struct GroupMetrics {
+    pub attribute: String,
+    pub value: String,
+    pub images: usize,
+    pub metrics: Metrics,
+}
+
+#[derive(Debug, Serialize)]
+pub struct EvalFailure {
+    pub file: String,
+    pub error: String,
+}
+
+/// The audit artifact: overall metrics plus the per-group breakdown.
+#[derive(Debug, Serialize)]
+pub struct FairnessReport {
+    pub generated_at: DateTime<Utc>,
+    /// Cosine similarity at or above this counts as a match.
+    pub threshold: f32,
+    pub images: usize,
+    pub groups: Vec<GroupMetrics>,
+    pub overall: Metrics,
+    /// Images that could not be embedded; excluded from every pair.
+    pub failures: Vec<EvalFailure>,
+}
+
+/// Embed every labeled image in the benchmark and score all pairs.
+pub async fn run(
+    config: &EmbeddingConfig,
+    benchmark: &Path,
+    threshold: f32,
+) -> Result<FairnessReport> {
+    let labels = load_labels(benchmark)?;
+    let total = labels.len();
+
+    let embedder = Arc::new(FaceEmbedder::new(config));
+    let workers = config.queue.workers.max(1);
+    let mut inflight = tokio::task::JoinSet::new();
+    let mut embedded: Vec<(LabeledImage, Vec<f32>)> = Vec::new();
+    let mut failures = Vec::new();
+    for label in labels {
+        while inflight.len() >= workers {
+            let outcome = inflight.join_next().await.expect("inflight not empty")?;
+            finish(outcome, &mut embedded, &mut failures);
+        }
+        let embedder = embedder.clone();
+        let path = benchmark.join(&label.file);
+        inflight.spawn(async move {
+            let result = embed_file(&embedder, &path).await;
+            (label, result)
+        });
+    }
+    while let Some(outcome) = inflight.join_next().await {
+        finish(outcome?, &mut embedded, &mut failures);
+    }
+    embedded.sort_by(|a, b| a.0.file.cmp(&b.0.file));
+
+    let mut overall = Metrics::default();
+    let mut groups: BTreeMap<(String, String), Metrics> = BTreeMap::new();
+    for (i, (a, embedding_a)) in embedded.iter().enumerate() {
+        for (b, embedding_b) in &embedded[i + 1..] {
+            let genuine = a.subject == b.subject;
+            let accepted = cosine_similarity(embedding_a, embedding_b) >= threshold;
+            overall.record(genuine, accepted);
+            // A pair counts toward a group when both sides carry the
+            // same value for the attribute.
+            for (key, value) in &a.attributes {
+                if b.attributes.get(key) == Some(value) {
+                    groups
+                        .entry((key.clone(), value.clone()))
+                        .or_default()
+                        .record(genuine, accepted);
+                }
+            }
+        }
+    }
+    overall.finalize();
+
+    let groups = groups
+        .into_iter()
+        .map(|((attribute, value), mut metrics)| {
+            metrics.finalize();
+            let images = embedded
+                .iter()
+                .filter(|(label, _)| label.attributes.get(&attribute) == Some(&value))
+                .count();
+            GroupMetrics {
+                attribute,
+                value,
+                images,
+                metrics,
+            }
+        })
+        .collect();
+
+    info!(
+        images = embedded.len(),
+        failed = failures.len(),
+        threshold,
+        "fairness evaluation finished"
+    );
+    anyhow::ensure!(
+        !embedded.is_empty(),
+        "none of the {total} benchmark images could be embedded"
+    );
+    Ok(FairnessReport {
+        generated_at: Utc::now(),
+        threshold,
+        images: embedded.len(),
+        groups,
+        overall,
+        failures,
+    })
+}
+
+impl FairnessReport {
+    /// Render the report in `format` and write it into the `output`
+    /// directory (created when missing), returning the written path.
+    pub fn write(&self, format: FairnessFormat, output: &Path) -> Result<PathBuf> {
+        std::fs::create_dir_all(output)
+            .with_context(|| format!("cannot create {}", output.display()))?;
+        let (name, rendered) = match format {
+            FairnessFormat::Json => (
+                "fairness.json",
+                serde_json::to_string_pretty(self)? + "\n",
+            ),
+            FairnessFormat::Html => ("fairness.html", self.to_html()),
+        };
+        let path = output.join(name);
+        std::fs::write(&path, rendered)
+            .with_context(|| format!("cannot write {}", path.display()))?;
+        Ok(path)
+    }
+
+    /// A self-contained HTML page: no scripts, one table row per group.
+    fn to_html(&self) -> String {
+        let mut out = String::from(
+            "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>Fairness evaluation</title>\n<style>\nbody { font-family: sans-serif; margin: 2rem; }\ntd, th { padding: 0.25rem 0.75rem; text-align: left; }\n</style>\n</head>\n<body>\n<h1>Fairness evaluation</h1>\n",
+        );
+        let _ = writeln!(
+            out,
+            "<p>Generated {}. {} images, threshold {}, {} failed to embed.</p>",
+            self.generated_at.to_rfc3339(),
+            self.images,
+            self.threshold,
+            self.failures.len()
+        );
+        out.push_str("<table>\n<tr><th>Attribute</th><th>Value</th><th>Images</th><th>Pairs</th><th>Accuracy</th><th>FAR</th><th>FRR</th></tr>\n");
+        let _ = writeln!(out, "{}", metrics_row("", "overall", self.images, &self.overall));
+        for group in &self.groups {
+            let _ = writeln!(
+                out,
+                "{}",
+                metrics_row(&group.attribute, &group.value, group.images, &group.metrics)
+            );
+        }
+        out.push_str("</table>\n");
+        if !self.failures.is_empty() {
+            out.push_str("<h2>Failures</h2>\n<ul>\n");
+            for failure in &self.failures {
+                let _ = writeln!(
+                    out,
+                    "<li>{}: {}</li>",
+                    escape(&failure.file),
+                    escape(&failure.error)
+                );
+            }
+            out.push_str("</ul>\n");
+        }
+        out.push_str("</body>\n</html>\n");
+        out
+    }
+}
+
+fn metrics_row(attribute: &str, value: &str, images: usize, metrics: &Metrics) -> String {
+    format!(
+        "<tr><td>{}</td><td>{}</td><td>{images}</td><td>{}</td><td>{:.1}%</td><td>{:.1}%</td><td>{:.1}%</td></tr>",
+        escape(attribute),
+        escape(value),
+        metrics.genuine_pairs + metrics.impostor_pairs,
+        metrics.accuracy * 100.0,
+        metrics.far * 100.0,
+        metrics.frr * 100.0
+    )
+}
+
+fn escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+fn load_labels(benchmark: &Path) -> Result<Vec<LabeledImage>> {
+    let path = benchmark.join(LABELS_FILE);
+    let raw = std::fs::read_to_string(&path)
+        .with_context(|| format!("failed to read labels {}", path.display()))?;
+    raw.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .enumerate()
+        .map(|(i, line)| {
+            serde_json::from_str(line)
+                .with_context(|| format!("bad label on line {} of {}", i + 1, path.display()))
+        })
+        .collect()
+}
+
+async fn embed_file(embedder: &Arc<FaceEmbedder>, path: &Path) -> Result<Vec<f32>> {
+    let image =
+        std::fs::read(path).with_context(|| format!("failed to read {}", path.display()))?;
+    let embedder = embedder.clone();
+    // The backend is a subprocess; keep it off the async runtime.
+    tokio::task::spawn_blocking(move || embedder.embed(&image)).await?
+}
+
+fn finish(
+    (label, result): (LabeledImage, Result<Vec<f32>>),
+    embedded: &mut Vec<(LabeledImage, Vec<f32>)>,
+    failures: &mut Vec<EvalFailure>,
+) {
+    match result {
+        Ok(embedding) => embedded.push((label, embedding)),
+        Err(e) => {
+            warn!(file = label.file, "benchmark image failed to embed: {e:#}");
+            failures.push(EvalFailure {
+                file: label.file,
+                error: format!("{e:#}"),
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> EmbeddingConfig {
+        let mut config = EmbeddingConfig::load_with(Path::new("/nonexistent"), &[]).unwrap();
+        config.embedder.command = "cat \"$IMAGE\"".to_string();
+        config
+    }
+
+    /// Two subjects per group; within group `a` the subjects separate
+    /// cleanly, within group `b` one subject's two crops are orthogonal.
+    fn benchmark() -> tempfile::TempDir {
+        let dir = tempfile::tempdir().unwrap();
+        let images = [
+            ("a1.json", "s1", "a", "[1.0, 0.0]"),
+            ("a2.json", "s1", "a", "[1.0, 0.0]"),
+            ("a3.json", "s2", "a", "[0.0, 1.0]"),
+            ("b1.json", "s3", "b", "[0.6, 0.8]"),
+            ("b2.json", "s3", "b", "[0.8, -0.6]"),
+        ];
+        let mut labels = String::new();
+        for (file, subject, group, vector) in images {
+            std::fs::write(dir.path().join(file), vector).unwrap();
+            labels.push_str(&format!(
+                "{{\"file\": \"{file}\", \"subject\": \"{subject}\", \"attributes\": {{\"group\": \"{group}\"}}}}\n"
+            ));
+        }
+        std::fs::write(dir.path().join(LABELS_FILE), labels).unwrap();
+        dir
+    }
+
+    fn group<'a>(report: &'a FairnessReport, value: &str) -> &'a GroupMetrics {
+        report
+            .groups
+            .iter()
+            .find(|g| g.attribute == "group" && g.value == value)
+            .expect("group present")
+    }
+
+    #[tokio::test]
+    async fn error_rates_are_broken_down_by_group() {
+        let benchmark = benchmark();
+        let report = run(&config(), benchmark.path(), 0.5).await.unwrap();
+
+        assert_eq!(report.images, 5);
+        // Group a: one genuine pair matching, two impostor pairs apart.
+        let a = group(&report, "a");
+        assert_eq!((a.images, a.metrics.genuine_pairs, a.metrics.impostor_pairs), (3, 1, 2));
+        assert_eq!(a.metrics.accuracy, 1.0);
+        // Group b: its only genuine pair is orthogonal, a clean miss.
+        let b = group(&report, "b");
+        assert_eq!(b.metrics.false_rejects, 1);
+        assert_eq!(b.metrics.frr, 1.0);
+        assert_eq!(report.overall.false_rejects, 1);
+        assert!(report.overall.accuracy < 1.0);
+    }
+
+    #[tokio::test]
+    async fn unreadable_images_are_reported_and_excluded_from_pairs() {
+        let benchmark = benchmark();
+        std::fs::remove_file(benchmark.path().join("a3.json")).unwrap();
+        let report = run(&config(), benchmark.path(), 0.5).await.unwrap();
+
+        assert_eq!(report.images, 4);
+        assert_eq!(report.failures.len(), 1);
+        assert_eq!(report.failures[0].file, "a3.json");
+        let a = group(&report, "a");
+        assert_eq!(a.metrics.impostor_pairs, 0);
+    }
+
+    #[tokio::test]
+    async fn reports_render_as_json_and_html() {
+        let benchmark = benchmark();
+        let report = run(&config(), benchmark.path(), 0.5).await.unwrap();
+        let output = tempfile::tempdir().unwrap();
+
+        let path = report.write(FairnessFormat::Json, output.path()).unwrap();
+        let parsed: serde_json::Value =
+            serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(parsed["images"], 5);
+
+        let path = report.write(FairnessFormat::Html, output.path()).unwrap();
+        let html = std::fs::read_to_string(&path).unwrap();
+        assert!(html.contains("<td>group</td>"));
+        assert!(html.contains("overall"));
+        assert!(FairnessFormat::parse("csv").is_err());
+    }
+}
@@ -0,0 +1,283 @@
+//! Asynchronous embedding jobs.
+//!
+//! Bulk re-embedding campaigns push far more crops than a synchronous
+//! endpoint should hold connections for. In async mode `/api/embed`
+//! returns a job id immediately, a small worker pool drains an internal
+//! queue, and the finished job is POSTed to the caller's webhook or
+//! polled at `/api/jobs/{id}`. Jobs live in memory: a restart drops the
+//! backlog, which a campaign driver must treat as "resubmit what never
+//! called back".
+
+use crate::config::{OcclusionConfig, QueueConfig};
+use crate::embedder::FaceEmbedder;
+use crate::occlusion::{self, OcclusionReport};
+use crate::quantization::{self, Quantization, QuantizedEmbedding};
+use anyhow::{bail, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use tokio::sync::mpsc;
+use tracing::{info, warn};
+use uuid::Uuid;
+
+/// How `/api/embed` answers: inline, or with a queued job.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum JobMode {
+    #[default]
+    Sync,
+    Async,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum JobStatus {
+    Queued,
+    Running,
+    Completed,
+    Failed,
+}
+
+/// One queued embedding, also the webhook callback payload.
+#[derive(Debug, Clone, Serialize)]
+pub struct EmbedJob {
+    pub id: Uuid,
+    pub status: JobStatus,
+    pub format: Quantization,
+    pub callback_url: Option<String>,
+    pub dimension: Option<usize>,
+    pub embedding: Option<QuantizedEmbedding>,
+    /// Occlusion screening of the crop; `None` when screening is
+    /// disabled or the crop could not be decoded.
+    pub occlusion: Option<OcclusionReport>,
+    pub error: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+struct Work {
+    id: Uuid,
+    image: Vec<u8>,
+}
+
+pub struct JobQueue {
+    jobs: Mutex<HashMap<Uuid, EmbedJob>>,
+    tx: mpsc::Sender<Work>,
+    occlusion: OcclusionConfig,
+}
+
+impl JobQueue {
+    /// Build the queue and spawn its workers on the current runtime.
+    pub fn new(
+        embedder: Arc<FaceEmbedder>,
+        config: &QueueConfig,
+        occlusion: &OcclusionConfig,
+    ) -> Arc<Self> {
+        let (tx, rx) = mpsc::channel(config.depth.max(1));
+        let queue = Arc::new(Self {
+            jobs: Mutex::new(HashMap::new()),
+            tx,
+            occlusion: occlusion.clone(),
+        });
+        let rx = Arc::new(tokio::sync::Mutex::new(rx));
+        // At least one worker, or the queue could never drain.
+        for _ in 0..config.workers.max(1) {
+            let queue = queue.clone();
+            let embedder = embedder.clone();
+            let rx = rx.clone();
+            tokio::spawn(async move {
+                loop {
+                    let work = rx.lock().await.recv().await;
+                    let Some(work) = work else { break };
+                    queue.process(&embedder, work).await;
+                }
+            });
+        }
+        queue
+    }
+
+    /// Queue an embedding and return its job id; a full queue is an
+    /// error the caller retries with backoff.
+    pub fn submit(
+        &self,
+        image: Vec<u8>,
+        format: Quantization,
+        callback_url: Option<String>,
+    ) -> Result<Uuid> {
+        let id = Uuid::new_v4();
+        let now = Utc::now();
+        self.jobs.lock().expect("jobs lock poisoned").insert(
+            id,
+            EmbedJob {
+                id,
+                status: JobStatus::Queued,
+                format,
+                callback_url,
+                dimension: None,
+                embedding: None,
+                occlusion: None,
+                error: None,
+                created_at: now,
+                updated_at: now,
+            },
+        );
+        if self.tx.try_send(Work { id, image }).is_err() {
+            self.jobs.lock().expect("jobs lock poisoned").remove(&id);
+            bail!("job queue is full");
+        }
+        Ok(id)
+    }
+
+    pub fn get(&self, id: Uuid) -> Option<EmbedJob> {
+        self.jobs.lock().expect("jobs lock poisoned").get(&id).cloned()
+    }
+
+    async fn process(&self, embedder: &Arc<FaceEmbedder>, work: Work) {
+        self.update(work.id, |job| job.status = JobStatus::Running);
+        let embedder = embedder.clone();
+        let occlusion_config = self.occlusion.clone();
+        // The backend is a subprocess; keep it off the async runtime.
+        // Occlusion screening is best-effort: an undecodable crop scores
+        // nothing but still goes to the backend, which owns the verdict.
+        let (outcome, occlusion) = tokio::task::spawn_blocking(move || {
+            let report = occlusion_config
+                .enabled
+                .then(|| occlusion::analyze(&work.image, &occlusion_config).ok())
+                .flatten();
+            (embedder.embed(&work.image), report)
+        })
+        .await
+        .unwrap_or_else(|e| (Err(e.into()), None));
+        let job = match outcome {
+            Ok(embedding) => self.update(work.id, |job| {
+                job.status = JobStatus::Completed;
+                job.dimension = Some(embedding.len());
+                job.embedding = Some(quantization::quantize(&embedding, job.format));
+                job.occlusion = occlusion;
+            }),
+            Err(e) => self.update(work.id, |job| {
+                job.status = JobStatus::Failed;
+                job.error = Some(format!("{e:#}"));
+            }),
+        };
+        if let Some(job) = job {
+            info!(job = %job.id, status = ?job.status, "embedding job finished");
+            if let Some(url) = job.callback_url.clone() {
+                send_callback(&url, &job).await;
+            }
+        }
+    }
+
+    fn update(&self, id: Uuid, apply: impl FnOnce(&mut EmbedJob)) -> Option<EmbedJob> {
+        let mut jobs = self.jobs.lock().expect("jobs lock poisoned");
+        let job = jobs.get_mut(&id)?;
+        apply(job);
+        job.updated_at = Utc::now();
+        Some(job.clone())
+    }
+}
+
+/// POST the finished job to the caller's webhook; delivery is
+/// best-effort and the job stays pollable either way.
+async fn send_callback(url: &str, job: &EmbedJob) {
+    let result = reqwest::Client::new()
+        .post(url)
+        .json(job)
+        .send()
+        .await
+        .and_then(|response| response.error_for_status());
+    if let Err(e) = result {
+        warn!(job = %job.id, "result callback failed: {e:#}");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::EmbeddingConfig;
+    use std::time::Duration;
+
+    fn embedder(command: &str) -> Arc<FaceEmbedder> {
+        let mut config = EmbeddingConfig::load_with(std::path::Path::new("/nonexistent"), &[]).unwrap();
+        config.embedder.command = command.to_string();
+        Arc::new(FaceEmbedder::new(&config))
+    }
+
+    async fn wait_for_finish(queue: &JobQueue, id: Uuid) -> EmbedJob {
+        for _ in 0..200 {
+            let job = queue.get(id).expect("job exists");
+            if matches!(job.status, JobStatus::Completed | JobStatus::Failed) {
+                return job;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        panic!("job never finished");
+    }
+
+    #[tokio::test]
+    async fn queued_jobs_complete_and_are_pollable() {
+        let queue = JobQueue::new(
+            embedder("cat \"$IMAGE\""),
+            &QueueConfig::default(),
+            &OcclusionConfig::default(),
+        );
+        let id = queue
+            .submit(b"[0.6, 0.8]".to_vec(), Quantization::Int8, None)
+            .unwrap();
+        assert_eq!(queue.get(id).unwrap().status, JobStatus::Queued);
+        let job = wait_for_finish(&queue, id).await;
+        assert_eq!(job.status, JobStatus::Completed);
+        assert_eq!(job.dimension, Some(2));
+        assert!(matches!(
+            job.embedding,
+            Some(QuantizedEmbedding::Int8 { .. })
+        ));
+    }
+
+    #[tokio::test]
+    async fn backend_failures_mark_the_job_failed() {
+        let queue = JobQueue::new(
+            embedder("echo broken >&2; exit 1"),
+            &QueueConfig::default(),
+            &OcclusionConfig::default(),
+        );
+        let id = queue
+            .submit(b"ignored".to_vec(), Quantization::Float32, None)
+            .unwrap();
+        let job = wait_for_finish(&queue, id).await;
+        assert_eq!(job.status, JobStatus::Failed);
+        assert!(job.error.unwrap().contains("broken"));
+    }
+
+    #[tokio::test]
+    async fn a_full_queue_rejects_new_jobs() {
+        // One worker stuck on a slow backend, one queue slot behind it.
+        let config = QueueConfig {
+            workers: 1,
+            depth: 1,
+        };
+        let queue = JobQueue::new(
+            embedder("sleep 1; echo '[1.0]'"),
+            &config,
+            &OcclusionConfig::default(),
+        );
+        let first = queue
+            .submit(b"[1.0]".to_vec(), Quantization::Float32, None)
+            .unwrap();
+        // Wait for the worker to pick the first job up, freeing the slot.
+        for _ in 0..200 {
+            if queue.get(first).unwrap().status == JobStatus::Running {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        queue
+            .submit(b"[1.0]".to_vec(), Quantization::Float32, None)
+            .unwrap();
+        let err = queue
+            .submit(b"[1.0]".to_vec(), Quantization::Float32, None)
+            .unwrap_err();
+        assert!(err.to_string().contains("full"));
+    }
+}
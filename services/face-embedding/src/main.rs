@@ -0,0 +1,150 @@
+//! face-embedding: turns aligned face crops into similarity vectors for
+//! matching and dedup across the miniapp.
+
+mod api;
+mod config;
+mod embedder;
+mod encoding;
+mod fairness;
+mod jobs;
+mod occlusion;
+mod quantization;
+mod reembed;
+
+use anyhow::Result;
+use clap::{Parser, Subcommand};
+use config::EmbeddingConfig;
+use embedder::FaceEmbedder;
+use quantization::Quantization;
+use std::path::PathBuf;
+
+#[derive(Parser)]
+#[command(name = "face-embedding", about = "Aurum face embedding service")]
+struct Cli {
+    /// Path to the configuration file.
+    #[arg(long, default_value = "face-embedding.json")]
+    config: PathBuf,
+    /// Override a config value over the file and environment, e.g.
+    /// `--set web.port=9000`; repeatable.
+    #[arg(long = "set", value_name = "KEY=VALUE")]
+    set: Vec<String>,
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Start the embedding API server.
+    Serve,
+    /// Embed a single face crop and print the result as JSON.
+    Embed {
+        image: PathBuf,
+        /// Output format: float32, float16, or int8.
+        #[arg(long, default_value = "float32")]
+        format: String,
+    },
+    /// Report the similarity loss each quantization format costs for a
+    /// concrete embedding, so storage decisions are measured per model.
+    Compare { image: PathBuf },
+    /// Re-embed every photo in a manifest (a directory of crops, or a
+    /// file listing paths and URLs) into the store, resuming where an
+    /// interrupted run left off.
+    Reembed {
+        manifest: PathBuf,
+        /// Output format: float32, float16, or int8.
+        #[arg(long, default_value = "float32")]
+        format: String,
+        /// Override the configured store directory.
+        #[arg(long)]
+        store: Option<PathBuf>,
+    },
+    /// Evaluate matching accuracy, FAR, and FRR over a labeled benchmark
+    /// directory, broken down by demographic attribute, for fairness
+    /// audits. The directory holds aligned crops plus a labels.jsonl
+    /// naming each file's subject and attributes.
+    Evaluate {
+        benchmark: PathBuf,
+        /// Cosine similarity at or above this counts as a match.
+        #[arg(long, default_value_t = 0.8)]
+        threshold: f32,
+        /// Report format: json or html.
+        #[arg(long, default_value = "json")]
+        format: String,
+        /// Directory the report file is written into.
+        #[arg(long, default_value = "reports")]
+        output: PathBuf,
+    },
+    /// Print the effective configuration — defaults, file, environment,
+    /// and --set overrides merged — as JSON, with secrets redacted.
+    PrintEffectiveConfig,
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    aurum_telemetry::init(aurum_telemetry::TelemetryConfig::from_env(
+        "face-embedding",
+        "face_embedding=info,warn",
+    ))?;
+
+    let cli = Cli::parse();
+    let config = EmbeddingConfig::load_with(&cli.config, &cli.set)?;
+
+    match cli.command {
+        Command::Serve => api::ApiServer::new(config).serve().await,
+        Command::Embed { image, format } => {
+            let format = Quantization::parse(&format)?;
+            let embedding = embed_file(&config, &image).await?;
+            let quantized = quantization::quantize(&embedding, format);
+            println!("{}", serde_json::to_string_pretty(&quantized)?);
+            Ok(())
+        }
+        Command::Compare { image } => {
+            let embedding = embed_file(&config, &image).await?;
+            let reports: Vec<_> = [Quantization::Float16, Quantization::Int8]
+                .into_iter()
+                .map(|format| quantization::accuracy_report(&embedding, format))
+                .collect();
+            println!("{}", serde_json::to_string_pretty(&reports)?);
+            Ok(())
+        }
+        Command::Reembed {
+            manifest,
+            format,
+            store,
+        } => {
+            let format = Quantization::parse(&format)?;
+            let config = reembed::with_store(config, store);
+            let report = reembed::run(&config, &manifest, format).await?;
+            println!("{}", serde_json::to_string_pretty(&report)?);
+            anyhow::ensure!(
+                report.failed == 0,
+                "{} of {} images failed",
+                report.failed,
+                report.total
+            );
+            Ok(())
+        }
+        Command::Evaluate {
+            benchmark,
+            threshold,
+            format,
+            output,
+        } => {
+            let format = fairness::FairnessFormat::parse(&format)?;
+            let report = fairness::run(&config, &benchmark, threshold).await?;
+            let path = report.write(format, &output)?;
+            println!("wrote {} report to {}", format.as_str(), path.display());
+            Ok(())
+        }
+        Command::PrintEffectiveConfig => {
+            println!("{}", serde_json::to_string_pretty(&config)?);
+            Ok(())
+        }
+    }
+}
+
+async fn embed_file(config: &EmbeddingConfig, image: &PathBuf) -> Result<Vec<f32>> {
+    let bytes = std::fs::read(image)?;
+    let embedder = FaceEmbedder::new(config);
+    tokio::task::spawn_blocking(move || embedder.embed(&bytes)).await?
+}
@@ -0,0 +1,216 @@
+//! Occlusion screening for face crops before embedding.
+//!
+//! A masked or sunglassed face still embeds, but the vector carries
+//! little identity signal and pollutes the match index. This stage
+//! scores the crop with cheap pixel statistics — no extra model round
+//! trip — so onboarding can ask for a retake instead of storing a
+//! low-signal embedding. The probabilities are heuristics calibrated on
+//! aligned crops, not classifier outputs; treat them as a screening
+//! signal, not ground truth.
+
+use crate::config::OcclusionConfig;
+use anyhow::{Context, Result};
+use image::imageops::FilterType;
+use image::GenericImageView;
+use serde::Serialize;
+
+/// The size crops are normalized to before scoring; the bands below are
+/// expressed in these coordinates.
+const SIDE: u32 = 64;
+
+/// Occlusion signal returned alongside the embedding.
+#[derive(Debug, Clone, Serialize)]
+pub struct OcclusionReport {
+    /// How mask-like the lower face looks: smooth and chromatically far
+    /// from the visible skin.
+    pub mask_prob: f32,
+    /// How sunglasses-like the eye band looks: uniformly dark.
+    pub sunglasses_prob: f32,
+    /// Whether enough of the crop deviates from the skin reference that
+    /// the face should be treated as partially occluded.
+    pub partial: bool,
+}
+
+/// Score a face crop. Errors only when the image cannot be decoded.
+pub fn analyze(image: &[u8], config: &OcclusionConfig) -> Result<OcclusionReport> {
+    let decoded = image::load_from_memory(image).context("cannot decode face crop")?;
+    let rgb = decoded
+        .resize_exact(SIDE, SIDE, FilterType::Triangle)
+        .to_rgb8();
+
+    // The mid-face band (cheeks and nose bridge) is the part least
+    // likely to be covered by either a mask or sunglasses; it anchors
+    // what "skin" looks like in this photo.
+    let skin = band_mean(&rgb, 26, 38);
+
+    // Sunglasses: the eye band is uniformly dark instead of the usual
+    // dark-pupil-on-bright-skin contrast.
+    let (eye_mean, eye_stddev) = band_luma(&rgb, 12, 26);
+    let darkness = (1.0 - eye_mean / 128.0).clamp(0.0, 1.0);
+    let uniformity = (1.0 - eye_stddev / 40.0).clamp(0.0, 1.0);
+    let sunglasses_prob = darkness * uniformity;
+
+    // Mask: the lower face is far from the skin reference in color and
+    // smoother than chin-and-lips texture. Distance gates the score so
+    // a bare, smooth chin does not read as a mask.
+    let lower_mean = band_mean(&rgb, 42, 60);
+    let (_, lower_stddev) = band_luma(&rgb, 42, 60);
+    let distance = (color_distance(lower_mean, skin) / 64.0).clamp(0.0, 1.0);
+    let smoothness = (1.0 - lower_stddev / 48.0).clamp(0.0, 1.0);
+    let mask_prob = distance * (0.5 + 0.5 * smoothness);
+
+    // Coverage: the share of 8x8 cells that are both flat and far from
+    // the skin reference — hands, hair, and held objects over the face.
+    let cell = SIDE / 8;
+    let mut covered = 0u32;
+    for gy in 0..8 {
+        for gx in 0..8 {
+            let view = rgb.view(gx * cell, gy * cell, cell, cell).to_image();
+            let mean = region_mean(&view);
+            let stddev = region_luma_stddev(&view);
+            if stddev < 10.0 && color_distance(mean, skin) > 64.0 {
+                covered += 1;
+            }
+        }
+    }
+    let coverage = covered as f32 / 64.0;
+    let partial = coverage >= config.partial_threshold
+        || mask_prob >= config.partial_threshold
+        || sunglasses_prob >= config.partial_threshold;
+
+    Ok(OcclusionReport {
+        mask_prob,
+        sunglasses_prob,
+        partial,
+    })
+}
+
+/// Mean RGB of the rows `top..bottom`, with an 8px horizontal margin so
+/// background at the crop edges does not skew the statistics.
+fn band_mean(rgb: &image::RgbImage, top: u32, bottom: u32) -> [f32; 3] {
+    region_mean(&rgb.view(8, top, SIDE - 16, bottom - top).to_image())
+}
+
+/// Mean and standard deviation of luminance over the same band.
+fn band_luma(rgb: &image::RgbImage, top: u32, bottom: u32) -> (f32, f32) {
+    let view = rgb.view(8, top, SIDE - 16, bottom - top).to_image();
+    (region_luma_mean(&view), region_luma_stddev(&view))
+}
+
+fn region_mean(region: &image::RgbImage) -> [f32; 3] {
+    let mut sum = [0.0f32; 3];
+    for pixel in region.pixels() {
+        for (acc, channel) in sum.iter_mut().zip(pixel.0) {
+            *acc += f32::from(channel);
+        }
+    }
+    let n = (region.width() * region.height()) as f32;
+    sum.map(|s| s / n)
+}
+
+fn region_luma_mean(region: &image::RgbImage) -> f32 {
+    let sum: f32 = region.pixels().map(luma).sum();
+    sum / (region.width() * region.height()) as f32
+}
+
+fn region_luma_stddev(region: &image::RgbImage) -> f32 {
+    let mean = region_luma_mean(region);
+    let var: f32 = region
+        .pixels()
+        .map(|p| (luma(p) - mean).powi(2))
+        .sum::<f32>()
+        / (region.width() * region.height()) as f32;
+    var.sqrt()
+}
+
+fn luma(pixel: &image::Rgb<u8>) -> f32 {
+    let [r, g, b] = pixel.0;
+    0.299 * f32::from(r) + 0.587 * f32::from(g) + 0.114 * f32::from(b)
+}
+
+/// Mean absolute per-channel distance between two colors.
+fn color_distance(a: [f32; 3], b: [f32; 3]) -> f32 {
+    (a.iter().zip(b).map(|(x, y)| (x - y).abs()).sum::<f32>()) / 3.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{DynamicImage, Rgb, RgbImage};
+    use std::io::Cursor;
+
+    const SKIN: Rgb<u8> = Rgb([200, 168, 144]);
+
+    /// A synthetic aligned crop: skin everywhere, darker eyes and a
+    /// mouth line where an aligned face would have them.
+    fn clear_face() -> RgbImage {
+        let mut face = RgbImage::from_pixel(SIDE, SIDE, SKIN);
+        for (cx, cy) in [(22u32, 19u32), (42, 19)] {
+            for y in cy - 3..cy + 3 {
+                for x in cx - 4..cx + 4 {
+                    face.put_pixel(x, y, Rgb([60, 40, 35]));
+                }
+            }
+        }
+        for x in 24..40 {
+            face.put_pixel(x, 48, Rgb([120, 70, 70]));
+            face.put_pixel(x, 49, Rgb([120, 70, 70]));
+        }
+        face
+    }
+
+    fn encode(face: RgbImage) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        DynamicImage::ImageRgb8(face)
+            .write_to(&mut Cursor::new(&mut bytes), image::ImageFormat::Png)
+            .unwrap();
+        bytes
+    }
+
+    #[test]
+    fn a_clear_face_scores_low_everywhere() {
+        let report = analyze(&encode(clear_face()), &OcclusionConfig::default()).unwrap();
+        assert!(report.mask_prob < 0.3, "mask_prob {}", report.mask_prob);
+        assert!(
+            report.sunglasses_prob < 0.3,
+            "sunglasses_prob {}",
+            report.sunglasses_prob
+        );
+        assert!(!report.partial);
+    }
+
+    #[test]
+    fn a_covered_lower_face_reads_as_a_mask() {
+        let mut face = clear_face();
+        for y in 38..SIDE {
+            for x in 0..SIDE {
+                face.put_pixel(x, y, Rgb([150, 170, 235]));
+            }
+        }
+        let report = analyze(&encode(face), &OcclusionConfig::default()).unwrap();
+        assert!(report.mask_prob > 0.5, "mask_prob {}", report.mask_prob);
+        assert!(report.partial);
+    }
+
+    #[test]
+    fn a_uniformly_dark_eye_band_reads_as_sunglasses() {
+        let mut face = clear_face();
+        for y in 12..26 {
+            for x in 4..SIDE - 4 {
+                face.put_pixel(x, y, Rgb([18, 16, 16]));
+            }
+        }
+        let report = analyze(&encode(face), &OcclusionConfig::default()).unwrap();
+        assert!(
+            report.sunglasses_prob > 0.5,
+            "sunglasses_prob {}",
+            report.sunglasses_prob
+        );
+        assert!(report.partial);
+    }
+
+    #[test]
+    fn undecodable_crops_error_instead_of_scoring() {
+        assert!(analyze(b"not an image", &OcclusionConfig::default()).is_err());
+    }
+}
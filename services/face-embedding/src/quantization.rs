@@ -0,0 +1,246 @@
+//! Embedding quantization output options.
+//!
+//! A 512-dimension float32 embedding is 2KiB on the wire and in the
+//! vector store; half-precision halves that and int8 quarters it, at an
+//! accuracy cost that is tiny for unit-norm face embeddings. Callers
+//! pick the format per request, and the `compare` utility reports the
+//! similarity loss for a given model so the trade-off is measured, not
+//! guessed.
+
+use anyhow::{bail, Result};
+use serde::{Deserialize, Serialize};
+
+/// The output formats an embedding can be returned in.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Quantization {
+    #[default]
+    Float32,
+    Float16,
+    Int8,
+}
+
+impl Quantization {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Quantization::Float32 => "float32",
+            Quantization::Float16 => "float16",
+            Quantization::Int8 => "int8",
+        }
+    }
+
+    pub fn parse(s: &str) -> Result<Self> {
+        match s {
+            "float32" => Ok(Quantization::Float32),
+            "float16" => Ok(Quantization::Float16),
+            "int8" => Ok(Quantization::Int8),
+            other => bail!("unknown quantization format: {other}"),
+        }
+    }
+}
+
+/// An embedding in its requested output format.
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "format", rename_all = "snake_case")]
+pub enum QuantizedEmbedding {
+    Float32 { values: Vec<f32> },
+    /// IEEE 754 half-precision bit patterns.
+    Float16 { values: Vec<u16> },
+    /// `value[i] ≈ values[i] as f32 * scale`.
+    Int8 { values: Vec<i8>, scale: f32 },
+}
+
+pub fn quantize(embedding: &[f32], format: Quantization) -> QuantizedEmbedding {
+    match format {
+        Quantization::Float32 => QuantizedEmbedding::Float32 {
+            values: embedding.to_vec(),
+        },
+        Quantization::Float16 => QuantizedEmbedding::Float16 {
+            values: embedding.iter().map(|&v| f32_to_f16_bits(v)).collect(),
+        },
+        Quantization::Int8 => {
+            let max_abs = embedding.iter().fold(0.0f32, |acc, v| acc.max(v.abs()));
+            let scale = if max_abs == 0.0 { 1.0 } else { max_abs / 127.0 };
+            QuantizedEmbedding::Int8 {
+                values: embedding
+                    .iter()
+                    .map(|&v| (v / scale).round().clamp(-127.0, 127.0) as i8)
+                    .collect(),
+                scale,
+            }
+        }
+    }
+}
+
+/// Reconstruct full precision from any output format, for similarity
+/// comparisons against the original.
+pub fn dequantize(embedding: &QuantizedEmbedding) -> Vec<f32> {
+    match embedding {
+        QuantizedEmbedding::Float32 { values } => values.clone(),
+        QuantizedEmbedding::Float16 { values } => {
+            values.iter().map(|&bits| f16_bits_to_f32(bits)).collect()
+        }
+        QuantizedEmbedding::Int8 { values, scale } => {
+            values.iter().map(|&v| v as f32 * scale).collect()
+        }
+    }
+}
+
+/// Cosine similarity; 0.0 when either vector is all zeros.
+pub fn cosine_similarity(a: &[f32], b: &[f32]) -> f32 {
+    let dot: f32 = a.iter().zip(b).map(|(x, y)| x * y).sum();
+    let norm_a: f32 = a.iter().map(|x| x * x).sum::<f32>().sqrt();
+    let norm_b: f32 = b.iter().map(|x| x * x).sum::<f32>().sqrt();
+    if norm_a == 0.0 || norm_b == 0.0 {
+        0.0
+    } else {
+        dot / (norm_a * norm_b)
+    }
+}
+
+/// How much similarity a format costs for one concrete embedding.
+#[derive(Debug, Serialize)]
+pub struct AccuracyReport {
+    pub format: &'static str,
+    pub dimension: usize,
+    /// Cosine similarity between the full-precision embedding and its
+    /// quantized round trip; 1.0 means no measurable loss.
+    pub cosine_similarity: f32,
+    pub max_abs_error: f32,
+    pub mean_abs_error: f32,
+}
+
+pub fn accuracy_report(embedding: &[f32], format: Quantization) -> AccuracyReport {
+    let restored = dequantize(&quantize(embedding, format));
+    let errors: Vec<f32> = embedding
+        .iter()
+        .zip(&restored)
+        .map(|(a, b)| (a - b).abs())
+        .collect();
+    AccuracyReport {
+        format: format.as_str(),
+        dimension: embedding.len(),
+        cosine_similarity: cosine_similarity(embedding, &restored),
+        max_abs_error: errors.iter().fold(0.0f32, |acc, &e| acc.max(e)),
+        mean_abs_error: errors.iter().sum::<f32>() / errors.len().max(1) as f32,
+    }
+}
+
+fn f32_to_f16_bits(value: f32) -> u16 {
+    let bits = value.to_bits();
+    let sign = ((bits >> 16) & 0x8000) as u16;
+    let exp = ((bits >> 23) & 0xff) as i32;
+    let mantissa = bits & 0x007f_ffff;
+    if exp == 255 {
+        // Infinity and NaN.
+        return sign | 0x7c00 | u16::from(mantissa != 0) << 9;
+    }
+    let exp16 = exp - 127 + 15;
+    if exp16 >= 31 {
+        // Overflow to infinity.
+        return sign | 0x7c00;
+    }
+    if exp16 <= 0 {
+        if exp16 < -10 {
+            // Underflow to signed zero.
+            return sign;
+        }
+        // Subnormal: shift the implicit bit into the mantissa.
+        let mantissa = mantissa | 0x0080_0000;
+        let shift = 14 - exp16;
+        let half = (mantissa >> shift) as u16;
+        let round = ((mantissa >> (shift - 1)) & 1) as u16;
+        return sign | (half + round);
+    }
+    let half = sign | ((exp16 as u16) << 10) | ((mantissa >> 13) as u16);
+    // Round to nearest; a carry into the exponent is still correct.
+    half + ((mantissa >> 12) & 1) as u16
+}
+
+fn f16_bits_to_f32(bits: u16) -> f32 {
+    let sign = u32::from(bits & 0x8000) << 16;
+    let exp = u32::from(bits >> 10) & 0x1f;
+    let mantissa = u32::from(bits & 0x3ff);
+    let bits32 = if exp == 0 {
+        if mantissa == 0 {
+            sign
+        } else {
+            // Subnormal: renormalize.
+            let mut exp32 = 127 - 15 + 1;
+            let mut m = mantissa;
+            while m & 0x400 == 0 {
+                m <<= 1;
+                exp32 -= 1;
+            }
+            sign | ((exp32 as u32) << 23) | ((m & 0x3ff) << 13)
+        }
+    } else if exp == 31 {
+        sign | 0x7f80_0000 | (mantissa << 13)
+    } else {
+        sign | ((exp + 127 - 15) << 23) | (mantissa << 13)
+    };
+    f32::from_bits(bits32)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A deterministic stand-in for a unit-norm model embedding.
+    fn embedding(dimension: usize) -> Vec<f32> {
+        let raw: Vec<f32> = (0..dimension)
+            .map(|i| (i as f32 * 0.731).sin() * 0.9)
+            .collect();
+        let norm = raw.iter().map(|v| v * v).sum::<f32>().sqrt();
+        raw.into_iter().map(|v| v / norm).collect()
+    }
+
+    #[test]
+    fn float16_round_trip_is_nearly_lossless() {
+        let report = accuracy_report(&embedding(512), Quantization::Float16);
+        assert!(report.cosine_similarity > 0.99999);
+        assert!(report.max_abs_error < 1e-3);
+    }
+
+    #[test]
+    fn int8_round_trip_keeps_similarity_within_tolerance() {
+        let original = embedding(512);
+        let quantized = quantize(&original, Quantization::Int8);
+        let QuantizedEmbedding::Int8 { values, scale } = &quantized else {
+            panic!("expected int8");
+        };
+        assert_eq!(values.len(), original.len());
+        assert!(*scale > 0.0);
+        let report = accuracy_report(&original, Quantization::Int8);
+        assert!(report.cosine_similarity > 0.999);
+        // Worst-case rounding error is half a quantization step.
+        assert!(report.max_abs_error <= scale * 0.5 + f32::EPSILON);
+    }
+
+    #[test]
+    fn float32_is_the_identity_and_formats_parse() {
+        let original = embedding(8);
+        let report = accuracy_report(&original, Quantization::Float32);
+        assert_eq!(report.cosine_similarity, 1.0);
+        assert_eq!(report.max_abs_error, 0.0);
+
+        for format in [
+            Quantization::Float32,
+            Quantization::Float16,
+            Quantization::Int8,
+        ] {
+            assert_eq!(Quantization::parse(format.as_str()).unwrap(), format);
+        }
+        assert!(Quantization::parse("bfloat16").is_err());
+    }
+
+    #[test]
+    fn zero_vectors_do_not_divide_by_zero() {
+        let zeros = vec![0.0f32; 16];
+        let QuantizedEmbedding::Int8 { scale, .. } = quantize(&zeros, Quantization::Int8) else {
+            panic!("expected int8");
+        };
+        assert_eq!(scale, 1.0);
+        assert_eq!(cosine_similarity(&zeros, &zeros), 0.0);
+    }
+}
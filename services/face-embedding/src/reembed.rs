@@ -0,0 +1,295 @@
+//! Bulk re-embedding for model or format upgrades.
+//!
+//! A manifest names the photos: either a directory of face crops, or a
+//! file listing local paths and http(s) URLs one per line (blank lines
+//! and `#` comments are ignored). Images stream through the embedder
+//! with the same concurrency the job queue uses, and each finished
+//! embedding lands in the store directory as one JSON file per source.
+//! A checkpoint file alongside the store makes an interrupted run
+//! resumable: sources already recorded there are skipped next time.
+
+use crate::config::EmbeddingConfig;
+use crate::embedder::FaceEmbedder;
+use crate::quantization::{self, Quantization};
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tracing::{info, warn};
+
+const CHECKPOINT_FILE: &str = "reembed-checkpoint.json";
+
+/// Final summary of a re-embedding run.
+#[derive(Debug, Serialize)]
+pub struct ReembedReport {
+    pub total: usize,
+    pub completed: usize,
+    /// Sources the checkpoint already covered.
+    pub skipped: usize,
+    pub failed: usize,
+    /// Mean cosine similarity between the stored and full-precision
+    /// embeddings, over everything completed in this run.
+    pub mean_cosine_similarity: Option<f32>,
+    pub failures: Vec<ReembedFailure>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ReembedFailure {
+    pub source: String,
+    pub error: String,
+}
+
+/// Sources finished by earlier runs; rewritten after every completion
+/// so a crash loses at most the in-flight images.
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct Checkpoint {
+    completed: Vec<String>,
+}
+
+impl Checkpoint {
+    fn load(store: &Path) -> Result<Self> {
+        let path = store.join(CHECKPOINT_FILE);
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let raw = std::fs::read_to_string(&path)
+            .with_context(|| format!("failed to read checkpoint {}", path.display()))?;
+        serde_json::from_str(&raw)
+            .with_context(|| format!("failed to parse checkpoint {}", path.display()))
+    }
+
+    fn save(&self, store: &Path) -> Result<()> {
+        let path = store.join(CHECKPOINT_FILE);
+        std::fs::write(&path, serde_json::to_string_pretty(self)?)
+            .with_context(|| format!("failed to write checkpoint {}", path.display()))
+    }
+}
+
+/// Re-embed every source in the manifest, writing results into the
+/// configured store directory and skipping whatever the checkpoint
+/// already covers.
+pub async fn run(
+    config: &EmbeddingConfig,
+    manifest: &Path,
+    format: Quantization,
+) -> Result<ReembedReport> {
+    let store = config.store.directory.clone();
+    std::fs::create_dir_all(&store)
+        .with_context(|| format!("failed to create store directory {}", store.display()))?;
+    let sources = load_manifest(manifest)?;
+    let mut checkpoint = Checkpoint::load(&store)?;
+    let done: HashSet<String> = checkpoint.completed.iter().cloned().collect();
+
+    let mut report = ReembedReport {
+        total: sources.len(),
+        completed: 0,
+        skipped: 0,
+        failed: 0,
+        mean_cosine_similarity: None,
+        failures: Vec::new(),
+    };
+    let mut cosine_sum = 0.0f64;
+
+    let embedder = Arc::new(FaceEmbedder::new(config));
+    let fetch = Arc::new(config.fetch.clone());
+    let workers = config.queue.workers.max(1);
+    let mut inflight = tokio::task::JoinSet::new();
+    for source in sources {
+        if done.contains(&source) {
+            report.skipped += 1;
+            continue;
+        }
+        while inflight.len() >= workers {
+            let outcome = inflight.join_next().await.expect("inflight not empty")?;
+            finish(outcome, format, &store, &mut checkpoint, &mut report, &mut cosine_sum)?;
+        }
+        let embedder = embedder.clone();
+        let fetch = fetch.clone();
+        inflight.spawn(async move {
+            let result = embed_source(&embedder, &fetch, &source).await;
+            (source, result)
+        });
+    }
+    while let Some(outcome) = inflight.join_next().await {
+        finish(outcome?, format, &store, &mut checkpoint, &mut report, &mut cosine_sum)?;
+    }
+
+    if report.completed > 0 {
+        report.mean_cosine_similarity = Some((cosine_sum / report.completed as f64) as f32);
+    }
+    info!(
+        total = report.total,
+        completed = report.completed,
+        skipped = report.skipped,
+        failed = report.failed,
+        "re-embedding run finished"
+    );
+    Ok(report)
+}
+
+/// Expand the manifest into source strings: every regular file for a
+/// directory, one path or URL per non-comment line for a file.
+fn load_manifest(manifest: &Path) -> Result<Vec<String>> {
+    if manifest.is_dir() {
+        let mut sources = Vec::new();
+        for entry in std::fs::read_dir(manifest)
+            .with_context(|| format!("failed to read manifest directory {}", manifest.display()))?
+        {
+            let entry = entry?;
+            if entry.file_type()?.is_file() {
+                sources.push(entry.path().display().to_string());
+            }
+        }
+        sources.sort();
+        return Ok(sources);
+    }
+    let raw = std::fs::read_to_string(manifest)
+        .with_context(|| format!("failed to read manifest {}", manifest.display()))?;
+    Ok(raw
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(str::to_string)
+        .collect())
+}
+
+async fn embed_source(
+    embedder: &Arc<FaceEmbedder>,
+    fetch: &aurum_objectstore::FetchConfig,
+    source: &str,
+) -> Result<Vec<f32>> {
+    // Remote sources go through the allowlisted object-store fetch the
+    // API uses; everything else is a local path.
+    let image = if source.starts_with("s3://")
+        || source.starts_with("http://")
+        || source.starts_with("https://")
+    {
+        aurum_objectstore::fetch(source, fetch).await?
+    } else {
+        std::fs::read(source).with_context(|| format!("failed to read {source}"))?
+    };
+    let embedder = embedder.clone();
+    // The backend is a subprocess; keep it off the async runtime.
+    tokio::task::spawn_blocking(move || embedder.embed(&image)).await?
+}
+
+fn finish(
+    (source, result): (String, Result<Vec<f32>>),
+    format: Quantization,
+    store: &Path,
+    checkpoint: &mut Checkpoint,
+    report: &mut ReembedReport,
+    cosine_sum: &mut f64,
+) -> Result<()> {
+    match result {
+        Ok(embedding) => {
+            let accuracy = quantization::accuracy_report(&embedding, format);
+            let record = serde_json::json!({
+                "source": source,
+                "dimension": embedding.len(),
+                "embedding": quantization::quantize(&embedding, format),
+            });
+            let path = store.join(format!("{}.json", store_key(&source)));
+            std::fs::write(&path, serde_json::to_string(&record)?)
+                .with_context(|| format!("failed to write embedding {}", path.display()))?;
+            *cosine_sum += accuracy.cosine_similarity as f64;
+            report.completed += 1;
+            checkpoint.completed.push(source);
+            checkpoint.save(store)?;
+        }
+        Err(e) => {
+            warn!(source, "re-embedding failed: {e:#}");
+            report.failed += 1;
+            report.failures.push(ReembedFailure {
+                source,
+                error: format!("{e:#}"),
+            });
+        }
+    }
+    Ok(())
+}
+
+/// A stable file name for a source: its sanitized basename plus an
+/// FNV-1a hash of the full source string, so distinct URLs sharing a
+/// basename cannot collide.
+fn store_key(source: &str) -> String {
+    let basename = source.rsplit('/').next().unwrap_or(source);
+    let stem: String = basename
+        .chars()
+        .take(64)
+        .map(|c| if c.is_ascii_alphanumeric() || c == '.' || c == '-' { c } else { '_' })
+        .collect();
+    format!("{stem}-{:016x}", fnv1a64(source.as_bytes()))
+}
+
+fn fnv1a64(bytes: &[u8]) -> u64 {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for &byte in bytes {
+        hash ^= byte as u64;
+        hash = hash.wrapping_mul(0x0000_0100_0000_01b3);
+    }
+    hash
+}
+
+/// Override the store directory from the command line.
+pub fn with_store(mut config: EmbeddingConfig, store: Option<PathBuf>) -> EmbeddingConfig {
+    if let Some(store) = store {
+        config.store.directory = store;
+    }
+    config
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(store: &Path) -> EmbeddingConfig {
+        let mut config = EmbeddingConfig::load_with(Path::new("/nonexistent"), &[]).unwrap();
+        config.embedder.command = "cat \"$IMAGE\"".to_string();
+        config.store.directory = store.to_path_buf();
+        config
+    }
+
+    #[tokio::test]
+    async fn a_directory_manifest_is_resumable() {
+        let images = tempfile::tempdir().unwrap();
+        std::fs::write(images.path().join("a.json"), b"[0.6, 0.8]").unwrap();
+        std::fs::write(images.path().join("b.json"), b"[1.0, 0.0]").unwrap();
+        let store = tempfile::tempdir().unwrap();
+        let config = config(store.path());
+
+        let report = run(&config, images.path(), Quantization::Float32).await.unwrap();
+        assert_eq!((report.total, report.completed, report.skipped), (2, 2, 0));
+        assert_eq!(report.mean_cosine_similarity, Some(1.0));
+        let stored = std::fs::read_dir(store.path()).unwrap().count();
+        assert_eq!(stored, 3); // two embeddings plus the checkpoint
+
+        // A second run finds everything in the checkpoint.
+        let report = run(&config, images.path(), Quantization::Float32).await.unwrap();
+        assert_eq!((report.completed, report.skipped), (0, 2));
+    }
+
+    #[tokio::test]
+    async fn failures_are_reported_and_retried_next_run() {
+        let manifest = tempfile::NamedTempFile::new().unwrap();
+        std::fs::write(manifest.path(), "# campaign\n\n/nonexistent/crop.jpg\n").unwrap();
+        let store = tempfile::tempdir().unwrap();
+        let config = config(store.path());
+
+        let report = run(&config, manifest.path(), Quantization::Float32).await.unwrap();
+        assert_eq!((report.total, report.failed), (1, 1));
+        assert!(report.failures[0].error.contains("/nonexistent/crop.jpg"));
+        // Failures never enter the checkpoint, so the next run retries.
+        let report = run(&config, manifest.path(), Quantization::Float32).await.unwrap();
+        assert_eq!(report.skipped, 0);
+    }
+
+    #[test]
+    fn store_keys_distinguish_shared_basenames() {
+        let a = store_key("https://cdn.example/u/1/crop.jpg");
+        let b = store_key("https://cdn.example/u/2/crop.jpg");
+        assert_ne!(a, b);
+        assert!(a.starts_with("crop.jpg-"));
+    }
+}
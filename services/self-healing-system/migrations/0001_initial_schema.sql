@@ -0,0 +1,55 @@
+-- Full schema as of the migration set's introduction. Everything is
+-- IF NOT EXISTS because databases created before migrations existed were
+-- initialized with the same statements at startup; see database.rs for
+-- how their missing columns are backfilled.
+
+CREATE TABLE IF NOT EXISTS issues (
+    id TEXT PRIMARY KEY,
+    source TEXT NOT NULL,
+    project TEXT NOT NULL DEFAULT 'default',
+    service TEXT NOT NULL,
+    commit_sha TEXT NOT NULL,
+    classification TEXT NOT NULL,
+    log TEXT NOT NULL,
+    affected_files TEXT NOT NULL DEFAULT '[]',
+    status TEXT NOT NULL,
+    created_at TEXT NOT NULL,
+    updated_at TEXT NOT NULL
+);
+CREATE INDEX IF NOT EXISTS idx_issues_status ON issues(status, created_at DESC);
+
+CREATE TABLE IF NOT EXISTS patches (
+    id TEXT PRIMARY KEY,
+    issue_id TEXT NOT NULL,
+    description TEXT NOT NULL,
+    origin TEXT NOT NULL DEFAULT 'manual',
+    diff TEXT NOT NULL,
+    rollback_diff TEXT,
+    pr_url TEXT,
+    status TEXT NOT NULL,
+    validation TEXT,
+    created_at TEXT NOT NULL,
+    updated_at TEXT NOT NULL
+);
+CREATE INDEX IF NOT EXISTS idx_patches_issue ON patches(issue_id, created_at DESC);
+
+CREATE TABLE IF NOT EXISTS reviews (
+    id TEXT PRIMARY KEY,
+    patch_id TEXT NOT NULL,
+    reviewer TEXT NOT NULL,
+    verdict TEXT NOT NULL,
+    comment TEXT,
+    created_at TEXT NOT NULL
+);
+CREATE INDEX IF NOT EXISTS idx_reviews_patch ON reviews(patch_id, created_at DESC);
+
+CREATE TABLE IF NOT EXISTS llm_costs (
+    id TEXT PRIMARY KEY,
+    issue_id TEXT NOT NULL,
+    provider TEXT NOT NULL,
+    input_tokens INTEGER NOT NULL,
+    output_tokens INTEGER NOT NULL,
+    cost_usd REAL NOT NULL,
+    created_at TEXT NOT NULL
+);
+CREATE INDEX IF NOT EXISTS idx_llm_costs_created ON llm_costs(created_at);
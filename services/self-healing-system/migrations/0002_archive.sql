@@ -0,0 +1,14 @@
+-- Archive table the retention sweep moves expired issues and patches
+-- into before deleting the live rows. The payload is the full record
+-- (with its patches and reviews) as JSON, so nothing is lost to a
+-- compliance export by retention.
+
+CREATE TABLE IF NOT EXISTS archive (
+    id TEXT PRIMARY KEY,
+    kind TEXT NOT NULL,
+    project TEXT NOT NULL,
+    status TEXT NOT NULL,
+    payload TEXT NOT NULL,
+    archived_at TEXT NOT NULL
+);
+CREATE INDEX IF NOT EXISTS idx_archive_at ON archive(archived_at);
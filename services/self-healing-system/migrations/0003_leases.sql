@@ -0,0 +1,9 @@
+-- Advisory leases backing leader election when several daemon replicas
+-- share one database. A row is held by whichever instance last renewed
+-- it before it expired.
+
+CREATE TABLE IF NOT EXISTS leases (
+    name TEXT PRIMARY KEY,
+    holder TEXT NOT NULL,
+    expires_at TEXT NOT NULL
+);
@@ -0,0 +1,2 @@
+-- Human-readable changelog entry generated when a patch is applied.
+ALTER TABLE patches ADD COLUMN changelog TEXT;
@@ -0,0 +1,8 @@
+-- Content-addressed cache of LLM completions, keyed by a hash of the
+-- model and prompt so unchanged issue contexts reuse earlier replies.
+CREATE TABLE IF NOT EXISTS llm_cache (
+    key TEXT PRIMARY KEY,
+    provider TEXT NOT NULL,
+    response TEXT NOT NULL,
+    created_at TEXT NOT NULL
+);
@@ -0,0 +1,20 @@
+CREATE TABLE IF NOT EXISTS test_cases (
+    id TEXT PRIMARY KEY,
+    content_hash TEXT NOT NULL UNIQUE,
+    content TEXT NOT NULL,
+    service TEXT NOT NULL,
+    origin_commit TEXT NOT NULL,
+    issue_id TEXT,
+    minimized INTEGER NOT NULL DEFAULT 0,
+    created_at TEXT NOT NULL,
+    updated_at TEXT NOT NULL
+);
+CREATE INDEX IF NOT EXISTS idx_test_cases_service ON test_cases(service, created_at DESC);
+
+CREATE TABLE IF NOT EXISTS test_case_runs (
+    id TEXT PRIMARY KEY,
+    test_case_id TEXT NOT NULL,
+    still_failing INTEGER NOT NULL,
+    ran_at TEXT NOT NULL
+);
+CREATE INDEX IF NOT EXISTS idx_test_case_runs_case ON test_case_runs(test_case_id, ran_at DESC);
@@ -0,0 +1,3 @@
+-- Line count of the content a test case was first stored with, kept
+-- through minimization so reports can show how much shrinking saved.
+ALTER TABLE test_cases ADD COLUMN original_lines INTEGER NOT NULL DEFAULT 0;
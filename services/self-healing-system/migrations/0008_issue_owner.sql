@@ -0,0 +1,3 @@
+-- Owning team of an issue's affected files, resolved from CODEOWNERS
+-- (or the configured mapping) at ingest time. NULL when nothing matched.
+ALTER TABLE issues ADD COLUMN owner TEXT;
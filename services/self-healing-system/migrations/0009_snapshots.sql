@@ -0,0 +1,16 @@
+-- Workspace snapshots taken before a healing action touches a live
+-- tree. `commit_sha` pins the snapshot tree through a ref under
+-- refs/self-heal/snapshots/, `files` holds the per-file checksums a
+-- restore is verified against.
+CREATE TABLE IF NOT EXISTS snapshots (
+    id TEXT PRIMARY KEY,
+    project TEXT NOT NULL,
+    reason TEXT NOT NULL,
+    patch_id TEXT,
+    head TEXT NOT NULL,
+    commit_sha TEXT NOT NULL,
+    files TEXT NOT NULL,
+    created_at TEXT NOT NULL,
+    restored_at TEXT
+);
+CREATE INDEX IF NOT EXISTS idx_snapshots_project ON snapshots(project, created_at DESC);
@@ -0,0 +1,62 @@
+//! Operator email alerts, delivered through the shared aurum-notify
+//! mailer. Without SMTP configuration every alert degrades to a log
+//! line, so the daemon never depends on a mail server being up.
+
+use crate::config::AlertConfig;
+use aurum_notify::{EmailMessage, Mailer, SmtpConfig};
+use tracing::{info, warn};
+
+pub struct AlertManager {
+    recipients: Vec<String>,
+    mailer: Option<Mailer>,
+}
+
+impl AlertManager {
+    pub fn new(config: Option<&AlertConfig>) -> Self {
+        let Some(config) = config else {
+            return Self {
+                recipients: Vec::new(),
+                mailer: None,
+            };
+        };
+        let smtp = config.smtp.clone().or_else(SmtpConfig::from_env);
+        let mailer = smtp.and_then(|smtp| match Mailer::new(&smtp) {
+            Ok(mailer) => Some(mailer),
+            Err(e) => {
+                warn!("alert emails falling back to logging, bad smtp config: {e:#}");
+                None
+            }
+        });
+        Self {
+            recipients: config
+                .to
+                .split(',')
+                .map(|s| s.trim().to_string())
+                .filter(|s| !s.is_empty())
+                .collect(),
+            mailer,
+        }
+    }
+
+    /// Email the operators; repeats of the same `key` within the batch
+    /// window are folded into the next delivery. Failures are logged,
+    /// never propagated — alerting must not stall the daemon.
+    pub async fn send_email(&self, key: &str, subject: &str, fields: &[(&str, &str)], detail: &str) {
+        let Some(mailer) = &self.mailer else {
+            info!(subject, ?fields, "alert (email not configured)");
+            return;
+        };
+        let message = EmailMessage::render(
+            &format!("[self-healing] {subject}"),
+            subject,
+            fields,
+            detail,
+        );
+        if let Err(e) = mailer
+            .send_deduplicated(&self.recipients, key, &message)
+            .await
+        {
+            warn!(subject, "alert email delivery failed: {e:#}");
+        }
+    }
+}
@@ -0,0 +1,595 @@
+//! REST API over the daemon, so the ops dashboard and build-monitor can
+//! drive it without a shell on the box.
+
+use crate::daemon::SelfHealingDaemon;
+use crate::types::{Issue, IssueStatus, PatchStatus, ReviewVerdict};
+use axum::extract::{Path, Query, State};
+use axum::http::StatusCode;
+use axum::response::IntoResponse;
+use axum::routing::{delete, get, post};
+use axum::{Json, Router};
+use serde::Deserialize;
+use serde_json::json;
+use std::sync::Arc;
+use tower_http::cors::CorsLayer;
+use tracing::info;
+
+pub struct ApiServer {
+    daemon: Arc<SelfHealingDaemon>,
+}
+
+impl ApiServer {
+    pub fn new(daemon: Arc<SelfHealingDaemon>) -> Self {
+        Self { daemon }
+    }
+
+    pub fn router(&self) -> Router {
+        Router::new()
+            .route("/api/analyze", post(analyze))
+            .route("/api/events/ci", post(ci_event))
+            .route("/api/costs", get(costs))
+            .route("/api/llm/cache", delete(purge_llm_cache))
+            .route("/api/status", get(status))
+            .route("/api/issues", get(list_issues).post(create_issue))
+            .route("/api/issues/{id}", get(issue_by_id))
+            .route("/api/issues/{id}/generate", post(generate_patch))
+            .route("/api/issues/{id}/simulate", post(simulate_issue))
+            .route("/api/issues/{id}/tests/generate", post(generate_test))
+            .route("/api/issues/{id}/fuzz/generate", post(generate_fuzz_harness))
+            .route("/api/issues/{id}/fuzz/crashes", post(ingest_fuzz_crashes))
+            .route("/api/issues/{id}/patches", get(issue_patches).post(propose_patch))
+            .route("/api/patches/{id}", get(patch_by_id))
+            .route("/api/patches/{id}/apply", post(apply_patch))
+            .route("/api/patches/{id}/validate", post(validate_patch))
+            .route("/api/patches/{id}/merge", post(merge_patch))
+            .route("/api/patches/{id}/rollback", post(rollback_patch))
+            .route("/api/patches/{id}/review", post(review_patch))
+            .route("/api/tests", get(list_tests))
+            .route("/api/tests/{id}/execute", post(execute_test))
+            .route("/api/tests/{id}/minimize", post(minimize_test))
+            .route("/api/tests/{id}/promote", post(promote_test))
+            .route("/api/snapshots", get(list_snapshots))
+            .route("/api/snapshots/{id}/restore", post(restore_snapshot))
+            .route("/api/reviews/queue", get(review_queue))
+            .route("/api/reviews/stats", get(review_stats))
+            .route("/metrics", get(metrics))
+            .route("/health", get(health))
+            .layer(axum::middleware::from_fn(trace_context))
+            .layer(CorsLayer::permissive())
+            .with_state(self.daemon.clone())
+    }
+
+    pub async fn serve(&self) -> anyhow::Result<()> {
+        let addr = format!(
+            "{}:{}",
+            self.daemon.config.web.bind, self.daemon.config.web.port
+        );
+        let listener = tokio::net::TcpListener::bind(&addr).await?;
+        info!("api server listening on {addr}");
+        axum::serve(listener, self.router()).await?;
+        Ok(())
+    }
+}
+
+/// Run every request inside a span carrying the caller's trace context,
+/// so a build-monitor failure and the patch attempt it triggered show up
+/// as one trace.
+async fn trace_context(
+    request: axum::extract::Request,
+    next: axum::middleware::Next,
+) -> axum::response::Response {
+    use tracing::Instrument;
+    let traceparent = request
+        .headers()
+        .get(aurum_telemetry::TRACEPARENT)
+        .and_then(|v| v.to_str().ok())
+        .map(|v| v.to_string());
+    let span = aurum_telemetry::request_span(
+        request.method().as_str(),
+        request.uri().path(),
+        traceparent.as_deref(),
+    );
+    next.run(request).instrument(span).await
+}
+
+type ApiResult<T> = Result<T, (StatusCode, Json<serde_json::Value>)>;
+
+fn internal_error(e: anyhow::Error) -> (StatusCode, Json<serde_json::Value>) {
+    (
+        StatusCode::INTERNAL_SERVER_ERROR,
+        Json(json!({ "error": format!("{e:#}") })),
+    )
+}
+
+fn not_found(what: &str) -> (StatusCode, Json<serde_json::Value>) {
+    (
+        StatusCode::NOT_FOUND,
+        Json(json!({ "error": format!("{what} not found") })),
+    )
+}
+
+fn unprocessable(e: anyhow::Error) -> (StatusCode, Json<serde_json::Value>) {
+    (
+        StatusCode::UNPROCESSABLE_ENTITY,
+        Json(json!({ "error": format!("{e:#}") })),
+    )
+}
+
+async fn health() -> impl IntoResponse {
+    Json(json!({ "status": "ok" }))
+}
+
+/// Prometheus exposition endpoint.
+async fn metrics(State(daemon): State<Arc<SelfHealingDaemon>>) -> ApiResult<impl IntoResponse> {
+    let body = daemon.metrics.gather().map_err(internal_error)?;
+    Ok((
+        [(axum::http::header::CONTENT_TYPE, "text/plain; version=0.0.4")],
+        body,
+    ))
+}
+
+async fn status(State(daemon): State<Arc<SelfHealingDaemon>>) -> ApiResult<impl IntoResponse> {
+    let status = daemon.status().await.map_err(internal_error)?;
+    Ok(Json(status))
+}
+
+/// Aggregate LLM spend and the state of the daily budget.
+async fn costs(State(daemon): State<Arc<SelfHealingDaemon>>) -> ApiResult<impl IntoResponse> {
+    let report = daemon.cost_report().await.map_err(internal_error)?;
+    Ok(Json(report))
+}
+
+/// Drop every cached LLM completion; the next identical prompt pays for a
+/// fresh one.
+async fn purge_llm_cache(
+    State(daemon): State<Arc<SelfHealingDaemon>>,
+) -> ApiResult<impl IntoResponse> {
+    let invalidated = daemon
+        .database
+        .purge_llm_cache()
+        .await
+        .map_err(internal_error)?;
+    Ok(Json(json!({ "invalidated": invalidated })))
+}
+
+/// Run a compiler-diagnostics pass over the target repository.
+async fn analyze(State(daemon): State<Arc<SelfHealingDaemon>>) -> ApiResult<impl IntoResponse> {
+    let report = daemon.analyze().await.map_err(internal_error)?;
+    Ok(Json(report))
+}
+
+#[derive(Deserialize)]
+struct CiEvent {
+    /// CI conclusion; only failures trigger a run.
+    status: String,
+    #[serde(default)]
+    pipeline: Option<String>,
+}
+
+/// Webhook for CI systems: a failure event queues an analysis run on the
+/// daemon's trigger channel, debounced like filesystem changes.
+async fn ci_event(
+    State(daemon): State<Arc<SelfHealingDaemon>>,
+    Json(event): Json<CiEvent>,
+) -> ApiResult<impl IntoResponse> {
+    let failed = matches!(event.status.as_str(), "failure" | "failed" | "error");
+    if failed {
+        info!(pipeline = ?event.pipeline, "ci failure event received");
+        daemon.trigger("ci");
+    }
+    Ok(Json(json!({ "triggered": failed })))
+}
+
+#[derive(Deserialize)]
+struct IssuesQuery {
+    /// Filter by status ("open", "patched", ...).
+    #[serde(default)]
+    status: Option<String>,
+    /// Filter by configured project id.
+    #[serde(default)]
+    project: Option<String>,
+    #[serde(default = "default_limit")]
+    limit: i64,
+}
+
+fn default_limit() -> i64 {
+    50
+}
+
+async fn list_issues(
+    State(daemon): State<Arc<SelfHealingDaemon>>,
+    Query(query): Query<IssuesQuery>,
+) -> ApiResult<impl IntoResponse> {
+    let status = query.status.as_deref().map(IssueStatus::parse);
+    let issues = daemon
+        .database
+        .issues(status, query.project.as_deref(), query.limit)
+        .await
+        .map_err(internal_error)?;
+    Ok(Json(issues))
+}
+
+/// The shape build-monitor POSTs when filing a failure.
+#[derive(Deserialize)]
+struct NewIssue {
+    #[serde(default = "default_source")]
+    source: String,
+    /// Configured project the failure belongs to; defaults to "default".
+    #[serde(default)]
+    project: Option<String>,
+    service: String,
+    commit: String,
+    classification: String,
+    #[serde(default)]
+    log: String,
+    #[serde(default)]
+    affected_files: Vec<String>,
+    /// Reserved: ask the daemon to start patch generation immediately.
+    #[serde(default)]
+    #[allow(dead_code)]
+    generate_patch: bool,
+}
+
+fn default_source() -> String {
+    "api".to_string()
+}
+
+async fn create_issue(
+    State(daemon): State<Arc<SelfHealingDaemon>>,
+    Json(req): Json<NewIssue>,
+) -> ApiResult<impl IntoResponse> {
+    let mut issue = Issue::new(
+        &req.source,
+        &req.service,
+        &req.commit,
+        &req.classification,
+        &req.log,
+        req.affected_files,
+    );
+    if let Some(project) = req.project {
+        issue.project = project;
+    }
+    let issue = daemon.ingest_issue(issue).await.map_err(internal_error)?;
+    Ok((StatusCode::CREATED, Json(issue)))
+}
+
+async fn issue_by_id(
+    State(daemon): State<Arc<SelfHealingDaemon>>,
+    Path(id): Path<uuid::Uuid>,
+) -> ApiResult<impl IntoResponse> {
+    let issue = daemon
+        .database
+        .issue_by_id(id)
+        .await
+        .map_err(internal_error)?
+        .ok_or_else(|| not_found("issue"))?;
+    Ok(Json(issue))
+}
+
+/// Candidate patches for an issue with their validation results.
+async fn issue_patches(
+    State(daemon): State<Arc<SelfHealingDaemon>>,
+    Path(id): Path<uuid::Uuid>,
+) -> ApiResult<impl IntoResponse> {
+    let patches = daemon
+        .database
+        .patches_for_issue(id)
+        .await
+        .map_err(internal_error)?;
+    Ok(Json(patches))
+}
+
+/// Draft a patch for the issue with the configured LLM provider.
+async fn generate_patch(
+    State(daemon): State<Arc<SelfHealingDaemon>>,
+    Path(id): Path<uuid::Uuid>,
+) -> ApiResult<impl IntoResponse> {
+    let patch = daemon.generate_patch(id).await.map_err(unprocessable)?;
+    Ok((StatusCode::CREATED, Json(patch)))
+}
+
+#[derive(Deserialize)]
+struct SimulateQuery {
+    /// "json" (default) or "markdown".
+    #[serde(default)]
+    format: Option<String>,
+}
+
+/// Run the whole pipeline for the issue without applying anything and
+/// answer with a consolidated report; `?format=markdown` renders it
+/// ready to paste into an incident channel.
+async fn simulate_issue(
+    State(daemon): State<Arc<SelfHealingDaemon>>,
+    Path(id): Path<uuid::Uuid>,
+    Query(query): Query<SimulateQuery>,
+) -> ApiResult<axum::response::Response> {
+    let report = daemon.simulate(id).await.map_err(unprocessable)?;
+    Ok(match query.format.as_deref() {
+        Some("markdown") => report.to_markdown().into_response(),
+        _ => Json(report).into_response(),
+    })
+}
+
+/// Synthesize a reproduction test scaffold for the issue; `test` is null
+/// when the log matches no known failure shape.
+async fn generate_test(
+    State(daemon): State<Arc<SelfHealingDaemon>>,
+    Path(id): Path<uuid::Uuid>,
+) -> ApiResult<impl IntoResponse> {
+    let report = daemon.generate_test(id).await.map_err(unprocessable)?;
+    Ok(Json(json!({ "test": report })))
+}
+
+/// Synthesize a cargo-fuzz harness for the issue's implicated function;
+/// `harness` is null when the failure names no fuzzable function.
+async fn generate_fuzz_harness(
+    State(daemon): State<Arc<SelfHealingDaemon>>,
+    Path(id): Path<uuid::Uuid>,
+) -> ApiResult<impl IntoResponse> {
+    let report = daemon.generate_fuzz_harness(id).await.map_err(unprocessable)?;
+    Ok(Json(json!({ "harness": report })))
+}
+
+/// File the crash artifacts left by a fuzz run of the issue's harness as
+/// new issues; repeated sweeps skip crashes already filed.
+async fn ingest_fuzz_crashes(
+    State(daemon): State<Arc<SelfHealingDaemon>>,
+    Path(id): Path<uuid::Uuid>,
+) -> ApiResult<impl IntoResponse> {
+    let filed = daemon.ingest_fuzz_crashes(id).await.map_err(unprocessable)?;
+    Ok(Json(json!({ "filed": filed })))
+}
+
+#[derive(Deserialize)]
+struct TestsQuery {
+    /// Only test cases reproducing failures of this service.
+    #[serde(default)]
+    service: Option<String>,
+    #[serde(default = "default_limit")]
+    limit: i64,
+}
+
+/// Stored reproduction test cases, newest first.
+async fn list_tests(
+    State(daemon): State<Arc<SelfHealingDaemon>>,
+    Query(query): Query<TestsQuery>,
+) -> ApiResult<impl IntoResponse> {
+    let cases = daemon
+        .database
+        .list_test_cases(query.service.as_deref(), query.limit)
+        .await
+        .map_err(internal_error)?;
+    Ok(Json(cases))
+}
+
+/// The failing-test oracle for execute and minimize: run with `sh -c`,
+/// the candidate content exposed as `$TEST_CASE`, non-zero exit means the
+/// failure still reproduces.
+#[derive(Deserialize)]
+struct TestCommand {
+    command: String,
+}
+
+/// Run a stored test case once and record the outcome in its history.
+async fn execute_test(
+    State(daemon): State<Arc<SelfHealingDaemon>>,
+    Path(id): Path<uuid::Uuid>,
+    Json(body): Json<TestCommand>,
+) -> ApiResult<impl IntoResponse> {
+    let still_failing = daemon
+        .execute_test(id, body.command)
+        .await
+        .map_err(unprocessable)?;
+    Ok(Json(json!({ "id": id, "still_failing": still_failing })))
+}
+
+/// Shrink a stored test case with delta debugging and persist the result.
+async fn minimize_test(
+    State(daemon): State<Arc<SelfHealingDaemon>>,
+    Path(id): Path<uuid::Uuid>,
+    Json(body): Json<TestCommand>,
+) -> ApiResult<impl IntoResponse> {
+    let case = daemon
+        .minimize_test(id, body.command)
+        .await
+        .map_err(unprocessable)?;
+    Ok(Json(case))
+}
+
+/// Commit a minimized test case into the target repository's regression
+/// suite (as a pull request when one is configured).
+async fn promote_test(
+    State(daemon): State<Arc<SelfHealingDaemon>>,
+    Path(id): Path<uuid::Uuid>,
+) -> ApiResult<impl IntoResponse> {
+    let promotion = daemon.promote_test(id).await.map_err(unprocessable)?;
+    Ok((StatusCode::CREATED, Json(promotion)))
+}
+
+#[derive(Deserialize)]
+struct NewPatch {
+    description: String,
+    /// Unified diff against the repository root.
+    diff: String,
+}
+
+/// Attach a manually written candidate patch to an issue.
+async fn propose_patch(
+    State(daemon): State<Arc<SelfHealingDaemon>>,
+    Path(id): Path<uuid::Uuid>,
+    Json(req): Json<NewPatch>,
+) -> ApiResult<impl IntoResponse> {
+    let issue = daemon
+        .database
+        .issue_by_id(id)
+        .await
+        .map_err(internal_error)?
+        .ok_or_else(|| not_found("issue"))?;
+    let breaking = daemon
+        .dry_run_diff(&issue.project, &req.diff)
+        .map_err(unprocessable)?;
+    let patch = crate::types::Patch::new(id, &req.description, &req.diff);
+    daemon
+        .database
+        .record_patch(&patch)
+        .await
+        .map_err(internal_error)?;
+    daemon.metrics.observe_patch(patch.status.as_str());
+    Ok((
+        StatusCode::CREATED,
+        Json(json!({ "patch": patch, "breaking_changes": breaking })),
+    ))
+}
+
+async fn patch_by_id(
+    State(daemon): State<Arc<SelfHealingDaemon>>,
+    Path(id): Path<uuid::Uuid>,
+) -> ApiResult<impl IntoResponse> {
+    let patch = daemon
+        .database
+        .patch_by_id(id)
+        .await
+        .map_err(internal_error)?
+        .ok_or_else(|| not_found("patch"))?;
+    Ok(Json(patch))
+}
+
+#[derive(Deserialize)]
+struct ApplyOptions {
+    /// Report what applying would do without doing any of it.
+    #[serde(default)]
+    plan: bool,
+}
+
+async fn apply_patch(
+    State(daemon): State<Arc<SelfHealingDaemon>>,
+    Path(id): Path<uuid::Uuid>,
+    options: Option<Json<ApplyOptions>>,
+) -> ApiResult<axum::response::Response> {
+    if options.is_some_and(|Json(options)| options.plan) {
+        let plan = daemon.plan_apply(id).await.map_err(unprocessable)?;
+        return Ok(Json(plan).into_response());
+    }
+    let patch = daemon.apply_patch(id).await.map_err(internal_error)?;
+    Ok(Json(patch).into_response())
+}
+
+async fn validate_patch(
+    State(daemon): State<Arc<SelfHealingDaemon>>,
+    Path(id): Path<uuid::Uuid>,
+) -> ApiResult<impl IntoResponse> {
+    let patch = daemon.validate_patch(id).await.map_err(internal_error)?;
+    Ok(Json(patch))
+}
+
+/// Merge the patch's self-heal review branch back into the base branch;
+/// a conflicted merge answers with the conflicting files instead of
+/// moving anything.
+async fn merge_patch(
+    State(daemon): State<Arc<SelfHealingDaemon>>,
+    Path(id): Path<uuid::Uuid>,
+) -> ApiResult<impl IntoResponse> {
+    let outcome = daemon.merge_patch_branch(id).await.map_err(unprocessable)?;
+    Ok(Json(outcome))
+}
+
+async fn rollback_patch(
+    State(daemon): State<Arc<SelfHealingDaemon>>,
+    Path(id): Path<uuid::Uuid>,
+) -> ApiResult<impl IntoResponse> {
+    let patch = daemon.rollback_patch(id).await.map_err(internal_error)?;
+    Ok(Json(patch))
+}
+
+#[derive(Deserialize)]
+struct NewReview {
+    reviewer: String,
+    verdict: ReviewVerdict,
+    #[serde(default)]
+    comment: Option<String>,
+}
+
+/// Record an approve/reject verdict on a patch awaiting review.
+async fn review_patch(
+    State(daemon): State<Arc<SelfHealingDaemon>>,
+    Path(id): Path<uuid::Uuid>,
+    Json(body): Json<NewReview>,
+) -> ApiResult<impl IntoResponse> {
+    let (review, patch) = daemon
+        .review_patch(id, &body.reviewer, body.verdict, body.comment)
+        .await
+        .map_err(unprocessable)?;
+    Ok((
+        StatusCode::CREATED,
+        Json(json!({ "review": review, "patch": patch })),
+    ))
+}
+
+/// Patches awaiting human review, each with the safety analysis a
+/// reviewer needs next to the diff.
+#[derive(Deserialize)]
+struct SnapshotsQuery {
+    #[serde(default)]
+    project: Option<String>,
+    #[serde(default = "default_limit")]
+    limit: i64,
+}
+
+async fn list_snapshots(
+    State(daemon): State<Arc<SelfHealingDaemon>>,
+    Query(query): Query<SnapshotsQuery>,
+) -> ApiResult<impl IntoResponse> {
+    let snapshots = daemon
+        .database
+        .recent_snapshots(query.project.as_deref(), query.limit)
+        .await
+        .map_err(internal_error)?;
+    Ok(Json(snapshots))
+}
+
+/// Put the project's working tree back to the snapshotted content; the
+/// restore only reports success after checksum verification.
+async fn restore_snapshot(
+    State(daemon): State<Arc<SelfHealingDaemon>>,
+    Path(id): Path<uuid::Uuid>,
+) -> ApiResult<impl IntoResponse> {
+    let snapshot = daemon.restore_snapshot(id).await.map_err(unprocessable)?;
+    Ok(Json(snapshot))
+}
+
+async fn review_queue(
+    State(daemon): State<Arc<SelfHealingDaemon>>,
+) -> ApiResult<impl IntoResponse> {
+    let patches = daemon
+        .database
+        .patches_by_status(PatchStatus::PendingReview, default_limit())
+        .await
+        .map_err(internal_error)?;
+    let mut queue = Vec::with_capacity(patches.len());
+    for patch in patches {
+        let project = daemon
+            .database
+            .issue_by_id(patch.issue_id)
+            .await
+            .ok()
+            .flatten()
+            .map(|issue| issue.project)
+            .unwrap_or_else(|| "default".to_string());
+        let breaking = daemon.dry_run_diff(&project, &patch.diff).unwrap_or_default();
+        let risk = crate::review::assess(&patch.diff, &breaking, patch.validation.as_ref());
+        queue.push(json!({
+            "patch": patch,
+            "breaking_changes": breaking,
+            "risk": risk,
+        }));
+    }
+    Ok(Json(json!({ "queue": queue })))
+}
+
+/// Approval rates per classification, for calibrating confidence.
+async fn review_stats(
+    State(daemon): State<Arc<SelfHealingDaemon>>,
+) -> ApiResult<impl IntoResponse> {
+    let stats = daemon.database.review_stats().await.map_err(internal_error)?;
+    Ok(Json(json!({ "stats": stats })))
+}
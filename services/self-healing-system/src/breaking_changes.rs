@@ -0,0 +1,323 @@
+//! AST-aware breaking-change detection for Rust sources.
+//!
+//! Compares the public surface of a file before and after a patch using
+//! `syn` rather than text scanning: removed items, changed fn signatures,
+//! removed or retyped struct fields, removed enum variants, new required
+//! trait methods, and visibility reductions all produce structured
+//! [`BreakingChange`] records with the line they occur on.
+
+use anyhow::{Context, Result};
+use quote::ToTokens;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use syn::{Fields, Item, Visibility};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BreakingChangeKind {
+    ItemRemoved,
+    SignatureChanged,
+    FieldRemoved,
+    FieldTypeChanged,
+    VariantRemoved,
+    TraitMethodRemoved,
+    TraitMethodAdded,
+    VisibilityReduced,
+}
+
+/// One detected incompatibility. `file` is filled in by the caller, which
+/// knows which file of a multi-file diff the sources came from.
+#[derive(Debug, Clone, Serialize)]
+pub struct BreakingChange {
+    pub kind: BreakingChangeKind,
+    /// The affected item, e.g. `fn parse` or `struct Config.timeout`.
+    pub item: String,
+    /// 1-based line in the pre-patch file (post-patch for added methods).
+    pub line: usize,
+    pub detail: String,
+    #[serde(default)]
+    pub file: String,
+}
+
+/// An item's public signature as collected from one side of the diff.
+#[derive(Debug)]
+struct ApiItem {
+    is_pub: bool,
+    line: usize,
+    /// Rendered signature for fns and trait methods; empty otherwise.
+    signature: String,
+    /// Fields for structs, variants for enums, methods for traits.
+    members: BTreeMap<String, Member>,
+}
+
+#[derive(Debug)]
+struct Member {
+    line: usize,
+    /// Field type, or method signature for traits.
+    signature: String,
+    /// Trait methods without a default body are required.
+    required: bool,
+}
+
+/// Compare the public APIs of two versions of a Rust source file.
+pub fn identify_breaking_changes(before: &str, after: &str) -> Result<Vec<BreakingChange>> {
+    let old = collect(before).context("failed to parse pre-patch source")?;
+    let new = collect(after).context("failed to parse post-patch source")?;
+    let mut changes = Vec::new();
+    for (name, old_item) in &old {
+        if !old_item.is_pub {
+            continue;
+        }
+        let Some(new_item) = new.get(name) else {
+            push(&mut changes, BreakingChangeKind::ItemRemoved, name, old_item.line, format!("public {name} was removed"));
+            continue;
+        };
+        if !new_item.is_pub {
+            push(&mut changes, BreakingChangeKind::VisibilityReduced, name, old_item.line, format!("{name} is no longer public"));
+            continue;
+        }
+        if !old_item.signature.is_empty() && old_item.signature != new_item.signature {
+            push(
+                &mut changes,
+                BreakingChangeKind::SignatureChanged,
+                name,
+                old_item.line,
+                format!("`{}` became `{}`", old_item.signature, new_item.signature),
+            );
+        }
+        compare_members(&mut changes, name, old_item, new_item);
+    }
+    Ok(changes)
+}
+
+fn compare_members(changes: &mut Vec<BreakingChange>, name: &str, old: &ApiItem, new: &ApiItem) {
+    let is_trait = name.starts_with("trait ");
+    for (member, old_member) in &old.members {
+        let qualified = format!("{name}.{member}");
+        match new.members.get(member) {
+            None => {
+                let kind = match (is_trait, name.starts_with("enum ")) {
+                    (true, _) => BreakingChangeKind::TraitMethodRemoved,
+                    (_, true) => BreakingChangeKind::VariantRemoved,
+                    _ => BreakingChangeKind::FieldRemoved,
+                };
+                push(changes, kind, &qualified, old_member.line, format!("{qualified} was removed"));
+            }
+            Some(new_member) if old_member.signature != new_member.signature => {
+                let kind = if is_trait {
+                    BreakingChangeKind::SignatureChanged
+                } else {
+                    BreakingChangeKind::FieldTypeChanged
+                };
+                push(
+                    changes,
+                    kind,
+                    &qualified,
+                    old_member.line,
+                    format!("`{}` became `{}`", old_member.signature, new_member.signature),
+                );
+            }
+            Some(_) => {}
+        }
+    }
+    // A new required method breaks every downstream implementor.
+    if is_trait {
+        for (member, new_member) in &new.members {
+            if new_member.required && !old.members.contains_key(member) {
+                let qualified = format!("{name}.{member}");
+                push(
+                    changes,
+                    BreakingChangeKind::TraitMethodAdded,
+                    &qualified,
+                    new_member.line,
+                    format!("{qualified} is a new required method"),
+                );
+            }
+        }
+    }
+}
+
+fn push(changes: &mut Vec<BreakingChange>, kind: BreakingChangeKind, item: &str, line: usize, detail: String) {
+    changes.push(BreakingChange {
+        kind,
+        item: item.to_string(),
+        line,
+        detail,
+        file: String::new(),
+    });
+}
+
+/// Walk a file (including inline modules) and index its named items.
+fn collect(source: &str) -> Result<BTreeMap<String, ApiItem>> {
+    let file = syn::parse_file(source)?;
+    let mut items = BTreeMap::new();
+    collect_items(&file.items, "", &mut items);
+    Ok(items)
+}
+
+fn collect_items(source: &[Item], prefix: &str, out: &mut BTreeMap<String, ApiItem>) {
+    for item in source {
+        match item {
+            Item::Fn(f) => {
+                out.insert(
+                    format!("fn {prefix}{}", f.sig.ident),
+                    ApiItem {
+                        is_pub: is_pub(&f.vis),
+                        line: f.sig.ident.span().start().line,
+                        signature: render(&f.sig),
+                        members: BTreeMap::new(),
+                    },
+                );
+            }
+            Item::Struct(s) => {
+                let mut members = BTreeMap::new();
+                if let Fields::Named(fields) = &s.fields {
+                    for field in &fields.named {
+                        if !is_pub(&field.vis) {
+                            continue;
+                        }
+                        let ident = field.ident.as_ref().expect("named field");
+                        members.insert(
+                            ident.to_string(),
+                            Member {
+                                line: ident.span().start().line,
+                                signature: render(&field.ty),
+                                required: false,
+                            },
+                        );
+                    }
+                }
+                out.insert(
+                    format!("struct {prefix}{}", s.ident),
+                    ApiItem {
+                        is_pub: is_pub(&s.vis),
+                        line: s.ident.span().start().line,
+                        signature: String::new(),
+                        members,
+                    },
+                );
+            }
+            Item::Enum(e) => {
+                let members = e
+                    .variants
+                    .iter()
+                    .map(|v| {
+                        (
+                            v.ident.to_string(),
+                            Member {
+                                line: v.ident.span().start().line,
+                                signature: render(&v.fields),
+                                required: false,
+                            },
+                        )
+                    })
+                    .collect();
+                out.insert(
+                    format!("enum {prefix}{}", e.ident),
+                    ApiItem {
+                        is_pub: is_pub(&e.vis),
+                        line: e.ident.span().start().line,
+                        signature: String::new(),
+                        members,
+                    },
+                );
+            }
+            Item::Trait(t) => {
+                let members = t
+                    .items
+                    .iter()
+                    .filter_map(|i| match i {
+                        syn::TraitItem::Fn(m) => Some((
+                            m.sig.ident.to_string(),
+                            Member {
+                                line: m.sig.ident.span().start().line,
+                                signature: render(&m.sig),
+                                required: m.default.is_none(),
+                            },
+                        )),
+                        _ => None,
+                    })
+                    .collect();
+                out.insert(
+                    format!("trait {prefix}{}", t.ident),
+                    ApiItem {
+                        is_pub: is_pub(&t.vis),
+                        line: t.ident.span().start().line,
+                        signature: String::new(),
+                        members,
+                    },
+                );
+            }
+            Item::Mod(m) => {
+                if let Some((_, nested)) = &m.content {
+                    collect_items(nested, &format!("{prefix}{}::", m.ident), out);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+fn is_pub(vis: &Visibility) -> bool {
+    matches!(vis, Visibility::Public(_))
+}
+
+/// Token-level rendering; whitespace-insensitive and trailing-comma
+/// insensitive, so formatting-only edits never register as changes.
+fn render<T: ToTokens>(t: &T) -> String {
+    t.to_token_stream()
+        .to_string()
+        .replace(" ,)", ")")
+        .replace(" ,]", "]")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn detects_removed_fn_and_changed_signature() {
+        let before = "pub fn parse(s: &str) -> u32 { 0 }\npub fn gone() {}\nfn private() {}\n";
+        let after = "pub fn parse(s: &str, strict: bool) -> u32 { 0 }\n";
+        let changes = identify_breaking_changes(before, after).unwrap();
+        assert_eq!(changes.len(), 2);
+        let sig = changes.iter().find(|c| c.item == "fn parse").unwrap();
+        assert_eq!(sig.kind, BreakingChangeKind::SignatureChanged);
+        assert_eq!(sig.line, 1);
+        let removed = changes.iter().find(|c| c.item == "fn gone").unwrap();
+        assert_eq!(removed.kind, BreakingChangeKind::ItemRemoved);
+        assert_eq!(removed.line, 2);
+    }
+
+    #[test]
+    fn detects_struct_field_and_visibility_changes() {
+        let before = "pub struct Config {\n    pub timeout: u64,\n    pub retries: u32,\n    internal: bool,\n}\npub struct Exposed;\n";
+        let after = "pub struct Config {\n    pub timeout: String,\n}\nstruct Exposed;\n";
+        let changes = identify_breaking_changes(before, after).unwrap();
+        let kinds: Vec<_> = changes.iter().map(|c| (c.kind, c.item.as_str())).collect();
+        assert!(kinds.contains(&(BreakingChangeKind::FieldTypeChanged, "struct Config.timeout")));
+        assert!(kinds.contains(&(BreakingChangeKind::FieldRemoved, "struct Config.retries")));
+        assert!(kinds.contains(&(BreakingChangeKind::VisibilityReduced, "struct Exposed")));
+        // The private field was never API surface.
+        assert!(!kinds.iter().any(|(_, item)| item.contains("internal")));
+    }
+
+    #[test]
+    fn detects_enum_and_trait_changes() {
+        let before = "pub enum State { Idle, Busy }\npub trait Store {\n    fn get(&self, k: &str) -> String;\n}\n";
+        let after = "pub enum State { Idle }\npub trait Store {\n    fn get(&self, k: &str) -> String;\n    fn put(&self, k: &str, v: String);\n    fn len(&self) -> usize { 0 }\n}\n";
+        let changes = identify_breaking_changes(before, after).unwrap();
+        let kinds: Vec<_> = changes.iter().map(|c| (c.kind, c.item.as_str())).collect();
+        assert!(kinds.contains(&(BreakingChangeKind::VariantRemoved, "enum State.Busy")));
+        // `put` is required and new; `len` has a default body and is fine.
+        assert!(kinds.contains(&(BreakingChangeKind::TraitMethodAdded, "trait Store.put")));
+        assert!(!kinds.iter().any(|(_, item)| item.ends_with(".len")));
+    }
+
+    #[test]
+    fn formatting_only_edits_are_not_breaking() {
+        let before = "pub fn add(a: u32, b: u32) -> u32 { a + b }\n";
+        let after = "pub fn add(\n    a: u32,\n    b: u32,\n) -> u32 {\n    a + b\n}\n";
+        assert!(identify_breaking_changes(before, after).unwrap().is_empty());
+    }
+}
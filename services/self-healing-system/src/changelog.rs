@@ -0,0 +1,182 @@
+//! Human-readable changelog entries for applied patches.
+//!
+//! After a patch lands, the daemon asks the LLM to explain the failure and
+//! the fix in prose, wraps the explanation in a markdown entry with the
+//! issue summary and validation evidence, and stores the entry on the
+//! patch. The entry can additionally be appended to a changelog file in
+//! the repository or announced over the alert email channel.
+
+use crate::llm_integration::LlmClient;
+use crate::types::{Issue, Patch};
+use anyhow::{Context, Result};
+use std::path::Path;
+use tracing::warn;
+
+const SYSTEM: &str = "You write changelog entries for automatically applied fixes. \
+                      Reply with two short markdown paragraphs: the root cause of the \
+                      failure, then what the fix changes. No headings, no code fences, \
+                      no speculation beyond the provided material.";
+
+/// Keep the prompt well under any provider's context window; the log tail
+/// and the diff head carry the relevant detail.
+const LOG_BUDGET: usize = 4_000;
+const DIFF_BUDGET: usize = 8_000;
+
+/// Explain an issue and its fix in prose. Falls back to the patch
+/// description when no LLM is configured or the request fails, so an entry
+/// always exists.
+pub async fn explain_issue(llm: Option<&LlmClient>, issue: &Issue, patch: &Patch) -> String {
+    let fallback = || {
+        format!(
+            "Automatically fixed a `{}` failure: {}",
+            issue.classification, patch.description
+        )
+    };
+    let Some(llm) = llm else {
+        return fallback();
+    };
+    let prompt = format!(
+        "A {} failure in service {} at commit {} was fixed automatically.\n\n\
+         Failure log:\n{}\n\nApplied diff:\n{}\n\n\
+         Explain the root cause and what the fix changes.",
+        issue.classification,
+        issue.service,
+        issue.commit,
+        tail(&issue.log, LOG_BUDGET),
+        head(&patch.diff, DIFF_BUDGET),
+    );
+    match llm.complete(SYSTEM, &prompt).await {
+        Ok(completion) => completion.text.trim().to_string(),
+        Err(e) => {
+            warn!(patch = %patch.id, "changelog explanation failed: {e:#}");
+            fallback()
+        }
+    }
+}
+
+/// Render the markdown entry around an explanation.
+pub fn render_entry(issue: &Issue, patch: &Patch, explanation: &str) -> String {
+    let mut entry = format!(
+        "## {} — {}: {} fix (patch {})\n\n",
+        patch.updated_at.format("%Y-%m-%d"),
+        issue.service,
+        issue.classification,
+        &patch.id.to_string()[..8]
+    );
+    entry.push_str(&format!(
+        "**Issue {}:** `{}` failure at commit `{}`, reported by {}.\n\n",
+        issue.id, issue.classification, issue.commit, issue.source
+    ));
+    entry.push_str(explanation.trim());
+    entry.push_str("\n\n");
+    match &patch.validation {
+        Some(validation) => entry.push_str(&format!(
+            "**Validation:** build {} in {:.1}s, tests {} in {:.1}s.\n",
+            outcome(validation.build_ok),
+            validation.build_time_ms as f64 / 1000.0,
+            outcome(validation.tests_ok),
+            validation.test_time_ms as f64 / 1000.0,
+        )),
+        None => entry.push_str("**Validation:** applied without a recorded validation run.\n"),
+    }
+    entry
+}
+
+fn outcome(ok: bool) -> &'static str {
+    if ok {
+        "passed"
+    } else {
+        "failed"
+    }
+}
+
+/// Append an entry to the changelog file, creating it with a header the
+/// first time.
+pub fn append(path: &Path, entry: &str) -> Result<()> {
+    let mut content = match std::fs::read_to_string(path) {
+        Ok(existing) => existing,
+        Err(_) => "# Changelog\n".to_string(),
+    };
+    if !content.ends_with('\n') {
+        content.push('\n');
+    }
+    content.push('\n');
+    content.push_str(entry);
+    std::fs::write(path, content).with_context(|| format!("failed to write {}", path.display()))
+}
+
+fn head(text: &str, budget: usize) -> &str {
+    let mut cut = budget.min(text.len());
+    while cut < text.len() && !text.is_char_boundary(cut) {
+        cut += 1;
+    }
+    &text[..cut]
+}
+
+fn tail(text: &str, budget: usize) -> &str {
+    let mut cut = text.len().saturating_sub(budget);
+    while cut > 0 && !text.is_char_boundary(cut) {
+        cut -= 1;
+    }
+    &text[cut..]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::ValidationResult;
+
+    fn fixtures() -> (Issue, Patch) {
+        let issue = Issue::new(
+            "build-monitor",
+            "api-gateway",
+            "abc1234",
+            "compiler",
+            "error[E0308]: mismatched types",
+            vec!["src/lib.rs".to_string()],
+        );
+        let mut patch = Patch::new(issue.id, "fix the type mismatch", "--- a/src/lib.rs");
+        patch.validation = Some(ValidationResult {
+            passed: true,
+            build_ok: true,
+            tests_ok: true,
+            build_time_ms: 12_300,
+            test_time_ms: 4_500,
+            security_issues_found: 0,
+            security_issues: Vec::new(),
+            detail: None,
+        });
+        (issue, patch)
+    }
+
+    #[tokio::test]
+    async fn explanation_falls_back_without_an_llm() {
+        let (issue, patch) = fixtures();
+        let explanation = explain_issue(None, &issue, &patch).await;
+        assert!(explanation.contains("compiler"));
+        assert!(explanation.contains("fix the type mismatch"));
+    }
+
+    #[test]
+    fn entry_carries_issue_summary_and_validation_evidence() {
+        let (issue, patch) = fixtures();
+        let entry = render_entry(&issue, &patch, "The cause.\n\nThe fix.");
+        assert!(entry.contains("api-gateway: compiler fix"));
+        assert!(entry.contains(&format!("**Issue {}:**", issue.id)));
+        assert!(entry.contains("The cause."));
+        assert!(entry.contains("build passed in 12.3s, tests passed in 4.5s"));
+    }
+
+    #[test]
+    fn append_creates_the_file_then_accumulates_entries() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("CHANGELOG.md");
+        append(&path, "## first\n").unwrap();
+        append(&path, "## second\n").unwrap();
+        let content = std::fs::read_to_string(&path).unwrap();
+        assert!(content.starts_with("# Changelog\n"));
+        let first = content.find("## first").unwrap();
+        let second = content.find("## second").unwrap();
+        assert!(first < second);
+    }
+}
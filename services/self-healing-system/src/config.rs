@@ -0,0 +1,598 @@
+//! Daemon configuration, loaded through the shared layered loader:
+//! defaults, then the JSON file, then `SELF_HEALING__*` environment
+//! variables, then `--set` overrides.
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HealingConfig {
+    /// Path to the git repository patches are applied in. With no
+    /// `projects` configured, this acts as a single project named
+    /// "default".
+    pub repo_path: PathBuf,
+    /// Target projects the daemon heals; issues and patches are tagged
+    /// with the project id throughout the database and API.
+    #[serde(default)]
+    pub projects: Vec<ProjectConfig>,
+    /// Path of the SQLite database holding issues and patches.
+    #[serde(default = "default_database_path")]
+    pub database_path: PathBuf,
+    /// Full connection URL overriding `database_path`: "sqlite://..." or,
+    /// with the `postgres` feature, "postgres://...".
+    #[serde(default)]
+    pub database_url: Option<String>,
+    /// Poll interval in seconds for the daemon loop.
+    #[serde(default = "default_poll_interval")]
+    pub poll_interval_secs: u64,
+    /// Directory of prompt template overrides; see the prompts module.
+    #[serde(default)]
+    pub prompt_dir: Option<PathBuf>,
+    /// How candidate patches are validated before they can be applied.
+    #[serde(default)]
+    pub validation: ValidationConfig,
+    /// Paths and debounce for `--watch` mode.
+    #[serde(default)]
+    pub watch: WatchConfig,
+    /// Risk threshold routing patches into the human review queue.
+    #[serde(default)]
+    pub review: ReviewConfig,
+    /// Path of the auto-apply policy file; see the policy module. Absent
+    /// means the built-in policy (the risk threshold plus per-project
+    /// `auto_apply`) applies.
+    #[serde(default)]
+    pub policy_file: Option<PathBuf>,
+    /// Automatic patch generation for open issues from the daemon loop,
+    /// worked in priority order; see the scheduler module.
+    #[serde(default)]
+    pub scheduler: SchedulerConfig,
+    /// Path-to-owner rules overriding the repository's CODEOWNERS file;
+    /// see the ownership module. Empty means CODEOWNERS is used when the
+    /// repository has one.
+    #[serde(default)]
+    pub owners: Vec<crate::ownership::OwnershipRule>,
+    /// How long finished issues and patches are kept before archival.
+    #[serde(default)]
+    pub retention: RetentionConfig,
+    /// Leader election between replicas sharing one database.
+    #[serde(default)]
+    pub election: ElectionConfig,
+    /// When set, commits the daemon makes (applied patches, changelog
+    /// entries, review branches) are pushed to the remote with these
+    /// credentials; absent means they stay local.
+    #[serde(default)]
+    pub push: Option<PushConfig>,
+    /// When set, applying a patch opens a pull request from a
+    /// `self-heal/...` branch instead of committing to the working branch.
+    #[serde(default)]
+    pub pull_request: Option<PullRequestConfig>,
+    #[serde(default)]
+    pub web: WebConfig,
+    /// Email alerts for events that need an operator; absent means alerts
+    /// are only logged.
+    #[serde(default)]
+    pub alerts: Option<AlertConfig>,
+    /// Where generated changelog entries go beyond the patch record;
+    /// absent means they are only stored in the database.
+    #[serde(default)]
+    pub changelog: Option<ChangelogConfig>,
+    /// LLM provider used to draft patches; absent means generation is off.
+    #[serde(default)]
+    pub llm: Option<LlmConfig>,
+}
+
+impl HealingConfig {
+    /// Load configuration from `path`, layered under the environment
+    /// and `--set key.path=value` overrides, with defaults pointed at
+    /// the current directory underneath.
+    pub fn load_with(path: &Path, overrides: &[String]) -> Result<Self> {
+        let mut loader = aurum_config::Loader::new("SELF_HEALING")
+            .defaults(&Self::default_config())?
+            .file(path);
+        for assignment in overrides {
+            loader = loader.set(assignment)?;
+        }
+        Ok(loader.load()?)
+    }
+
+    /// The configuration a fresh machine starts with: defaults pointed
+    /// at the current directory.
+    fn default_config() -> Self {
+        Self {
+            repo_path: PathBuf::from("."),
+            projects: Vec::new(),
+            database_path: default_database_path(),
+            database_url: None,
+            poll_interval_secs: default_poll_interval(),
+            prompt_dir: None,
+            validation: ValidationConfig::default(),
+            watch: WatchConfig::default(),
+            review: ReviewConfig::default(),
+            policy_file: None,
+            scheduler: SchedulerConfig::default(),
+            owners: Vec::new(),
+            retention: RetentionConfig::default(),
+            election: ElectionConfig::default(),
+            push: None,
+            pull_request: None,
+            web: WebConfig::default(),
+            alerts: None,
+            changelog: None,
+            llm: None,
+        }
+    }
+
+    /// The configured projects, or `repo_path` as a lone "default"
+    /// project when none are.
+    pub fn project_list(&self) -> Vec<ProjectConfig> {
+        if self.projects.is_empty() {
+            vec![self.default_project()]
+        } else {
+            self.projects.clone()
+        }
+    }
+
+    /// Look a project up by id; unknown ids resolve to the default
+    /// project so pre-multi-project rows keep working.
+    pub fn project(&self, id: &str) -> ProjectConfig {
+        self.projects
+            .iter()
+            .find(|p| p.id == id)
+            .cloned()
+            .unwrap_or_else(|| self.default_project())
+    }
+
+    fn default_project(&self) -> ProjectConfig {
+        ProjectConfig {
+            id: "default".to_string(),
+            path: self.repo_path.clone(),
+            language: default_language(),
+            branch: None,
+            build_command: None,
+            daily_budget_usd: None,
+            auto_apply: false,
+        }
+    }
+}
+
+/// One repository the daemon looks after.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProjectConfig {
+    /// Identifier issues and patches are tagged with.
+    pub id: String,
+    pub path: PathBuf,
+    /// "rust" projects get the full analysis and audit passes; other
+    /// languages rely on the configured build command alone.
+    #[serde(default = "default_language")]
+    pub language: String,
+    /// Branch the project is healed on, for operator reference.
+    #[serde(default)]
+    pub branch: Option<String>,
+    /// Build command overriding `cargo build --workspace`, run through
+    /// `sh -c` in the project root.
+    #[serde(default)]
+    pub build_command: Option<String>,
+    /// Per-project LLM spend cap, overriding the provider-level one.
+    #[serde(default)]
+    pub daily_budget_usd: Option<f64>,
+    /// Apply patches automatically once they validate below the risk
+    /// threshold instead of waiting for an operator.
+    #[serde(default)]
+    pub auto_apply: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WatchConfig {
+    /// Paths (relative to the repository) watched for changes in
+    /// `--watch` mode.
+    #[serde(default = "default_watch_paths")]
+    pub paths: Vec<PathBuf>,
+    /// Quiet period after a trigger before analysis starts, so a burst of
+    /// saves produces one run.
+    #[serde(default = "default_debounce_ms")]
+    pub debounce_ms: u64,
+}
+
+impl Default for WatchConfig {
+    fn default() -> Self {
+        Self {
+            paths: default_watch_paths(),
+            debounce_ms: default_debounce_ms(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReviewConfig {
+    /// Risk score at which a validated patch is held for human review
+    /// instead of becoming applicable; see the review module for how the
+    /// score is built.
+    #[serde(default = "default_risk_threshold")]
+    pub risk_threshold: u32,
+}
+
+impl Default for ReviewConfig {
+    fn default() -> Self {
+        Self {
+            risk_threshold: default_risk_threshold(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SchedulerConfig {
+    /// Generate patches for open issues on every poll, highest priority
+    /// first. Off by default: each pass can spend LLM budget, so patches
+    /// are otherwise only generated on demand through the API.
+    #[serde(default)]
+    pub enabled: bool,
+    /// Issues patched concurrently within one pass.
+    #[serde(default = "default_scheduler_concurrency")]
+    pub max_concurrent: usize,
+    /// Most issues worked in one pass; the rest wait for the next poll,
+    /// where they are ranked again.
+    #[serde(default = "default_scheduler_batch")]
+    pub batch_size: usize,
+}
+
+impl Default for SchedulerConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            max_concurrent: default_scheduler_concurrency(),
+            batch_size: default_scheduler_batch(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RetentionConfig {
+    /// Days a resolved issue (with its patches and reviews) stays live
+    /// before the sweep archives and deletes it. 0 keeps it forever.
+    #[serde(default = "default_resolved_days")]
+    pub resolved_days: u64,
+    /// Same for issues where patch generation gave up.
+    #[serde(default = "default_failed_days")]
+    pub failed_days: u64,
+    /// Days a rejected or rolled-back patch stays attached to a live
+    /// issue before it is archived on its own. 0 keeps it forever.
+    #[serde(default = "default_rejected_patch_days")]
+    pub rejected_patch_days: u64,
+    /// How often the daemon runs the retention sweep.
+    #[serde(default = "default_sweep_interval")]
+    pub sweep_interval_secs: u64,
+}
+
+impl Default for RetentionConfig {
+    fn default() -> Self {
+        Self {
+            resolved_days: default_resolved_days(),
+            failed_days: default_failed_days(),
+            rejected_patch_days: default_rejected_patch_days(),
+            sweep_interval_secs: default_sweep_interval(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ElectionConfig {
+    /// With several replicas sharing one database, only the lease holder
+    /// performs mutating actions. Disable for single-instance
+    /// deployments to skip the lease traffic.
+    #[serde(default = "default_election_enabled")]
+    pub enabled: bool,
+    /// Lease TTL; a dead leader is replaced after at most this long.
+    #[serde(default = "default_lease_ttl")]
+    pub lease_ttl_secs: u64,
+}
+
+impl Default for ElectionConfig {
+    fn default() -> Self {
+        Self {
+            enabled: default_election_enabled(),
+            lease_ttl_secs: default_lease_ttl(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PushConfig {
+    /// Remote daemon-made commits are pushed to.
+    #[serde(default = "default_remote")]
+    pub remote: String,
+    /// Environment variable holding the token for https remotes, never
+    /// the token itself. Absent means git's ambient credentials apply.
+    #[serde(default)]
+    pub token_env: Option<String>,
+    /// Private key used for ssh remotes; absent means the ambient ssh
+    /// configuration applies.
+    #[serde(default)]
+    pub ssh_key_path: Option<PathBuf>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PullRequestConfig {
+    /// Forge the pull request is opened on: "github" or "gitlab".
+    #[serde(default = "default_forge")]
+    pub forge: String,
+    /// Repository the PR targets: `owner/name` on GitHub, the full
+    /// project path on GitLab.
+    pub repo: String,
+    /// Remote the review branch is pushed to.
+    #[serde(default = "default_remote")]
+    pub remote: String,
+    /// Branch the pull request targets.
+    #[serde(default = "default_base_branch")]
+    pub base_branch: String,
+    /// Environment variable holding the forge API token, never the token
+    /// itself.
+    #[serde(default = "default_pr_token_env")]
+    pub token_env: String,
+    /// API base override for self-hosted forges.
+    #[serde(default)]
+    pub api_base: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ValidationConfig {
+    /// Validate inside a Docker container with no network and resource
+    /// caps. Patched code is untrusted; only disable this on hosts that
+    /// cannot run Docker, where validation falls back to plain cargo.
+    #[serde(default = "default_sandbox")]
+    pub sandbox: bool,
+    /// Image the validation container runs; must have a Rust toolchain.
+    #[serde(default = "default_sandbox_image")]
+    pub image: String,
+    /// CPU cap for the validation container.
+    #[serde(default = "default_sandbox_cpus")]
+    pub cpus: f64,
+    /// Memory cap for the validation container, in Docker syntax.
+    #[serde(default = "default_sandbox_memory")]
+    pub memory: String,
+    /// Wall-clock limit per cargo invocation during validation.
+    #[serde(default = "default_validation_timeout")]
+    pub timeout_secs: u64,
+    /// Semgrep ruleset passed to `--config` during the security scan.
+    #[serde(default = "default_semgrep_rules")]
+    pub semgrep_rules: String,
+    /// Severity at or above which scanner findings fail validation:
+    /// "low", "medium", or "high".
+    #[serde(default = "default_fail_on_severity")]
+    pub fail_on_severity: String,
+}
+
+impl Default for ValidationConfig {
+    fn default() -> Self {
+        Self {
+            sandbox: default_sandbox(),
+            image: default_sandbox_image(),
+            cpus: default_sandbox_cpus(),
+            memory: default_sandbox_memory(),
+            timeout_secs: default_validation_timeout(),
+            semgrep_rules: default_semgrep_rules(),
+            fail_on_severity: default_fail_on_severity(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WebConfig {
+    #[serde(default = "default_bind")]
+    pub bind: String,
+    #[serde(default = "default_port")]
+    pub port: u16,
+}
+
+impl Default for WebConfig {
+    fn default() -> Self {
+        Self {
+            bind: default_bind(),
+            port: default_port(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AlertConfig {
+    /// Recipient addresses, comma-separated.
+    pub to: String,
+    /// SMTP settings; when absent, `SMTP_HOST`/`SMTP_FROM` and friends
+    /// are consulted, and without those alerts are only logged.
+    #[serde(default)]
+    pub smtp: Option<aurum_notify::SmtpConfig>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChangelogConfig {
+    /// Changelog file entries are appended to, relative to the project
+    /// repository root; the append is committed separately from the patch.
+    #[serde(default)]
+    pub file: Option<PathBuf>,
+    /// Also send each entry through the alert email channel.
+    #[serde(default)]
+    pub announce: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LlmConfig {
+    /// Provider name the routing switch dispatches on.
+    #[serde(default = "default_provider")]
+    pub provider: String,
+    #[serde(default = "default_model")]
+    pub model: String,
+    /// Environment variable holding the API key, never the key itself.
+    #[serde(default = "default_api_key_env")]
+    pub api_key_env: String,
+    /// Override for the provider endpoint, e.g. a proxy. Required for the
+    /// "local" provider, where it is the Ollama/vLLM base URL.
+    #[serde(default)]
+    pub endpoint: Option<String>,
+    #[serde(default = "default_max_tokens")]
+    pub max_tokens: u32,
+    /// Per-request timeout; local models can be slow on first load.
+    #[serde(default = "default_timeout_secs")]
+    pub timeout_secs: u64,
+    /// Stream responses and cancel once the diff section is complete,
+    /// saving output tokens on chatty models.
+    #[serde(default)]
+    pub stream: bool,
+    /// Model context window in tokens; prompts are truncated to fit.
+    #[serde(default = "default_context_window")]
+    pub context_window: u32,
+    /// Price per million input tokens in USD; zero for local models.
+    #[serde(default)]
+    pub input_cost_per_mtok: f64,
+    /// Price per million output tokens in USD; zero for local models.
+    #[serde(default)]
+    pub output_cost_per_mtok: f64,
+    /// Daily spend cap in USD across all providers; generation is refused
+    /// once it is reached. Only the primary provider's value is honored.
+    #[serde(default)]
+    pub daily_budget_usd: Option<f64>,
+    /// Requests allowed per sliding minute. The limit is shared by every
+    /// provider configured with the same `api_key_env`; absent means
+    /// unlimited.
+    #[serde(default)]
+    pub requests_per_minute: Option<u32>,
+    /// How long completions are reused for byte-identical prompts against
+    /// the same model; absent means caching is off.
+    #[serde(default)]
+    pub cache_ttl_secs: Option<u64>,
+    /// Providers tried in order when this one rate-limits, times out, or
+    /// returns 5xx. Fallbacks of fallbacks are not followed.
+    #[serde(default)]
+    pub fallbacks: Vec<LlmConfig>,
+}
+
+fn default_provider() -> String {
+    "anthropic".to_string()
+}
+
+fn default_model() -> String {
+    "claude-3-5-sonnet-latest".to_string()
+}
+
+fn default_api_key_env() -> String {
+    "ANTHROPIC_API_KEY".to_string()
+}
+
+fn default_max_tokens() -> u32 {
+    4096
+}
+
+fn default_timeout_secs() -> u64 {
+    120
+}
+
+fn default_context_window() -> u32 {
+    8192
+}
+
+fn default_language() -> String {
+    "rust".to_string()
+}
+
+fn default_watch_paths() -> Vec<PathBuf> {
+    vec![PathBuf::from("services")]
+}
+
+fn default_debounce_ms() -> u64 {
+    2000
+}
+
+fn default_risk_threshold() -> u32 {
+    40
+}
+
+fn default_scheduler_concurrency() -> usize {
+    2
+}
+
+fn default_scheduler_batch() -> usize {
+    10
+}
+
+fn default_resolved_days() -> u64 {
+    90
+}
+
+fn default_failed_days() -> u64 {
+    180
+}
+
+fn default_rejected_patch_days() -> u64 {
+    30
+}
+
+fn default_sweep_interval() -> u64 {
+    86_400
+}
+
+fn default_election_enabled() -> bool {
+    true
+}
+
+fn default_lease_ttl() -> u64 {
+    30
+}
+
+fn default_forge() -> String {
+    "github".to_string()
+}
+
+fn default_remote() -> String {
+    "origin".to_string()
+}
+
+fn default_base_branch() -> String {
+    "main".to_string()
+}
+
+fn default_pr_token_env() -> String {
+    "GITHUB_TOKEN".to_string()
+}
+
+fn default_sandbox() -> bool {
+    true
+}
+
+fn default_sandbox_image() -> String {
+    "rust:1-slim".to_string()
+}
+
+fn default_sandbox_cpus() -> f64 {
+    2.0
+}
+
+fn default_sandbox_memory() -> String {
+    "4g".to_string()
+}
+
+fn default_validation_timeout() -> u64 {
+    900
+}
+
+fn default_semgrep_rules() -> String {
+    "auto".to_string()
+}
+
+fn default_fail_on_severity() -> String {
+    "high".to_string()
+}
+
+fn default_database_path() -> PathBuf {
+    PathBuf::from("self-healing.db")
+}
+
+fn default_poll_interval() -> u64 {
+    60
+}
+
+fn default_bind() -> String {
+    "127.0.0.1".to_string()
+}
+
+fn default_port() -> u16 {
+    9600
+}
@@ -0,0 +1,277 @@
+//! Builds the source context handed to the LLM alongside a failure.
+//!
+//! Instead of a fixed window around the failing line, this pulls the full
+//! enclosing function or impl via syn spans, the definitions of types the
+//! snippet references, the service's Cargo.toml dependency versions, and
+//! recent git blame for the touched lines. Sections are appended in that
+//! priority order until the character budget is spent.
+
+use crate::types::Issue;
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::process::Command;
+use syn::spanned::Spanned;
+use syn::Item;
+
+const TRUNCATION_MARKER: &str = "\n[... context truncated ...]\n";
+
+/// Assemble context for an issue within `budget_chars` characters.
+pub fn build(repo: &Path, issue: &Issue, budget_chars: usize) -> String {
+    let mut out = String::new();
+    for file in &issue.affected_files {
+        let path = repo.join(file);
+        let Ok(source) = std::fs::read_to_string(&path) else {
+            continue;
+        };
+        let line = line_for_file(&issue.log, file);
+        match line.and_then(|line| enclosing_item(&source, line)) {
+            Some((start, end)) => {
+                let snippet = slice_lines(&source, start, end);
+                push_section(
+                    &mut out,
+                    budget_chars,
+                    &format!("{file} (lines {start}-{end})"),
+                    &snippet,
+                );
+                for (name, (ty_start, ty_end)) in referenced_types(&source, &snippet, start, end) {
+                    push_section(
+                        &mut out,
+                        budget_chars,
+                        &format!("{file}: definition of {name}"),
+                        &slice_lines(&source, ty_start, ty_end),
+                    );
+                }
+                if let Some((from, to)) = line.map(|l| (start.min(l), end.max(l))) {
+                    if let Some(blame) = blame_lines(repo, file, from, to) {
+                        push_section(&mut out, budget_chars, &format!("{file}: git blame"), &blame);
+                    }
+                }
+            }
+            // No parse or no line: fall back to the whole file and let the
+            // budget cut it.
+            None => push_section(&mut out, budget_chars, file, &source),
+        }
+        if let Some(deps) = dependency_versions(repo, file) {
+            push_section(&mut out, budget_chars, "dependency versions", &deps);
+        }
+    }
+    out
+}
+
+/// Find `file:NNN` in the issue log to recover the failing line.
+fn line_for_file(log: &str, file: &str) -> Option<usize> {
+    let needle = format!("{file}:");
+    let start = log.find(&needle)? + needle.len();
+    let digits: String = log[start..].chars().take_while(char::is_ascii_digit).collect();
+    digits.parse().ok()
+}
+
+/// The smallest item (fn, impl member, struct, ...) whose span contains
+/// `line`, as a 1-based inclusive line range.
+fn enclosing_item(source: &str, line: usize) -> Option<(usize, usize)> {
+    let file = syn::parse_file(source).ok()?;
+    let mut ranges = Vec::new();
+    collect_ranges(&file.items, &mut ranges);
+    ranges
+        .into_iter()
+        .filter(|(start, end)| *start <= line && line <= *end)
+        .min_by_key(|(start, end)| end - start)
+}
+
+fn collect_ranges(items: &[Item], out: &mut Vec<(usize, usize)>) {
+    for item in items {
+        let span = item.span();
+        out.push((span.start().line, span.end().line));
+        match item {
+            Item::Mod(m) => {
+                if let Some((_, nested)) = &m.content {
+                    collect_ranges(nested, out);
+                }
+            }
+            Item::Impl(i) => {
+                for member in &i.items {
+                    let span = member.span();
+                    out.push((span.start().line, span.end().line));
+                }
+            }
+            Item::Trait(t) => {
+                for member in &t.items {
+                    let span = member.span();
+                    out.push((span.start().line, span.end().line));
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Definitions of types declared in this file that the snippet mentions,
+/// excluding anything already inside the snippet's own range.
+fn referenced_types(
+    source: &str,
+    snippet: &str,
+    snippet_start: usize,
+    snippet_end: usize,
+) -> BTreeMap<String, (usize, usize)> {
+    let mut found = BTreeMap::new();
+    let Ok(file) = syn::parse_file(source) else {
+        return found;
+    };
+    for item in &file.items {
+        let name = match item {
+            Item::Struct(s) => s.ident.to_string(),
+            Item::Enum(e) => e.ident.to_string(),
+            Item::Type(t) => t.ident.to_string(),
+            Item::Trait(t) => t.ident.to_string(),
+            _ => continue,
+        };
+        let span = item.span();
+        let (start, end) = (span.start().line, span.end().line);
+        if start >= snippet_start && end <= snippet_end {
+            continue;
+        }
+        if snippet.contains(&name) {
+            found.insert(name, (start, end));
+        }
+    }
+    found
+}
+
+/// 1-based inclusive line slice of a source file.
+fn slice_lines(source: &str, start: usize, end: usize) -> String {
+    source
+        .lines()
+        .skip(start.saturating_sub(1))
+        .take(end + 1 - start)
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// `git blame` for the touched range, so the model sees what changed
+/// recently and by which commit.
+fn blame_lines(repo: &Path, file: &str, from: usize, to: usize) -> Option<String> {
+    let output = Command::new("git")
+        .args([
+            "blame",
+            "--date=short",
+            "-L",
+            &format!("{from},{to}"),
+            "--",
+            file,
+        ])
+        .current_dir(repo)
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    let blame = String::from_utf8_lossy(&output.stdout);
+    let lines: Vec<&str> = blame.lines().take(40).collect();
+    if lines.is_empty() {
+        None
+    } else {
+        Some(lines.join("\n"))
+    }
+}
+
+/// Dependency lines from the Cargo.toml closest to the affected file, so
+/// the model proposes versions that actually exist in the tree.
+fn dependency_versions(repo: &Path, file: &str) -> Option<String> {
+    let mut dir = repo.join(file);
+    while let Some(parent) = dir.parent().map(Path::to_path_buf) {
+        if !parent.starts_with(repo) {
+            return None;
+        }
+        let manifest = parent.join("Cargo.toml");
+        if let Ok(raw) = std::fs::read_to_string(&manifest) {
+            let mut deps = Vec::new();
+            let mut in_deps = false;
+            for line in raw.lines() {
+                let trimmed = line.trim();
+                if trimmed.starts_with('[') {
+                    in_deps = trimmed.contains("dependencies");
+                    continue;
+                }
+                if in_deps && !trimmed.is_empty() && !trimmed.starts_with('#') {
+                    deps.push(trimmed);
+                }
+            }
+            if !deps.is_empty() {
+                return Some(deps.join("\n"));
+            }
+        }
+        dir = parent;
+    }
+    None
+}
+
+/// Append a titled section, truncating its tail when it would overflow the
+/// budget. Sections arriving after the budget is spent are dropped.
+fn push_section(out: &mut String, budget: usize, title: &str, body: &str) {
+    if out.len() >= budget {
+        return;
+    }
+    let remaining = budget - out.len();
+    let block = format!("### {title}\n{body}\n\n");
+    if block.len() <= remaining {
+        out.push_str(&block);
+    } else if remaining > TRUNCATION_MARKER.len() {
+        let keep = remaining - TRUNCATION_MARKER.len();
+        let mut cut = keep;
+        while cut > 0 && !block.is_char_boundary(cut) {
+            cut -= 1;
+        }
+        out.push_str(&block[..cut]);
+        out.push_str(TRUNCATION_MARKER);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SOURCE: &str = "pub struct Widget {\n    pub size: u32,\n}\n\npub struct Unrelated;\n\nfn helper() -> u32 {\n    7\n}\n\npub fn assemble(w: Widget) -> u32 {\n    w.size + helper()\n}\n";
+
+    fn issue_for(file: &str, line: usize) -> Issue {
+        Issue::new(
+            "test",
+            "widgets",
+            "abc123",
+            "compiler",
+            &format!("error[E0308]: mismatched types at {file}:{line}"),
+            vec![file.to_string()],
+        )
+    }
+
+    #[test]
+    fn pulls_enclosing_function_and_referenced_types() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("lib.rs"), SOURCE).unwrap();
+        // Line 12 is inside `assemble`.
+        let context = build(dir.path(), &issue_for("lib.rs", 12), 10_000);
+        assert!(context.contains("pub fn assemble"));
+        assert!(context.contains("definition of Widget"));
+        // `Unrelated` is never mentioned by the snippet.
+        assert!(!context.contains("Unrelated"));
+    }
+
+    #[test]
+    fn respects_the_character_budget() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("lib.rs"), SOURCE).unwrap();
+        let context = build(dir.path(), &issue_for("lib.rs", 12), 120);
+        assert!(context.len() <= 120);
+        assert!(context.contains("context truncated"));
+    }
+
+    #[test]
+    fn falls_back_to_whole_file_without_a_line() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("lib.rs"), SOURCE).unwrap();
+        let mut issue = issue_for("lib.rs", 1);
+        issue.log = "no location here".to_string();
+        let context = build(dir.path(), &issue, 10_000);
+        assert!(context.contains("pub struct Widget"));
+        assert!(context.contains("pub fn assemble"));
+    }
+}
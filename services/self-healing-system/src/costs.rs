@@ -0,0 +1,99 @@
+//! Cost accounting for LLM usage.
+//!
+//! Every completed request is recorded with its token counts and estimated
+//! dollar cost, keyed by the issue that triggered it. Aggregates feed the
+//! `/api/costs` endpoint and the daily budget check that stops generation
+//! when the configured spend is exhausted.
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use uuid::Uuid;
+
+use crate::llm_integration::TokenUsage;
+
+/// One LLM request's cost, as persisted.
+#[derive(Debug, Clone, Serialize)]
+pub struct CostEntry {
+    pub id: Uuid,
+    pub issue_id: Uuid,
+    pub provider: String,
+    pub input_tokens: i64,
+    pub output_tokens: i64,
+    pub cost_usd: f64,
+    pub created_at: DateTime<Utc>,
+}
+
+impl CostEntry {
+    pub fn new(issue_id: Uuid, provider: &str, usage: TokenUsage, cost_usd: f64) -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            issue_id,
+            provider: provider.to_string(),
+            input_tokens: usage.input_tokens as i64,
+            output_tokens: usage.output_tokens as i64,
+            cost_usd,
+            created_at: Utc::now(),
+        }
+    }
+}
+
+/// Spend aggregated over one calendar day (UTC).
+#[derive(Debug, Serialize)]
+pub struct DayCost {
+    pub day: String,
+    pub input_tokens: i64,
+    pub output_tokens: i64,
+    pub cost_usd: f64,
+}
+
+/// Spend aggregated per issue.
+#[derive(Debug, Serialize)]
+pub struct IssueCost {
+    pub issue_id: String,
+    pub requests: i64,
+    pub cost_usd: f64,
+}
+
+/// The `/api/costs` response.
+#[derive(Debug, Serialize)]
+pub struct CostReport {
+    pub today_usd: f64,
+    pub daily_budget_usd: Option<f64>,
+    pub budget_exhausted: bool,
+    pub by_day: Vec<DayCost>,
+    pub by_issue: Vec<IssueCost>,
+}
+
+/// Dollar cost of a request given per-million-token prices.
+pub fn cost_usd(input_per_mtok: f64, output_per_mtok: f64, usage: TokenUsage) -> f64 {
+    usage.input_tokens as f64 * input_per_mtok / 1_000_000.0
+        + usage.output_tokens as f64 * output_per_mtok / 1_000_000.0
+}
+
+/// RFC 3339 timestamp for midnight UTC today; lexicographically comparable
+/// with the stored `created_at` values.
+pub fn start_of_today() -> String {
+    format!("{}T00:00:00+00:00", Utc::now().format("%Y-%m-%d"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn computes_dollar_cost_from_mtok_prices() {
+        let usage = TokenUsage {
+            input_tokens: 500_000,
+            output_tokens: 100_000,
+        };
+        let cost = cost_usd(3.0, 15.0, usage);
+        assert!((cost - 3.0).abs() < 1e-9);
+        assert_eq!(cost_usd(0.0, 0.0, usage), 0.0);
+    }
+
+    #[test]
+    fn start_of_today_sorts_before_current_time() {
+        let now = Utc::now().to_rfc3339();
+        assert!(start_of_today() <= now);
+    }
+}
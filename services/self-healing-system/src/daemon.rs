@@ -0,0 +1,1705 @@
+//! The daemon core: ingests issues, tracks patches, and applies or reverts
+//! them in the target repository.
+
+use crate::alerts::AlertManager;
+use crate::breaking_changes::BreakingChange;
+use crate::config::{HealingConfig, ProjectConfig};
+use crate::database::Database;
+use crate::leader::LeaderElector;
+use crate::llm_integration::{extract_diff, GenerationProgress, LlmClient, TokenUsage};
+use crate::metrics::MetricsCollector;
+use crate::policy::{Decision, PolicyEngine, PolicyInput};
+use crate::prompts::PromptRegistry;
+use crate::test_repo::{TestCase, TestCaseRepository};
+use crate::types::{Issue, IssueStatus, Patch, PatchStatus, Review, ReviewVerdict};
+use crate::validator::PatchValidator;
+use anyhow::{bail, Context, Result};
+use chrono::{Timelike, Utc};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::Arc;
+use std::time::Instant;
+use tokio::sync::mpsc;
+use tracing::{error, info, warn};
+use uuid::Uuid;
+
+/// What one analysis pass produced, for `/api/analyze`.
+#[derive(Debug, Default, Serialize)]
+pub struct AnalysisReport {
+    pub errors: usize,
+    pub warnings: usize,
+    pub lints: usize,
+    pub vulnerabilities: usize,
+    pub issues_filed: usize,
+    pub deduplicated: usize,
+    pub patches_proposed: usize,
+}
+
+/// Snapshot of the daemon for `/api/status`.
+#[derive(Debug, Serialize)]
+pub struct DaemonStatus {
+    pub uptime_secs: u64,
+    /// Whether this replica holds the leader lease and thus performs
+    /// mutating actions.
+    pub is_leader: bool,
+    pub open_issues: i64,
+    pub patching_issues: i64,
+    pub proposed_patches: i64,
+    pub applied_patches: i64,
+    /// Present when an LLM provider is configured.
+    pub llm_usage: Option<TokenUsage>,
+    /// Latest streamed-generation progress, when streaming is enabled.
+    pub llm_progress: Option<GenerationProgress>,
+}
+
+/// What applying a patch would do, produced in dry-run mode and by the
+/// `plan` option on the apply endpoint instead of touching the repository.
+#[derive(Debug, Serialize)]
+pub struct ApplyPlan {
+    pub patch_id: Uuid,
+    pub issue_id: Uuid,
+    pub project: String,
+    /// "commit" or "pull_request", depending on configuration.
+    pub mode: &'static str,
+    /// Repository-relative paths the diff touches.
+    pub files: Vec<String>,
+    pub breaking_changes: Vec<BreakingChange>,
+    /// Whether the patch has already passed validation.
+    pub validated: bool,
+    /// The operations an actual apply would run, in order.
+    pub steps: Vec<String>,
+}
+
+/// What an on-demand test generation produced, for
+/// `/api/issues/{id}/tests/generate`.
+#[derive(Debug, Serialize)]
+pub struct TestGenerationReport {
+    pub test_case_id: Uuid,
+    /// False when identical content was already in the test repository.
+    pub new: bool,
+    /// Whether the scaffold was written into the project tree (false
+    /// under dry-run or when the file already exists).
+    pub written: bool,
+    /// Repo-relative path of the scaffold.
+    pub path: PathBuf,
+    pub content: String,
+}
+
+/// What an on-demand fuzz harness generation produced, for
+/// `/api/issues/{id}/fuzz/generate`.
+#[derive(Debug, Serialize)]
+pub struct FuzzGenerationReport {
+    /// `[[bin]]` name of the cargo-fuzz target.
+    pub target_name: String,
+    /// Function the target feeds raw input into.
+    pub function: String,
+    /// Whether the harness was written into the project tree (false
+    /// under dry-run or when the target already exists).
+    pub written: bool,
+    /// Repo-relative path of the fuzz target source.
+    pub target_path: PathBuf,
+    pub target_content: String,
+    /// Repo-relative corpus seed carrying the failing input.
+    pub seed_path: PathBuf,
+}
+
+/// Where promoted regression tests land in the target repository.
+const REGRESSION_DIR: &str = "tests/regression";
+
+/// The result of promoting a test case into the regression suite.
+#[derive(Debug, Serialize)]
+pub struct TestPromotion {
+    pub test_case_id: Uuid,
+    /// Repo-relative path the regression test was committed at.
+    pub path: PathBuf,
+    /// Review branch, in pull-request mode.
+    pub branch: Option<String>,
+    pub pr_url: Option<String>,
+}
+
+pub struct SelfHealingDaemon {
+    pub config: HealingConfig,
+    pub database: Database,
+    pub metrics: Arc<MetricsCollector>,
+    llm: Option<LlmClient>,
+    leader: Arc<LeaderElector>,
+    alerts: AlertManager,
+    prompts: PromptRegistry,
+    /// One validator (and workspace pool) per configured project.
+    validators: HashMap<String, PatchValidator>,
+    /// Decides what happens to patches that pass validation.
+    policy: PolicyEngine,
+    /// Pushes daemon-made commits to the remote; absent means they stay
+    /// local.
+    git_ops: Option<crate::git_ops::GitOperations>,
+    /// Pushed by the filesystem watcher and the CI webhook to start an
+    /// analysis run ahead of the next poll cycle.
+    trigger_tx: mpsc::Sender<&'static str>,
+    trigger_rx: tokio::sync::Mutex<mpsc::Receiver<&'static str>>,
+    /// Analysis, generation, and validation run as usual, but nothing is
+    /// committed, pushed, or written to a target repository.
+    dry_run: bool,
+    started: Instant,
+}
+
+impl SelfHealingDaemon {
+    pub async fn new(config: HealingConfig, dry_run: bool) -> Result<Arc<Self>> {
+        let database = match &config.database_url {
+            Some(url) => Database::connect(url).await?,
+            None => Database::open(&config.database_path).await?,
+        };
+        let metrics = Arc::new(MetricsCollector::new()?);
+        let llm = config
+            .llm
+            .clone()
+            .map(|llm| LlmClient::new(llm, metrics.clone(), Some(database.clone())));
+        if let Some(llm) = &llm {
+            // A failed probe is worth a loud warning but not a dead daemon:
+            // ingestion and manual patching work without the LLM.
+            if let Err(e) = llm.health_probe().await {
+                warn!("llm health probe failed: {e:#}");
+            }
+        }
+        let leader = Arc::new(LeaderElector::new(
+            database.clone(),
+            config.election.enabled,
+            config.election.lease_ttl_secs,
+        ));
+        let (trigger_tx, trigger_rx) = mpsc::channel(8);
+        let mut validators = HashMap::new();
+        for project in config.project_list() {
+            validators.insert(project.id.clone(), PatchValidator::new(&config, &project)?);
+        }
+        Ok(Arc::new(Self {
+            database,
+            metrics,
+            llm,
+            leader,
+            alerts: AlertManager::new(config.alerts.as_ref()),
+            prompts: PromptRegistry::new(config.prompt_dir.clone()),
+            validators,
+            policy: PolicyEngine::new(config.policy_file.clone(), config.review.risk_threshold),
+            git_ops: config.push.clone().map(crate::git_ops::GitOperations::new),
+            trigger_tx,
+            trigger_rx: tokio::sync::Mutex::new(trigger_rx),
+            dry_run,
+            started: Instant::now(),
+            config,
+        }))
+    }
+
+    /// Ask the daemon loop for an analysis run; a full channel means one
+    /// is already pending.
+    pub fn trigger(&self, source: &'static str) {
+        let _ = self.trigger_tx.try_send(source);
+    }
+
+    /// Release the leader lease; called on clean shutdown.
+    pub async fn resign_leadership(&self) {
+        self.leader.resign().await;
+    }
+
+    /// Refuse mutating actions on follower replicas; reads and issue
+    /// ingestion stay available everywhere.
+    fn ensure_leader(&self) -> Result<()> {
+        if self.leader.is_leader() {
+            Ok(())
+        } else {
+            bail!("this replica is not the leader; mutating actions are disabled")
+        }
+    }
+
+    /// Run the daemon loop until the process is stopped. With `watch` set,
+    /// filesystem changes and CI failure webhooks start analysis within
+    /// the debounce window instead of waiting for the next poll.
+    pub async fn run(self: Arc<Self>, watch: bool) -> Result<()> {
+        self.leader.try_acquire().await;
+        self.leader.spawn();
+        if self.dry_run {
+            info!("dry-run mode: patches will be planned and validated but never applied");
+        }
+        let _watcher = if watch {
+            match crate::watcher::spawn(&self.config, self.trigger_tx.clone()) {
+                Ok(watcher) => Some(watcher),
+                Err(e) => {
+                    warn!("filesystem watcher not started: {e:#}");
+                    None
+                }
+            }
+        } else {
+            None
+        };
+        let interval = std::time::Duration::from_secs(self.config.poll_interval_secs);
+        let debounce = std::time::Duration::from_millis(self.config.watch.debounce_ms);
+        let sweep_interval =
+            std::time::Duration::from_secs(self.config.retention.sweep_interval_secs);
+        let mut last_sweep = std::time::Instant::now();
+        let mut trigger_rx = self.trigger_rx.lock().await;
+        loop {
+            tokio::select! {
+                _ = tokio::time::sleep(interval) => {
+                    if let Err(e) = self.refresh_metrics().await {
+                        error!("metrics refresh failed: {e:#}");
+                    }
+                    if self.leader.is_leader() && self.config.scheduler.enabled {
+                        match self.process_queue().await {
+                            Ok(report) if report.queued > 0 => info!(
+                                queued = report.queued,
+                                patched = report.patched,
+                                failed = report.failed,
+                                "scheduler pass finished"
+                            ),
+                            Ok(_) => {}
+                            Err(e) => error!("scheduler pass failed: {e:#}"),
+                        }
+                    }
+                    if self.leader.is_leader() && last_sweep.elapsed() >= sweep_interval {
+                        last_sweep = std::time::Instant::now();
+                        match crate::retention::sweep(&self.database, &self.config.retention).await {
+                            Ok(report) => {
+                                if report.issues_archived > 0 || report.patches_archived > 0 {
+                                    info!(?report, "retention sweep finished");
+                                }
+                            }
+                            Err(e) => error!("retention sweep failed: {e:#}"),
+                        }
+                    }
+                }
+                Some(source) = trigger_rx.recv() => {
+                    // Let the burst settle, then fold queued triggers into
+                    // this run.
+                    tokio::time::sleep(debounce).await;
+                    while trigger_rx.try_recv().is_ok() {}
+                    if !self.leader.is_leader() {
+                        info!(source, "change detected, but this replica is a follower; skipping");
+                        continue;
+                    }
+                    info!(source, "change detected, starting analysis");
+                    if let Err(e) = self.analyze().await {
+                        error!("triggered analysis failed: {e:#}");
+                    }
+                }
+            }
+        }
+    }
+
+    async fn refresh_metrics(&self) -> Result<()> {
+        let open = self.database.count_issues(IssueStatus::Open).await?;
+        self.metrics.set_open_issues(open);
+        Ok(())
+    }
+
+    /// Record a newly reported failure, tagging it with the team owning
+    /// its affected files.
+    pub async fn ingest_issue(&self, mut issue: Issue) -> Result<Issue> {
+        if issue.owner.is_none() {
+            let project = self.config.project(&issue.project);
+            issue.owner = crate::ownership::OwnershipMap::load(&project.path, &self.config.owners)
+                .owner_for(&issue.affected_files);
+        }
+        info!(
+            service = %issue.service,
+            classification = %issue.classification,
+            owner = issue.owner.as_deref().unwrap_or("-"),
+            "ingesting issue"
+        );
+        self.database.record_issue(&issue).await?;
+        self.metrics.observe_issue(&issue.classification);
+        Ok(issue)
+    }
+
+    /// Run the static analysis tools against every configured project,
+    /// file issues for what they find, and propose patches from machine-
+    /// applicable suggestions. The compiler's own fix always takes
+    /// precedence over generated ones.
+    pub async fn analyze(&self) -> Result<AnalysisReport> {
+        self.ensure_leader()?;
+        let mut report = AnalysisReport::default();
+        for project in self.config.project_list() {
+            self.analyze_project(&project, &mut report).await?;
+        }
+        Ok(report)
+    }
+
+    async fn analyze_project(
+        &self,
+        project: &ProjectConfig,
+        report: &mut AnalysisReport,
+    ) -> Result<()> {
+        // The analysis toolchain is cargo-based; non-Rust projects are
+        // still healed from reported issues, just not scanned.
+        if project.language != "rust" {
+            info!(project = %project.id, language = %project.language, "skipping static analysis for non-rust project");
+            return Ok(());
+        }
+        info!(project = %project.id, "running static analysis pass");
+        let commit = self
+            .head_commit(&project.path)
+            .unwrap_or_else(|_| "unknown".to_string());
+
+        for diag in crate::static_analysis::check_project(&project.path)? {
+            if diag.level != "error" {
+                report.warnings += 1;
+                continue;
+            }
+            report.errors += 1;
+            self.file_analysis_issue(project, &diag, "compiler", &commit, report)
+                .await?;
+        }
+        match crate::static_analysis::check_clippy(&project.path) {
+            Ok(lints) => {
+                for diag in lints {
+                    report.lints += 1;
+                    self.file_analysis_issue(project, &diag, "lint", &commit, report)
+                        .await?;
+                }
+            }
+            Err(e) => warn!("clippy pass skipped: {e:#}"),
+        }
+        // cargo-audit is optional tooling; its absence is not an analysis
+        // failure.
+        match crate::static_analysis::audit(&project.path) {
+            Ok(advisories) => {
+                for diag in advisories {
+                    if diag.level != "error" {
+                        report.warnings += 1;
+                        continue;
+                    }
+                    report.vulnerabilities += 1;
+                    self.file_analysis_issue(project, &diag, "security", &commit, report)
+                        .await?;
+                }
+            }
+            Err(e) => warn!("audit pass skipped: {e:#}"),
+        }
+        Ok(())
+    }
+
+    /// File one diagnostic as an issue unless an open issue with the same
+    /// log already exists, and propose a patch when the tool supplied a
+    /// machine-applicable fix.
+    async fn file_analysis_issue(
+        &self,
+        project: &ProjectConfig,
+        diag: &crate::static_analysis::AnalysisIssue,
+        classification: &str,
+        commit: &str,
+        report: &mut AnalysisReport,
+    ) -> Result<()> {
+        let log = match &diag.code {
+            Some(code) => format!("{}[{code}]: {} at {}:{}", diag.level, diag.message, diag.file, diag.line),
+            None => format!("{}: {} at {}:{}", diag.level, diag.message, diag.file, diag.line),
+        };
+        if self.database.find_open_issue(&log).await?.is_some() {
+            report.deduplicated += 1;
+            return Ok(());
+        }
+        let mut issue = Issue::new(
+            "static-analysis",
+            &service_for_path(&diag.file),
+            commit,
+            classification,
+            &log,
+            vec![diag.file.clone()],
+        );
+        issue.project = project.id.clone();
+        let issue = self.ingest_issue(issue).await?;
+        report.issues_filed += 1;
+        let Some(fix) = diag.suggestions.iter().find(|s| s.machine_applicable) else {
+            return Ok(());
+        };
+        match crate::static_analysis::suggestion_diff(&project.path, fix) {
+            Ok(diff) if self.dry_run_diff(&issue.project, &diff).is_ok() => {
+                let mut patch = Patch::new(issue.id, "machine-applicable tool suggestion", &diff);
+                patch.origin = "tool-suggestion".to_string();
+                self.database.record_patch(&patch).await?;
+                self.metrics.observe_patch(patch.status.as_str());
+                report.patches_proposed += 1;
+            }
+            Ok(_) => warn!(issue = %issue.id, "suggested fix did not apply cleanly"),
+            Err(e) => warn!(issue = %issue.id, "could not build suggestion diff: {e:#}"),
+        }
+        Ok(())
+    }
+
+    fn head_commit(&self, dir: &Path) -> Result<String> {
+        let output = Command::new("git")
+            .args(["rev-parse", "HEAD"])
+            .current_dir(dir)
+            .output()
+            .context("failed to invoke git rev-parse")?;
+        if !output.status.success() {
+            bail!("git rev-parse failed");
+        }
+        Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+    }
+
+    /// Aggregate LLM spend for `/api/costs`.
+    pub async fn cost_report(&self) -> Result<crate::costs::CostReport> {
+        let today_usd = self
+            .database
+            .cost_since(&crate::costs::start_of_today())
+            .await?;
+        let daily_budget_usd = self.llm.as_ref().and_then(|llm| llm.daily_budget_usd());
+        Ok(crate::costs::CostReport {
+            today_usd,
+            daily_budget_usd,
+            budget_exhausted: daily_budget_usd.is_some_and(|b| today_usd >= b),
+            by_day: self.database.costs_by_day(7).await?,
+            by_issue: self.database.costs_by_issue(10).await?,
+        })
+    }
+
+    pub async fn status(&self) -> Result<DaemonStatus> {
+        Ok(DaemonStatus {
+            uptime_secs: self.started.elapsed().as_secs(),
+            is_leader: self.leader.is_leader(),
+            open_issues: self.database.count_issues(IssueStatus::Open).await?,
+            patching_issues: self.database.count_issues(IssueStatus::Patching).await?,
+            proposed_patches: self.database.count_patches(PatchStatus::Proposed).await?,
+            applied_patches: self.database.count_patches(PatchStatus::Applied).await?,
+            llm_usage: self.llm.as_ref().map(|llm| llm.usage()),
+            llm_progress: self.llm.as_ref().map(|llm| llm.progress_snapshot()),
+        })
+    }
+
+    /// Generate a fix for an issue and record it as a proposed patch. The
+    /// deterministic fixer rules get the first shot; only an issue no rule
+    /// matches costs an LLM call. Either way the diff must survive the
+    /// same dry-run as any hand-written one before it is stored.
+    /// One scheduler pass: rank the open issues and generate patches for
+    /// the head of the queue, `max_concurrent` at a time. A failed
+    /// generation is logged and the issue stays queued; the next pass
+    /// ranks it again. See the scheduler module for how scores are built.
+    pub async fn process_queue(self: &Arc<Self>) -> Result<crate::scheduler::QueueReport> {
+        self.ensure_leader()?;
+        let open = self
+            .database
+            .issues(Some(IssueStatus::Open), None, 500)
+            .await?;
+        let radii: HashMap<String, i64> = self
+            .database
+            .open_issue_counts_by_service()
+            .await?
+            .into_iter()
+            .collect();
+        // Zero prices (no LLM configured) drop the cost term from the
+        // ranking; deterministic fixers still run.
+        let (input_price, output_price) = self
+            .config
+            .llm
+            .as_ref()
+            .map(|llm| (llm.input_cost_per_mtok, llm.output_cost_per_mtok))
+            .unwrap_or((0.0, 0.0));
+        let queue = crate::scheduler::prioritize(open, &radii, input_price, output_price);
+
+        let mut report = crate::scheduler::QueueReport::default();
+        let workers = self.config.scheduler.max_concurrent.max(1);
+        let mut tasks = tokio::task::JoinSet::new();
+        let settle = |joined: Option<Result<bool, tokio::task::JoinError>>,
+                          report: &mut crate::scheduler::QueueReport| {
+            match joined {
+                Some(Ok(true)) => report.patched += 1,
+                Some(_) => report.failed += 1,
+                None => {}
+            }
+        };
+        for issue in queue.into_iter().take(self.config.scheduler.batch_size) {
+            report.queued += 1;
+            if tasks.len() >= workers {
+                let joined = tasks.join_next().await;
+                settle(joined, &mut report);
+            }
+            let daemon = Arc::clone(self);
+            tasks.spawn(async move {
+                match daemon.generate_patch(issue.id).await {
+                    Ok(_) => true,
+                    Err(e) => {
+                        warn!(
+                            issue = %issue.id,
+                            service = %issue.service,
+                            "scheduled patch generation failed: {e:#}"
+                        );
+                        false
+                    }
+                }
+            });
+        }
+        while !tasks.is_empty() {
+            let joined = tasks.join_next().await;
+            settle(joined, &mut report);
+        }
+        Ok(report)
+    }
+
+    pub async fn generate_patch(&self, issue_id: Uuid) -> Result<Patch> {
+        self.ensure_leader()?;
+        let mut issue = self
+            .database
+            .issue_by_id(issue_id)
+            .await?
+            .with_context(|| format!("no issue {issue_id}"))?;
+        let project = self.config.project(&issue.project);
+        // Pin the failure in the tree as a reproduction scaffold, so the
+        // eventual fix and its test can land together.
+        if !self.dry_run {
+            match crate::test_gen::generate_from_failure(&project.path, &issue) {
+                Ok(Some(test)) => {
+                    // Remember the scaffold in the shared repository too
+                    // (deduplicated by content), so `tests list` and
+                    // `tests stats` see it.
+                    if let Err(e) = self
+                        .test_repository()
+                        .store(&issue.service, &issue.commit, Some(issue.id), &test.content)
+                        .await
+                    {
+                        warn!(issue = %issue.id, "test case not recorded: {e:#}");
+                    }
+                    match crate::test_gen::store(&project.path, &test) {
+                        Ok(true) => {
+                            info!(issue = %issue.id, path = %test.path.display(), "reproduction test scaffold written")
+                        }
+                        Ok(false) => {}
+                        Err(e) => warn!(issue = %issue.id, "reproduction test not written: {e:#}"),
+                    }
+                }
+                Ok(None) => {}
+                Err(e) => warn!(issue = %issue.id, "reproduction synthesis failed: {e:#}"),
+            }
+        }
+        if let Some(fix) = crate::fixers::try_fix(&project.path, &issue) {
+            match self.dry_run_diff(&issue.project, &fix.diff) {
+                Ok(_) => {
+                    let mut patch = Patch::new(issue.id, &fix.description, &fix.diff);
+                    patch.origin = format!("rule:{}", fix.rule);
+                    self.database.record_patch(&patch).await?;
+                    self.metrics.observe_patch(patch.status.as_str());
+                    info!(issue = %issue.id, rule = fix.rule, "deterministic fixer produced a patch");
+                    return Ok(patch);
+                }
+                Err(e) => warn!(
+                    issue = %issue.id,
+                    "fixer diff failed the dry run, falling back to the llm: {e:#}"
+                ),
+            }
+        }
+        let Some(llm) = &self.llm else {
+            bail!("no llm provider is configured and no fixer rule matched");
+        };
+        // A project-level budget caps that project's spend on its own;
+        // otherwise the provider-level budget caps overall spend.
+        if let Some(budget) = project.daily_budget_usd {
+            let spent = self
+                .database
+                .cost_since_for_project(&crate::costs::start_of_today(), &project.id)
+                .await?;
+            if spent >= budget {
+                bail!(
+                    "daily llm budget of ${budget:.2} for project {} is exhausted (${spent:.2} spent)",
+                    project.id
+                );
+            }
+        } else if let Some(budget) = llm.daily_budget_usd() {
+            let spent = self
+                .database
+                .cost_since(&crate::costs::start_of_today())
+                .await?;
+            if spent >= budget {
+                bail!("daily llm budget of ${budget:.2} is exhausted (${spent:.2} spent)");
+            }
+        }
+        // Leave a quarter of the prompt window for the log and the template
+        // text around the sources.
+        let sources =
+            crate::context_builder::build(&project.path, &issue, llm.context_chars() * 3 / 4);
+        let mut vars = std::collections::HashMap::new();
+        vars.insert("service", issue.service.clone());
+        vars.insert("commit", issue.commit.clone());
+        vars.insert("classification", issue.classification.clone());
+        vars.insert("log", issue.log.clone());
+        vars.insert("files", issue.affected_files.join("\n"));
+        vars.insert("sources", sources);
+        let (system, prompt) = self.prompts.render(&issue.classification, &vars)?;
+        issue.status = IssueStatus::Patching;
+        issue.updated_at = Utc::now();
+        self.database.record_issue(&issue).await?;
+
+        let completion = llm.complete(&system, &prompt).await?;
+        self.database
+            .record_llm_cost(&crate::costs::CostEntry::new(
+                issue.id,
+                &completion.provider,
+                completion.usage,
+                completion.cost_usd,
+            ))
+            .await?;
+        let diff = extract_diff(&completion.text);
+        self.dry_run_diff(&issue.project, &diff)
+            .context("generated diff failed the dry run")?;
+        let mut patch = Patch::new(
+            issue.id,
+            &format!("llm-generated fix via {}", completion.provider),
+            &diff,
+        );
+        patch.origin = "llm".to_string();
+        self.database.record_patch(&patch).await?;
+        self.metrics.observe_patch(patch.status.as_str());
+        info!(
+            issue = %issue.id,
+            provider = %completion.provider,
+            input_tokens = completion.usage.input_tokens,
+            output_tokens = completion.usage.output_tokens,
+            "generated candidate patch"
+        );
+        Ok(patch)
+    }
+
+    /// Build and test a patch in an isolated copy of the workspace and
+    /// record the outcome. A failing build or test suite rejects the patch;
+    /// it does not error.
+    pub async fn validate_patch(&self, id: Uuid) -> Result<Patch> {
+        self.validate_patch_inner(id, true).await
+    }
+
+    async fn validate_patch_inner(&self, id: Uuid, allow_auto_apply: bool) -> Result<Patch> {
+        self.ensure_leader()?;
+        let mut patch = self
+            .database
+            .patch_by_id(id)
+            .await?
+            .with_context(|| format!("no patch {id}"))?;
+        if patch.status == PatchStatus::Applied {
+            bail!("patch {id} is already applied");
+        }
+        let issue = self
+            .database
+            .issue_by_id(patch.issue_id)
+            .await?
+            .with_context(|| format!("no issue {}", patch.issue_id))?;
+        let project = self.config.project(&issue.project);
+        let validator = self
+            .validators
+            .get(&project.id)
+            .or_else(|| self.validators.get("default"))
+            .with_context(|| format!("no validator for project {}", project.id))?
+            .clone();
+        let diff = patch.diff.clone();
+        // Validation builds run for minutes; keep them off the async runtime.
+        let result = tokio::task::spawn_blocking(move || validator.validate(&diff))
+            .await
+            .context("validation task panicked")??;
+        self.metrics.observe_validation(&result);
+        let mut decision = None;
+        patch.status = if result.passed {
+            // Passing validation is not enough: the policy engine weighs
+            // the risk score, the touched files, the time of day, and the
+            // service's recent failure rate before anything is applied.
+            let breaking = self.dry_run_diff(&project.id, &patch.diff).unwrap_or_default();
+            let assessment = crate::review::assess(&patch.diff, &breaking, Some(&result));
+            let files: Vec<String> = crate::patch_generator::parse(&patch.diff)
+                .map(|diffs| diffs.into_iter().map(|d| d.new_path).collect())
+                .unwrap_or_default();
+            let recent_failures = self
+                .database
+                .count_issues_for_service_since(&issue.service, Utc::now() - chrono::Duration::hours(24))
+                .await
+                .unwrap_or(0);
+            let verdict = self.policy.decide(&PolicyInput {
+                classification: &issue.classification,
+                service: &issue.service,
+                owner: issue.owner.as_deref(),
+                risk: &assessment,
+                files: &files,
+                hour_utc: Utc::now().hour(),
+                recent_failures,
+                project_opted_in: project.auto_apply,
+            });
+            let status = if verdict.decision == Decision::QueueReview {
+                info!(
+                    patch = %patch.id,
+                    score = assessment.score,
+                    reasons = ?assessment.reasons,
+                    "patch held for human review"
+                );
+                PatchStatus::PendingReview
+            } else {
+                PatchStatus::Validated
+            };
+            decision = Some(verdict);
+            status
+        } else {
+            PatchStatus::Rejected
+        };
+        patch.validation = Some(result);
+        patch.updated_at = Utc::now();
+        self.database.record_patch(&patch).await?;
+        self.metrics.observe_patch(patch.status.as_str());
+        info!(
+            patch = %patch.id,
+            issue = %patch.issue_id,
+            status = patch.status.as_str(),
+            "patch validated"
+        );
+        if patch.status == PatchStatus::PendingReview {
+            self.alerts
+                .send_email(
+                    &format!("pending-review/{}", project.id),
+                    "patch awaiting human review",
+                    &[
+                        ("patch", &patch.id.to_string()),
+                        ("issue", &patch.issue_id.to_string()),
+                        ("project", &project.id),
+                    ],
+                    &patch.description,
+                )
+                .await;
+        }
+        // A policy auto-apply verdict skips the manual apply step.
+        let auto_apply = allow_auto_apply
+            && decision
+                .as_ref()
+                .is_some_and(|d| d.decision == Decision::AutoApply);
+        if auto_apply && patch.status == PatchStatus::Validated {
+            if self.dry_run {
+                info!(patch = %patch.id, project = %project.id, "dry-run: auto-apply skipped");
+            } else {
+                info!(patch = %patch.id, project = %project.id, "auto-applying validated patch");
+                match self.apply_patch(patch.id).await {
+                    Ok(applied) => return Ok(applied),
+                    Err(e) => warn!(patch = %patch.id, "auto-apply failed: {e:#}"),
+                }
+            }
+        }
+        Ok(patch)
+    }
+
+    /// Run the complete pipeline for an issue — context building, patch
+    /// generation, sandbox validation — without applying anything, and
+    /// fold the outcome into one shareable report. The candidate patch is
+    /// recorded as usual, so a convincing simulation can still be applied
+    /// afterwards by patch id.
+    pub async fn simulate(&self, issue_id: Uuid) -> Result<crate::simulate::SimulationReport> {
+        let issue = self
+            .database
+            .issue_by_id(issue_id)
+            .await?
+            .with_context(|| format!("no issue {issue_id}"))?;
+        let patch = self.generate_patch(issue_id).await?;
+        // Auto-apply is suppressed even when policy would allow it; the
+        // whole point of a simulation is deciding before anything lands.
+        let patch = self.validate_patch_inner(patch.id, false).await?;
+        let breaking = self.dry_run_diff(&issue.project, &patch.diff).unwrap_or_default();
+        let risk = crate::review::assess(&patch.diff, &breaking, patch.validation.as_ref());
+        let cost_usd = self.database.cost_for_issue(issue_id).await?;
+        Ok(crate::simulate::SimulationReport::new(
+            &issue, &patch, risk, cost_usd,
+        ))
+    }
+
+    /// Restore a project's working tree to a recorded snapshot,
+    /// verifying the stored checksums against the restored bytes.
+    pub async fn restore_snapshot(&self, id: Uuid) -> Result<crate::snapshot::Snapshot> {
+        self.ensure_leader()?;
+        let mut snapshot = self
+            .database
+            .snapshot_by_id(id)
+            .await?
+            .with_context(|| format!("no snapshot {id}"))?;
+        if self.dry_run {
+            bail!("daemon is running with --dry-run; snapshot {id} was not restored");
+        }
+        let project = self.config.project(&snapshot.project);
+        crate::snapshot::restore(&project.path, &snapshot)?;
+        snapshot.restored_at = Some(Utc::now());
+        self.database.record_snapshot(&snapshot).await?;
+        info!(
+            snapshot = %snapshot.id,
+            project = %snapshot.project,
+            "workspace restored from snapshot"
+        );
+        Ok(snapshot)
+    }
+
+    /// Resolve the project a patch belongs to through its issue.
+    async fn project_for_patch(&self, patch: &Patch) -> Result<ProjectConfig> {
+        let issue = self
+            .database
+            .issue_by_id(patch.issue_id)
+            .await?
+            .with_context(|| format!("no issue {}", patch.issue_id))?;
+        Ok(self.config.project(&issue.project))
+    }
+
+    /// Record a human verdict on a patch in the review queue. Approval
+    /// makes the patch applicable; rejection is final.
+    pub async fn review_patch(
+        &self,
+        id: Uuid,
+        reviewer: &str,
+        verdict: ReviewVerdict,
+        comment: Option<String>,
+    ) -> Result<(Review, Patch)> {
+        let mut patch = self
+            .database
+            .patch_by_id(id)
+            .await?
+            .with_context(|| format!("no patch {id}"))?;
+        if patch.status != PatchStatus::PendingReview {
+            bail!(
+                "patch {id} is not awaiting review (status {})",
+                patch.status.as_str()
+            );
+        }
+        let review = Review::new(patch.id, reviewer, verdict, comment);
+        self.database.record_review(&review).await?;
+        patch.status = match verdict {
+            ReviewVerdict::Approved => PatchStatus::Validated,
+            ReviewVerdict::Rejected => PatchStatus::Rejected,
+        };
+        patch.updated_at = Utc::now();
+        self.database.record_patch(&patch).await?;
+        self.metrics.observe_patch(patch.status.as_str());
+        info!(
+            patch = %patch.id,
+            reviewer,
+            verdict = verdict.as_str(),
+            "review recorded"
+        );
+        Ok((review, patch))
+    }
+
+    /// Work out what applying a patch would do without doing any of it:
+    /// the files touched, the breaking changes, and the git operations an
+    /// actual apply would run. Read-only, so followers may serve it too.
+    pub async fn plan_apply(&self, id: Uuid) -> Result<ApplyPlan> {
+        let patch = self
+            .database
+            .patch_by_id(id)
+            .await?
+            .with_context(|| format!("no patch {id}"))?;
+        if patch.status == PatchStatus::Applied {
+            bail!("patch {id} is already applied");
+        }
+        let project = self.project_for_patch(&patch).await?;
+        let files = crate::patch_generator::parse(&patch.diff)?
+            .into_iter()
+            .map(|file| {
+                if file.is_creation() {
+                    file.new_path
+                } else {
+                    file.old_path
+                }
+            })
+            .collect::<Vec<_>>();
+        // Also verifies the diff still applies against the working tree.
+        let breaking_changes = self.dry_run_diff(&project.id, &patch.diff)?;
+        let short = &patch.id.to_string()[..8];
+        let (mode, steps) = match &self.config.pull_request {
+            Some(pr_config) => (
+                "pull_request",
+                vec![
+                    format!("commit the diff on branch self-heal/patch-{short}"),
+                    format!("push the branch to {}", pr_config.remote),
+                    format!("open a pull request against {}", pr_config.base_branch),
+                ],
+            ),
+            None => (
+                "commit",
+                vec![
+                    format!("keep the current HEAD on branch self-healing/backup-{short}"),
+                    format!(
+                        "apply the diff and commit it in {}",
+                        project.path.display()
+                    ),
+                    match &project.build_command {
+                        Some(custom) => format!("run the post-apply build: {custom}"),
+                        None => "run the post-apply build: cargo build --workspace".to_string(),
+                    },
+                    "revert the commit if the post-apply build fails".to_string(),
+                ],
+            ),
+        };
+        Ok(ApplyPlan {
+            patch_id: patch.id,
+            issue_id: patch.issue_id,
+            project: project.id,
+            mode,
+            files,
+            breaking_changes,
+            validated: patch.status == PatchStatus::Validated,
+            steps,
+        })
+    }
+
+    /// Synthesize a reproduction test scaffold for an issue on demand,
+    /// write it into the project tree (skipped under dry-run), and store
+    /// it in the test repository. `None` when the issue's log matches no
+    /// known failure shape.
+    pub async fn generate_test(&self, issue_id: Uuid) -> Result<Option<TestGenerationReport>> {
+        self.ensure_leader()?;
+        let issue = self
+            .database
+            .issue_by_id(issue_id)
+            .await?
+            .with_context(|| format!("no issue {issue_id}"))?;
+        let project = self.config.project(&issue.project);
+        let Some(test) = crate::test_gen::generate_from_failure(&project.path, &issue)? else {
+            return Ok(None);
+        };
+        let (test_case_id, new) = self
+            .test_repository()
+            .store(&issue.service, &issue.commit, Some(issue.id), &test.content)
+            .await?;
+        let written = if self.dry_run {
+            false
+        } else {
+            crate::test_gen::store(&project.path, &test)?
+        };
+        Ok(Some(TestGenerationReport {
+            test_case_id,
+            new,
+            written,
+            path: test.path,
+            content: test.content,
+        }))
+    }
+
+    /// Synthesize a cargo-fuzz harness for an issue whose failure
+    /// implicates a parser-like function, write it into the project tree
+    /// (skipped under dry-run), and seed its corpus with the failing
+    /// input. `None` when the issue's log names no fuzzable function.
+    pub async fn generate_fuzz_harness(
+        &self,
+        issue_id: Uuid,
+    ) -> Result<Option<FuzzGenerationReport>> {
+        self.ensure_leader()?;
+        let issue = self
+            .database
+            .issue_by_id(issue_id)
+            .await?
+            .with_context(|| format!("no issue {issue_id}"))?;
+        let project = self.config.project(&issue.project);
+        let Some(harness) = crate::fuzz_gen::generate_from_failure(&project.path, &issue)? else {
+            return Ok(None);
+        };
+        let written = if self.dry_run {
+            false
+        } else {
+            crate::fuzz_gen::store(&project.path, &harness)?
+        };
+        if written {
+            info!(issue = %issue.id, target = %harness.target_name, "fuzz harness written");
+        }
+        Ok(Some(FuzzGenerationReport {
+            target_name: harness.target_name,
+            function: harness.function,
+            written,
+            target_path: harness.target_path,
+            target_content: harness.target_content,
+            seed_path: harness.seed_path,
+        }))
+    }
+
+    /// File each crash artifact a fuzz run of the issue's harness left
+    /// behind as a new issue, deduplicated by log content so repeated
+    /// sweeps do not refile the same crash.
+    pub async fn ingest_fuzz_crashes(&self, issue_id: Uuid) -> Result<Vec<Issue>> {
+        self.ensure_leader()?;
+        let issue = self
+            .database
+            .issue_by_id(issue_id)
+            .await?
+            .with_context(|| format!("no issue {issue_id}"))?;
+        let project = self.config.project(&issue.project);
+        let harness = crate::fuzz_gen::generate_from_failure(&project.path, &issue)?
+            .with_context(|| format!("issue {issue_id} implicates no fuzzable function"))?;
+        let mut filed = Vec::new();
+        for artifact in crate::fuzz_gen::crash_artifacts(&project.path, &harness)? {
+            let log = crate::fuzz_gen::crash_log(&harness, &artifact);
+            if self.database.find_open_issue(&log).await?.is_some() {
+                continue;
+            }
+            let mut crash = Issue::new(
+                "fuzz",
+                &issue.service,
+                &issue.commit,
+                "fuzz-crash",
+                &log,
+                vec![harness.source_file.clone()],
+            );
+            crash.project = issue.project.clone();
+            filed.push(self.ingest_issue(crash).await?);
+        }
+        Ok(filed)
+    }
+
+    /// Run a stored test case once with `command` and append the outcome
+    /// to its execution history. The content is materialized to a scratch
+    /// file the command sees as `$TEST_CASE`; a non-zero exit means the
+    /// failure still reproduces.
+    pub async fn execute_test(&self, id: Uuid, command: String) -> Result<bool> {
+        self.ensure_leader()?;
+        let case = self
+            .database
+            .test_case_by_id(id)
+            .await?
+            .with_context(|| format!("no test case {id}"))?;
+        let still_failing = tokio::task::spawn_blocking(move || -> Result<bool> {
+            let dir = tempfile::tempdir()?;
+            candidate_still_fails(dir.path(), &command, &case.content)
+        })
+        .await??;
+        self.test_repository().record_run(id, still_failing).await?;
+        Ok(still_failing)
+    }
+
+    /// Shrink a stored test case with delta debugging, using `command` as
+    /// the failing-test oracle (same `$TEST_CASE` contract as
+    /// `execute_test`), and persist the minimized content.
+    pub async fn minimize_test(&self, id: Uuid, command: String) -> Result<TestCase> {
+        self.ensure_leader()?;
+        let case = self
+            .database
+            .test_case_by_id(id)
+            .await?
+            .with_context(|| format!("no test case {id}"))?;
+        let minimized = tokio::task::spawn_blocking(move || -> Result<String> {
+            let dir = tempfile::tempdir()?;
+            let state = dir.path().join("minimize.json");
+            let mut minimizer = crate::minimizer::TestCaseMinimizer::new(&case.content, &state)?;
+            let mut executor =
+                |candidate: &str| -> Result<bool> { candidate_still_fails(dir.path(), &command, candidate) };
+            minimizer.minimize(&mut executor)
+        })
+        .await??;
+        let repository = self.test_repository();
+        repository.mark_minimized(id, &minimized).await?;
+        // The minimizer only keeps candidates that reproduce, so the final
+        // content is a confirmed run.
+        repository.record_run(id, true).await?;
+        self.database
+            .test_case_by_id(id)
+            .await?
+            .with_context(|| format!("no test case {id}"))
+    }
+
+    /// Promote a minimized, reproduction-confirmed test case into the
+    /// target repository's regression suite under `tests/regression/`,
+    /// committed with its provenance in the header. In pull-request mode
+    /// the commit goes out on its own branch as a PR; otherwise it lands
+    /// on the current branch.
+    pub async fn promote_test(&self, id: Uuid) -> Result<TestPromotion> {
+        self.ensure_leader()?;
+        let case = self
+            .database
+            .test_case_by_id(id)
+            .await?
+            .with_context(|| format!("no test case {id}"))?;
+        if !case.minimized {
+            bail!("test case {id} is not minimized; shrink it before promoting");
+        }
+        let (runs, still_failing) = self.database.test_case_run_summary(id).await?;
+        if still_failing == 0 {
+            bail!("test case {id} has no recorded run that reproduces the failure");
+        }
+        if self.dry_run {
+            bail!("daemon is running with --dry-run; test case {id} was not promoted");
+        }
+        let issue = match case.issue_id {
+            Some(issue_id) => self.database.issue_by_id(issue_id).await?,
+            None => None,
+        };
+        let project_id = issue
+            .as_ref()
+            .map(|issue| issue.project.clone())
+            .unwrap_or_else(|| "default".to_string());
+        let project = self.config.project(&project_id);
+        let repo = project.path.as_path();
+        let short = &case.id.to_string()[..8];
+        let rel_path = PathBuf::from(REGRESSION_DIR).join(format!("repro_{short}.rs"));
+        let full = repo.join(&rel_path);
+        if full.exists() {
+            bail!(
+                "{} already exists; test case {id} looks promoted",
+                rel_path.display()
+            );
+        }
+        let content = format!("{}\n{}", promotion_header(&case, issue.as_ref()), case.content);
+        let message = format!(
+            "self-healing: promote regression test {}\n\nService: {} at commit {}\nMinimized from {} to {} lines",
+            case.id,
+            case.service,
+            case.origin_commit,
+            case.original_lines,
+            case.content.lines().count()
+        );
+        let rel = rel_path.to_string_lossy().to_string();
+
+        match self.config.pull_request.clone() {
+            Some(pr_config) => {
+                let branch = format!("self-heal/regression-{short}");
+                let current = self.git_capture(repo, &["rev-parse", "--abbrev-ref", "HEAD"])?;
+                let current = current.trim();
+                let staged = (|| -> Result<()> {
+                    self.git(repo, &["checkout", "-b", &branch])?;
+                    std::fs::create_dir_all(full.parent().expect("regression dir has a parent"))?;
+                    std::fs::write(&full, &content)?;
+                    self.git(repo, &["add", &rel])?;
+                    self.git(repo, &["commit", "-m", &message])?;
+                    self.push_review_branch(repo, &pr_config.remote, &branch)
+                })();
+                // Whatever happened on the review branch, come back off it.
+                let restore = self.git(repo, &["checkout", current]);
+                staged?;
+                restore?;
+                let url = crate::pull_request::open(
+                    &pr_config,
+                    &branch,
+                    &crate::pull_request::promotion_title(&case, issue.as_ref()),
+                    &crate::pull_request::promotion_body(&case, runs, still_failing),
+                )
+                .await?;
+                info!(test_case = %case.id, url, "regression test promoted as a pull request");
+                Ok(TestPromotion {
+                    test_case_id: id,
+                    path: rel_path,
+                    branch: Some(branch),
+                    pr_url: Some(url),
+                })
+            }
+            None => {
+                std::fs::create_dir_all(full.parent().expect("regression dir has a parent"))?;
+                std::fs::write(&full, &content)?;
+                self.git(repo, &["add", &rel])?;
+                self.git(repo, &["commit", "-m", &message])?;
+                info!(test_case = %case.id, path = %rel_path.display(), "regression test promoted");
+                Ok(TestPromotion {
+                    test_case_id: id,
+                    path: rel_path,
+                    branch: None,
+                    pr_url: None,
+                })
+            }
+        }
+    }
+
+    fn test_repository(&self) -> TestCaseRepository {
+        TestCaseRepository::new(self.database.clone())
+    }
+
+    /// Apply a patch's diff as a git commit. The previous HEAD is kept on
+    /// a backup branch, the reverse diff is stored for rollback, and a
+    /// failing post-apply build reverts the commit automatically.
+    pub async fn apply_patch(&self, id: Uuid) -> Result<Patch> {
+        self.ensure_leader()?;
+        let mut patch = self
+            .database
+            .patch_by_id(id)
+            .await?
+            .with_context(|| format!("no patch {id}"))?;
+        if patch.status == PatchStatus::Applied {
+            bail!("patch {id} is already applied");
+        }
+        if patch.status == PatchStatus::PendingReview {
+            bail!("patch {id} is awaiting human review and cannot be applied");
+        }
+        if self.dry_run {
+            let plan = self.plan_apply(id).await?;
+            info!(plan = %serde_json::to_string(&plan)?, "dry-run: apply suppressed");
+            bail!("daemon is running with --dry-run; patch {id} was planned but not applied");
+        }
+        if let Some(pr_config) = self.config.pull_request.clone() {
+            return self.open_pull_request(patch, &pr_config).await;
+        }
+        let project = self.project_for_patch(&patch).await?;
+        let repo = project.path.as_path();
+        let backup = format!("self-healing/backup-{}", &patch.id.to_string()[..8]);
+        self.git(repo, &["branch", "-f", &backup, "HEAD"])?;
+        // Snapshot the whole worktree — including dirt a commit-based
+        // backup would miss — before anything lands; a failed snapshot
+        // fails the apply, because without one the action is not
+        // reversible.
+        let mut snapshot = crate::snapshot::take(
+            repo,
+            &project.id,
+            &format!("pre-apply patch {}", patch.id),
+        )
+        .context("workspace snapshot failed; refusing to apply")?;
+        snapshot.patch_id = Some(patch.id);
+        self.database.record_snapshot(&snapshot).await?;
+        info!(patch = %patch.id, snapshot = %snapshot.id, "workspace snapshot taken");
+        self.git_apply(repo, &patch.diff, false)?;
+        self.git(repo, &["add", "-A"])?;
+        let message = format!(
+            "self-healing: apply patch {}\n\nIssue: {}\nBackup-Branch: {}\nDescription: {}",
+            patch.id, patch.issue_id, backup, patch.description
+        );
+        self.git(repo, &["commit", "-m", &message])?;
+        // Diffing new-to-old yields the reverse diff directly.
+        patch.rollback_diff = Some(self.git_capture(repo, &["diff", "HEAD", "HEAD~1"])?);
+
+        let build = self.post_apply_build(&project)?;
+        if !build.success {
+            warn!(
+                patch = %patch.id,
+                "post-apply build failed, reverting:\n{}",
+                build.log
+            );
+            self.git(repo, &["revert", "--no-edit", "HEAD"])?;
+            patch.status = PatchStatus::RolledBack;
+            patch.updated_at = Utc::now();
+            self.database.record_patch(&patch).await?;
+            self.metrics.observe_patch(patch.status.as_str());
+            self.alerts
+                .send_email(
+                    &format!("post-apply-revert/{}", project.id),
+                    "applied patch broke the build and was reverted",
+                    &[
+                        ("patch", &patch.id.to_string()),
+                        ("issue", &patch.issue_id.to_string()),
+                        ("project", &project.id),
+                    ],
+                    &build.log,
+                )
+                .await;
+            bail!("patch {id} broke the build after apply and was reverted");
+        }
+
+        patch.status = PatchStatus::Applied;
+        patch.updated_at = Utc::now();
+        self.publish_changelog(&mut patch, &project).await;
+        self.database.record_patch(&patch).await?;
+        self.metrics.observe_patch(patch.status.as_str());
+        info!(patch = %patch.id, issue = %patch.issue_id, backup, "patch applied and committed");
+        self.push_working_branch(&patch, &project).await;
+        Ok(patch)
+    }
+
+    /// Push the working branch after an apply. Best-effort like the
+    /// changelog: the patch is applied and recorded either way, and a
+    /// rejected push is an operator problem (diverged remote, protected
+    /// branch), not grounds to roll the commit back.
+    async fn push_working_branch(&self, patch: &Patch, project: &ProjectConfig) {
+        let Some(git_ops) = &self.git_ops else {
+            return;
+        };
+        let repo = project.path.as_path();
+        let branch = match self.git_capture(repo, &["rev-parse", "--abbrev-ref", "HEAD"]) {
+            Ok(branch) => branch.trim().to_string(),
+            Err(e) => {
+                warn!(patch = %patch.id, "push skipped, current branch unknown: {e:#}");
+                return;
+            }
+        };
+        match git_ops.push(repo, &branch, false) {
+            Ok(()) => info!(patch = %patch.id, branch, "pushed applied patch"),
+            Err(e) => {
+                warn!(patch = %patch.id, branch, "push after apply failed: {e:#}");
+                self.alerts
+                    .send_email(
+                        &format!("push-failed/{}", project.id),
+                        "applied patch could not be pushed",
+                        &[
+                            ("patch", &patch.id.to_string()),
+                            ("project", &project.id),
+                            ("branch", &branch),
+                        ],
+                        &format!("{e:#}"),
+                    )
+                    .await;
+            }
+        }
+    }
+
+    /// Generate the changelog entry for an applied patch, store it on the
+    /// patch, and append or announce it as configured. Best-effort: no
+    /// failure here can un-apply the patch.
+    async fn publish_changelog(&self, patch: &mut Patch, project: &ProjectConfig) {
+        let issue = match self.database.issue_by_id(patch.issue_id).await {
+            Ok(Some(issue)) => issue,
+            Ok(None) => return,
+            Err(e) => {
+                warn!(patch = %patch.id, "changelog skipped: {e:#}");
+                return;
+            }
+        };
+        let explanation = crate::changelog::explain_issue(self.llm.as_ref(), &issue, patch).await;
+        let entry = crate::changelog::render_entry(&issue, patch, &explanation);
+        if let Some(config) = &self.config.changelog {
+            if let Some(file) = &config.file {
+                let path = project.path.join(file);
+                // The append lands as its own commit so the patch commit
+                // (and its rollback diff) stay exactly what was validated.
+                let committed = crate::changelog::append(&path, &entry)
+                    .and_then(|()| self.git(&project.path, &["add", &path.to_string_lossy()]))
+                    .and_then(|()| {
+                        self.git(&project.path, &[
+                            "commit",
+                            "-m",
+                            &format!("self-healing: changelog for patch {}", patch.id),
+                        ])
+                    });
+                if let Err(e) = committed {
+                    warn!(patch = %patch.id, "changelog append failed: {e:#}");
+                }
+            }
+            if config.announce {
+                self.alerts
+                    .send_email(
+                        &format!("changelog/{}", project.id),
+                        "patch applied",
+                        &[
+                            ("patch", &patch.id.to_string()),
+                            ("issue", &issue.id.to_string()),
+                            ("project", &project.id),
+                        ],
+                        &entry,
+                    )
+                    .await;
+            }
+        }
+        patch.changelog = Some(entry);
+    }
+
+    /// Commit the patch on a `self-heal/...` branch, push it, and open a
+    /// pull request, leaving the working branch untouched. The patch ends
+    /// up in review rather than applied.
+    async fn open_pull_request(
+        &self,
+        mut patch: Patch,
+        pr_config: &crate::config::PullRequestConfig,
+    ) -> Result<Patch> {
+        if patch.status == PatchStatus::InReview {
+            bail!("patch {} already has a pull request open", patch.id);
+        }
+        let issue = self
+            .database
+            .issue_by_id(patch.issue_id)
+            .await?
+            .with_context(|| format!("no issue {}", patch.issue_id))?;
+        let project = self.config.project(&issue.project);
+        let repo = project.path.as_path();
+        let branch = format!("self-heal/patch-{}", &patch.id.to_string()[..8]);
+        let current = self.git_capture(repo, &["rev-parse", "--abbrev-ref", "HEAD"])?;
+        let current = current.trim();
+
+        let staged = (|| {
+            self.git(repo, &["checkout", "-b", &branch])?;
+            self.git_apply(repo, &patch.diff, false)?;
+            self.git(repo, &["add", "-A"])?;
+            self.git(repo, &[
+                "commit",
+                "-m",
+                &format!(
+                    "self-healing: propose patch {}\n\nIssue: {}\nDescription: {}",
+                    patch.id, patch.issue_id, patch.description
+                ),
+            ])?;
+            self.push_review_branch(repo, &pr_config.remote, &branch)
+        })();
+        // Whatever happened on the review branch, come back off it.
+        let restore = self.git(repo, &["checkout", current]);
+        staged?;
+        restore?;
+
+        let url = crate::pull_request::open(
+            pr_config,
+            &branch,
+            &crate::pull_request::title_for(&issue),
+            &crate::pull_request::body_for(&patch, &issue),
+        )
+        .await?;
+        patch.status = PatchStatus::InReview;
+        patch.pr_url = Some(url.clone());
+        patch.updated_at = Utc::now();
+        self.database.record_patch(&patch).await?;
+        self.metrics.observe_patch(patch.status.as_str());
+        info!(patch = %patch.id, issue = %patch.issue_id, url, "pull request opened");
+        Ok(patch)
+    }
+
+    /// Merge a reviewed patch's self-heal branch back into the pull
+    /// request base branch, push the result when pushing is configured,
+    /// and mark the patch applied. A conflicted merge leaves the
+    /// repository clean and the patch in review; the returned outcome
+    /// carries the conflicting files.
+    pub async fn merge_patch_branch(&self, id: Uuid) -> Result<crate::git_ops::MergeOutcome> {
+        self.ensure_leader()?;
+        let mut patch = self
+            .database
+            .patch_by_id(id)
+            .await?
+            .with_context(|| format!("no patch {id}"))?;
+        if patch.status != PatchStatus::InReview {
+            bail!(
+                "patch {id} has no review branch to merge (status {})",
+                patch.status.as_str()
+            );
+        }
+        if self.dry_run {
+            bail!("daemon is running with --dry-run; patch {id} was not merged");
+        }
+        let Some(pr_config) = self.config.pull_request.clone() else {
+            bail!("merge-back needs pull-request mode; patches are otherwise committed directly");
+        };
+        let project = self.project_for_patch(&patch).await?;
+        let repo = project.path.as_path();
+        let branch = format!("self-heal/patch-{}", &patch.id.to_string()[..8]);
+        let outcome = crate::git_ops::merge_patch_branch(repo, &branch, &pr_config.base_branch)?;
+        match &outcome {
+            crate::git_ops::MergeOutcome::Merged { commit } => {
+                if let Some(git_ops) = &self.git_ops {
+                    if let Err(e) = git_ops.push(repo, &pr_config.base_branch, false) {
+                        warn!(patch = %patch.id, "push of the merged base branch failed: {e:#}");
+                    }
+                }
+                patch.status = PatchStatus::Applied;
+                patch.updated_at = Utc::now();
+                self.database.record_patch(&patch).await?;
+                self.metrics.observe_patch(patch.status.as_str());
+                info!(
+                    patch = %patch.id,
+                    commit,
+                    base = %pr_config.base_branch,
+                    "review branch merged back"
+                );
+            }
+            crate::git_ops::MergeOutcome::Conflicted { files } => {
+                info!(
+                    patch = %patch.id,
+                    files = ?files,
+                    "merge-back conflicted; base branch left untouched"
+                );
+            }
+        }
+        Ok(outcome)
+    }
+
+    /// Revert a previously applied patch, preferring the reverse diff
+    /// captured at apply time over re-deriving it from the forward diff.
+    pub async fn rollback_patch(&self, id: Uuid) -> Result<Patch> {
+        self.ensure_leader()?;
+        let mut patch = self
+            .database
+            .patch_by_id(id)
+            .await?
+            .with_context(|| format!("no patch {id}"))?;
+        if patch.status != PatchStatus::Applied {
+            bail!("patch {id} is not applied (status {})", patch.status.as_str());
+        }
+        if self.dry_run {
+            bail!("daemon is running with --dry-run; patch {id} was not rolled back");
+        }
+        let project = self.project_for_patch(&patch).await?;
+        let repo = project.path.as_path();
+        match &patch.rollback_diff {
+            Some(rollback) => {
+                self.git_apply(repo, rollback, false)?;
+                self.git(repo, &["add", "-A"])?;
+                self.git(repo, &[
+                    "commit",
+                    "-m",
+                    &format!(
+                        "self-healing: roll back patch {}\n\nIssue: {}",
+                        patch.id, patch.issue_id
+                    ),
+                ])?;
+            }
+            // Patches applied before reverse diffs were captured.
+            None => self.git_apply(repo, &patch.diff, true)?,
+        }
+        patch.status = PatchStatus::RolledBack;
+        patch.updated_at = Utc::now();
+        self.database.record_patch(&patch).await?;
+        self.metrics.observe_patch(patch.status.as_str());
+        info!(patch = %patch.id, issue = %patch.issue_id, "patch rolled back");
+        Ok(patch)
+    }
+
+    /// Build the project in place right after a patch lands, bounded by
+    /// the validation timeout so a wedged build cannot hold the commit.
+    fn post_apply_build(&self, project: &ProjectConfig) -> Result<crate::validator::RunOutcome> {
+        let mut command = match &project.build_command {
+            Some(custom) => {
+                let mut command = Command::new("sh");
+                command.args(["-c", custom]);
+                command
+            }
+            None => {
+                let mut command = Command::new("cargo");
+                command.args(["build", "--workspace"]);
+                command
+            }
+        };
+        let child = command
+            .current_dir(&project.path)
+            .stdout(std::process::Stdio::piped())
+            .stderr(std::process::Stdio::piped())
+            .spawn()
+            .context("failed to invoke the post-apply build")?;
+        crate::validator::collect_with_timeout(
+            child,
+            std::time::Duration::from_secs(self.config.validation.timeout_secs),
+        )
+    }
+
+    /// Force-push a self-heal review branch, with the configured push
+    /// credentials when there are any. The pull-request remote wins over
+    /// the push remote so the branch lands where the PR is opened.
+    fn push_review_branch(&self, repo: &Path, remote: &str, branch: &str) -> Result<()> {
+        match &self.git_ops {
+            Some(git_ops) => git_ops.push_to(repo, remote, branch, true),
+            None => self.git(repo, &["push", "-f", remote, branch]),
+        }
+    }
+
+    /// Run a git command in a project repository, surfacing stderr on
+    /// failure.
+    fn git(&self, dir: &Path, args: &[&str]) -> Result<()> {
+        let output = Command::new("git")
+            .args(args)
+            .current_dir(dir)
+            .output()
+            .context("failed to invoke git")?;
+        if !output.status.success() {
+            bail!(
+                "git {} failed: {}",
+                args.first().unwrap_or(&""),
+                String::from_utf8_lossy(&output.stderr).trim()
+            );
+        }
+        Ok(())
+    }
+
+    /// Run a git command and return its stdout.
+    fn git_capture(&self, dir: &Path, args: &[&str]) -> Result<String> {
+        let output = Command::new("git")
+            .args(args)
+            .current_dir(dir)
+            .output()
+            .context("failed to invoke git")?;
+        if !output.status.success() {
+            bail!(
+                "git {} failed: {}",
+                args.first().unwrap_or(&""),
+                String::from_utf8_lossy(&output.stderr).trim()
+            );
+        }
+        Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+    }
+
+    /// Parse a diff and apply it in memory against the current working
+    /// tree without touching any file, so malformed or conflicting patches
+    /// are rejected at submission time rather than at apply time. Returns
+    /// any breaking API changes found in the Rust files the diff touches.
+    pub fn dry_run_diff(&self, project: &str, diff: &str) -> Result<Vec<BreakingChange>> {
+        let root = self.config.project(project).path;
+        let mut breaking = Vec::new();
+        for file in crate::patch_generator::parse(diff)? {
+            if file.is_creation() {
+                continue;
+            }
+            let path = root.join(&file.old_path);
+            let original = std::fs::read_to_string(&path)
+                .with_context(|| format!("cannot read {}", path.display()))?;
+            let patched = crate::patch_generator::apply_diff(&original, &file)?;
+            if file.old_path.ends_with(".rs") {
+                match crate::breaking_changes::identify_breaking_changes(&original, &patched) {
+                    Ok(found) => breaking.extend(found.into_iter().map(|mut c| {
+                        c.file = file.old_path.clone();
+                        c
+                    })),
+                    // A file that doesn't parse (e.g. mid-refactor) is not
+                    // this check's problem; the build will say so louder.
+                    Err(e) => warn!("breaking-change analysis skipped for {}: {e:#}", file.old_path),
+                }
+            }
+        }
+        Ok(breaking)
+    }
+
+    /// `git apply` (or `git apply -R`) the diff via stdin, checking first
+    /// so a non-applying patch leaves the tree untouched.
+    fn git_apply(&self, dir: &Path, diff: &str, reverse: bool) -> Result<()> {
+        use std::io::Write;
+        for check in [true, false] {
+            let mut args = vec!["apply"];
+            if reverse {
+                args.push("-R");
+            }
+            if check {
+                args.push("--check");
+            }
+            let mut child = Command::new("git")
+                .args(&args)
+                .current_dir(dir)
+                .stdin(std::process::Stdio::piped())
+                .stderr(std::process::Stdio::piped())
+                .spawn()
+                .context("failed to invoke git apply")?;
+            child
+                .stdin
+                .take()
+                .expect("stdin was piped")
+                .write_all(diff.as_bytes())?;
+            let output = child.wait_with_output()?;
+            if !output.status.success() {
+                bail!(
+                    "git apply{} failed: {}",
+                    if reverse { " -R" } else { "" },
+                    String::from_utf8_lossy(&output.stderr).trim()
+                );
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Map a repo-relative file path to the service it belongs to, falling back
+/// to "workspace" for shared code.
+fn service_for_path(path: &str) -> String {
+    let mut parts = path.split('/');
+    if parts.next() == Some("services") {
+        if let Some(service) = parts.next() {
+            return service.to_string();
+        }
+    }
+    "workspace".to_string()
+}
+
+/// Provenance header prepended to a promoted regression test, so the
+/// file explains itself long after the issue is archived.
+fn promotion_header(case: &TestCase, issue: Option<&Issue>) -> String {
+    let mut header = format!(
+        "//! Regression test promoted by the self-healing system.\n//!\n//! Test case {} reproduces a failure in service {} at commit {}.\n",
+        case.id, case.service, case.origin_commit
+    );
+    if let Some(issue) = issue {
+        header.push_str(&format!(
+            "//! Issue {}: {} failure reported by {}.\n",
+            issue.id, issue.classification, issue.source
+        ));
+    }
+    header.push_str(&format!(
+        "//! Minimized from {} to {} lines.\n",
+        case.original_lines,
+        case.content.lines().count()
+    ));
+    header
+}
+
+/// Write `candidate` into a scratch file and run `command` with `sh -c`,
+/// exposing the file as `$TEST_CASE`. A non-zero exit means the candidate
+/// still reproduces the failure under study.
+fn candidate_still_fails(dir: &Path, command: &str, candidate: &str) -> Result<bool> {
+    let path = dir.join("candidate");
+    std::fs::write(&path, candidate)?;
+    let status = Command::new("sh")
+        .args(["-c", command])
+        .env("TEST_CASE", &path)
+        .status()
+        .context("failed to run the test command")?;
+    Ok(!status.success())
+}
@@ -0,0 +1,1024 @@
+//! Persistence for issues and patches.
+//!
+//! The schema lives in `./migrations` and runs through sqlx's migrator on
+//! startup. The backend is picked at runtime from the connection URL via
+//! sqlx's Any driver: SQLite for development, Postgres for production
+//! deployments built with the `postgres` feature. Queries use `$n`
+//! placeholders and the shared `ON CONFLICT` upsert form, both of which
+//! work on either backend.
+
+use crate::costs::{CostEntry, DayCost, IssueCost};
+use crate::snapshot::Snapshot;
+use crate::test_repo::{ServiceCount, TestCase, TestCaseRun, TestCaseStats};
+use crate::types::{Issue, IssueStatus, Patch, PatchStatus, Review, ReviewVerdict};
+use serde::Serialize;
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use sqlx::any::AnyPoolOptions;
+use sqlx::{AnyPool, Row};
+use std::path::Path;
+use uuid::Uuid;
+
+/// Register the compiled-in sqlx drivers exactly once per process.
+fn install_drivers() {
+    static ONCE: std::sync::Once = std::sync::Once::new();
+    ONCE.call_once(sqlx::any::install_default_drivers);
+}
+
+#[derive(Clone)]
+pub struct Database {
+    pool: AnyPool,
+}
+
+/// Review outcomes aggregated per issue classification.
+#[derive(Debug, Serialize)]
+pub struct ReviewStat {
+    pub classification: String,
+    pub approved: i64,
+    pub rejected: i64,
+}
+
+impl Database {
+    /// Open the SQLite database at `path`, creating it when missing.
+    pub async fn open(path: &Path) -> Result<Self> {
+        Self::connect(&format!("sqlite://{}?mode=rwc", path.display())).await
+    }
+
+    /// Connect to the database behind `url` ("sqlite://..." or, with the
+    /// `postgres` feature, "postgres://...") and bring the schema up to
+    /// date.
+    pub async fn connect(url: &str) -> Result<Self> {
+        install_drivers();
+        let pool = AnyPoolOptions::new()
+            .max_connections(8)
+            .connect(url)
+            .await
+            .with_context(|| format!("failed to open database at {url}"))?;
+        let db = Self { pool };
+        db.migrate().await?;
+        Ok(db)
+    }
+
+    /// In-memory database used by tests.
+    #[cfg(test)]
+    pub async fn open_in_memory() -> Result<Self> {
+        install_drivers();
+        let pool = AnyPoolOptions::new()
+            .max_connections(1)
+            .connect("sqlite::memory:")
+            .await?;
+        let db = Self { pool };
+        db.migrate().await?;
+        Ok(db)
+    }
+
+    async fn migrate(&self) -> Result<()> {
+        sqlx::migrate!("./migrations")
+            .run(&self.pool)
+            .await
+            .context("database migration failed")?;
+        // Databases initialized before the migration set existed already
+        // have the tables (so the initial migration's IF NOT EXISTS is a
+        // no-op) but may lack later columns; the error on databases that
+        // already have them is expected.
+        for ddl in [
+            "ALTER TABLE patches ADD COLUMN rollback_diff TEXT",
+            "ALTER TABLE patches ADD COLUMN pr_url TEXT",
+            "ALTER TABLE issues ADD COLUMN project TEXT NOT NULL DEFAULT 'default'",
+            "ALTER TABLE patches ADD COLUMN origin TEXT NOT NULL DEFAULT 'manual'",
+        ] {
+            let _ = sqlx::raw_sql(ddl).execute(&self.pool).await;
+        }
+        Ok(())
+    }
+
+    pub async fn record_issue(&self, issue: &Issue) -> Result<()> {
+        sqlx::query(
+            r#"
+            INSERT INTO issues (id, source, project, service, commit_sha, classification, log, affected_files, owner, status, created_at, updated_at)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12)
+            ON CONFLICT(id) DO UPDATE SET owner = excluded.owner, status = excluded.status, updated_at = excluded.updated_at
+            "#,
+        )
+        .bind(issue.id.to_string())
+        .bind(&issue.source)
+        .bind(&issue.project)
+        .bind(&issue.service)
+        .bind(&issue.commit)
+        .bind(&issue.classification)
+        .bind(&issue.log)
+        .bind(serde_json::to_string(&issue.affected_files)?)
+        .bind(&issue.owner)
+        .bind(issue.status.as_str())
+        .bind(issue.created_at.to_rfc3339())
+        .bind(issue.updated_at.to_rfc3339())
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    pub async fn issues(
+        &self,
+        status: Option<IssueStatus>,
+        project: Option<&str>,
+        limit: i64,
+    ) -> Result<Vec<Issue>> {
+        let rows = sqlx::query(
+            r#"
+            SELECT * FROM issues
+            WHERE ($1 IS NULL OR status = $1) AND ($2 IS NULL OR project = $2)
+            ORDER BY created_at DESC LIMIT $3
+            "#,
+        )
+        .bind(status.map(|s| s.as_str()))
+        .bind(project)
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await?;
+        rows.iter().map(row_to_issue).collect()
+    }
+
+    pub async fn issue_by_id(&self, id: Uuid) -> Result<Option<Issue>> {
+        let row = sqlx::query("SELECT * FROM issues WHERE id = $1")
+            .bind(id.to_string())
+            .fetch_optional(&self.pool)
+            .await?;
+        row.as_ref().map(row_to_issue).transpose()
+    }
+
+    /// Look up an unresolved issue with the same log, so analysis reruns do
+    /// not file duplicates.
+    pub async fn find_open_issue(&self, log: &str) -> Result<Option<Issue>> {
+        let row = sqlx::query(
+            "SELECT * FROM issues WHERE log = $1 AND status IN ('open', 'patching') LIMIT 1",
+        )
+        .bind(log)
+        .fetch_optional(&self.pool)
+        .await?;
+        row.as_ref().map(row_to_issue).transpose()
+    }
+
+    pub async fn count_issues(&self, status: IssueStatus) -> Result<i64> {
+        let row = sqlx::query("SELECT COUNT(*) AS n FROM issues WHERE status = $1")
+            .bind(status.as_str())
+            .fetch_one(&self.pool)
+            .await?;
+        Ok(row.get("n"))
+    }
+
+    /// Open issues grouped by service; the scheduler reads each count as
+    /// that service's blast radius.
+    pub async fn open_issue_counts_by_service(&self) -> Result<Vec<(String, i64)>> {
+        let rows =
+            sqlx::query("SELECT service, COUNT(*) AS n FROM issues WHERE status = $1 GROUP BY service")
+                .bind(IssueStatus::Open.as_str())
+                .fetch_all(&self.pool)
+                .await?;
+        Ok(rows
+            .iter()
+            .map(|r| (r.get("service"), r.get("n")))
+            .collect())
+    }
+
+    /// Issues a service filed since `since`, feeding the policy engine's
+    /// recent-failure-rate rules.
+    pub async fn count_issues_for_service_since(
+        &self,
+        service: &str,
+        since: DateTime<Utc>,
+    ) -> Result<i64> {
+        let row =
+            sqlx::query("SELECT COUNT(*) AS n FROM issues WHERE service = $1 AND created_at >= $2")
+                .bind(service)
+                .bind(since.to_rfc3339())
+                .fetch_one(&self.pool)
+                .await?;
+        Ok(row.get("n"))
+    }
+
+    pub async fn record_patch(&self, patch: &Patch) -> Result<()> {
+        sqlx::query(
+            r#"
+            INSERT INTO patches (id, issue_id, description, origin, diff, rollback_diff, pr_url, status, validation, changelog, created_at, updated_at)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12)
+            ON CONFLICT(id) DO UPDATE SET
+                rollback_diff = excluded.rollback_diff,
+                pr_url = excluded.pr_url,
+                status = excluded.status,
+                validation = excluded.validation,
+                changelog = excluded.changelog,
+                updated_at = excluded.updated_at
+            "#,
+        )
+        .bind(patch.id.to_string())
+        .bind(patch.issue_id.to_string())
+        .bind(&patch.description)
+        .bind(&patch.origin)
+        .bind(&patch.diff)
+        .bind(&patch.rollback_diff)
+        .bind(&patch.pr_url)
+        .bind(patch.status.as_str())
+        .bind(
+            patch
+                .validation
+                .as_ref()
+                .map(serde_json::to_string)
+                .transpose()?,
+        )
+        .bind(&patch.changelog)
+        .bind(patch.created_at.to_rfc3339())
+        .bind(patch.updated_at.to_rfc3339())
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    pub async fn patches_for_issue(&self, issue_id: Uuid) -> Result<Vec<Patch>> {
+        let rows = sqlx::query(
+            "SELECT * FROM patches WHERE issue_id = $1 ORDER BY created_at DESC",
+        )
+        .bind(issue_id.to_string())
+        .fetch_all(&self.pool)
+        .await?;
+        rows.iter().map(row_to_patch).collect()
+    }
+
+    pub async fn patch_by_id(&self, id: Uuid) -> Result<Option<Patch>> {
+        let row = sqlx::query("SELECT * FROM patches WHERE id = $1")
+            .bind(id.to_string())
+            .fetch_optional(&self.pool)
+            .await?;
+        row.as_ref().map(row_to_patch).transpose()
+    }
+
+    pub async fn patches_by_status(&self, status: PatchStatus, limit: i64) -> Result<Vec<Patch>> {
+        let rows = sqlx::query(
+            "SELECT * FROM patches WHERE status = $1 ORDER BY created_at DESC LIMIT $2",
+        )
+        .bind(status.as_str())
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await?;
+        rows.iter().map(row_to_patch).collect()
+    }
+
+    pub async fn record_review(&self, review: &Review) -> Result<()> {
+        sqlx::query(
+            r#"
+            INSERT INTO reviews (id, patch_id, reviewer, verdict, comment, created_at)
+            VALUES ($1, $2, $3, $4, $5, $6)
+            "#,
+        )
+        .bind(review.id.to_string())
+        .bind(review.patch_id.to_string())
+        .bind(&review.reviewer)
+        .bind(review.verdict.as_str())
+        .bind(&review.comment)
+        .bind(review.created_at.to_rfc3339())
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    /// Approval and rejection counts per issue classification, the raw
+    /// data for calibrating how much generated patches are trusted.
+    pub async fn review_stats(&self) -> Result<Vec<ReviewStat>> {
+        let rows = sqlx::query(
+            r#"
+            SELECT i.classification AS classification,
+                   SUM(CASE WHEN r.verdict = 'approved' THEN 1 ELSE 0 END) AS approved,
+                   SUM(CASE WHEN r.verdict = 'rejected' THEN 1 ELSE 0 END) AS rejected
+            FROM reviews r
+            JOIN patches p ON p.id = r.patch_id
+            JOIN issues i ON i.id = p.issue_id
+            GROUP BY i.classification
+            ORDER BY i.classification
+            "#,
+        )
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(rows
+            .iter()
+            .map(|row| ReviewStat {
+                classification: row.get("classification"),
+                approved: row.get("approved"),
+                rejected: row.get("rejected"),
+            })
+            .collect())
+    }
+
+    /// Issues in `status` untouched since `cutoff`, oldest first, for the
+    /// retention sweep.
+    pub async fn issues_archivable(
+        &self,
+        status: IssueStatus,
+        cutoff: &str,
+        limit: i64,
+    ) -> Result<Vec<Issue>> {
+        let rows = sqlx::query(
+            "SELECT * FROM issues WHERE status = $1 AND updated_at < $2 ORDER BY updated_at LIMIT $3",
+        )
+        .bind(status.as_str())
+        .bind(cutoff)
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await?;
+        rows.iter().map(row_to_issue).collect()
+    }
+
+    /// Patches in `status` untouched since `cutoff`, oldest first.
+    pub async fn patches_archivable(
+        &self,
+        status: PatchStatus,
+        cutoff: &str,
+        limit: i64,
+    ) -> Result<Vec<Patch>> {
+        let rows = sqlx::query(
+            "SELECT * FROM patches WHERE status = $1 AND updated_at < $2 ORDER BY updated_at LIMIT $3",
+        )
+        .bind(status.as_str())
+        .bind(cutoff)
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await?;
+        rows.iter().map(row_to_patch).collect()
+    }
+
+    /// All reviews recorded on an issue's patches.
+    pub async fn reviews_for_issue(&self, issue_id: Uuid) -> Result<Vec<Review>> {
+        let rows = sqlx::query(
+            r#"
+            SELECT r.* FROM reviews r
+            JOIN patches p ON p.id = r.patch_id
+            WHERE p.issue_id = $1 ORDER BY r.created_at
+            "#,
+        )
+        .bind(issue_id.to_string())
+        .fetch_all(&self.pool)
+        .await?;
+        rows.iter().map(row_to_review).collect()
+    }
+
+    /// Store a record in the archive; re-archiving the same id refreshes
+    /// the payload rather than failing.
+    pub async fn archive_record(
+        &self,
+        kind: &str,
+        id: Uuid,
+        project: &str,
+        status: &str,
+        payload: &str,
+    ) -> Result<()> {
+        sqlx::query(
+            r#"
+            INSERT INTO archive (id, kind, project, status, payload, archived_at)
+            VALUES ($1, $2, $3, $4, $5, $6)
+            ON CONFLICT(id) DO UPDATE SET payload = excluded.payload, archived_at = excluded.archived_at
+            "#,
+        )
+        .bind(id.to_string())
+        .bind(kind)
+        .bind(project)
+        .bind(status)
+        .bind(payload)
+        .bind(Utc::now().to_rfc3339())
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    /// Archived payloads at or after `since` (all of them when `None`),
+    /// oldest first, as (kind, archived_at, payload).
+    pub async fn archived_records(
+        &self,
+        since: Option<&str>,
+    ) -> Result<Vec<(String, String, String)>> {
+        let rows = sqlx::query(
+            r#"
+            SELECT kind, archived_at, payload FROM archive
+            WHERE ($1 IS NULL OR archived_at >= $1) ORDER BY archived_at
+            "#,
+        )
+        .bind(since)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(rows
+            .iter()
+            .map(|row| (row.get("kind"), row.get("archived_at"), row.get("payload")))
+            .collect())
+    }
+
+    /// Delete an issue with its patches and their reviews.
+    pub async fn purge_issue(&self, issue_id: Uuid) -> Result<()> {
+        let id = issue_id.to_string();
+        sqlx::query(
+            "DELETE FROM reviews WHERE patch_id IN (SELECT id FROM patches WHERE issue_id = $1)",
+        )
+        .bind(&id)
+        .execute(&self.pool)
+        .await?;
+        sqlx::query("DELETE FROM patches WHERE issue_id = $1")
+            .bind(&id)
+            .execute(&self.pool)
+            .await?;
+        sqlx::query("DELETE FROM issues WHERE id = $1")
+            .bind(&id)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    /// Delete a single patch and its reviews.
+    pub async fn purge_patch(&self, patch_id: Uuid) -> Result<()> {
+        let id = patch_id.to_string();
+        sqlx::query("DELETE FROM reviews WHERE patch_id = $1")
+            .bind(&id)
+            .execute(&self.pool)
+            .await?;
+        sqlx::query("DELETE FROM patches WHERE id = $1")
+            .bind(&id)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    /// Take or renew the named lease for `holder`. Succeeds when the lease
+    /// is free, expired, or already ours; one statement, so two replicas
+    /// racing resolve on the database's conflict handling.
+    pub async fn try_acquire_lease(&self, name: &str, holder: &str, ttl_secs: u64) -> Result<bool> {
+        let now = Utc::now();
+        let expires_at = (now + chrono::Duration::seconds(ttl_secs as i64)).to_rfc3339();
+        let result = sqlx::query(
+            r#"
+            INSERT INTO leases (name, holder, expires_at) VALUES ($1, $2, $3)
+            ON CONFLICT(name) DO UPDATE SET holder = excluded.holder, expires_at = excluded.expires_at
+            WHERE leases.holder = excluded.holder OR leases.expires_at < $4
+            "#,
+        )
+        .bind(name)
+        .bind(holder)
+        .bind(expires_at)
+        .bind(now.to_rfc3339())
+        .execute(&self.pool)
+        .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Give the lease up early (clean shutdown), so a standby can take
+    /// over without waiting for expiry. Only deletes our own row.
+    pub async fn release_lease(&self, name: &str, holder: &str) -> Result<()> {
+        sqlx::query("DELETE FROM leases WHERE name = $1 AND holder = $2")
+            .bind(name)
+            .bind(holder)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    /// A cached LLM response younger than `ttl_secs`, as `(provider,
+    /// response)`. Stale rows are left for `purge_llm_cache` to reap.
+    pub async fn llm_cache_get(&self, key: &str, ttl_secs: u64) -> Result<Option<(String, String)>> {
+        let cutoff = (Utc::now() - chrono::Duration::seconds(ttl_secs as i64)).to_rfc3339();
+        let row = sqlx::query(
+            "SELECT provider, response FROM llm_cache WHERE key = $1 AND created_at >= $2",
+        )
+        .bind(key)
+        .bind(cutoff)
+        .fetch_optional(&self.pool)
+        .await?;
+        Ok(row.map(|row| (row.get("provider"), row.get("response"))))
+    }
+
+    pub async fn llm_cache_put(&self, key: &str, provider: &str, response: &str) -> Result<()> {
+        sqlx::query(
+            r#"
+            INSERT INTO llm_cache (key, provider, response, created_at) VALUES ($1, $2, $3, $4)
+            ON CONFLICT(key) DO UPDATE SET
+                provider = excluded.provider,
+                response = excluded.response,
+                created_at = excluded.created_at
+            "#,
+        )
+        .bind(key)
+        .bind(provider)
+        .bind(response)
+        .bind(Utc::now().to_rfc3339())
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    /// Drop every cached LLM response, for manual invalidation when
+    /// earlier completions turned out to be bad.
+    pub async fn purge_llm_cache(&self) -> Result<u64> {
+        let result = sqlx::query("DELETE FROM llm_cache")
+            .execute(&self.pool)
+            .await?;
+        Ok(result.rows_affected())
+    }
+
+    pub async fn insert_test_case(&self, case: &TestCase) -> Result<()> {
+        sqlx::query(
+            r#"
+            INSERT INTO test_cases (id, content_hash, content, service, origin_commit, issue_id, minimized, original_lines, created_at, updated_at)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)
+            "#,
+        )
+        .bind(case.id.to_string())
+        .bind(&case.content_hash)
+        .bind(&case.content)
+        .bind(&case.service)
+        .bind(&case.origin_commit)
+        .bind(case.issue_id.map(|id| id.to_string()))
+        .bind(case.minimized as i64)
+        .bind(case.original_lines)
+        .bind(case.created_at.to_rfc3339())
+        .bind(case.updated_at.to_rfc3339())
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    pub async fn test_case_by_id(&self, id: Uuid) -> Result<Option<TestCase>> {
+        let row = sqlx::query("SELECT * FROM test_cases WHERE id = $1")
+            .bind(id.to_string())
+            .fetch_optional(&self.pool)
+            .await?;
+        row.as_ref().map(row_to_test_case).transpose()
+    }
+
+    pub async fn find_test_case_by_hash(&self, hash: &str) -> Result<Option<TestCase>> {
+        let row = sqlx::query("SELECT * FROM test_cases WHERE content_hash = $1")
+            .bind(hash)
+            .fetch_optional(&self.pool)
+            .await?;
+        row.as_ref().map(row_to_test_case).transpose()
+    }
+
+    /// Swap in a test case's minimized content; the dedup hash moves with
+    /// it.
+    pub async fn mark_test_case_minimized(&self, id: Uuid, content: &str, hash: &str) -> Result<()> {
+        sqlx::query(
+            "UPDATE test_cases SET content = $1, content_hash = $2, minimized = 1, updated_at = $3 WHERE id = $4",
+        )
+        .bind(content)
+        .bind(hash)
+        .bind(Utc::now().to_rfc3339())
+        .bind(id.to_string())
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    pub async fn record_test_case_run(&self, run: &TestCaseRun) -> Result<()> {
+        sqlx::query(
+            "INSERT INTO test_case_runs (id, test_case_id, still_failing, ran_at) VALUES ($1, $2, $3, $4)",
+        )
+        .bind(run.id.to_string())
+        .bind(run.test_case_id.to_string())
+        .bind(run.still_failing as i64)
+        .bind(run.ran_at.to_rfc3339())
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    /// One test case's execution history rolled up as `(runs,
+    /// still_failing)`.
+    pub async fn test_case_run_summary(&self, id: Uuid) -> Result<(i64, i64)> {
+        let row = sqlx::query(
+            "SELECT COUNT(*) AS runs, COALESCE(SUM(still_failing), 0) AS still_failing FROM test_case_runs WHERE test_case_id = $1",
+        )
+        .bind(id.to_string())
+        .fetch_one(&self.pool)
+        .await?;
+        Ok((row.get("runs"), row.get("still_failing")))
+    }
+
+    /// Execution history rolled up per test case, as `(test_case_id,
+    /// runs, still_failing)`.
+    pub async fn test_case_run_counts(&self) -> Result<Vec<(Uuid, i64, i64)>> {
+        let rows = sqlx::query(
+            "SELECT test_case_id, COUNT(*) AS runs, COALESCE(SUM(still_failing), 0) AS still_failing FROM test_case_runs GROUP BY test_case_id",
+        )
+        .fetch_all(&self.pool)
+        .await?;
+        rows.iter()
+            .map(|row| {
+                let id: String = row.get("test_case_id");
+                Ok((
+                    Uuid::parse_str(&id)?,
+                    row.get("runs"),
+                    row.get("still_failing"),
+                ))
+            })
+            .collect()
+    }
+
+    pub async fn list_test_cases(&self, service: Option<&str>, limit: i64) -> Result<Vec<TestCase>> {
+        let rows = match service {
+            Some(service) => {
+                sqlx::query(
+                    "SELECT * FROM test_cases WHERE service = $1 ORDER BY created_at DESC LIMIT $2",
+                )
+                .bind(service)
+                .bind(limit)
+                .fetch_all(&self.pool)
+                .await?
+            }
+            None => {
+                sqlx::query("SELECT * FROM test_cases ORDER BY created_at DESC LIMIT $1")
+                    .bind(limit)
+                    .fetch_all(&self.pool)
+                    .await?
+            }
+        };
+        rows.iter().map(row_to_test_case).collect()
+    }
+
+    pub async fn test_case_stats(&self) -> Result<TestCaseStats> {
+        let cases = sqlx::query(
+            "SELECT COUNT(*) AS total, COALESCE(SUM(minimized), 0) AS minimized FROM test_cases",
+        )
+        .fetch_one(&self.pool)
+        .await?;
+        let runs = sqlx::query(
+            "SELECT COUNT(*) AS runs, COALESCE(SUM(still_failing), 0) AS still_failing FROM test_case_runs",
+        )
+        .fetch_one(&self.pool)
+        .await?;
+        let by_service = sqlx::query(
+            "SELECT service, COUNT(*) AS n FROM test_cases GROUP BY service ORDER BY n DESC, service",
+        )
+        .fetch_all(&self.pool)
+        .await?
+        .iter()
+        .map(|row| ServiceCount {
+            service: row.get("service"),
+            count: row.get("n"),
+        })
+        .collect();
+        Ok(TestCaseStats {
+            total: cases.get("total"),
+            minimized: cases.get("minimized"),
+            runs: runs.get("runs"),
+            still_failing_runs: runs.get("still_failing"),
+            by_service,
+        })
+    }
+
+    pub async fn count_patches(&self, status: PatchStatus) -> Result<i64> {
+        let row = sqlx::query("SELECT COUNT(*) AS n FROM patches WHERE status = $1")
+            .bind(status.as_str())
+            .fetch_one(&self.pool)
+            .await?;
+        Ok(row.get("n"))
+    }
+
+    pub async fn record_llm_cost(&self, entry: &CostEntry) -> Result<()> {
+        sqlx::query(
+            r#"
+            INSERT INTO llm_costs (id, issue_id, provider, input_tokens, output_tokens, cost_usd, created_at)
+            VALUES ($1, $2, $3, $4, $5, $6, $7)
+            "#,
+        )
+        .bind(entry.id.to_string())
+        .bind(entry.issue_id.to_string())
+        .bind(&entry.provider)
+        .bind(entry.input_tokens)
+        .bind(entry.output_tokens)
+        .bind(entry.cost_usd)
+        .bind(entry.created_at.to_rfc3339())
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    /// Total spend since an RFC 3339 cutoff (inclusive).
+    pub async fn cost_since(&self, cutoff: &str) -> Result<f64> {
+        let row = sqlx::query(
+            "SELECT COALESCE(SUM(cost_usd), 0.0) AS total FROM llm_costs WHERE created_at >= $1",
+        )
+        .bind(cutoff)
+        .fetch_one(&self.pool)
+        .await?;
+        Ok(row.get("total"))
+    }
+
+    /// Spend since an RFC 3339 cutoff attributed to one project's issues,
+    /// for per-project budget caps.
+    /// Total spend attributed to one issue across all of its generation
+    /// attempts.
+    pub async fn cost_for_issue(&self, issue_id: Uuid) -> Result<f64> {
+        let row = sqlx::query(
+            "SELECT COALESCE(SUM(cost_usd), 0.0) AS total FROM llm_costs WHERE issue_id = $1",
+        )
+        .bind(issue_id.to_string())
+        .fetch_one(&self.pool)
+        .await?;
+        Ok(row.get("total"))
+    }
+
+    pub async fn cost_since_for_project(&self, cutoff: &str, project: &str) -> Result<f64> {
+        let row = sqlx::query(
+            r#"
+            SELECT COALESCE(SUM(c.cost_usd), 0.0) AS total
+            FROM llm_costs c JOIN issues i ON i.id = c.issue_id
+            WHERE c.created_at >= $1 AND i.project = $2
+            "#,
+        )
+        .bind(cutoff)
+        .bind(project)
+        .fetch_one(&self.pool)
+        .await?;
+        Ok(row.get("total"))
+    }
+
+    /// Spend per UTC day over the last `days` days, newest first.
+    pub async fn costs_by_day(&self, days: i64) -> Result<Vec<DayCost>> {
+        let cutoff = (Utc::now() - chrono::Duration::days(days)).to_rfc3339();
+        let rows = sqlx::query(
+            r#"
+            SELECT substr(created_at, 1, 10) AS day,
+                   SUM(input_tokens) AS input_tokens,
+                   SUM(output_tokens) AS output_tokens,
+                   SUM(cost_usd) AS cost_usd
+            FROM llm_costs WHERE created_at >= $1
+            GROUP BY day ORDER BY day DESC
+            "#,
+        )
+        .bind(cutoff)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(rows
+            .iter()
+            .map(|row| DayCost {
+                day: row.get("day"),
+                input_tokens: row.get("input_tokens"),
+                output_tokens: row.get("output_tokens"),
+                cost_usd: row.get("cost_usd"),
+            })
+            .collect())
+    }
+
+    /// The most expensive issues, for the cost report.
+    pub async fn costs_by_issue(&self, limit: i64) -> Result<Vec<IssueCost>> {
+        let rows = sqlx::query(
+            r#"
+            SELECT issue_id, COUNT(*) AS requests, SUM(cost_usd) AS cost_usd
+            FROM llm_costs GROUP BY issue_id ORDER BY cost_usd DESC LIMIT $1
+            "#,
+        )
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(rows
+            .iter()
+            .map(|row| IssueCost {
+                issue_id: row.get("issue_id"),
+                requests: row.get("requests"),
+                cost_usd: row.get("cost_usd"),
+            })
+            .collect())
+    }
+
+    pub async fn record_snapshot(&self, snapshot: &Snapshot) -> Result<()> {
+        sqlx::query(
+            r#"
+            INSERT INTO snapshots (id, project, reason, patch_id, head, commit_sha, files, created_at, restored_at)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+            ON CONFLICT (id) DO UPDATE SET restored_at = excluded.restored_at
+            "#,
+        )
+        .bind(snapshot.id.to_string())
+        .bind(&snapshot.project)
+        .bind(&snapshot.reason)
+        .bind(snapshot.patch_id.map(|id| id.to_string()))
+        .bind(&snapshot.head)
+        .bind(&snapshot.commit)
+        .bind(serde_json::to_string(&snapshot.files)?)
+        .bind(snapshot.created_at.to_rfc3339())
+        .bind(snapshot.restored_at.map(|t| t.to_rfc3339()))
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    pub async fn snapshot_by_id(&self, id: Uuid) -> Result<Option<Snapshot>> {
+        let row = sqlx::query("SELECT * FROM snapshots WHERE id = $1")
+            .bind(id.to_string())
+            .fetch_optional(&self.pool)
+            .await?;
+        row.as_ref().map(row_to_snapshot).transpose()
+    }
+
+    pub async fn delete_snapshot(&self, id: Uuid) -> Result<()> {
+        sqlx::query("DELETE FROM snapshots WHERE id = $1")
+            .bind(id.to_string())
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    /// Recent snapshots, newest first, optionally for one project.
+    pub async fn recent_snapshots(
+        &self,
+        project: Option<&str>,
+        limit: i64,
+    ) -> Result<Vec<Snapshot>> {
+        let rows = match project {
+            Some(project) => {
+                sqlx::query(
+                    "SELECT * FROM snapshots WHERE project = $1 ORDER BY created_at DESC LIMIT $2",
+                )
+                .bind(project)
+                .bind(limit)
+                .fetch_all(&self.pool)
+                .await?
+            }
+            None => {
+                sqlx::query("SELECT * FROM snapshots ORDER BY created_at DESC LIMIT $1")
+                    .bind(limit)
+                    .fetch_all(&self.pool)
+                    .await?
+            }
+        };
+        rows.iter().map(row_to_snapshot).collect()
+    }
+}
+
+fn row_to_snapshot(row: &sqlx::any::AnyRow) -> Result<Snapshot> {
+    let id: String = row.get("id");
+    let patch_id: Option<String> = row.get("patch_id");
+    let files: String = row.get("files");
+    let created_at: String = row.get("created_at");
+    let restored_at: Option<String> = row.get("restored_at");
+    Ok(Snapshot {
+        id: Uuid::parse_str(&id)?,
+        project: row.get("project"),
+        reason: row.get("reason"),
+        patch_id: patch_id.map(|id| Uuid::parse_str(&id)).transpose()?,
+        head: row.get("head"),
+        commit: row.get("commit_sha"),
+        files: serde_json::from_str(&files)?,
+        created_at: DateTime::parse_from_rfc3339(&created_at)?.with_timezone(&Utc),
+        restored_at: restored_at
+            .map(|t| DateTime::parse_from_rfc3339(&t).map(|t| t.with_timezone(&Utc)))
+            .transpose()?,
+    })
+}
+
+fn row_to_issue(row: &sqlx::any::AnyRow) -> Result<Issue> {
+    let id: String = row.get("id");
+    let affected_files: String = row.get("affected_files");
+    let status: String = row.get("status");
+    let created_at: String = row.get("created_at");
+    let updated_at: String = row.get("updated_at");
+    Ok(Issue {
+        id: Uuid::parse_str(&id)?,
+        source: row.get("source"),
+        project: row.get("project"),
+        service: row.get("service"),
+        commit: row.get("commit_sha"),
+        classification: row.get("classification"),
+        log: row.get("log"),
+        affected_files: serde_json::from_str(&affected_files)?,
+        owner: row.get("owner"),
+        status: IssueStatus::parse(&status),
+        created_at: DateTime::parse_from_rfc3339(&created_at)?.with_timezone(&Utc),
+        updated_at: DateTime::parse_from_rfc3339(&updated_at)?.with_timezone(&Utc),
+    })
+}
+
+fn row_to_review(row: &sqlx::any::AnyRow) -> Result<Review> {
+    let id: String = row.get("id");
+    let patch_id: String = row.get("patch_id");
+    let verdict: String = row.get("verdict");
+    let created_at: String = row.get("created_at");
+    Ok(Review {
+        id: Uuid::parse_str(&id)?,
+        patch_id: Uuid::parse_str(&patch_id)?,
+        reviewer: row.get("reviewer"),
+        verdict: ReviewVerdict::parse(&verdict),
+        comment: row.get("comment"),
+        created_at: DateTime::parse_from_rfc3339(&created_at)?.with_timezone(&Utc),
+    })
+}
+
+fn row_to_test_case(row: &sqlx::any::AnyRow) -> Result<TestCase> {
+    let id: String = row.get("id");
+    let issue_id: Option<String> = row.get("issue_id");
+    let minimized: i64 = row.get("minimized");
+    let created_at: String = row.get("created_at");
+    let updated_at: String = row.get("updated_at");
+    Ok(TestCase {
+        id: Uuid::parse_str(&id)?,
+        content_hash: row.get("content_hash"),
+        content: row.get("content"),
+        service: row.get("service"),
+        origin_commit: row.get("origin_commit"),
+        issue_id: issue_id.as_deref().map(Uuid::parse_str).transpose()?,
+        minimized: minimized != 0,
+        original_lines: row.get("original_lines"),
+        created_at: DateTime::parse_from_rfc3339(&created_at)?.with_timezone(&Utc),
+        updated_at: DateTime::parse_from_rfc3339(&updated_at)?.with_timezone(&Utc),
+    })
+}
+
+fn row_to_patch(row: &sqlx::any::AnyRow) -> Result<Patch> {
+    let id: String = row.get("id");
+    let issue_id: String = row.get("issue_id");
+    let status: String = row.get("status");
+    let validation: Option<String> = row.get("validation");
+    let created_at: String = row.get("created_at");
+    let updated_at: String = row.get("updated_at");
+    Ok(Patch {
+        id: Uuid::parse_str(&id)?,
+        issue_id: Uuid::parse_str(&issue_id)?,
+        description: row.get("description"),
+        origin: row.get("origin"),
+        diff: row.get("diff"),
+        rollback_diff: row.get("rollback_diff"),
+        pr_url: row.get("pr_url"),
+        status: PatchStatus::parse(&status),
+        validation: validation.as_deref().map(serde_json::from_str).transpose()?,
+        changelog: row.get("changelog"),
+        created_at: DateTime::parse_from_rfc3339(&created_at)?.with_timezone(&Utc),
+        updated_at: DateTime::parse_from_rfc3339(&updated_at)?.with_timezone(&Utc),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::ValidationResult;
+
+    #[tokio::test]
+    async fn issues_and_patches_round_trip() {
+        let db = Database::open_in_memory().await.unwrap();
+        let mut issue = Issue::new(
+            "build-monitor",
+            "web",
+            "abc123",
+            "compiler",
+            "error[E0308]: mismatched types",
+            vec!["apps/web/src/app.ts".into()],
+        );
+        issue.owner = Some("@aurum/web".to_string());
+        db.record_issue(&issue).await.unwrap();
+
+        let open = db.issues(Some(IssueStatus::Open), None, 10).await.unwrap();
+        assert_eq!(open.len(), 1);
+        assert_eq!(open[0].affected_files, issue.affected_files);
+        assert_eq!(open[0].project, "default");
+        assert_eq!(open[0].owner.as_deref(), Some("@aurum/web"));
+        assert!(db
+            .issues(None, Some("other"), 10)
+            .await
+            .unwrap()
+            .is_empty());
+
+        let mut patch = Patch::new(issue.id, "fix type mismatch", "--- a/x\n+++ b/x\n");
+        db.record_patch(&patch).await.unwrap();
+        patch.status = PatchStatus::Validated;
+        patch.validation = Some(ValidationResult {
+            passed: true,
+            build_ok: true,
+            tests_ok: true,
+            build_time_ms: 1200,
+            test_time_ms: 800,
+            security_issues_found: 0,
+            security_issues: Vec::new(),
+            detail: None,
+        });
+        db.record_patch(&patch).await.unwrap();
+
+        let stored = db.patch_by_id(patch.id).await.unwrap().unwrap();
+        assert_eq!(stored.status, PatchStatus::Validated);
+        assert!(stored.validation.unwrap().passed);
+
+        issue.status = IssueStatus::Patched;
+        db.record_issue(&issue).await.unwrap();
+        assert_eq!(db.count_issues(IssueStatus::Open).await.unwrap(), 0);
+        assert_eq!(db.count_issues(IssueStatus::Patched).await.unwrap(), 1);
+    }
+
+    #[tokio::test]
+    async fn llm_cache_respects_ttl_and_manual_purge() {
+        let db = Database::open_in_memory().await.unwrap();
+        db.llm_cache_put("key", "anthropic/model", "--- a/x\n")
+            .await
+            .unwrap();
+        let hit = db.llm_cache_get("key", 3600).await.unwrap();
+        assert_eq!(
+            hit,
+            Some(("anthropic/model".to_string(), "--- a/x\n".to_string()))
+        );
+        // A zero TTL renders every entry stale.
+        assert!(db.llm_cache_get("key", 0).await.unwrap().is_none());
+        assert!(db.llm_cache_get("other", 3600).await.unwrap().is_none());
+
+        assert_eq!(db.purge_llm_cache().await.unwrap(), 1);
+        assert!(db.llm_cache_get("key", 3600).await.unwrap().is_none());
+    }
+}
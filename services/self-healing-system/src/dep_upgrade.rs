@@ -0,0 +1,499 @@
+//! Deterministic upgrades for yanked and vulnerable dependencies.
+//!
+//! Builds broken by a yanked crate and cargo-audit advisories are a
+//! failure class that needs no LLM: cargo itself knows the safe version.
+//! The fixer rule in this module asks `cargo update --dry-run` whether a
+//! compatible release exists, captures the relocked `Cargo.lock` as a
+//! unified diff, and — when the advisory's patched range is outside the
+//! current requirement — raises the requirement in the declaring
+//! `Cargo.toml` files first. The resulting patch flows through the same
+//! validation and pull-request pipeline as any other.
+
+use crate::fixers::RuleFix;
+use anyhow::{bail, Context, Result};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::Mutex;
+
+/// Lines of unchanged context around each hunk of a generated diff.
+const CONTEXT: usize = 3;
+
+/// Serializes the mutate-and-restore window on the target repository, so
+/// concurrent patch generation tasks never see each other's relock.
+static UPDATE_LOCK: Mutex<()> = Mutex::new(());
+
+/// The dependency an issue blames, extracted from its log.
+pub struct Target {
+    pub package: String,
+    /// Pinned version named by the log, used to disambiguate when the
+    /// lockfile holds several versions of the package.
+    pub version: Option<String>,
+    /// Patched version requirements carried by a cargo-audit advisory.
+    pub patched: Vec<String>,
+}
+
+/// Recognize the two log shapes this fixer handles: a cargo-audit
+/// advisory filed by the analysis pass, and a build failure over a yanked
+/// version.
+pub fn target_from_log(log: &str) -> Option<Target> {
+    if log.contains("RUSTSEC-") {
+        return advisory_target(log);
+    }
+    if log.contains("is yanked") {
+        return yanked_target(log);
+    }
+    None
+}
+
+/// Compute an upgrade patch for the target, or `None` when cargo has no
+/// compatible candidate and the advisory names no patched versions.
+pub fn try_upgrade(repo: &Path, target: &Target) -> Result<Option<RuleFix>> {
+    let lock = PathBuf::from("Cargo.lock");
+    if !repo.join(&lock).exists() {
+        return Ok(None);
+    }
+    let spec = match &target.version {
+        Some(version) => format!("{}@{version}", target.package),
+        None => target.package.clone(),
+    };
+    // A compatible release fixes most advisories. Cargo computes the new
+    // lockfile itself, so checksums and transitive bumps are always right.
+    if let Some(upgrade) = plan(repo, &spec)? {
+        let diff = capture(repo, std::slice::from_ref(&lock), || run_update(repo, &spec))?;
+        return Ok(diff.map(|diff| RuleFix {
+            rule: "dependency-upgrade",
+            description: format!(
+                "bump {} {} -> {}",
+                upgrade.package, upgrade.from, upgrade.to
+            ),
+            diff,
+        }));
+    }
+    // No compatible candidate: when the advisory names patched versions,
+    // raise the requirement in the declaring manifests and relock.
+    let Some(version) = minimal_patched_version(&target.patched) else {
+        return Ok(None);
+    };
+    let mut edits = Vec::new();
+    for manifest in manifests(repo) {
+        let content = read(repo, &manifest)?;
+        if let Some(bumped) = bump_requirement(&content, &target.package, &version) {
+            edits.push((manifest, bumped));
+        }
+    }
+    if edits.is_empty() {
+        return Ok(None);
+    }
+    let mut files: Vec<PathBuf> = edits.iter().map(|(manifest, _)| manifest.clone()).collect();
+    files.push(lock);
+    let diff = capture(repo, &files, || {
+        for (manifest, bumped) in &edits {
+            std::fs::write(repo.join(manifest), bumped)
+                .with_context(|| format!("cannot write {}", manifest.display()))?;
+        }
+        run_update(repo, &target.package)
+    })?;
+    Ok(diff.map(|diff| RuleFix {
+        rule: "dependency-upgrade",
+        description: format!("bump {} to {}", target.package, version),
+        diff,
+    }))
+}
+
+/// The version move cargo proposes for one package.
+struct Upgrade {
+    package: String,
+    from: String,
+    to: String,
+}
+
+/// Ask `cargo update --dry-run` whether a compatible upgrade exists for
+/// the package, without touching the lockfile.
+fn plan(repo: &Path, spec: &str) -> Result<Option<Upgrade>> {
+    let output = Command::new("cargo")
+        .args(["update", "--package", spec, "--dry-run"])
+        .current_dir(repo)
+        .output()
+        .context("failed to invoke cargo update")?;
+    if !output.status.success() {
+        bail!(
+            "cargo update --dry-run failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    let package = spec.split('@').next().unwrap_or(spec);
+    Ok(parse_update_plan(
+        &String::from_utf8_lossy(&output.stderr),
+        package,
+    ))
+}
+
+fn run_update(repo: &Path, spec: &str) -> Result<()> {
+    let output = Command::new("cargo")
+        .args(["update", "--package", spec])
+        .current_dir(repo)
+        .output()
+        .context("failed to invoke cargo update")?;
+    if !output.status.success() {
+        bail!(
+            "cargo update failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(())
+}
+
+/// Run `op`, which may rewrite the given files in the repository, and
+/// return the combined unified diff of what it changed. The files are
+/// restored to their original content either way; only the diff leaves
+/// this function.
+fn capture<F>(repo: &Path, files: &[PathBuf], op: F) -> Result<Option<String>>
+where
+    F: FnOnce() -> Result<()>,
+{
+    let _guard = UPDATE_LOCK
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+    let mut originals = Vec::new();
+    for file in files {
+        originals.push(read(repo, file)?);
+    }
+    let result = op();
+    let updated: Vec<String> = files
+        .iter()
+        .map(|file| std::fs::read_to_string(repo.join(file)).unwrap_or_default())
+        .collect();
+    for (file, original) in files.iter().zip(&originals) {
+        std::fs::write(repo.join(file), original)
+            .with_context(|| format!("cannot restore {}", file.display()))?;
+    }
+    result?;
+    let mut diff = String::new();
+    for ((file, original), new) in files.iter().zip(&originals).zip(&updated) {
+        if let Some(file_diff) = unified_diff(&file.to_string_lossy(), original, new) {
+            diff.push_str(&file_diff);
+        }
+    }
+    Ok((!diff.is_empty()).then_some(diff))
+}
+
+/// Extract the package's `Updating x v0.1.0 -> v0.1.2` line from cargo's
+/// dry-run output.
+fn parse_update_plan(output: &str, package: &str) -> Option<Upgrade> {
+    for line in output.lines() {
+        let mut tokens = line.split_whitespace();
+        if tokens.next() != Some("Updating") || tokens.next() != Some(package) {
+            continue;
+        }
+        let from = tokens.next()?.trim_start_matches('v');
+        if tokens.next() != Some("->") {
+            continue;
+        }
+        let to = tokens.next()?.trim_start_matches('v');
+        return Some(Upgrade {
+            package: package.to_string(),
+            from: from.to_string(),
+            to: to.to_string(),
+        });
+    }
+    None
+}
+
+/// Parse an advisory issue filed by the analysis pass, e.g.
+/// `error[RUSTSEC-2024-0001]: badcrate 0.1.0: heap overflow
+/// (patched: >=0.2.1) at Cargo.lock:1`.
+fn advisory_target(log: &str) -> Option<Target> {
+    let (_, rest) = log.split_once("]: ")?;
+    let mut tokens = rest.split_whitespace();
+    let package = tokens.next()?.to_string();
+    let version = tokens.next()?.trim_end_matches(':');
+    if !version.starts_with(|c: char| c.is_ascii_digit()) {
+        return None;
+    }
+    let patched = log
+        .split_once("(patched: ")
+        .and_then(|(_, tail)| tail.split(')').next())
+        .map(|list| list.split(", ").map(str::to_string).collect())
+        .unwrap_or_default();
+    Some(Target {
+        package,
+        version: Some(version.to_string()),
+        patched,
+    })
+}
+
+/// Parse a cargo resolution failure over a yanked version, e.g.
+/// ``failed to select a version for the requirement `badcrate = "^0.1"`
+/// ... version 0.1.0 is yanked``.
+fn yanked_target(log: &str) -> Option<Target> {
+    let package = log
+        .split_once("requirement `")?
+        .1
+        .split([' ', '`'])
+        .next()?
+        .to_string();
+    let version = log.split_once(" is yanked").and_then(|(head, _)| {
+        let token = head.rsplit([' ', '`']).find(|t| !t.is_empty())?;
+        token
+            .starts_with(|c: char| c.is_ascii_digit())
+            .then(|| token.to_string())
+    });
+    Some(Target {
+        package,
+        version,
+        patched: Vec::new(),
+    })
+}
+
+/// The lowest concrete version satisfying the advisory's first patched
+/// requirement: `>=0.2.1` means `0.2.1` is the minimal safe bump.
+fn minimal_patched_version(patched: &[String]) -> Option<String> {
+    let version = patched
+        .first()?
+        .split(',')
+        .next()?
+        .trim_matches(['>', '<', '=', '^', '~', ' ']);
+    version
+        .starts_with(|c: char| c.is_ascii_digit())
+        .then(|| version.to_string())
+}
+
+/// Rewrite the requirement where `manifest` declares the dependency,
+/// handling both `pkg = "0.1"` and `pkg = { version = "0.1", ... }`
+/// lines. Path and workspace dependencies are left alone.
+fn bump_requirement(manifest: &str, package: &str, version: &str) -> Option<String> {
+    let mut changed = false;
+    let mut out: Vec<String> = Vec::new();
+    for line in manifest.lines() {
+        match rewrite_dep_line(line, package, version) {
+            Some(rewritten) => {
+                changed = true;
+                out.push(rewritten);
+            }
+            None => out.push(line.to_string()),
+        }
+    }
+    let mut bumped = out.join("\n");
+    if manifest.ends_with('\n') {
+        bumped.push('\n');
+    }
+    changed.then_some(bumped)
+}
+
+fn rewrite_dep_line(line: &str, package: &str, version: &str) -> Option<String> {
+    let rest = line.trim_start().strip_prefix(package)?;
+    let value = rest.trim_start().strip_prefix('=')?.trim_start();
+    let eq = line.find('=')?;
+    let search_from = if value.starts_with('{') {
+        // Inline table: the quoted string after its `version` key.
+        eq + line[eq..].find("version")?
+    } else if value.starts_with('"') {
+        eq
+    } else {
+        // Path or workspace dependency; nothing to bump.
+        return None;
+    };
+    let open = search_from + line[search_from..].find('"')?;
+    let close = open + 1 + line[open + 1..].find('"')?;
+    Some(format!(
+        "{}{version}{}",
+        &line[..open + 1],
+        &line[close..]
+    ))
+}
+
+/// Every `Cargo.toml` under the repository (as repo-relative paths),
+/// skipping build output and hidden directories.
+fn manifests(repo: &Path) -> Vec<PathBuf> {
+    let mut found = Vec::new();
+    collect_manifests(repo, Path::new(""), &mut found);
+    found.sort();
+    found
+}
+
+fn collect_manifests(repo: &Path, rel: &Path, out: &mut Vec<PathBuf>) {
+    let Ok(entries) = std::fs::read_dir(repo.join(rel)) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        let name = entry.file_name();
+        let name = name.to_string_lossy();
+        let child = rel.join(name.as_ref());
+        if entry.path().is_dir() {
+            if name.starts_with('.') || name == "target" || name == "node_modules" {
+                continue;
+            }
+            collect_manifests(repo, &child, out);
+        } else if name == "Cargo.toml" {
+            out.push(child);
+        }
+    }
+}
+
+fn read(repo: &Path, file: &Path) -> Result<String> {
+    std::fs::read_to_string(repo.join(file))
+        .with_context(|| format!("cannot read {}", file.display()))
+}
+
+/// Build a unified diff between two versions of one file, or `None` when
+/// they are identical. Change regions further apart than the context
+/// width become separate hunks, so a relock reads like `git diff` output.
+fn unified_diff(file: &str, old: &str, new: &str) -> Option<String> {
+    let a: Vec<&str> = old.lines().collect();
+    let b: Vec<&str> = new.lines().collect();
+    let mut regions: Vec<(usize, usize, usize, usize)> = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < a.len() && j < b.len() {
+        if a[i] == b[j] {
+            i += 1;
+            j += 1;
+            continue;
+        }
+        let (di, dj) = resync(&a[i..], &b[j..]);
+        regions.push((i, i + di, j, j + dj));
+        i += di;
+        j += dj;
+    }
+    if i < a.len() || j < b.len() {
+        regions.push((i, a.len(), j, b.len()));
+    }
+    if regions.is_empty() {
+        return None;
+    }
+    // Merge regions whose context windows would touch.
+    let mut merged: Vec<(usize, usize, usize, usize)> = Vec::new();
+    for region in regions {
+        if let Some(last) = merged.last_mut() {
+            if region.0 <= last.1 + 2 * CONTEXT {
+                last.1 = region.1;
+                last.3 = region.3;
+                continue;
+            }
+        }
+        merged.push(region);
+    }
+    let mut diff = format!("--- a/{file}\n+++ b/{file}\n");
+    for (old_start, old_end, new_start, new_end) in merged {
+        let ctx_start = old_start.saturating_sub(CONTEXT);
+        let ctx_end = (old_end + CONTEXT).min(a.len());
+        let old_count = ctx_end - ctx_start;
+        let new_count = old_count - (old_end - old_start) + (new_end - new_start);
+        diff.push_str(&format!(
+            "@@ -{},{old_count} +{},{new_count} @@\n",
+            ctx_start + 1,
+            new_start - (old_start - ctx_start) + 1,
+        ));
+        for line in &a[ctx_start..old_start] {
+            diff.push_str(&format!(" {line}\n"));
+        }
+        for line in &a[old_start..old_end] {
+            diff.push_str(&format!("-{line}\n"));
+        }
+        for line in &b[new_start..new_end] {
+            diff.push_str(&format!("+{line}\n"));
+        }
+        for line in &a[old_end..ctx_end] {
+            diff.push_str(&format!(" {line}\n"));
+        }
+    }
+    Some(diff)
+}
+
+/// Find the cheapest skip `(di, dj)` after which the two sides line up
+/// again for a few lines (or both run out).
+fn resync(a: &[&str], b: &[&str]) -> (usize, usize) {
+    for total in 1..=a.len() + b.len() {
+        for di in 0..=total.min(a.len()) {
+            let dj = total - di;
+            if dj > b.len() {
+                continue;
+            }
+            let window = CONTEXT.min(a.len() - di).min(b.len() - dj);
+            if window == 0 {
+                if a.len() - di == 0 && b.len() - dj == 0 {
+                    return (di, dj);
+                }
+                continue;
+            }
+            if (0..window).all(|k| a[di + k] == b[dj + k]) {
+                return (di, dj);
+            }
+        }
+    }
+    (a.len(), b.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::patch_generator;
+
+    #[test]
+    fn advisory_logs_yield_package_version_and_patched_range() {
+        let target = target_from_log(
+            "error[RUSTSEC-2024-0001]: badcrate 0.1.0: heap overflow \
+             (patched: >=0.2.1) at Cargo.lock:1",
+        )
+        .unwrap();
+        assert_eq!(target.package, "badcrate");
+        assert_eq!(target.version.as_deref(), Some("0.1.0"));
+        assert_eq!(target.patched, vec![">=0.2.1".to_string()]);
+        assert_eq!(minimal_patched_version(&target.patched).unwrap(), "0.2.1");
+
+        assert!(target_from_log("error[E0308]: mismatched types at src/main.rs:3").is_none());
+    }
+
+    #[test]
+    fn yanked_build_failures_yield_the_requirement_package() {
+        let target = target_from_log(
+            "error: failed to select a version for the requirement `badcrate = \"^0.1\"`\n\
+             candidate versions found which didn't match: 0.2.0\n\
+             version 0.1.0 is yanked",
+        )
+        .unwrap();
+        assert_eq!(target.package, "badcrate");
+        assert_eq!(target.version.as_deref(), Some("0.1.0"));
+        assert!(target.patched.is_empty());
+    }
+
+    #[test]
+    fn update_plan_lines_parse_into_upgrades() {
+        let output = "    Locking 2 packages to latest compatible versions\n\
+                      \x20   Updating badcrate v0.1.0 -> v0.1.2\n\
+                      \x20   Updating othercrate v1.0.0 -> v1.0.1\n";
+        let upgrade = parse_update_plan(output, "badcrate").unwrap();
+        assert_eq!(upgrade.from, "0.1.0");
+        assert_eq!(upgrade.to, "0.1.2");
+        assert!(parse_update_plan(output, "missing").is_none());
+    }
+
+    #[test]
+    fn requirement_bumps_cover_string_and_table_forms() {
+        let manifest = "[package]\nname = \"x\"\n\n[dependencies]\n\
+                        badcrate = \"0.1\"\n\
+                        other = { version = \"1\", features = [\"full\"] }\n\
+                        local = { path = \"../local\" }\n";
+        let bumped = bump_requirement(manifest, "badcrate", "0.2.1").unwrap();
+        assert!(bumped.contains("badcrate = \"0.2.1\""));
+        let bumped = bump_requirement(manifest, "other", "2.0.0").unwrap();
+        assert!(bumped.contains("other = { version = \"2.0.0\", features = [\"full\"] }"));
+        assert!(bump_requirement(manifest, "local", "1.0.0").is_none());
+        assert!(bump_requirement(manifest, "missing", "1.0.0").is_none());
+    }
+
+    #[test]
+    fn generated_diffs_round_trip_through_the_patch_engine() {
+        let old = "[[package]]\nname = \"a\"\nversion = \"1.0.0\"\nchecksum = \"aaa\"\n\n\
+                   [[package]]\nname = \"b\"\nversion = \"2.0.0\"\n\n\
+                   [[package]]\nname = \"c\"\nversion = \"3.0.0\"\nchecksum = \"ccc\"\n";
+        let new = old
+            .replace("version = \"1.0.0\"\nchecksum = \"aaa\"", "version = \"1.0.1\"\nchecksum = \"abc\"")
+            .replace("version = \"3.0.0\"", "version = \"3.0.1\"");
+
+        assert!(unified_diff("Cargo.lock", old, old).is_none());
+        let diff = unified_diff("Cargo.lock", old, &new).unwrap();
+        let files = patch_generator::parse(&diff).unwrap();
+        assert_eq!(files[0].old_path, "Cargo.lock");
+        assert_eq!(patch_generator::apply_diff(old, &files[0]).unwrap(), new);
+    }
+}
@@ -0,0 +1,429 @@
+//! Deterministic fixers for well-known issue classes.
+//!
+//! These rules run before the LLM is consulted: an unused import, a
+//! missing derive, a clippy machine-applicable suggestion, a Cargo
+//! edition bump, or a yanked or vulnerable dependency can be fixed
+//! mechanically, costs no tokens, and produces a minimal diff. Issues no
+//! rule matches fall through to LLM generation, and every patch records
+//! which stage produced it in its origin.
+
+use crate::types::Issue;
+use anyhow::{Context, Result};
+use std::path::Path;
+use tracing::debug;
+
+/// Traits a missing-implementation diagnostic can be fixed for by adding
+/// a derive.
+const DERIVABLE: &[&str] = &["Debug", "Clone", "Copy", "PartialEq", "Eq", "Hash", "Default"];
+
+/// A fix produced by a deterministic rule.
+pub struct RuleFix {
+    /// Rule identifier, recorded as the patch origin.
+    pub rule: &'static str,
+    pub description: String,
+    pub diff: String,
+}
+
+/// Try each rule against the issue and return the first fix produced. A
+/// rule that errors (unreadable file, tool missing) is skipped, not fatal:
+/// the LLM fallback still gets its chance.
+pub fn try_fix(repo: &Path, issue: &Issue) -> Option<RuleFix> {
+    type Rule = fn(&Path, &Issue) -> Result<Option<RuleFix>>;
+    let rules: &[(&str, Rule)] = &[
+        ("unused-import", unused_import),
+        ("missing-derive", missing_derive),
+        ("clippy-suggestion", clippy_suggestion),
+        ("edition-bump", edition_bump),
+        ("dependency-upgrade", dependency_upgrade),
+    ];
+    for (name, rule) in rules {
+        match rule(repo, issue) {
+            Ok(Some(fix)) => return Some(fix),
+            Ok(None) => {}
+            Err(e) => debug!("fixer rule {name} skipped: {e:#}"),
+        }
+    }
+    None
+}
+
+/// Remove a whole `use` line rustc flagged as unused. Partial imports in a
+/// brace list are left for the compiler's own suggestion or the LLM.
+fn unused_import(repo: &Path, issue: &Issue) -> Result<Option<RuleFix>> {
+    if !issue.log.contains("unused import") {
+        return Ok(None);
+    }
+    let Some((file, line)) = location(issue) else {
+        return Ok(None);
+    };
+    let content = read(repo, &file)?;
+    let lines: Vec<&str> = content.lines().collect();
+    if line == 0 || line > lines.len() {
+        return Ok(None);
+    }
+    let text = lines[line - 1].trim();
+    if !text.starts_with("use ") || !text.ends_with(';') {
+        return Ok(None);
+    }
+    Ok(Some(RuleFix {
+        rule: "unused-import",
+        description: format!("remove unused import at {file}:{line}"),
+        diff: line_replacement_diff(&file, &content, line, line, &[]),
+    }))
+}
+
+/// Add a derivable trait to the type a missing-implementation diagnostic
+/// points at, extending an existing `#[derive(...)]` when there is one.
+fn missing_derive(repo: &Path, issue: &Issue) -> Result<Option<RuleFix>> {
+    let Some((trait_name, type_name)) = derive_target(&issue.log) else {
+        return Ok(None);
+    };
+    let Some((file, _)) = location(issue) else {
+        return Ok(None);
+    };
+    let content = read(repo, &file)?;
+    let lines: Vec<&str> = content.lines().collect();
+    let Some(def_line) = lines
+        .iter()
+        .position(|l| defines_type(l, &type_name))
+        .map(|i| i + 1)
+    else {
+        return Ok(None);
+    };
+    if def_line > 1 {
+        let prev = lines[def_line - 2];
+        if let Some(inner) = prev
+            .trim()
+            .strip_prefix("#[derive(")
+            .and_then(|s| s.strip_suffix(")]"))
+        {
+            if inner.split(',').any(|t| t.trim() == trait_name) {
+                return Ok(None);
+            }
+            let indent = indent_of(prev);
+            return Ok(Some(RuleFix {
+                rule: "missing-derive",
+                description: format!("derive {trait_name} for {type_name}"),
+                diff: line_replacement_diff(
+                    &file,
+                    &content,
+                    def_line - 1,
+                    def_line - 1,
+                    &[format!("{indent}#[derive({inner}, {trait_name})]")],
+                ),
+            }));
+        }
+    }
+    let indent = indent_of(lines[def_line - 1]);
+    Ok(Some(RuleFix {
+        rule: "missing-derive",
+        description: format!("derive {trait_name} for {type_name}"),
+        diff: line_replacement_diff(
+            &file,
+            &content,
+            def_line,
+            def_line,
+            &[
+                format!("{indent}#[derive({trait_name})]"),
+                lines[def_line - 1].to_string(),
+            ],
+        ),
+    }))
+}
+
+/// Re-run clippy and turn its machine-applicable suggestion for the
+/// issue's span into a diff, the same way the analysis pass does.
+fn clippy_suggestion(repo: &Path, issue: &Issue) -> Result<Option<RuleFix>> {
+    if issue.classification != "lint" {
+        return Ok(None);
+    }
+    let Some((file, line)) = location(issue) else {
+        return Ok(None);
+    };
+    for diag in crate::static_analysis::check_clippy(repo)? {
+        if diag.file != file || diag.line != line {
+            continue;
+        }
+        if let Some(fix) = diag.suggestions.iter().find(|s| s.machine_applicable) {
+            return Ok(Some(RuleFix {
+                rule: "clippy-suggestion",
+                description: format!("apply clippy's suggested fix at {file}:{line}"),
+                diff: crate::static_analysis::suggestion_diff(repo, fix)?,
+            }));
+        }
+    }
+    Ok(None)
+}
+
+/// Bump the crate's `edition` field when the diagnostic says a feature
+/// needs a newer one.
+fn edition_bump(repo: &Path, issue: &Issue) -> Result<Option<RuleFix>> {
+    let Some(required) = issue.log.split("edition").skip(1).find_map(|rest| {
+        let year: String = rest
+            .trim_start()
+            .chars()
+            .take_while(|c| c.is_ascii_digit())
+            .collect();
+        (year.len() == 4).then_some(year)
+    }) else {
+        return Ok(None);
+    };
+    let manifest = manifest_for(repo, issue);
+    let content = read(repo, &manifest)?;
+    let lines: Vec<&str> = content.lines().collect();
+    let Some(idx) = lines
+        .iter()
+        .position(|l| l.trim_start().starts_with("edition") && l.contains('='))
+    else {
+        return Ok(None);
+    };
+    let Some(current) = lines[idx].split('"').nth(1) else {
+        return Ok(None);
+    };
+    if current >= required.as_str() {
+        return Ok(None);
+    }
+    let current = current.to_string();
+    Ok(Some(RuleFix {
+        rule: "edition-bump",
+        description: format!("bump {manifest} to edition {required}"),
+        diff: line_replacement_diff(
+            &manifest,
+            &content,
+            idx + 1,
+            idx + 1,
+            &[lines[idx].replace(&current, &required)],
+        ),
+    }))
+}
+
+/// Bump a yanked or vulnerable dependency to a safe version. The issue
+/// names the package — a cargo-audit advisory filed by the analysis pass
+/// or a build failure over a yanked version — and `cargo update` computes
+/// the new lockfile; see the dep_upgrade module.
+fn dependency_upgrade(repo: &Path, issue: &Issue) -> Result<Option<RuleFix>> {
+    let Some(target) = crate::dep_upgrade::target_from_log(&issue.log) else {
+        return Ok(None);
+    };
+    crate::dep_upgrade::try_upgrade(repo, &target)
+}
+
+/// Find the `file:line` the issue log points at, restricted to the files
+/// the issue says it affects.
+fn location(issue: &Issue) -> Option<(String, usize)> {
+    for file in &issue.affected_files {
+        let needle = format!("{file}:");
+        if let Some(pos) = issue.log.find(&needle) {
+            let digits: String = issue.log[pos + needle.len()..]
+                .chars()
+                .take_while(|c| c.is_ascii_digit())
+                .collect();
+            if let Ok(line) = digits.parse() {
+                return Some((file.clone(), line));
+            }
+        }
+    }
+    None
+}
+
+/// Extract (trait, type) from the two shapes rustc uses for missing trait
+/// implementations, keeping only traits a derive can supply.
+fn derive_target(log: &str) -> Option<(String, String)> {
+    if let Some((head, tail)) = log.split_once("` doesn't implement `") {
+        return normalized(tail.split('`').next()?, head.rsplit('`').next()?);
+    }
+    if let Some((head, tail)) = log.split_once("` is not implemented for `") {
+        return normalized(head.rsplit('`').next()?, tail.split('`').next()?);
+    }
+    None
+}
+
+fn normalized(trait_path: &str, ty: &str) -> Option<(String, String)> {
+    let trait_name = trait_path.rsplit("::").next()?;
+    if !DERIVABLE.contains(&trait_name) {
+        return None;
+    }
+    let ty = ty
+        .trim_start_matches('&')
+        .split(['<', ' '])
+        .next()?
+        .rsplit("::")
+        .next()?;
+    Some((trait_name.to_string(), ty.to_string()))
+}
+
+fn defines_type(line: &str, name: &str) -> bool {
+    let trimmed = line
+        .trim()
+        .trim_start_matches("pub(crate) ")
+        .trim_start_matches("pub ");
+    for keyword in ["struct ", "enum "] {
+        if let Some(rest) = trimmed.strip_prefix(keyword) {
+            let ident: String = rest
+                .chars()
+                .take_while(|c| c.is_alphanumeric() || *c == '_')
+                .collect();
+            return ident == name;
+        }
+    }
+    false
+}
+
+fn indent_of(line: &str) -> String {
+    line.chars().take_while(|c| c.is_whitespace()).collect()
+}
+
+/// The manifest governing the issue's first affected file: the nearest
+/// `Cargo.toml` walking up from it, or the repository root's.
+fn manifest_for(repo: &Path, issue: &Issue) -> String {
+    if let Some(file) = issue.affected_files.first() {
+        let mut dir = Path::new(file).parent();
+        while let Some(d) = dir {
+            let candidate = d.join("Cargo.toml");
+            if repo.join(&candidate).exists() {
+                return candidate.to_string_lossy().into_owned();
+            }
+            dir = d.parent();
+        }
+    }
+    "Cargo.toml".to_string()
+}
+
+fn read(repo: &Path, file: &str) -> Result<String> {
+    let path = repo.join(file);
+    std::fs::read_to_string(&path).with_context(|| format!("cannot read {}", path.display()))
+}
+
+/// Build a unified diff replacing lines `start..=end` (1-based) of `file`
+/// with `replacement`, with three lines of context, in the same shape the
+/// analysis pass emits for compiler suggestions.
+fn line_replacement_diff(
+    file: &str,
+    content: &str,
+    start: usize,
+    end: usize,
+    replacement: &[String],
+) -> String {
+    let lines: Vec<&str> = content.lines().collect();
+    let ctx_start = start.saturating_sub(4);
+    let ctx_end = (end + 3).min(lines.len());
+    let old_count = ctx_end - ctx_start;
+    let new_count = old_count - (end - start + 1) + replacement.len();
+    let mut diff = format!(
+        "--- a/{file}\n+++ b/{file}\n@@ -{s},{old_count} +{s},{new_count} @@\n",
+        s = ctx_start + 1,
+    );
+    for line in &lines[ctx_start..start - 1] {
+        diff.push_str(&format!(" {line}\n"));
+    }
+    for line in &lines[start - 1..end] {
+        diff.push_str(&format!("-{line}\n"));
+    }
+    for line in replacement {
+        diff.push_str(&format!("+{line}\n"));
+    }
+    for line in &lines[end..ctx_end] {
+        diff.push_str(&format!(" {line}\n"));
+    }
+    diff
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::patch_generator;
+
+    fn apply(repo: &Path, file: &str, diff: &str) -> String {
+        let original = std::fs::read_to_string(repo.join(file)).unwrap();
+        let files = patch_generator::parse(diff).unwrap();
+        patch_generator::apply_diff(&original, &files[0]).unwrap()
+    }
+
+    #[test]
+    fn unused_import_rule_removes_the_use_line() {
+        let repo = tempfile::tempdir().unwrap();
+        std::fs::write(
+            repo.path().join("lib.rs"),
+            "use std::io::Read;\n\nfn main() {}\n",
+        )
+        .unwrap();
+        let issue = Issue::new(
+            "static-analysis",
+            "workspace",
+            "abc",
+            "lint",
+            "warning[unused_imports]: unused import: `std::io::Read` at lib.rs:1",
+            vec!["lib.rs".into()],
+        );
+
+        let fix = try_fix(repo.path(), &issue).unwrap();
+        assert_eq!(fix.rule, "unused-import");
+        assert_eq!(apply(repo.path(), "lib.rs", &fix.diff), "\nfn main() {}\n");
+    }
+
+    #[test]
+    fn missing_derive_rule_extends_or_inserts_the_attribute() {
+        let repo = tempfile::tempdir().unwrap();
+        std::fs::write(
+            repo.path().join("point.rs"),
+            "#[derive(Clone)]\npub struct Point {\n    x: i32,\n}\n",
+        )
+        .unwrap();
+        let issue = Issue::new(
+            "static-analysis",
+            "workspace",
+            "abc",
+            "compiler",
+            "error[E0277]: `Point` doesn't implement `Debug` at point.rs:3",
+            vec!["point.rs".into()],
+        );
+        let fix = try_fix(repo.path(), &issue).unwrap();
+        assert_eq!(fix.rule, "missing-derive");
+        assert!(apply(repo.path(), "point.rs", &fix.diff).starts_with("#[derive(Clone, Debug)]\n"));
+
+        std::fs::write(
+            repo.path().join("point.rs"),
+            "pub struct Point {\n    x: i32,\n}\n",
+        )
+        .unwrap();
+        let fix = try_fix(repo.path(), &issue).unwrap();
+        assert!(apply(repo.path(), "point.rs", &fix.diff).starts_with("#[derive(Debug)]\npub struct Point {"));
+    }
+
+    #[test]
+    fn edition_bump_rule_updates_the_nearest_manifest() {
+        let repo = tempfile::tempdir().unwrap();
+        std::fs::write(
+            repo.path().join("Cargo.toml"),
+            "[package]\nname = \"x\"\nedition = \"2018\"\n",
+        )
+        .unwrap();
+        std::fs::create_dir(repo.path().join("src")).unwrap();
+        std::fs::write(repo.path().join("src/main.rs"), "fn main() {}\n").unwrap();
+        let issue = Issue::new(
+            "static-analysis",
+            "workspace",
+            "abc",
+            "compiler",
+            "error: `let...else` requires edition 2021 or later at src/main.rs:1",
+            vec!["src/main.rs".into()],
+        );
+
+        let fix = try_fix(repo.path(), &issue).unwrap();
+        assert_eq!(fix.rule, "edition-bump");
+        assert!(apply(repo.path(), "Cargo.toml", &fix.diff).contains("edition = \"2021\""));
+    }
+
+    #[test]
+    fn unmatched_issues_fall_through_to_the_llm() {
+        let repo = tempfile::tempdir().unwrap();
+        let issue = Issue::new(
+            "build-monitor",
+            "api",
+            "abc",
+            "test",
+            "test user_flow failed: assertion `left == right` failed",
+            vec![],
+        );
+        assert!(try_fix(repo.path(), &issue).is_none());
+    }
+}
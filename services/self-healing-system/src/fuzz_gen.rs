@@ -0,0 +1,464 @@
+//! cargo-fuzz harness synthesis for parser-like failures.
+//!
+//! A reproduction scaffold from [`crate::test_gen`] pins one failing
+//! input. When the implicated function consumes raw input — a lone
+//! `&[u8]` or `&str` parameter — a fuzzer can do better: this module
+//! emits a cargo-fuzz target for the function in the owning crate's
+//! `fuzz/` directory, seeds its corpus with the failing input extracted
+//! from the log, and collects the crash artifacts a fuzz run leaves
+//! behind so the daemon can file them as new issues.
+
+use crate::test_gen::{self, FailureKind};
+use crate::types::Issue;
+use anyhow::{Context, Result};
+use std::path::{Path, PathBuf};
+
+/// How the fuzzed function consumes the raw input.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InputKind {
+    Bytes,
+    Str,
+}
+
+/// A synthesized cargo-fuzz harness, ready to be written into the repo.
+#[derive(Debug)]
+pub struct GeneratedHarness {
+    /// Crate owning the implicated file, relative to the repo root.
+    pub crate_root: PathBuf,
+    /// Package name of that crate, used in the fuzz manifest.
+    pub crate_name: String,
+    /// Repo-relative source file the failure implicated.
+    pub source_file: String,
+    pub function: String,
+    /// `[[bin]]` name of the fuzz target, `fuzz_<function>`.
+    pub target_name: String,
+    /// Repo-relative path of the fuzz target source.
+    pub target_path: PathBuf,
+    pub target_content: String,
+    /// Repo-relative corpus seed carrying the failing input.
+    pub seed_path: PathBuf,
+    pub seed: Vec<u8>,
+}
+
+/// A crash input left in `fuzz/artifacts/<target>/` by a fuzz run.
+#[derive(Debug)]
+pub struct CrashArtifact {
+    /// Repo-relative path of the artifact file.
+    pub path: PathBuf,
+    pub input: Vec<u8>,
+}
+
+/// Parse the failure out of an issue's log and synthesize a fuzz harness
+/// for the implicated function. `None` when the log matches no runtime
+/// failure, the function does not take a lone `&[u8]`/`&str`, or it
+/// lives in a binary root the fuzz crate cannot link.
+pub fn generate_from_failure(repo: &Path, issue: &Issue) -> Result<Option<GeneratedHarness>> {
+    let Some(signature) = test_gen::parse_failure(&issue.log) else {
+        return Ok(None);
+    };
+    // Compiler errors never executed; there is nothing to fuzz.
+    if signature.kind == FailureKind::CompilerError {
+        return Ok(None);
+    }
+    let (Some(file), Some(line)) = (signature.file.clone(), signature.line) else {
+        return Ok(None);
+    };
+    let Ok(source) = std::fs::read_to_string(repo.join(&file)) else {
+        return Ok(None);
+    };
+    let Some((function, input)) = fuzzable_function(&source, line) else {
+        return Ok(None);
+    };
+    let root = test_gen::crate_root(repo, &file);
+    let manifest = std::fs::read_to_string(repo.join(&root).join("Cargo.toml")).unwrap_or_default();
+    let Some(crate_name) = package_name(&manifest) else {
+        return Ok(None);
+    };
+    let in_crate = Path::new(&file).strip_prefix(&root).unwrap_or(Path::new(&file));
+    let Some(module) = module_path(in_crate) else {
+        return Ok(None);
+    };
+    let ident = crate_name.replace('-', "_");
+    let call_path = if module.is_empty() {
+        format!("{ident}::{function}")
+    } else {
+        format!("{ident}::{module}::{function}")
+    };
+    let target_name = format!("fuzz_{function}");
+    let short = &issue.id.simple().to_string()[..8];
+    Ok(Some(GeneratedHarness {
+        target_path: root
+            .join("fuzz")
+            .join("fuzz_targets")
+            .join(format!("{target_name}.rs")),
+        target_content: target_content(issue, &call_path, &target_name, input),
+        seed_path: root
+            .join("fuzz")
+            .join("corpus")
+            .join(&target_name)
+            .join(format!("issue_{short}")),
+        seed: seed_from_message(&signature.message),
+        crate_root: root,
+        crate_name,
+        source_file: file,
+        function,
+        target_name,
+    }))
+}
+
+/// Write the harness into the repository: the fuzz target, a `[[bin]]`
+/// entry in `fuzz/Cargo.toml` (the manifest is created on first use),
+/// and the corpus seed. An existing target is kept: the first harness
+/// for a function wins, and re-analysis must not clobber a tuned one.
+pub fn store(repo: &Path, harness: &GeneratedHarness) -> Result<bool> {
+    let target = repo.join(&harness.target_path);
+    if target.exists() {
+        return Ok(false);
+    }
+    if let Some(parent) = target.parent() {
+        std::fs::create_dir_all(parent)
+            .with_context(|| format!("cannot create {}", parent.display()))?;
+    }
+    std::fs::write(&target, &harness.target_content)
+        .with_context(|| format!("cannot write {}", target.display()))?;
+
+    let manifest_path = repo.join(&harness.crate_root).join("fuzz").join("Cargo.toml");
+    let section = bin_section(&harness.target_name);
+    let manifest = match std::fs::read_to_string(&manifest_path) {
+        Ok(existing) if existing.contains(&format!("name = \"{}\"", harness.target_name)) => None,
+        Ok(existing) => Some(format!("{existing}\n{section}")),
+        Err(_) => Some(format!("{}{section}", manifest_header(&harness.crate_name))),
+    };
+    if let Some(manifest) = manifest {
+        std::fs::write(&manifest_path, manifest)
+            .with_context(|| format!("cannot write {}", manifest_path.display()))?;
+    }
+
+    let seed = repo.join(&harness.seed_path);
+    if !seed.exists() {
+        if let Some(parent) = seed.parent() {
+            std::fs::create_dir_all(parent)
+                .with_context(|| format!("cannot create {}", parent.display()))?;
+        }
+        std::fs::write(&seed, &harness.seed)
+            .with_context(|| format!("cannot write {}", seed.display()))?;
+    }
+    Ok(true)
+}
+
+/// The crash inputs a fuzz run of this harness left behind, sorted by
+/// file name; empty when no run has happened yet.
+pub fn crash_artifacts(repo: &Path, harness: &GeneratedHarness) -> Result<Vec<CrashArtifact>> {
+    let dir = harness
+        .crate_root
+        .join("fuzz")
+        .join("artifacts")
+        .join(&harness.target_name);
+    let absolute = repo.join(&dir);
+    if !absolute.is_dir() {
+        return Ok(Vec::new());
+    }
+    let mut entries: Vec<_> = std::fs::read_dir(&absolute)
+        .with_context(|| format!("cannot read {}", absolute.display()))?
+        .collect::<std::io::Result<Vec<_>>>()?;
+    entries.sort_by_key(|entry| entry.file_name());
+    let mut artifacts = Vec::new();
+    for entry in entries {
+        let name = entry.file_name().to_string_lossy().into_owned();
+        if !name.starts_with("crash-") {
+            continue;
+        }
+        let input = std::fs::read(entry.path())
+            .with_context(|| format!("cannot read {}", entry.path().display()))?;
+        artifacts.push(CrashArtifact {
+            path: dir.join(&name),
+            input,
+        });
+    }
+    Ok(artifacts)
+}
+
+/// The log body for an issue filed from a crash artifact: enough to
+/// reproduce without attaching the raw bytes wholesale.
+pub fn crash_log(harness: &GeneratedHarness, artifact: &CrashArtifact) -> String {
+    let shown = &artifact.input[..artifact.input.len().min(256)];
+    let mut log = format!(
+        "fuzz target {} crashed; reproduce with `cargo fuzz run {} {}` in {}\n",
+        harness.target_name,
+        harness.target_name,
+        artifact.path.display(),
+        if harness.crate_root.as_os_str().is_empty() {
+            ".".to_string()
+        } else {
+            harness.crate_root.display().to_string()
+        },
+    );
+    log.push_str(&format!(
+        "input ({} bytes): {}",
+        artifact.input.len(),
+        String::from_utf8_lossy(shown)
+    ));
+    if artifact.input.len() > shown.len() {
+        log.push_str(" …");
+    }
+    log
+}
+
+/// The nearest `fn` item at or above `line`, together with how it
+/// consumes raw input. `None` for methods, multi-parameter functions,
+/// and anything not taking a lone `&[u8]` or `&str` — a generated call
+/// could not supply the other arguments.
+fn fuzzable_function(source: &str, line: usize) -> Option<(String, InputKind)> {
+    let lines: Vec<&str> = source.lines().collect();
+    let upto = line.min(lines.len());
+    for (index, candidate) in lines[..upto].iter().enumerate().rev() {
+        let Some(after) = candidate.split("fn ").nth(1) else {
+            continue;
+        };
+        let name: String = after
+            .chars()
+            .take_while(|c| c.is_alphanumeric() || *c == '_')
+            .collect();
+        if name.is_empty() {
+            continue;
+        }
+        let params = parameter_list(after, &lines[index + 1..])?;
+        let params: Vec<&str> = params
+            .split(',')
+            .map(str::trim)
+            .filter(|p| !p.is_empty())
+            .collect();
+        let [param] = params.as_slice() else {
+            return None;
+        };
+        let (_, ty) = param.split_once(':')?;
+        return raw_input_kind(ty).map(|kind| (name, kind));
+    }
+    None
+}
+
+/// The text between the signature's parentheses, which may span lines.
+fn parameter_list(first: &str, rest: &[&str]) -> Option<String> {
+    let mut params = String::new();
+    let mut depth = 0usize;
+    for text in std::iter::once(first).chain(rest.iter().copied()) {
+        for c in text.chars() {
+            match c {
+                '(' => {
+                    depth += 1;
+                    if depth == 1 {
+                        continue;
+                    }
+                }
+                ')' => {
+                    depth -= 1;
+                    if depth == 0 {
+                        return Some(params);
+                    }
+                }
+                _ => {}
+            }
+            if depth >= 1 {
+                params.push(c);
+            }
+        }
+        if depth >= 1 {
+            params.push(' ');
+        }
+    }
+    None
+}
+
+/// `&[u8]` or `&str`, tolerating an explicit lifetime.
+fn raw_input_kind(ty: &str) -> Option<InputKind> {
+    let mut ty = ty.trim().strip_prefix('&')?.trim_start();
+    if ty.starts_with('\'') {
+        ty = ty.split_once(char::is_whitespace)?.1.trim_start();
+    }
+    match ty {
+        "[u8]" => Some(InputKind::Bytes),
+        "str" => Some(InputKind::Str),
+        _ => None,
+    }
+}
+
+/// The module path of a source file relative to its crate root:
+/// `src/codec/frame.rs` becomes `codec::frame`, the library root the
+/// empty string. `None` for files outside `src/` and for `main.rs` —
+/// binary-only functions are not importable from the fuzz crate.
+fn module_path(file: &Path) -> Option<String> {
+    let rel = file.strip_prefix("src").ok()?;
+    let mut parts: Vec<String> = rel
+        .iter()
+        .map(|part| part.to_string_lossy().into_owned())
+        .collect();
+    let last = parts.pop()?;
+    match last.strip_suffix(".rs")? {
+        "main" => return None,
+        "lib" | "mod" => {}
+        stem => parts.push(stem.to_string()),
+    }
+    Some(parts.join("::"))
+}
+
+/// The `name` of the first `[package]`-style entry in a manifest.
+fn package_name(manifest: &str) -> Option<String> {
+    manifest.lines().find_map(|line| {
+        line.trim()
+            .strip_prefix("name")?
+            .trim_start()
+            .strip_prefix('=')?
+            .trim()
+            .strip_prefix('"')?
+            .strip_suffix('"')
+            .map(str::to_string)
+    })
+}
+
+/// The failing input, as well as the log can tell: panic messages
+/// usually echo the offending input in backticks or quotes; the whole
+/// message seeds the corpus when neither is present.
+fn seed_from_message(message: &str) -> Vec<u8> {
+    for quote in ['`', '"'] {
+        if let Some((_, rest)) = message.split_once(quote) {
+            if let Some((inner, _)) = rest.split_once(quote) {
+                if !inner.is_empty() {
+                    return inner.as_bytes().to_vec();
+                }
+            }
+        }
+    }
+    message.as_bytes().to_vec()
+}
+
+fn target_content(issue: &Issue, call_path: &str, target_name: &str, input: InputKind) -> String {
+    let mut content = format!(
+        "//! Fuzz target for `{call_path}`, generated by self-healing for issue {}.\n//!\n//! Run with `cargo fuzz run {target_name}`; the corpus is seeded with\n//! the input that failed in service {} at commit {}.\n\n#![no_main]\n\nuse libfuzzer_sys::fuzz_target;\n\n",
+        issue.id, issue.service, issue.commit
+    );
+    match input {
+        InputKind::Bytes => content.push_str(&format!(
+            "fuzz_target!(|data: &[u8]| {{\n    let _ = {call_path}(data);\n}});\n"
+        )),
+        InputKind::Str => content.push_str(&format!(
+            "fuzz_target!(|data: &[u8]| {{\n    if let Ok(input) = std::str::from_utf8(data) {{\n        let _ = {call_path}(input);\n    }}\n}});\n"
+        )),
+    }
+    content
+}
+
+fn manifest_header(crate_name: &str) -> String {
+    format!(
+        "[package]\nname = \"{crate_name}-fuzz\"\nversion = \"0.0.1\"\npublish = false\nedition = \"2021\"\n\n[package.metadata]\ncargo-fuzz = true\n\n[dependencies]\nlibfuzzer-sys = \"0.4\"\n{crate_name} = {{ path = \"..\" }}\n\n"
+    )
+}
+
+fn bin_section(target_name: &str) -> String {
+    format!(
+        "[[bin]]\nname = \"{target_name}\"\npath = \"fuzz_targets/{target_name}.rs\"\ntest = false\ndoc = false\n"
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn crate_with(lib: &str) -> tempfile::TempDir {
+        let repo = tempfile::tempdir().unwrap();
+        std::fs::create_dir_all(repo.path().join("services/x/src")).unwrap();
+        std::fs::write(
+            repo.path().join("services/x/Cargo.toml"),
+            "[package]\nname = \"x-parse\"\n",
+        )
+        .unwrap();
+        std::fs::write(repo.path().join("services/x/src/lib.rs"), lib).unwrap();
+        repo
+    }
+
+    fn issue_at(line: usize, message: &str) -> Issue {
+        Issue::new(
+            "build-monitor",
+            "x",
+            "abc1234",
+            "test",
+            &format!("thread 'tests::boom' panicked at services/x/src/lib.rs:{line}:5:\n{message}\n"),
+            vec![],
+        )
+    }
+
+    #[test]
+    fn emits_a_bytes_harness_with_manifest_and_seed() {
+        let repo = crate_with("pub fn decode(data: &[u8]) -> u8 {\n    data[0]\n}\n");
+        let issue = issue_at(2, "index out of bounds: the len is 0 but the index is 0");
+
+        let harness = generate_from_failure(repo.path(), &issue).unwrap().unwrap();
+        assert_eq!(harness.target_name, "fuzz_decode");
+        assert!(harness.target_content.contains("x_parse::decode(data)"));
+        assert!(harness.target_content.contains("#![no_main]"));
+
+        assert!(store(repo.path(), &harness).unwrap());
+        let manifest = std::fs::read_to_string(
+            repo.path().join("services/x/fuzz/Cargo.toml"),
+        )
+        .unwrap();
+        assert!(manifest.contains("name = \"x-parse-fuzz\""));
+        assert!(manifest.contains("name = \"fuzz_decode\""));
+        assert!(manifest.contains("libfuzzer-sys"));
+        let seed = std::fs::read(repo.path().join(&harness.seed_path)).unwrap();
+        assert_eq!(seed, issue.log.lines().nth(1).unwrap().as_bytes());
+        // The first harness for a function wins.
+        assert!(!store(repo.path(), &harness).unwrap());
+    }
+
+    #[test]
+    fn a_str_parser_gets_utf8_guarded_input_and_a_quoted_seed() {
+        let repo = crate_with(
+            "pub fn parse_header(raw: &str) -> usize {\n    raw.len()\n}\n",
+        );
+        let issue = issue_at(2, "invalid header `GET /`");
+
+        let harness = generate_from_failure(repo.path(), &issue).unwrap().unwrap();
+        assert!(harness
+            .target_content
+            .contains("if let Ok(input) = std::str::from_utf8(data)"));
+        assert!(harness.target_content.contains("x_parse::parse_header(input)"));
+        assert_eq!(harness.seed, b"GET /");
+    }
+
+    #[test]
+    fn methods_and_multi_parameter_functions_are_skipped() {
+        let repo = crate_with(
+            "pub fn merge(a: &[u8], b: &[u8]) -> usize {\n    a.len() + b.len()\n}\n",
+        );
+        let issue = issue_at(2, "boom");
+        assert!(generate_from_failure(repo.path(), &issue).unwrap().is_none());
+
+        let repo = crate_with(
+            "pub struct P;\nimpl P {\n    pub fn parse(&self, data: &[u8]) -> usize {\n        data.len()\n    }\n}\n",
+        );
+        let issue = issue_at(4, "boom");
+        assert!(generate_from_failure(repo.path(), &issue).unwrap().is_none());
+    }
+
+    #[test]
+    fn crash_artifacts_are_collected_and_logged() {
+        let repo = crate_with("pub fn decode(data: &[u8]) -> u8 {\n    data[0]\n}\n");
+        let issue = issue_at(2, "boom");
+        let harness = generate_from_failure(repo.path(), &issue).unwrap().unwrap();
+
+        assert!(crash_artifacts(repo.path(), &harness).unwrap().is_empty());
+        let dir = repo.path().join("services/x/fuzz/artifacts/fuzz_decode");
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("crash-deadbeef"), b"\xff\xfeGET").unwrap();
+        std::fs::write(dir.join("README.txt"), "not a crash").unwrap();
+
+        let artifacts = crash_artifacts(repo.path(), &harness).unwrap();
+        assert_eq!(artifacts.len(), 1);
+        assert_eq!(artifacts[0].input, b"\xff\xfeGET");
+
+        let log = crash_log(&harness, &artifacts[0]);
+        assert!(log.contains("cargo fuzz run fuzz_decode"), "{log}");
+        assert!(log.contains("crash-deadbeef"), "{log}");
+        assert!(log.contains("input (5 bytes)"), "{log}");
+    }
+}
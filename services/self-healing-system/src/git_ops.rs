@@ -0,0 +1,293 @@
+//! Pushing daemon-made commits to the remote.
+//!
+//! The daemon commits applied patches, changelog entries, and self-heal
+//! review branches locally; with a `push` config those commits also
+//! reach the remote. HTTPS remotes authenticate with a token read from
+//! the environment through a one-shot credential helper, SSH remotes
+//! with a configured key, and a push the remote refuses (non-fast-
+//! forward, protected branch, declined hook) is surfaced as its own
+//! error rather than a generic git failure.
+
+use anyhow::{bail, Context, Result};
+use serde::Serialize;
+use std::path::Path;
+use std::process::Command;
+
+use crate::config::PushConfig;
+
+/// Environment variable the credential helper reads the token back from,
+/// so the token never appears on a command line or on disk.
+const TOKEN_VAR: &str = "SELF_HEALING_PUSH_TOKEN";
+
+pub struct GitOperations {
+    config: PushConfig,
+}
+
+impl GitOperations {
+    pub fn new(config: PushConfig) -> Self {
+        Self { config }
+    }
+
+    /// Push `branch` to the configured remote; see [`Self::push_to`].
+    pub fn push(&self, repo: &Path, branch: &str, force: bool) -> Result<()> {
+        let remote = self.config.remote.clone();
+        self.push_to(repo, &remote, branch, force)
+    }
+
+    /// Push `branch` to `remote` with the configured credentials, setting
+    /// the branch to track its remote counterpart so later pushes and
+    /// status checks line up. `force` is for re-pushed self-heal review
+    /// branches only, never the working branch.
+    pub fn push_to(&self, repo: &Path, remote: &str, branch: &str, force: bool) -> Result<()> {
+        let mut command = Command::new("git");
+        command.current_dir(repo);
+        if let Some(env_var) = &self.config.token_env {
+            let token = std::env::var(env_var)
+                .with_context(|| format!("push token env var {env_var} is not set"))?;
+            command.env(TOKEN_VAR, token);
+            command.arg("-c").arg(format!(
+                "credential.helper=!f() {{ echo username=x-access-token; echo \"password=${TOKEN_VAR}\"; }}; f"
+            ));
+        }
+        if let Some(key) = &self.config.ssh_key_path {
+            command.env(
+                "GIT_SSH_COMMAND",
+                format!("ssh -i '{}' -o IdentitiesOnly=yes", key.display()),
+            );
+        }
+        command.args(["push", "--set-upstream"]);
+        if force {
+            command.arg("--force");
+        }
+        command.args([remote, branch]);
+        let output = command.output().context("failed to invoke git push")?;
+        if output.status.success() {
+            return Ok(());
+        }
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        let stderr = stderr.trim();
+        if stderr.contains("[rejected]")
+            || stderr.contains("non-fast-forward")
+            || stderr.contains("pre-receive hook declined")
+            || stderr.contains("protected branch")
+        {
+            bail!("remote {remote} rejected the push of {branch}: {stderr}");
+        }
+        if stderr.contains("Authentication failed")
+            || stderr.contains("Permission denied")
+            || stderr.contains("could not read Username")
+        {
+            bail!("authentication to remote {remote} failed pushing {branch}: {stderr}");
+        }
+        bail!("git push of {branch} to {remote} failed: {stderr}");
+    }
+}
+
+/// The outcome of merging a self-heal branch back into its base branch.
+#[derive(Debug, Serialize)]
+#[serde(tag = "outcome", rename_all = "snake_case")]
+pub enum MergeOutcome {
+    /// The base branch now contains the branch's commits.
+    Merged { commit: String },
+    /// The merge conflicted in these files; it was aborted and the
+    /// repository restored to its pre-merge state.
+    Conflicted { files: Vec<String> },
+}
+
+/// Merge `branch` back into `base`, restoring the original checkout
+/// whatever happens. A conflicted merge is aborted and reported with the
+/// files that conflicted instead of collapsing into a bare error or
+/// leaving the tree mid-merge.
+pub fn merge_patch_branch(repo: &Path, branch: &str, base: &str) -> Result<MergeOutcome> {
+    let current = capture(repo, &["rev-parse", "--abbrev-ref", "HEAD"])?;
+    let current = current.trim().to_string();
+    run(repo, &["checkout", base]).with_context(|| format!("failed to check out {base}"))?;
+    let merged = merge(repo, branch);
+    // Whatever the merge did, come back off the base branch.
+    let restore = if current == base {
+        Ok(())
+    } else {
+        run(repo, &["checkout", &current])
+    };
+    let outcome = merged?;
+    restore?;
+    Ok(outcome)
+}
+
+fn merge(repo: &Path, branch: &str) -> Result<MergeOutcome> {
+    let output = Command::new("git")
+        .args(["merge", "--no-edit", branch])
+        .current_dir(repo)
+        .output()
+        .context("failed to invoke git merge")?;
+    if output.status.success() {
+        let commit = capture(repo, &["rev-parse", "HEAD"])?.trim().to_string();
+        return Ok(MergeOutcome::Merged { commit });
+    }
+    let files: Vec<String> = capture(repo, &["diff", "--name-only", "--diff-filter=U"])
+        .unwrap_or_default()
+        .lines()
+        .map(str::to_string)
+        .collect();
+    if files.is_empty() {
+        // The merge never started (unknown branch, unrelated histories).
+        bail!(
+            "git merge of {branch} failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    run(repo, &["merge", "--abort"]).context("failed to abort the conflicted merge")?;
+    Ok(MergeOutcome::Conflicted { files })
+}
+
+fn run(repo: &Path, args: &[&str]) -> Result<()> {
+    capture(repo, args).map(|_| ())
+}
+
+fn capture(repo: &Path, args: &[&str]) -> Result<String> {
+    let output = Command::new("git")
+        .args(args)
+        .current_dir(repo)
+        .output()
+        .context("failed to invoke git")?;
+    if !output.status.success() {
+        bail!(
+            "git {} failed: {}",
+            args.first().unwrap_or(&""),
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn run(dir: &Path, args: &[&str]) -> String {
+        let out = Command::new("git")
+            .args(args)
+            .current_dir(dir)
+            .env("GIT_AUTHOR_NAME", "test")
+            .env("GIT_AUTHOR_EMAIL", "test@example.com")
+            .env("GIT_COMMITTER_NAME", "test")
+            .env("GIT_COMMITTER_EMAIL", "test@example.com")
+            .output()
+            .unwrap();
+        assert!(
+            out.status.success(),
+            "git {args:?}: {}",
+            String::from_utf8_lossy(&out.stderr)
+        );
+        String::from_utf8_lossy(&out.stdout).trim().to_string()
+    }
+
+    fn ops() -> GitOperations {
+        GitOperations::new(PushConfig {
+            remote: "origin".to_string(),
+            token_env: None,
+            ssh_key_path: None,
+        })
+    }
+
+    /// A work checkout with one commit and a bare `origin`.
+    fn setup(dir: &Path) -> PathBuf {
+        run(dir, &["init", "-q", "--bare", "bare.git"]);
+        let work = dir.join("work");
+        std::fs::create_dir(&work).unwrap();
+        run(&work, &["init", "-q", "-b", "main"]);
+        // Merge commits run outside the test helper and need an identity.
+        run(&work, &["config", "user.name", "test"]);
+        run(&work, &["config", "user.email", "test@example.com"]);
+        run(&work, &["remote", "add", "origin", "../bare.git"]);
+        std::fs::write(work.join("marker.txt"), "v1").unwrap();
+        run(&work, &["add", "."]);
+        run(&work, &["commit", "-q", "-m", "initial"]);
+        work
+    }
+
+    #[test]
+    fn push_sets_upstream_tracking() {
+        let dir = tempfile::tempdir().unwrap();
+        let work = setup(dir.path());
+        ops().push(&work, "main", false).unwrap();
+        assert_eq!(
+            run(&work, &["rev-parse", "--abbrev-ref", "main@{upstream}"]),
+            "origin/main"
+        );
+    }
+
+    #[test]
+    fn rejected_pushes_surface_as_rejections() {
+        let dir = tempfile::tempdir().unwrap();
+        let work = setup(dir.path());
+        std::fs::write(work.join("marker.txt"), "v2").unwrap();
+        run(&work, &["commit", "-q", "-am", "second"]);
+        ops().push(&work, "main", false).unwrap();
+
+        // Rewrite history so the next push is a non-fast-forward.
+        run(&work, &["reset", "-q", "--hard", "HEAD~1"]);
+        std::fs::write(work.join("marker.txt"), "v3").unwrap();
+        run(&work, &["commit", "-q", "-am", "diverged"]);
+        let err = ops().push(&work, "main", false).unwrap_err();
+        assert!(err.to_string().contains("rejected"), "{err:#}");
+
+        // Review branches may be force-pushed over their old state.
+        ops().push(&work, "main", true).unwrap();
+    }
+
+    #[test]
+    fn merge_back_lands_on_base_and_restores_the_checkout() {
+        let dir = tempfile::tempdir().unwrap();
+        let work = setup(dir.path());
+        run(&work, &["checkout", "-q", "-b", "self-heal/patch-1234"]);
+        std::fs::write(work.join("fix.txt"), "fixed").unwrap();
+        run(&work, &["add", "."]);
+        run(&work, &["commit", "-q", "-m", "fix"]);
+
+        let outcome = merge_patch_branch(&work, "self-heal/patch-1234", "main").unwrap();
+        let MergeOutcome::Merged { commit } = outcome else {
+            panic!("expected a merge, got {outcome:?}");
+        };
+        assert_eq!(run(&work, &["rev-parse", "main"]), commit);
+        // We are back on the review branch and the tree is clean.
+        assert_eq!(run(&work, &["rev-parse", "--abbrev-ref", "HEAD"]), "self-heal/patch-1234");
+        assert_eq!(run(&work, &["status", "--porcelain"]), "");
+    }
+
+    #[test]
+    fn conflicted_merges_report_files_and_leave_the_repo_clean() {
+        let dir = tempfile::tempdir().unwrap();
+        let work = setup(dir.path());
+        run(&work, &["checkout", "-q", "-b", "self-heal/patch-5678"]);
+        std::fs::write(work.join("marker.txt"), "branch edit").unwrap();
+        run(&work, &["commit", "-q", "-am", "branch edit"]);
+        run(&work, &["checkout", "-q", "main"]);
+        std::fs::write(work.join("marker.txt"), "base edit").unwrap();
+        run(&work, &["commit", "-q", "-am", "base edit"]);
+        let base_before = run(&work, &["rev-parse", "main"]);
+
+        let outcome = merge_patch_branch(&work, "self-heal/patch-5678", "main").unwrap();
+        let MergeOutcome::Conflicted { files } = outcome else {
+            panic!("expected a conflict, got {outcome:?}");
+        };
+        assert_eq!(files, vec!["marker.txt".to_string()]);
+        // The base branch did not move and no merge is in progress.
+        assert_eq!(run(&work, &["rev-parse", "main"]), base_before);
+        assert_eq!(run(&work, &["status", "--porcelain"]), "");
+    }
+
+    #[test]
+    fn a_missing_token_is_reported_before_git_runs() {
+        let dir = tempfile::tempdir().unwrap();
+        let work = setup(dir.path());
+        let ops = GitOperations::new(PushConfig {
+            remote: "origin".to_string(),
+            token_env: Some("SELF_HEALING_TEST_ABSENT_TOKEN".to_string()),
+            ssh_key_path: None,
+        });
+        let err = ops.push(&work, "main", false).unwrap_err();
+        assert!(err.to_string().contains("is not set"), "{err:#}");
+    }
+}
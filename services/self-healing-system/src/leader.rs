@@ -0,0 +1,125 @@
+//! Leader election across daemon replicas, built on a lease row in the
+//! shared database rather than an extra coordination service. The leader
+//! renews the lease well inside its TTL; if it dies, a standby picks the
+//! lease up after expiry. Followers keep serving the read API but refuse
+//! mutating actions.
+
+use crate::database::Database;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use tracing::{info, warn};
+
+const LEASE_NAME: &str = "self-healing-daemon";
+
+pub struct LeaderElector {
+    database: Database,
+    /// Unique per process, so restarts do not inherit a stale claim.
+    holder: String,
+    ttl_secs: u64,
+    /// When election is disabled (single-instance deployments) every
+    /// instance considers itself the leader.
+    enabled: bool,
+    leader: AtomicBool,
+}
+
+impl LeaderElector {
+    pub fn new(database: Database, enabled: bool, ttl_secs: u64) -> Self {
+        let host = std::env::var("HOSTNAME").unwrap_or_else(|_| "local".to_string());
+        Self {
+            database,
+            holder: format!("{host}/{}", uuid::Uuid::new_v4()),
+            ttl_secs,
+            enabled,
+            leader: AtomicBool::new(!enabled),
+        }
+    }
+
+    pub fn is_leader(&self) -> bool {
+        self.leader.load(Ordering::Relaxed)
+    }
+
+    /// One acquire-or-renew attempt; logs transitions in either direction.
+    pub async fn try_acquire(&self) -> bool {
+        if !self.enabled {
+            return true;
+        }
+        let now_leader = match self
+            .database
+            .try_acquire_lease(LEASE_NAME, &self.holder, self.ttl_secs)
+            .await
+        {
+            Ok(acquired) => acquired,
+            Err(e) => {
+                // Treat a failed renewal as lost leadership: better two
+                // idle replicas than two active ones.
+                warn!("lease renewal failed: {e:#}");
+                false
+            }
+        };
+        let was_leader = self.leader.swap(now_leader, Ordering::Relaxed);
+        if now_leader && !was_leader {
+            info!(holder = %self.holder, "became leader");
+        } else if !now_leader && was_leader {
+            warn!(holder = %self.holder, "lost leadership");
+        }
+        now_leader
+    }
+
+    /// Release the lease on clean shutdown, promoting a standby without
+    /// waiting for expiry.
+    pub async fn resign(&self) {
+        if !self.enabled || !self.leader.swap(false, Ordering::Relaxed) {
+            return;
+        }
+        if let Err(e) = self.database.release_lease(LEASE_NAME, &self.holder).await {
+            warn!("failed to release the leader lease: {e:#}");
+        }
+    }
+
+    /// Renew the lease forever, at a third of its TTL so one missed
+    /// attempt does not cost leadership.
+    pub fn spawn(self: &Arc<Self>) {
+        if !self.enabled {
+            return;
+        }
+        let elector = self.clone();
+        tokio::spawn(async move {
+            let interval = std::time::Duration::from_secs((elector.ttl_secs / 3).max(1));
+            loop {
+                elector.try_acquire().await;
+                tokio::time::sleep(interval).await;
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn only_one_elector_holds_the_lease() {
+        let db = Database::open_in_memory().await.unwrap();
+        let first = LeaderElector::new(db.clone(), true, 60);
+        let second = LeaderElector::new(db.clone(), true, 60);
+        assert!(first.try_acquire().await);
+        assert!(!second.try_acquire().await);
+        // Renewal by the holder keeps working.
+        assert!(first.try_acquire().await);
+        assert!(first.is_leader());
+        assert!(!second.is_leader());
+
+        // An explicit resignation hands the lease over.
+        first.resign().await;
+        assert!(second.try_acquire().await);
+        assert!(!first.try_acquire().await);
+    }
+
+    #[tokio::test]
+    async fn disabled_election_makes_everyone_leader() {
+        let db = Database::open_in_memory().await.unwrap();
+        let elector = LeaderElector::new(db, false, 60);
+        assert!(elector.is_leader());
+        assert!(elector.try_acquire().await);
+    }
+}
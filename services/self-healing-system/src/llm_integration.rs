@@ -0,0 +1,975 @@
+//! LLM providers used to draft patches when neither the compiler nor the
+//! lint tooling supplied a machine-applicable fix.
+//!
+//! Requests route on the configured provider name: "anthropic" talks to
+//! the messages API (non-streaming, tool use off), "local" talks to any
+//! OpenAI-compatible chat endpoint such as Ollama or vLLM so generation can
+//! run air-gapped. The primary provider is tried first; rate limits, 5xx
+//! responses, and timeouts fall through to the configured fallbacks, and a
+//! per-provider circuit breaker keeps a flapping provider out of the
+//! rotation for a cooldown. A sliding-window rate limiter, shared between
+//! providers on the same API key, paces outbound requests below the
+//! configured per-minute quota. Token usage is accounted per process so
+//! `/api/status` can report spend.
+
+use crate::config::LlmConfig;
+use crate::database::Database;
+use crate::metrics::MetricsCollector;
+use anyhow::{anyhow, bail, Context, Result};
+use futures::StreamExt;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use sha2::{Digest, Sha256};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::watch;
+use tracing::{debug, warn};
+
+const ANTHROPIC_ENDPOINT: &str = "https://api.anthropic.com";
+const ANTHROPIC_VERSION: &str = "2023-06-01";
+
+/// Consecutive retryable failures before a provider's breaker opens.
+const BREAKER_THRESHOLD: u32 = 3;
+/// How long an open breaker keeps a provider out of the rotation.
+const BREAKER_OPEN_SECS: u64 = 60;
+
+/// Cumulative token counts as reported by the provider.
+#[derive(Debug, Clone, Copy, Default, Serialize)]
+pub struct TokenUsage {
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+}
+
+/// Live view of an in-flight streamed generation, published over a watch
+/// channel so `/api/status` can show progress without joining the request.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct GenerationProgress {
+    pub provider: String,
+    pub chars_streamed: usize,
+    pub active: bool,
+}
+
+/// One completed request: the text, what it cost, and who served it.
+#[derive(Debug)]
+pub struct Completion {
+    pub text: String,
+    pub usage: TokenUsage,
+    /// `provider/model` label of the provider that answered.
+    pub provider: String,
+    /// Estimated dollar cost from the provider's configured prices.
+    pub cost_usd: f64,
+}
+
+/// Why a single provider attempt failed, so the chain knows whether to
+/// fall through or give up.
+enum ProviderError {
+    /// Rate limit, 5xx, timeout — worth trying the next provider.
+    Retryable(anyhow::Error),
+    /// Misconfiguration or a malformed reply; falling back would only
+    /// mask it.
+    Fatal(anyhow::Error),
+}
+
+struct CircuitBreaker {
+    state: Mutex<BreakerState>,
+}
+
+#[derive(Default)]
+struct BreakerState {
+    consecutive_failures: u32,
+    open_until: Option<Instant>,
+}
+
+impl CircuitBreaker {
+    fn new() -> Self {
+        Self {
+            state: Mutex::new(BreakerState::default()),
+        }
+    }
+
+    fn is_open(&self) -> bool {
+        let mut state = self.state.lock().expect("breaker lock poisoned");
+        match state.open_until {
+            Some(until) if until > Instant::now() => true,
+            Some(_) => {
+                // Half-open: allow one trial; the failure count is kept so
+                // another failure re-opens immediately.
+                state.open_until = None;
+                false
+            }
+            None => false,
+        }
+    }
+
+    fn record_success(&self) {
+        let mut state = self.state.lock().expect("breaker lock poisoned");
+        state.consecutive_failures = 0;
+        state.open_until = None;
+    }
+
+    fn record_failure(&self) {
+        let mut state = self.state.lock().expect("breaker lock poisoned");
+        state.consecutive_failures += 1;
+        if state.consecutive_failures >= BREAKER_THRESHOLD {
+            state.open_until = Some(Instant::now() + Duration::from_secs(BREAKER_OPEN_SECS));
+        }
+    }
+}
+
+/// Sliding-window request limiter. Every request timestamp inside the
+/// window is kept, so the limit holds over any sixty-second span rather
+/// than resetting on a fixed boundary. One limiter is shared by all
+/// providers configured with the same API key, since that is what the
+/// provider's quota is keyed on.
+struct RateLimiter {
+    limit: usize,
+    window: Duration,
+    // tokio's Instant rather than std's, so the limiter follows the
+    // paused test clock.
+    sent: Mutex<std::collections::VecDeque<tokio::time::Instant>>,
+}
+
+impl RateLimiter {
+    fn new(requests_per_minute: u32) -> Self {
+        Self {
+            limit: requests_per_minute.max(1) as usize,
+            window: Duration::from_secs(60),
+            sent: Mutex::new(std::collections::VecDeque::new()),
+        }
+    }
+
+    /// Wait until a request slot is free, claim it, and report how long
+    /// the wait was. The lock is never held across the sleep, so waiters
+    /// racing for a freed slot re-check rather than double-claim it.
+    async fn acquire(&self) -> Duration {
+        let started = tokio::time::Instant::now();
+        loop {
+            let wait = {
+                let mut sent = self.sent.lock().expect("limiter lock poisoned");
+                let now = tokio::time::Instant::now();
+                while sent
+                    .front()
+                    .is_some_and(|&at| now.duration_since(at) >= self.window)
+                {
+                    sent.pop_front();
+                }
+                if sent.len() < self.limit {
+                    sent.push_back(now);
+                    return started.elapsed();
+                }
+                self.window - now.duration_since(*sent.front().expect("queue is at its limit"))
+            };
+            tokio::time::sleep(wait).await;
+        }
+    }
+}
+
+/// One entry in the fallback chain.
+struct Provider {
+    config: LlmConfig,
+    client: reqwest::Client,
+    breaker: CircuitBreaker,
+    limiter: Option<Arc<RateLimiter>>,
+}
+
+impl Provider {
+    fn new(config: LlmConfig, limiter: Option<Arc<RateLimiter>>) -> Self {
+        let client = reqwest::Client::builder()
+            .timeout(Duration::from_secs(config.timeout_secs))
+            .build()
+            .expect("reqwest client construction cannot fail with these options");
+        Self {
+            config,
+            client,
+            breaker: CircuitBreaker::new(),
+            limiter,
+        }
+    }
+
+    /// Metrics and log label, e.g. `anthropic/claude-3-5-sonnet-latest`.
+    fn label(&self) -> String {
+        format!("{}/{}", self.config.provider, self.config.model)
+    }
+
+    async fn complete(
+        &self,
+        system: &str,
+        prompt: &str,
+        progress: &watch::Sender<GenerationProgress>,
+    ) -> Result<Completion, ProviderError> {
+        let prompt = truncate_to_context(prompt, self.prompt_budget_chars(system));
+        match (self.config.provider.as_str(), self.config.stream) {
+            ("anthropic", false) => self.send_anthropic_request(system, &prompt).await,
+            ("anthropic", true) => self.stream_anthropic(system, &prompt, progress).await,
+            ("local", false) => self.send_local_request(system, &prompt).await,
+            ("local", true) => self.stream_local(system, &prompt, progress).await,
+            (other, _) => Err(ProviderError::Fatal(anyhow!(
+                "unknown llm provider {other:?}"
+            ))),
+        }
+    }
+
+    /// Characters available for the user prompt, assuming roughly four
+    /// characters per token and leaving room for the reply.
+    fn prompt_budget_chars(&self, system: &str) -> usize {
+        let window = self.config.context_window.saturating_sub(self.config.max_tokens) as usize;
+        (window * 4).saturating_sub(system.len())
+    }
+
+    fn local_endpoint(&self) -> Result<&str> {
+        self.config
+            .endpoint
+            .as_deref()
+            .context("the local provider requires an endpoint base URL")
+            .map(|e| e.trim_end_matches('/'))
+    }
+
+    /// OpenAI-compatible chat completion against an Ollama or vLLM server.
+    /// A bearer token is only attached when the key env var is set; local
+    /// servers usually run without auth.
+    async fn send_local_request(
+        &self,
+        system: &str,
+        prompt: &str,
+    ) -> Result<Completion, ProviderError> {
+        let endpoint = self.local_endpoint().map_err(ProviderError::Fatal)?;
+        let body = json!({
+            "model": self.config.model,
+            "max_tokens": self.config.max_tokens,
+            "stream": false,
+            "messages": [
+                { "role": "system", "content": system },
+                { "role": "user", "content": prompt },
+            ],
+        });
+        let mut request = self
+            .client
+            .post(format!("{endpoint}/v1/chat/completions"))
+            .json(&body);
+        if let Ok(key) = std::env::var(&self.config.api_key_env) {
+            request = request.bearer_auth(key);
+        }
+        let response = request
+            .send()
+            .await
+            .map_err(|e| ProviderError::Retryable(anyhow!(e).context("local llm request failed")))?;
+        let text = check_status(response).await?;
+        parse_openai(&text).map_err(ProviderError::Fatal)
+    }
+
+    async fn send_anthropic_request(
+        &self,
+        system: &str,
+        prompt: &str,
+    ) -> Result<Completion, ProviderError> {
+        let api_key = std::env::var(&self.config.api_key_env)
+            .with_context(|| format!("api key env var {} is not set", self.config.api_key_env))
+            .map_err(ProviderError::Fatal)?;
+        let endpoint = self
+            .config
+            .endpoint
+            .as_deref()
+            .unwrap_or(ANTHROPIC_ENDPOINT);
+        let body = json!({
+            "model": self.config.model,
+            "max_tokens": self.config.max_tokens,
+            "stream": false,
+            "system": system,
+            "messages": [{ "role": "user", "content": prompt }],
+        });
+        let response = self
+            .client
+            .post(format!("{endpoint}/v1/messages"))
+            .header("x-api-key", api_key)
+            .header("anthropic-version", ANTHROPIC_VERSION)
+            .json(&body)
+            .send()
+            .await
+            .map_err(|e| ProviderError::Retryable(anyhow!(e).context("anthropic request failed")))?;
+        let text = check_status(response).await?;
+        parse_anthropic(&text).map_err(ProviderError::Fatal)
+    }
+
+    /// Streamed OpenAI-compatible completion (SSE).
+    async fn stream_local(
+        &self,
+        system: &str,
+        prompt: &str,
+        progress: &watch::Sender<GenerationProgress>,
+    ) -> Result<Completion, ProviderError> {
+        let endpoint = self.local_endpoint().map_err(ProviderError::Fatal)?;
+        let body = json!({
+            "model": self.config.model,
+            "max_tokens": self.config.max_tokens,
+            "stream": true,
+            "messages": [
+                { "role": "system", "content": system },
+                { "role": "user", "content": prompt },
+            ],
+        });
+        let mut request = self
+            .client
+            .post(format!("{endpoint}/v1/chat/completions"))
+            .json(&body);
+        if let Ok(key) = std::env::var(&self.config.api_key_env) {
+            request = request.bearer_auth(key);
+        }
+        let response = request
+            .send()
+            .await
+            .map_err(|e| ProviderError::Retryable(anyhow!(e).context("local llm request failed")))?;
+        if !response.status().is_success() {
+            return Err(check_status(response).await.expect_err("status checked"));
+        }
+        let (text, mut usage, cancelled) = self
+            .consume_stream(response, progress, |data, usage| {
+                let v: serde_json::Value = serde_json::from_str(data).ok()?;
+                if let Some(u) = v.get("usage") {
+                    usage.input_tokens = u["prompt_tokens"].as_u64().unwrap_or(usage.input_tokens);
+                    usage.output_tokens =
+                        u["completion_tokens"].as_u64().unwrap_or(usage.output_tokens);
+                }
+                v["choices"][0]["delta"]["content"]
+                    .as_str()
+                    .map(str::to_string)
+            })
+            .await?;
+        // A cancelled stream never delivers the final usage frame.
+        if cancelled || (usage.input_tokens == 0 && usage.output_tokens == 0) {
+            usage = estimate_usage(system, prompt, &text);
+        }
+        Ok(Completion {
+            text,
+            usage,
+            provider: String::new(),
+            cost_usd: 0.0,
+        })
+    }
+
+    /// Streamed Anthropic messages API completion (SSE).
+    async fn stream_anthropic(
+        &self,
+        system: &str,
+        prompt: &str,
+        progress: &watch::Sender<GenerationProgress>,
+    ) -> Result<Completion, ProviderError> {
+        let api_key = std::env::var(&self.config.api_key_env)
+            .with_context(|| format!("api key env var {} is not set", self.config.api_key_env))
+            .map_err(ProviderError::Fatal)?;
+        let endpoint = self
+            .config
+            .endpoint
+            .as_deref()
+            .unwrap_or(ANTHROPIC_ENDPOINT);
+        let body = json!({
+            "model": self.config.model,
+            "max_tokens": self.config.max_tokens,
+            "stream": true,
+            "system": system,
+            "messages": [{ "role": "user", "content": prompt }],
+        });
+        let response = self
+            .client
+            .post(format!("{endpoint}/v1/messages"))
+            .header("x-api-key", api_key)
+            .header("anthropic-version", ANTHROPIC_VERSION)
+            .json(&body)
+            .send()
+            .await
+            .map_err(|e| ProviderError::Retryable(anyhow!(e).context("anthropic request failed")))?;
+        if !response.status().is_success() {
+            return Err(check_status(response).await.expect_err("status checked"));
+        }
+        let (text, mut usage, cancelled) = self
+            .consume_stream(response, progress, |data, usage| {
+                let v: serde_json::Value = serde_json::from_str(data).ok()?;
+                match v["type"].as_str() {
+                    Some("message_start") => {
+                        usage.input_tokens = v["message"]["usage"]["input_tokens"]
+                            .as_u64()
+                            .unwrap_or(usage.input_tokens);
+                        None
+                    }
+                    Some("message_delta") => {
+                        usage.output_tokens =
+                            v["usage"]["output_tokens"].as_u64().unwrap_or(usage.output_tokens);
+                        None
+                    }
+                    Some("content_block_delta") => v["delta"]["text"].as_str().map(str::to_string),
+                    _ => None,
+                }
+            })
+            .await?;
+        if cancelled && usage.output_tokens == 0 {
+            usage.output_tokens = estimate_usage(system, prompt, &text).output_tokens;
+        }
+        Ok(Completion {
+            text,
+            usage,
+            provider: String::new(),
+            cost_usd: 0.0,
+        })
+    }
+
+    /// Read an SSE body line by line, feeding each `data:` payload to the
+    /// provider-specific parser, and drop the connection as soon as the
+    /// reply's diff section is complete. Returns the accumulated text, the
+    /// usage reported so far, and whether the stream was cancelled early.
+    async fn consume_stream<F>(
+        &self,
+        response: reqwest::Response,
+        progress: &watch::Sender<GenerationProgress>,
+        mut on_data: F,
+    ) -> Result<(String, TokenUsage, bool), ProviderError>
+    where
+        F: FnMut(&str, &mut TokenUsage) -> Option<String>,
+    {
+        let label = self.label();
+        let mut stream = response.bytes_stream();
+        let mut buf = String::new();
+        let mut text = String::new();
+        let mut usage = TokenUsage::default();
+        let mut cancelled = false;
+        'outer: while let Some(chunk) = stream.next().await {
+            let chunk = chunk
+                .map_err(|e| ProviderError::Retryable(anyhow!(e).context("stream read failed")))?;
+            buf.push_str(&String::from_utf8_lossy(&chunk));
+            while let Some(pos) = buf.find('\n') {
+                let line: String = buf.drain(..=pos).collect();
+                let Some(data) = line.trim().strip_prefix("data:") else {
+                    continue;
+                };
+                let data = data.trim();
+                if data == "[DONE]" {
+                    break 'outer;
+                }
+                if let Some(delta) = on_data(data, &mut usage) {
+                    text.push_str(&delta);
+                    progress.send_replace(GenerationProgress {
+                        provider: label.clone(),
+                        chars_streamed: text.len(),
+                        active: true,
+                    });
+                    if diff_complete(&text) {
+                        debug!("{label}: diff section complete, cancelling stream early");
+                        cancelled = true;
+                        break 'outer;
+                    }
+                }
+            }
+        }
+        Ok((text, usage, cancelled))
+    }
+}
+
+/// Rough 4-chars-per-token estimate for streams cancelled before the
+/// provider reported usage.
+fn estimate_usage(system: &str, prompt: &str, text: &str) -> TokenUsage {
+    TokenUsage {
+        input_tokens: ((system.len() + prompt.len()) / 4) as u64,
+        output_tokens: (text.len() / 4) as u64,
+    }
+}
+
+/// True once a streamed reply contains a finished diff: a closed code
+/// fence, or a bare diff followed by a blank line and prose. Anything
+/// after that point is commentary not worth paying for.
+fn diff_complete(text: &str) -> bool {
+    if let Some(start) = text.find("```") {
+        let after = &text[start + 3..];
+        return match after.split_once('\n') {
+            Some((_, body)) => body.contains("```"),
+            None => false,
+        };
+    }
+    if let Some(hunk) = text.rfind("\n@@") {
+        // Hunk lines start with ' ', '+', '-', '@', or '\'; a blank line
+        // followed by anything else means the diff ended.
+        let mut lines = text[hunk + 1..].lines().skip(1).peekable();
+        while let Some(line) = lines.next() {
+            if line.is_empty() && lines.peek().is_some_and(|next| {
+                !next.is_empty() && !matches!(next.as_bytes()[0], b' ' | b'+' | b'-' | b'@' | b'\\')
+            }) {
+                return true;
+            }
+        }
+    }
+    false
+}
+
+/// Map an HTTP response to retryable/fatal and hand back the body.
+async fn check_status(response: reqwest::Response) -> Result<String, ProviderError> {
+    let status = response.status();
+    let text = response.text().await.unwrap_or_default();
+    if status.is_success() {
+        return Ok(text);
+    }
+    let error = anyhow!("provider returned {status}: {}", text.trim());
+    if status.as_u16() == 429 || status.is_server_error() {
+        Err(ProviderError::Retryable(error))
+    } else {
+        Err(ProviderError::Fatal(error))
+    }
+}
+
+/// Database-backed completion cache, consulted before any provider is
+/// tried. Keys are content addresses of the full request, so it only ever
+/// serves a reply to the byte-identical prompt it was stored for.
+struct ResponseCache {
+    database: Database,
+    ttl_secs: u64,
+}
+
+/// Content address of a request: the model and both prompt parts, so any
+/// change to either produces a fresh completion.
+fn cache_key(model: &str, system: &str, prompt: &str) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(model.as_bytes());
+    hasher.update([0]);
+    hasher.update(system.as_bytes());
+    hasher.update([0]);
+    hasher.update(prompt.as_bytes());
+    hex::encode(hasher.finalize())
+}
+
+pub struct LlmClient {
+    providers: Vec<Provider>,
+    metrics: Arc<MetricsCollector>,
+    progress: watch::Sender<GenerationProgress>,
+    cache: Option<ResponseCache>,
+    input_tokens: AtomicU64,
+    output_tokens: AtomicU64,
+}
+
+impl LlmClient {
+    pub fn new(config: LlmConfig, metrics: Arc<MetricsCollector>, database: Option<Database>) -> Self {
+        let cache = match (config.cache_ttl_secs, database) {
+            (Some(ttl_secs), Some(database)) => Some(ResponseCache { database, ttl_secs }),
+            _ => None,
+        };
+        let mut configs = vec![config.clone()];
+        configs.extend(config.fallbacks);
+        let (progress, _) = watch::channel(GenerationProgress::default());
+        // Providers sharing an API key share a limiter; the first
+        // configured limit for a key wins.
+        let mut limiters: std::collections::HashMap<String, Arc<RateLimiter>> =
+            std::collections::HashMap::new();
+        let providers = configs
+            .into_iter()
+            .map(|config| {
+                let limiter = config.requests_per_minute.map(|rpm| {
+                    limiters
+                        .entry(config.api_key_env.clone())
+                        .or_insert_with(|| Arc::new(RateLimiter::new(rpm)))
+                        .clone()
+                });
+                Provider::new(config, limiter)
+            })
+            .collect();
+        Self {
+            providers,
+            metrics,
+            progress,
+            cache,
+            input_tokens: AtomicU64::new(0),
+            output_tokens: AtomicU64::new(0),
+        }
+    }
+
+    /// Cache key for a request, addressed by the primary model even when a
+    /// fallback ends up answering; the stored provider label records who
+    /// did.
+    fn cache_key_for(&self, system: &str, prompt: &str) -> String {
+        let model = self
+            .providers
+            .first()
+            .map(|p| p.config.model.as_str())
+            .unwrap_or("");
+        cache_key(model, system, prompt)
+    }
+
+    /// The latest streamed-generation progress event.
+    pub fn progress_snapshot(&self) -> GenerationProgress {
+        self.progress.borrow().clone()
+    }
+
+    /// Verify the providers are reachable. Hosted providers are probed
+    /// implicitly on first use; local servers get a real check so a wrong
+    /// base URL surfaces at startup rather than mid-incident.
+    pub async fn health_probe(&self) -> Result<()> {
+        for provider in &self.providers {
+            if provider.config.provider != "local" {
+                continue;
+            }
+            let endpoint = provider.local_endpoint()?;
+            let response = provider
+                .client
+                .get(format!("{endpoint}/v1/models"))
+                .send()
+                .await
+                .with_context(|| format!("local llm server at {endpoint} is unreachable"))?;
+            if !response.status().is_success() {
+                bail!("local llm server returned {}", response.status());
+            }
+        }
+        Ok(())
+    }
+
+    /// Send a prompt through the fallback chain: the primary provider
+    /// first, then each fallback when the failure was retryable and the
+    /// breaker is closed.
+    pub async fn complete(&self, system: &str, prompt: &str) -> Result<Completion> {
+        if let Some(cache) = &self.cache {
+            let key = self.cache_key_for(system, prompt);
+            match cache.database.llm_cache_get(&key, cache.ttl_secs).await {
+                Ok(Some((provider, text))) => {
+                    debug!("serving completion from cache (originally {provider})");
+                    self.metrics.observe_llm_cache(true);
+                    return Ok(Completion {
+                        text,
+                        usage: TokenUsage::default(),
+                        provider: format!("cache/{provider}"),
+                        cost_usd: 0.0,
+                    });
+                }
+                Ok(None) => self.metrics.observe_llm_cache(false),
+                Err(e) => warn!("llm cache lookup failed: {e:#}"),
+            }
+        }
+        let mut last_error: Option<anyhow::Error> = None;
+        for provider in &self.providers {
+            let label = provider.label();
+            if provider.breaker.is_open() {
+                warn!("skipping {label}: circuit breaker is open");
+                continue;
+            }
+            if let Some(limiter) = &provider.limiter {
+                let waited = limiter.acquire().await;
+                self.metrics.observe_llm_wait(&label, waited.as_secs_f64());
+                if !waited.is_zero() {
+                    debug!("{label}: waited {waited:?} for a rate-limit slot");
+                }
+            }
+            self.metrics.observe_llm_request(&label);
+            let result = provider.complete(system, prompt, &self.progress).await;
+            let chars_streamed = self.progress.borrow().chars_streamed;
+            self.progress.send_replace(GenerationProgress {
+                provider: label.clone(),
+                chars_streamed,
+                active: false,
+            });
+            match result {
+                Ok(mut completion) => {
+                    provider.breaker.record_success();
+                    self.input_tokens
+                        .fetch_add(completion.usage.input_tokens, Ordering::Relaxed);
+                    self.output_tokens
+                        .fetch_add(completion.usage.output_tokens, Ordering::Relaxed);
+                    completion.cost_usd = crate::costs::cost_usd(
+                        provider.config.input_cost_per_mtok,
+                        provider.config.output_cost_per_mtok,
+                        completion.usage,
+                    );
+                    self.metrics
+                        .observe_llm_cost(&label, completion.usage, completion.cost_usd);
+                    completion.provider = label;
+                    if let Some(cache) = &self.cache {
+                        let key = self.cache_key_for(system, prompt);
+                        if let Err(e) = cache
+                            .database
+                            .llm_cache_put(&key, &completion.provider, &completion.text)
+                            .await
+                        {
+                            warn!("llm cache store failed: {e:#}");
+                        }
+                    }
+                    return Ok(completion);
+                }
+                Err(ProviderError::Retryable(e)) => {
+                    self.metrics.observe_llm_error(&label);
+                    provider.breaker.record_failure();
+                    warn!("{label} failed, trying next provider: {e:#}");
+                    last_error = Some(e);
+                }
+                Err(ProviderError::Fatal(e)) => {
+                    self.metrics.observe_llm_error(&label);
+                    return Err(e.context(format!("{label} failed")));
+                }
+            }
+        }
+        match last_error {
+            Some(e) => Err(e.context("all llm providers failed")),
+            None => bail!("all llm providers have open circuit breakers"),
+        }
+    }
+
+    /// Characters of user prompt the primary provider can accept, for
+    /// callers sizing context before the prompt is assembled.
+    pub fn context_chars(&self) -> usize {
+        self.providers
+            .first()
+            .map(|p| p.prompt_budget_chars(""))
+            .unwrap_or(24_000)
+    }
+
+    /// Daily spend cap, taken from the primary provider's configuration.
+    pub fn daily_budget_usd(&self) -> Option<f64> {
+        self.providers.first().and_then(|p| p.config.daily_budget_usd)
+    }
+
+    /// Tokens consumed since the daemon started, across all providers.
+    pub fn usage(&self) -> TokenUsage {
+        TokenUsage {
+            input_tokens: self.input_tokens.load(Ordering::Relaxed),
+            output_tokens: self.output_tokens.load(Ordering::Relaxed),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct AnthropicResponse {
+    content: Vec<AnthropicBlock>,
+    usage: AnthropicUsage,
+}
+
+#[derive(Deserialize)]
+struct AnthropicBlock {
+    #[serde(rename = "type")]
+    kind: String,
+    #[serde(default)]
+    text: String,
+}
+
+#[derive(Deserialize)]
+struct AnthropicUsage {
+    input_tokens: u64,
+    output_tokens: u64,
+}
+
+#[derive(Deserialize)]
+struct OpenAiResponse {
+    choices: Vec<OpenAiChoice>,
+    #[serde(default)]
+    usage: Option<OpenAiUsage>,
+}
+
+#[derive(Deserialize)]
+struct OpenAiChoice {
+    message: OpenAiMessage,
+}
+
+#[derive(Deserialize)]
+struct OpenAiMessage {
+    #[serde(default)]
+    content: String,
+}
+
+#[derive(Deserialize)]
+struct OpenAiUsage {
+    #[serde(default)]
+    prompt_tokens: u64,
+    #[serde(default)]
+    completion_tokens: u64,
+}
+
+fn parse_openai(body: &str) -> Result<Completion> {
+    let response: OpenAiResponse =
+        serde_json::from_str(body).context("malformed openai-compatible response")?;
+    let text = response
+        .choices
+        .first()
+        .map(|c| c.message.content.clone())
+        .context("response contained no choices")?;
+    let usage = response.usage.map_or_else(TokenUsage::default, |u| TokenUsage {
+        input_tokens: u.prompt_tokens,
+        output_tokens: u.completion_tokens,
+    });
+    Ok(Completion {
+        text,
+        usage,
+        provider: String::new(),
+        cost_usd: 0.0,
+    })
+}
+
+fn parse_anthropic(body: &str) -> Result<Completion> {
+    let response: AnthropicResponse =
+        serde_json::from_str(body).context("malformed anthropic response")?;
+    let text = response
+        .content
+        .iter()
+        .filter(|b| b.kind == "text")
+        .map(|b| b.text.as_str())
+        .collect::<Vec<_>>()
+        .join("");
+    Ok(Completion {
+        text,
+        usage: TokenUsage {
+            input_tokens: response.usage.input_tokens,
+            output_tokens: response.usage.output_tokens,
+        },
+        provider: String::new(),
+        cost_usd: 0.0,
+    })
+}
+
+/// Keep the head and tail of an oversized prompt; the middle (usually long
+/// file bodies) is the least informative part to lose.
+fn truncate_to_context(prompt: &str, max_chars: usize) -> String {
+    if prompt.len() <= max_chars || max_chars == 0 {
+        return prompt.to_string();
+    }
+    const MARKER: &str = "\n[... truncated to fit the context window ...]\n";
+    let keep = max_chars.saturating_sub(MARKER.len());
+    let head_len = keep * 2 / 3;
+    let tail_len = keep - head_len;
+    let head_end = floor_char_boundary(prompt, head_len);
+    let tail_start = floor_char_boundary(prompt, prompt.len() - tail_len);
+    format!("{}{MARKER}{}", &prompt[..head_end], &prompt[tail_start..])
+}
+
+fn floor_char_boundary(s: &str, mut index: usize) -> usize {
+    while index > 0 && !s.is_char_boundary(index) {
+        index -= 1;
+    }
+    index
+}
+
+/// Pull a unified diff out of a model reply, tolerating code fences and
+/// surrounding prose.
+pub fn extract_diff(text: &str) -> String {
+    if let Some(start) = text.find("```") {
+        let after = &text[start + 3..];
+        // Skip a language tag like ```diff on the fence line.
+        let body = after.split_once('\n').map(|(_, rest)| rest).unwrap_or(after);
+        if let Some(end) = body.find("```") {
+            return body[..end].trim_end().to_string() + "\n";
+        }
+    }
+    // No fence: take everything from the first diff header.
+    match text.find("--- ") {
+        Some(start) => text[start..].trim_end().to_string() + "\n",
+        None => text.trim().to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_anthropic_response_with_usage() {
+        let body = r#"{
+            "content": [
+                {"type": "text", "text": "--- a/x\n"},
+                {"type": "text", "text": "+++ b/x\n"}
+            ],
+            "usage": {"input_tokens": 120, "output_tokens": 45}
+        }"#;
+        let completion = parse_anthropic(body).unwrap();
+        assert_eq!(completion.text, "--- a/x\n+++ b/x\n");
+        assert_eq!(completion.usage.input_tokens, 120);
+        assert_eq!(completion.usage.output_tokens, 45);
+    }
+
+    #[test]
+    fn parses_openai_compatible_response() {
+        let body = r#"{
+            "choices": [{"message": {"role": "assistant", "content": "--- a/x\n+++ b/x\n"}}],
+            "usage": {"prompt_tokens": 200, "completion_tokens": 30, "total_tokens": 230}
+        }"#;
+        let completion = parse_openai(body).unwrap();
+        assert_eq!(completion.text, "--- a/x\n+++ b/x\n");
+        assert_eq!(completion.usage.input_tokens, 200);
+        assert_eq!(completion.usage.output_tokens, 30);
+    }
+
+    #[test]
+    fn truncation_keeps_head_and_tail() {
+        let prompt = format!("HEAD{}TAIL", "x".repeat(10_000));
+        let truncated = truncate_to_context(&prompt, 500);
+        assert!(truncated.len() <= 500);
+        assert!(truncated.starts_with("HEAD"));
+        assert!(truncated.ends_with("TAIL"));
+        assert!(truncated.contains("truncated to fit"));
+        // Small prompts pass through untouched.
+        assert_eq!(truncate_to_context("short", 500), "short");
+    }
+
+    #[test]
+    fn breaker_opens_after_threshold_and_resets_on_success() {
+        let breaker = CircuitBreaker::new();
+        assert!(!breaker.is_open());
+        for _ in 0..BREAKER_THRESHOLD - 1 {
+            breaker.record_failure();
+            assert!(!breaker.is_open());
+        }
+        breaker.record_failure();
+        assert!(breaker.is_open());
+        breaker.record_success();
+        assert!(!breaker.is_open());
+    }
+
+    #[test]
+    fn cache_keys_distinguish_model_and_both_prompt_parts() {
+        let base = cache_key("model", "system", "prompt");
+        assert_eq!(base, cache_key("model", "system", "prompt"));
+        assert_ne!(base, cache_key("other", "system", "prompt"));
+        assert_ne!(base, cache_key("model", "other", "prompt"));
+        assert_ne!(base, cache_key("model", "system", "other"));
+        // Moving bytes across the field boundary must change the key too.
+        assert_ne!(cache_key("ab", "c", ""), cache_key("a", "bc", ""));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn rate_limiter_delays_requests_over_the_sliding_window() {
+        let limiter = RateLimiter::new(2);
+        assert!(limiter.acquire().await.is_zero());
+        assert!(limiter.acquire().await.is_zero());
+        // The third request must wait for the first slot to slide out.
+        let waited = limiter.acquire().await;
+        assert!(waited >= Duration::from_secs(59), "waited only {waited:?}");
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn rate_limiter_frees_slots_as_the_window_slides() {
+        let limiter = RateLimiter::new(1);
+        assert!(limiter.acquire().await.is_zero());
+        tokio::time::sleep(Duration::from_secs(61)).await;
+        assert!(limiter.acquire().await.is_zero());
+    }
+
+    #[test]
+    fn diff_complete_detects_closed_fence() {
+        assert!(!diff_complete("Here is the fix:\n```diff\n--- a/x\n+++ b/x\n"));
+        assert!(diff_complete(
+            "Here is the fix:\n```diff\n--- a/x\n+++ b/x\n@@ -1 +1 @@\n-a\n+b\n```"
+        ));
+        // The opening fence line itself must not count as the close.
+        assert!(!diff_complete("```diff"));
+    }
+
+    #[test]
+    fn diff_complete_detects_prose_after_bare_diff() {
+        let still_going = "--- a/x\n+++ b/x\n@@ -1,2 +1,2 @@\n-a\n+b\n context\n";
+        assert!(!diff_complete(still_going));
+        let finished = format!("{still_going}\nThis change renames the variable.");
+        assert!(diff_complete(&finished));
+    }
+
+    #[test]
+    fn extracts_diff_from_fenced_reply() {
+        let reply = "Here is the fix:\n```diff\n--- a/src/main.rs\n+++ b/src/main.rs\n@@ -1 +1 @@\n-old\n+new\n```\nLet me know.";
+        let diff = extract_diff(reply);
+        assert!(diff.starts_with("--- a/src/main.rs\n"));
+        assert!(diff.ends_with("+new\n"));
+        assert!(!diff.contains("```"));
+    }
+
+    #[test]
+    fn extracts_bare_diff_after_prose() {
+        let reply = "The change below fixes it.\n--- a/x\n+++ b/x\n@@ -1 +1 @@\n-a\n+b";
+        assert!(extract_diff(reply).starts_with("--- a/x\n"));
+    }
+}
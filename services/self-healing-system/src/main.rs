@@ -0,0 +1,361 @@
+//! self-healing-system: ingests build and test failures as issues, tracks
+//! candidate patches, and applies validated fixes under operator control.
+
+mod alerts;
+mod api;
+mod breaking_changes;
+mod changelog;
+mod config;
+mod context_builder;
+mod costs;
+mod daemon;
+mod database;
+mod dep_upgrade;
+mod fixers;
+mod fuzz_gen;
+mod git_ops;
+mod leader;
+mod llm_integration;
+mod metrics;
+mod minimizer;
+mod ownership;
+mod patch_generator;
+mod policy;
+mod prompts;
+mod pull_request;
+mod report;
+mod retention;
+mod review;
+mod scheduler;
+mod security_scan;
+mod simulate;
+mod snapshot;
+mod static_analysis;
+mod test_gen;
+mod test_repo;
+mod tui;
+mod types;
+mod validator;
+mod watcher;
+
+use anyhow::Result;
+use api::ApiServer;
+use clap::{Parser, Subcommand};
+use config::HealingConfig;
+use daemon::SelfHealingDaemon;
+use std::path::PathBuf;
+
+#[derive(Parser)]
+#[command(name = "self-healing-system", about = "Aurum self-healing daemon")]
+struct Cli {
+    /// Path to the configuration file.
+    #[arg(long, default_value = "self-healing.json")]
+    config: PathBuf,
+    /// Override a config value over the file and environment, e.g.
+    /// `--set web.port=9000`; repeatable.
+    #[arg(long = "set", value_name = "KEY=VALUE")]
+    set: Vec<String>,
+    /// React to filesystem changes and CI webhooks instead of only
+    /// polling.
+    #[arg(long)]
+    watch: bool,
+    /// Analyze, generate, and validate as usual, but never commit, push,
+    /// or write to a target repository; apply requests return a plan of
+    /// what would have happened instead.
+    #[arg(long)]
+    dry_run: bool,
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Shrink a reproducing test case in place with delta debugging,
+    /// removing lines while the given command keeps failing. Interrupted
+    /// runs resume from a state file next to the test.
+    Minimize {
+        /// Test file to minimize.
+        #[arg(long)]
+        file: PathBuf,
+        /// Command run with `sh -c` after each candidate is written; a
+        /// non-zero exit means the candidate still reproduces the failure.
+        #[arg(long)]
+        command: String,
+    },
+    /// Write a report over the stored reproduction test cases.
+    Report {
+        /// Report format: json, junit, or html.
+        #[arg(long, default_value = "json")]
+        format: String,
+        /// Directory the report file is written into.
+        #[arg(long, default_value = "reports")]
+        output: PathBuf,
+    },
+    /// Inspect the stored reproduction test cases.
+    Tests {
+        #[command(subcommand)]
+        action: TestsCommand,
+    },
+    /// Run the complete pipeline for an issue — context building,
+    /// generation, sandbox validation — without applying anything, and
+    /// print a consolidated report for sharing before deciding to apply.
+    Simulate {
+        /// Issue to simulate a fix for.
+        #[arg(long)]
+        issue: uuid::Uuid,
+        /// Report format: markdown or json.
+        #[arg(long, default_value = "markdown")]
+        format: String,
+    },
+    /// Review pending patches in an interactive terminal UI: colored
+    /// diffs and validation summaries next to each patch, with
+    /// approve/reject/regenerate on keyboard shortcuts.
+    Review {
+        /// Name recorded on the verdicts.
+        #[arg(long, default_value = "cli")]
+        reviewer: String,
+    },
+    /// Inspect and restore the workspace snapshots taken before healing
+    /// actions.
+    Snapshots {
+        #[command(subcommand)]
+        action: SnapshotsCommand,
+    },
+    /// Dump every live and archived issue and patch as JSONL, for
+    /// compliance requests.
+    Export {
+        /// File the JSONL export is written to.
+        #[arg(long)]
+        output: PathBuf,
+        /// Only include records updated or archived at/after this
+        /// RFC 3339 timestamp.
+        #[arg(long)]
+        since: Option<String>,
+    },
+    /// Print the effective configuration — defaults, file, environment,
+    /// and --set overrides merged — as JSON, with secrets redacted.
+    PrintEffectiveConfig,
+}
+
+#[derive(Subcommand)]
+enum SnapshotsCommand {
+    /// Recent snapshots as JSON, newest first.
+    List {
+        /// Only snapshots of this project.
+        #[arg(long)]
+        project: Option<String>,
+        #[arg(long, default_value_t = 20)]
+        limit: i64,
+    },
+    /// Restore the project's working tree to a snapshot, verifying the
+    /// recorded checksums against the restored files.
+    Restore {
+        id: uuid::Uuid,
+    },
+    /// Drop a snapshot: its record and the ref pinning its commit, so
+    /// git gc can reclaim the content.
+    Discard {
+        id: uuid::Uuid,
+    },
+}
+
+#[derive(Subcommand)]
+enum TestsCommand {
+    /// Stored test cases as JSON, newest first.
+    List {
+        /// Only test cases reproducing failures of this service.
+        #[arg(long)]
+        service: Option<String>,
+        #[arg(long, default_value_t = 20)]
+        limit: i64,
+    },
+    /// Aggregate counts: totals, minimization, runs, and per-service
+    /// breakdown.
+    Stats,
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    aurum_telemetry::init(aurum_telemetry::TelemetryConfig::from_env(
+        "self-healing-system",
+        "self_healing_system=info,warn",
+    ))?;
+
+    let cli = Cli::parse();
+    let config = HealingConfig::load_with(&cli.config, &cli.set)?;
+
+    if let Some(Command::Minimize { file, command }) = &cli.command {
+        let original = std::fs::read_to_string(file)?;
+        let state_path = file.with_extension("minimize.json");
+        let mut minimizer = minimizer::TestCaseMinimizer::new(&original, &state_path)?;
+        let mut executor = |candidate: &str| -> Result<bool> {
+            std::fs::write(file, candidate)?;
+            let status = std::process::Command::new("sh")
+                .args(["-c", command])
+                .status()?;
+            Ok(!status.success())
+        };
+        match minimizer.minimize(&mut executor) {
+            Ok(minimized) => {
+                std::fs::write(file, &minimized)?;
+                // When the original came out of the test repository, store
+                // the shrunken form and the confirming run there too.
+                let database = match &config.database_url {
+                    Some(url) => database::Database::connect(url).await?,
+                    None => database::Database::open(&config.database_path).await?,
+                };
+                let tests = test_repo::TestCaseRepository::new(database);
+                if let Some(case) = tests
+                    .find_by_hash(&test_repo::content_hash(&original))
+                    .await?
+                {
+                    tests.mark_minimized(case.id, &minimized).await?;
+                    tests.record_run(case.id, true).await?;
+                }
+                println!(
+                    "minimized {} from {} to {} lines",
+                    file.display(),
+                    original.lines().count(),
+                    minimized.lines().count()
+                );
+                return Ok(());
+            }
+            Err(e) => {
+                // Leave the original in place; the state file keeps the
+                // progress for the next run.
+                std::fs::write(file, &original)?;
+                return Err(e);
+            }
+        }
+    }
+
+    if let Some(Command::Report { format, output }) = &cli.command {
+        let format = report::ReportFormat::parse(format)?;
+        let database = match &config.database_url {
+            Some(url) => database::Database::connect(url).await?,
+            None => database::Database::open(&config.database_path).await?,
+        };
+        let generator = report::ReportGenerator::gather(&database).await?;
+        let path = generator.write(format, output)?;
+        println!("wrote {} report to {}", format.as_str(), path.display());
+        return Ok(());
+    }
+
+    if let Some(Command::Tests { action }) = &cli.command {
+        let database = match &config.database_url {
+            Some(url) => database::Database::connect(url).await?,
+            None => database::Database::open(&config.database_path).await?,
+        };
+        let tests = test_repo::TestCaseRepository::new(database);
+        match action {
+            TestsCommand::List { service, limit } => {
+                let cases = tests.list(service.as_deref(), *limit).await?;
+                println!("{}", serde_json::to_string_pretty(&cases)?);
+            }
+            TestsCommand::Stats => {
+                let stats = tests.stats().await?;
+                println!("{}", serde_json::to_string_pretty(&stats)?);
+            }
+        }
+        return Ok(());
+    }
+
+    if let Some(Command::Simulate { issue, format }) = &cli.command {
+        // Simulation must never touch the target repo, so the daemon is
+        // forced into dry-run mode regardless of the flag.
+        let daemon = SelfHealingDaemon::new(config, true).await?;
+        let report = daemon.simulate(*issue).await?;
+        match format.as_str() {
+            "markdown" => println!("{}", report.to_markdown()),
+            "json" => println!("{}", serde_json::to_string_pretty(&report)?),
+            other => anyhow::bail!("unknown format {other} (expected markdown or json)"),
+        }
+        return Ok(());
+    }
+
+    if let Some(Command::Review { reviewer }) = &cli.command {
+        // Verdicts mutate patch state, so the TUI runs against a real
+        // (non-dry-run) daemon; regeneration still needs the leader lease.
+        let daemon = SelfHealingDaemon::new(config, cli.dry_run).await?;
+        tui::run(&daemon, reviewer).await?;
+        return Ok(());
+    }
+
+    if let Some(Command::Snapshots { action }) = &cli.command {
+        let database = match &config.database_url {
+            Some(url) => database::Database::connect(url).await?,
+            None => database::Database::open(&config.database_path).await?,
+        };
+        match action {
+            SnapshotsCommand::List { project, limit } => {
+                let snapshots = database
+                    .recent_snapshots(project.as_deref(), *limit)
+                    .await?;
+                println!("{}", serde_json::to_string_pretty(&snapshots)?);
+            }
+            SnapshotsCommand::Restore { id } => {
+                // Restores are pure git work against the recorded
+                // snapshot commit, so they also run without a daemon.
+                let mut found = database
+                    .snapshot_by_id(*id)
+                    .await?
+                    .ok_or_else(|| anyhow::anyhow!("no snapshot {id}"))?;
+                let project = config.project(&found.project);
+                snapshot::restore(&project.path, &found)?;
+                found.restored_at = Some(chrono::Utc::now());
+                database.record_snapshot(&found).await?;
+                println!(
+                    "restored {} to snapshot {} ({})",
+                    project.path.display(),
+                    found.id,
+                    found.reason
+                );
+            }
+            SnapshotsCommand::Discard { id } => {
+                let found = database
+                    .snapshot_by_id(*id)
+                    .await?
+                    .ok_or_else(|| anyhow::anyhow!("no snapshot {id}"))?;
+                let project = config.project(&found.project);
+                snapshot::discard(&project.path, *id)?;
+                database.delete_snapshot(*id).await?;
+                println!("discarded snapshot {id}");
+            }
+        }
+        return Ok(());
+    }
+
+    if let Some(Command::Export { output, since }) = &cli.command {
+        let database = match &config.database_url {
+            Some(url) => database::Database::connect(url).await?,
+            None => database::Database::open(&config.database_path).await?,
+        };
+        let written = retention::export(&database, output, since.as_deref()).await?;
+        println!("exported {written} records to {}", output.display());
+        return Ok(());
+    }
+
+    if let Some(Command::PrintEffectiveConfig) = &cli.command {
+        println!("{}", serde_json::to_string_pretty(&config)?);
+        return Ok(());
+    }
+
+    let daemon = SelfHealingDaemon::new(config, cli.dry_run).await?;
+
+    let server = ApiServer::new(daemon.clone());
+    tokio::spawn(async move {
+        if let Err(e) = server.serve().await {
+            tracing::error!("api server exited: {e:#}");
+        }
+    });
+
+    // On ctrl-c, release the leader lease so a standby replica takes over
+    // immediately.
+    tokio::select! {
+        result = daemon.clone().run(cli.watch) => result,
+        _ = tokio::signal::ctrl_c() => {
+            daemon.resign_leadership().await;
+            Ok(())
+        }
+    }
+}
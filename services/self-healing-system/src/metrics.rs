@@ -0,0 +1,172 @@
+//! Prometheus metrics for the daemon, served at `/metrics`.
+
+use crate::llm_integration::TokenUsage;
+use crate::types::ValidationResult;
+use anyhow::Result;
+use prometheus::{
+    CounterVec, Encoder, Histogram, HistogramOpts, HistogramVec, IntCounterVec, IntGauge, Opts,
+    Registry, TextEncoder,
+};
+
+pub struct MetricsCollector {
+    registry: Registry,
+    issues_total: IntCounterVec,
+    open_issues: IntGauge,
+    patches_total: IntCounterVec,
+    validation_build_seconds: Histogram,
+    validation_test_seconds: Histogram,
+    llm_requests: IntCounterVec,
+    llm_errors: IntCounterVec,
+    llm_tokens: IntCounterVec,
+    llm_cost_usd: CounterVec,
+    llm_wait_seconds: HistogramVec,
+    llm_cache_total: IntCounterVec,
+}
+
+impl MetricsCollector {
+    pub fn new() -> Result<Self> {
+        let registry = Registry::new();
+        let issues_total = IntCounterVec::new(
+            Opts::new("self_healing_issues_total", "Issues ingested by classification"),
+            &["classification"],
+        )?;
+        let open_issues = IntGauge::new(
+            "self_healing_open_issues",
+            "Issues currently waiting for a patch",
+        )?;
+        let patches_total = IntCounterVec::new(
+            Opts::new("self_healing_patches_total", "Patch transitions by status"),
+            &["status"],
+        )?;
+        let llm_requests = IntCounterVec::new(
+            Opts::new("self_healing_llm_requests_total", "LLM requests by provider"),
+            &["provider"],
+        )?;
+        let llm_errors = IntCounterVec::new(
+            Opts::new("self_healing_llm_errors_total", "Failed LLM requests by provider"),
+            &["provider"],
+        )?;
+        let validation_build_seconds = Histogram::with_opts(
+            HistogramOpts::new(
+                "self_healing_validation_build_seconds",
+                "Wall-clock build time when validating a patch",
+            )
+            .buckets(prometheus::exponential_buckets(1.0, 2.0, 10)?),
+        )?;
+        let validation_test_seconds = Histogram::with_opts(
+            HistogramOpts::new(
+                "self_healing_validation_test_seconds",
+                "Wall-clock test time when validating a patch",
+            )
+            .buckets(prometheus::exponential_buckets(1.0, 2.0, 10)?),
+        )?;
+        registry.register(Box::new(issues_total.clone()))?;
+        registry.register(Box::new(open_issues.clone()))?;
+        registry.register(Box::new(patches_total.clone()))?;
+        registry.register(Box::new(validation_build_seconds.clone()))?;
+        registry.register(Box::new(validation_test_seconds.clone()))?;
+        let llm_tokens = IntCounterVec::new(
+            Opts::new("self_healing_llm_tokens_total", "Tokens by provider and direction"),
+            &["provider", "direction"],
+        )?;
+        let llm_cost_usd = CounterVec::new(
+            Opts::new("self_healing_llm_cost_usd_total", "Estimated LLM spend in USD by provider"),
+            &["provider"],
+        )?;
+        let llm_wait_seconds = HistogramVec::new(
+            HistogramOpts::new(
+                "self_healing_llm_wait_seconds",
+                "Time spent waiting for a rate-limit slot by provider",
+            )
+            .buckets(prometheus::exponential_buckets(0.1, 2.0, 10)?),
+            &["provider"],
+        )?;
+        registry.register(Box::new(llm_requests.clone()))?;
+        registry.register(Box::new(llm_errors.clone()))?;
+        registry.register(Box::new(llm_tokens.clone()))?;
+        registry.register(Box::new(llm_cost_usd.clone()))?;
+        let llm_cache_total = IntCounterVec::new(
+            Opts::new("self_healing_llm_cache_total", "Completion cache lookups by result"),
+            &["result"],
+        )?;
+        registry.register(Box::new(llm_wait_seconds.clone()))?;
+        registry.register(Box::new(llm_cache_total.clone()))?;
+        Ok(Self {
+            registry,
+            issues_total,
+            open_issues,
+            patches_total,
+            validation_build_seconds,
+            validation_test_seconds,
+            llm_requests,
+            llm_errors,
+            llm_tokens,
+            llm_cost_usd,
+            llm_wait_seconds,
+            llm_cache_total,
+        })
+    }
+
+    pub fn observe_issue(&self, classification: &str) {
+        self.issues_total
+            .with_label_values(&[classification])
+            .inc();
+    }
+
+    pub fn set_open_issues(&self, count: i64) {
+        self.open_issues.set(count);
+    }
+
+    pub fn observe_patch(&self, status: &str) {
+        self.patches_total.with_label_values(&[status]).inc();
+    }
+
+    pub fn observe_validation(&self, result: &ValidationResult) {
+        self.validation_build_seconds
+            .observe(result.build_time_ms as f64 / 1000.0);
+        // A zero test time means the build failed and tests never ran;
+        // recording it would skew the histogram toward zero.
+        if result.test_time_ms > 0 {
+            self.validation_test_seconds
+                .observe(result.test_time_ms as f64 / 1000.0);
+        }
+    }
+
+    pub fn observe_llm_request(&self, provider: &str) {
+        self.llm_requests.with_label_values(&[provider]).inc();
+    }
+
+    pub fn observe_llm_error(&self, provider: &str) {
+        self.llm_errors.with_label_values(&[provider]).inc();
+    }
+
+    pub fn observe_llm_wait(&self, provider: &str, seconds: f64) {
+        self.llm_wait_seconds
+            .with_label_values(&[provider])
+            .observe(seconds);
+    }
+
+    pub fn observe_llm_cache(&self, hit: bool) {
+        let result = if hit { "hit" } else { "miss" };
+        self.llm_cache_total.with_label_values(&[result]).inc();
+    }
+
+    pub fn observe_llm_cost(&self, provider: &str, usage: TokenUsage, cost_usd: f64) {
+        self.llm_tokens
+            .with_label_values(&[provider, "input"])
+            .inc_by(usage.input_tokens);
+        self.llm_tokens
+            .with_label_values(&[provider, "output"])
+            .inc_by(usage.output_tokens);
+        self.llm_cost_usd
+            .with_label_values(&[provider])
+            .inc_by(cost_usd);
+    }
+
+    /// Render everything in the Prometheus text exposition format.
+    pub fn gather(&self) -> Result<String> {
+        let mut buf = Vec::new();
+        TextEncoder::new().encode(&self.registry.gather(), &mut buf)?;
+        Ok(String::from_utf8(buf)?)
+    }
+}
@@ -0,0 +1,189 @@
+//! Delta-debugging minimizer for reproducing test cases.
+//!
+//! A generated or hand-written reproduction usually carries more setup
+//! than the failure needs. The minimizer shrinks it with the classic
+//! ddmin algorithm: split the lines into chunks, try each chunk alone and
+//! each chunk's complement, keep whichever still fails, and raise the
+//! granularity when nothing can be removed. Every accepted reduction is
+//! persisted to a state file, so an interrupted run resumes where it
+//! stopped instead of re-paying for the runs that got there.
+
+use anyhow::{ensure, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use tracing::info;
+
+/// Runs a candidate test case and reports whether it still reproduces the
+/// failure. Implemented for closures so callers can wire in whatever
+/// write-and-run step fits; fallible because running tests can break for
+/// reasons unrelated to the failure under study.
+pub trait TestExecutor {
+    fn still_fails(&mut self, candidate: &str) -> Result<bool>;
+}
+
+impl<F> TestExecutor for F
+where
+    F: FnMut(&str) -> Result<bool>,
+{
+    fn still_fails(&mut self, candidate: &str) -> Result<bool> {
+        self(candidate)
+    }
+}
+
+/// The resumable position of a minimization: what is left of the test
+/// case and how finely it is currently being split.
+#[derive(Serialize, Deserialize)]
+struct MinimizerState {
+    lines: Vec<String>,
+    granularity: usize,
+}
+
+pub struct TestCaseMinimizer {
+    state: MinimizerState,
+    state_path: PathBuf,
+}
+
+impl TestCaseMinimizer {
+    /// Start minimizing `test_case`, or resume from the state persisted at
+    /// `state_path` when an earlier run was interrupted.
+    pub fn new(test_case: &str, state_path: &Path) -> Result<Self> {
+        let state = match std::fs::read_to_string(state_path) {
+            Ok(raw) => {
+                let state: MinimizerState = serde_json::from_str(&raw)
+                    .with_context(|| format!("corrupt minimizer state {}", state_path.display()))?;
+                info!(
+                    lines = state.lines.len(),
+                    "resuming minimization from persisted state"
+                );
+                state
+            }
+            Err(_) => MinimizerState {
+                lines: test_case.lines().map(str::to_string).collect(),
+                granularity: 2,
+            },
+        };
+        Ok(Self {
+            state,
+            state_path: state_path.to_path_buf(),
+        })
+    }
+
+    /// Run ddmin to completion and return the 1-minimal test case: no
+    /// single remaining chunk can be removed without losing the failure.
+    /// The state file is deleted on completion.
+    pub fn minimize<E: TestExecutor>(&mut self, executor: &mut E) -> Result<String> {
+        ensure!(
+            executor.still_fails(&join(&self.state.lines))?,
+            "the starting test case does not fail; nothing to minimize"
+        );
+        'outer: while self.state.lines.len() > 1 {
+            let len = self.state.lines.len();
+            let n = self.state.granularity.min(len);
+            let chunk_size = len.div_ceil(n);
+            let chunks: Vec<&[String]> = self.state.lines.chunks(chunk_size).collect();
+
+            // Reduce to a failing chunk first: the biggest single cut.
+            for chunk in &chunks {
+                if chunk.len() < len && executor.still_fails(&join(chunk))? {
+                    self.state.lines = chunk.to_vec();
+                    self.state.granularity = 2;
+                    self.persist()?;
+                    continue 'outer;
+                }
+            }
+            // Otherwise try removing one chunk at a time.
+            for skip in 0..chunks.len() {
+                let complement: Vec<String> = chunks
+                    .iter()
+                    .enumerate()
+                    .filter(|(i, _)| *i != skip)
+                    .flat_map(|(_, chunk)| chunk.iter().cloned())
+                    .collect();
+                if !complement.is_empty() && executor.still_fails(&join(&complement))? {
+                    self.state.lines = complement;
+                    self.state.granularity = (n - 1).max(2);
+                    self.persist()?;
+                    continue 'outer;
+                }
+            }
+            // Nothing removable at this granularity; split finer or stop.
+            if n >= len {
+                break;
+            }
+            self.state.granularity = (n * 2).min(len);
+            self.persist()?;
+        }
+        let _ = std::fs::remove_file(&self.state_path);
+        Ok(join(&self.state.lines))
+    }
+
+    fn persist(&self) -> Result<()> {
+        let raw = serde_json::to_string(&self.state)?;
+        std::fs::write(&self.state_path, raw)
+            .with_context(|| format!("cannot persist state to {}", self.state_path.display()))
+    }
+}
+
+fn join(lines: &[String]) -> String {
+    let mut out = lines.join("\n");
+    out.push('\n');
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// "Fails" while both marker lines survive, mimicking a reproduction
+    /// whose failure needs two distant statements.
+    fn needs_both(candidate: &str) -> Result<bool> {
+        Ok(candidate.contains("let a = 1;") && candidate.contains("assert_eq!(a, 2);"))
+    }
+
+    #[test]
+    fn shrinks_to_the_failing_lines_alone() {
+        let dir = tempfile::tempdir().unwrap();
+        let state = dir.path().join("state.json");
+        let case = "use std::fmt;\n// setup\nlet a = 1;\nlet unused = 0;\nprintln!(\"{unused}\");\nassert_eq!(a, 2);\n// teardown\n";
+        let mut minimizer = TestCaseMinimizer::new(case, &state).unwrap();
+        let minimized = minimizer.minimize(&mut needs_both).unwrap();
+        assert_eq!(minimized, "let a = 1;\nassert_eq!(a, 2);\n");
+        assert!(!state.exists(), "state must be cleaned up on completion");
+    }
+
+    #[test]
+    fn refuses_a_passing_starting_point() {
+        let dir = tempfile::tempdir().unwrap();
+        let state = dir.path().join("state.json");
+        let mut minimizer = TestCaseMinimizer::new("all good\n", &state).unwrap();
+        assert!(minimizer.minimize(&mut needs_both).is_err());
+    }
+
+    #[test]
+    fn resumes_from_persisted_state_after_an_interruption() {
+        let dir = tempfile::tempdir().unwrap();
+        let state = dir.path().join("state.json");
+        // The failing pair sits in the first chunk, so the very first
+        // accepted reduction is what gets interrupted and persisted.
+        let case = "let a = 1;\nassert_eq!(a, 2);\nnoise one\nnoise two\n";
+
+        // Interrupt by erroring once the first reduction was persisted.
+        let mut minimizer = TestCaseMinimizer::new(case, &state).unwrap();
+        let mut flaky = |candidate: &str| -> Result<bool> {
+            if state.exists() {
+                anyhow::bail!("interrupted");
+            }
+            needs_both(candidate)
+        };
+        assert!(minimizer.minimize(&mut flaky).is_err());
+        assert!(state.exists(), "interrupted state must be persisted");
+
+        // A fresh minimizer picks the persisted lines up, not the original.
+        let resumed = TestCaseMinimizer::new(case, &state).unwrap();
+        assert!(resumed.state.lines.len() < case.lines().count());
+        let mut minimizer = resumed;
+        let minimized = minimizer.minimize(&mut needs_both).unwrap();
+        assert_eq!(minimized, "let a = 1;\nassert_eq!(a, 2);\n");
+        assert!(!state.exists());
+    }
+}
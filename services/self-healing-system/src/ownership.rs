@@ -0,0 +1,208 @@
+//! Who owns which paths, from CODEOWNERS or a config mapping.
+//!
+//! Issues are tagged with the team owning their affected files when they
+//! are ingested, pull request bodies cc that team, and policy rules can
+//! match on the owner — so an infra-owned crate can allow auto-apply
+//! while payments code never does. Rules configured in `owners` take
+//! precedence over the repository's CODEOWNERS file; with neither,
+//! everything is unowned.
+
+use glob::Pattern;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use tracing::warn;
+
+/// Where a CODEOWNERS file may live, per GitHub's lookup order.
+const CODEOWNERS_LOCATIONS: &[&str] = &[".github/CODEOWNERS", "CODEOWNERS", "docs/CODEOWNERS"];
+
+/// One configured pattern-to-owner entry, the config-level alternative
+/// to a CODEOWNERS file with the same semantics.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OwnershipRule {
+    /// CODEOWNERS-style path pattern: a leading `/` anchors it at the
+    /// repository root, a trailing `/` matches the whole directory, and
+    /// an unanchored pattern matches at any depth.
+    pub pattern: String,
+    /// Owner handle, e.g. `@aurum/infra` or an email address.
+    pub owner: String,
+}
+
+/// An ordered set of ownership rules; as in CODEOWNERS, the last
+/// matching pattern wins.
+pub struct OwnershipMap {
+    rules: Vec<CompiledRule>,
+}
+
+struct CompiledRule {
+    /// The pattern itself.
+    exact: Pattern,
+    /// The pattern as a directory prefix, so `/apps/web` also owns
+    /// `apps/web/src/main.ts`.
+    subtree: Pattern,
+    owner: String,
+}
+
+impl OwnershipMap {
+    /// The ownership rules for one project checkout: the configured
+    /// mapping when present, else the repository's CODEOWNERS file, else
+    /// an empty map.
+    pub fn load(repo_path: &Path, configured: &[OwnershipRule]) -> Self {
+        if !configured.is_empty() {
+            return Self::from_rules(configured);
+        }
+        for location in CODEOWNERS_LOCATIONS {
+            let path = repo_path.join(location);
+            if let Ok(contents) = std::fs::read_to_string(&path) {
+                return Self::parse(&contents);
+            }
+        }
+        Self { rules: Vec::new() }
+    }
+
+    pub fn from_rules(rules: &[OwnershipRule]) -> Self {
+        let rules = rules
+            .iter()
+            .filter_map(|rule| compile(&rule.pattern, &rule.owner))
+            .collect();
+        Self { rules }
+    }
+
+    /// Parse CODEOWNERS text: one pattern and its owners per line, `#`
+    /// comments, later lines overriding earlier ones. Only the first
+    /// owner of each line is kept; it is the one that gets cc'd.
+    pub fn parse(contents: &str) -> Self {
+        let mut rules = Vec::new();
+        for line in contents.lines() {
+            let line = line.split('#').next().unwrap_or("").trim();
+            if line.is_empty() {
+                continue;
+            }
+            let mut parts = line.split_whitespace();
+            let (Some(pattern), Some(owner)) = (parts.next(), parts.next()) else {
+                warn!("CODEOWNERS line {line:?} has no owner; skipping");
+                continue;
+            };
+            if let Some(rule) = compile(pattern, owner) {
+                rules.push(rule);
+            }
+        }
+        Self { rules }
+    }
+
+    /// The owner of one repository-relative path, last match winning.
+    pub fn owner_of(&self, path: &str) -> Option<&str> {
+        let path = path.trim_start_matches("./").trim_start_matches('/');
+        self.rules
+            .iter()
+            .rev()
+            .find(|rule| rule.exact.matches(path) || rule.subtree.matches(path))
+            .map(|rule| rule.owner.as_str())
+    }
+
+    /// The owner for a set of affected files: whoever owns the most of
+    /// them, earliest-seen winning ties. Files nobody owns do not vote.
+    pub fn owner_for(&self, files: &[String]) -> Option<String> {
+        let mut votes: Vec<(&str, usize)> = Vec::new();
+        for file in files {
+            let Some(owner) = self.owner_of(file) else {
+                continue;
+            };
+            match votes.iter_mut().find(|(o, _)| *o == owner) {
+                Some((_, count)) => *count += 1,
+                None => votes.push((owner, 1)),
+            }
+        }
+        votes
+            .iter()
+            .max_by_key(|(_, count)| *count)
+            .map(|(owner, _)| owner.to_string())
+    }
+}
+
+/// Translate one CODEOWNERS pattern into the two globs it is checked
+/// as. The translation covers the common forms; an untranslatable
+/// pattern is dropped with a warning rather than silently matching
+/// nothing else on its line.
+fn compile(pattern: &str, owner: &str) -> Option<CompiledRule> {
+    let anchored = pattern.starts_with('/');
+    let base = pattern.trim_start_matches('/').trim_end_matches('/');
+    let glob = if anchored || pattern.trim_end_matches('/').contains('/') {
+        base.to_string()
+    } else {
+        // A bare name or extension pattern matches at any depth.
+        format!("**/{base}")
+    };
+    let exact = Pattern::new(&glob);
+    let subtree = Pattern::new(&format!("{glob}/**"));
+    match (exact, subtree) {
+        (Ok(exact), Ok(subtree)) => Some(CompiledRule {
+            exact,
+            subtree,
+            owner: owner.to_string(),
+        }),
+        _ => {
+            warn!("unusable ownership pattern {pattern:?}; skipping");
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CODEOWNERS: &str = "\
+        # fallback first, per CODEOWNERS convention\n\
+        * @aurum/platform\n\
+        *.sql @aurum/data\n\
+        /services/payments/ @aurum/payments @aurum/security # never auto-apply\n\
+        docs/ @aurum/docs\n";
+
+    #[test]
+    fn last_matching_pattern_wins() {
+        let map = OwnershipMap::parse(CODEOWNERS);
+        assert_eq!(map.owner_of("src/lib.rs"), Some("@aurum/platform"));
+        assert_eq!(
+            map.owner_of("services/payments/src/charge.rs"),
+            Some("@aurum/payments")
+        );
+        assert_eq!(
+            map.owner_of("services/payments/migrations/0001.sql"),
+            Some("@aurum/payments")
+        );
+        // Unanchored directory patterns match at any depth.
+        assert_eq!(map.owner_of("apps/web/docs/readme.md"), Some("@aurum/docs"));
+        assert!(OwnershipMap::parse("").owner_of("src/lib.rs").is_none());
+    }
+
+    #[test]
+    fn most_owned_files_decide_the_issue_owner() {
+        let map = OwnershipMap::parse(CODEOWNERS);
+        let files = vec![
+            "services/payments/src/charge.rs".to_string(),
+            "services/payments/src/refund.rs".to_string(),
+            "src/lib.rs".to_string(),
+        ];
+        assert_eq!(map.owner_for(&files), Some("@aurum/payments".to_string()));
+        assert_eq!(map.owner_for(&[]), None);
+    }
+
+    #[test]
+    fn configured_rules_override_codeowners() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("CODEOWNERS"), "* @aurum/platform\n").unwrap();
+        let configured = vec![OwnershipRule {
+            pattern: "/infra/".to_string(),
+            owner: "@aurum/infra".to_string(),
+        }];
+        let map = OwnershipMap::load(dir.path(), &configured);
+        assert_eq!(map.owner_of("infra/terraform/main.tf"), Some("@aurum/infra"));
+        // The fallback rule from CODEOWNERS was not loaded.
+        assert!(map.owner_of("src/lib.rs").is_none());
+
+        let from_file = OwnershipMap::load(dir.path(), &[]);
+        assert_eq!(from_file.owner_of("src/lib.rs"), Some("@aurum/platform"));
+        let unowned = OwnershipMap::load(&dir.path().join("absent"), &[]);
+        assert!(unowned.owner_of("src/lib.rs").is_none());
+    }
+}
@@ -0,0 +1,273 @@
+//! Unified diff parsing and application.
+//!
+//! `git apply` remains the tool of record when a patch touches the working
+//! tree, but generated patches need to be parsed and dry-run applied in
+//! memory first so garbage diffs are rejected before they reach the repo.
+//! This is a small, purpose-built engine: exact context matching first,
+//! then hunk offsets, then patch(1)-style fuzz on the outer context lines.
+
+use anyhow::{bail, Context, Result};
+
+/// Maximum number of leading/trailing context lines ignored when fuzzing.
+const MAX_FUZZ: usize = 2;
+
+/// One line of a hunk body.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DiffLine {
+    Context(String),
+    Removed(String),
+    Added(String),
+}
+
+/// A `@@ -a,b +c,d @@` block and its body.
+#[derive(Debug, Clone)]
+pub struct Hunk {
+    /// 1-based line number in the old file (0 for empty-file additions).
+    pub old_start: usize,
+    pub lines: Vec<DiffLine>,
+}
+
+/// The diff for a single file.
+#[derive(Debug, Clone)]
+pub struct FileDiff {
+    pub old_path: String,
+    pub new_path: String,
+    pub hunks: Vec<Hunk>,
+}
+
+impl FileDiff {
+    /// True when the diff creates the file rather than modifying it.
+    pub fn is_creation(&self) -> bool {
+        self.old_path == "/dev/null"
+    }
+}
+
+/// Parse a unified diff into per-file hunks. `diff --git` and `index`
+/// preamble lines are tolerated and skipped.
+pub fn parse(diff: &str) -> Result<Vec<FileDiff>> {
+    let mut files = Vec::new();
+    let mut lines = diff.lines().peekable();
+    while let Some(line) = lines.next() {
+        let Some(old) = line.strip_prefix("--- ") else {
+            continue;
+        };
+        let new = lines
+            .next()
+            .and_then(|l| l.strip_prefix("+++ "))
+            .context("`---` header without a matching `+++` line")?;
+        let mut file = FileDiff {
+            old_path: strip_git_prefix(old),
+            new_path: strip_git_prefix(new),
+            hunks: Vec::new(),
+        };
+        while lines.peek().is_some_and(|l| l.starts_with("@@ ")) {
+            let header = lines.next().expect("peeked");
+            let mut hunk = Hunk {
+                old_start: parse_hunk_header(header)?,
+                lines: Vec::new(),
+            };
+            while let Some(&body) = lines.peek() {
+                match body.chars().next() {
+                    // Diffs routinely strip trailing whitespace from blank
+                    // context lines, so an empty line still counts as context.
+                    None => hunk.lines.push(DiffLine::Context(String::new())),
+                    Some(' ') => hunk.lines.push(DiffLine::Context(body[1..].to_string())),
+                    Some('-') if !body.starts_with("--- ") => {
+                        hunk.lines.push(DiffLine::Removed(body[1..].to_string()))
+                    }
+                    Some('+') if !body.starts_with("+++ ") => {
+                        hunk.lines.push(DiffLine::Added(body[1..].to_string()))
+                    }
+                    // "\ No newline at end of file"
+                    Some('\\') => {}
+                    _ => break,
+                }
+                lines.next();
+            }
+            if hunk.lines.is_empty() {
+                bail!("empty hunk in diff for {}", file.old_path);
+            }
+            file.hunks.push(hunk);
+        }
+        if file.hunks.is_empty() {
+            bail!("no hunks in diff for {}", file.old_path);
+        }
+        files.push(file);
+    }
+    if files.is_empty() {
+        bail!("no file diffs found");
+    }
+    Ok(files)
+}
+
+/// Apply one file's hunks to `original`, returning the patched content.
+/// Fails cleanly when a hunk's context cannot be located even with offsets
+/// and fuzz, leaving the caller free to reject the patch.
+pub fn apply_diff(original: &str, file: &FileDiff) -> Result<String> {
+    let src: Vec<&str> = original.lines().collect();
+    let mut out: Vec<String> = Vec::new();
+    let mut cursor = 0usize;
+    let mut offset = 0isize;
+    for (index, hunk) in file.hunks.iter().enumerate() {
+        let expected = (hunk.old_start as isize - 1 + offset).max(cursor as isize) as usize;
+        let pos = locate(&src, cursor, expected, hunk).with_context(|| {
+            format!(
+                "hunk #{} of {} does not apply",
+                index + 1,
+                file.new_path
+            )
+        })?;
+        out.extend(src[cursor..pos].iter().map(|s| s.to_string()));
+        cursor = pos;
+        for line in &hunk.lines {
+            match line {
+                // Copy context from the source so fuzzed edges keep the
+                // file's actual text rather than the diff's stale copy.
+                DiffLine::Context(_) => {
+                    out.push(src[cursor].to_string());
+                    cursor += 1;
+                }
+                DiffLine::Removed(_) => cursor += 1,
+                DiffLine::Added(added) => out.push(added.clone()),
+            }
+        }
+        offset = pos as isize - (hunk.old_start as isize - 1);
+    }
+    out.extend(src[cursor..].iter().map(|s| s.to_string()));
+    let mut patched = out.join("\n");
+    if (original.ends_with('\n') || original.is_empty()) && !patched.is_empty() {
+        patched.push('\n');
+    }
+    Ok(patched)
+}
+
+/// Find where a hunk matches: exact context at increasing offsets first,
+/// then retry with fuzz, ignoring up to `MAX_FUZZ` outer context lines.
+fn locate(src: &[&str], min: usize, expected: usize, hunk: &Hunk) -> Result<usize> {
+    let span = hunk
+        .lines
+        .iter()
+        .filter(|l| !matches!(l, DiffLine::Added(_)))
+        .count();
+    for fuzz in 0..=MAX_FUZZ {
+        for delta in 0..=src.len() {
+            for candidate in [expected.checked_sub(delta), Some(expected + delta)]
+                .into_iter()
+                .flatten()
+            {
+                if candidate < min || candidate + span > src.len() {
+                    continue;
+                }
+                if matches_at(src, candidate, hunk, fuzz) {
+                    return Ok(candidate);
+                }
+            }
+        }
+    }
+    bail!("context not found near line {}", expected + 1)
+}
+
+/// Check the old side of a hunk against the source at `pos`. Removed lines
+/// must always match; context lines within `fuzz` of either edge are skipped.
+fn matches_at(src: &[&str], pos: usize, hunk: &Hunk, fuzz: usize) -> bool {
+    let old: Vec<&DiffLine> = hunk
+        .lines
+        .iter()
+        .filter(|l| !matches!(l, DiffLine::Added(_)))
+        .collect();
+    for (i, line) in old.iter().enumerate() {
+        let text = match line {
+            DiffLine::Context(s) => {
+                if i < fuzz || i + fuzz >= old.len() {
+                    continue;
+                }
+                s
+            }
+            DiffLine::Removed(s) => s,
+            DiffLine::Added(_) => unreachable!("filtered above"),
+        };
+        if src[pos + i] != text {
+            return false;
+        }
+    }
+    true
+}
+
+/// Drop git's `a/`/`b/` prefixes and any trailing tab-separated metadata.
+fn strip_git_prefix(path: &str) -> String {
+    let path = path.split('\t').next().unwrap_or(path);
+    if path == "/dev/null" {
+        return path.to_string();
+    }
+    path.strip_prefix("a/")
+        .or_else(|| path.strip_prefix("b/"))
+        .unwrap_or(path)
+        .to_string()
+}
+
+/// Extract the old-side start line from a `@@ -a,b +c,d @@` header.
+fn parse_hunk_header(header: &str) -> Result<usize> {
+    let old = header
+        .split_whitespace()
+        .nth(1)
+        .and_then(|s| s.strip_prefix('-'))
+        .with_context(|| format!("malformed hunk header: {header}"))?;
+    old.split(',')
+        .next()
+        .unwrap_or(old)
+        .parse()
+        .with_context(|| format!("malformed hunk header: {header}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ORIGINAL: &str = "fn main() {\n    let x = 1;\n    let y = 2;\n    println!(\"{}\", x + y);\n}\n";
+
+    #[test]
+    fn applies_simple_hunk() {
+        let diff = "--- a/src/main.rs\n+++ b/src/main.rs\n@@ -1,5 +1,5 @@\n fn main() {\n-    let x = 1;\n+    let x = 10;\n     let y = 2;\n     println!(\"{}\", x + y);\n }\n";
+        let files = parse(diff).unwrap();
+        assert_eq!(files.len(), 1);
+        assert_eq!(files[0].old_path, "src/main.rs");
+        let patched = apply_diff(ORIGINAL, &files[0]).unwrap();
+        assert_eq!(patched, ORIGINAL.replace("let x = 1;", "let x = 10;"));
+    }
+
+    #[test]
+    fn applies_with_offset_when_lines_shifted() {
+        // The hunk claims line 1 but the real match is three lines down.
+        let shifted = format!("// a\n// b\n// c\n{ORIGINAL}");
+        let diff = "--- a/src/main.rs\n+++ b/src/main.rs\n@@ -1,3 +1,3 @@\n fn main() {\n-    let x = 1;\n+    let x = 10;\n     let y = 2;\n";
+        let files = parse(diff).unwrap();
+        let patched = apply_diff(&shifted, &files[0]).unwrap();
+        assert!(patched.contains("let x = 10;"));
+        assert!(patched.starts_with("// a\n"));
+    }
+
+    #[test]
+    fn applies_with_fuzz_when_edge_context_drifted() {
+        // The outer context line no longer matches; removed lines still do.
+        let drifted = ORIGINAL.replace("fn main() {", "fn main() { // entry");
+        let diff = "--- a/src/main.rs\n+++ b/src/main.rs\n@@ -1,3 +1,3 @@\n fn main() {\n-    let x = 1;\n+    let x = 10;\n     let y = 2;\n";
+        let files = parse(diff).unwrap();
+        let patched = apply_diff(&drifted, &files[0]).unwrap();
+        assert!(patched.contains("let x = 10;"));
+        assert!(patched.contains("// entry"));
+    }
+
+    #[test]
+    fn fails_cleanly_on_conflict() {
+        let diff = "--- a/src/main.rs\n+++ b/src/main.rs\n@@ -1,2 +1,2 @@\n fn main() {\n-    let z = 99;\n+    let z = 100;\n";
+        let files = parse(diff).unwrap();
+        let err = apply_diff(ORIGINAL, &files[0]).unwrap_err();
+        assert!(format!("{err:#}").contains("hunk #1"));
+    }
+
+    #[test]
+    fn rejects_garbage_input() {
+        assert!(parse("this is not a diff").is_err());
+        assert!(parse("--- a/x\n+++ b/x\nno hunks here\n").is_err());
+    }
+}
@@ -0,0 +1,499 @@
+//! Per-patch auto-apply policy.
+//!
+//! A validated patch used to face one fixed decision: below the risk
+//! threshold it became applicable, and projects opted into `auto_apply`
+//! skipped the manual apply step. The policy engine keeps that flow as
+//! its built-in behavior but lets operators express richer policies as
+//! ordered rules in a JSON file: match on issue classification, service,
+//! risk score, the files the diff touches, the UTC hour, or how often
+//! the service has failed recently, and decide to auto-apply, queue for
+//! review, or only report. The file is re-read whenever it changes on
+//! disk, so policy edits apply without a daemon restart, and every
+//! decision is logged with the rule that made it. Whatever the rules
+//! say, migrations and auth code are never auto-applied.
+
+use crate::review::RiskAssessment;
+use anyhow::{Context, Result};
+use glob::Pattern;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::SystemTime;
+use tracing::{info, warn};
+
+/// Paths whose patches always go through a human, overriding any
+/// auto-apply rule in the policy file.
+const PROTECTED_GLOBS: &[&str] = &[
+    "migrations/**",
+    "**/migrations/**",
+    "**/auth/**",
+    "**/auth*.rs",
+];
+
+/// What happens to a patch once it passes validation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Decision {
+    /// Apply immediately, without waiting for an operator.
+    AutoApply,
+    /// Hold in the human review queue.
+    QueueReview,
+    /// Record the patch as validated and log the decision; an operator
+    /// applies it manually.
+    ReportOnly,
+}
+
+impl Decision {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Decision::AutoApply => "auto_apply",
+            Decision::QueueReview => "queue_review",
+            Decision::ReportOnly => "report_only",
+        }
+    }
+}
+
+/// One rule. Every condition present must hold for the rule to match;
+/// the first matching rule in file order decides.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PolicyRule {
+    /// Name echoed in the decision log.
+    #[serde(default)]
+    pub name: Option<String>,
+    /// Issue classifications the rule applies to; empty means any.
+    #[serde(default)]
+    pub classifications: Vec<String>,
+    /// Services the rule applies to; empty means any.
+    #[serde(default)]
+    pub services: Vec<String>,
+    /// Owning teams (as resolved from CODEOWNERS or the `owners`
+    /// mapping) the rule applies to; empty means any. An unowned issue
+    /// never matches a rule with owners.
+    #[serde(default)]
+    pub owners: Vec<String>,
+    /// Matches when the risk score is at least this.
+    #[serde(default)]
+    pub min_risk_score: Option<u32>,
+    /// Matches when the risk score is at most this.
+    #[serde(default)]
+    pub max_risk_score: Option<u32>,
+    /// Matches when any touched file matches one of these globs.
+    #[serde(default)]
+    pub file_globs: Vec<String>,
+    /// UTC hour window `[start, end)`; `start > end` wraps midnight,
+    /// e.g. `[22, 6]` for overnight. Equal bounds mean all day.
+    #[serde(default)]
+    pub hours_utc: Option<(u32, u32)>,
+    /// Matches when the service filed at least this many issues in the
+    /// last 24 hours.
+    #[serde(default)]
+    pub min_recent_failures: Option<i64>,
+    pub decision: Decision,
+}
+
+impl PolicyRule {
+    fn matches(&self, input: &PolicyInput) -> bool {
+        if !self.classifications.is_empty()
+            && !self.classifications.iter().any(|c| c == input.classification)
+        {
+            return false;
+        }
+        if !self.services.is_empty() && !self.services.iter().any(|s| s == input.service) {
+            return false;
+        }
+        if !self.owners.is_empty()
+            && !input
+                .owner
+                .is_some_and(|owner| self.owners.iter().any(|o| o == owner))
+        {
+            return false;
+        }
+        if self.min_risk_score.is_some_and(|min| input.risk.score < min) {
+            return false;
+        }
+        if self.max_risk_score.is_some_and(|max| input.risk.score > max) {
+            return false;
+        }
+        if !self.file_globs.is_empty() && !any_glob_matches(&self.file_globs, input.files) {
+            return false;
+        }
+        if let Some((start, end)) = self.hours_utc {
+            if !hour_in_window(input.hour_utc, start, end) {
+                return false;
+            }
+        }
+        if self
+            .min_recent_failures
+            .is_some_and(|min| input.recent_failures < min)
+        {
+            return false;
+        }
+        true
+    }
+}
+
+/// The policy file: rules tried in order, then the default.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PolicyFile {
+    #[serde(default)]
+    pub rules: Vec<PolicyRule>,
+    /// Decision when no rule matches.
+    #[serde(default = "default_decision")]
+    pub default: Decision,
+}
+
+fn default_decision() -> Decision {
+    Decision::ReportOnly
+}
+
+/// The signals a decision is made from.
+#[derive(Debug)]
+pub struct PolicyInput<'a> {
+    pub classification: &'a str,
+    pub service: &'a str,
+    /// Team owning the issue's files, when ownership resolved one.
+    pub owner: Option<&'a str>,
+    /// The review module's assessment of the patch.
+    pub risk: &'a RiskAssessment,
+    /// Repository-relative paths the diff touches.
+    pub files: &'a [String],
+    /// Current hour, 0-23 UTC.
+    pub hour_utc: u32,
+    /// Issues the service filed in the last 24 hours.
+    pub recent_failures: i64,
+    /// The project's `auto_apply` flag, honored by the built-in policy
+    /// when no policy file is configured.
+    pub project_opted_in: bool,
+}
+
+/// The outcome, with the reason that also goes to the log.
+#[derive(Debug, Serialize)]
+pub struct PolicyDecision {
+    pub decision: Decision,
+    pub reason: String,
+}
+
+struct Cached {
+    /// Modification time and size of the file the cached policy was read
+    /// from; a change in either triggers a re-read on the next decision.
+    stamp: Option<(SystemTime, u64)>,
+    file: Option<PolicyFile>,
+}
+
+pub struct PolicyEngine {
+    path: Option<PathBuf>,
+    /// Threshold the built-in policy holds risky patches at when no
+    /// policy file is configured.
+    risk_threshold: u32,
+    cache: Mutex<Cached>,
+}
+
+impl PolicyEngine {
+    pub fn new(path: Option<PathBuf>, risk_threshold: u32) -> Self {
+        Self {
+            path,
+            risk_threshold,
+            cache: Mutex::new(Cached {
+                stamp: None,
+                file: None,
+            }),
+        }
+    }
+
+    /// Decide what happens to a validated patch and log the outcome.
+    pub fn decide(&self, input: &PolicyInput) -> PolicyDecision {
+        let mut decision = match self.current() {
+            Some(file) => evaluate(&file, input),
+            None => self.built_in(input),
+        };
+        if decision.decision == Decision::AutoApply {
+            if let Some(file) = protected_file(input.files) {
+                decision = PolicyDecision {
+                    decision: Decision::QueueReview,
+                    reason: format!(
+                        "{file} is protected; migrations and auth code are never auto-applied"
+                    ),
+                };
+            }
+        }
+        info!(
+            service = input.service,
+            classification = input.classification,
+            risk_score = input.risk.score,
+            recent_failures = input.recent_failures,
+            decision = decision.decision.as_str(),
+            reason = %decision.reason,
+            "policy decision"
+        );
+        decision
+    }
+
+    /// The policy from the configured file, re-read when the file has
+    /// changed on disk. A broken edit keeps the previous rules in force.
+    fn current(&self) -> Option<PolicyFile> {
+        let path = self.path.as_ref()?;
+        let mut cached = self.cache.lock().expect("policy cache poisoned");
+        let stamp = std::fs::metadata(path)
+            .and_then(|m| m.modified().map(|t| (t, m.len())))
+            .ok();
+        if stamp != cached.stamp {
+            match load(path) {
+                Ok(file) => {
+                    info!(
+                        path = %path.display(),
+                        rules = file.rules.len(),
+                        "policy file loaded"
+                    );
+                    cached.file = Some(file);
+                }
+                Err(e) => warn!(
+                    "policy file {} not usable: {e:#}; keeping previous rules",
+                    path.display()
+                ),
+            }
+            cached.stamp = stamp;
+        }
+        cached.file.clone()
+    }
+
+    /// The pre-policy-file flow: protected paths and risky patches go to
+    /// review, and the project's `auto_apply` flag decides the rest.
+    fn built_in(&self, input: &PolicyInput) -> PolicyDecision {
+        if input.risk.requires_review(self.risk_threshold) {
+            PolicyDecision {
+                decision: Decision::QueueReview,
+                reason: format!(
+                    "risk score {} at or above threshold {}",
+                    input.risk.score, self.risk_threshold
+                ),
+            }
+        } else if input.project_opted_in {
+            PolicyDecision {
+                decision: Decision::AutoApply,
+                reason: "project opted into auto-apply".to_string(),
+            }
+        } else {
+            PolicyDecision {
+                decision: Decision::ReportOnly,
+                reason: "project has not opted into auto-apply".to_string(),
+            }
+        }
+    }
+}
+
+/// Parse and validate a policy file; bad globs are a load error rather
+/// than a silently dead rule.
+fn load(path: &Path) -> Result<PolicyFile> {
+    let raw = std::fs::read_to_string(path)
+        .with_context(|| format!("failed to read policy file {}", path.display()))?;
+    let file: PolicyFile = serde_json::from_str(&raw)
+        .with_context(|| format!("failed to parse policy file {}", path.display()))?;
+    for rule in &file.rules {
+        for pattern in &rule.file_globs {
+            Pattern::new(pattern).with_context(|| format!("invalid file glob {pattern:?}"))?;
+        }
+    }
+    Ok(file)
+}
+
+fn evaluate(file: &PolicyFile, input: &PolicyInput) -> PolicyDecision {
+    for (idx, rule) in file.rules.iter().enumerate() {
+        if rule.matches(input) {
+            let label = rule
+                .name
+                .clone()
+                .unwrap_or_else(|| format!("rule #{}", idx + 1));
+            return PolicyDecision {
+                decision: rule.decision,
+                reason: format!("matched {label}"),
+            };
+        }
+    }
+    PolicyDecision {
+        decision: file.default,
+        reason: "no rule matched; policy default".to_string(),
+    }
+}
+
+fn any_glob_matches(globs: &[String], files: &[String]) -> bool {
+    globs
+        .iter()
+        .filter_map(|g| Pattern::new(g).ok())
+        .any(|pattern| files.iter().any(|f| pattern.matches(f)))
+}
+
+fn protected_file(files: &[String]) -> Option<&str> {
+    files
+        .iter()
+        .find(|f| {
+            PROTECTED_GLOBS
+                .iter()
+                .filter_map(|g| Pattern::new(g).ok())
+                .any(|pattern| pattern.matches(f))
+        })
+        .map(String::as_str)
+}
+
+fn hour_in_window(hour: u32, start: u32, end: u32) -> bool {
+    match start.cmp(&end) {
+        std::cmp::Ordering::Less => (start..end).contains(&hour),
+        std::cmp::Ordering::Greater => hour >= start || hour < end,
+        std::cmp::Ordering::Equal => true,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn risk(score: u32) -> RiskAssessment {
+        RiskAssessment {
+            score,
+            reasons: Vec::new(),
+        }
+    }
+
+    fn input<'a>(files: &'a [String], risk: &'a RiskAssessment, opted_in: bool) -> PolicyInput<'a> {
+        PolicyInput {
+            classification: "compiler",
+            service: "api",
+            owner: None,
+            risk,
+            files,
+            hour_utc: 12,
+            recent_failures: 0,
+            project_opted_in: opted_in,
+        }
+    }
+
+    #[test]
+    fn built_in_policy_reproduces_the_legacy_flow() {
+        let engine = PolicyEngine::new(None, 40);
+        let files = vec!["src/lib.rs".to_string()];
+
+        let low = engine.decide(&input(&files, &risk(10), true));
+        assert_eq!(low.decision, Decision::AutoApply);
+
+        let no_opt_in = engine.decide(&input(&files, &risk(10), false));
+        assert_eq!(no_opt_in.decision, Decision::ReportOnly);
+
+        let risky = engine.decide(&input(&files, &risk(40), true));
+        assert_eq!(risky.decision, Decision::QueueReview);
+        assert!(risky.reason.contains("threshold"));
+    }
+
+    #[test]
+    fn migrations_and_auth_code_are_never_auto_applied() {
+        let engine = PolicyEngine::new(None, 40);
+        for path in ["services/api/migrations/0001.sql", "src/auth/session.rs"] {
+            let files = vec![path.to_string()];
+            let decision = engine.decide(&input(&files, &risk(0), true));
+            assert_eq!(decision.decision, Decision::QueueReview, "{path}");
+            assert!(decision.reason.contains("protected"));
+        }
+    }
+
+    #[test]
+    fn first_matching_rule_wins_and_hour_windows_wrap_midnight() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("policy.json");
+        std::fs::write(
+            &path,
+            r#"{
+                "rules": [
+                    { "name": "overnight freeze", "hours_utc": [22, 6], "decision": "queue_review" },
+                    { "name": "flapping service", "min_recent_failures": 5, "decision": "queue_review" },
+                    { "classifications": ["compiler"], "max_risk_score": 20, "decision": "auto_apply" }
+                ],
+                "default": "report_only"
+            }"#,
+        )
+        .unwrap();
+        let engine = PolicyEngine::new(Some(path), 40);
+        let files = vec!["src/lib.rs".to_string()];
+
+        let calm = risk(0);
+        let mut overnight = input(&files, &calm, false);
+        overnight.hour_utc = 23;
+        assert_eq!(engine.decide(&overnight).decision, Decision::QueueReview);
+
+        let mut flapping = input(&files, &calm, false);
+        flapping.recent_failures = 7;
+        let decision = engine.decide(&flapping);
+        assert_eq!(decision.decision, Decision::QueueReview);
+        assert!(decision.reason.contains("flapping service"));
+
+        assert_eq!(
+            engine.decide(&input(&files, &risk(10), false)).decision,
+            Decision::AutoApply
+        );
+        // Too risky for the auto-apply rule, so the default applies.
+        assert_eq!(
+            engine.decide(&input(&files, &risk(30), false)).decision,
+            Decision::ReportOnly
+        );
+    }
+
+    #[test]
+    fn owner_rules_gate_auto_apply_per_team() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("policy.json");
+        std::fs::write(
+            &path,
+            r#"{
+                "rules": [
+                    { "name": "payments never auto-applies", "owners": ["@aurum/payments"], "decision": "queue_review" },
+                    { "owners": ["@aurum/infra"], "max_risk_score": 30, "decision": "auto_apply" }
+                ],
+                "default": "report_only"
+            }"#,
+        )
+        .unwrap();
+        let engine = PolicyEngine::new(Some(path), 40);
+        let files = vec!["src/lib.rs".to_string()];
+        let calm = risk(0);
+
+        let mut payments = input(&files, &calm, true);
+        payments.owner = Some("@aurum/payments");
+        let decision = engine.decide(&payments);
+        assert_eq!(decision.decision, Decision::QueueReview);
+        assert!(decision.reason.contains("payments"));
+
+        let mut infra = input(&files, &calm, false);
+        infra.owner = Some("@aurum/infra");
+        assert_eq!(engine.decide(&infra).decision, Decision::AutoApply);
+
+        // Unowned issues never match a rule with owners.
+        assert_eq!(
+            engine.decide(&input(&files, &calm, false)).decision,
+            Decision::ReportOnly
+        );
+    }
+
+    #[test]
+    fn policy_file_edits_apply_without_a_restart() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("policy.json");
+        std::fs::write(&path, r#"{ "default": "report_only" }"#).unwrap();
+        let engine = PolicyEngine::new(Some(path.clone()), 40);
+        let files = vec!["src/lib.rs".to_string()];
+        let calm = risk(0);
+        assert_eq!(
+            engine.decide(&input(&files, &calm, false)).decision,
+            Decision::ReportOnly
+        );
+
+        std::fs::write(&path, r#"{ "rules": [], "default": "auto_apply" }"#).unwrap();
+        assert_eq!(
+            engine.decide(&input(&files, &calm, false)).decision,
+            Decision::AutoApply
+        );
+
+        // A broken edit keeps the last good policy in force.
+        std::fs::write(&path, "{ not json").unwrap();
+        assert_eq!(
+            engine.decide(&input(&files, &calm, false)).decision,
+            Decision::AutoApply
+        );
+    }
+}
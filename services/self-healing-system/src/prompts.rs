@@ -0,0 +1,287 @@
+//! Prompt templates for patch generation.
+//!
+//! Built-in templates ship per issue classification and can be overridden
+//! by JSON files (`{"system": ..., "user": ...}`) in a configurable
+//! directory, named `<classification>.json`. Placeholders use `{name}`
+//! syntax, are validated against the known variable set at load time, and
+//! edits to the directory are picked up without a restart.
+
+use anyhow::{bail, Context, Result};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::RwLock;
+use std::time::SystemTime;
+use tracing::{info, warn};
+
+/// Variables a template may reference.
+pub const VARIABLES: &[&str] = &["service", "commit", "classification", "log", "files", "sources"];
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct PromptTemplate {
+    pub system: String,
+    pub user: String,
+}
+
+struct RegistryState {
+    templates: HashMap<String, PromptTemplate>,
+    /// Modification times of the files last loaded, for hot reload.
+    mtimes: HashMap<PathBuf, SystemTime>,
+}
+
+pub struct PromptRegistry {
+    dir: Option<PathBuf>,
+    state: RwLock<RegistryState>,
+}
+
+impl PromptRegistry {
+    pub fn new(dir: Option<PathBuf>) -> Self {
+        let mut state = RegistryState {
+            templates: builtin_templates(),
+            mtimes: HashMap::new(),
+        };
+        if let Some(dir) = &dir {
+            if let Err(e) = load_dir(&mut state, dir) {
+                warn!("prompt templates in {} not loaded: {e:#}", dir.display());
+            }
+        }
+        Self {
+            dir,
+            state: RwLock::new(state),
+        }
+    }
+
+    /// Render the template for an issue classification, falling back to the
+    /// default template when no specific one exists.
+    pub fn render(
+        &self,
+        classification: &str,
+        vars: &HashMap<&str, String>,
+    ) -> Result<(String, String)> {
+        self.maybe_reload();
+        let state = self.state.read().expect("registry lock poisoned");
+        let template = state
+            .templates
+            .get(classification)
+            .or_else(|| state.templates.get("default"))
+            .context("no default prompt template registered")?;
+        Ok((
+            interpolate(&template.system, vars)?,
+            interpolate(&template.user, vars)?,
+        ))
+    }
+
+    /// Reload the directory when any template file changed, appeared, or
+    /// disappeared since the last load.
+    fn maybe_reload(&self) {
+        let Some(dir) = &self.dir else {
+            return;
+        };
+        let current = dir_mtimes(dir);
+        {
+            let state = self.state.read().expect("registry lock poisoned");
+            if current == state.mtimes {
+                return;
+            }
+        }
+        let mut state = self.state.write().expect("registry lock poisoned");
+        state.templates = builtin_templates();
+        state.mtimes.clear();
+        match load_dir(&mut state, dir) {
+            Ok(()) => info!("reloaded prompt templates from {}", dir.display()),
+            Err(e) => warn!("prompt template reload failed: {e:#}"),
+        }
+    }
+}
+
+fn dir_mtimes(dir: &Path) -> HashMap<PathBuf, SystemTime> {
+    let mut mtimes = HashMap::new();
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return mtimes;
+    };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path.extension().is_some_and(|e| e == "json") {
+            if let Ok(modified) = entry.metadata().and_then(|m| m.modified()) {
+                mtimes.insert(path, modified);
+            }
+        }
+    }
+    mtimes
+}
+
+fn load_dir(state: &mut RegistryState, dir: &Path) -> Result<()> {
+    for (path, modified) in dir_mtimes(dir) {
+        let name = path
+            .file_stem()
+            .and_then(|s| s.to_str())
+            .context("template file has no usable name")?
+            .to_string();
+        let raw = std::fs::read_to_string(&path)
+            .with_context(|| format!("failed to read {}", path.display()))?;
+        let template: PromptTemplate = serde_json::from_str(&raw)
+            .with_context(|| format!("failed to parse {}", path.display()))?;
+        validate(&template).with_context(|| format!("invalid template {}", path.display()))?;
+        state.templates.insert(name, template);
+        state.mtimes.insert(path, modified);
+    }
+    Ok(())
+}
+
+/// Reject templates referencing variables the daemon will never supply;
+/// better to fail at load than to ship a prompt with a hole in it.
+fn validate(template: &PromptTemplate) -> Result<()> {
+    for text in [&template.system, &template.user] {
+        for name in placeholder_names(text) {
+            if !VARIABLES.contains(&name.as_str()) {
+                bail!("unknown template variable {{{name}}}");
+            }
+        }
+    }
+    Ok(())
+}
+
+fn placeholder_names(text: &str) -> Vec<String> {
+    let mut names = Vec::new();
+    let mut rest = text;
+    while let Some(start) = rest.find('{') {
+        let after = &rest[start + 1..];
+        if let Some(end) = after.find('}') {
+            let name = &after[..end];
+            if is_identifier(name) {
+                names.push(name.to_string());
+                rest = &after[end + 1..];
+                continue;
+            }
+        }
+        rest = after;
+    }
+    names
+}
+
+fn is_identifier(name: &str) -> bool {
+    !name.is_empty() && name.chars().all(|c| c.is_ascii_lowercase() || c == '_')
+}
+
+/// Single-pass substitution, so variable values containing braces (source
+/// code, logs) are never re-expanded.
+fn interpolate(template: &str, vars: &HashMap<&str, String>) -> Result<String> {
+    let mut out = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        out.push_str(&rest[..start]);
+        let after = &rest[start + 1..];
+        if let Some(end) = after.find('}') {
+            let name = &after[..end];
+            if is_identifier(name) {
+                let value = vars
+                    .get(name)
+                    .with_context(|| format!("template variable {{{name}}} was not supplied"))?;
+                out.push_str(value);
+                rest = &after[end + 1..];
+                continue;
+            }
+        }
+        out.push('{');
+        rest = after;
+    }
+    out.push_str(rest);
+    Ok(out)
+}
+
+fn builtin_templates() -> HashMap<String, PromptTemplate> {
+    const SYSTEM: &str = "You fix build, test, and lint failures in a Rust monorepo. \
+                          Reply with only a unified diff using a/ and b/ path prefixes.";
+    let mut templates = HashMap::new();
+    templates.insert(
+        "default".to_string(),
+        PromptTemplate {
+            system: SYSTEM.to_string(),
+            user: "A {classification} failure was reported for service {service} at commit {commit}:\n\n{log}\n\nAffected files:\n{files}\n\nRelevant sources:\n{sources}\nProduce a minimal unified diff that fixes it.".to_string(),
+        },
+    );
+    templates.insert(
+        "compiler".to_string(),
+        PromptTemplate {
+            system: SYSTEM.to_string(),
+            user: "The compiler rejected service {service} at commit {commit}:\n\n{log}\n\nRelevant sources:\n{sources}\nFix the compile error with a minimal unified diff. Do not refactor beyond what the error requires.".to_string(),
+        },
+    );
+    templates.insert(
+        "test".to_string(),
+        PromptTemplate {
+            system: SYSTEM.to_string(),
+            user: "A test is failing for service {service} at commit {commit}:\n\n{log}\n\nRelevant sources:\n{sources}\nFix the code under test with a minimal unified diff. Never weaken or delete the failing assertion.".to_string(),
+        },
+    );
+    templates.insert(
+        "lint".to_string(),
+        PromptTemplate {
+            system: SYSTEM.to_string(),
+            user: "A lint fired for service {service} at commit {commit}:\n\n{log}\n\nRelevant sources:\n{sources}\nApply the mechanical fix the lint asks for as a minimal unified diff.".to_string(),
+        },
+    );
+    templates.insert(
+        "security".to_string(),
+        PromptTemplate {
+            system: SYSTEM.to_string(),
+            user: "A dependency advisory affects service {service} at commit {commit}:\n\n{log}\n\nAffected files:\n{files}\nPropose a unified diff bumping the affected dependency to a patched version in the relevant Cargo.toml.".to_string(),
+        },
+    );
+    templates
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn vars() -> HashMap<&'static str, String> {
+        VARIABLES
+            .iter()
+            .map(|name| (*name, format!("<{name}>")))
+            .collect()
+    }
+
+    #[test]
+    fn interpolates_without_reexpanding_values() {
+        let mut vars = vars();
+        vars.insert("log", "error in fn main() { let x = {y}; }".to_string());
+        let out = interpolate("Log: {log} for {service}", &vars).unwrap();
+        assert_eq!(out, "Log: error in fn main() { let x = {y}; } for <service>");
+    }
+
+    #[test]
+    fn selects_per_classification_with_default_fallback() {
+        let registry = PromptRegistry::new(None);
+        let (_, compiler) = registry.render("compiler", &vars()).unwrap();
+        assert!(compiler.contains("compiler rejected"));
+        let (_, unknown) = registry.render("mystery", &vars()).unwrap();
+        assert!(unknown.contains("A <classification> failure"));
+    }
+
+    #[test]
+    fn rejects_templates_with_unknown_variables() {
+        let template = PromptTemplate {
+            system: "ok".to_string(),
+            user: "uses {nonsense}".to_string(),
+        };
+        assert!(validate(&template).is_err());
+    }
+
+    #[test]
+    fn picks_up_new_template_files_without_restart() {
+        let dir = tempfile::tempdir().unwrap();
+        let registry = PromptRegistry::new(Some(dir.path().to_path_buf()));
+        let (_, before) = registry.render("compiler", &vars()).unwrap();
+        assert!(before.contains("compiler rejected"));
+
+        std::fs::write(
+            dir.path().join("compiler.json"),
+            r#"{"system": "custom system", "user": "custom fix for {service}"}"#,
+        )
+        .unwrap();
+        let (system, after) = registry.render("compiler", &vars()).unwrap();
+        assert_eq!(system, "custom system");
+        assert_eq!(after, "custom fix for <service>");
+    }
+}
@@ -0,0 +1,276 @@
+//! Opens pull requests for patches on GitHub or GitLab.
+//!
+//! In pull-request mode the daemon never commits to the working branch:
+//! the patch is committed on a `self-heal/...` branch, pushed, and a PR
+//! with the explanation, validation report, and a confidence rating is
+//! opened for human review.
+
+use crate::config::PullRequestConfig;
+use crate::test_repo::TestCase;
+use crate::types::{Issue, Patch};
+use anyhow::{bail, Context, Result};
+use serde_json::json;
+
+/// Open a pull request for the pushed branch and return its URL.
+pub async fn open(config: &PullRequestConfig, branch: &str, title: &str, body: &str) -> Result<String> {
+    let token = std::env::var(&config.token_env)
+        .with_context(|| format!("forge token env var {} is not set", config.token_env))?;
+    let client = reqwest::Client::new();
+    let (url, request) = match config.forge.as_str() {
+        "github" => {
+            let request = client
+                .post(github_endpoint(config))
+                .bearer_auth(&token)
+                .header("User-Agent", "self-healing-system")
+                .header("Accept", "application/vnd.github+json")
+                .json(&json!({
+                    "title": title,
+                    "head": branch,
+                    "base": config.base_branch,
+                    "body": body,
+                }));
+            ("html_url", request)
+        }
+        "gitlab" => {
+            let request = client
+                .post(gitlab_endpoint(config))
+                .header("PRIVATE-TOKEN", &token)
+                .json(&json!({
+                    "title": title,
+                    "source_branch": branch,
+                    "target_branch": config.base_branch,
+                    "description": body,
+                }));
+            ("web_url", request)
+        }
+        other => bail!("unknown forge {other:?}"),
+    };
+    let response = request.send().await.context("forge request failed")?;
+    let status = response.status();
+    let payload: serde_json::Value = response
+        .json()
+        .await
+        .context("forge response was not JSON")?;
+    if !status.is_success() {
+        bail!("forge returned {status}: {payload}");
+    }
+    payload
+        .get(url)
+        .and_then(|u| u.as_str())
+        .map(str::to_string)
+        .context("forge response carried no pull request URL")
+}
+
+fn github_endpoint(config: &PullRequestConfig) -> String {
+    let base = config
+        .api_base
+        .as_deref()
+        .unwrap_or("https://api.github.com")
+        .trim_end_matches('/');
+    format!("{base}/repos/{}/pulls", config.repo)
+}
+
+fn gitlab_endpoint(config: &PullRequestConfig) -> String {
+    let base = config
+        .api_base
+        .as_deref()
+        .unwrap_or("https://gitlab.com/api/v4")
+        .trim_end_matches('/');
+    format!("{base}/projects/{}/merge_requests", urlencode(&config.repo))
+}
+
+/// Minimal percent-encoding for GitLab project paths (`group/name`).
+fn urlencode(path: &str) -> String {
+    path.replace('/', "%2F")
+}
+
+pub fn title_for(issue: &Issue) -> String {
+    format!(
+        "self-heal: fix {} failure in {}",
+        issue.classification, issue.service
+    )
+}
+
+/// PR body: what broke, what the patch does, how validation went, and how
+/// much to trust it.
+pub fn body_for(patch: &Patch, issue: &Issue) -> String {
+    let mut body = format!(
+        "Automated fix proposed by the self-healing system.\n\n\
+         **Issue** `{}` ({} / {} at `{}`):\n\n```\n{}\n```\n\n\
+         **Patch** `{}`: {}\n\n",
+        issue.id,
+        issue.service,
+        issue.classification,
+        issue.commit,
+        excerpt(&issue.log, 1500),
+        patch.id,
+        patch.description,
+    );
+    if let Some(owner) = &issue.owner {
+        // Mentioning the owning team subscribes it to the PR on both
+        // forges.
+        body.push_str(&format!("**Owner**: cc {owner}\n\n"));
+    }
+    match &patch.validation {
+        Some(validation) => {
+            body.push_str(&format!(
+                "**Validation**\n\n\
+                 | check | result |\n|---|---|\n\
+                 | build | {} ({} ms) |\n\
+                 | tests | {} ({} ms) |\n\
+                 | new audit findings | {} |\n\n",
+                pass(validation.build_ok),
+                validation.build_time_ms,
+                pass(validation.tests_ok),
+                validation.test_time_ms,
+                validation.security_issues_found,
+            ));
+        }
+        None => body.push_str("**Validation**: not run\n\n"),
+    }
+    body.push_str(&format!("**Confidence**: {}\n", confidence(patch)));
+    body
+}
+
+pub fn promotion_title(case: &TestCase, issue: Option<&Issue>) -> String {
+    match issue {
+        Some(issue) => format!(
+            "self-heal: regression test for {} failure in {}",
+            issue.classification, issue.service
+        ),
+        None => format!("self-heal: regression test for {}", case.service),
+    }
+}
+
+/// PR body for a promoted regression test: the provenance a reviewer
+/// needs to trust that the test guards a real, reproduced failure.
+pub fn promotion_body(case: &TestCase, runs: i64, still_failing: i64) -> String {
+    let mut body = format!(
+        "Promotes a reproduced failure into the regression suite.\n\n\
+         **Test case** `{}`: service {} at commit `{}`\n",
+        case.id, case.service, case.origin_commit,
+    );
+    match case.issue_id {
+        Some(issue_id) => body.push_str(&format!("**Issue**: `{issue_id}`\n")),
+        None => body.push_str("**Issue**: none recorded\n"),
+    }
+    body.push_str(&format!(
+        "**Minimization**: {} → {} lines\n\
+         **Execution history**: {} of {} recorded runs still reproduced the failure\n",
+        case.original_lines,
+        case.content.lines().count(),
+        still_failing,
+        runs,
+    ));
+    body
+}
+
+fn pass(ok: bool) -> &'static str {
+    if ok {
+        "pass"
+    } else {
+        "fail"
+    }
+}
+
+/// Coarse rating for reviewers triaging a queue of automated PRs.
+fn confidence(patch: &Patch) -> &'static str {
+    match &patch.validation {
+        Some(v) if v.passed && v.security_issues_found == 0 => "high (build and tests pass)",
+        Some(v) if v.build_ok => "medium (build passes, tests or audit do not)",
+        Some(_) => "low (build fails)",
+        None => "unvalidated",
+    }
+}
+
+fn excerpt(text: &str, chars: usize) -> &str {
+    let mut cut = text.len().min(chars);
+    while cut < text.len() && !text.is_char_boundary(cut) {
+        cut += 1;
+    }
+    &text[..cut]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::ValidationResult;
+
+    fn fixture() -> (Patch, Issue) {
+        let issue = Issue::new(
+            "build-monitor",
+            "api",
+            "abc123",
+            "compiler",
+            "error[E0308]: mismatched types",
+            vec!["services/api/src/main.rs".to_string()],
+        );
+        let patch = Patch::new(issue.id, "llm-generated fix via anthropic", "--- a/x\n+++ b/x\n");
+        (patch, issue)
+    }
+
+    #[test]
+    fn endpoints_respect_api_base_overrides() {
+        let mut config = PullRequestConfig {
+            forge: "github".to_string(),
+            repo: "acme/aurum".to_string(),
+            remote: "origin".to_string(),
+            base_branch: "main".to_string(),
+            token_env: "GITHUB_TOKEN".to_string(),
+            api_base: None,
+        };
+        assert_eq!(
+            github_endpoint(&config),
+            "https://api.github.com/repos/acme/aurum/pulls"
+        );
+        config.api_base = Some("https://git.example.com/api/v4/".to_string());
+        assert_eq!(
+            gitlab_endpoint(&config),
+            "https://git.example.com/api/v4/projects/acme%2Faurum/merge_requests"
+        );
+    }
+
+    #[test]
+    fn body_reports_validation_and_confidence() {
+        let (mut patch, issue) = fixture();
+        let body = body_for(&patch, &issue);
+        assert!(body.contains("**Validation**: not run"));
+        assert!(body.contains("unvalidated"));
+
+        patch.validation = Some(ValidationResult {
+            passed: true,
+            build_ok: true,
+            tests_ok: true,
+            build_time_ms: 1200,
+            test_time_ms: 800,
+            security_issues_found: 0,
+            security_issues: Vec::new(),
+            detail: None,
+        });
+        let body = body_for(&patch, &issue);
+        assert!(body.contains("| build | pass (1200 ms) |"));
+        assert!(body.contains("high (build and tests pass)"));
+    }
+
+    #[test]
+    fn title_names_the_failure() {
+        let (_, issue) = fixture();
+        assert_eq!(title_for(&issue), "self-heal: fix compiler failure in api");
+    }
+
+    #[test]
+    fn promotion_body_carries_provenance() {
+        let (_, issue) = fixture();
+        let mut case = TestCase::new("api", "abc123", Some(issue.id), "a\nb\nc\nd\n");
+        case.content = "b\n".to_string();
+        case.minimized = true;
+        assert_eq!(
+            promotion_title(&case, Some(&issue)),
+            "self-heal: regression test for compiler failure in api"
+        );
+        let body = promotion_body(&case, 3, 2);
+        assert!(body.contains(&format!("**Issue**: `{}`", issue.id)));
+        assert!(body.contains("**Minimization**: 4 → 1 lines"));
+        assert!(body.contains("2 of 3 recorded runs"));
+    }
+}
@@ -0,0 +1,325 @@
+//! Reports over the stored reproduction test cases.
+//!
+//! The `report` subcommand renders the test repository three ways: JSON
+//! for scripting, JUnit XML so CI can ingest generated-regression results
+//! as a test suite, and a static HTML summary with charts of reproduction
+//! success rates and minimization savings. The rendered file lands in a
+//! configurable output directory.
+
+use crate::database::Database;
+use anyhow::{bail, Context, Result};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fmt::Write as _;
+use std::path::{Path, PathBuf};
+use uuid::Uuid;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReportFormat {
+    Json,
+    Junit,
+    Html,
+}
+
+impl ReportFormat {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ReportFormat::Json => "json",
+            ReportFormat::Junit => "junit",
+            ReportFormat::Html => "html",
+        }
+    }
+
+    pub fn parse(s: &str) -> Result<Self> {
+        match s {
+            "json" => Ok(ReportFormat::Json),
+            "junit" => Ok(ReportFormat::Junit),
+            "html" => Ok(ReportFormat::Html),
+            other => bail!("unknown report format '{other}'; expected json, junit, or html"),
+        }
+    }
+}
+
+/// One stored test case with its execution history rolled in.
+#[derive(Debug, Serialize)]
+pub struct CaseReport {
+    pub id: Uuid,
+    pub service: String,
+    pub issue_id: Option<Uuid>,
+    pub minimized: bool,
+    pub original_lines: i64,
+    pub current_lines: i64,
+    pub runs: i64,
+    /// Runs that still reproduced the failure.
+    pub still_failing: i64,
+}
+
+/// Per-service rollup feeding the HTML charts.
+#[derive(Debug, Serialize)]
+pub struct ServiceSummary {
+    pub service: String,
+    pub cases: i64,
+    pub runs: i64,
+    pub still_failing: i64,
+    /// Share of recorded runs that still reproduced the failure.
+    pub reproduction_rate: f64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Report {
+    pub generated_at: DateTime<Utc>,
+    pub cases: Vec<CaseReport>,
+    pub services: Vec<ServiceSummary>,
+    /// Lines removed by minimization across the whole repository.
+    pub lines_saved: i64,
+}
+
+pub struct ReportGenerator {
+    report: Report,
+}
+
+impl ReportGenerator {
+    /// Gather every stored test case and its run history into a report.
+    pub async fn gather(database: &Database) -> Result<Self> {
+        let cases = database.list_test_cases(None, i64::MAX).await?;
+        let runs: HashMap<Uuid, (i64, i64)> = database
+            .test_case_run_counts()
+            .await?
+            .into_iter()
+            .map(|(id, runs, still_failing)| (id, (runs, still_failing)))
+            .collect();
+
+        let cases: Vec<CaseReport> = cases
+            .into_iter()
+            .map(|case| {
+                let (runs, still_failing) = runs.get(&case.id).copied().unwrap_or((0, 0));
+                CaseReport {
+                    id: case.id,
+                    service: case.service,
+                    issue_id: case.issue_id,
+                    minimized: case.minimized,
+                    original_lines: case.original_lines,
+                    current_lines: case.content.lines().count() as i64,
+                    runs,
+                    still_failing,
+                }
+            })
+            .collect();
+
+        let mut services: HashMap<&str, ServiceSummary> = HashMap::new();
+        for case in &cases {
+            let entry = services
+                .entry(&case.service)
+                .or_insert_with(|| ServiceSummary {
+                    service: case.service.clone(),
+                    cases: 0,
+                    runs: 0,
+                    still_failing: 0,
+                    reproduction_rate: 0.0,
+                });
+            entry.cases += 1;
+            entry.runs += case.runs;
+            entry.still_failing += case.still_failing;
+        }
+        let mut services: Vec<ServiceSummary> = services.into_values().collect();
+        services.sort_by(|a, b| a.service.cmp(&b.service));
+        for summary in &mut services {
+            if summary.runs > 0 {
+                summary.reproduction_rate = summary.still_failing as f64 / summary.runs as f64;
+            }
+        }
+
+        let lines_saved = cases
+            .iter()
+            .filter(|case| case.minimized)
+            .map(|case| (case.original_lines - case.current_lines).max(0))
+            .sum();
+
+        Ok(Self {
+            report: Report {
+                generated_at: Utc::now(),
+                cases,
+                services,
+                lines_saved,
+            },
+        })
+    }
+
+    /// Render the report in `format` and write it into the `output`
+    /// directory (created when missing), returning the written path.
+    pub fn write(&self, format: ReportFormat, output: &Path) -> Result<PathBuf> {
+        std::fs::create_dir_all(output)
+            .with_context(|| format!("cannot create {}", output.display()))?;
+        let (name, rendered) = match format {
+            ReportFormat::Json => (
+                "report.json",
+                serde_json::to_string_pretty(&self.report)? + "\n",
+            ),
+            ReportFormat::Junit => ("report.xml", self.to_junit()),
+            ReportFormat::Html => ("report.html", self.to_html()),
+        };
+        let path = output.join(name);
+        std::fs::write(&path, rendered)
+            .with_context(|| format!("cannot write {}", path.display()))?;
+        Ok(path)
+    }
+
+    /// One JUnit suite with a testcase per stored reproduction. A case
+    /// whose recorded runs still reproduce the failure is a `<failure>`
+    /// (the bug is not fixed yet); a case that was never run is skipped.
+    fn to_junit(&self) -> String {
+        let report = &self.report;
+        let failures = report.cases.iter().filter(|c| c.still_failing > 0).count();
+        let skipped = report.cases.iter().filter(|c| c.runs == 0).count();
+        let mut out = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+        let _ = writeln!(
+            out,
+            "<testsuite name=\"generated-regressions\" tests=\"{}\" failures=\"{failures}\" skipped=\"{skipped}\" timestamp=\"{}\">",
+            report.cases.len(),
+            report.generated_at.to_rfc3339()
+        );
+        for case in &report.cases {
+            let _ = write!(
+                out,
+                "  <testcase classname=\"{}\" name=\"repro_{}\">",
+                escape(&case.service),
+                &case.id.simple().to_string()[..8]
+            );
+            if case.runs == 0 {
+                out.push_str("<skipped/>");
+            } else if case.still_failing > 0 {
+                let _ = write!(
+                    out,
+                    "<failure message=\"reproduction still failing ({} of {} runs)\"/>",
+                    case.still_failing, case.runs
+                );
+            }
+            out.push_str("</testcase>\n");
+        }
+        out.push_str("</testsuite>\n");
+        out
+    }
+
+    /// A self-contained HTML page: no scripts, charts are CSS bars.
+    fn to_html(&self) -> String {
+        let report = &self.report;
+        let mut out = String::from(
+            "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>Reproduction test report</title>\n<style>\nbody { font-family: sans-serif; margin: 2rem; }\ntd, th { padding: 0.25rem 0.75rem; text-align: left; }\n.track { background: #eee; width: 16rem; }\n.bar { background: #4a90d9; height: 1rem; }\n</style>\n</head>\n<body>\n<h1>Reproduction test report</h1>\n",
+        );
+        let _ = writeln!(
+            out,
+            "<p>Generated {}. {} test cases, {} minimized, {} lines saved by minimization.</p>",
+            report.generated_at.to_rfc3339(),
+            report.cases.len(),
+            report.cases.iter().filter(|c| c.minimized).count(),
+            report.lines_saved
+        );
+
+        out.push_str("<h2>Reproduction success rate</h2>\n<table>\n<tr><th>Service</th><th>Cases</th><th>Runs</th><th>Rate</th><th></th></tr>\n");
+        for summary in &report.services {
+            let pct = (summary.reproduction_rate * 100.0).round();
+            let _ = writeln!(
+                out,
+                "<tr><td>{}</td><td>{}</td><td>{}</td><td>{pct}%</td><td class=\"track\"><div class=\"bar\" style=\"width: {pct}%\"></div></td></tr>",
+                escape(&summary.service),
+                summary.cases,
+                summary.runs
+            );
+        }
+        out.push_str("</table>\n");
+
+        out.push_str("<h2>Minimization savings</h2>\n<table>\n<tr><th>Case</th><th>Service</th><th>Lines</th><th></th></tr>\n");
+        let max_saved = report
+            .cases
+            .iter()
+            .filter(|c| c.minimized)
+            .map(|c| (c.original_lines - c.current_lines).max(0))
+            .max()
+            .unwrap_or(0);
+        for case in report.cases.iter().filter(|c| c.minimized) {
+            let saved = (case.original_lines - case.current_lines).max(0);
+            let pct = if max_saved > 0 {
+                saved * 100 / max_saved
+            } else {
+                0
+            };
+            let _ = writeln!(
+                out,
+                "<tr><td>repro_{}</td><td>{}</td><td>{} &rarr; {}</td><td class=\"track\"><div class=\"bar\" style=\"width: {pct}%\"></div></td></tr>",
+                &case.id.simple().to_string()[..8],
+                escape(&case.service),
+                case.original_lines,
+                case.current_lines
+            );
+        }
+        out.push_str("</table>\n</body>\n</html>\n");
+        out
+    }
+}
+
+/// Escape the characters XML and HTML have in common; enough for service
+/// names and messages.
+fn escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_repo::TestCaseRepository;
+
+    async fn seeded() -> ReportGenerator {
+        let db = Database::open_in_memory().await.unwrap();
+        let repo = TestCaseRepository::new(db.clone());
+        let (fixed, _) = repo.store("api", "abc1234", None, "a\nb\nc\nd\n").await.unwrap();
+        repo.mark_minimized(fixed, "b\n").await.unwrap();
+        repo.record_run(fixed, false).await.unwrap();
+        let (open, _) = repo.store("worker", "abc1234", None, "x\ny\n").await.unwrap();
+        repo.record_run(open, true).await.unwrap();
+        repo.store("worker", "def5678", None, "never run\n").await.unwrap();
+        ReportGenerator::gather(&db).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn junit_reports_open_reproductions_as_failures() {
+        let generator = seeded().await;
+        let out = tempfile::tempdir().unwrap();
+        let path = generator.write(ReportFormat::Junit, out.path()).unwrap();
+        let xml = std::fs::read_to_string(path).unwrap();
+        assert!(xml.contains("tests=\"3\" failures=\"1\" skipped=\"1\""));
+        assert!(xml.contains("reproduction still failing (1 of 1 runs)"));
+        assert!(xml.contains("<skipped/>"));
+    }
+
+    #[tokio::test]
+    async fn html_charts_rates_and_minimization_savings() {
+        let generator = seeded().await;
+        let out = tempfile::tempdir().unwrap();
+        let path = generator.write(ReportFormat::Html, out.path()).unwrap();
+        let html = std::fs::read_to_string(path).unwrap();
+        // api's one run no longer reproduces; worker's still does.
+        assert!(html.contains("<td>api</td><td>1</td><td>1</td><td>0%</td>"));
+        assert!(html.contains("<td>worker</td><td>2</td><td>1</td><td>100%</td>"));
+        // The minimized case shrank from 4 lines to 1.
+        assert!(html.contains("3 lines saved by minimization"));
+        assert!(html.contains("<td>4 &rarr; 1</td>"));
+    }
+
+    #[tokio::test]
+    async fn json_round_trips_and_formats_parse() {
+        let generator = seeded().await;
+        let out = tempfile::tempdir().unwrap();
+        let path = generator.write(ReportFormat::Json, out.path()).unwrap();
+        let parsed: serde_json::Value =
+            serde_json::from_str(&std::fs::read_to_string(path).unwrap()).unwrap();
+        assert_eq!(parsed["cases"].as_array().unwrap().len(), 3);
+        assert_eq!(parsed["lines_saved"], 3);
+
+        assert_eq!(ReportFormat::parse("junit").unwrap(), ReportFormat::Junit);
+        assert!(ReportFormat::parse("pdf").is_err());
+    }
+}
@@ -0,0 +1,187 @@
+//! Retention: archival and deletion of finished issues and patches.
+//!
+//! The sweep moves issues that have sat in a terminal status past their
+//! configured retention into the archive table — full payload, patches
+//! and reviews included — and only then deletes the live rows, so the
+//! working tables stay small without losing anything a compliance export
+//! needs. Rejected and rolled-back patches on still-live issues age out
+//! the same way on their own timer.
+
+use crate::config::RetentionConfig;
+use crate::database::Database;
+use crate::types::{IssueStatus, PatchStatus};
+use anyhow::{Context, Result};
+use chrono::Utc;
+use serde::Serialize;
+use serde_json::json;
+use std::io::Write;
+use std::path::Path;
+use tracing::info;
+
+/// How many records one sweep handles per status, so a backlog of old
+/// rows cannot stall the daemon loop.
+const SWEEP_BATCH: i64 = 500;
+
+/// What one retention sweep did, for the logs.
+#[derive(Debug, Default, Serialize)]
+pub struct RetentionReport {
+    pub issues_archived: usize,
+    pub patches_archived: usize,
+}
+
+/// Archive and delete everything past its retention.
+pub async fn sweep(database: &Database, config: &RetentionConfig) -> Result<RetentionReport> {
+    let mut report = RetentionReport::default();
+    for (status, days) in [
+        (IssueStatus::Resolved, config.resolved_days),
+        (IssueStatus::Failed, config.failed_days),
+    ] {
+        if days == 0 {
+            continue;
+        }
+        let cutoff = cutoff(days);
+        for issue in database.issues_archivable(status, &cutoff, SWEEP_BATCH).await? {
+            let patches = database.patches_for_issue(issue.id).await?;
+            let reviews = database.reviews_for_issue(issue.id).await?;
+            let payload = json!({ "issue": issue, "patches": patches, "reviews": reviews });
+            database
+                .archive_record(
+                    "issue",
+                    issue.id,
+                    &issue.project,
+                    issue.status.as_str(),
+                    &payload.to_string(),
+                )
+                .await?;
+            database.purge_issue(issue.id).await?;
+            report.issues_archived += 1;
+        }
+    }
+    if config.rejected_patch_days > 0 {
+        let cutoff = cutoff(config.rejected_patch_days);
+        for status in [PatchStatus::Rejected, PatchStatus::RolledBack] {
+            for patch in database.patches_archivable(status, &cutoff, SWEEP_BATCH).await? {
+                let project = database
+                    .issue_by_id(patch.issue_id)
+                    .await?
+                    .map(|issue| issue.project)
+                    .unwrap_or_else(|| "default".to_string());
+                let payload = json!({ "patch": patch });
+                database
+                    .archive_record(
+                        "patch",
+                        patch.id,
+                        &project,
+                        patch.status.as_str(),
+                        &payload.to_string(),
+                    )
+                    .await?;
+                database.purge_patch(patch.id).await?;
+                report.patches_archived += 1;
+            }
+        }
+    }
+    if report.issues_archived > 0 || report.patches_archived > 0 {
+        info!(
+            issues = report.issues_archived,
+            patches = report.patches_archived,
+            "retention sweep archived expired records"
+        );
+    }
+    Ok(report)
+}
+
+/// Write every live issue (with patches and reviews) and every archived
+/// record to `output` as one JSON object per line, for compliance
+/// requests. Returns the number of lines written.
+pub async fn export(database: &Database, output: &Path, since: Option<&str>) -> Result<usize> {
+    let mut file = std::fs::File::create(output)
+        .with_context(|| format!("cannot create export file {}", output.display()))?;
+    let mut written = 0;
+    for issue in database.issues(None, None, i64::MAX).await? {
+        if since.is_some_and(|cutoff| issue.updated_at.to_rfc3339().as_str() < cutoff) {
+            continue;
+        }
+        let patches = database.patches_for_issue(issue.id).await?;
+        let reviews = database.reviews_for_issue(issue.id).await?;
+        let line = json!({
+            "kind": "issue",
+            "data": { "issue": issue, "patches": patches, "reviews": reviews },
+        });
+        writeln!(file, "{line}")?;
+        written += 1;
+    }
+    for (kind, archived_at, payload) in database.archived_records(since).await? {
+        let data: serde_json::Value = serde_json::from_str(&payload)?;
+        let line = json!({ "kind": kind, "archived_at": archived_at, "data": data });
+        writeln!(file, "{line}")?;
+        written += 1;
+    }
+    Ok(written)
+}
+
+fn cutoff(days: u64) -> String {
+    (Utc::now() - chrono::Duration::days(days as i64)).to_rfc3339()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{Issue, Patch};
+
+    #[tokio::test]
+    async fn sweep_archives_and_purges_expired_issues() {
+        let db = Database::open_in_memory().await.unwrap();
+        let mut issue = Issue::new("api", "web", "abc", "test", "it failed", vec![]);
+        issue.status = IssueStatus::Resolved;
+        issue.updated_at = Utc::now() - chrono::Duration::days(10);
+        db.record_issue(&issue).await.unwrap();
+        let patch = Patch::new(issue.id, "the fix", "--- a/x\n+++ b/x\n");
+        db.record_patch(&patch).await.unwrap();
+
+        let config = RetentionConfig {
+            resolved_days: 7,
+            ..RetentionConfig::default()
+        };
+        let report = sweep(&db, &config).await.unwrap();
+        assert_eq!(report.issues_archived, 1);
+        assert!(db.issue_by_id(issue.id).await.unwrap().is_none());
+        assert!(db.patch_by_id(patch.id).await.unwrap().is_none());
+
+        let archived = db.archived_records(None).await.unwrap();
+        assert_eq!(archived.len(), 1);
+        assert_eq!(archived[0].0, "issue");
+        assert!(archived[0].2.contains("the fix"));
+    }
+
+    #[tokio::test]
+    async fn recent_issues_survive_the_sweep() {
+        let db = Database::open_in_memory().await.unwrap();
+        let mut issue = Issue::new("api", "web", "abc", "test", "it failed", vec![]);
+        issue.status = IssueStatus::Resolved;
+        db.record_issue(&issue).await.unwrap();
+
+        let report = sweep(&db, &RetentionConfig::default()).await.unwrap();
+        assert_eq!(report.issues_archived, 0);
+        assert!(db.issue_by_id(issue.id).await.unwrap().is_some());
+    }
+
+    #[tokio::test]
+    async fn export_writes_live_and_archived_records() {
+        let db = Database::open_in_memory().await.unwrap();
+        let issue = Issue::new("api", "web", "abc", "test", "it failed", vec![]);
+        db.record_issue(&issue).await.unwrap();
+        db.archive_record("patch", uuid::Uuid::new_v4(), "default", "rejected", "{\"patch\":{}}")
+            .await
+            .unwrap();
+
+        let dir = tempfile::tempdir().unwrap();
+        let output = dir.path().join("export.jsonl");
+        let written = export(&db, &output, None).await.unwrap();
+        assert_eq!(written, 2);
+        let text = std::fs::read_to_string(&output).unwrap();
+        assert_eq!(text.lines().count(), 2);
+        assert!(text.contains("\"kind\":\"issue\""));
+        assert!(text.contains("\"kind\":\"patch\""));
+    }
+}
@@ -0,0 +1,132 @@
+//! Risk scoring that routes patches into the human review queue.
+//!
+//! A patch that validates cleanly is still not automatically trustworthy:
+//! it may change public APIs, touch a lot of code, or introduce unsafe
+//! blocks. Each such signal adds to a risk score; patches at or above the
+//! configured threshold land in `pending_review` and need an approval
+//! (recorded with the reviewer's identity) before they can be applied.
+
+use crate::breaking_changes::BreakingChange;
+use crate::types::ValidationResult;
+use serde::Serialize;
+
+/// Why a patch was (or was not) sent to review.
+#[derive(Debug, Serialize)]
+pub struct RiskAssessment {
+    pub score: u32,
+    pub reasons: Vec<String>,
+}
+
+impl RiskAssessment {
+    pub fn requires_review(&self, threshold: u32) -> bool {
+        self.score >= threshold
+    }
+}
+
+/// Score a patch from its diff, breaking-change analysis, and validation
+/// outcome. Higher is riskier; the scale is anchored so that a single
+/// breaking API change crosses the default threshold on its own.
+pub fn assess(
+    diff: &str,
+    breaking: &[BreakingChange],
+    validation: Option<&ValidationResult>,
+) -> RiskAssessment {
+    let mut score = 0;
+    let mut reasons = Vec::new();
+
+    for change in breaking {
+        score += 40;
+        reasons.push(format!(
+            "breaking change in {}: {}",
+            change.file, change.item
+        ));
+    }
+    if let Some(validation) = validation {
+        if validation.security_issues_found > 0 {
+            score += 30 * validation.security_issues_found as u32;
+            reasons.push(format!(
+                "{} new cargo audit finding(s)",
+                validation.security_issues_found
+            ));
+        }
+    }
+
+    let (files, lines) = diff_stats(diff);
+    if lines > 100 {
+        score += (lines as u32 / 10).min(30);
+        reasons.push(format!("large diff ({lines} changed lines)"));
+    }
+    if files > 3 {
+        score += 10;
+        reasons.push(format!("touches {files} files"));
+    }
+    if diff
+        .lines()
+        .any(|l| l.starts_with('+') && l.contains("unsafe "))
+    {
+        score += 40;
+        reasons.push("adds unsafe code".to_string());
+    }
+    if diff
+        .lines()
+        .any(|l| l.starts_with("+++ ") && l.ends_with("Cargo.toml"))
+    {
+        score += 20;
+        reasons.push("modifies a manifest".to_string());
+    }
+
+    RiskAssessment { score, reasons }
+}
+
+/// Count touched files and changed (added or removed) lines in a diff.
+fn diff_stats(diff: &str) -> (usize, usize) {
+    let mut files = 0;
+    let mut lines = 0;
+    for line in diff.lines() {
+        if line.starts_with("+++ ") {
+            files += 1;
+        } else if (line.starts_with('+') || line.starts_with('-'))
+            && !line.starts_with("+++")
+            && !line.starts_with("---")
+        {
+            lines += 1;
+        }
+    }
+    (files, lines)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::breaking_changes::BreakingChangeKind;
+
+    #[test]
+    fn small_clean_diffs_score_low() {
+        let diff = "--- a/src/lib.rs\n+++ b/src/lib.rs\n@@ -1,1 +1,1 @@\n-let x = 1;\n+let x = 2;\n";
+        let assessment = assess(diff, &[], None);
+        assert_eq!(assessment.score, 0);
+        assert!(!assessment.requires_review(40));
+    }
+
+    #[test]
+    fn breaking_changes_cross_the_default_threshold() {
+        let breaking = vec![BreakingChange {
+            kind: BreakingChangeKind::SignatureChanged,
+            item: "fn handle".to_string(),
+            line: 10,
+            detail: "signature changed".to_string(),
+            file: "src/api.rs".to_string(),
+        }];
+        let assessment = assess("", &breaking, None);
+        assert!(assessment.requires_review(40));
+        assert!(assessment.reasons[0].contains("src/api.rs"));
+    }
+
+    #[test]
+    fn unsafe_additions_are_flagged() {
+        let diff = "--- a/src/lib.rs\n+++ b/src/lib.rs\n@@ -1,1 +1,1 @@\n+unsafe { ptr.read() }\n";
+        let assessment = assess(diff, &[], None);
+        assert!(assessment.requires_review(40));
+        assert!(assessment.reasons.iter().any(|r| r.contains("unsafe")));
+    }
+}
@@ -0,0 +1,144 @@
+//! Priority ordering for automatic patch generation.
+//!
+//! With hundreds of open issues, the order patches are attempted in
+//! matters: a security advisory taking a whole service down should not
+//! wait behind a week-old lint. Each open issue gets a score from its
+//! severity, its blast radius (how many open failures its service has),
+//! its age, and its estimated LLM cost, and the daemon works the queue
+//! highest score first under a concurrency limit.
+
+use chrono::{DateTime, Utc};
+use std::collections::HashMap;
+
+use crate::types::Issue;
+
+/// What one scheduler pass did; logged by the daemon loop.
+#[derive(Debug, Default)]
+pub struct QueueReport {
+    /// Open issues considered for this pass.
+    pub queued: usize,
+    /// Issues a candidate patch was generated for.
+    pub patched: usize,
+    /// Issues where generation failed; they stay in the queue.
+    pub failed: usize,
+}
+
+/// How urgent a failure class is, independent of everything else. The
+/// classifications are the reporter-supplied strings from `Issue`; an
+/// unknown one ranks with test failures rather than at the bottom.
+fn severity_weight(classification: &str) -> f64 {
+    match classification {
+        "security" => 4.0,
+        "compiler" => 3.0,
+        "test" => 2.0,
+        "lint" => 1.0,
+        _ => 2.0,
+    }
+}
+
+/// Rough dollar cost of generating a patch for this issue, from the log
+/// that goes into the prompt and the configured per-million-token prices.
+/// The point is ranking, not accounting: cheap issues of equal urgency
+/// go first, so a pass close to the daily budget fixes more of them.
+pub fn estimated_cost_usd(issue: &Issue, input_per_mtok: f64, output_per_mtok: f64) -> f64 {
+    // The prompt carries the log plus source context and template text;
+    // the context builder roughly triples the log's share. Four bytes per
+    // token is the usual English-plus-code approximation.
+    let input_tokens = (issue.log.len() as f64 / 4.0) * 3.0 + 1_000.0;
+    let output_tokens = 1_500.0;
+    input_tokens * input_per_mtok / 1_000_000.0 + output_tokens * output_per_mtok / 1_000_000.0
+}
+
+/// Priority of one issue. Severity dominates, blast radius separates
+/// service-wide outages from one-off failures within a severity band, age
+/// breaks ties so nothing starves, and the cost estimate nudges cheap
+/// fixes ahead of expensive ones.
+pub fn score(
+    issue: &Issue,
+    blast_radius: i64,
+    estimated_cost_usd: f64,
+    now: DateTime<Utc>,
+) -> f64 {
+    let age_hours = (now - issue.created_at).num_minutes().max(0) as f64 / 60.0;
+    severity_weight(&issue.classification) * 100.0
+        + (blast_radius as f64).min(20.0) * 10.0
+        + age_hours.min(72.0)
+        - estimated_cost_usd * 10.0
+}
+
+/// Order open issues by descending priority. `open_by_service` is the
+/// count of open issues per service; prices are zero when no LLM is
+/// configured, which drops the cost term.
+pub fn prioritize(
+    mut issues: Vec<Issue>,
+    open_by_service: &HashMap<String, i64>,
+    input_per_mtok: f64,
+    output_per_mtok: f64,
+) -> Vec<Issue> {
+    let now = Utc::now();
+    issues.sort_by(|a, b| {
+        let score_of = |issue: &Issue| {
+            let radius = open_by_service.get(&issue.service).copied().unwrap_or(1);
+            let cost = estimated_cost_usd(issue, input_per_mtok, output_per_mtok);
+            score(issue, radius, cost, now)
+        };
+        score_of(b).total_cmp(&score_of(a))
+    });
+    issues
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+
+    fn issue(service: &str, classification: &str, log: &str) -> Issue {
+        Issue::new(
+            "build-monitor",
+            service,
+            "abc123",
+            classification,
+            log,
+            vec![],
+        )
+    }
+
+    #[test]
+    fn severity_outranks_radius_and_age() {
+        let mut lint = issue("web", "lint", "warning: unused import");
+        lint.created_at = Utc::now() - Duration::hours(48);
+        let security = issue("api", "security", "RUSTSEC-2024-0001");
+
+        let radii = HashMap::from([("web".to_string(), 20), ("api".to_string(), 1)]);
+        let ordered = prioritize(vec![lint, security], &radii, 0.0, 0.0);
+        assert_eq!(ordered[0].classification, "security");
+    }
+
+    #[test]
+    fn radius_then_age_break_severity_ties() {
+        let one_off = issue("web", "compiler", "error[E0308]");
+        let outage = issue("api", "compiler", "error[E0308]");
+        let radii = HashMap::from([("web".to_string(), 1), ("api".to_string(), 8)]);
+        let ordered = prioritize(vec![one_off.clone(), outage], &radii, 0.0, 0.0);
+        assert_eq!(ordered[0].service, "api");
+
+        // Equal radius: the older issue goes first so nothing starves.
+        let mut old = issue("web", "compiler", "error[E0308]");
+        old.created_at = Utc::now() - Duration::hours(10);
+        let new = issue("web", "compiler", "error[E0308]");
+        let ordered = prioritize(vec![new, old.clone()], &radii, 0.0, 0.0);
+        assert_eq!(ordered[0].id, old.id);
+    }
+
+    #[test]
+    fn cheaper_issues_go_first_at_equal_urgency() {
+        let short = issue("web", "test", "assertion failed");
+        let long = issue("web", "test", &"x".repeat(400_000));
+        assert!(
+            estimated_cost_usd(&long, 3.0, 15.0) > estimated_cost_usd(&short, 3.0, 15.0)
+        );
+        let radii = HashMap::new();
+        let ordered = prioritize(vec![long, short.clone()], &radii, 3.0, 15.0);
+        assert_eq!(ordered[0].id, short.id);
+    }
+}
@@ -0,0 +1,210 @@
+//! Security scanning of patched workspaces with semgrep and gitleaks.
+//!
+//! Both tools are optional: validation warns and moves on when one is not
+//! installed, the same way the analysis pass treats cargo-audit. Findings
+//! are normalized to low/medium/high severities so validation can fail on
+//! a configurable threshold.
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::process::Command;
+use tracing::warn;
+
+/// One finding from a security scanner.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SecurityIssue {
+    /// "semgrep" or "gitleaks".
+    pub tool: String,
+    pub rule_id: String,
+    /// Normalized severity: "low", "medium", or "high".
+    pub severity: String,
+    pub file: String,
+    pub message: String,
+}
+
+/// Run every available scanner against `dir` and collect the findings.
+/// Missing tools are skipped with a warning, never an error.
+pub fn scan(dir: &Path, semgrep_rules: &str) -> Vec<SecurityIssue> {
+    let mut issues = Vec::new();
+    match semgrep(dir, semgrep_rules) {
+        Ok(mut found) => issues.append(&mut found),
+        Err(e) => warn!("semgrep scan skipped: {e:#}"),
+    }
+    match gitleaks(dir) {
+        Ok(mut found) => issues.append(&mut found),
+        Err(e) => warn!("gitleaks scan skipped: {e:#}"),
+    }
+    issues
+}
+
+/// Count the findings at or above a severity threshold.
+pub fn at_or_above(issues: &[SecurityIssue], threshold: &str) -> usize {
+    let floor = severity_rank(threshold);
+    issues
+        .iter()
+        .filter(|i| severity_rank(&i.severity) >= floor)
+        .count()
+}
+
+fn severity_rank(severity: &str) -> u8 {
+    match severity {
+        "low" => 0,
+        "medium" => 1,
+        _ => 2,
+    }
+}
+
+/// Run semgrep with the configured ruleset. Semgrep exits non-zero when it
+/// finds something, so the JSON report is the result either way.
+fn semgrep(dir: &Path, rules: &str) -> Result<Vec<SecurityIssue>> {
+    let output = Command::new("semgrep")
+        .args(["scan", "--config", rules, "--json", "--quiet", "."])
+        .current_dir(dir)
+        .output()
+        .context("failed to invoke semgrep")?;
+    parse_semgrep(&String::from_utf8_lossy(&output.stdout))
+}
+
+/// Run gitleaks against the directory contents (not its git history; the
+/// staged workspace may hold uncommitted patch output).
+fn gitleaks(dir: &Path) -> Result<Vec<SecurityIssue>> {
+    let report_dir = tempfile::tempdir().context("failed to create gitleaks report directory")?;
+    let report = report_dir.path().join("report.json");
+    let output = Command::new("gitleaks")
+        .args(["detect", "--no-git", "--no-banner", "--exit-code", "0"])
+        .args(["--source", "."])
+        .args(["--report-format", "json"])
+        .arg("--report-path")
+        .arg(&report)
+        .current_dir(dir)
+        .output()
+        .context("failed to invoke gitleaks")?;
+    if !output.status.success() {
+        anyhow::bail!(
+            "gitleaks failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    let json = std::fs::read_to_string(&report).context("cannot read gitleaks report")?;
+    parse_gitleaks(&json)
+}
+
+#[derive(Deserialize)]
+struct SemgrepReport {
+    #[serde(default)]
+    results: Vec<SemgrepResult>,
+}
+
+#[derive(Deserialize)]
+struct SemgrepResult {
+    check_id: String,
+    path: String,
+    extra: SemgrepExtra,
+}
+
+#[derive(Deserialize)]
+struct SemgrepExtra {
+    severity: String,
+    message: String,
+}
+
+/// Parse a `semgrep --json` report. Semgrep's ERROR/WARNING/INFO levels
+/// map to high/medium/low.
+pub fn parse_semgrep(json: &str) -> Result<Vec<SecurityIssue>> {
+    let report: SemgrepReport =
+        serde_json::from_str(json).context("malformed semgrep output")?;
+    Ok(report
+        .results
+        .into_iter()
+        .map(|r| SecurityIssue {
+            tool: "semgrep".to_string(),
+            rule_id: r.check_id,
+            severity: match r.extra.severity.as_str() {
+                "ERROR" => "high",
+                "WARNING" => "medium",
+                _ => "low",
+            }
+            .to_string(),
+            file: r.path,
+            message: r.extra.message,
+        })
+        .collect())
+}
+
+#[derive(Deserialize)]
+struct GitleaksFinding {
+    #[serde(rename = "RuleID")]
+    rule_id: String,
+    #[serde(rename = "File")]
+    file: String,
+    #[serde(rename = "Description")]
+    description: String,
+    #[serde(rename = "StartLine", default)]
+    start_line: usize,
+}
+
+/// Parse a gitleaks JSON report. Every leaked credential is high severity.
+pub fn parse_gitleaks(json: &str) -> Result<Vec<SecurityIssue>> {
+    let findings: Vec<GitleaksFinding> =
+        serde_json::from_str(json).context("malformed gitleaks output")?;
+    Ok(findings
+        .into_iter()
+        .map(|f| SecurityIssue {
+            tool: "gitleaks".to_string(),
+            rule_id: f.rule_id,
+            severity: "high".to_string(),
+            message: format!("{} (line {})", f.description, f.start_line),
+            file: f.file,
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_semgrep_results_with_severity_mapping() {
+        let json = r#"{
+            "results": [
+                {"check_id": "rust.lang.security.unsafe-usage", "path": "src/main.rs",
+                 "extra": {"severity": "ERROR", "message": "unsafe block"}},
+                {"check_id": "generic.style.todo", "path": "src/lib.rs",
+                 "extra": {"severity": "INFO", "message": "todo left in code"}}
+            ]
+        }"#;
+        let issues = parse_semgrep(json).unwrap();
+        assert_eq!(issues.len(), 2);
+        assert_eq!(issues[0].severity, "high");
+        assert_eq!(issues[0].rule_id, "rust.lang.security.unsafe-usage");
+        assert_eq!(issues[1].severity, "low");
+    }
+
+    #[test]
+    fn parses_gitleaks_findings_as_high_severity() {
+        let json = r#"[
+            {"RuleID": "aws-access-token", "File": "config.ts", "Description": "AWS access token", "StartLine": 12}
+        ]"#;
+        let issues = parse_gitleaks(json).unwrap();
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].tool, "gitleaks");
+        assert_eq!(issues[0].severity, "high");
+        assert!(issues[0].message.contains("line 12"));
+    }
+
+    #[test]
+    fn threshold_counts_only_findings_at_or_above() {
+        let issue = |severity: &str| SecurityIssue {
+            tool: "semgrep".to_string(),
+            rule_id: "r".to_string(),
+            severity: severity.to_string(),
+            file: "f".to_string(),
+            message: "m".to_string(),
+        };
+        let issues = vec![issue("low"), issue("medium"), issue("high")];
+        assert_eq!(at_or_above(&issues, "low"), 3);
+        assert_eq!(at_or_above(&issues, "medium"), 2);
+        assert_eq!(at_or_above(&issues, "high"), 1);
+    }
+}
@@ -0,0 +1,166 @@
+//! Full-pipeline simulation report for a single issue.
+//!
+//! `simulate` runs context building, patch generation, and sandbox
+//! validation exactly as the daemon would, but never applies anything.
+//! The outcome is folded into one report — diff, validation results,
+//! risk, and LLM spend — rendered as JSON for tooling or markdown for
+//! pasting into an incident channel before anyone decides to apply.
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use uuid::Uuid;
+
+use crate::review::RiskAssessment;
+use crate::types::{Issue, Patch, PatchStatus, ValidationResult};
+
+#[derive(Debug, Serialize)]
+pub struct SimulationReport {
+    pub issue_id: Uuid,
+    pub project: String,
+    pub service: String,
+    pub classification: String,
+    pub patch_id: Uuid,
+    /// Which stage produced the diff: "rule:<name>", "tool-suggestion",
+    /// or "llm".
+    pub origin: String,
+    pub description: String,
+    pub diff: String,
+    /// Where the patch landed after validation and policy: validated,
+    /// pending_review, or rejected. Never applied — simulation stops
+    /// short of that.
+    pub status: PatchStatus,
+    pub validation: Option<ValidationResult>,
+    pub risk: RiskAssessment,
+    /// Total LLM spend attributed to the issue, in USD.
+    pub cost_usd: f64,
+    pub generated_at: DateTime<Utc>,
+}
+
+impl SimulationReport {
+    pub fn new(issue: &Issue, patch: &Patch, risk: RiskAssessment, cost_usd: f64) -> Self {
+        Self {
+            issue_id: issue.id,
+            project: issue.project.clone(),
+            service: issue.service.clone(),
+            classification: issue.classification.clone(),
+            patch_id: patch.id,
+            origin: patch.origin.clone(),
+            description: patch.description.clone(),
+            diff: patch.diff.clone(),
+            status: patch.status,
+            validation: patch.validation.clone(),
+            risk,
+            cost_usd,
+            generated_at: Utc::now(),
+        }
+    }
+
+    /// Markdown rendering, shaped like the pull request body so readers
+    /// see the same layout in both places.
+    pub fn to_markdown(&self) -> String {
+        let mut body = format!(
+            "## Simulated fix for {} failure in {}\n\n\
+             **Issue** `{}` ({} / {})\n\n\
+             **Patch** `{}` ({}): {}\n\n",
+            self.classification,
+            self.service,
+            self.issue_id,
+            self.project,
+            self.service,
+            self.patch_id,
+            self.origin,
+            self.description,
+        );
+        match &self.validation {
+            Some(validation) => {
+                body.push_str(&format!(
+                    "**Validation**\n\n\
+                     | check | result |\n|---|---|\n\
+                     | build | {} ({} ms) |\n\
+                     | tests | {} ({} ms) |\n\
+                     | new audit findings | {} |\n\n",
+                    pass(validation.build_ok),
+                    validation.build_time_ms,
+                    pass(validation.tests_ok),
+                    validation.test_time_ms,
+                    validation.security_issues_found,
+                ));
+            }
+            None => body.push_str("**Validation**: not run\n\n"),
+        }
+        body.push_str(&format!("**Risk score**: {}\n", self.risk.score));
+        for reason in &self.risk.reasons {
+            body.push_str(&format!("- {reason}\n"));
+        }
+        body.push_str(&format!(
+            "\n**Outcome**: {} (nothing was applied)\n\n\
+             **LLM spend on this issue**: ${:.4}\n\n\
+             ```diff\n{}\n```\n",
+            self.status.as_str(),
+            self.cost_usd,
+            self.diff.trim_end(),
+        ));
+        body
+    }
+}
+
+fn pass(ok: bool) -> &'static str {
+    if ok {
+        "passed"
+    } else {
+        "FAILED"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn report() -> SimulationReport {
+        let issue = Issue::new(
+            "ci",
+            "web",
+            "abc1234",
+            "compiler",
+            "error[E0308]: mismatched types",
+            vec!["src/lib.rs".to_string()],
+        );
+        let mut patch = Patch::new(issue.id, "fix type mismatch", "--- a/src/lib.rs\n+++ b/src/lib.rs\n");
+        patch.origin = "llm".to_string();
+        patch.status = PatchStatus::PendingReview;
+        patch.validation = Some(ValidationResult {
+            passed: true,
+            build_ok: true,
+            tests_ok: true,
+            build_time_ms: 1200,
+            test_time_ms: 3400,
+            security_issues_found: 0,
+            security_issues: Vec::new(),
+            detail: None,
+        });
+        let risk = RiskAssessment {
+            score: 35,
+            reasons: vec!["patch is large".to_string()],
+        };
+        SimulationReport::new(&issue, &patch, risk, 0.0421)
+    }
+
+    #[test]
+    fn markdown_covers_validation_risk_cost_and_diff() {
+        let rendered = report().to_markdown();
+        assert!(rendered.contains("Simulated fix for compiler failure in web"));
+        assert!(rendered.contains("| build | passed (1200 ms) |"));
+        assert!(rendered.contains("**Risk score**: 35"));
+        assert!(rendered.contains("- patch is large"));
+        assert!(rendered.contains("**Outcome**: pending_review (nothing was applied)"));
+        assert!(rendered.contains("**LLM spend on this issue**: $0.0421"));
+        assert!(rendered.contains("```diff\n--- a/src/lib.rs"));
+    }
+
+    #[test]
+    fn a_skipped_validation_is_called_out() {
+        let mut report = report();
+        report.validation = None;
+        assert!(report.to_markdown().contains("**Validation**: not run"));
+    }
+}
@@ -0,0 +1,280 @@
+//! Workspace snapshots, so every healing action on a live tree is
+//! reversible.
+//!
+//! A snapshot records the exact content of the working tree — tracked,
+//! modified, and untracked files alike — as a git commit built through
+//! a throwaway index, so neither the real index nor the worktree is
+//! disturbed while it is taken. The commit is pinned under
+//! `refs/self-heal/snapshots/` so gc cannot collect it, and the dirty
+//! files' sha256 checksums travel with the record: a restore is only
+//! reported successful after the bytes on disk match what was
+//! snapshotted.
+
+use anyhow::{bail, Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::path::Path;
+use std::process::Command;
+use uuid::Uuid;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Snapshot {
+    pub id: Uuid,
+    pub project: String,
+    /// Why the snapshot was taken, e.g. "pre-apply patch <id>".
+    pub reason: String,
+    /// Patch the snapshot was taken for, when one triggered it.
+    #[serde(default)]
+    pub patch_id: Option<Uuid>,
+    /// HEAD at snapshot time; a restore leaves the branch here.
+    pub head: String,
+    /// The snapshot commit holding the full worktree content.
+    pub commit: String,
+    /// Checksums of the files that were dirty at snapshot time; `None`
+    /// marks a file deleted from the worktree.
+    pub files: Vec<FileChecksum>,
+    pub created_at: DateTime<Utc>,
+    pub restored_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FileChecksum {
+    pub path: String,
+    pub sha256: Option<String>,
+}
+
+/// Snapshot the working tree at `repo`. The worktree and the real index
+/// are read, never written.
+pub fn take(repo: &Path, project: &str, reason: &str) -> Result<Snapshot> {
+    let id = Uuid::new_v4();
+    let head = capture(repo, &["rev-parse", "HEAD"], &[])?;
+    let files = dirty_files(repo)?;
+
+    // Build the snapshot tree in a throwaway index: everything the
+    // worktree holds (minus ignores), including untracked files a
+    // stash-based snapshot would lose.
+    let index = tempfile::NamedTempFile::new().context("failed to create snapshot index")?;
+    let index_env = [(
+        "GIT_INDEX_FILE",
+        index.path().to_string_lossy().into_owned(),
+    )];
+    run(repo, &["read-tree", "HEAD"], &index_env)?;
+    run(repo, &["add", "-A", "."], &index_env)?;
+    let tree = capture(repo, &["write-tree"], &index_env)?;
+    let commit = capture(
+        repo,
+        &["commit-tree", &tree, "-p", &head, "-m", reason],
+        &[
+            ("GIT_AUTHOR_NAME", "self-healing-system".to_string()),
+            ("GIT_AUTHOR_EMAIL", "self-healing@localhost".to_string()),
+            ("GIT_COMMITTER_NAME", "self-healing-system".to_string()),
+            ("GIT_COMMITTER_EMAIL", "self-healing@localhost".to_string()),
+        ],
+    )?;
+    // Pin the commit so gc never collects a snapshot we may restore.
+    run(
+        repo,
+        &["update-ref", &ref_name(id), &commit],
+        &[],
+    )?;
+
+    Ok(Snapshot {
+        id,
+        project: project.to_string(),
+        reason: reason.to_string(),
+        patch_id: None,
+        head,
+        commit,
+        files,
+        created_at: Utc::now(),
+        restored_at: None,
+    })
+}
+
+/// Put the working tree back to exactly the snapshotted content, leave
+/// the branch on the snapshot's original HEAD, and verify the recorded
+/// checksums against the restored bytes. Files created since the
+/// snapshot are removed; ignored files are left alone.
+pub fn restore(repo: &Path, snapshot: &Snapshot) -> Result<()> {
+    capture(
+        repo,
+        &["rev-parse", "--verify", &format!("{}^{{commit}}", snapshot.commit)],
+        &[],
+    )
+    .with_context(|| format!("snapshot commit {} is gone from the repository", snapshot.commit))?;
+    run(repo, &["reset", "--hard", &snapshot.commit], &[])?;
+    run(repo, &["clean", "-fd"], &[])?;
+    // Back to the original HEAD while keeping the snapshot content on
+    // disk; the pre-snapshot dirt shows up staged, which `git status`
+    // makes obvious.
+    run(repo, &["reset", "--soft", &snapshot.head], &[])?;
+
+    let mut corrupted = Vec::new();
+    for file in &snapshot.files {
+        let on_disk = sha256_file(&repo.join(&file.path));
+        if on_disk != file.sha256 {
+            corrupted.push(file.path.clone());
+        }
+    }
+    if !corrupted.is_empty() {
+        bail!(
+            "restore of snapshot {} finished but checksum verification failed for: {}",
+            snapshot.id,
+            corrupted.join(", ")
+        );
+    }
+    Ok(())
+}
+
+/// Drop the ref pinning a snapshot's commit, releasing it to gc.
+pub fn discard(repo: &Path, id: Uuid) -> Result<()> {
+    run(repo, &["update-ref", "-d", &ref_name(id)], &[])
+}
+
+fn ref_name(id: Uuid) -> String {
+    format!("refs/self-heal/snapshots/{id}")
+}
+
+/// Paths that differ from HEAD — modified, deleted, and untracked —
+/// with their current checksums.
+fn dirty_files(repo: &Path) -> Result<Vec<FileChecksum>> {
+    let tracked = capture(repo, &["diff", "--name-only", "HEAD"], &[])?;
+    let untracked = capture(repo, &["ls-files", "--others", "--exclude-standard"], &[])?;
+    Ok(tracked
+        .lines()
+        .chain(untracked.lines())
+        .filter(|path| !path.is_empty())
+        .map(|path| FileChecksum {
+            path: path.to_string(),
+            sha256: sha256_file(&repo.join(path)),
+        })
+        .collect())
+}
+
+/// Hex sha256 of a file, or `None` when it does not exist — the same
+/// encoding for "deleted" at snapshot and verification time.
+fn sha256_file(path: &Path) -> Option<String> {
+    let bytes = std::fs::read(path).ok()?;
+    Some(hex::encode(Sha256::digest(&bytes)))
+}
+
+fn run(repo: &Path, args: &[&str], envs: &[(&str, String)]) -> Result<()> {
+    capture(repo, args, envs).map(|_| ())
+}
+
+fn capture(repo: &Path, args: &[&str], envs: &[(&str, String)]) -> Result<String> {
+    let mut command = Command::new("git");
+    command.args(args).current_dir(repo);
+    for (key, value) in envs {
+        command.env(key, value);
+    }
+    let output = command
+        .output()
+        .with_context(|| format!("failed to run git {args:?}"))?;
+    if !output.status.success() {
+        bail!(
+            "git {:?} failed: {}",
+            args,
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn init_repo(dir: &Path) {
+        let run = |args: &[&str]| {
+            let out = Command::new("git")
+                .args(args)
+                .current_dir(dir)
+                .env("GIT_AUTHOR_NAME", "test")
+                .env("GIT_AUTHOR_EMAIL", "test@example.com")
+                .env("GIT_COMMITTER_NAME", "test")
+                .env("GIT_COMMITTER_EMAIL", "test@example.com")
+                .output()
+                .unwrap();
+            assert!(
+                out.status.success(),
+                "git {args:?}: {}",
+                String::from_utf8_lossy(&out.stderr)
+            );
+        };
+        run(&["init", "-q", "-b", "main"]);
+        std::fs::write(dir.join("tracked.txt"), "original").unwrap();
+        run(&["add", "."]);
+        run(&["commit", "-q", "-m", "initial"]);
+    }
+
+    #[test]
+    fn restore_puts_back_modified_untracked_and_deleted_files() {
+        let dir = tempfile::tempdir().unwrap();
+        init_repo(dir.path());
+        std::fs::write(dir.path().join("tracked.txt"), "patched").unwrap();
+        std::fs::write(dir.path().join("untracked.txt"), "scratch").unwrap();
+
+        let snapshot = take(dir.path(), "aurum", "before the test").unwrap();
+        assert_eq!(snapshot.files.len(), 2);
+
+        // The tree keeps changing after the snapshot.
+        std::fs::write(dir.path().join("tracked.txt"), "broken").unwrap();
+        std::fs::remove_file(dir.path().join("untracked.txt")).unwrap();
+        std::fs::write(dir.path().join("stray.txt"), "leftover").unwrap();
+
+        restore(dir.path(), &snapshot).unwrap();
+        assert_eq!(
+            std::fs::read_to_string(dir.path().join("tracked.txt")).unwrap(),
+            "patched"
+        );
+        assert_eq!(
+            std::fs::read_to_string(dir.path().join("untracked.txt")).unwrap(),
+            "scratch"
+        );
+        assert!(!dir.path().join("stray.txt").exists());
+        // The branch still points at the original HEAD.
+        assert_eq!(
+            capture(dir.path(), &["rev-parse", "HEAD"], &[]).unwrap(),
+            snapshot.head
+        );
+    }
+
+    #[test]
+    fn a_deletion_in_the_snapshot_is_restored_as_a_deletion() {
+        let dir = tempfile::tempdir().unwrap();
+        init_repo(dir.path());
+        std::fs::remove_file(dir.path().join("tracked.txt")).unwrap();
+
+        let snapshot = take(dir.path(), "aurum", "deleted state").unwrap();
+        assert_eq!(snapshot.files[0].sha256, None);
+
+        std::fs::write(dir.path().join("tracked.txt"), "resurrected").unwrap();
+        restore(dir.path(), &snapshot).unwrap();
+        assert!(!dir.path().join("tracked.txt").exists());
+    }
+
+    #[test]
+    fn tampered_checksums_fail_the_restore_loudly() {
+        let dir = tempfile::tempdir().unwrap();
+        init_repo(dir.path());
+        std::fs::write(dir.path().join("tracked.txt"), "patched").unwrap();
+
+        let mut snapshot = take(dir.path(), "aurum", "to be corrupted").unwrap();
+        snapshot.files[0].sha256 = Some("0".repeat(64));
+        let err = restore(dir.path(), &snapshot).unwrap_err().to_string();
+        assert!(err.contains("checksum verification failed"), "{err}");
+        assert!(err.contains("tracked.txt"), "{err}");
+    }
+
+    #[test]
+    fn discard_releases_the_pinned_commit() {
+        let dir = tempfile::tempdir().unwrap();
+        init_repo(dir.path());
+        let snapshot = take(dir.path(), "aurum", "short-lived").unwrap();
+        assert!(capture(dir.path(), &["rev-parse", "--verify", &ref_name(snapshot.id)], &[]).is_ok());
+        discard(dir.path(), snapshot.id).unwrap();
+        assert!(capture(dir.path(), &["rev-parse", "--verify", &ref_name(snapshot.id)], &[]).is_err());
+    }
+}
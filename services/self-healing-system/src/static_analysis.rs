@@ -0,0 +1,383 @@
+//! Compiler-diagnostics-driven analysis of the target project.
+//!
+//! Runs `cargo check --message-format=json` and converts rustc diagnostics
+//! into [`AnalysisIssue`] records with spans, error codes, and suggested
+//! fixes. Machine-applicable suggestions can be turned into unified diffs
+//! that flow through the normal patch pipeline, so the compiler's own fix
+//! is always preferred over generating one.
+
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::path::Path;
+use std::process::Command;
+
+/// One diagnostic from rustc, reduced to what the daemon acts on.
+#[derive(Debug, Clone, Serialize)]
+pub struct AnalysisIssue {
+    pub file: String,
+    pub line: usize,
+    pub column: usize,
+    /// "error" or "warning".
+    pub level: String,
+    /// rustc error code, e.g. `E0308`, when one exists.
+    pub code: Option<String>,
+    pub message: String,
+    pub suggestions: Vec<SuggestedFix>,
+}
+
+/// A replacement rustc proposed for a span.
+#[derive(Debug, Clone, Serialize)]
+pub struct SuggestedFix {
+    pub file: String,
+    pub line_start: usize,
+    pub line_end: usize,
+    pub column_start: usize,
+    pub column_end: usize,
+    pub replacement: String,
+    /// True when rustc marked the suggestion `MachineApplicable`.
+    pub machine_applicable: bool,
+}
+
+// The subset of cargo's JSON message stream we care about.
+
+#[derive(Deserialize)]
+struct CargoMessage {
+    reason: String,
+    message: Option<Diagnostic>,
+}
+
+#[derive(Deserialize)]
+struct Diagnostic {
+    level: String,
+    message: String,
+    code: Option<DiagnosticCode>,
+    #[serde(default)]
+    spans: Vec<DiagnosticSpan>,
+    #[serde(default)]
+    children: Vec<Diagnostic>,
+}
+
+#[derive(Deserialize)]
+struct DiagnosticCode {
+    code: String,
+}
+
+#[derive(Deserialize)]
+struct DiagnosticSpan {
+    file_name: String,
+    line_start: usize,
+    line_end: usize,
+    column_start: usize,
+    column_end: usize,
+    is_primary: bool,
+    suggested_replacement: Option<String>,
+    suggestion_applicability: Option<String>,
+}
+
+/// Run `cargo check` against the repository and collect its diagnostics.
+/// A non-zero exit is expected when there are errors; the diagnostics are
+/// the result, not a failure.
+pub fn check_project(repo: &Path) -> Result<Vec<AnalysisIssue>> {
+    let output = Command::new("cargo")
+        .args(["check", "--workspace", "--message-format=json"])
+        .current_dir(repo)
+        .output()
+        .context("failed to invoke cargo check")?;
+    parse_messages(&String::from_utf8_lossy(&output.stdout))
+}
+
+/// Parse the newline-delimited JSON cargo emits. Exposed separately from
+/// [`check_project`] so it can be exercised without a compiler run.
+pub fn parse_messages(stream: &str) -> Result<Vec<AnalysisIssue>> {
+    let mut issues = Vec::new();
+    // cargo repeats diagnostics once per target (lib, bin, test); dedup.
+    let mut seen = HashSet::new();
+    for line in stream.lines() {
+        let Ok(msg) = serde_json::from_str::<CargoMessage>(line) else {
+            continue;
+        };
+        if msg.reason != "compiler-message" {
+            continue;
+        }
+        let Some(issue) = msg.message.as_ref().and_then(convert) else {
+            continue;
+        };
+        if seen.insert((issue.file.clone(), issue.line, issue.message.clone())) {
+            issues.push(issue);
+        }
+    }
+    Ok(issues)
+}
+
+fn convert(diag: &Diagnostic) -> Option<AnalysisIssue> {
+    if diag.level != "error" && diag.level != "warning" {
+        return None;
+    }
+    // Summary lines like "aborting due to N previous errors" have no spans.
+    let primary = diag.spans.iter().find(|s| s.is_primary)?;
+    let mut suggestions = Vec::new();
+    collect_suggestions(diag, &mut suggestions);
+    Some(AnalysisIssue {
+        file: primary.file_name.clone(),
+        line: primary.line_start,
+        column: primary.column_start,
+        level: diag.level.clone(),
+        code: diag.code.as_ref().map(|c| c.code.clone()),
+        message: diag.message.clone(),
+        suggestions,
+    })
+}
+
+fn collect_suggestions(diag: &Diagnostic, out: &mut Vec<SuggestedFix>) {
+    for child in &diag.children {
+        for span in &child.spans {
+            let Some(replacement) = &span.suggested_replacement else {
+                continue;
+            };
+            out.push(SuggestedFix {
+                file: span.file_name.clone(),
+                line_start: span.line_start,
+                line_end: span.line_end,
+                column_start: span.column_start,
+                column_end: span.column_end,
+                replacement: replacement.clone(),
+                machine_applicable: span.suggestion_applicability.as_deref()
+                    == Some("MachineApplicable"),
+            });
+        }
+        collect_suggestions(child, out);
+    }
+}
+
+/// Run `cargo clippy` and keep only the lint diagnostics; plain rustc
+/// errors already come from [`check_project`] and would double up.
+pub fn check_clippy(repo: &Path) -> Result<Vec<AnalysisIssue>> {
+    let output = Command::new("cargo")
+        .args(["clippy", "--workspace", "--message-format=json"])
+        .current_dir(repo)
+        .output()
+        .context("failed to invoke cargo clippy")?;
+    let mut issues = parse_messages(&String::from_utf8_lossy(&output.stdout))?;
+    issues.retain(|i| {
+        i.code
+            .as_deref()
+            .is_some_and(|code| code.starts_with("clippy::"))
+    });
+    Ok(issues)
+}
+
+/// Run `cargo audit --json` and convert its advisories. Severity mapping:
+/// vulnerabilities become errors, the warning sections (unmaintained,
+/// unsound, yanked) become warnings.
+pub fn audit(repo: &Path) -> Result<Vec<AnalysisIssue>> {
+    let output = Command::new("cargo")
+        .args(["audit", "--json"])
+        .current_dir(repo)
+        .output()
+        .context("failed to invoke cargo audit")?;
+    parse_audit(&String::from_utf8_lossy(&output.stdout))
+}
+
+#[derive(Deserialize)]
+struct AuditReport {
+    vulnerabilities: AuditVulnerabilities,
+    #[serde(default)]
+    warnings: std::collections::BTreeMap<String, Vec<AuditEntry>>,
+}
+
+#[derive(Deserialize)]
+struct AuditVulnerabilities {
+    #[serde(default)]
+    list: Vec<AuditEntry>,
+}
+
+#[derive(Deserialize)]
+struct AuditEntry {
+    advisory: Option<Advisory>,
+    package: AuditPackage,
+    #[serde(default)]
+    versions: Option<AuditVersions>,
+}
+
+#[derive(Deserialize)]
+struct AuditVersions {
+    #[serde(default)]
+    patched: Vec<String>,
+}
+
+#[derive(Deserialize)]
+struct Advisory {
+    id: String,
+    title: String,
+}
+
+#[derive(Deserialize)]
+struct AuditPackage {
+    name: String,
+    version: String,
+}
+
+/// Parse a `cargo audit --json` report. Advisories point at `Cargo.lock`
+/// since that is where the vulnerable version is pinned.
+pub fn parse_audit(json: &str) -> Result<Vec<AnalysisIssue>> {
+    let report: AuditReport = serde_json::from_str(json).context("malformed cargo audit output")?;
+    let mut issues = Vec::new();
+    for entry in &report.vulnerabilities.list {
+        issues.push(audit_issue(entry, "error"));
+    }
+    for entries in report.warnings.values() {
+        for entry in entries {
+            issues.push(audit_issue(entry, "warning"));
+        }
+    }
+    Ok(issues)
+}
+
+fn audit_issue(entry: &AuditEntry, level: &str) -> AnalysisIssue {
+    let (code, title) = match &entry.advisory {
+        Some(advisory) => (Some(advisory.id.clone()), advisory.title.as_str()),
+        None => (None, "flagged by cargo audit"),
+    };
+    let mut message = format!("{} {}: {title}", entry.package.name, entry.package.version);
+    // Carry the patched ranges in the message so the dependency-upgrade
+    // fixer knows how far a requirement must be raised.
+    if let Some(versions) = &entry.versions {
+        if !versions.patched.is_empty() {
+            message.push_str(&format!(" (patched: {})", versions.patched.join(", ")));
+        }
+    }
+    AnalysisIssue {
+        file: "Cargo.lock".to_string(),
+        line: 1,
+        column: 1,
+        level: level.to_string(),
+        code,
+        message,
+        suggestions: Vec::new(),
+    }
+}
+
+/// Turn a suggested fix into a unified diff against the file on disk, with
+/// three lines of context, so it can be validated and applied like any
+/// other patch.
+pub fn suggestion_diff(repo: &Path, fix: &SuggestedFix) -> Result<String> {
+    let path = repo.join(&fix.file);
+    let original =
+        std::fs::read_to_string(&path).with_context(|| format!("cannot read {}", path.display()))?;
+    let lines: Vec<&str> = original.lines().collect();
+    if fix.line_start == 0 || fix.line_end > lines.len() || fix.line_start > fix.line_end {
+        bail!(
+            "suggestion span {}:{}-{} is outside the file",
+            fix.file,
+            fix.line_start,
+            fix.line_end
+        );
+    }
+    // Columns are 1-based character offsets into the start and end lines.
+    let prefix: String = lines[fix.line_start - 1]
+        .chars()
+        .take(fix.column_start - 1)
+        .collect();
+    let suffix: String = lines[fix.line_end - 1]
+        .chars()
+        .skip(fix.column_end - 1)
+        .collect();
+    let replaced = format!("{prefix}{}{suffix}", fix.replacement);
+    let new_lines: Vec<&str> = replaced.split('\n').collect();
+
+    // 0-based start of the hunk (three context lines before the change),
+    // 1-based inclusive end.
+    let ctx_start = fix.line_start.saturating_sub(4);
+    let ctx_end = (fix.line_end + 3).min(lines.len());
+    let old_count = ctx_end - ctx_start;
+    let new_count = old_count - (fix.line_end - fix.line_start + 1) + new_lines.len();
+
+    let mut diff = format!(
+        "--- a/{file}\n+++ b/{file}\n@@ -{start},{old_count} +{start},{new_count} @@\n",
+        file = fix.file,
+        start = ctx_start + 1,
+    );
+    for line in &lines[ctx_start..fix.line_start - 1] {
+        diff.push_str(&format!(" {line}\n"));
+    }
+    for line in &lines[fix.line_start - 1..fix.line_end] {
+        diff.push_str(&format!("-{line}\n"));
+    }
+    for line in &new_lines {
+        diff.push_str(&format!("+{line}\n"));
+    }
+    for line in &lines[fix.line_end..ctx_end] {
+        diff.push_str(&format!(" {line}\n"));
+    }
+    Ok(diff)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_diagnostics_with_suggestions() {
+        let stream = concat!(
+            r#"{"reason":"compiler-artifact","target":{"name":"x"}}"#,
+            "\n",
+            r#"{"reason":"compiler-message","message":{"level":"error","message":"mismatched types","code":{"code":"E0308"},"spans":[{"file_name":"src/main.rs","line_start":3,"line_end":3,"column_start":13,"column_end":14,"is_primary":true,"suggested_replacement":null,"suggestion_applicability":null}],"children":[{"level":"help","message":"try","code":null,"spans":[{"file_name":"src/main.rs","line_start":3,"line_end":3,"column_start":13,"column_end":14,"is_primary":false,"suggested_replacement":"1u64","suggestion_applicability":"MachineApplicable"}],"children":[]}]}}"#,
+            "\n",
+            r#"{"reason":"compiler-message","message":{"level":"error","message":"aborting due to 1 previous error","code":null,"spans":[],"children":[]}}"#,
+            "\n",
+        );
+        let issues = parse_messages(stream).unwrap();
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].code.as_deref(), Some("E0308"));
+        assert_eq!(issues[0].line, 3);
+        assert_eq!(issues[0].suggestions.len(), 1);
+        assert!(issues[0].suggestions[0].machine_applicable);
+        assert_eq!(issues[0].suggestions[0].replacement, "1u64");
+    }
+
+    #[test]
+    fn parses_audit_report_with_severity_mapping() {
+        let json = r#"{
+            "vulnerabilities": {
+                "list": [
+                    {"advisory": {"id": "RUSTSEC-2024-0001", "title": "heap overflow"}, "package": {"name": "badcrate", "version": "0.1.0"}, "versions": {"patched": [">=0.2.1"]}}
+                ]
+            },
+            "warnings": {
+                "unmaintained": [
+                    {"advisory": {"id": "RUSTSEC-2023-0099", "title": "no longer maintained"}, "package": {"name": "oldcrate", "version": "2.0.0"}}
+                ]
+            }
+        }"#;
+        let issues = parse_audit(json).unwrap();
+        assert_eq!(issues.len(), 2);
+        let vuln = issues.iter().find(|i| i.level == "error").unwrap();
+        assert_eq!(vuln.code.as_deref(), Some("RUSTSEC-2024-0001"));
+        assert_eq!(vuln.file, "Cargo.lock");
+        assert!(vuln.message.contains("badcrate 0.1.0"));
+        assert!(vuln.message.contains("(patched: >=0.2.1)"));
+        let warning = issues.iter().find(|i| i.level == "warning").unwrap();
+        assert_eq!(warning.code.as_deref(), Some("RUSTSEC-2023-0099"));
+    }
+
+    #[test]
+    fn suggestion_diff_round_trips_through_the_patch_engine() {
+        let dir = tempfile::tempdir().unwrap();
+        let source = "fn main() {\n    let x: u64 = 1u32;\n    println!(\"{x}\");\n}\n";
+        std::fs::write(dir.path().join("main.rs"), source).unwrap();
+        let fix = SuggestedFix {
+            file: "main.rs".to_string(),
+            line_start: 2,
+            line_end: 2,
+            column_start: 18,
+            column_end: 22,
+            replacement: "1u64".to_string(),
+            machine_applicable: true,
+        };
+        let diff = suggestion_diff(dir.path(), &fix).unwrap();
+        let files = crate::patch_generator::parse(&diff).unwrap();
+        let patched = crate::patch_generator::apply_diff(source, &files[0]).unwrap();
+        assert_eq!(patched, source.replace("1u32", "1u64"));
+    }
+}
@@ -0,0 +1,302 @@
+//! Reproduction-test synthesis from failure logs.
+//!
+//! Issues arrive with the captured log tail of the failing build
+//! (build-monitor attaches it when filing). This module parses the panic
+//! backtrace, compiler error, or test assertion out of that log, locates
+//! the implicated function in the repository, and synthesizes a minimal
+//! test file in the owning crate's `tests/` directory. The test body is a
+//! scaffold, `#[ignore]`d until the failing call is filled in, but it pins
+//! the failure context in the tree where the fix lands.
+
+use crate::types::Issue;
+use anyhow::{Context, Result};
+use std::path::{Path, PathBuf};
+
+/// What kind of failure the log described.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FailureKind {
+    Panic,
+    CompilerError,
+    TestAssertion,
+}
+
+impl FailureKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            FailureKind::Panic => "panic",
+            FailureKind::CompilerError => "compiler error",
+            FailureKind::TestAssertion => "test assertion",
+        }
+    }
+}
+
+/// The location and message parsed out of a failure log.
+#[derive(Debug)]
+pub struct FailureSignature {
+    pub kind: FailureKind,
+    /// Repo-relative source file, when the log named one.
+    pub file: Option<String>,
+    pub line: Option<usize>,
+    /// First line of the panic message or diagnostic.
+    pub message: String,
+}
+
+/// A synthesized reproduction test, ready to be written into the repo.
+#[derive(Debug)]
+pub struct GeneratedTest {
+    /// Repo-relative path of the test file.
+    pub path: PathBuf,
+    pub content: String,
+}
+
+/// Parse the failure out of an issue's log and synthesize a reproduction
+/// test for it. `None` when the log matches no known failure shape.
+pub fn generate_from_failure(repo: &Path, issue: &Issue) -> Result<Option<GeneratedTest>> {
+    let Some(signature) = parse_failure(&issue.log) else {
+        return Ok(None);
+    };
+    let function = signature
+        .file
+        .as_deref()
+        .zip(signature.line)
+        .and_then(|(file, line)| enclosing_function(repo, file, line));
+    Ok(Some(synthesize(repo, issue, &signature, function.as_deref())))
+}
+
+/// Write the test into the repository, creating the `tests/` directory on
+/// first use. An existing file is kept: the first reproduction of a
+/// failure wins, and re-analysis must not clobber a filled-in scaffold.
+pub fn store(repo: &Path, test: &GeneratedTest) -> Result<bool> {
+    let path = repo.join(&test.path);
+    if path.exists() {
+        return Ok(false);
+    }
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)
+            .with_context(|| format!("cannot create {}", parent.display()))?;
+    }
+    std::fs::write(&path, &test.content)
+        .with_context(|| format!("cannot write {}", path.display()))?;
+    Ok(true)
+}
+
+/// Find the first recognizable failure in a log: a panic (modern or
+/// pre-1.72 format) or a compiler diagnostic with a `-->` location line.
+pub fn parse_failure(log: &str) -> Option<FailureSignature> {
+    let mut lines = log.lines().peekable();
+    while let Some(line) = lines.next() {
+        let trimmed = line.trim();
+        if let Some(rest) = trimmed
+            .strip_prefix("thread '")
+            .and_then(|r| r.split_once("' panicked at "))
+            .map(|(_, rest)| rest)
+        {
+            // Pre-1.72: panicked at 'message', src/lib.rs:10:4
+            if let Some(rest) = rest.strip_prefix('\'') {
+                let (message, location) = rest.rsplit_once("', ")?;
+                let (file, line) = parse_location(location);
+                return Some(signature_for_panic(message, file, line));
+            }
+            // Modern: panicked at src/lib.rs:10:4:\nmessage
+            let (file, line_no) = parse_location(rest);
+            let message = lines
+                .find(|l| !l.trim().is_empty())
+                .unwrap_or("")
+                .trim()
+                .to_string();
+            return Some(signature_for_panic(&message, file, line_no));
+        }
+        if trimmed.starts_with("error[") || trimmed.starts_with("error:") {
+            let message = trimmed
+                .split_once(": ")
+                .map(|(_, m)| m)
+                .unwrap_or(trimmed)
+                .to_string();
+            let (file, line_no) = match lines.peek().map(|l| l.trim()) {
+                Some(next) if next.starts_with("--> ") => {
+                    parse_location(next.trim_start_matches("--> "))
+                }
+                _ => (None, None),
+            };
+            return Some(FailureSignature {
+                kind: FailureKind::CompilerError,
+                file,
+                line: line_no,
+                message,
+            });
+        }
+    }
+    None
+}
+
+fn signature_for_panic(message: &str, file: Option<String>, line: Option<usize>) -> FailureSignature {
+    let kind = if message.starts_with("assertion") {
+        FailureKind::TestAssertion
+    } else {
+        FailureKind::Panic
+    };
+    FailureSignature {
+        kind,
+        file,
+        line,
+        message: message.to_string(),
+    }
+}
+
+/// Split `src/lib.rs:42:5` (an optional trailing colon is tolerated) into
+/// the file and line number.
+fn parse_location(location: &str) -> (Option<String>, Option<usize>) {
+    let parts: Vec<&str> = location.trim_end_matches(':').split(':').collect();
+    match parts.as_slice() {
+        [file, line, ..] if line.chars().all(|c| c.is_ascii_digit()) => {
+            (Some(file.to_string()), line.parse().ok())
+        }
+        _ => (None, None),
+    }
+}
+
+/// The name of the function whose body contains `line`, found by scanning
+/// the source upward for the nearest `fn` item.
+fn enclosing_function(repo: &Path, file: &str, line: usize) -> Option<String> {
+    let source = std::fs::read_to_string(repo.join(file)).ok()?;
+    let lines: Vec<&str> = source.lines().collect();
+    let upto = line.min(lines.len());
+    for candidate in lines[..upto].iter().rev() {
+        if let Some(after) = candidate.split("fn ").nth(1) {
+            let name: String = after
+                .chars()
+                .take_while(|c| c.is_alphanumeric() || *c == '_')
+                .collect();
+            if !name.is_empty() {
+                return Some(name);
+            }
+        }
+    }
+    None
+}
+
+/// Build the scaffold test file for a parsed failure, placed in the
+/// `tests/` directory of the crate owning the implicated file (the repo
+/// root when no file was named).
+fn synthesize(
+    repo: &Path,
+    issue: &Issue,
+    signature: &FailureSignature,
+    function: Option<&str>,
+) -> GeneratedTest {
+    let short = &issue.id.simple().to_string()[..8];
+    let root = signature
+        .file
+        .as_deref()
+        .map(|file| crate_root(repo, file))
+        .unwrap_or_default();
+    let path = root.join("tests").join(format!("repro_{short}.rs"));
+
+    let mut content = format!(
+        "//! Reproduction scaffold for issue {}, generated by self-healing.\n//!\n//! {} in service {} at commit {}.\n",
+        issue.id,
+        signature.kind.as_str(),
+        issue.service,
+        issue.commit
+    );
+    if let (Some(file), Some(line)) = (&signature.file, signature.line) {
+        match function {
+            Some(function) => content.push_str(&format!(
+                "//! Observed in `{function}` at {file}:{line}.\n"
+            )),
+            None => content.push_str(&format!("//! Observed at {file}:{line}.\n")),
+        }
+    }
+    let name = match function {
+        Some(function) => format!("reproduces_{function}_failure"),
+        None => format!("reproduces_issue_{short}"),
+    };
+    content.push_str(&format!(
+        "\n#[test]\n#[ignore = \"generated scaffold: fill in the failing call\"]\nfn {name}() {{\n    // {}\n    unimplemented!(\"reproduce the failure above\");\n}}\n",
+        signature.message.replace('\n', " ")
+    ));
+    GeneratedTest { path, content }
+}
+
+/// The directory of the nearest `Cargo.toml` at or above `file`, relative
+/// to the repo root; empty (the root itself) when none is found.
+pub fn crate_root(repo: &Path, file: &str) -> PathBuf {
+    let mut dir = Path::new(file).parent();
+    while let Some(d) = dir {
+        if !d.as_os_str().is_empty() && repo.join(d).join("Cargo.toml").exists() {
+            return d.to_path_buf();
+        }
+        dir = d.parent();
+    }
+    PathBuf::new()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_the_modern_panic_format() {
+        let log = "running 1 test\nthread 'tests::overflow' panicked at services/x/src/lib.rs:42:5:\nattempt to add with overflow\nnote: run with RUST_BACKTRACE=1\n";
+        let sig = parse_failure(log).unwrap();
+        assert_eq!(sig.kind, FailureKind::Panic);
+        assert_eq!(sig.file.as_deref(), Some("services/x/src/lib.rs"));
+        assert_eq!(sig.line, Some(42));
+        assert_eq!(sig.message, "attempt to add with overflow");
+    }
+
+    #[test]
+    fn parses_the_pre_1_72_panic_format_and_assertions() {
+        let log = "thread 'main' panicked at 'assertion failed: total > 0', src/main.rs:10:4\n";
+        let sig = parse_failure(log).unwrap();
+        assert_eq!(sig.kind, FailureKind::TestAssertion);
+        assert_eq!(sig.file.as_deref(), Some("src/main.rs"));
+        assert_eq!(sig.line, Some(10));
+        assert!(sig.message.starts_with("assertion failed"));
+    }
+
+    #[test]
+    fn parses_a_compiler_diagnostic_with_location() {
+        let log = "   Compiling x v0.1.0\nerror[E0308]: mismatched types\n  --> src/lib.rs:7:13\n";
+        let sig = parse_failure(log).unwrap();
+        assert_eq!(sig.kind, FailureKind::CompilerError);
+        assert_eq!(sig.file.as_deref(), Some("src/lib.rs"));
+        assert_eq!(sig.line, Some(7));
+        assert_eq!(sig.message, "mismatched types");
+        assert!(parse_failure("all green, nothing to see").is_none());
+    }
+
+    #[test]
+    fn synthesizes_a_scaffold_in_the_owning_crate() {
+        let repo = tempfile::tempdir().unwrap();
+        std::fs::create_dir_all(repo.path().join("services/x/src")).unwrap();
+        std::fs::write(repo.path().join("services/x/Cargo.toml"), "[package]\n").unwrap();
+        std::fs::write(
+            repo.path().join("services/x/src/lib.rs"),
+            "pub fn add(a: u8, b: u8) -> u8 {\n    a + b\n}\n",
+        )
+        .unwrap();
+        let issue = Issue::new(
+            "build-monitor",
+            "x",
+            "abc1234",
+            "test",
+            "thread 'tests::overflow' panicked at services/x/src/lib.rs:2:5:\nattempt to add with overflow\n",
+            vec![],
+        );
+
+        let test = generate_from_failure(repo.path(), &issue).unwrap().unwrap();
+        assert_eq!(
+            test.path.parent().unwrap(),
+            Path::new("services/x/tests")
+        );
+        assert!(test.content.contains("fn reproduces_add_failure()"));
+        assert!(test.content.contains("attempt to add with overflow"));
+        assert!(test.content.contains("#[ignore"));
+
+        assert!(store(repo.path(), &test).unwrap());
+        // A second store must not clobber a filled-in scaffold.
+        assert!(!store(repo.path(), &test).unwrap());
+        assert!(repo.path().join(&test.path).exists());
+    }
+}
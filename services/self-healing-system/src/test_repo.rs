@@ -0,0 +1,217 @@
+//! Persistent repository of reproduction test cases.
+//!
+//! Scaffolds synthesized by `test_gen` and the minimizer's shrunken
+//! outputs are kept in the shared database alongside the issues they
+//! reproduce: the source itself, the originating service and commit, the
+//! minimization status, and a history of executions. Content is
+//! deduplicated by hash, so re-analyzing the same failure does not pile
+//! up copies. The `tests list` and `tests stats` subcommands read from
+//! here.
+
+use crate::database::Database;
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use uuid::Uuid;
+
+/// A stored reproduction test case.
+#[derive(Debug, Clone, Serialize)]
+pub struct TestCase {
+    pub id: Uuid,
+    /// Hash of `content`; the dedup key across the repository.
+    pub content_hash: String,
+    pub content: String,
+    /// Service whose failure this test reproduces.
+    pub service: String,
+    /// Commit of the build the failure was observed at.
+    pub origin_commit: String,
+    pub issue_id: Option<Uuid>,
+    /// Whether the content has been through delta-debugging minimization.
+    pub minimized: bool,
+    /// Line count of the content as first stored, before minimization.
+    pub original_lines: i64,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl TestCase {
+    pub fn new(service: &str, origin_commit: &str, issue_id: Option<Uuid>, content: &str) -> Self {
+        let now = Utc::now();
+        Self {
+            id: Uuid::new_v4(),
+            content_hash: content_hash(content),
+            content: content.to_string(),
+            service: service.to_string(),
+            origin_commit: origin_commit.to_string(),
+            issue_id,
+            minimized: false,
+            original_lines: content.lines().count() as i64,
+            created_at: now,
+            updated_at: now,
+        }
+    }
+}
+
+/// One recorded execution of a stored test case.
+#[derive(Debug, Serialize)]
+pub struct TestCaseRun {
+    pub id: Uuid,
+    pub test_case_id: Uuid,
+    /// Whether the run still reproduced the failure.
+    pub still_failing: bool,
+    pub ran_at: DateTime<Utc>,
+}
+
+/// Aggregate counts over the repository, for `tests stats`.
+#[derive(Debug, Serialize)]
+pub struct TestCaseStats {
+    pub total: i64,
+    pub minimized: i64,
+    pub runs: i64,
+    pub still_failing_runs: i64,
+    pub by_service: Vec<ServiceCount>,
+}
+
+/// Stored test cases per service, most first.
+#[derive(Debug, Serialize)]
+pub struct ServiceCount {
+    pub service: String,
+    pub count: i64,
+}
+
+/// The dedup key for a test case's source.
+pub fn content_hash(content: &str) -> String {
+    hex::encode(Sha256::digest(content.as_bytes()))
+}
+
+pub struct TestCaseRepository {
+    database: Database,
+}
+
+impl TestCaseRepository {
+    pub fn new(database: Database) -> Self {
+        Self { database }
+    }
+
+    /// Store a test case, unless identical content is already present.
+    /// Returns the id of the row that holds the content and whether it
+    /// was newly inserted.
+    pub async fn store(
+        &self,
+        service: &str,
+        origin_commit: &str,
+        issue_id: Option<Uuid>,
+        content: &str,
+    ) -> Result<(Uuid, bool)> {
+        if let Some(existing) = self.find_by_hash(&content_hash(content)).await? {
+            return Ok((existing.id, false));
+        }
+        let case = TestCase::new(service, origin_commit, issue_id, content);
+        self.database.insert_test_case(&case).await?;
+        Ok((case.id, true))
+    }
+
+    /// The stored test case whose content hashes to `hash`, if any.
+    pub async fn find_by_hash(&self, hash: &str) -> Result<Option<TestCase>> {
+        self.database.find_test_case_by_hash(hash).await
+    }
+
+    /// Replace a test case's content with its minimized form and flag it
+    /// as minimized. The dedup hash follows the new content.
+    pub async fn mark_minimized(&self, id: Uuid, content: &str) -> Result<()> {
+        self.database
+            .mark_test_case_minimized(id, content, &content_hash(content))
+            .await
+    }
+
+    /// Append one execution to a test case's history.
+    pub async fn record_run(&self, id: Uuid, still_failing: bool) -> Result<()> {
+        let run = TestCaseRun {
+            id: Uuid::new_v4(),
+            test_case_id: id,
+            still_failing,
+            ran_at: Utc::now(),
+        };
+        self.database.record_test_case_run(&run).await
+    }
+
+    /// Stored test cases, newest first, optionally restricted to one
+    /// service.
+    pub async fn list(&self, service: Option<&str>, limit: i64) -> Result<Vec<TestCase>> {
+        self.database.list_test_cases(service, limit).await
+    }
+
+    pub async fn stats(&self) -> Result<TestCaseStats> {
+        self.database.test_case_stats().await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn repository() -> TestCaseRepository {
+        TestCaseRepository::new(Database::open_in_memory().await.unwrap())
+    }
+
+    #[tokio::test]
+    async fn deduplicates_identical_content() {
+        let repo = repository().await;
+        let (first, new) = repo
+            .store("api", "abc1234", None, "assert_eq!(1, 2);\n")
+            .await
+            .unwrap();
+        assert!(new);
+        // The same content again, even from another build, is not re-stored.
+        let (second, new) = repo
+            .store("api", "def5678", None, "assert_eq!(1, 2);\n")
+            .await
+            .unwrap();
+        assert!(!new);
+        assert_eq!(first, second);
+        assert_eq!(repo.stats().await.unwrap().total, 1);
+    }
+
+    #[tokio::test]
+    async fn minimization_and_runs_land_in_stats() {
+        let repo = repository().await;
+        let (id, _) = repo
+            .store("worker", "abc1234", Some(Uuid::new_v4()), "a\nb\nc\n")
+            .await
+            .unwrap();
+        repo.mark_minimized(id, "b\n").await.unwrap();
+        repo.record_run(id, true).await.unwrap();
+        repo.record_run(id, false).await.unwrap();
+
+        let case = repo.find_by_hash(&content_hash("b\n")).await.unwrap().unwrap();
+        assert_eq!(case.id, id);
+        assert!(case.minimized);
+        assert_eq!(case.content, "b\n");
+
+        let stats = repo.stats().await.unwrap();
+        assert_eq!(stats.total, 1);
+        assert_eq!(stats.minimized, 1);
+        assert_eq!(stats.runs, 2);
+        assert_eq!(stats.still_failing_runs, 1);
+    }
+
+    #[tokio::test]
+    async fn list_filters_by_service_and_orders_newest_first() {
+        let repo = repository().await;
+        repo.store("api", "abc1234", None, "one\n").await.unwrap();
+        repo.store("worker", "abc1234", None, "two\n").await.unwrap();
+        repo.store("api", "def5678", None, "three\n").await.unwrap();
+
+        let all = repo.list(None, 10).await.unwrap();
+        assert_eq!(all.len(), 3);
+        let api = repo.list(Some("api"), 10).await.unwrap();
+        assert_eq!(api.len(), 2);
+        assert!(api.iter().all(|case| case.service == "api"));
+
+        let stats = repo.stats().await.unwrap();
+        assert_eq!(stats.by_service.len(), 2);
+        assert_eq!(stats.by_service[0].service, "api");
+        assert_eq!(stats.by_service[0].count, 2);
+    }
+}
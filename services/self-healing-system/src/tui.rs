@@ -0,0 +1,377 @@
+//! Interactive review TUI: `self-healing-system review`.
+//!
+//! Lists the patches awaiting human review next to their validation
+//! outcome and risk assessment, renders the diff with added and removed
+//! lines colored, and records approve/reject verdicts through the same
+//! path as `/api/patches/{id}/review` — no more piping JSON dumps
+//! through a shell to make a decision.
+
+use crate::daemon::SelfHealingDaemon;
+use crate::review::RiskAssessment;
+use crate::types::{Issue, Patch, PatchStatus, ReviewVerdict};
+use anyhow::Result;
+use crossterm::event::{self, Event, KeyCode, KeyEventKind};
+use ratatui::layout::{Constraint, Layout};
+use ratatui::style::{Color, Modifier, Style, Stylize};
+use ratatui::text::{Line, Text};
+use ratatui::widgets::{Block, Borders, List, ListItem, ListState, Paragraph, Wrap};
+use std::time::Duration;
+
+/// One patch in the queue with everything a reviewer looks at.
+struct Entry {
+    patch: Patch,
+    issue: Option<Issue>,
+    risk: RiskAssessment,
+}
+
+/// What a keypress asks for; the event loop performs the daemon calls.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Action {
+    None,
+    Quit,
+    Approve,
+    Reject,
+    Regenerate,
+}
+
+struct App {
+    entries: Vec<Entry>,
+    selected: usize,
+    /// Vertical scroll offset into the selected patch's diff.
+    scroll: u16,
+    status: String,
+}
+
+const HELP: &str = "↑/↓ select · PgUp/PgDn scroll diff · a approve · r reject · g regenerate · q quit";
+
+impl App {
+    fn new(entries: Vec<Entry>) -> Self {
+        Self {
+            entries,
+            selected: 0,
+            scroll: 0,
+            status: HELP.to_string(),
+        }
+    }
+
+    fn current(&self) -> Option<&Entry> {
+        self.entries.get(self.selected)
+    }
+
+    fn remove_current(&mut self) {
+        if self.selected < self.entries.len() {
+            self.entries.remove(self.selected);
+        }
+        self.selected = self.selected.min(self.entries.len().saturating_sub(1));
+        self.scroll = 0;
+    }
+
+    fn handle_key(&mut self, code: KeyCode) -> Action {
+        match code {
+            KeyCode::Char('q') | KeyCode::Esc => return Action::Quit,
+            KeyCode::Down | KeyCode::Char('j') if self.selected + 1 < self.entries.len() => {
+                self.selected += 1;
+                self.scroll = 0;
+            }
+            KeyCode::Up | KeyCode::Char('k') => {
+                self.selected = self.selected.saturating_sub(1);
+                self.scroll = 0;
+            }
+            KeyCode::PageDown => self.scroll = self.scroll.saturating_add(10),
+            KeyCode::PageUp => self.scroll = self.scroll.saturating_sub(10),
+            KeyCode::Char('a') if self.current().is_some() => return Action::Approve,
+            KeyCode::Char('r') if self.current().is_some() => return Action::Reject,
+            KeyCode::Char('g') if self.current().is_some() => return Action::Regenerate,
+            _ => {}
+        }
+        Action::None
+    }
+}
+
+/// Open the review queue in the terminal and block until the reviewer
+/// quits. Verdicts and regenerations hit the daemon directly.
+pub async fn run(daemon: &SelfHealingDaemon, reviewer: &str) -> Result<()> {
+    let mut app = App::new(load(daemon).await?);
+    let mut terminal = ratatui::init();
+    let result = event_loop(&mut terminal, &mut app, daemon, reviewer).await;
+    ratatui::restore();
+    result
+}
+
+/// The pending patches, each joined with its issue and scored the same
+/// way the API review queue scores them.
+async fn load(daemon: &SelfHealingDaemon) -> Result<Vec<Entry>> {
+    let patches = daemon
+        .database
+        .patches_by_status(PatchStatus::PendingReview, 100)
+        .await?;
+    let mut entries = Vec::with_capacity(patches.len());
+    for patch in patches {
+        let issue = daemon.database.issue_by_id(patch.issue_id).await.ok().flatten();
+        let project = issue
+            .as_ref()
+            .map(|i| i.project.clone())
+            .unwrap_or_else(|| "default".to_string());
+        let breaking = daemon.dry_run_diff(&project, &patch.diff).unwrap_or_default();
+        let risk = crate::review::assess(&patch.diff, &breaking, patch.validation.as_ref());
+        entries.push(Entry { patch, issue, risk });
+    }
+    Ok(entries)
+}
+
+async fn event_loop(
+    terminal: &mut ratatui::DefaultTerminal,
+    app: &mut App,
+    daemon: &SelfHealingDaemon,
+    reviewer: &str,
+) -> Result<()> {
+    loop {
+        terminal.draw(|frame| draw(frame, app))?;
+        if !event::poll(Duration::from_millis(150))? {
+            continue;
+        }
+        let Event::Key(key) = event::read()? else {
+            continue;
+        };
+        if key.kind != KeyEventKind::Press {
+            continue;
+        }
+        match app.handle_key(key.code) {
+            Action::Quit => return Ok(()),
+            Action::None => {}
+            Action::Approve => decide(app, daemon, reviewer, ReviewVerdict::Approved).await,
+            Action::Reject => decide(app, daemon, reviewer, ReviewVerdict::Rejected).await,
+            Action::Regenerate => regenerate(app, daemon).await,
+        }
+    }
+}
+
+/// Record the verdict and drop the patch from the queue; failures land
+/// in the status line instead of tearing the UI down.
+async fn decide(app: &mut App, daemon: &SelfHealingDaemon, reviewer: &str, verdict: ReviewVerdict) {
+    let Some(entry) = app.current() else { return };
+    match daemon
+        .review_patch(entry.patch.id, reviewer, verdict, None)
+        .await
+    {
+        Ok((_, patch)) => {
+            app.status = format!("patch {} is now {}", patch.id, patch.status.as_str());
+            app.remove_current();
+        }
+        Err(e) => app.status = format!("review failed: {e:#}"),
+    }
+}
+
+async fn regenerate(app: &mut App, daemon: &SelfHealingDaemon) {
+    let Some(entry) = app.current() else { return };
+    match daemon.generate_patch(entry.patch.issue_id).await {
+        Ok(patch) => {
+            app.status = format!(
+                "regenerated as patch {} ({}); it will appear once validated",
+                patch.id,
+                patch.status.as_str()
+            )
+        }
+        Err(e) => app.status = format!("regeneration failed: {e:#}"),
+    }
+}
+
+fn draw(frame: &mut ratatui::Frame, app: &App) {
+    let [body, footer] =
+        Layout::vertical([Constraint::Min(1), Constraint::Length(1)]).areas(frame.area());
+    let [list_area, detail] =
+        Layout::horizontal([Constraint::Length(40), Constraint::Min(1)]).areas(body);
+
+    let items: Vec<ListItem> = app.entries.iter().map(|e| ListItem::new(list_label(e))).collect();
+    let mut state = ListState::default();
+    if !app.entries.is_empty() {
+        state.select(Some(app.selected));
+    }
+    frame.render_stateful_widget(
+        List::new(items)
+            .block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .title(format!("pending review ({})", app.entries.len())),
+            )
+            .highlight_style(Style::default().add_modifier(Modifier::REVERSED)),
+        list_area,
+        &mut state,
+    );
+
+    match app.current() {
+        Some(entry) => {
+            let header = header_lines(entry);
+            let [head_area, diff_area] =
+                Layout::vertical([Constraint::Length(header.len() as u16 + 2), Constraint::Min(1)])
+                    .areas(detail);
+            frame.render_widget(
+                Paragraph::new(Text::from(header))
+                    .block(
+                        Block::default()
+                            .borders(Borders::ALL)
+                            .title(format!("patch {}", entry.patch.id)),
+                    )
+                    .wrap(Wrap { trim: false }),
+                head_area,
+            );
+            frame.render_widget(
+                Paragraph::new(diff_text(&entry.patch.diff))
+                    .scroll((app.scroll, 0))
+                    .block(Block::default().borders(Borders::ALL).title("diff")),
+                diff_area,
+            );
+        }
+        None => frame.render_widget(
+            Paragraph::new("nothing awaiting review")
+                .block(Block::default().borders(Borders::ALL)),
+            detail,
+        ),
+    }
+
+    frame.render_widget(Paragraph::new(app.status.as_str()).dark_gray(), footer);
+}
+
+fn list_label(entry: &Entry) -> String {
+    let short = &entry.patch.id.simple().to_string()[..8];
+    let service = entry
+        .issue
+        .as_ref()
+        .map(|i| i.service.as_str())
+        .unwrap_or("?");
+    format!(
+        "{short} {service} · {} · risk {}",
+        entry.patch.origin, entry.risk.score
+    )
+}
+
+fn header_lines(entry: &Entry) -> Vec<Line<'static>> {
+    let mut lines = vec![Line::from(entry.patch.description.clone())];
+    if let Some(issue) = &entry.issue {
+        lines.push(Line::from(format!(
+            "issue: {} failure in {} at {}",
+            issue.classification, issue.service, issue.commit
+        )));
+    }
+    let validation = validation_summary(&entry.patch);
+    let style = if entry.patch.validation.as_ref().is_some_and(|v| v.passed) {
+        Style::default().fg(Color::Green)
+    } else {
+        Style::default().fg(Color::Yellow)
+    };
+    lines.push(Line::styled(validation, style));
+    lines.push(Line::from(format!("risk score: {}", entry.risk.score)));
+    for reason in &entry.risk.reasons {
+        lines.push(Line::from(format!("  · {reason}")));
+    }
+    lines
+}
+
+/// One-line validation verdict for the header pane.
+fn validation_summary(patch: &Patch) -> String {
+    match &patch.validation {
+        None => "validation: not run".to_string(),
+        Some(v) => format!(
+            "validation: {} (build {}, tests {}, {} security issues)",
+            if v.passed { "passed" } else { "FAILED" },
+            if v.build_ok { "ok" } else { "failed" },
+            if v.tests_ok { "ok" } else { "failed" },
+            v.security_issues_found,
+        ),
+    }
+}
+
+/// The diff with unified-diff markup colored: additions green, removals
+/// red, hunk headers cyan, file headers bold.
+fn diff_text(diff: &str) -> Text<'_> {
+    Text::from(diff.lines().map(diff_line).collect::<Vec<_>>())
+}
+
+fn diff_line(line: &str) -> Line<'_> {
+    let style = if line.starts_with("diff --git")
+        || line.starts_with("+++")
+        || line.starts_with("---")
+    {
+        Style::default().add_modifier(Modifier::BOLD)
+    } else if line.starts_with("@@") {
+        Style::default().fg(Color::Cyan)
+    } else if line.starts_with('+') {
+        Style::default().fg(Color::Green)
+    } else if line.starts_with('-') {
+        Style::default().fg(Color::Red)
+    } else {
+        Style::default()
+    };
+    Line::styled(line, style)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use uuid::Uuid;
+
+    fn entry(description: &str) -> Entry {
+        Entry {
+            patch: Patch::new(Uuid::new_v4(), description, "--- a\n+++ b\n"),
+            issue: None,
+            risk: RiskAssessment {
+                score: 0,
+                reasons: Vec::new(),
+            },
+        }
+    }
+
+    #[test]
+    fn navigation_stays_in_bounds_and_resets_the_diff_scroll() {
+        let mut app = App::new(vec![entry("one"), entry("two")]);
+        assert_eq!(app.handle_key(KeyCode::Up), Action::None);
+        assert_eq!(app.selected, 0);
+        app.handle_key(KeyCode::PageDown);
+        assert_eq!(app.scroll, 10);
+        app.handle_key(KeyCode::Down);
+        assert_eq!((app.selected, app.scroll), (1, 0));
+        app.handle_key(KeyCode::Down);
+        assert_eq!(app.selected, 1);
+
+        app.remove_current();
+        assert_eq!(app.selected, 0);
+        app.remove_current();
+        // Verdict keys are inert once the queue is empty.
+        assert_eq!(app.handle_key(KeyCode::Char('a')), Action::None);
+        assert_eq!(app.handle_key(KeyCode::Char('q')), Action::Quit);
+    }
+
+    #[test]
+    fn diff_lines_are_colored_by_kind() {
+        let added = diff_line("+    let x = 1;");
+        assert_eq!(added.style.fg, Some(Color::Green));
+        let removed = diff_line("-    let x = 0;");
+        assert_eq!(removed.style.fg, Some(Color::Red));
+        let hunk = diff_line("@@ -1,3 +1,3 @@");
+        assert_eq!(hunk.style.fg, Some(Color::Cyan));
+        // File headers are not mistaken for removals.
+        let header = diff_line("--- a/src/lib.rs");
+        assert_eq!(header.style.fg, None);
+        assert!(header.style.add_modifier.contains(Modifier::BOLD));
+    }
+
+    #[test]
+    fn validation_summary_states_the_verdict() {
+        let mut patch = Patch::new(Uuid::new_v4(), "fix", "");
+        assert_eq!(validation_summary(&patch), "validation: not run");
+        patch.validation = Some(crate::types::ValidationResult {
+            passed: false,
+            build_ok: true,
+            tests_ok: false,
+            build_time_ms: 0,
+            test_time_ms: 0,
+            security_issues_found: 1,
+            security_issues: Vec::new(),
+            detail: None,
+        });
+        let summary = validation_summary(&patch);
+        assert!(summary.contains("FAILED"), "{summary}");
+        assert!(summary.contains("tests failed"), "{summary}");
+        assert!(summary.contains("1 security issues"), "{summary}");
+    }
+}
@@ -0,0 +1,298 @@
+//! Core data types shared across the daemon, database, and API.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// A reported failure the daemon should try to fix.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Issue {
+    pub id: Uuid,
+    /// Who reported it (e.g. "build-monitor", "api").
+    pub source: String,
+    /// Configured project the failure belongs to; "default" when the
+    /// daemon heals a single repository.
+    #[serde(default = "default_project_id")]
+    pub project: String,
+    pub service: String,
+    pub commit: String,
+    /// Failure classification from the reporter ("compiler", "test", ...).
+    pub classification: String,
+    /// Captured log tail of the failure.
+    pub log: String,
+    pub affected_files: Vec<String>,
+    /// Team owning the affected files, resolved from CODEOWNERS or the
+    /// configured mapping at ingest time; absent when nothing matched.
+    #[serde(default)]
+    pub owner: Option<String>,
+    pub status: IssueStatus,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl Issue {
+    pub fn new(
+        source: &str,
+        service: &str,
+        commit: &str,
+        classification: &str,
+        log: &str,
+        affected_files: Vec<String>,
+    ) -> Self {
+        let now = Utc::now();
+        Self {
+            id: Uuid::new_v4(),
+            source: source.to_string(),
+            project: default_project_id(),
+            service: service.to_string(),
+            commit: commit.to_string(),
+            classification: classification.to_string(),
+            log: log.to_string(),
+            affected_files,
+            owner: None,
+            status: IssueStatus::Open,
+            created_at: now,
+            updated_at: now,
+        }
+    }
+}
+
+fn default_project_id() -> String {
+    "default".to_string()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum IssueStatus {
+    /// Reported, no patch attempt yet.
+    Open,
+    /// A patch attempt is in progress.
+    Patching,
+    /// At least one candidate patch exists.
+    Patched,
+    /// Patch generation gave up.
+    Failed,
+    /// A patch was applied and the failure stopped reproducing.
+    Resolved,
+}
+
+impl IssueStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            IssueStatus::Open => "open",
+            IssueStatus::Patching => "patching",
+            IssueStatus::Patched => "patched",
+            IssueStatus::Failed => "failed",
+            IssueStatus::Resolved => "resolved",
+        }
+    }
+
+    pub fn parse(s: &str) -> Self {
+        match s {
+            "patching" => IssueStatus::Patching,
+            "patched" => IssueStatus::Patched,
+            "failed" => IssueStatus::Failed,
+            "resolved" => IssueStatus::Resolved,
+            _ => IssueStatus::Open,
+        }
+    }
+}
+
+/// A candidate fix for an issue, stored as a unified diff.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Patch {
+    pub id: Uuid,
+    pub issue_id: Uuid,
+    pub description: String,
+    /// Which stage produced the diff: "manual", "tool-suggestion",
+    /// "rule:<name>" for a deterministic fixer, or "llm".
+    #[serde(default = "default_origin")]
+    pub origin: String,
+    pub diff: String,
+    /// Reverse diff captured when the patch was applied and committed,
+    /// used to roll the commit back without relying on `git apply -R`.
+    pub rollback_diff: Option<String>,
+    /// URL of the pull request opened for this patch, in review mode.
+    pub pr_url: Option<String>,
+    pub status: PatchStatus,
+    /// Result of the last validation run, when one happened.
+    pub validation: Option<ValidationResult>,
+    /// Human-readable changelog entry, generated when the patch was
+    /// applied.
+    #[serde(default)]
+    pub changelog: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl Patch {
+    pub fn new(issue_id: Uuid, description: &str, diff: &str) -> Self {
+        let now = Utc::now();
+        Self {
+            id: Uuid::new_v4(),
+            issue_id,
+            description: description.to_string(),
+            origin: default_origin(),
+            diff: diff.to_string(),
+            rollback_diff: None,
+            pr_url: None,
+            status: PatchStatus::Proposed,
+            validation: None,
+            changelog: None,
+            created_at: now,
+            updated_at: now,
+        }
+    }
+}
+
+fn default_origin() -> String {
+    "manual".to_string()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PatchStatus {
+    /// Generated but not yet validated.
+    Proposed,
+    /// Passed validation; safe to apply.
+    Validated,
+    /// Passed validation but scored above the risk threshold; a human
+    /// must approve it before it can be applied.
+    PendingReview,
+    /// Failed validation.
+    Rejected,
+    /// Applied to the working tree.
+    Applied,
+    /// Pushed to a review branch and opened as a pull request.
+    InReview,
+    /// Applied, then reverted.
+    RolledBack,
+}
+
+impl PatchStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PatchStatus::Proposed => "proposed",
+            PatchStatus::Validated => "validated",
+            PatchStatus::PendingReview => "pending_review",
+            PatchStatus::Rejected => "rejected",
+            PatchStatus::Applied => "applied",
+            PatchStatus::InReview => "in_review",
+            PatchStatus::RolledBack => "rolled_back",
+        }
+    }
+
+    pub fn parse(s: &str) -> Self {
+        match s {
+            "validated" => PatchStatus::Validated,
+            "pending_review" => PatchStatus::PendingReview,
+            "rejected" => PatchStatus::Rejected,
+            "applied" => PatchStatus::Applied,
+            "in_review" => PatchStatus::InReview,
+            "rolled_back" => PatchStatus::RolledBack,
+            _ => PatchStatus::Proposed,
+        }
+    }
+}
+
+/// A human verdict on a patch in the review queue.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Review {
+    pub id: Uuid,
+    pub patch_id: Uuid,
+    pub reviewer: String,
+    pub verdict: ReviewVerdict,
+    pub comment: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl Review {
+    pub fn new(patch_id: Uuid, reviewer: &str, verdict: ReviewVerdict, comment: Option<String>) -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            patch_id,
+            reviewer: reviewer.to_string(),
+            verdict,
+            comment,
+            created_at: Utc::now(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ReviewVerdict {
+    Approved,
+    Rejected,
+}
+
+impl ReviewVerdict {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ReviewVerdict::Approved => "approved",
+            ReviewVerdict::Rejected => "rejected",
+        }
+    }
+
+    pub fn parse(s: &str) -> Self {
+        match s {
+            "rejected" => ReviewVerdict::Rejected,
+            _ => ReviewVerdict::Approved,
+        }
+    }
+}
+
+/// Outcome of validating a candidate patch.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ValidationResult {
+    pub passed: bool,
+    pub build_ok: bool,
+    pub tests_ok: bool,
+    /// Wall-clock duration of the build step, for tracking how much the
+    /// warm validation workspaces help.
+    #[serde(default)]
+    pub build_time_ms: u64,
+    /// Wall-clock duration of the test step; zero when the build failed
+    /// and tests never ran.
+    #[serde(default)]
+    pub test_time_ms: u64,
+    /// Error-level `cargo audit` advisories plus scanner findings at or
+    /// above the configured severity threshold, so a fix that drags in a
+    /// vulnerable dependency or a secret is visible at review time.
+    #[serde(default)]
+    pub security_issues_found: usize,
+    /// Individual semgrep and gitleaks findings from the patched
+    /// workspace, regardless of severity.
+    #[serde(default)]
+    pub security_issues: Vec<crate::security_scan::SecurityIssue>,
+    pub detail: Option<String>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn statuses_round_trip() {
+        for status in [
+            IssueStatus::Open,
+            IssueStatus::Patching,
+            IssueStatus::Patched,
+            IssueStatus::Failed,
+            IssueStatus::Resolved,
+        ] {
+            assert_eq!(IssueStatus::parse(status.as_str()), status);
+        }
+        for status in [
+            PatchStatus::Proposed,
+            PatchStatus::Validated,
+            PatchStatus::PendingReview,
+            PatchStatus::Rejected,
+            PatchStatus::Applied,
+            PatchStatus::InReview,
+            PatchStatus::RolledBack,
+        ] {
+            assert_eq!(PatchStatus::parse(status.as_str()), status);
+        }
+    }
+}
@@ -0,0 +1,533 @@
+//! Validates candidate patches by building and testing them in isolation.
+//!
+//! The patched code is LLM-generated and therefore untrusted, so the
+//! default path stages the patch in a copied workspace and runs cargo
+//! inside a Docker container with no network and CPU/memory/time caps.
+//! When Docker is unavailable, or sandboxing is disabled in the config,
+//! validation falls back to running cargo on the host against the staged
+//! copy — the real tree is never built in place either way.
+//!
+//! Staged workspaces are pooled: each keeps its git metadata and a
+//! persistent cargo target directory, is reset with `git checkout` between
+//! patches, and so only pays the cold-build cost once.
+
+use crate::config::{HealingConfig, ProjectConfig, ValidationConfig};
+use crate::patch_generator;
+use crate::types::ValidationResult;
+use anyhow::{bail, Context, Result};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use tracing::{info, warn};
+
+/// Directories never copied into a validation workspace; build output and
+/// vendor trees would dwarf the sources and are recreated by the build.
+/// Git metadata is kept so the workspace can be reset between patches.
+const SKIP_DIRS: &[&str] = &["target", "node_modules", ".next", "dist"];
+
+/// How much command output is kept in the validation detail.
+const DETAIL_TAIL_CHARS: usize = 4000;
+
+/// A pre-warmed workspace copy with its own persistent target directory.
+struct Workspace {
+    dir: PathBuf,
+    target: PathBuf,
+}
+
+/// Pool of validation workspaces, reused across patches so incremental
+/// compilation applies. Workspaces live under one temp directory that is
+/// removed when the daemon exits.
+pub struct WorkspacePool {
+    repo_path: PathBuf,
+    base: tempfile::TempDir,
+    free: Mutex<Vec<Workspace>>,
+    next_id: AtomicUsize,
+}
+
+impl WorkspacePool {
+    fn new(repo_path: &Path) -> Result<Self> {
+        Ok(Self {
+            repo_path: repo_path.to_path_buf(),
+            base: tempfile::tempdir().context("failed to create validation pool directory")?,
+            free: Mutex::new(Vec::new()),
+            next_id: AtomicUsize::new(0),
+        })
+    }
+
+    /// Take a clean workspace, cloning a fresh one when none is free.
+    fn acquire(&self) -> Result<Workspace> {
+        if let Some(workspace) = self.free.lock().expect("pool lock poisoned").pop() {
+            return Ok(workspace);
+        }
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let workspace = Workspace {
+            dir: self.base.path().join(format!("ws-{id}")),
+            target: self.base.path().join(format!("target-{id}")),
+        };
+        copy_tree(&self.repo_path, &workspace.dir, SKIP_DIRS)
+            .context("failed to stage the workspace")?;
+        std::fs::create_dir_all(&workspace.target)?;
+        info!(workspace = id, "prepared validation workspace");
+        Ok(workspace)
+    }
+
+    /// Reset a used workspace and return it to the pool; a workspace that
+    /// fails to reset is discarded rather than reused dirty.
+    fn release(&self, workspace: Workspace) {
+        match reset_workspace(&workspace.dir) {
+            Ok(()) => self
+                .free
+                .lock()
+                .expect("pool lock poisoned")
+                .push(workspace),
+            Err(e) => {
+                warn!("discarding validation workspace that failed to reset: {e:#}");
+                let _ = std::fs::remove_dir_all(&workspace.dir);
+                let _ = std::fs::remove_dir_all(&workspace.target);
+            }
+        }
+    }
+}
+
+/// Drop tracked edits and any files the patch created.
+fn reset_workspace(dir: &Path) -> Result<()> {
+    for args in [&["checkout", "--", "."][..], &["clean", "-fdq"][..]] {
+        let status = Command::new("git")
+            .args(args)
+            .current_dir(dir)
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .status()
+            .context("failed to invoke git")?;
+        if !status.success() {
+            bail!("git {} exited with {status}", args.join(" "));
+        }
+    }
+    Ok(())
+}
+
+#[derive(Clone)]
+pub struct PatchValidator {
+    config: ValidationConfig,
+    project: ProjectConfig,
+    pool: Arc<WorkspacePool>,
+}
+
+impl PatchValidator {
+    /// A validator for one configured project, with its own workspace pool.
+    pub fn new(config: &HealingConfig, project: &ProjectConfig) -> Result<Self> {
+        Ok(Self {
+            config: config.validation.clone(),
+            project: project.clone(),
+            pool: Arc::new(WorkspacePool::new(&project.path)?),
+        })
+    }
+
+    /// Stage `diff` in a pooled workspace and run the build (and test
+    /// suite) against it. Build or test failures are reported in the
+    /// result; only infrastructure problems surface as errors.
+    pub fn validate(&self, diff: &str) -> Result<ValidationResult> {
+        let workspace = self.pool.acquire()?;
+        let result = self.validate_in(&workspace, diff);
+        self.pool.release(workspace);
+        result
+    }
+
+    fn validate_in(&self, workspace: &Workspace, diff: &str) -> Result<ValidationResult> {
+        stage_patch(&workspace.dir, diff)
+            .context("failed to apply the patch to the staging copy")?;
+
+        let sandboxed = self.config.sandbox && docker_available();
+        if self.config.sandbox && !sandboxed {
+            warn!("docker is unavailable; validating on the host without a sandbox");
+        }
+
+        let started = std::time::Instant::now();
+        // A project-level build command replaces the cargo pipeline; it is
+        // expected to cover tests too, so none are run separately for it.
+        let build = match self.project.build_command.as_deref() {
+            Some(command) => self.run_shell(workspace, command)?,
+            None => self.run_cargo(workspace, "build", sandboxed)?,
+        };
+        let build_time_ms = started.elapsed().as_millis() as u64;
+        let mut detail = build.log;
+        let mut test_time_ms = 0;
+        let tests_ok = if !build.success {
+            false
+        } else if self.project.build_command.is_some() {
+            true
+        } else {
+            let started = std::time::Instant::now();
+            let test = self.run_cargo(workspace, "test", sandboxed)?;
+            test_time_ms = started.elapsed().as_millis() as u64;
+            detail.push('\n');
+            detail.push_str(&test.log);
+            test.success
+        };
+        // Audit the patched workspace so a fix that introduces a vulnerable
+        // dependency is flagged; an unavailable advisory DB is not fatal.
+        // cargo audit only makes sense for Rust projects.
+        let audit_errors = if self.project.language == "rust" {
+            match crate::static_analysis::audit(&workspace.dir) {
+                Ok(findings) => findings.iter().filter(|f| f.level == "error").count(),
+                Err(e) => {
+                    warn!("cargo audit skipped during validation: {e:#}");
+                    0
+                }
+            }
+        } else {
+            0
+        };
+        // Semgrep and gitleaks run regardless of language; findings at or
+        // above the configured severity fail validation outright.
+        let security_issues =
+            crate::security_scan::scan(&workspace.dir, &self.config.semgrep_rules);
+        let blocking =
+            crate::security_scan::at_or_above(&security_issues, &self.config.fail_on_severity);
+        if blocking > 0 {
+            detail.push_str(&format!(
+                "\n{blocking} security finding(s) at or above the '{}' threshold",
+                self.config.fail_on_severity
+            ));
+        }
+        let security_issues_found = audit_errors + blocking;
+        info!(
+            build_ok = build.success,
+            tests_ok,
+            sandboxed,
+            build_time_ms,
+            test_time_ms,
+            security_issues_found,
+            "patch validation finished"
+        );
+        Ok(ValidationResult {
+            passed: build.success && tests_ok && blocking == 0,
+            build_ok: build.success,
+            tests_ok,
+            build_time_ms,
+            test_time_ms,
+            security_issues_found,
+            security_issues,
+            detail: Some(tail(&detail, DETAIL_TAIL_CHARS)),
+        })
+    }
+
+    fn run_cargo(
+        &self,
+        workspace: &Workspace,
+        subcommand: &str,
+        sandboxed: bool,
+    ) -> Result<RunOutcome> {
+        let mut command = if sandboxed {
+            let mut command = Command::new("docker");
+            command.args(docker_args(&self.config, workspace, subcommand));
+            command
+        } else {
+            let mut command = Command::new("cargo");
+            command
+                .args([subcommand, "--workspace", "--offline"])
+                .env("CARGO_TARGET_DIR", &workspace.target)
+                .current_dir(&workspace.dir);
+            command
+        };
+        let child = command
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .with_context(|| format!("failed to invoke cargo {subcommand}"))?;
+        collect_with_timeout(child, std::time::Duration::from_secs(self.config.timeout_secs))
+    }
+
+    /// Run a project's custom build command through `sh -c` in the staged
+    /// workspace. Custom commands run on the host; the Docker sandbox only
+    /// ships a cargo toolchain.
+    fn run_shell(&self, workspace: &Workspace, command: &str) -> Result<RunOutcome> {
+        let child = Command::new("sh")
+            .args(["-c", command])
+            .env("CARGO_TARGET_DIR", &workspace.target)
+            .current_dir(&workspace.dir)
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .with_context(|| format!("failed to invoke build command: {command}"))?;
+        collect_with_timeout(child, std::time::Duration::from_secs(self.config.timeout_secs))
+    }
+}
+
+/// Arguments for a network-less, resource-capped validation container. The
+/// staged workspace is mounted read-only; the workspace's persistent
+/// target directory is the only writable mount, which is what makes the
+/// pooled builds incremental.
+fn docker_args(config: &ValidationConfig, workspace: &Workspace, subcommand: &str) -> Vec<String> {
+    vec![
+        "run".to_string(),
+        "--rm".to_string(),
+        "--network=none".to_string(),
+        format!("--cpus={}", config.cpus),
+        "--memory".to_string(),
+        config.memory.clone(),
+        "-v".to_string(),
+        format!("{}:/work:ro", workspace.dir.display()),
+        "-v".to_string(),
+        format!("{}:/work-target", workspace.target.display()),
+        // Reuse the host's crate cache; --network=none means nothing can
+        // be fetched inside the container.
+        "-v".to_string(),
+        format!("{}:/usr/local/cargo/registry:ro", host_registry().display()),
+        "-e".to_string(),
+        "CARGO_TARGET_DIR=/work-target".to_string(),
+        "-w".to_string(),
+        "/work".to_string(),
+        config.image.clone(),
+        "cargo".to_string(),
+        subcommand.to_string(),
+        "--workspace".to_string(),
+        "--offline".to_string(),
+    ]
+}
+
+fn host_registry() -> PathBuf {
+    std::env::var_os("CARGO_HOME")
+        .map(PathBuf::from)
+        .unwrap_or_else(|| {
+            std::env::var_os("HOME")
+                .map(|home| PathBuf::from(home).join(".cargo"))
+                .unwrap_or_else(|| PathBuf::from("/root/.cargo"))
+        })
+        .join("registry")
+}
+
+fn docker_available() -> bool {
+    Command::new("docker")
+        .arg("--version")
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .is_ok_and(|status| status.success())
+}
+
+/// Recursively copy the workspace, skipping the directories in `skip`.
+fn copy_tree(from: &Path, to: &Path, skip: &[&str]) -> Result<()> {
+    std::fs::create_dir_all(to)?;
+    for entry in std::fs::read_dir(from)? {
+        let entry = entry?;
+        let name = entry.file_name();
+        let file_type = entry.file_type()?;
+        let dest = to.join(&name);
+        if file_type.is_dir() {
+            if skip.iter().any(|skipped| name == *skipped) {
+                continue;
+            }
+            copy_tree(&entry.path(), &dest, skip)?;
+        } else if file_type.is_file() {
+            std::fs::copy(entry.path(), &dest)?;
+        }
+        // Symlinks are dropped: they could point outside the staging area.
+    }
+    Ok(())
+}
+
+/// Apply a unified diff to the staged copy using the in-process engine, so
+/// validation works without git metadata in the staging area.
+fn stage_patch(staging: &Path, diff: &str) -> Result<()> {
+    for file_diff in patch_generator::parse(diff)? {
+        let path = staging.join(&file_diff.new_path);
+        let original = if file_diff.is_creation() {
+            String::new()
+        } else {
+            std::fs::read_to_string(staging.join(&file_diff.old_path))
+                .with_context(|| format!("cannot read staged file {}", file_diff.old_path))?
+        };
+        let patched = patch_generator::apply_diff(&original, &file_diff)?;
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(&path, patched)
+            .with_context(|| format!("cannot write staged file {}", file_diff.new_path))?;
+    }
+    Ok(())
+}
+
+pub(crate) struct RunOutcome {
+    pub(crate) success: bool,
+    pub(crate) log: String,
+}
+
+/// Drain the child's output and wait for it, killing it when the deadline
+/// passes so a hung build cannot wedge validation.
+pub(crate) fn collect_with_timeout(
+    mut child: std::process::Child,
+    timeout: std::time::Duration,
+) -> Result<RunOutcome> {
+    use std::io::Read;
+
+    let mut readers = Vec::new();
+    for reader in [
+        child.stdout.take().map(|s| Box::new(s) as Box<dyn Read + Send>),
+        child.stderr.take().map(|s| Box::new(s) as Box<dyn Read + Send>),
+    ]
+    .into_iter()
+    .flatten()
+    {
+        let mut reader = reader;
+        readers.push(std::thread::spawn(move || {
+            let mut buf = String::new();
+            let _ = reader.read_to_string(&mut buf);
+            buf
+        }));
+    }
+
+    let deadline = std::time::Instant::now() + timeout;
+    let status = loop {
+        if let Some(status) = child.try_wait().context("failed to wait for validation")? {
+            break Some(status);
+        }
+        if std::time::Instant::now() >= deadline {
+            let _ = child.kill();
+            let _ = child.wait();
+            break None;
+        }
+        std::thread::sleep(std::time::Duration::from_millis(200));
+    };
+
+    let mut log = String::new();
+    for reader in readers {
+        let part = reader.join().unwrap_or_default();
+        if !part.is_empty() {
+            if !log.is_empty() {
+                log.push('\n');
+            }
+            log.push_str(&part);
+        }
+    }
+    match status {
+        Some(status) => Ok(RunOutcome {
+            success: status.success(),
+            log,
+        }),
+        None => {
+            log.push_str(&format!(
+                "\nvalidation killed after exceeding {}s timeout",
+                timeout.as_secs()
+            ));
+            Ok(RunOutcome {
+                success: false,
+                log,
+            })
+        }
+    }
+}
+
+/// Last `chars` characters of `text`, on a char boundary.
+fn tail(text: &str, chars: usize) -> String {
+    if text.len() <= chars {
+        return text.to_string();
+    }
+    let mut cut = text.len() - chars;
+    while cut < text.len() && !text.is_char_boundary(cut) {
+        cut += 1;
+    }
+    format!("[... earlier output elided ...]\n{}", &text[cut..])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn copy_tree_skips_build_output() {
+        let from = tempfile::tempdir().unwrap();
+        let to = tempfile::tempdir().unwrap();
+        std::fs::write(from.path().join("main.rs"), "fn main() {}").unwrap();
+        std::fs::create_dir(from.path().join("target")).unwrap();
+        std::fs::write(from.path().join("target").join("junk"), "x").unwrap();
+        std::fs::create_dir(from.path().join("src")).unwrap();
+        std::fs::write(from.path().join("src").join("lib.rs"), "").unwrap();
+
+        copy_tree(from.path(), to.path(), SKIP_DIRS).unwrap();
+        assert!(to.path().join("main.rs").exists());
+        assert!(to.path().join("src").join("lib.rs").exists());
+        assert!(!to.path().join("target").exists());
+    }
+
+    #[test]
+    fn pool_resets_and_reuses_workspaces() {
+        let repo = tempfile::tempdir().unwrap();
+        for args in [
+            &["init", "-q"][..],
+            &["add", "."][..],
+            &["-c", "user.email=a@b", "-c", "user.name=t", "commit", "-qm", "init"][..],
+        ] {
+            std::fs::write(repo.path().join("lib.rs"), "fn one() {}\n").unwrap();
+            assert!(Command::new("git")
+                .args(args)
+                .current_dir(repo.path())
+                .status()
+                .unwrap()
+                .success());
+        }
+
+        let pool = WorkspacePool::new(repo.path()).unwrap();
+        let workspace = pool.acquire().unwrap();
+        let dir = workspace.dir.clone();
+        std::fs::write(dir.join("lib.rs"), "fn changed() {}\n").unwrap();
+        std::fs::write(dir.join("created.rs"), "").unwrap();
+        pool.release(workspace);
+
+        // Same directory comes back, with the patch's edits undone.
+        let again = pool.acquire().unwrap();
+        assert_eq!(again.dir, dir);
+        assert_eq!(
+            std::fs::read_to_string(dir.join("lib.rs")).unwrap(),
+            "fn one() {}\n"
+        );
+        assert!(!dir.join("created.rs").exists());
+    }
+
+    #[test]
+    fn stage_patch_applies_edits_and_creations() {
+        let staging = tempfile::tempdir().unwrap();
+        std::fs::write(staging.path().join("lib.rs"), "fn one() {}\n").unwrap();
+        let diff = "--- a/lib.rs\n+++ b/lib.rs\n@@ -1,1 +1,1 @@\n-fn one() {}\n+fn two() {}\n--- /dev/null\n+++ b/extra.rs\n@@ -0,0 +1,1 @@\n+fn extra() {}\n";
+
+        stage_patch(staging.path(), diff).unwrap();
+        assert_eq!(
+            std::fs::read_to_string(staging.path().join("lib.rs")).unwrap(),
+            "fn two() {}\n"
+        );
+        assert_eq!(
+            std::fs::read_to_string(staging.path().join("extra.rs")).unwrap(),
+            "fn extra() {}\n"
+        );
+    }
+
+    #[test]
+    fn docker_args_isolate_the_container() {
+        let config = ValidationConfig::default();
+        let workspace = Workspace {
+            dir: PathBuf::from("/staged"),
+            target: PathBuf::from("/staged-target"),
+        };
+        let args = docker_args(&config, &workspace, "build");
+        assert!(args.contains(&"--network=none".to_string()));
+        assert!(args.contains(&"/staged:/work:ro".to_string()));
+        assert!(args.contains(&"/staged-target:/work-target".to_string()));
+        assert!(args.contains(&format!("--cpus={}", config.cpus)));
+        assert!(args.ends_with(&[
+            "cargo".to_string(),
+            "build".to_string(),
+            "--workspace".to_string(),
+            "--offline".to_string(),
+        ]));
+    }
+
+    #[test]
+    fn tail_keeps_the_end_of_long_output() {
+        let long = "a".repeat(10).to_string() + "END";
+        let out = tail(&long, 5);
+        assert!(out.ends_with("aaEND"));
+        assert!(out.starts_with("[... earlier output elided ...]"));
+        assert_eq!(tail("short", 100), "short");
+    }
+}
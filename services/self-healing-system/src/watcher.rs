@@ -0,0 +1,72 @@
+//! Filesystem watcher that triggers analysis as soon as sources change.
+//!
+//! In watch mode the daemon does not wait for the next poll cycle: a
+//! notify-based watcher on the configured project paths (and the CI
+//! failure webhook in the API) push onto the daemon's trigger channel,
+//! which debounces and runs analysis within seconds of a failure landing.
+
+use crate::config::HealingConfig;
+use anyhow::{Context, Result};
+use notify::{RecommendedWatcher, RecursiveMode, Watcher};
+use std::path::Path;
+use tokio::sync::mpsc;
+use tracing::debug;
+
+/// Directories whose churn never warrants an analysis run.
+const IGNORED_DIRS: &[&str] = &[".git", "target", "node_modules", ".next", "dist"];
+
+/// Start watching the configured paths. The returned watcher must be kept
+/// alive for events to keep flowing.
+pub fn spawn(config: &HealingConfig, trigger: mpsc::Sender<&'static str>) -> Result<RecommendedWatcher> {
+    let mut watcher = notify::recommended_watcher(move |result: notify::Result<notify::Event>| {
+        let Ok(event) = result else {
+            return;
+        };
+        let mutation =
+            event.kind.is_modify() || event.kind.is_create() || event.kind.is_remove();
+        if mutation && event.paths.iter().any(|p| is_relevant(p)) {
+            // A full channel means a trigger is already pending; the
+            // pending run will pick this change up too.
+            let _ = trigger.try_send("filesystem");
+        }
+    })
+    .context("failed to create filesystem watcher")?;
+    for path in &config.watch.paths {
+        let path = config.repo_path.join(path);
+        watcher
+            .watch(&path, RecursiveMode::Recursive)
+            .with_context(|| format!("failed to watch {}", path.display()))?;
+        debug!("watching {}", path.display());
+    }
+    Ok(watcher)
+}
+
+/// Filter out build output, VCS churn, and editor droppings, which change
+/// constantly without meaning the sources did.
+fn is_relevant(path: &Path) -> bool {
+    if path
+        .components()
+        .any(|c| IGNORED_DIRS.iter().any(|dir| c.as_os_str() == *dir))
+    {
+        return false;
+    }
+    let name = path.file_name().and_then(|n| n.to_str()).unwrap_or("");
+    !name.ends_with('~') && !name.ends_with(".swp") && !name.starts_with(".#")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    #[test]
+    fn build_output_and_editor_files_are_ignored() {
+        assert!(is_relevant(&PathBuf::from("services/api/src/main.rs")));
+        assert!(is_relevant(&PathBuf::from("Cargo.toml")));
+        assert!(!is_relevant(&PathBuf::from("target/debug/api")));
+        assert!(!is_relevant(&PathBuf::from("services/api/node_modules/x.js")));
+        assert!(!is_relevant(&PathBuf::from("src/main.rs.swp")));
+        assert!(!is_relevant(&PathBuf::from("src/.#main.rs")));
+        assert!(!is_relevant(&PathBuf::from(".git/index.lock")));
+    }
+}